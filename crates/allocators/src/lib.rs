@@ -4,5 +4,5 @@ mod pool;
 mod stack;
 
 // pub use freelist::{FreeListPtr, FreeListAllocator};
-pub use pool::{PoolAllocator, TypedPoolAllocator};
-pub use stack::StackAllocator;
+pub use pool::{GrowablePool, GrowthPolicy, PoolAllocator, PoolHandle, TypedPoolAllocator};
+pub use stack::{ScopedArena, StackAllocator, StackMarker};
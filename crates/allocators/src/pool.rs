@@ -82,7 +82,7 @@ pub struct TypedPoolAllocator<T> {
 }
 
 impl<T> TypedPoolAllocator<T> {
-    /// # Safety 
+    /// # Safety
     /// see ``PoolAllocator``
     pub unsafe fn new(memory: *mut i8, pool_count: usize) -> Self {
         Self {
@@ -97,3 +97,279 @@ impl<T> TypedPoolAllocator<T> {
         self.pool.free(ptr.cast());
     }
 }
+
+/// how a ``GrowablePool`` gets more space once it is full
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthPolicy {
+    /// never grow, inserting into a full pool fails
+    Fixed,
+    /// add a single chunk
+    #[default]
+    Linear,
+    /// add as many chunks as there already are, doubling the capacity
+    Doubling,
+}
+
+/// points to an item in a ``GrowablePool``
+/// stays valid when the pool grows or shrinks, until the item is removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PoolHandle {
+    index: u32,
+}
+
+impl PoolHandle {
+    #[must_use]
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// the index should come from ``PoolHandle::index`` of the same pool,
+    /// used to store handles in a compact form, for example on the gpu
+    #[must_use]
+    pub fn from_index(index: u32) -> Self {
+        Self { index }
+    }
+}
+
+enum Slot<T> {
+    Occupied(T),
+    /// works like the ``PoolAllocator``, every free slot points to the next free slot
+    Free {
+        next: Option<u32>,
+    },
+}
+
+/// a pool that owns its memory and grows by allocating additional chunks
+/// items never move, so handles stay valid when new chunks are added
+///
+/// |  chunk 0  |  chunk 1  |  chunk 2  |
+/// | a | - | b | c | d | - | - | - | - |
+///
+/// ``shrink_to_fit`` can only free chunks at the end that are completely empty,
+/// as moving items would invalidate their handles
+pub struct GrowablePool<T> {
+    chunks: Vec<Box<[Slot<T>]>>,
+    /// how many items are alive in every chunk
+    chunk_len: Vec<usize>,
+    chunk_size: usize,
+    policy: GrowthPolicy,
+    head: Option<u32>,
+    len: usize,
+}
+
+impl<T> GrowablePool<T> {
+    /// creates a pool with a single chunk
+    /// # Panics
+    /// if ``chunk_size`` is 0
+    #[must_use]
+    pub fn new(chunk_size: usize, policy: GrowthPolicy) -> Self {
+        assert!(
+            chunk_size > 0,
+            "a chunk needs to have space for at least one item"
+        );
+
+        let mut pool = Self {
+            chunks: vec![],
+            chunk_len: vec![],
+            chunk_size,
+            policy,
+            head: None,
+            len: 0,
+        };
+        pool.add_chunks(1);
+        pool
+    }
+
+    /// returns None if the pool is full and the policy doesn't allow growing
+    /// # Panics
+    /// if there are more than ``u32::MAX`` items
+    pub fn insert(&mut self, value: T) -> Option<PoolHandle> {
+        if self.head.is_none() {
+            match self.policy {
+                GrowthPolicy::Fixed => return None,
+                GrowthPolicy::Linear => self.add_chunks(1),
+                GrowthPolicy::Doubling => self.add_chunks(self.chunks.len().max(1)),
+            }
+        }
+
+        let index = self.head?;
+        let (chunk, offset) = self.locate(index);
+        let slot = &mut self.chunks[chunk][offset];
+
+        let Slot::Free { next } = *slot else {
+            unreachable!("the free list points to an occupied slot");
+        };
+
+        *slot = Slot::Occupied(value);
+        self.head = next;
+        self.chunk_len[chunk] += 1;
+        self.len += 1;
+
+        Some(PoolHandle { index })
+    }
+
+    /// returns None if the handle doesn't point to an item
+    pub fn remove(&mut self, handle: PoolHandle) -> Option<T> {
+        let (chunk, offset) = self.locate(handle.index);
+        let slot = self.chunks.get_mut(chunk)?.get_mut(offset)?;
+
+        if !matches!(slot, Slot::Occupied(_)) {
+            return None;
+        }
+
+        let Slot::Occupied(value) = std::mem::replace(slot, Slot::Free { next: self.head }) else {
+            unreachable!()
+        };
+
+        self.head = Some(handle.index);
+        self.chunk_len[chunk] -= 1;
+        self.len -= 1;
+
+        Some(value)
+    }
+
+    #[must_use]
+    pub fn get(&self, handle: PoolHandle) -> Option<&T> {
+        let (chunk, offset) = self.locate(handle.index);
+        match self.chunks.get(chunk)?.get(offset)? {
+            Slot::Occupied(value) => Some(value),
+            Slot::Free { .. } => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: PoolHandle) -> Option<&mut T> {
+        let (chunk, offset) = self.locate(handle.index);
+        match self.chunks.get_mut(chunk)?.get_mut(offset)? {
+            Slot::Occupied(value) => Some(value),
+            Slot::Free { .. } => None,
+        }
+    }
+
+    /// the amount of items that are alive
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// how many items fit without allocating a new chunk
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.chunks.len() * self.chunk_size
+    }
+
+    /// free the empty chunks at the end of the pool, at least one chunk is kept
+    pub fn shrink_to_fit(&mut self) {
+        let used_chunks = self
+            .chunk_len
+            .iter()
+            .rposition(|len| *len > 0)
+            .map_or(1, |last| last + 1);
+
+        if used_chunks == self.chunks.len() {
+            return;
+        }
+
+        self.chunks.truncate(used_chunks);
+        self.chunk_len.truncate(used_chunks);
+        self.rebuild_free_list();
+    }
+
+    /// iterate over all items that are alive, ordered by their index
+    pub fn iter(&self) -> impl Iterator<Item = (PoolHandle, &T)> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied(value) => Some((
+                    PoolHandle {
+                        index: index as u32,
+                    },
+                    value,
+                )),
+                Slot::Free { .. } => None,
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (PoolHandle, &mut T)> {
+        self.chunks
+            .iter_mut()
+            .flat_map(|chunk| chunk.iter_mut())
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied(value) => Some((
+                    PoolHandle {
+                        index: index as u32,
+                    },
+                    value,
+                )),
+                Slot::Free { .. } => None,
+            })
+    }
+
+    /// remove all items, the chunks are kept
+    pub fn clear(&mut self) {
+        for chunk in &mut self.chunks {
+            for slot in chunk.iter_mut() {
+                *slot = Slot::Free { next: None };
+            }
+        }
+        self.chunk_len.fill(0);
+        self.len = 0;
+        self.rebuild_free_list();
+    }
+
+    fn locate(&self, index: u32) -> (usize, usize) {
+        let index = index as usize;
+        (index / self.chunk_size, index % self.chunk_size)
+    }
+
+    fn add_chunks(&mut self, count: usize) {
+        let first = self.capacity();
+        let last = first + count * self.chunk_size;
+        assert!(
+            u32::try_from(last).is_ok(),
+            "the pool can't hold more than u32::MAX items"
+        );
+
+        for chunk_index in 0..count {
+            let start = first + chunk_index * self.chunk_size;
+            // every new slot points to the one after it, the last one to the old head
+            let chunk = (start..start + self.chunk_size)
+                .map(|index| {
+                    let next = index + 1;
+                    Slot::Free {
+                        next: if next == last {
+                            self.head
+                        } else {
+                            Some(next as u32)
+                        },
+                    }
+                })
+                .collect();
+
+            self.chunks.push(chunk);
+            self.chunk_len.push(0);
+        }
+
+        self.head = Some(first as u32);
+    }
+
+    /// link all free slots, lowest index first
+    fn rebuild_free_list(&mut self) {
+        let mut next = None;
+        for index in (0..self.capacity()).rev() {
+            let (chunk, offset) = self.locate(index as u32);
+            if let Slot::Free { next: slot_next } = &mut self.chunks[chunk][offset] {
+                *slot_next = next;
+                next = Some(index as u32);
+            }
+        }
+        self.head = next;
+    }
+}
@@ -1,4 +1,4 @@
-use std::{alloc::Layout, ptr::null_mut};
+use std::{alloc::Layout, marker::PhantomData, ptr::null_mut};
 
 /// the ``StackAllocator`` is good to use for algorithms
 /// it eliminates memory fragmentation and improves cache locality
@@ -57,6 +57,8 @@ impl StackAllocator {
         self.mem_used += size;
 
         if self.mem_used > self.mem_size {
+            // a failed allocation shouldn't use up the space
+            self.mem_used = old_size;
             return null_mut();
             // resizing invalidated all pointers!!
             // (needs smart pointers)
@@ -94,6 +96,57 @@ impl StackAllocator {
         }
     }
 
+    /// allocate space for a single T, aligned to T
+    /// returns null if there is no space left
+    pub fn allocate_typed<T>(&mut self) -> *mut T {
+        self.allocate(Layout::new::<T>()).cast()
+    }
+
+    /// allocate space for ``len`` Ts in a row, aligned to T
+    /// returns null if there is no space left
+    /// # Panics
+    /// if the size overflows
+    pub fn allocate_array<T>(&mut self, len: usize) -> *mut T {
+        let layout = Layout::array::<T>(len).expect("array is too big");
+        self.allocate(layout).cast()
+    }
+
+    /// how many bytes are currently used, including padding
+    #[must_use]
+    pub fn used(&self) -> usize {
+        self.mem_used
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.mem_size
+    }
+
+    /// gets the marker to the current end of the stack
+    #[must_use]
+    pub fn marker(&self) -> StackMarker {
+        StackMarker {
+            marker: self.mem_used,
+        }
+    }
+
+    /// free everything that has been allocated after the marker
+    /// # Safety
+    /// this invalidates every pointer allocated after this marker
+    pub unsafe fn reset_to(&mut self, marker: StackMarker) {
+        self.mem_used = marker.marker;
+    }
+
+    /// start a scope, everything allocated through the ``ScopedArena``
+    /// is freed once it is dropped
+    pub fn scope(&mut self) -> ScopedArena<'_> {
+        ScopedArena {
+            marker: self.marker(),
+            stack: self,
+            _lifetime: PhantomData,
+        }
+    }
+
     /// gets the marker to the current end of the stack
     /// this will clear everything that has been allocated after this marker
     pub fn get_marker(&mut self) -> StackMarker {
@@ -114,3 +167,70 @@ impl StackAllocator {
         self.mem_used = 0;
     }
 }
+
+/// frees everything allocated through it when dropped
+/// the references it hands out can't outlive it, so this is safe to use
+/// only ``Copy`` types can be allocated, as nothing is dropped
+///
+/// ```
+/// # use allocators::StackAllocator;
+/// # let mut memory = [0u64; 16];
+/// let mut stack = StackAllocator::new(memory.as_mut_ptr().cast(), 128);
+/// {
+///     let arena = stack.scope();
+///     let keys = arena.alloc_slice_fill_with(4, |i| 4 - i).unwrap();
+///     keys.sort_unstable();
+/// }
+/// assert_eq!(stack.used(), 0);
+/// ```
+pub struct ScopedArena<'a> {
+    stack: *mut StackAllocator,
+    marker: StackMarker,
+    _lifetime: PhantomData<&'a mut StackAllocator>,
+}
+
+#[allow(clippy::mut_from_ref)] // every allocation is a new piece of memory
+impl ScopedArena<'_> {
+    /// returns None if there is no space left
+    pub fn alloc<T: Copy>(&self, value: T) -> Option<&mut T> {
+        let ptr = unsafe { (*self.stack).allocate_typed::<T>() };
+        if ptr.is_null() {
+            return None;
+        }
+
+        unsafe {
+            ptr.write(value);
+            Some(&mut *ptr)
+        }
+    }
+
+    /// returns None if there is no space left
+    pub fn alloc_slice_copy<T: Copy>(&self, data: &[T]) -> Option<&mut [T]> {
+        self.alloc_slice_fill_with(data.len(), |i| data[i])
+    }
+
+    /// allocate a slice and initialize every element with ``f(index)``
+    /// returns None if there is no space left
+    pub fn alloc_slice_fill_with<T: Copy>(
+        &self,
+        len: usize,
+        mut f: impl FnMut(usize) -> T,
+    ) -> Option<&mut [T]> {
+        let ptr = unsafe { (*self.stack).allocate_array::<T>(len) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        for i in 0..len {
+            unsafe { ptr.add(i).write(f(i)) };
+        }
+
+        Some(unsafe { std::slice::from_raw_parts_mut(ptr, len) })
+    }
+}
+
+impl Drop for ScopedArena<'_> {
+    fn drop(&mut self) {
+        unsafe { (*self.stack).reset_to(self.marker) };
+    }
+}
@@ -161,3 +161,71 @@ fn pool_alignment_test() {
 
     unsafe { dealloc(pool_memory, pool_layout) };
 }
+
+#[test]
+fn growable_pool_grows() {
+    use allocators::{GrowablePool, GrowthPolicy};
+
+    let mut pool = GrowablePool::new(2, GrowthPolicy::Linear);
+
+    let handles: Vec<_> = (0..5).map(|i| pool.insert(i).unwrap()).collect();
+    assert_eq!(pool.capacity(), 6);
+    assert_eq!(pool.len(), 5);
+
+    // growing doesn't move existing items
+    for (i, handle) in handles.iter().enumerate() {
+        assert_eq!(pool.get(*handle), Some(&i));
+    }
+
+    let mut doubling = GrowablePool::new(2, GrowthPolicy::Doubling);
+    for i in 0..5 {
+        doubling.insert(i).unwrap();
+    }
+    assert_eq!(doubling.capacity(), 8);
+}
+
+#[test]
+fn growable_pool_fixed() {
+    use allocators::{GrowablePool, GrowthPolicy};
+
+    let mut pool = GrowablePool::new(2, GrowthPolicy::Fixed);
+
+    let a = pool.insert(1).unwrap();
+    pool.insert(2).unwrap();
+    assert!(pool.insert(3).is_none());
+
+    assert_eq!(pool.remove(a), Some(1));
+    assert_eq!(pool.remove(a), None);
+    assert!(pool.insert(4).is_some());
+}
+
+#[test]
+fn growable_pool_shrink_and_iter() {
+    use allocators::{GrowablePool, GrowthPolicy};
+
+    let mut pool = GrowablePool::new(2, GrowthPolicy::Linear);
+
+    let handles: Vec<_> = (0..6).map(|i| pool.insert(i).unwrap()).collect();
+    // freed slots are reused first, so the item lands in the first chunk
+    for handle in handles[1..].iter().rev() {
+        pool.remove(*handle);
+    }
+    let kept = pool.insert(10).unwrap();
+
+    pool.shrink_to_fit();
+    assert_eq!(pool.capacity(), 2);
+    assert_eq!(pool.get(handles[0]), Some(&0));
+    assert_eq!(pool.get(kept), Some(&10));
+
+    let items: Vec<_> = pool.iter().map(|(_, v)| *v).collect();
+    assert_eq!(items, [0, 10]);
+
+    for (_, value) in pool.iter_mut() {
+        *value += 1;
+    }
+    assert_eq!(pool.get(handles[0]), Some(&1));
+
+    // the freed slots can be used again
+    pool.insert(20).unwrap();
+    assert_eq!(pool.len(), 3);
+}
@@ -234,3 +234,39 @@ fn no_overwrite_beyond_stack_limit() {
         dealloc(stack_memory, stack_layout);
     }
 }
+
+#[test]
+fn scoped_arena() {
+    unsafe {
+        let stack_layout = Layout::from_size_align(64, 16).unwrap();
+        let stack_memory = alloc(stack_layout);
+
+        let mut allocator = StackAllocator::new(stack_memory.cast(), stack_layout.size());
+        allocator.allocate(Layout::new::<u8>());
+
+        {
+            let arena = allocator.scope();
+            let a = arena.alloc(1u64).unwrap();
+            let b = arena.alloc_slice_copy(&[2u32, 3, 4]).unwrap();
+            assert_eq!(a as *mut u64 as usize % align_of::<u64>(), 0);
+
+            *a += 1;
+            b[0] = 5;
+            assert_eq!((*a, &*b), (2, &[5, 3, 4][..]));
+
+            // too big, but the arena can still be used afterwards
+            assert!(arena.alloc([0u8; 64]).is_none());
+            assert!(arena.alloc(0u8).is_some());
+        }
+
+        // everything allocated in the scope is freed
+        assert_eq!(allocator.used(), 1);
+
+        let marker = allocator.marker();
+        allocator.allocate_array::<u32>(4);
+        allocator.reset_to(marker);
+        assert_eq!(allocator.used(), 1);
+
+        dealloc(stack_memory, stack_layout);
+    }
+}
@@ -4,3 +4,38 @@ slang=~/Programs/slang/bin/slangc
 $slang -O3 ./shaders/shader.slang -target spirv -o ./shaders/shader.spv
 spirv-opt -o ./shaders/shader.spv ./shaders/shader.spv
 
+$slang -O3 ./shaders/particles.slang -target spirv -o ./shaders/particles.spv
+spirv-opt -o ./shaders/particles.spv ./shaders/particles.spv
+
+$slang -O3 ./shaders/tonemap.slang -target spirv -o ./shaders/tonemap.spv
+spirv-opt -o ./shaders/tonemap.spv ./shaders/tonemap.spv
+
+$slang -O3 ./shaders/ui.slang -target spirv -o ./shaders/ui.spv
+spirv-opt -o ./shaders/ui.spv ./shaders/ui.spv
+
+$slang -O3 ./shaders/voxel_mesh.slang -target spirv -o ./shaders/voxel_mesh.spv
+spirv-opt -o ./shaders/voxel_mesh.spv ./shaders/voxel_mesh.spv
+
+$slang -O3 ./shaders/taa.slang -target spirv -o ./shaders/taa.spv
+spirv-opt -o ./shaders/taa.spv ./shaders/taa.spv
+
+$slang -O3 ./shaders/ssao.slang -target spirv -o ./shaders/ssao.spv
+spirv-opt -o ./shaders/ssao.spv ./shaders/ssao.spv
+
+$slang -O3 ./shaders/oit.slang -target spirv -o ./shaders/oit.spv
+spirv-opt -o ./shaders/oit.spv ./shaders/oit.spv
+
+$slang -O3 ./shaders/skinned.slang -target spirv -o ./shaders/skinned.spv
+spirv-opt -o ./shaders/skinned.spv ./shaders/skinned.spv
+
+$slang -O3 ./shaders/sprites.slang -target spirv -o ./shaders/sprites.spv
+spirv-opt -o ./shaders/sprites.spv ./shaders/sprites.spv
+
+$slang -O3 ./shaders/outline.slang -target spirv -o ./shaders/outline.spv
+spirv-opt -o ./shaders/outline.spv ./shaders/outline.spv
+
+$slang -O3 ./shaders/gi.slang -target spirv -o ./shaders/gi.spv
+spirv-opt -o ./shaders/gi.spv ./shaders/gi.spv
+
+$slang -O3 ./shaders/volumetric.slang -target spirv -o ./shaders/volumetric.spv
+spirv-opt -o ./shaders/volumetric.spv ./shaders/volumetric.spv
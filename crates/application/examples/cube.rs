@@ -1,8 +1,8 @@
 use std::error::Error;
 
 use application::{
-    world::{svo::OctreeNode, World},
-    Application,
+    world::{svo::Octree, World},
+    Application, Settings,
 };
 use ash::vk;
 use math::dvec3;
@@ -25,23 +25,17 @@ fn create_octree(app: &mut Application) {
     )
     .unwrap();
 
-    let handle = app.renderer.set_storage_buffer(voxel_buffer.clone(), 0);
+    let buffer = app.renderer.import_buffer(voxel_buffer.clone());
+    let handle = app.renderer.bind_storage_buffer(buffer, Some(0)).unwrap();
     assert!(handle.index == 0);
 
-    let mut octree = OctreeNode::default();
+    let mut octree = Octree::new();
     octree.write(dvec3(0.0, 0.0, 0.0), 255, 3);
 
-    let flatten = octree.flatten();
-    let bytes = flatten.as_bytes();
-
-    voxel_buffer.write(0, bytes);
-
-    app.world.voxel_octrees.push(octree);
-    app.world.voxel_buffers.push(voxel_buffer);
+    app.world.add_octree(octree, voxel_buffer, handle.index);
 }
 
 fn write_octree(world: &mut World) {
-    let buffer = &world.voxel_buffers[0];
     let octree = &mut world.voxel_octrees[0];
     let t = world.start_time.elapsed().as_secs_f64() * 10.0;
 
@@ -64,14 +58,12 @@ fn write_octree(world: &mut World) {
 
     octree.write(dvec3(-x / 3.0, -y / 100.0, -z / 3.0), 255, 9);
 
-    let flatten = octree.flatten();
-    let bytes = flatten.as_bytes();
-
-    buffer.write(0, bytes);
+    // only the nodes that changed are copied
+    world.upload_octree(0);
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut app = Application::new()?;
+    let mut app = Application::new(Settings::load()?)?;
     // std::thread::sleep(std::time::Duration::from_secs_f32(3.0));
 
     create_octree(&mut app);
@@ -82,7 +74,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 }
 
 // fn main() {
-//     let mut octree = OctreeNode::default();
+//     let mut octree = Octree::new();
 //
 //     octree.write(dvec3(-1.0, -1.0, -1.0), 255, 2);
 //     octree.write(dvec3(0.0, 0.0, 0.0), 255, 2);
@@ -0,0 +1,52 @@
+use std::error::Error;
+
+use application::{
+    world::{vox, World},
+    Application, Settings,
+};
+use ash::vk;
+use math::{Transform, Vec3};
+use rendering::vulkan::Buffer;
+
+fn update_camera(world: &mut World) {
+    let t = world.start_time.elapsed().as_secs_f32() / 5.0;
+
+    world.camera.transform =
+        Transform::from_xyz(t.cos() * 2.0, 0.5, t.sin() * 2.0).looking_at(Vec3::ZERO, Vec3::Y);
+}
+
+/// usage: cargo run --example vox -- path/to/model.vox
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or("expected the path to a .vox file")?;
+
+    let file = vox::load(path)?;
+    let model = file
+        .models
+        .first()
+        .ok_or("the file doesn't contain a model")?;
+
+    let mut app = Application::new(Settings::load()?)?;
+
+    let octree = model.to_octree();
+    let size = octree.flatten().as_bytes().len();
+
+    let voxel_buffer = Buffer::new(
+        app.renderer.device.clone(),
+        size as u64,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+
+    // the shader traces the octree in storage buffer 0
+    let buffer = app.renderer.import_buffer(voxel_buffer.clone());
+    app.renderer.bind_storage_buffer(buffer, Some(0));
+    app.world.set_palette(&file.palette);
+    app.world.add_octree(octree, voxel_buffer, 0);
+
+    app.add_task(update_camera);
+    app.run();
+
+    Ok(())
+}
@@ -0,0 +1,386 @@
+// loads assets on the job system
+// ``AssetRegistry::load_async`` hands out a handle right away, a job reads and decodes the file
+// and ``AssetRegistry::finish_loads`` uploads the result on the main thread, where the renderer is
+//
+// every load started while others are still running belongs to the same batch,
+// an ``AssetEvent`` is emitted once the whole batch is done
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+};
+
+use ash::vk;
+use jobs::JobSystem;
+use rendering::{
+    assets::texture::{self, TextureError, TextureHandle, TextureOptions},
+    handler::RenderHandler,
+};
+
+use crate::world::vox::{self, VoxError, VoxFile};
+
+use super::{AssetHandle, AssetRegistry, Shader};
+
+/// how many threads decode assets if the registry doesn't share a job system, see ``AssetRegistry::set_jobs``
+const WORKERS: usize = 2;
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(std::io::Error),
+    /// the file isn't a valid asset of the type
+    Decode(String),
+    Vulkan(vk::Result),
+    /// the asset held resources of a device that was lost, see ``AssetRegistry::drop_gpu_assets``
+    DeviceLost,
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read asset: {err}"),
+            Self::Decode(err) => write!(f, "failed to decode asset: {err}"),
+            Self::Vulkan(err) => write!(f, "failed to upload asset: {err}"),
+            Self::DeviceLost => write!(f, "the asset belonged to a device that was lost"),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+impl From<std::io::Error> for AssetError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<vk::Result> for AssetError {
+    fn from(err: vk::Result) -> Self {
+        Self::Vulkan(err)
+    }
+}
+
+impl From<TextureError> for AssetError {
+    fn from(err: TextureError) -> Self {
+        match err {
+            TextureError::Io(err) => Self::Io(err),
+            TextureError::Vulkan(err) => Self::Vulkan(err),
+            err => Self::Decode(err.to_string()),
+        }
+    }
+}
+
+impl From<VoxError> for AssetError {
+    fn from(err: VoxError) -> Self {
+        match err {
+            VoxError::Io(err) => Self::Io(err),
+            VoxError::Invalid(err) => Self::Decode(err),
+        }
+    }
+}
+
+/// an asset that can be loaded with ``AssetRegistry::load_async``
+pub trait LoadAsset: Sized + 'static {
+    /// what the worker threads hand to the main thread
+    type Decoded: Send + 'static;
+
+    /// read and decode the file, runs on a worker thread
+    /// # Errors
+    /// if the file can't be read or isn't valid
+    fn decode(path: &Path) -> Result<Self::Decoded, AssetError>;
+
+    /// create the gpu resources, runs on the main thread in ``AssetRegistry::finish_loads``
+    /// # Errors
+    /// if there is no space to allocate the resources
+    fn upload(decoded: Self::Decoded, renderer: &mut RenderHandler) -> Result<Self, AssetError>;
+}
+
+impl LoadAsset for Shader {
+    type Decoded = Self;
+
+    fn decode(path: &Path) -> Result<Self, AssetError> {
+        Ok(Self::from_file(path)?)
+    }
+
+    fn upload(decoded: Self, _: &mut RenderHandler) -> Result<Self, AssetError> {
+        Ok(decoded)
+    }
+}
+
+impl LoadAsset for VoxFile {
+    type Decoded = Self;
+
+    fn decode(path: &Path) -> Result<Self, AssetError> {
+        Ok(vox::load(path)?)
+    }
+
+    fn upload(decoded: Self, _: &mut RenderHandler) -> Result<Self, AssetError> {
+        Ok(decoded)
+    }
+}
+
+/// a png, jpeg or ktx2 file with the default ``TextureOptions``
+/// the file is read in the background, decoding needs the renderer to pick a format
+impl LoadAsset for TextureHandle {
+    type Decoded = Vec<u8>;
+
+    fn decode(path: &Path) -> Result<Vec<u8>, AssetError> {
+        Ok(std::fs::read(path)?)
+    }
+
+    fn upload(decoded: Vec<u8>, renderer: &mut RenderHandler) -> Result<Self, AssetError> {
+        let options = TextureOptions::default();
+        let texture = texture::create_texture(renderer, &decoded, options)?;
+        let sampler = renderer.get_sampler(options.sampler)?;
+
+        renderer
+            .add_texture(texture, sampler)
+            .ok_or_else(|| TextureError::NoFreeSlot.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Ready,
+    Failed,
+}
+
+/// the loads of the current batch
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub pending: usize,
+    pub loaded: usize,
+    pub failed: usize,
+}
+
+impl LoadProgress {
+    /// how much of the batch is done, 1 if nothing is loading
+    #[must_use]
+    pub fn fraction(&self) -> f32 {
+        let done = self.loaded + self.failed;
+        let total = done + self.pending;
+        if total == 0 {
+            1.0
+        } else {
+            done as f32 / total as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetEvent {
+    /// every background load of a batch is done
+    BatchFinished { loaded: usize, failed: usize },
+}
+
+/// stores the result of a load in the registry, runs on the main thread
+type Finish = Box<dyn FnOnce(&mut AssetRegistry, &mut RenderHandler) + Send>;
+type Job = Box<dyn FnOnce() -> Finish + Send>;
+
+pub(super) struct AssetLoader {
+    pub jobs: Arc<JobSystem>,
+    finish_sender: Sender<Finish>,
+    finished: Receiver<Finish>,
+    progress: LoadProgress,
+    /// the events of the last ``finish_loads``
+    events: Vec<AssetEvent>,
+}
+
+impl AssetLoader {
+    fn new(jobs: Option<Arc<JobSystem>>) -> Self {
+        let (finish_sender, finished) = mpsc::channel();
+
+        Self {
+            jobs: jobs.unwrap_or_else(|| Arc::new(JobSystem::new(WORKERS))),
+            finish_sender,
+            finished,
+            progress: LoadProgress::default(),
+            events: vec![],
+        }
+    }
+
+    fn send(&mut self, job: Job) {
+        let finished = self.finish_sender.clone();
+        // the result is dropped if the registry is gone by then
+        let _ = self.jobs.spawn(move || finished.send(job()));
+        self.progress.pending += 1;
+    }
+}
+
+impl AssetRegistry {
+    /// start loading an asset on the job system, relative paths are resolved against ``root``
+    /// the handle can be used right away, ``get`` returns the asset once ``finish_loads`` uploaded it
+    /// if the file is already loaded or loading it's acquired instead
+    /// assets loaded like this aren't reloaded by ``poll_changes``
+    pub fn load_async<T: LoadAsset>(&mut self, path: impl AsRef<Path>) -> AssetHandle<T> {
+        let path = path.as_ref();
+        let name = self.intern(&path.to_string_lossy());
+
+        if let Some(handle) = self.find_name(name) {
+            return self.acquire(handle);
+        }
+
+        let handle = self.storage_mut::<T>().insert(name, None, None);
+        self.storage_mut::<T>().slots[handle.index as usize].background = true;
+        self.queue_load(handle, self.root.join(path));
+        handle
+    }
+
+    /// load the assets of type ``T`` again after ``RenderHandler::reinitialize``, the old ones belong to the lost device
+    /// the handles and names stay, the files loaded with ``load_async`` are loaded again in the background,
+    /// the others are dropped like with ``drop_gpu_assets``
+    pub fn reload_gpu_assets<T: LoadAsset>(&mut self) {
+        for handle in self.storage_mut::<T>().drop_assets() {
+            let Some(slot) = self.storage_mut().slot_mut(handle) else {
+                continue;
+            };
+            slot.error = None;
+            let name = slot.name;
+
+            let full_path = self.root.join(self.name_str(name));
+            self.queue_load(handle, full_path);
+        }
+    }
+
+    fn queue_load<T: LoadAsset>(&mut self, handle: AssetHandle<T>, full_path: PathBuf) {
+        let job: Job = Box::new(move || {
+            let decoded = T::decode(&full_path);
+            Box::new(move |registry, renderer| {
+                let result = decoded.and_then(|decoded| T::upload(decoded, renderer));
+                registry.finish_load(handle, &full_path, result);
+            })
+        });
+
+        let jobs = self.jobs.clone();
+        self.loader
+            .get_or_insert_with(|| AssetLoader::new(jobs))
+            .send(job);
+    }
+
+    fn finish_load<T: 'static>(
+        &mut self,
+        handle: AssetHandle<T>,
+        path: &Path,
+        result: Result<T, AssetError>,
+    ) {
+        let loader = self.loader.as_mut().expect("only loads finish");
+        loader.progress.pending -= 1;
+
+        match result {
+            Ok(asset) => {
+                loader.progress.loaded += 1;
+                // the asset might have been released while it was loading
+                self.storage_mut().replace(handle, asset);
+            }
+            Err(err) => {
+                loader.progress.failed += 1;
+                // the error is kept for ``load_error``, so it's only logged when debugging
+                tracing::debug!("failed to load asset {}: {err}", path.display());
+                if let Some(slot) = self.storage_mut().slot_mut(handle) {
+                    slot.error = Some(err);
+                }
+            }
+        }
+    }
+
+    /// upload the assets the workers are done with, should be called every frame
+    /// the events of the last call are replaced, see ``events``
+    pub fn finish_loads(&mut self, renderer: &mut RenderHandler) {
+        let Some(loader) = &mut self.loader else {
+            return;
+        };
+        loader.events.clear();
+
+        let finished: Vec<Finish> = loader.finished.try_iter().collect();
+        if finished.is_empty() {
+            return;
+        }
+
+        for finish in finished {
+            finish(self, renderer);
+        }
+
+        let loader = self.loader.as_mut().expect("the loader isn't removed");
+        if loader.progress.pending == 0 {
+            let LoadProgress { loaded, failed, .. } = std::mem::take(&mut loader.progress);
+            loader
+                .events
+                .push(AssetEvent::BatchFinished { loaded, failed });
+        }
+    }
+
+    /// the events of the last ``finish_loads``
+    #[must_use]
+    pub fn events(&self) -> &[AssetEvent] {
+        self.loader
+            .as_ref()
+            .map_or(&[], |loader| loader.events.as_slice())
+    }
+
+    /// the background loads of the current batch
+    #[must_use]
+    pub fn progress(&self) -> LoadProgress {
+        self.loader
+            .as_ref()
+            .map_or(LoadProgress::default(), |loader| loader.progress)
+    }
+
+    /// None if the handle was released
+    #[must_use]
+    pub fn load_state<T: 'static>(&self, handle: AssetHandle<T>) -> Option<LoadState> {
+        let slot = self.storage()?.slot(handle)?;
+
+        Some(match (&slot.asset, &slot.error) {
+            (Some(_), _) => LoadState::Ready,
+            (None, Some(_)) => LoadState::Failed,
+            (None, None) => LoadState::Loading,
+        })
+    }
+
+    /// why the asset failed to load
+    #[must_use]
+    pub fn load_error<T: 'static>(&self, handle: AssetHandle<T>) -> Option<&AssetError> {
+        self.storage()?.slot(handle)?.error.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_of_a_batch() {
+        let progress = |pending, loaded, failed| LoadProgress {
+            pending,
+            loaded,
+            failed,
+        };
+
+        assert_eq!(progress(0, 0, 0).fraction(), 1.0);
+        assert_eq!(progress(3, 1, 0).fraction(), 0.25);
+        assert_eq!(progress(0, 3, 1).fraction(), 1.0);
+    }
+
+    #[test]
+    fn handles_are_ready_to_use_while_loading() {
+        let mut assets = AssetRegistry::new("missing");
+
+        let handle = assets.load_async::<Shader>("shader.spv");
+        assert_eq!(assets.load_state(handle), Some(LoadState::Loading));
+        assert!(assets.get(handle).is_none());
+        assert_eq!(assets.progress().pending, 1);
+
+        // the second load shares the first one
+        assert_eq!(assets.load_async::<Shader>("shader.spv"), handle);
+        assert_eq!(assets.ref_count(handle), 2);
+        assert_eq!(assets.progress().pending, 1);
+
+        assets.release(handle);
+        assets.release(handle);
+        assert_eq!(assets.load_state(handle), None);
+    }
+}
@@ -0,0 +1,638 @@
+// a central place for everything loaded from disk or built once and shared,
+// content refers to assets with small handles instead of holding ``Arc<Buffer>``s
+//
+// every asset has a name, names are interned so comparing and hashing them is cheap,
+// assets loaded from a file are named after their path and can be reloaded when the file changes
+//
+// any type can be an asset, the registry keeps a separate storage for every type
+// types that implement ``LoadAsset`` can also be loaded in the background, see ``loading.rs``
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use ash::vk;
+use jobs::JobSystem;
+use loading::AssetLoader;
+use rendering::{handler::render_batch::DrawData, vulkan::Buffer};
+
+pub use loading::{AssetError, AssetEvent, LoadAsset, LoadProgress, LoadState};
+
+mod loading;
+
+/// how often ``AssetRegistry::poll_changes`` looks at the files
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// an interned string, see ``AssetRegistry::intern``
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AssetName(u32);
+
+/// points to an asset of type ``T`` in an ``AssetRegistry``
+/// handles of released assets stay invalid, even if their slot is reused
+pub struct AssetHandle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+// derive would only implement these if ``T`` does
+impl<T> Clone for AssetHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetHandle<T> {}
+
+impl<T> PartialEq for AssetHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for AssetHandle<T> {}
+
+impl<T> Hash for AssetHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for AssetHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssetHandle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+/// the buffers of something that can be drawn
+pub struct Mesh {
+    pub vertex_buffer: Option<Arc<Buffer>>,
+    pub index_buffer: Option<Arc<Buffer>>,
+    pub index_type: vk::IndexType,
+    pub index_count: u32,
+    pub vertex_count: u32,
+}
+
+impl Mesh {
+    /// a draw call of the mesh, with an optional instance buffer
+    #[must_use]
+    pub fn draw_data(&self, instance_buffer: Option<Arc<Buffer>>, instance_count: u32) -> DrawData {
+        DrawData {
+            vertex_buffer: self.vertex_buffer.clone(),
+            index_buffer: self.index_buffer.clone(),
+            index_type: self.index_type,
+            index_count: self.index_count,
+            vertex_count: self.vertex_count,
+            instance_buffer,
+            instance_count,
+            ..Default::default()
+        }
+    }
+}
+
+/// compiled spir-v, the modules are created from it when a material is built
+pub struct Shader {
+    pub code: Vec<u32>,
+}
+
+impl Shader {
+    /// # Errors
+    /// if the file can't be read or isn't spir-v
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let code = std::fs::read(path)?;
+        let code = ash::util::read_spv(&mut std::io::Cursor::new(code))?;
+        Ok(Self { code })
+    }
+}
+
+type LoadFn<T> = dyn Fn(&Path) -> std::io::Result<T>;
+type ReloadHook<T> = dyn FnMut(AssetHandle<T>, &T);
+
+/// the file an asset was loaded from, so it can be loaded again when it changes
+struct Source<T> {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    load: Box<LoadFn<T>>,
+}
+
+struct Slot<T> {
+    /// None while the asset is loaded in the background or if that failed
+    asset: Option<T>,
+    error: Option<AssetError>,
+    name: AssetName,
+    generation: u32,
+    refs: u32,
+    source: Option<Source<T>>,
+    /// loaded with ``load_async``, the name is the path of the file
+    background: bool,
+}
+
+struct Storage<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    by_name: HashMap<AssetName, u32>,
+    hooks: Vec<Box<ReloadHook<T>>>,
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Self {
+            slots: vec![],
+            free: vec![],
+            by_name: HashMap::new(),
+            hooks: vec![],
+        }
+    }
+}
+
+impl<T> Storage<T> {
+    fn slot(&self, handle: AssetHandle<T>) -> Option<&Slot<T>> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && slot.refs > 0)
+    }
+
+    fn slot_mut(&mut self, handle: AssetHandle<T>) -> Option<&mut Slot<T>> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && slot.refs > 0)
+    }
+
+    fn handle(&self, index: u32) -> AssetHandle<T> {
+        AssetHandle {
+            index,
+            generation: self.slots[index as usize].generation,
+            _marker: PhantomData,
+        }
+    }
+
+    fn insert(
+        &mut self,
+        name: AssetName,
+        asset: Option<T>,
+        source: Option<Source<T>>,
+    ) -> AssetHandle<T> {
+        let slot = Slot {
+            asset,
+            error: None,
+            name,
+            generation: 0,
+            refs: 1,
+            source,
+            background: false,
+        };
+
+        let index = if let Some(index) = self.free.pop() {
+            let old = &mut self.slots[index as usize];
+            *old = Slot {
+                generation: old.generation.wrapping_add(1),
+                ..slot
+            };
+            index
+        } else {
+            self.slots.push(slot);
+            self.slots.len() as u32 - 1
+        };
+
+        self.by_name.insert(name, index);
+        self.handle(index)
+    }
+
+    /// swap the asset and tell the hooks
+    fn replace(&mut self, handle: AssetHandle<T>, asset: T) -> Option<T> {
+        let slot = self.slot_mut(handle)?;
+        let old = slot.asset.replace(asset);
+        slot.error = None;
+
+        let asset = self.slots[handle.index as usize].asset.as_ref()?;
+        for hook in &mut self.hooks {
+            hook(handle, asset);
+        }
+        old
+    }
+
+    /// drop every loaded asset, they fail with ``AssetError::DeviceLost`` until they're replaced
+    /// returns the handles of the ones that were loaded in the background
+    fn drop_assets(&mut self) -> Vec<AssetHandle<T>> {
+        let mut background = vec![];
+        for index in 0..self.slots.len() as u32 {
+            let slot = &mut self.slots[index as usize];
+            if slot.refs == 0 || slot.asset.take().is_none() {
+                continue;
+            }
+
+            slot.error = Some(AssetError::DeviceLost);
+            if slot.background {
+                background.push(self.handle(index));
+            }
+        }
+        background
+    }
+}
+
+/// the part of a storage that doesn't depend on the asset type
+trait AnyStorage: Any {
+    /// load the assets whose file changed again, returns how many were reloaded
+    fn reload_changed(&mut self, names: &[Box<str>]) -> usize;
+
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+    fn reload_changed(&mut self, names: &[Box<str>]) -> usize {
+        let mut reloaded = 0;
+
+        for index in 0..self.slots.len() as u32 {
+            let slot = &mut self.slots[index as usize];
+            let (Some(source), Some(_)) = (&mut slot.source, &slot.asset) else {
+                continue;
+            };
+
+            let modified = modified_time(&source.path);
+            if modified == source.modified {
+                continue;
+            }
+
+            // a broken file is only reported once, it's loaded again after the next change
+            source.modified = modified;
+            let name = slot.name;
+            match (source.load)(&source.path) {
+                Ok(asset) => {
+                    let handle = self.handle(index);
+                    self.replace(handle, asset);
+                    reloaded += 1;
+                }
+                Err(err) => {
+                    tracing::error!("failed to reload asset {}: {err}", names[name.0 as usize]);
+                }
+            }
+        }
+
+        reloaded
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// owns the meshes, textures, materials, shaders and octrees shared by the content of the world
+/// every asset is reference counted, it's dropped once it was released as often as it was added or acquired
+pub struct AssetRegistry {
+    /// relative paths are resolved against this, see ``Settings::asset_root``
+    pub root: PathBuf,
+    names: Vec<Box<str>>,
+    name_indices: HashMap<Box<str>, AssetName>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    last_poll: Instant,
+    /// shared with the renderer, see ``set_jobs``
+    jobs: Option<Arc<JobSystem>>,
+    /// only created by the first ``load_async``
+    loader: Option<AssetLoader>,
+}
+
+impl Default for AssetRegistry {
+    fn default() -> Self {
+        Self::new("assets")
+    }
+}
+
+impl AssetRegistry {
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            names: vec![],
+            name_indices: HashMap::new(),
+            storages: HashMap::new(),
+            last_poll: Instant::now(),
+            jobs: None,
+            loader: None,
+        }
+    }
+
+    /// run the background loads on these workers,
+    /// without them the first ``load_async`` starts a small job system of its own
+    pub fn set_jobs(&mut self, jobs: Arc<JobSystem>) {
+        if let Some(loader) = &mut self.loader {
+            loader.jobs = jobs.clone();
+        }
+        self.jobs = Some(jobs);
+    }
+
+    /// the same string always gives the same name
+    pub fn intern(&mut self, name: &str) -> AssetName {
+        if let Some(&interned) = self.name_indices.get(name) {
+            return interned;
+        }
+
+        let interned = AssetName(self.names.len() as u32);
+        self.names.push(name.into());
+        self.name_indices.insert(name.into(), interned);
+        interned
+    }
+
+    /// the string of an interned name
+    #[must_use]
+    pub fn name_str(&self, name: AssetName) -> &str {
+        &self.names[name.0 as usize]
+    }
+
+    fn storage<T: 'static>(&self) -> Option<&Storage<T>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        storage.as_any().downcast_ref()
+    }
+
+    fn storage_mut<T: 'static>(&mut self) -> &mut Storage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T>::default()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("the storage is keyed by its type")
+    }
+
+    /// add an asset under a name, or replace the asset that already has the name and acquire it
+    pub fn insert<T: 'static>(&mut self, name: &str, asset: T) -> AssetHandle<T> {
+        let name = self.intern(name);
+        let storage = self.storage_mut::<T>();
+
+        if let Some(&index) = storage.by_name.get(&name) {
+            let handle = storage.handle(index);
+            storage.slots[index as usize].refs += 1;
+            storage.replace(handle, asset);
+            return handle;
+        }
+
+        storage.insert(name, Some(asset), None)
+    }
+
+    /// load an asset from a file, relative paths are resolved against ``root``
+    /// if the file is already loaded it's acquired instead
+    /// ``poll_changes`` loads the file again with the same function when it changes
+    /// # Errors
+    /// if the function failed to load the file
+    pub fn load<T: 'static>(
+        &mut self,
+        path: impl AsRef<Path>,
+        load: impl Fn(&Path) -> std::io::Result<T> + 'static,
+    ) -> std::io::Result<AssetHandle<T>> {
+        let path = path.as_ref();
+        let name = self.intern(&path.to_string_lossy());
+
+        if let Some(handle) = self.find_name(name) {
+            return Ok(self.acquire(handle));
+        }
+
+        let full_path = self.root.join(path);
+        let asset = load(&full_path)?;
+
+        let source = Source {
+            modified: modified_time(&full_path),
+            path: full_path,
+            load: Box::new(load),
+        };
+
+        Ok(self
+            .storage_mut::<T>()
+            .insert(name, Some(asset), Some(source)))
+    }
+
+    /// the handle of the asset with this name, doesn't acquire it
+    #[must_use]
+    pub fn find<T: 'static>(&self, name: &str) -> Option<AssetHandle<T>> {
+        self.find_name(*self.name_indices.get(name)?)
+    }
+
+    fn find_name<T: 'static>(&self, name: AssetName) -> Option<AssetHandle<T>> {
+        let storage = self.storage::<T>()?;
+        let &index = storage.by_name.get(&name)?;
+        Some(storage.handle(index))
+    }
+
+    /// None if the handle was released or the asset isn't loaded yet
+    #[must_use]
+    pub fn get<T: 'static>(&self, handle: AssetHandle<T>) -> Option<&T> {
+        self.storage()?.slot(handle)?.asset.as_ref()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, handle: AssetHandle<T>) -> Option<&mut T> {
+        self.storage_mut().slot_mut(handle)?.asset.as_mut()
+    }
+
+    /// the name the asset was added with
+    #[must_use]
+    pub fn name<T: 'static>(&self, handle: AssetHandle<T>) -> Option<AssetName> {
+        Some(self.storage()?.slot(handle)?.name)
+    }
+
+    /// 0 if the handle was released
+    #[must_use]
+    pub fn ref_count<T: 'static>(&self, handle: AssetHandle<T>) -> u32 {
+        self.storage()
+            .and_then(|storage| storage.slot(handle))
+            .map_or(0, |slot| slot.refs)
+    }
+
+    /// add a reference, the asset stays until it was released once more
+    pub fn acquire<T: 'static>(&mut self, handle: AssetHandle<T>) -> AssetHandle<T> {
+        if let Some(slot) = self.storage_mut().slot_mut(handle) {
+            slot.refs += 1;
+        }
+        handle
+    }
+
+    /// remove a reference, returns the asset if it was the last one
+    /// gpu resources in the asset might still be in use by the frames in flight, they are freed when it's dropped
+    pub fn release<T: 'static>(&mut self, handle: AssetHandle<T>) -> Option<T> {
+        let storage = self.storage_mut();
+        let slot = storage.slot_mut(handle)?;
+
+        slot.refs -= 1;
+        if slot.refs > 0 {
+            return None;
+        }
+
+        let name = slot.name;
+        slot.source = None;
+        slot.error = None;
+        let asset = slot.asset.take();
+
+        storage.by_name.remove(&name);
+        storage.free.push(handle.index);
+        asset
+    }
+
+    /// swap the asset behind a handle, every handle to it sees the new one
+    /// the reload hooks of the type are called, returns the old asset
+    pub fn reload<T: 'static>(&mut self, handle: AssetHandle<T>, asset: T) -> Option<T> {
+        self.storage_mut().replace(handle, asset)
+    }
+
+    /// drop the assets of type ``T``, for assets that hold resources of a device that was lost
+    /// the handles and names stay, ``get`` returns None and ``load_error`` is ``AssetError::DeviceLost`` until they're replaced
+    pub fn drop_gpu_assets<T: 'static>(&mut self) {
+        self.storage_mut::<T>().drop_assets();
+    }
+
+    /// called every time an asset of type ``T`` is replaced or reloaded from its file
+    /// use this to update what was built from the asset, like draw calls or materials
+    pub fn on_reload<T: 'static>(&mut self, hook: impl FnMut(AssetHandle<T>, &T) + 'static) {
+        self.storage_mut::<T>().hooks.push(Box::new(hook));
+    }
+
+    /// load the assets whose file changed since they were loaded, returns how many were reloaded
+    /// the files are only checked a few times per second, so this can be called every frame
+    pub fn poll_changes(&mut self) -> usize {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return 0;
+        }
+        self.last_poll = Instant::now();
+
+        let names = &self.names;
+        self.storages
+            .values_mut()
+            .map(|storage| storage.reload_changed(names))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn names_are_interned() {
+        let mut assets = AssetRegistry::default();
+
+        let a = assets.intern("meshes/cube");
+        let b = assets.intern("meshes/sphere");
+        assert_ne!(a, b);
+        assert_eq!(assets.intern("meshes/cube"), a);
+        assert_eq!(assets.name_str(b), "meshes/sphere");
+    }
+
+    #[test]
+    fn dropped_gpu_assets_keep_their_handles() {
+        let mut assets = AssetRegistry::default();
+        let handle = assets.insert("mesh", 1u32);
+
+        assets.drop_gpu_assets::<u32>();
+        assert!(assets.get(handle).is_none());
+        assert_eq!(assets.load_state(handle), Some(LoadState::Failed));
+        assert!(matches!(
+            assets.load_error(handle),
+            Some(AssetError::DeviceLost)
+        ));
+
+        assert_eq!(assets.insert("mesh", 2u32), handle);
+        assert_eq!(assets.get(handle), Some(&2));
+        assert_eq!(assets.load_state(handle), Some(LoadState::Ready));
+    }
+
+    #[test]
+    fn assets_are_reference_counted() {
+        let mut assets = AssetRegistry::default();
+
+        let handle = assets.insert("answer", 42u32);
+        assert_eq!(assets.find::<u32>("answer"), Some(handle));
+        // the same name with another type is another asset
+        assert_eq!(assets.find::<i64>("answer"), None);
+
+        assets.acquire(handle);
+        assert_eq!(assets.ref_count(handle), 2);
+
+        assert_eq!(assets.release(handle), None);
+        assert_eq!(assets.get(handle), Some(&42));
+        assert_eq!(assets.release(handle), Some(42));
+
+        assert_eq!(assets.get(handle), None);
+        assert_eq!(assets.find::<u32>("answer"), None);
+        assert_eq!(assets.release(handle), None);
+    }
+
+    #[test]
+    fn reused_slots_invalidate_old_handles() {
+        let mut assets = AssetRegistry::default();
+
+        let old = assets.insert("a", 1u32);
+        assets.release(old);
+        let new = assets.insert("b", 2u32);
+
+        assert_eq!(old.index, new.index);
+        assert_eq!(assets.get(old), None);
+        assert_eq!(assets.get(new), Some(&2));
+    }
+
+    #[test]
+    fn replacing_calls_the_hooks() {
+        let mut assets = AssetRegistry::default();
+        let seen = Rc::new(RefCell::new(vec![]));
+
+        let hook_seen = seen.clone();
+        assets.on_reload(move |_, value: &u32| hook_seen.borrow_mut().push(*value));
+
+        let handle = assets.insert("value", 1u32);
+        assert_eq!(assets.insert("value", 2u32), handle);
+        assert_eq!(assets.reload(handle, 3), Some(2));
+
+        assert_eq!(*seen.borrow(), [2, 3]);
+        assert_eq!(assets.ref_count(handle), 2);
+    }
+
+    #[test]
+    fn changed_files_are_reloaded() {
+        let root = std::env::temp_dir().join(format!("puddle_assets_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("value.txt"), "1").unwrap();
+
+        let mut assets = AssetRegistry::new(&root);
+        let load = |path: &Path| -> std::io::Result<u32> {
+            std::fs::read_to_string(path)?
+                .trim()
+                .parse()
+                .map_err(std::io::Error::other)
+        };
+
+        let handle = assets.load("value.txt", load).unwrap();
+        assert_eq!(assets.load("value.txt", load).unwrap(), handle);
+        assert_eq!(assets.get(handle), Some(&1));
+
+        std::fs::write(root.join("value.txt"), "2").unwrap();
+        let file = std::fs::File::options()
+            .write(true)
+            .open(root.join("value.txt"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        assets.last_poll -= POLL_INTERVAL;
+        assert_eq!(assets.poll_changes(), 1);
+        assert_eq!(assets.get(handle), Some(&2));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+// decoded sounds, read from wav files with hound and ogg vorbis files with lewton
+// the samples are kept as interleaved f32 in the sample rate of the file, the mixer resamples them while playing
+
+use std::{fmt, fs::File, io::BufReader, path::Path, sync::Arc};
+
+use rendering::handler::RenderHandler;
+
+use crate::assets::{AssetError, LoadAsset};
+
+#[derive(Debug)]
+pub enum AudioError {
+    Io(std::io::Error),
+    /// the file isn't a valid wav or ogg file
+    Invalid(String),
+    /// the extension isn't wav or ogg
+    Unsupported(String),
+    /// there is no output device or it can't play f32 or i16 samples
+    Device(String),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read sound: {err}"),
+            Self::Invalid(err) => write!(f, "invalid sound file: {err}"),
+            Self::Unsupported(extension) => write!(f, "unsupported sound format: {extension}"),
+            Self::Device(err) => write!(f, "failed to open the audio device: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+impl From<std::io::Error> for AudioError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<AudioError> for AssetError {
+    fn from(err: AudioError) -> Self {
+        match err {
+            AudioError::Io(err) => Self::Io(err),
+            err => Self::Decode(err.to_string()),
+        }
+    }
+}
+
+/// a decoded sound
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioClip {
+    pub sample_rate: u32,
+    /// 1 for mono, 2 for stereo, the mixer only plays the first two channels
+    pub channels: u16,
+    /// interleaved, between -1 and 1
+    pub samples: Vec<f32>,
+}
+
+impl AudioClip {
+    /// # Errors
+    /// if the file can't be read, isn't valid or isn't a wav or ogg file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match extension.as_str() {
+            "wav" => load_wav(path),
+            "ogg" => load_ogg(path),
+            _ => Err(AudioError::Unsupported(extension)),
+        }
+    }
+
+    /// the amount of samples per channel
+    #[must_use]
+    pub fn frames(&self) -> usize {
+        self.samples.len() / usize::from(self.channels.max(1))
+    }
+
+    /// in seconds
+    #[must_use]
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate.max(1) as f32
+    }
+
+    /// the left and right sample of the frame, mono clips play the same sample on both sides
+    #[must_use]
+    pub(super) fn frame(&self, index: usize) -> [f32; 2] {
+        let channels = usize::from(self.channels.max(1));
+        let first = index * channels;
+        match self.samples.get(first..first + channels) {
+            Some([mono]) => [*mono, *mono],
+            Some([left, right, ..]) => [*left, *right],
+            _ => [0.0; 2],
+        }
+    }
+}
+
+fn load_wav(path: &Path) -> Result<AudioClip, AudioError> {
+    let reader = hound::WavReader::open(path).map_err(|err| match err {
+        hound::Error::IoError(err) => AudioError::Io(err),
+        err => AudioError::Invalid(err.to_string()),
+    })?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect::<Result<_, _>>()
+        }
+    }
+    .map_err(|err| AudioError::Invalid(err.to_string()))?;
+
+    Ok(AudioClip {
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        samples,
+    })
+}
+
+fn load_ogg(path: &Path) -> Result<AudioClip, AudioError> {
+    let invalid = |err: lewton::VorbisError| AudioError::Invalid(err.to_string());
+
+    let file = BufReader::new(File::open(path)?);
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(file).map_err(invalid)?;
+
+    let mut samples = vec![];
+    while let Some(packet) = reader.read_dec_packet_itl().map_err(invalid)? {
+        samples.extend(packet.iter().map(|&sample| f32::from(sample) / 32768.0));
+    }
+
+    Ok(AudioClip {
+        sample_rate: reader.ident_hdr.audio_sample_rate,
+        channels: u16::from(reader.ident_hdr.audio_channels),
+        samples,
+    })
+}
+
+/// decoded on a worker thread, shared with the mixer through the ``Arc``
+impl LoadAsset for Arc<AudioClip> {
+    type Decoded = AudioClip;
+
+    fn decode(path: &Path) -> Result<AudioClip, AssetError> {
+        Ok(AudioClip::load(path)?)
+    }
+
+    fn upload(decoded: AudioClip, _: &mut RenderHandler) -> Result<Self, AssetError> {
+        Ok(Arc::new(decoded))
+    }
+}
@@ -0,0 +1,159 @@
+// mixes the playing sounds in to the buffer of the output device, runs on the audio thread
+// the main thread only changes the gains and the play state of the voices, so the lock is held briefly
+
+use std::sync::Arc;
+
+use super::clip::AudioClip;
+
+/// one playing sound, it belongs to the ``AudioSource`` with the same index
+pub(super) struct Voice {
+    pub clip: Arc<AudioClip>,
+    /// in frames of the clip, fractional because the clip is resampled to the output rate
+    pub cursor: f64,
+    pub looping: bool,
+    pub playing: bool,
+    /// the left and right gain, with the volume and spatialization applied
+    pub gain: [f32; 2],
+}
+
+impl Voice {
+    pub fn new(clip: Arc<AudioClip>) -> Self {
+        Self {
+            clip,
+            cursor: 0.0,
+            looping: false,
+            playing: false,
+            gain: [0.0; 2],
+        }
+    }
+
+    /// add ``frames`` stereo frames to ``out``, stops at the end of the clip if it doesn't loop
+    fn mix(&mut self, out: &mut [[f32; 2]], output_rate: u32) {
+        let frames = self.clip.frames();
+        if frames == 0 {
+            self.playing = false;
+            return;
+        }
+
+        let step = f64::from(self.clip.sample_rate) / f64::from(output_rate.max(1));
+
+        for frame in out {
+            if self.cursor >= frames as f64 {
+                if !self.looping {
+                    self.playing = false;
+                    self.cursor = 0.0;
+                    return;
+                }
+                self.cursor %= frames as f64;
+            }
+
+            // linear interpolation between the two closest frames
+            let index = self.cursor as usize;
+            let t = (self.cursor - index as f64) as f32;
+            let next = if index + 1 < frames {
+                index + 1
+            } else if self.looping {
+                0
+            } else {
+                index
+            };
+            let [a, b] = [self.clip.frame(index), self.clip.frame(next)];
+
+            for channel in 0..2 {
+                let sample = a[channel] + (b[channel] - a[channel]) * t;
+                frame[channel] += sample * self.gain[channel];
+            }
+
+            self.cursor += step;
+        }
+    }
+}
+
+pub(super) struct Mixer {
+    pub voices: Vec<Voice>,
+    pub sample_rate: u32,
+    /// scratch space for one callback, in stereo frames
+    frames: Vec<[f32; 2]>,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            voices: vec![],
+            sample_rate,
+            frames: vec![],
+        }
+    }
+
+    /// overwrite ``out`` with the playing voices, ``out`` is interleaved with ``channels`` channels
+    /// mono outputs get the average of both sides, channels after the second one stay silent
+    pub fn mix(&mut self, out: &mut [f32], channels: usize) {
+        let channels = channels.max(1);
+        self.frames.clear();
+        self.frames.resize(out.len() / channels, [0.0; 2]);
+
+        for voice in self.voices.iter_mut().filter(|voice| voice.playing) {
+            voice.mix(&mut self.frames, self.sample_rate);
+        }
+
+        for (out, [left, right]) in out.chunks_mut(channels).zip(&self.frames) {
+            match out {
+                [mono] => *mono = ((left + right) * 0.5).clamp(-1.0, 1.0),
+                [out_left, out_right, rest @ ..] => {
+                    *out_left = left.clamp(-1.0, 1.0);
+                    *out_right = right.clamp(-1.0, 1.0);
+                    rest.fill(0.0);
+                }
+                [] => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(samples: Vec<f32>, sample_rate: u32) -> Arc<AudioClip> {
+        Arc::new(AudioClip {
+            sample_rate,
+            channels: 1,
+            samples,
+        })
+    }
+
+    #[test]
+    fn voices_stop_or_loop_at_the_end() {
+        let mut mixer = Mixer::new(4);
+        let mut voice = Voice::new(clip(vec![0.5, 0.25], 4));
+        voice.playing = true;
+        voice.gain = [1.0, 0.5];
+        mixer.voices.push(voice);
+
+        let mut out = [1.0; 8];
+        mixer.mix(&mut out, 2);
+        assert_eq!(out, [0.5, 0.25, 0.25, 0.125, 0.0, 0.0, 0.0, 0.0]);
+        assert!(!mixer.voices[0].playing);
+
+        mixer.voices[0].playing = true;
+        mixer.voices[0].looping = true;
+        let mut out = [0.0; 3];
+        mixer.mix(&mut out, 1);
+        assert_eq!(out, [0.375, 0.1875, 0.375]);
+        assert!(mixer.voices[0].playing);
+    }
+
+    #[test]
+    fn clips_are_resampled_to_the_output_rate() {
+        // twice the rate of the clip, every second frame is in between two samples
+        let mut mixer = Mixer::new(8);
+        let mut voice = Voice::new(clip(vec![0.0, 1.0, 0.0], 4));
+        voice.playing = true;
+        voice.gain = [1.0; 2];
+        mixer.voices.push(voice);
+
+        let mut out = [0.0; 4];
+        mixer.mix(&mut out, 1);
+        assert_eq!(out, [0.0, 0.5, 1.0, 0.5]);
+    }
+}
@@ -0,0 +1,289 @@
+// plays sounds through the default output device with cpal
+// every ``AudioSource`` has a voice in the mixer, the mixer runs on the audio thread and
+// ``Audio::update`` writes the volume and the spatialization of every source to its voice once per frame
+//
+// sources that follow an entity get quieter with the distance to the listener and are panned
+// between the left and right side, the listener is where the camera is
+
+use std::sync::{Arc, Mutex};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SizedSample,
+};
+use math::{Camera, Transform, Vec3};
+
+use crate::world::hierarchy::{Entities, Entity};
+use mixer::{Mixer, Voice};
+
+pub mod clip;
+mod mixer;
+
+pub use clip::{AudioClip, AudioError};
+
+/// how the volume of a source falls off with the distance to the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    /// closer than this the source plays at full volume
+    pub min_distance: f32,
+    /// further away than this the source is silent
+    pub max_distance: f32,
+    /// how fast the volume falls off after ``min_distance``, 1 halves it at twice the distance
+    pub rolloff: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self {
+            min_distance: 1.0,
+            max_distance: 100.0,
+            rolloff: 1.0,
+        }
+    }
+}
+
+impl Attenuation {
+    /// the volume at ``distance``, between 0 and 1
+    #[must_use]
+    pub fn gain(&self, distance: f32) -> f32 {
+        if distance >= self.max_distance {
+            return 0.0;
+        }
+        let min_distance = self.min_distance.max(f32::EPSILON);
+        let distance = distance.max(min_distance);
+        (min_distance / (min_distance + self.rolloff * (distance - min_distance))).clamp(0.0, 1.0)
+    }
+}
+
+/// a sound in the world, added with ``Audio::add_source``
+#[derive(Debug, Clone)]
+pub struct AudioSource {
+    /// the source plays at the position of the entity, None plays it without spatialization
+    pub entity: Option<Entity>,
+    pub clip: Arc<AudioClip>,
+    pub volume: f32,
+    /// start over at the end of the clip instead of stopping
+    pub looping: bool,
+    pub attenuation: Attenuation,
+}
+
+impl AudioSource {
+    #[must_use]
+    pub fn new(clip: Arc<AudioClip>) -> Self {
+        Self {
+            entity: None,
+            clip,
+            volume: 1.0,
+            looping: false,
+            attenuation: Attenuation::default(),
+        }
+    }
+}
+
+/// hears the sources from the transform of the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+    /// applied to every source
+    pub volume: f32,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self { volume: 1.0 }
+    }
+}
+
+pub struct Audio {
+    pub listener: Listener,
+    sources: Vec<AudioSource>,
+    mixer: Arc<Mutex<Mixer>>,
+    /// the sound stops when the stream is dropped
+    _stream: cpal::Stream,
+}
+
+impl Audio {
+    /// open the default output device and start an empty stream
+    /// # Errors
+    /// if there is no output device or it doesn't support f32 or i16 samples
+    pub fn new() -> Result<Self, AudioError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| AudioError::Device("no output device".into()))?;
+        let config = device
+            .default_output_config()
+            .map_err(|err| AudioError::Device(err.to_string()))?;
+
+        let mixer = Arc::new(Mutex::new(Mixer::new(config.sample_rate().0)));
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config.config(), &mixer),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config.config(), &mixer),
+            format => Err(AudioError::Device(format!(
+                "unsupported sample format {format}"
+            ))),
+        }?;
+        stream
+            .play()
+            .map_err(|err| AudioError::Device(err.to_string()))?;
+
+        Ok(Self {
+            listener: Listener::default(),
+            sources: vec![],
+            mixer,
+            _stream: stream,
+        })
+    }
+
+    /// the source is stopped until ``play`` is called
+    pub fn add_source(&mut self, source: AudioSource) -> usize {
+        self.lock().voices.push(Voice::new(source.clip.clone()));
+        self.sources.push(source);
+        self.sources.len() - 1
+    }
+
+    /// changes to the clip are applied the next time the source is played
+    #[must_use]
+    pub fn source_mut(&mut self, index: usize) -> Option<&mut AudioSource> {
+        self.sources.get_mut(index)
+    }
+
+    /// play the source from the start
+    /// # Panics
+    /// if the index is out of bounds
+    pub fn play(&mut self, index: usize) {
+        let clip = self.sources[index].clip.clone();
+        let voice = &mut self.lock().voices[index];
+        voice.clip = clip;
+        voice.cursor = 0.0;
+        voice.playing = true;
+    }
+
+    /// stop the source, ``play`` starts it from the start again
+    /// # Panics
+    /// if the index is out of bounds
+    pub fn stop(&mut self, index: usize) {
+        self.lock().voices[index].playing = false;
+    }
+
+    /// false once a source that doesn't loop reached the end of its clip
+    /// # Panics
+    /// if the index is out of bounds
+    #[must_use]
+    pub fn is_playing(&self, index: usize) -> bool {
+        self.lock().voices[index].playing
+    }
+
+    /// update the volume and spatialization of every source, the transforms have to be propagated
+    pub fn update(&mut self, entities: &Entities, camera: &Camera) {
+        let gains: Vec<[f32; 2]> = self
+            .sources
+            .iter()
+            .map(|source| {
+                let gain = source.volume * self.listener.volume;
+                let pan = match source.entity {
+                    Some(entity) if entities.contains(entity) => spatialize(
+                        &camera.transform,
+                        entities.global_transform(entity).translation(),
+                        &source.attenuation,
+                    ),
+                    Some(_) => [0.0; 2],
+                    None => [1.0; 2],
+                };
+                pan.map(|pan| pan * gain)
+            })
+            .collect();
+
+        let mut mixer = self.lock();
+        for ((voice, source), gain) in mixer.voices.iter_mut().zip(&self.sources).zip(gains) {
+            voice.gain = gain;
+            voice.looping = source.looping;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Mixer> {
+        // the audio callback can't panic while holding the lock, the mixer stays valid either way
+        self.mixer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// the left and right gain of a source at ``position``
+/// equal power panning, so the source doesn't get louder while it moves from one side to the other
+#[must_use]
+pub fn spatialize(listener: &Transform, position: Vec3, attenuation: &Attenuation) -> [f32; 2] {
+    let offset = position - listener.translation;
+    let gain = attenuation.gain(offset.length());
+
+    // -1 is left, 1 is right, sources in front or on the listener are in the middle
+    let pan = offset.normalize_or_zero().dot(listener.right());
+    let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+
+    [angle.cos() * gain, angle.sin() * gain]
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mixer: &Arc<Mutex<Mixer>>,
+) -> Result<cpal::Stream, AudioError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let mixer = mixer.clone();
+    let channels = usize::from(config.channels);
+    let mut samples = vec![];
+
+    device
+        .build_output_stream(
+            config,
+            move |out: &mut [T], _| {
+                samples.resize(out.len(), 0.0);
+                match mixer.lock() {
+                    Ok(mut mixer) => mixer.mix(&mut samples, channels),
+                    Err(_) => samples.fill(0.0),
+                }
+                for (out, sample) in out.iter_mut().zip(&samples) {
+                    *out = T::from_sample(*sample);
+                }
+            },
+            |err| eprintln!("audio stream error: {err}"),
+            None,
+        )
+        .map_err(|err| AudioError::Device(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_fall_off_with_distance() {
+        let attenuation = Attenuation {
+            min_distance: 2.0,
+            max_distance: 10.0,
+            rolloff: 1.0,
+        };
+        assert_eq!(attenuation.gain(0.0), 1.0);
+        assert_eq!(attenuation.gain(2.0), 1.0);
+        assert_eq!(attenuation.gain(4.0), 0.5);
+        assert_eq!(attenuation.gain(10.0), 0.0);
+    }
+
+    #[test]
+    fn sources_are_panned_to_their_side() {
+        let listener = Transform::IDENTITY;
+        let attenuation = Attenuation::default();
+
+        let [left, right] = spatialize(&listener, listener.right(), &attenuation);
+        assert!(left.abs() < 1e-6 && (right - 1.0).abs() < 1e-6);
+
+        let [left, right] = spatialize(&listener, listener.left(), &attenuation);
+        assert!((left - 1.0).abs() < 1e-6 && right.abs() < 1e-6);
+
+        // equal power in the middle
+        let [left, right] = spatialize(&listener, listener.forward(), &attenuation);
+        assert!((left - right).abs() < 1e-6);
+        assert!((left * left + right * right - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,487 @@
+// the built-in debug panels, drawn with egui
+// every panel is its own window, so they can be moved, collapsed and closed on their own
+
+use std::time::Duration;
+
+use math::{Camera, EulerRot, Projection, Quat, Transform};
+use rendering::handler::{stats::SlotUsage, RenderHandler};
+
+use crate::{
+    profiling::CpuTimings,
+    visibility::check_batch,
+    world::{
+        brush::{Brush, BrushShape},
+        hierarchy::Entity,
+        preview::BrushPreview,
+        World,
+    },
+};
+
+/// which panels are shown while the debug ui is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugPanels {
+    pub frame_stats: bool,
+    pub bindless: bool,
+    pub resources: bool,
+    pub batches: bool,
+    pub camera: bool,
+    pub entities: bool,
+    pub brush: bool,
+    pub time: bool,
+}
+
+impl Default for DebugPanels {
+    fn default() -> Self {
+        Self {
+            frame_stats: true,
+            bindless: false,
+            resources: false,
+            batches: false,
+            camera: false,
+            entities: false,
+            brush: false,
+            time: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DebugUi {
+    /// toggled by ``Application::debug_ui_key``
+    pub open: bool,
+    pub panels: DebugPanels,
+    /// the entity shown in the inspector
+    selected: Option<Entity>,
+    /// the batch whose visibility is checked on the cpu, see ``visibility::check_batch``
+    inspected_batch: Option<usize>,
+}
+
+impl DebugUi {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        world: &mut World,
+        renderer: &mut RenderHandler,
+        cpu: &CpuTimings,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let panels = &mut self.panels;
+
+        egui::TopBottomPanel::top("debug panels").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut panels.frame_stats, "frame stats");
+                ui.toggle_value(&mut panels.bindless, "bindless");
+                ui.toggle_value(&mut panels.resources, "resources");
+                ui.toggle_value(&mut panels.batches, "batches");
+                ui.toggle_value(&mut panels.camera, "camera");
+                ui.toggle_value(&mut panels.entities, "entities");
+                ui.toggle_value(&mut panels.brush, "brush");
+                ui.toggle_value(&mut panels.time, "time");
+            });
+        });
+
+        egui::Window::new("frame stats")
+            .open(&mut panels.frame_stats)
+            .show(ctx, |ui| frame_stats(ui, renderer, cpu));
+
+        egui::Window::new("bindless")
+            .open(&mut panels.bindless)
+            .show(ctx, |ui| bindless(ui, renderer));
+
+        egui::Window::new("resources")
+            .open(&mut panels.resources)
+            .show(ctx, |ui| resources(ui, renderer));
+
+        let inspected = &mut self.inspected_batch;
+        egui::Window::new("batches")
+            .open(&mut panels.batches)
+            .show(ctx, |ui| batches(ui, renderer, &world.camera, inspected));
+
+        egui::Window::new("camera")
+            .open(&mut panels.camera)
+            .show(ctx, |ui| camera(ui, world));
+
+        let selected = &mut self.selected;
+        egui::Window::new("entities")
+            .open(&mut panels.entities)
+            .show(ctx, |ui| entities(ui, world, selected));
+
+        egui::Window::new("brush")
+            .open(&mut panels.brush)
+            .show(ctx, |ui| brush(ui, world, renderer));
+
+        egui::Window::new("time")
+            .open(&mut panels.time)
+            .show(ctx, |ui| time(ui, world));
+    }
+}
+
+fn ms(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn frame_stats(ui: &mut egui::Ui, renderer: &RenderHandler, cpu: &CpuTimings) {
+    let stats = renderer.frame_stats();
+    let fps = 1.0 / stats.frame_time.as_secs_f64().max(f64::EPSILON);
+
+    egui::Grid::new("frame stats").striped(true).show(ui, |ui| {
+        let mut row = |name: &str, value: String| {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        };
+
+        row(
+            "frame time",
+            format!("{} ({fps:.0} fps)", ms(stats.frame_time)),
+        );
+        row(
+            "gpu time",
+            stats.gpu_time.map_or_else(|| "not supported".into(), ms),
+        );
+        row("latency", ms(stats.latency));
+        row("presented frames", stats.presented_frames.to_string());
+        row("reused scenes", stats.reused_scenes.to_string());
+        let validation = stats.validation;
+        row(
+            "validation",
+            format!(
+                "{} errors, {} warnings, {} suppressed",
+                validation.errors, validation.warnings, validation.suppressed
+            ),
+        );
+
+        row("cpu tasks", ms(cpu.tasks));
+        row("cpu update", ms(cpu.update));
+        row("cpu ui", ms(cpu.ui));
+        row("cpu render", ms(cpu.render));
+        row("cpu events", ms(cpu.events));
+    });
+}
+
+fn bindless(ui: &mut egui::Ui, renderer: &RenderHandler) {
+    let usage = renderer.bindless_usage();
+
+    let mut slots = |name: &str, slots: SlotUsage| {
+        ui.label(name);
+        ui.add(
+            egui::ProgressBar::new((slots.used + slots.reserved) as f32 / slots.capacity as f32)
+                .text(format!(
+                    "{} used, {} reserved, {} free",
+                    slots.used,
+                    slots.reserved,
+                    slots.free()
+                )),
+        );
+    };
+
+    slots("uniform buffers", usage.uniform_buffers);
+    slots("storage buffers", usage.storage_buffers);
+    slots("storage images", usage.storage_images);
+    slots("textures", usage.textures);
+}
+
+fn resources(ui: &mut egui::Ui, renderer: &RenderHandler) {
+    let counts = renderer.resource_counts();
+    let memory = renderer.texture_memory();
+
+    egui::Grid::new("resources").striped(true).show(ui, |ui| {
+        let mut row = |name: &str, value: String| {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        };
+
+        row("render batches", counts.render_batches.to_string());
+        row("draw calls", counts.draw_calls.to_string());
+        row("materials", counts.materials.to_string());
+        row("material instances", counts.material_instances.to_string());
+        row("particle systems", counts.particle_systems.to_string());
+        row("sprite batches", counts.sprite_batches.to_string());
+        row("pending destroys", counts.pending_destroys.to_string());
+        row(
+            "texture memory",
+            format!(
+                "{} KiB ({} KiB uncompressed)",
+                memory.size / 1024,
+                memory.uncompressed_size / 1024
+            ),
+        );
+    });
+}
+
+fn batches(
+    ui: &mut egui::Ui,
+    renderer: &mut RenderHandler,
+    camera: &Camera,
+    inspected: &mut Option<usize>,
+) {
+    for i in 0..renderer.render_batches().len() {
+        let batch = &renderer.render_batches()[i];
+        let name = batch
+            .name()
+            .map_or_else(|| format!("batch {i}"), str::to_owned);
+        let draws = batch.draw_count();
+        let mut visible = batch.is_visible();
+
+        ui.horizontal(|ui| {
+            // the batch is only borrowed mutably when it changes, so the renderer can still reuse the scene
+            if ui
+                .checkbox(&mut visible, format!("{name} ({draws} draws)"))
+                .changed()
+            {
+                if let Some(batch) = renderer.render_batch_mut(i) {
+                    batch.set_visible(visible);
+                }
+            }
+
+            let mut checked = *inspected == Some(i);
+            if ui.toggle_value(&mut checked, "why?").changed() {
+                *inspected = checked.then_some(i);
+            }
+        });
+    }
+
+    let Some(batch) = inspected.and_then(|i| renderer.render_batches().get(i)) else {
+        *inspected = None;
+        return;
+    };
+
+    ui.separator();
+    let report = check_batch(camera, batch);
+
+    egui::Grid::new("visibility").striped(true).show(ui, |ui| {
+        let mut row = |name: &str, value: String| {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        };
+
+        row("verdict", report.verdict.to_string());
+        if let Some(bounds) = batch.bounds() {
+            row(
+                "bounds",
+                format!("{:.2?} to {:.2?}", bounds.min, bounds.max),
+            );
+            row(
+                "corners in front",
+                format!("{} of 8", report.corners_in_front),
+            );
+        }
+        if let Some([min, max]) = report.screen {
+            row(
+                "screen",
+                format!("{:.2} {:.2} to {:.2} {:.2}", min.x, min.y, max.x, max.y),
+            );
+        }
+        if let Some([near, far]) = report.depth {
+            row("depth", format!("{near:.5} to {far:.5}"));
+        }
+    });
+}
+
+fn camera(ui: &mut egui::Ui, world: &mut World) {
+    let camera = &mut world.camera;
+
+    transform(ui, &mut camera.transform);
+    ui.separator();
+
+    ui.label(format!("aspect {:.3}", camera.aspect));
+
+    match &mut camera.projection {
+        Projection::Perspective {
+            fovy,
+            znear,
+            zfar,
+            infinite_reverse_z,
+        } => {
+            ui.add(egui::Slider::new(fovy, 1.0..=179.0).text("fov"));
+            ui.add(egui::DragValue::new(znear).speed(0.01).prefix("near "));
+            if !*infinite_reverse_z {
+                ui.add(egui::DragValue::new(zfar).prefix("far "));
+            }
+            // switching it needs a different depth test, so it can't be changed here
+            if *infinite_reverse_z {
+                ui.label("infinite reverse z");
+            }
+        }
+        Projection::Orthographic {
+            height,
+            znear,
+            zfar,
+        } => {
+            ui.add(egui::DragValue::new(height).prefix("height "));
+            ui.add(egui::DragValue::new(znear).speed(0.01).prefix("near "));
+            ui.add(egui::DragValue::new(zfar).prefix("far "));
+        }
+        Projection::Custom(_) => {
+            ui.label("custom projection");
+        }
+    }
+}
+
+fn entities(ui: &mut egui::Ui, world: &mut World, selected: &mut Option<Entity>) {
+    let entities = &mut world.entities;
+    ui.label(format!("{} entities", entities.len()));
+
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            let roots: Vec<Entity> = entities.roots().collect();
+            for root in roots {
+                entity_tree(ui, entities, root, selected);
+            }
+        });
+
+    ui.separator();
+
+    match *selected {
+        Some(entity) if entities.contains(entity) => {
+            ui.label(format!("{entity:?}"));
+            transform(ui, entities.transform_mut(entity));
+        }
+        _ => {
+            *selected = None;
+            ui.label("no entity selected");
+        }
+    }
+}
+
+fn entity_tree(
+    ui: &mut egui::Ui,
+    entities: &crate::world::hierarchy::Entities,
+    entity: Entity,
+    selected: &mut Option<Entity>,
+) {
+    let label = format!("{entity:?}");
+    let children = entities.children(entity);
+
+    if children.is_empty() {
+        ui.selectable_value(selected, Some(entity), label);
+        return;
+    }
+
+    egui::CollapsingHeader::new(label)
+        .id_salt(entity)
+        .show(ui, |ui| {
+            ui.selectable_value(selected, Some(entity), "select");
+            for child in children {
+                entity_tree(ui, entities, *child, selected);
+            }
+        });
+}
+
+/// the rotation is edited as euler angles in degrees
+fn brush(ui: &mut egui::Ui, world: &mut World, renderer: &mut RenderHandler) {
+    let mut enabled = world.brush.is_some();
+    if ui
+        .checkbox(&mut enabled, "paint with the left mouse button")
+        .changed()
+    {
+        world.brush = enabled.then(Brush::default);
+    }
+
+    // hidden instead of removed, so the sprites are cleared in the next frame
+    let mut preview = world
+        .preview
+        .as_ref()
+        .is_some_and(|preview| preview.visible);
+    if ui.checkbox(&mut preview, "preview").changed() {
+        if world.preview.is_none() {
+            world.preview = BrushPreview::new(renderer)
+                .inspect_err(|err| eprintln!("failed to create the brush preview: {err}"))
+                .ok();
+        }
+        if let Some(world_preview) = &mut world.preview {
+            world_preview.visible = preview;
+        }
+    }
+
+    if let Some(brush) = &mut world.brush {
+        egui::ComboBox::from_label("shape")
+            .selected_text(brush.shape.name())
+            .show_ui(ui, |ui| {
+                for shape in BrushShape::ALL {
+                    ui.selectable_value(&mut brush.shape, shape, shape.name());
+                }
+            });
+        ui.add(egui::Slider::new(&mut brush.radius, 0.0..=0.5).text("radius"));
+        ui.add(egui::Slider::new(&mut brush.strength, 0.0..=1.0).text("strength"));
+        if brush.shape != BrushShape::Smooth {
+            ui.add(egui::Slider::new(&mut brush.color, 0..=255).text("color, 0 erases"));
+        }
+        ui.add(egui::Slider::new(&mut brush.layer, 1..=10).text("layer"));
+    }
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        let undo = format!("undo ({})", world.edits.undo_len());
+        if ui
+            .add_enabled(world.edits.undo_len() > 0, egui::Button::new(undo))
+            .clicked()
+        {
+            world.undo_edit();
+        }
+        let redo = format!("redo ({})", world.edits.redo_len());
+        if ui
+            .add_enabled(world.edits.redo_len() > 0, egui::Button::new(redo))
+            .clicked()
+        {
+            world.redo_edit();
+        }
+    });
+}
+
+fn time(ui: &mut egui::Ui, world: &mut World) {
+    ui.label(format!(
+        "{:.2} s, step {}",
+        world.time(),
+        ms(Duration::from_secs_f32(world.delta()))
+    ));
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut world.paused, "paused");
+        if ui
+            .add_enabled(world.paused, egui::Button::new("step"))
+            .clicked()
+        {
+            world.step_once();
+        }
+    });
+    ui.add(egui::Slider::new(&mut world.time_scale, 0.0..=4.0).text("time scale"));
+}
+
+fn transform(ui: &mut egui::Ui, transform: &mut Transform) {
+    egui::Grid::new("transform").show(ui, |ui| {
+        ui.label("translation");
+        for axis in transform.translation.as_mut() {
+            ui.add(egui::DragValue::new(axis).speed(0.1));
+        }
+        ui.end_row();
+
+        let (y, x, z) = transform.rotation.to_euler(EulerRot::YXZ);
+        let mut euler = [x.to_degrees(), y.to_degrees(), z.to_degrees()];
+
+        ui.label("rotation");
+        let mut changed = false;
+        for angle in &mut euler {
+            changed |= ui
+                .add(egui::DragValue::new(angle).speed(1.0).suffix("°"))
+                .changed();
+        }
+        if changed {
+            let [x, y, z] = euler.map(f32::to_radians);
+            transform.rotation = Quat::from_euler(EulerRot::YXZ, y, x, z);
+        }
+        ui.end_row();
+
+        ui.label("scale");
+        for axis in transform.scale.as_mut() {
+            ui.add(egui::DragValue::new(axis).speed(0.01));
+        }
+        ui.end_row();
+    });
+}
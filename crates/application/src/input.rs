@@ -0,0 +1,49 @@
+// the state of the mouse, updated from the window events every frame so tasks can read it
+
+use math::Vec2;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Input {
+    /// in pixels from the top left corner of the rendered frame,
+    /// which isn't the framebuffer with an internal resolution, see ``RenderHandler::window_to_render``
+    pub cursor: Vec2,
+    /// the size of the rendered frame in pixels
+    pub viewport: Vec2,
+    /// left, right and middle
+    pub mouse_buttons: [bool; 3],
+}
+
+impl Input {
+    #[must_use]
+    pub fn left_pressed(&self) -> bool {
+        self.mouse_buttons[0]
+    }
+
+    /// apply a window event, the cursor is scaled from window coordinates to framebuffer pixels
+    pub fn on_event(&mut self, window: &glfw::Window, event: &glfw::WindowEvent) {
+        match *event {
+            glfw::WindowEvent::CursorPos(x, y) => {
+                let (width, height) = window.get_size();
+                let (fb_width, fb_height) = window.get_framebuffer_size();
+                let scale = Vec2::new(
+                    fb_width as f32 / width.max(1) as f32,
+                    fb_height as f32 / height.max(1) as f32,
+                );
+                self.cursor = Vec2::new(x as f32, y as f32) * scale;
+            }
+            glfw::WindowEvent::MouseButton(button, action, _) => {
+                let index = match button {
+                    glfw::MouseButton::Button1 => 0,
+                    glfw::MouseButton::Button2 => 1,
+                    glfw::MouseButton::Button3 => 2,
+                    _ => return,
+                };
+                self.mouse_buttons[index] = action != glfw::Action::Release;
+            }
+            glfw::WindowEvent::FramebufferSize(width, height) => {
+                self.viewport = Vec2::new(width as f32, height as f32);
+            }
+            _ => {}
+        }
+    }
+}
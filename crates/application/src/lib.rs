@@ -1,40 +1,174 @@
 #![feature(debug_closure_helpers)]
-#![feature(box_as_ptr)]
 #![allow(clippy::cast_possible_truncation)]
 
-use ash::prelude::VkResult;
+use std::{
+    cell::Cell,
+    io::Cursor,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+    time::{Duration, Instant},
+};
+
+use ash::{prelude::VkResult, vk};
+pub use profiling::CpuTimings;
+use profiling::Profiler;
 use rendering::handler::RenderHandler;
-use window::AppWindow;
-use world::World;
+use replay::{FramePlayback, FrameRecorder, Recording};
+pub use settings::{BackgroundPolicy, Settings, SettingsError};
+use window::{AppWindow, DisplayMode};
+use world::{rng::Rng, World};
 
-mod window;
+pub mod assets;
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "egui")]
+pub mod debug_ui;
+pub mod input;
+#[cfg(feature = "net")]
+pub mod net;
+mod profiling;
+pub mod replay;
+#[cfg(feature = "lua")]
+pub mod scripting;
+pub mod settings;
+#[cfg(feature = "egui")]
+pub mod ui;
+pub mod visibility;
+pub mod window;
 pub mod world;
 
+/// how often the world is updated per second with ``BackgroundPolicy::PauseRendering`` if there is no ``fps_cap``
+const BACKGROUND_UPDATES: u32 = 30;
+/// the longest ``BackgroundPolicy::PauseAll`` sleeps before checking if the window should close, in seconds
+const BACKGROUND_WAIT: f64 = 0.5;
+
+/// set by the panic hook when a thread panicked while ``run`` was running on another one
+static THREAD_PANICKED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// the thread is inside ``run``, its panics are caught there
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+}
+
 type TaskFn = dyn Fn(&mut World);
+#[cfg(feature = "egui")]
+type UiTaskFn = dyn Fn(&egui::Context, &mut World);
 
 pub struct Application {
     pub tasks: Vec<Box<TaskFn>>,
     pub world: World,
     pub renderer: RenderHandler,
+    /// the settings the application was created with
+    /// changing ``fps_cap``, ``background`` and ``reuse_scene`` takes effect right away, the rest is only read in ``Application::new``
+    pub settings: Settings,
+    /// captures the next frame with RenderDoc when pressed, needs the ``renderdoc`` feature
+    pub capture_key: Option<glfw::Key>,
+    /// pauses and resumes the tasks and the time of the world, see ``set_paused``
+    pub pause_key: Option<glfw::Key>,
+    /// runs a single frame while paused, see ``step_once``
+    pub step_key: Option<glfw::Key>,
+    #[cfg(feature = "egui")]
+    pub ui: ui::UiLayer,
+    #[cfg(feature = "egui")]
+    pub ui_tasks: Vec<Box<UiTaskFn>>,
+    #[cfg(feature = "egui")]
+    pub debug_ui: debug_ui::DebugUi,
+    /// opens and closes the debug ui
+    #[cfg(feature = "egui")]
+    pub debug_ui_key: Option<glfw::Key>,
+    profiler: Profiler,
+    /// the window has the keyboard focus, see ``Settings::background``
+    focused: bool,
+    /// the window is minimized
+    iconified: bool,
+    /// collects the frames while ``Settings::record`` is set, they are saved when ``run`` returns
+    pub recorder: Option<FrameRecorder>,
+    /// replaces the input, the camera and the voxel writes with the recorded ones
+    /// the window is closed once every frame was played
+    pub playback: Option<FramePlayback>,
     /// window should be dropped last as it invalidates the surface and so the swapchain
     pub window: AppWindow,
 }
 
+/// the fields are dropped in order after the gpu is idle, also when a task panicked and the application unwinds,
+/// the world frees its buffers first, then the renderer its passes, the swapchain and the device and at last the window
+impl Drop for Application {
+    fn drop(&mut self) {
+        self.wait_for_gpu();
+    }
+}
+
 impl Application {
     /// # Errors
     /// if your gpu isn't supported by the renderer
     /// or something else causes vulkan to error (for example ``OutOfMemory``)
-    pub fn new() -> VkResult<Self> {
-        let window = AppWindow::new();
+    pub fn new(settings: Settings) -> VkResult<Self> {
+        let profiler = Profiler::init();
+        #[allow(unused_mut)]
+        let mut window = AppWindow::new(&settings);
+
+        let mut renderer = RenderHandler::with_config(
+            &window.window,
+            window.get_size(),
+            settings.renderer_config(),
+        )?;
+        let mut world = World::new(&mut renderer);
+        world.assets.root.clone_from(&settings.asset_root);
+        world.rng = Rng::new(settings.seed);
+        #[cfg(feature = "audio")]
+        {
+            world.audio = audio::Audio::new()
+                .inspect_err(|err| eprintln!("playing without sound: {err}"))
+                .ok();
+        }
+        load_oit_shader(&mut renderer)?;
+        load_ssao_shaders(&mut renderer)?;
+        load_taa_shader(&mut renderer)?;
+        load_outline_shader(&mut renderer)?;
+        load_gi_shader(&mut renderer)?;
+        load_volumetric_shader(&mut renderer)?;
+
+        let playback = settings.replay.as_ref().and_then(|path| {
+            Recording::load(path)
+                .map(FramePlayback::new)
+                .inspect_err(|err| eprintln!("can't replay {}: {err}", path.display()))
+                .ok()
+        });
+        let recorder = settings.record.is_some().then(FrameRecorder::new);
+
+        #[cfg(feature = "egui")]
+        let ui = ui::UiLayer::new(&mut window.window);
+        #[cfg(feature = "egui")]
+        ui::UiLayer::set_shaders(&mut renderer)?;
 
-        let mut renderer = RenderHandler::new(&window.window, window.get_size())?;
-        let world = World::new(&mut renderer);
+        let focused = window.window.is_focused();
+        let iconified = window.window.is_iconified();
 
         Ok(Self {
             window,
             renderer,
             world,
+            settings,
             tasks: vec![],
+            capture_key: Some(glfw::Key::F12),
+            pause_key: Some(glfw::Key::Pause),
+            step_key: Some(glfw::Key::F10),
+            #[cfg(feature = "egui")]
+            ui,
+            #[cfg(feature = "egui")]
+            ui_tasks: vec![],
+            #[cfg(feature = "egui")]
+            debug_ui: debug_ui::DebugUi::default(),
+            #[cfg(feature = "egui")]
+            debug_ui_key: Some(glfw::Key::F3),
+            focused,
+            iconified,
+            profiler,
+            recorder,
+            playback,
         })
     }
 
@@ -46,43 +180,553 @@ impl Application {
         self
     }
 
+    /// build the ui every frame, the tasks run in the order they were added
+    #[cfg(feature = "egui")]
+    pub fn add_ui_task<F>(&mut self, task: F) -> &mut Self
+    where
+        F: Fn(&egui::Context, &mut World) + 'static,
+    {
+        self.ui_tasks.push(Box::new(task));
+        self
+    }
+
+    /// switch between windowed and fullscreen, see ``AppWindow::set_display_mode``
+    /// the renderer is resized once the window got its new size
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.window.set_display_mode(mode);
+
+        // the window might be on a monitor with a different scale now
+        #[cfg(feature = "egui")]
+        {
+            let [x, y] = self.window.content_scale();
+            self.ui.on_event(
+                &mut self.window.window,
+                &glfw::WindowEvent::ContentScale(x, y),
+            );
+        }
+    }
+
+    /// stop running the tasks and advancing the animations, to look at a frame of the simulation
+    /// rendering, the input and the ui keep going, so the camera can still be moved from the debug ui
+    pub fn set_paused(&mut self, paused: bool) {
+        self.world.paused = paused;
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.world.paused
+    }
+
+    /// run the tasks and advance the world for the next frame only, while paused
+    pub fn step_once(&mut self) {
+        self.world.step_once();
+    }
+
+    /// how fast the world advances, 0.5 is half the speed, negative scales are clamped to 0
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.world.time_scale = scale.max(0.0);
+    }
+
+    /// how long the parts of the last frame took on the cpu
+    #[must_use]
+    pub fn cpu_timings(&self) -> CpuTimings {
+        self.profiler.cpu
+    }
+
+    /// run frames until the window is closed
+    /// if a frame panics, the gpu is waited for before the panic goes on, so nothing it still uses is dropped,
+    /// a panic on another thread, like a job worker, stops the frames as if the window was closed
     pub fn run(&mut self) {
-        let mut dt = std::time::Instant::now();
+        install_panic_hook();
+        THREAD_PANICKED.store(false, Ordering::Relaxed);
+        RUNNING.set(true);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            while !self.window.window.should_close() && !THREAD_PANICKED.load(Ordering::Relaxed) {
+                self.frame();
+            }
+        }));
+        RUNNING.set(false);
+
+        if let Err(payload) = result {
+            self.wait_for_gpu();
+            panic::resume_unwind(payload);
+        }
+        if THREAD_PANICKED.load(Ordering::Relaxed) {
+            eprintln!("stopping, another thread panicked");
+        }
+
+        self.save_recording();
+    }
 
-        while !self.window.window.should_close() {
-            // println!("fps: {}", 1.0 / dt.elapsed().as_secs_f64());
-            dt = std::time::Instant::now();
+    fn wait_for_gpu(&mut self) {
+        if let Err(err) = self.renderer.wait_idle() {
+            eprintln!("failed to wait for the gpu before shutting down: {err}");
+        }
+    }
+
+    /// run the tasks, render and handle the window events once
+    /// for tools that drive the frames themselves, like the benchmarks, ``run`` calls it until the window is closed
+    /// while the window is in the background, ``Settings::background`` decides what still happens
+    pub fn frame(&mut self) {
+        let frame_start = Instant::now();
+        let background = self.background_policy();
+        if background == Some(BackgroundPolicy::PauseAll) {
+            self.wait_in_background();
+            return;
+        }
 
-            for task in &self.tasks {
+        let frame_span = tracing::info_span!("frame").entered();
+
+        if let Some(frame) = self.playback.as_ref().and_then(FramePlayback::current) {
+            frame.apply_input(&mut self.world);
+        }
+
+        let start = Instant::now();
+        if self.world.is_advancing() {
+            let _span = tracing::info_span!("tasks").entered();
+            for (i, task) in self.tasks.iter().enumerate() {
+                let _span = tracing::info_span!("task", index = i).entered();
                 (task)(&mut self.world);
             }
+        }
+        self.profiler.cpu.tasks = start.elapsed();
 
+        self.replay_frame();
+
+        let start = Instant::now();
+        {
+            let _span = tracing::info_span!("update world").entered();
+            self.world.assets.poll_changes();
+            self.world.camera.jitter = self.renderer.taa_jitter().into();
             self.world.update();
+            self.world.sync_renderer(&mut self.renderer);
+            self.world.assets.finish_loads(&mut self.renderer);
 
-            let _ = self
-                .renderer
-                .on_render()
-                .inspect_err(|v| eprintln!("{v:?}"));
+            // the changes are taken even if the scene isn't reused, so turning it on doesn't start from old ones
+            if !self.world.take_scene_changed() && self.settings.reuse_scene {
+                self.renderer.reuse_scene();
+            }
+        }
+        self.profiler.cpu.update = start.elapsed();
+
+        if background != Some(BackgroundPolicy::PauseRendering) {
+            self.render();
+        }
 
+        let start = Instant::now();
+        {
+            let _span = tracing::info_span!("window events").entered();
             self.window.glfw_ctx.poll_events();
+            self.handle_window_events();
+        }
+        self.profiler.cpu.events = start.elapsed();
+
+        drop(frame_span);
+        self.profiler.frame_mark();
+
+        self.limit_fps(frame_start, background);
+    }
 
-            for (_, event) in glfw::flush_messages(&self.window.glfw_events) {
-                match event {
-                    glfw::WindowEvent::Size(x, y) => {
-                        let _ = self.renderer.on_window_resize([x as u32, y as u32]);
-                        self.world.camera.aspect = x as f32 / y as f32;
-                    }
-                    glfw::WindowEvent::Close => {
-                        self.window.window.set_should_close(true);
-                    }
-
-                    _ => {}
+    /// paint the ui and render the frame, the renderer is recreated if it got lost
+    fn render(&mut self) {
+        #[cfg(feature = "egui")]
+        {
+            let start = Instant::now();
+            self.paint_ui();
+            self.profiler.cpu.ui = start.elapsed();
+        }
+
+        let start = Instant::now();
+        let _ = self
+            .renderer
+            .on_render()
+            .inspect_err(|v| eprintln!("{v:?}"));
+        self.profiler.cpu.render = start.elapsed();
+
+        for event in self.renderer.poll_events() {
+            eprintln!("renderer event: {event:?}");
+        }
+
+        if self.renderer.lost().is_some() {
+            self.recover();
+        }
+    }
+
+    /// the policy that applies in this frame, None while the window is in front
+    fn background_policy(&self) -> Option<BackgroundPolicy> {
+        (!self.focused || self.iconified).then_some(self.settings.background)
+    }
+
+    /// sleep until a window event arrives instead of running a frame, for ``BackgroundPolicy::PauseAll``
+    fn wait_in_background(&mut self) {
+        let _span = tracing::info_span!("background").entered();
+        self.window.glfw_ctx.wait_events_timeout(BACKGROUND_WAIT);
+        self.handle_window_events();
+        // the world continues where it stopped instead of jumping over the time in the background
+        self.world.reset_clock();
+    }
+
+    /// the events since the last ``poll_events`` or ``wait_events_timeout``
+    fn handle_window_events(&mut self) {
+        for (_, event) in glfw::flush_messages(&self.window.glfw_events) {
+            #[cfg(feature = "egui")]
+            self.ui.on_event(&mut self.window.window, &event);
+            self.world.input.on_event(&self.window.window, &event);
+
+            match event {
+                glfw::WindowEvent::FramebufferSize(x, y) => {
+                    let _ = self.renderer.on_window_resize([x as u32, y as u32]);
+                    // with an internal resolution the frame keeps its size and aspect ratio
+                    let extent = self.renderer.get_swapchain_resolution();
+                    self.world.input.viewport =
+                        math::Vec2::new(extent.width as f32, extent.height as f32);
+                    self.world.camera.aspect = extent.width as f32 / extent.height as f32;
+                }
+                // the tasks get the cursor in the pixels of the rendered frame
+                glfw::WindowEvent::CursorPos(..) => {
+                    let cursor = self.world.input.cursor.to_array();
+                    self.world.input.cursor = self.renderer.window_to_render(cursor).into();
+                }
+                #[cfg(feature = "egui")]
+                glfw::WindowEvent::Key(key, _, glfw::Action::Press, _)
+                    if Some(key) == self.debug_ui_key =>
+                {
+                    self.debug_ui.open = !self.debug_ui.open;
+                }
+                glfw::WindowEvent::Key(key, _, glfw::Action::Press, _)
+                    if Some(key) == self.pause_key =>
+                {
+                    self.world.paused = !self.world.paused;
+                }
+                glfw::WindowEvent::Key(key, _, glfw::Action::Press | glfw::Action::Repeat, _)
+                    if Some(key) == self.step_key =>
+                {
+                    self.world.step_once();
+                }
+                glfw::WindowEvent::Key(key, _, glfw::Action::Press, _)
+                    if Some(key) == self.capture_key && !self.renderer.trigger_capture() =>
+                {
+                    eprintln!("can't capture the frame, RenderDoc isn't loaded");
                 }
+                glfw::WindowEvent::Focus(focused) => self.focused = focused,
+                glfw::WindowEvent::Iconify(iconified) => self.iconified = iconified,
+                glfw::WindowEvent::Close => {
+                    self.window.window.set_should_close(true);
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    /// record the frame or replace it with the recorded one, after the tasks ran
+    fn replay_frame(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&mut self.world);
+        }
+
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        match playback.current() {
+            Some(frame) => {
+                frame.apply_world(&mut self.world);
+                playback.advance();
+            }
+            None => {
+                let (frames, _) = playback.progress();
+                eprintln!("replay finished after {frames} frames");
+                self.playback = None;
+                self.window.window.set_should_close(true);
+            }
+        }
+    }
+
+    fn save_recording(&mut self) {
+        let (Some(recorder), Some(path)) = (self.recorder.take(), &self.settings.record) else {
+            return;
+        };
+
+        let frames = recorder.frame_count();
+        match recorder.finish().save(path) {
+            Ok(()) => eprintln!("recorded {frames} frames to {}", path.display()),
+            Err(err) => eprintln!("failed to save the recording to {}: {err}", path.display()),
+        }
+    }
+
+    /// sleep for the rest of the frame if it was faster than ``Settings::fps_cap`` or the background policy allows
+    fn limit_fps(&self, frame_start: Instant, background: Option<BackgroundPolicy>) {
+        let background_cap = match background {
+            Some(BackgroundPolicy::Throttle(fps)) => Some(fps),
+            // nothing waits for the swapchain, the tasks would run as often as they can
+            Some(BackgroundPolicy::PauseRendering) => Some(BACKGROUND_UPDATES),
+            _ => None,
+        };
+        let Some(fps_cap) = [self.settings.fps_cap, background_cap]
+            .into_iter()
+            .flatten()
+            .filter(|&cap| cap > 0)
+            .min()
+        else {
+            return;
+        };
+
+        let frame_time = Duration::from_secs_f64(1.0 / f64::from(fps_cap));
+        if let Some(left) = frame_time.checked_sub(frame_start.elapsed()) {
+            let _span = tracing::info_span!("fps cap").entered();
+            std::thread::sleep(left);
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    fn paint_ui(&mut self) {
+        let cpu = self.profiler.cpu;
+        let output = self.ui.run(&mut self.window.window, |ctx| {
+            for task in &self.ui_tasks {
+                (task)(ctx, &mut self.world);
             }
+
+            self.debug_ui
+                .show(ctx, &mut self.world, &mut self.renderer, &cpu);
+        });
+
+        let _ = self
+            .renderer
+            .paint_ui(
+                &output.textures_delta,
+                &output.primitives,
+                output.pixels_per_point,
+            )
+            .inspect_err(|v| eprintln!("{v:?}"));
+    }
+
+    /// recreate the renderer and the gpu resources of the world after the device or surface was lost
+    /// if the renderer can't be reinitialized, it's tried again next frame
+    fn recover(&mut self) {
+        let result = self
+            .renderer
+            .reinitialize(&self.window.window, self.window.get_size())
+            .and_then(|()| self.world.recreate_gpu_resources(&mut self.renderer))
+            .and_then(|()| load_oit_shader(&mut self.renderer))
+            .and_then(|()| load_ssao_shaders(&mut self.renderer))
+            .and_then(|()| load_taa_shader(&mut self.renderer))
+            .and_then(|()| load_outline_shader(&mut self.renderer))
+            .and_then(|()| load_gi_shader(&mut self.renderer))
+            .and_then(|()| load_volumetric_shader(&mut self.renderer));
+
+        // egui only sends its textures once, a new context sends them again
+        #[cfg(feature = "egui")]
+        let result = result.and_then(|()| {
+            self.ui.ctx = egui::Context::default();
+            ui::UiLayer::set_shaders(&mut self.renderer)
+        });
+
+        if let Err(err) = result {
+            eprintln!("failed to reinitialize the renderer: {err:?}");
         }
     }
 }
 
-impl Drop for AppWindow {
-    fn drop(&mut self) {}
+/// flag panics of other threads for ``run``, the panic is still reported by the hook that was set before
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !RUNNING.get() {
+                THREAD_PANICKED.store(true, Ordering::Relaxed);
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// load ``shaders/oit.spv`` and set it as the shader that composites weighted blended transparency
+/// the shader is read at runtime, as it has to be compiled with ``build.sh`` first
+fn load_oit_shader(renderer: &mut RenderHandler) -> VkResult<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/oit.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!("{path} is missing, weighted blended transparency is invisible until the shaders are built with build.sh");
+        return Ok(());
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stage = vk::PipelineShaderStageCreateInfo::default()
+        .name(c"cs_resolve")
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module);
+    let result = renderer.set_oit_shader(stage);
+
+    // the pipeline doesn't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+    result
+}
+
+/// load ``shaders/ssao.spv`` and set the ambient occlusion shaders of the renderer
+/// the shaders are read at runtime, as they have to be compiled with ``build.sh`` first
+fn load_ssao_shaders(renderer: &mut RenderHandler) -> VkResult<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/ssao.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!("{path} is missing, there is no ambient occlusion until the shaders are built with build.sh");
+        return Ok(());
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stage = |name| {
+        vk::PipelineShaderStageCreateInfo::default()
+            .name(name)
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(module)
+    };
+    let result = renderer.set_ssao_shaders(stage(c"cs_ssao"), stage(c"cs_blur"));
+
+    // the pipelines don't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+    result
+}
+
+/// load ``shaders/taa.spv`` and set it as the temporal anti aliasing shader of the renderer
+/// the shader is read at runtime, as it has to be compiled with ``build.sh`` first
+fn load_taa_shader(renderer: &mut RenderHandler) -> VkResult<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/taa.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!("{path} is missing, there is no anti aliasing until the shaders are built with build.sh");
+        return Ok(());
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stage = vk::PipelineShaderStageCreateInfo::default()
+        .name(c"cs_taa")
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module);
+    let result = renderer.set_taa_shader(stage);
+
+    // the pipeline doesn't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+    result
+}
+
+/// load ``shaders/outline.spv`` and set it as the outline shader of the renderer
+/// the shader is read at runtime, as it has to be compiled with ``build.sh`` first
+fn load_outline_shader(renderer: &mut RenderHandler) -> VkResult<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/outline.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!("{path} is missing, selected objects have no outline until the shaders are built with build.sh");
+        return Ok(());
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stages = [
+        vk::PipelineShaderStageCreateInfo::default()
+            .name(c"vs_outline")
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(module),
+        vk::PipelineShaderStageCreateInfo::default()
+            .name(c"fs_outline")
+            .stage(vk::ShaderStageFlags::FRAGMENT)
+            .module(module),
+    ];
+    let result = renderer.set_outline_shader(&stages);
+
+    // the pipeline doesn't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+    result
+}
+
+/// load ``shaders/gi.spv`` and set it as the global illumination shader of the renderer
+/// only if the renderer was created with ``Settings::gi``
+fn load_gi_shader(renderer: &mut RenderHandler) -> VkResult<()> {
+    if !renderer.gi_enabled() {
+        return Ok(());
+    }
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/gi.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!("{path} is missing, there is no global illumination until the shaders are built with build.sh");
+        return Ok(());
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stage = vk::PipelineShaderStageCreateInfo::default()
+        .name(c"cs_gi")
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module);
+    let result = renderer.set_gi_shader(stage);
+
+    // the pipeline doesn't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+    result
+}
+
+/// load ``shaders/volumetric.spv`` and set it as the shader that draws the fluids of ``World::set_fluids``
+/// the shader is read at runtime, as it has to be compiled with ``build.sh`` first
+fn load_volumetric_shader(renderer: &mut RenderHandler) -> VkResult<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/volumetric.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!(
+            "{path} is missing, fluids are invisible until the shaders are built with build.sh"
+        );
+        return Ok(());
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stage = vk::PipelineShaderStageCreateInfo::default()
+        .name(c"cs_volumetric")
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module);
+    let result = renderer.set_volumetric_shader(stage);
+
+    // the pipeline doesn't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use rendering::handler::RenderHandler;
+
+    /// the shaders import the slots from ``shaders/bindless_slots.slang``,
+    /// it's written again if the slots of the renderer changed, the shaders have to be built again after that
+    #[test]
+    fn bindless_slots_match_the_renderer() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/bindless_slots.slang");
+        let header = RenderHandler::bindless_shader_header();
+
+        if std::fs::read_to_string(path).ok().as_deref() != Some(header.as_str()) {
+            std::fs::write(path, header).unwrap();
+            panic!(
+                "{path} was outdated and has been written again, build the shaders with build.sh"
+            );
+        }
+    }
 }
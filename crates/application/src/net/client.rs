@@ -0,0 +1,346 @@
+// mirrors the world of a server
+// the patches of the octrees are written to the buffers of the local ``World::voxel_buffers`` with the same index,
+// the transforms of the entities of the server are interpolated between the two snapshots around
+// ``interpolation_delay`` in the past, so they move smoothly even though the snapshots arrive at a lower rate
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, ErrorKind},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use math::Transform;
+
+use super::{
+    protocol::{Edit, Message, Packet, Patch, MAX_PACKET},
+    reliable::ReliableChannel,
+    TIMEOUT,
+};
+use crate::world::{hierarchy::Entity, svo::FlatOctreeNode, World};
+
+/// how often ``Hello`` is sent until the server answers
+const HELLO_INTERVAL: Duration = Duration::from_millis(250);
+/// the snapshots that are kept per entity
+const KEPT_SNAPSHOTS: usize = 8;
+/// entities that weren't in a snapshot for this long are despawned
+const ENTITY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// the nodes of an octree of the server
+#[derive(Default)]
+struct ReplicatedOctree {
+    nodes: Vec<FlatOctreeNode>,
+    /// changed since the last time they were written to the buffer
+    dirty: Vec<Range<usize>>,
+}
+
+struct RemoteEntity {
+    entity: Entity,
+    /// the time on the server and the transform, oldest first
+    snapshots: VecDeque<(f64, Transform)>,
+    last_seen: Instant,
+}
+
+pub struct Client {
+    socket: UdpSocket,
+    channel: ReliableChannel,
+    /// None until the server answered
+    last_heard: Option<Instant>,
+    last_hello: Option<Instant>,
+    octrees: Vec<ReplicatedOctree>,
+    /// by the index of the entity on the server
+    entities: HashMap<u32, RemoteEntity>,
+    /// the newest time of the server and when it arrived
+    clock: Option<(f64, Instant)>,
+    /// how far in the past the entities are shown, should cover a few snapshots
+    pub interpolation_delay: Duration,
+}
+
+impl Client {
+    /// start connecting to the server, ``update`` has to be called to finish joining
+    /// ``update`` also joins again if the connection was lost
+    /// # Errors
+    /// if the address can't be resolved or no socket can be bound
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let server = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "no server address"))?;
+
+        let local: SocketAddr = if server.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            channel: ReliableChannel::default(),
+            last_heard: None,
+            last_hello: None,
+            octrees: vec![],
+            entities: HashMap::new(),
+            clock: None,
+            interpolation_delay: Duration::from_millis(100),
+        })
+    }
+
+    /// the server has answered and hasn't timed out since
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.last_heard
+            .is_some_and(|last_heard| last_heard.elapsed() < TIMEOUT)
+    }
+
+    /// ask the server to apply the edit, it comes back as a patch once the server applied it
+    pub fn edit(&mut self, edit: Edit) {
+        self.channel.push(Message::Edit(edit));
+    }
+
+    /// the edits the server hasn't received yet
+    #[must_use]
+    pub fn pending_edits(&self) -> usize {
+        self.channel.unacked()
+    }
+
+    /// the nodes of the octree that have arrived so far, in the layout of the server
+    #[must_use]
+    pub fn octree_nodes(&self, index: usize) -> Option<&[FlatOctreeNode]> {
+        self.octrees
+            .get(index)
+            .map(|octree| octree.nodes.as_slice())
+    }
+
+    /// the local entity that mirrors the entity of the server
+    #[must_use]
+    pub fn entity(&self, server_index: u32) -> Option<Entity> {
+        self.entities.get(&server_index).map(|remote| remote.entity)
+    }
+
+    /// receive what changed on the server and apply it to the world
+    /// patches of octrees without a big enough buffer in ``World::voxel_buffers`` are kept until there is one
+    pub fn update(&mut self, world: &mut World) {
+        let _span = tracing::info_span!("client update").entered();
+        let now = Instant::now();
+
+        let mut buffer = [0; MAX_PACKET];
+        loop {
+            let len = match self.socket.recv(&mut buffer) {
+                Ok(len) => len,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // the server isn't there (yet), linux reports it on the next receive
+                Err(err) if err.kind() == ErrorKind::ConnectionRefused => continue,
+                Err(err) => {
+                    tracing::error!("failed to receive a packet: {err}");
+                    break;
+                }
+            };
+            if let Some(packet) = Packet::decode(&buffer[..len]) {
+                self.receive(world, packet, now);
+            }
+        }
+
+        self.write_patches(world);
+        self.interpolate(world, now);
+
+        // join again after the server went away, it starts with a new channel
+        if !self.is_connected()
+            && self
+                .last_hello
+                .is_none_or(|last| now - last >= HELLO_INTERVAL)
+        {
+            if self.last_heard.take().is_some() {
+                self.channel = ReliableChannel::default();
+            }
+            self.last_hello = Some(now);
+            self.send(&Packet::Hello);
+        }
+
+        self.send(&Packet::Ack {
+            next: self.channel.next_expected(),
+        });
+        for (seq, message) in self.channel.outgoing(now) {
+            self.send(&Packet::Reliable { seq, message });
+        }
+    }
+
+    fn receive(&mut self, world: &mut World, packet: Packet, now: Instant) {
+        self.last_heard = Some(now);
+
+        match packet {
+            Packet::Hello => {}
+            Packet::Disconnect => {
+                tracing::info!("the server closed the connection");
+                self.last_heard = None;
+                self.channel = ReliableChannel::default();
+            }
+            Packet::Ack { next } => self.channel.ack(next),
+            Packet::Reliable { seq, message } => {
+                self.channel.receive(seq, message);
+                for message in self.channel.deliver() {
+                    match message {
+                        Message::Patch(patch) => self.apply_patch(patch),
+                        Message::Edit(_) => tracing::warn!("the server sent an edit"),
+                    }
+                }
+            }
+            Packet::Snapshot { time, transforms } => {
+                if self.clock.is_none_or(|(newest, _)| time > newest) {
+                    self.clock = Some((time, now));
+                }
+
+                for (index, transform) in transforms {
+                    let remote = self.entities.entry(index).or_insert_with(|| RemoteEntity {
+                        entity: world.entities.spawn(transform),
+                        snapshots: VecDeque::new(),
+                        last_seen: now,
+                    });
+                    remote.last_seen = now;
+
+                    // snapshots can arrive out of order
+                    let at = remote.snapshots.partition_point(|(t, _)| *t < time);
+                    if remote.snapshots.get(at).is_some_and(|(t, _)| *t == time) {
+                        continue;
+                    }
+                    remote.snapshots.insert(at, (time, transform));
+                    if remote.snapshots.len() > KEPT_SNAPSHOTS {
+                        remote.snapshots.pop_front();
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply_patch(&mut self, patch: Patch) {
+        let index = patch.octree as usize;
+        if self.octrees.len() <= index {
+            self.octrees.resize_with(index + 1, Default::default);
+        }
+        let octree = &mut self.octrees[index];
+
+        let start = patch.start as usize;
+        let end = start + patch.nodes.len();
+        if end > patch.len as usize {
+            tracing::warn!("the server sent a patch outside of octree {index}");
+            return;
+        }
+
+        octree
+            .nodes
+            .resize(patch.len as usize, FlatOctreeNode::default());
+        octree.nodes[start..end].copy_from_slice(&patch.nodes);
+        octree.dirty.push(start..end);
+    }
+
+    fn write_patches(&mut self, world: &World) {
+        for (index, octree) in self.octrees.iter_mut().enumerate() {
+            let Some(buffer) = world.voxel_buffers.get(index) else {
+                continue;
+            };
+            let size = octree.nodes.len() * size_of::<FlatOctreeNode>();
+            if octree.dirty.is_empty() || size as u64 > buffer.size() {
+                continue;
+            }
+
+            for range in octree.dirty.drain(..) {
+                // the layout might have shrunk since the patch arrived
+                let end = range.end.min(octree.nodes.len());
+                if range.start < end {
+                    buffer.write(range.start, &octree.nodes[range.start..end]);
+                }
+            }
+        }
+    }
+
+    fn interpolate(&mut self, world: &mut World, now: Instant) {
+        let Some((newest, received)) = self.clock else {
+            return;
+        };
+        let time = newest + (now - received).as_secs_f64() - self.interpolation_delay.as_secs_f64();
+
+        self.entities.retain(|_, remote| {
+            let alive = now - remote.last_seen < ENTITY_TIMEOUT;
+            if !alive {
+                world.entities.despawn(remote.entity);
+            }
+            alive
+        });
+
+        for remote in self.entities.values() {
+            if let Some(transform) = interpolate(&remote.snapshots, time) {
+                *world.entities.transform_mut(remote.entity) = transform;
+            }
+        }
+    }
+
+    fn send(&self, packet: &Packet) {
+        match self.socket.send(&packet.encode()) {
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => {}
+            Err(err) => tracing::error!("failed to send a packet: {err}"),
+            Ok(_) => {}
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if self.last_heard.is_some() {
+            self.send(&Packet::Disconnect);
+        }
+    }
+}
+
+/// the transform at ``time`` between the two snapshots around it
+/// before the first snapshot the first one is used, after the last one the last one
+fn interpolate(snapshots: &VecDeque<(f64, Transform)>, time: f64) -> Option<Transform> {
+    let after = snapshots.partition_point(|(t, _)| *t <= time);
+
+    let (a, b) = match (after.checked_sub(1), snapshots.get(after)) {
+        (Some(before), Some(after)) => (snapshots[before], *after),
+        (Some(before), None) => return Some(snapshots[before].1),
+        (None, Some(after)) => return Some(after.1),
+        (None, None) => return None,
+    };
+
+    let t = ((time - a.0) / (b.0 - a.0)) as f32;
+    Some(Transform {
+        translation: a.1.translation.lerp(b.1.translation, t),
+        rotation: a.1.rotation.slerp(b.1.rotation, t),
+        scale: a.1.scale.lerp(b.1.scale, t),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{Quat, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn transforms_are_interpolated_between_snapshots() {
+        let snapshots = VecDeque::from([
+            (1.0, Transform::from_xyz(0.0, 0.0, 0.0)),
+            (
+                2.0,
+                Transform::from_xyz(2.0, 0.0, 0.0).with_rotation(Quat::from_rotation_y(1.0)),
+            ),
+        ]);
+
+        assert_eq!(interpolate(&VecDeque::new(), 1.0), None);
+        assert_eq!(interpolate(&snapshots, 0.0), Some(snapshots[0].1));
+        assert_eq!(interpolate(&snapshots, 3.0), Some(snapshots[1].1));
+
+        let half_way = interpolate(&snapshots, 1.5).unwrap();
+        assert!(half_way
+            .translation
+            .abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-6));
+        assert!(half_way
+            .rotation
+            .abs_diff_eq(Quat::from_rotation_y(0.5), 1e-6));
+    }
+}
@@ -0,0 +1,43 @@
+// lets several clients explore the voxel world of one server over udp
+// the server owns the octrees and the entities, clients send their voxel edits to it and get back
+// the changed nodes of the octrees and snapshots of the entity transforms
+//
+// patches and edits are sent with ``reliable::ReliableChannel``, snapshots are sent without it,
+// a lost snapshot is replaced by the next one anyway
+
+use std::time::Duration;
+
+use crate::world::World;
+
+pub mod client;
+pub mod protocol;
+mod reliable;
+pub mod server;
+
+pub use client::Client;
+pub use protocol::Edit;
+pub use server::Server;
+
+/// the other side is dropped if nothing arrived for this long
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// the side of the connection this world is on, see ``World::net``
+pub enum Net {
+    Server(Server),
+    Client(Client),
+}
+
+/// a task that sends and receives the changes of ``World::net``
+/// a server uploads the octrees itself, so ``World::upload_octree`` doesn't need to be called for them
+pub fn net_task(world: &mut World) {
+    let Some(mut net) = world.net.take() else {
+        return;
+    };
+
+    match &mut net {
+        Net::Server(server) => server.update(world),
+        Net::Client(client) => client.update(world),
+    }
+
+    world.net = Some(net);
+}
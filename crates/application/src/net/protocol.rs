@@ -0,0 +1,287 @@
+// the packets sent between the server and the clients
+// every packet starts with ``MAGIC`` and a kind byte, the numbers are little endian
+// packets are kept under ``MAX_PACKET`` bytes so they aren't fragmented on the way
+
+use math::{DVec3, Quat, Transform, Vec3};
+
+use crate::world::svo::FlatOctreeNode;
+
+const MAGIC: [u8; 4] = *b"PDL1";
+
+/// the largest packet that is sent, stays below the usual mtu
+pub(super) const MAX_PACKET: usize = 1200;
+/// the nodes in one ``Patch``, the rest of the range is sent in more patches
+pub(super) const PATCH_NODES: usize = 64;
+/// the transforms in one ``Snapshot``, the rest of the entities are sent in more snapshots
+pub(super) const SNAPSHOT_TRANSFORMS: usize = 24;
+
+/// a voxel edit that a client asks the server to apply, see ``Octree::write``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edit {
+    /// the index in ``World::voxel_octrees``
+    pub octree: u32,
+    /// between -1 and 1
+    pub pos: DVec3,
+    pub color: u8,
+    pub layer: u8,
+}
+
+/// the nodes of an ``OctreeLayout`` that changed
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Patch {
+    pub octree: u32,
+    /// the amount of nodes in the whole layout
+    pub len: u32,
+    /// the index of the first node
+    pub start: u32,
+    pub nodes: Vec<FlatOctreeNode>,
+}
+
+/// a message that arrives exactly once and in order, see ``ReliableChannel``
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Message {
+    Edit(Edit),
+    Patch(Patch),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Packet {
+    /// a client wants to join, repeated until the server answers
+    Hello,
+    /// the sender is gone, it isn't repeated so the other side might still time out
+    Disconnect,
+    /// every reliable message before ``next`` has arrived, also keeps the connection alive
+    Ack {
+        next: u32,
+    },
+    Reliable {
+        seq: u32,
+        message: Message,
+    },
+    /// the transforms of some entities of the server
+    Snapshot {
+        /// in seconds since the server started
+        time: f64,
+        /// the index of the entity on the server and its global transform
+        transforms: Vec<(u32, Transform)>,
+    },
+}
+
+impl Packet {
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        match self {
+            Self::Hello => out.push(0),
+            Self::Disconnect => out.push(1),
+            Self::Ack { next } => {
+                out.push(2);
+                out.extend(next.to_le_bytes());
+            }
+            Self::Reliable { seq, message } => {
+                out.push(3);
+                out.extend(seq.to_le_bytes());
+                encode_message(&mut out, message);
+            }
+            Self::Snapshot { time, transforms } => {
+                out.push(4);
+                out.extend(time.to_le_bytes());
+                out.extend((transforms.len() as u16).to_le_bytes());
+                for (index, transform) in transforms {
+                    out.extend(index.to_le_bytes());
+                    let Transform {
+                        translation,
+                        rotation,
+                        scale,
+                    } = transform;
+                    for value in translation
+                        .to_array()
+                        .into_iter()
+                        .chain(rotation.to_array())
+                    {
+                        out.extend(value.to_le_bytes());
+                    }
+                    for value in scale.to_array() {
+                        out.extend(value.to_le_bytes());
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// None if it isn't a valid packet, for example one that wasn't sent by puddle
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes.strip_prefix(&MAGIC)?);
+
+        let packet = match reader.u8()? {
+            0 => Self::Hello,
+            1 => Self::Disconnect,
+            2 => Self::Ack {
+                next: reader.u32()?,
+            },
+            3 => Self::Reliable {
+                seq: reader.u32()?,
+                message: decode_message(&mut reader)?,
+            },
+            4 => {
+                let time = reader.f64()?;
+                let count = reader.u16()?;
+                let transforms = (0..count)
+                    .map(|_| {
+                        let index = reader.u32()?;
+                        let transform = Transform {
+                            translation: Vec3::from_array(reader.f32s()?),
+                            rotation: Quat::from_array(reader.f32s()?),
+                            scale: Vec3::from_array(reader.f32s()?),
+                        };
+                        Some((index, transform))
+                    })
+                    .collect::<Option<_>>()?;
+                Self::Snapshot { time, transforms }
+            }
+            _ => return None,
+        };
+
+        reader.0.is_empty().then_some(packet)
+    }
+}
+
+fn encode_message(out: &mut Vec<u8>, message: &Message) {
+    match message {
+        Message::Edit(edit) => {
+            out.push(0);
+            out.extend(edit.octree.to_le_bytes());
+            for value in edit.pos.to_array() {
+                out.extend(value.to_le_bytes());
+            }
+            out.extend([edit.color, edit.layer]);
+        }
+        Message::Patch(patch) => {
+            out.push(1);
+            out.extend(patch.octree.to_le_bytes());
+            out.extend(patch.len.to_le_bytes());
+            out.extend(patch.start.to_le_bytes());
+            out.extend((patch.nodes.len() as u16).to_le_bytes());
+            for node in &patch.nodes {
+                out.extend(node.to_bytes());
+            }
+        }
+    }
+}
+
+fn decode_message(reader: &mut Reader) -> Option<Message> {
+    Some(match reader.u8()? {
+        0 => Message::Edit(Edit {
+            octree: reader.u32()?,
+            pos: DVec3::new(reader.f64()?, reader.f64()?, reader.f64()?),
+            color: reader.u8()?,
+            layer: reader.u8()?,
+        }),
+        1 => {
+            let octree = reader.u32()?;
+            let len = reader.u32()?;
+            let start = reader.u32()?;
+            let count = reader.u16()?;
+            let nodes = (0..count)
+                .map(|_| reader.array().map(FlatOctreeNode::from_bytes))
+                .collect::<Option<_>>()?;
+            Message::Patch(Patch {
+                octree,
+                len,
+                start,
+                nodes,
+            })
+        }
+        _ => return None,
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (bytes, rest) = self.0.split_first_chunk::<N>()?;
+        self.0 = rest;
+        Some(*bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.array().map(u8::from_le_bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn f32s<const N: usize>(&mut self) -> Option<[f32; N]> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = self.array().map(f32::from_le_bytes)?;
+        }
+        Some(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_round_trip_and_fit() {
+        let mut node = FlatOctreeNode::default();
+        node.set_valid_mask(0b1010_0001);
+        node.set_child_ptr(42);
+
+        let transform = Transform::from_xyz(1.0, 2.0, 3.0).with_scale(Vec3::splat(2.0));
+
+        let packets = [
+            Packet::Hello,
+            Packet::Disconnect,
+            Packet::Ack { next: 7 },
+            Packet::Reliable {
+                seq: 3,
+                message: Message::Edit(Edit {
+                    octree: 1,
+                    pos: DVec3::new(0.5, -0.25, 0.0),
+                    color: 4,
+                    layer: 6,
+                }),
+            },
+            Packet::Reliable {
+                seq: u32::MAX,
+                message: Message::Patch(Patch {
+                    octree: 0,
+                    len: 100,
+                    start: 10,
+                    nodes: vec![node; PATCH_NODES],
+                }),
+            },
+            Packet::Snapshot {
+                time: 1.5,
+                transforms: vec![(9, transform); SNAPSHOT_TRANSFORMS],
+            },
+        ];
+
+        for packet in packets {
+            let bytes = packet.encode();
+            assert!(bytes.len() <= MAX_PACKET, "{packet:?} is too large");
+            assert_eq!(Packet::decode(&bytes), Some(packet));
+        }
+
+        assert_eq!(Packet::decode(b"PDL1"), None);
+        assert_eq!(Packet::decode(&[0; 16]), None);
+        let mut trailing = Packet::Hello.encode();
+        trailing.push(0);
+        assert_eq!(Packet::decode(&trailing), None);
+    }
+}
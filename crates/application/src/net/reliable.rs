@@ -0,0 +1,166 @@
+// makes messages arrive exactly once and in order on top of udp
+// every message gets a sequence number and is sent again until the other side acknowledges it,
+// the receiver holds back messages that arrived too early until the ones before them are there
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use super::protocol::Message;
+
+/// how long to wait for an ack before sending a message again
+const RESEND: Duration = Duration::from_millis(200);
+/// the most messages that are on the way at once, the rest waits until those are acked
+const WINDOW: usize = 256;
+
+struct Pending {
+    seq: u32,
+    message: Message,
+    /// None if it hasn't been sent yet
+    sent: Option<Instant>,
+}
+
+#[derive(Default)]
+pub(super) struct ReliableChannel {
+    next_seq: u32,
+    /// sent or waiting to be sent, oldest first
+    unacked: VecDeque<Pending>,
+    /// the next message that is delivered
+    next_expected: u32,
+    /// arrived before the messages in front of them
+    early: BTreeMap<u32, Message>,
+}
+
+impl ReliableChannel {
+    /// send the message with the next ``outgoing``
+    pub fn push(&mut self, message: Message) {
+        self.unacked.push_back(Pending {
+            seq: self.next_seq,
+            message,
+            sent: None,
+        });
+        self.next_seq += 1;
+    }
+
+    /// the other side got every message before ``next``
+    pub fn ack(&mut self, next: u32) {
+        while self
+            .unacked
+            .front()
+            .is_some_and(|pending| pending.seq < next)
+        {
+            self.unacked.pop_front();
+        }
+    }
+
+    /// the messages that have to be sent now, they count as sent afterwards
+    pub fn outgoing(&mut self, now: Instant) -> Vec<(u32, Message)> {
+        self.unacked
+            .iter_mut()
+            .take(WINDOW)
+            .filter(|pending| pending.sent.is_none_or(|sent| now - sent >= RESEND))
+            .map(|pending| {
+                pending.sent = Some(now);
+                (pending.seq, pending.message.clone())
+            })
+            .collect()
+    }
+
+    /// the amount of messages the other side hasn't acknowledged yet
+    #[must_use]
+    pub fn unacked(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// store a message that arrived, duplicates are ignored
+    pub fn receive(&mut self, seq: u32, message: Message) {
+        if seq >= self.next_expected {
+            self.early.entry(seq).or_insert(message);
+        }
+    }
+
+    /// the messages that can be handled now, in the order they were sent
+    pub fn deliver(&mut self) -> Vec<Message> {
+        let mut delivered = vec![];
+        while let Some(message) = self.early.remove(&self.next_expected) {
+            delivered.push(message);
+            self.next_expected += 1;
+        }
+        delivered
+    }
+
+    /// sent back in an ``Ack``
+    #[must_use]
+    pub fn next_expected(&self) -> u32 {
+        self.next_expected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::DVec3;
+
+    use super::*;
+    use crate::net::protocol::Edit;
+
+    fn edit(color: u8) -> Message {
+        Message::Edit(Edit {
+            octree: 0,
+            pos: DVec3::ZERO,
+            color,
+            layer: 1,
+        })
+    }
+
+    #[test]
+    fn lost_messages_are_resent_and_delivered_in_order() {
+        let mut sender = ReliableChannel::default();
+        let mut receiver = ReliableChannel::default();
+        let start = Instant::now();
+
+        for color in 0..3 {
+            sender.push(edit(color));
+        }
+
+        // the first message gets lost
+        let sent = sender.outgoing(start);
+        assert_eq!(sent.len(), 3);
+        for (seq, message) in sent.into_iter().skip(1) {
+            receiver.receive(seq, message);
+        }
+        assert!(receiver.deliver().is_empty());
+        sender.ack(receiver.next_expected());
+        assert_eq!(sender.unacked(), 3);
+
+        // nothing is sent again before the timeout
+        assert!(sender.outgoing(start + RESEND / 2).is_empty());
+
+        let resent = sender.outgoing(start + RESEND);
+        assert_eq!(resent.len(), 3);
+        for (seq, message) in resent {
+            receiver.receive(seq, message);
+        }
+        assert_eq!(receiver.deliver(), vec![edit(0), edit(1), edit(2)]);
+
+        sender.ack(receiver.next_expected());
+        assert_eq!(sender.unacked(), 0);
+
+        // duplicates that arrive late are dropped
+        receiver.receive(1, edit(1));
+        assert!(receiver.deliver().is_empty());
+    }
+
+    #[test]
+    fn only_a_window_of_messages_is_on_the_way() {
+        let mut sender = ReliableChannel::default();
+        for _ in 0..WINDOW + 10 {
+            sender.push(edit(0));
+        }
+
+        let now = Instant::now();
+        assert_eq!(sender.outgoing(now).len(), WINDOW);
+        sender.ack(10);
+        assert_eq!(sender.outgoing(now).len(), 10);
+    }
+}
@@ -0,0 +1,247 @@
+// owns the world that the clients see
+// edits of the clients are applied to the octrees of the server, the nodes that changed are uploaded
+// with ``World::upload_octree`` and the same ranges are sent to every client as patches
+// new clients get every node of every octree first, then the patches after that
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use math::DVec3;
+
+use super::{
+    protocol::{Edit, Message, Packet, Patch, MAX_PACKET, PATCH_NODES, SNAPSHOT_TRANSFORMS},
+    reliable::ReliableChannel,
+    TIMEOUT,
+};
+use crate::world::World;
+
+/// edits deeper than this are ignored, every layer adds a node to the octree
+pub const MAX_EDIT_LAYER: u8 = 16;
+
+struct Peer {
+    channel: ReliableChannel,
+    last_heard: Instant,
+}
+
+pub struct Server {
+    socket: UdpSocket,
+    clients: HashMap<SocketAddr, Peer>,
+    start: Instant,
+    /// how many snapshots of the entity transforms are sent per second
+    pub snapshot_rate: f32,
+    last_snapshot: Option<Instant>,
+}
+
+impl Server {
+    /// # Errors
+    /// if the socket can't be bound to the address
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            clients: HashMap::new(),
+            start: Instant::now(),
+            snapshot_rate: 20.0,
+            last_snapshot: None,
+        })
+    }
+
+    /// # Errors
+    /// see ``UdpSocket::local_addr``
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    #[must_use]
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// receive the edits of the clients and send them what changed
+    /// uploads the octrees, so ``World::upload_octree`` doesn't have to be called for them
+    pub fn update(&mut self, world: &mut World) {
+        let _span = tracing::info_span!("server update").entered();
+        let now = Instant::now();
+
+        let mut buffer = [0; MAX_PACKET];
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // a client that went away, windows reports it on the next receive
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+                Err(err) => {
+                    tracing::error!("failed to receive a packet: {err}");
+                    break;
+                }
+            };
+            let Some(packet) = Packet::decode(&buffer[..len]) else {
+                continue;
+            };
+            self.receive(world, addr, packet, now);
+        }
+
+        self.clients.retain(|addr, peer| {
+            let alive = now - peer.last_heard < TIMEOUT;
+            if !alive {
+                tracing::info!("client {addr} timed out");
+            }
+            alive
+        });
+
+        for index in 0..world.voxel_octrees.len() {
+            let ranges = world.upload_octree(index);
+            for range in ranges {
+                let patches = patches(world, index, range);
+                for peer in self.clients.values_mut() {
+                    for patch in &patches {
+                        peer.channel.push(Message::Patch(patch.clone()));
+                    }
+                }
+            }
+        }
+
+        let interval = Duration::from_secs_f32(1.0 / self.snapshot_rate.max(f32::EPSILON));
+        if self.last_snapshot.is_none_or(|last| now - last >= interval) {
+            self.last_snapshot = Some(now);
+            self.send_snapshot(world, now);
+        }
+
+        for (addr, peer) in &mut self.clients {
+            let ack = Packet::Ack {
+                next: peer.channel.next_expected(),
+            };
+            let reliable = peer
+                .channel
+                .outgoing(now)
+                .into_iter()
+                .map(|(seq, message)| Packet::Reliable { seq, message });
+
+            for packet in std::iter::once(ack).chain(reliable) {
+                send(&self.socket, &packet, *addr);
+            }
+        }
+    }
+
+    fn receive(&mut self, world: &mut World, addr: SocketAddr, packet: Packet, now: Instant) {
+        if packet == Packet::Hello && !self.clients.contains_key(&addr) {
+            tracing::info!("client {addr} joined");
+
+            // everything that has been uploaded so far, the next upload sends the rest
+            let mut channel = ReliableChannel::default();
+            for (index, layout) in world.voxel_layouts.iter().enumerate() {
+                for patch in patches(world, index, 0..layout.len()) {
+                    channel.push(Message::Patch(patch));
+                }
+            }
+            self.clients.insert(
+                addr,
+                Peer {
+                    channel,
+                    last_heard: now,
+                },
+            );
+        }
+
+        let Some(peer) = self.clients.get_mut(&addr) else {
+            return;
+        };
+        peer.last_heard = now;
+
+        match packet {
+            Packet::Hello | Packet::Snapshot { .. } => {}
+            Packet::Disconnect => {
+                tracing::info!("client {addr} left");
+                self.clients.remove(&addr);
+            }
+            Packet::Ack { next } => peer.channel.ack(next),
+            Packet::Reliable { seq, message } => {
+                peer.channel.receive(seq, message);
+                for message in peer.channel.deliver() {
+                    match message {
+                        Message::Edit(edit) => apply_edit(world, edit),
+                        Message::Patch(_) => tracing::warn!("client {addr} sent a patch"),
+                    }
+                }
+            }
+        }
+    }
+
+    fn send_snapshot(&self, world: &World, now: Instant) {
+        let time = (now - self.start).as_secs_f64();
+        let transforms: Vec<_> = world
+            .entities
+            .iter()
+            .map(|entity| {
+                let transform = world.entities.global_transform(entity).compute_transform();
+                (entity.index(), transform)
+            })
+            .collect();
+
+        for transforms in transforms.chunks(SNAPSHOT_TRANSFORMS) {
+            let packet = Packet::Snapshot {
+                time,
+                transforms: transforms.to_vec(),
+            };
+            for addr in self.clients.keys() {
+                send(&self.socket, &packet, *addr);
+            }
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        for addr in self.clients.keys() {
+            send(&self.socket, &Packet::Disconnect, *addr);
+        }
+    }
+}
+
+/// the nodes in ``range`` of the uploaded layout of the octree
+fn patches(world: &World, index: usize, range: std::ops::Range<usize>) -> Vec<Patch> {
+    let layout = &world.voxel_layouts[index];
+    let nodes = &layout.nodes()[range.clone()];
+
+    nodes
+        .chunks(PATCH_NODES)
+        .enumerate()
+        .map(|(i, nodes)| Patch {
+            octree: index as u32,
+            len: layout.len() as u32,
+            start: (range.start + i * PATCH_NODES) as u32,
+            nodes: nodes.to_vec(),
+        })
+        .collect()
+}
+
+/// the edits come from the network, so they are checked before they touch the octree
+fn apply_edit(world: &mut World, edit: Edit) {
+    let inside = edit.pos.is_finite() && edit.pos.abs().cmple(DVec3::ONE).all();
+    if !inside || edit.layer > MAX_EDIT_LAYER {
+        return;
+    }
+    if (edit.octree as usize) < world.voxel_octrees.len() {
+        world.write_voxel(
+            edit.octree as usize,
+            edit.pos,
+            edit.color,
+            usize::from(edit.layer),
+        );
+    }
+}
+
+fn send(socket: &UdpSocket, packet: &Packet, addr: SocketAddr) {
+    match socket.send_to(&packet.encode(), addr) {
+        // the socket buffer is full, reliable messages are sent again later
+        Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+        Err(err) => tracing::error!("failed to send a packet to {addr}: {err}"),
+        Ok(_) => {}
+    }
+}
@@ -0,0 +1,63 @@
+// sends the tracing spans of the frame to a profiler
+// ``profile-chrome`` writes a ``trace-<timestamp>.json`` that can be opened in chrome://tracing or perfetto,
+// ``profile-tracy`` streams the spans to a running Tracy
+
+use std::time::Duration;
+
+/// how long the parts of the last frame took on the cpu
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CpuTimings {
+    pub tasks: Duration,
+    pub update: Duration,
+    pub ui: Duration,
+    /// recording and submitting, including waiting for a free frame
+    pub render: Duration,
+    pub events: Duration,
+}
+
+/// keeps the profiler running, the chrome trace is written when this is dropped
+pub(crate) struct Profiler {
+    pub cpu: CpuTimings,
+    #[cfg(feature = "profile-chrome")]
+    _chrome: tracing_chrome::FlushGuard,
+}
+
+impl Profiler {
+    /// does nothing if no profiler feature is enabled
+    pub fn init() -> Self {
+        #[cfg(any(feature = "profile-chrome", feature = "profile-tracy"))]
+        use tracing_subscriber::layer::SubscriberExt;
+
+        #[cfg(feature = "profile-chrome")]
+        let (chrome, guard) = tracing_chrome::ChromeLayerBuilder::new()
+            .include_args(true)
+            .build();
+
+        #[cfg(any(feature = "profile-chrome", feature = "profile-tracy"))]
+        {
+            let registry = tracing_subscriber::registry();
+            #[cfg(feature = "profile-chrome")]
+            let registry = registry.with(chrome);
+            #[cfg(feature = "profile-tracy")]
+            let registry = registry.with(tracing_tracy::TracyLayer::default());
+
+            if tracing::subscriber::set_global_default(registry).is_err() {
+                eprintln!("a tracing subscriber is already set, the profiler isn't used");
+            }
+        }
+
+        Self {
+            cpu: CpuTimings::default(),
+            #[cfg(feature = "profile-chrome")]
+            _chrome: guard,
+        }
+    }
+
+    /// marks the end of a frame in the profiler
+    pub fn frame_mark(&self) {
+        #[cfg(feature = "profile-tracy")]
+        if let Some(client) = tracing_tracy::client::Client::running() {
+            client.frame_mark();
+        }
+    }
+}
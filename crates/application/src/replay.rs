@@ -0,0 +1,396 @@
+// records what changed in every frame to a file and plays it back
+// the same recording always produces the same frames, so two builds can be compared on the same flythrough
+// and a bug can be reproduced by sending the file
+//
+// a recording stores per frame the input, the camera and the voxel writes,
+// together with the time step the world was updated with, which is reused during playback
+
+use std::{io, path::Path, time::Instant};
+
+use math::{DVec3, Quat, Transform, Vec2, Vec3};
+
+use crate::{input::Input, world::World};
+
+const MAGIC: [u8; 4] = *b"PDLR";
+const VERSION: u8 = 1;
+
+/// a single voxel written with ``World::write_voxel``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelWrite {
+    pub octree: u32,
+    pub pos: DVec3,
+    pub color: u8,
+    pub layer: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameEvent {
+    Input(Input),
+    Camera(Transform),
+    Voxel(VoxelWrite),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordedFrame {
+    /// seconds since the recording started
+    pub time: f64,
+    /// the time step ``World::update`` used in this frame
+    pub delta: f32,
+    pub events: Vec<FrameEvent>,
+}
+
+impl RecordedFrame {
+    /// the input and the time step, applied before the tasks run so they see what was recorded
+    pub fn apply_input(&self, world: &mut World) {
+        world.fixed_delta = Some(self.delta);
+        for event in &self.events {
+            if let FrameEvent::Input(input) = event {
+                world.input = *input;
+            }
+        }
+    }
+
+    /// the camera and the voxel writes, applied after the tasks so they replace what the tasks did
+    /// writing the same voxel twice doesn't change it, so tasks that edit from the input are fine
+    pub fn apply_world(&self, world: &mut World) {
+        let mut touched = vec![];
+
+        for event in &self.events {
+            match *event {
+                FrameEvent::Camera(transform) => world.camera.transform = transform,
+                FrameEvent::Voxel(write) => {
+                    let octree = write.octree as usize;
+                    if octree >= world.voxel_octrees.len() {
+                        eprintln!("replay writes to octree {octree}, which doesn't exist");
+                        continue;
+                    }
+                    world.voxel_octrees[octree].write(write.pos, write.color, write.layer.into());
+                    if !touched.contains(&octree) {
+                        touched.push(octree);
+                    }
+                }
+                FrameEvent::Input(_) => {}
+            }
+        }
+
+        for octree in touched {
+            world.upload_octree(octree);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    /// # Errors
+    /// if the file can't be read or isn't a recording
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::decode(&bytes).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "not a valid frame recording")
+        })
+    }
+
+    /// # Errors
+    /// if the file can't be written
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.encode())
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend((self.frames.len() as u32).to_le_bytes());
+
+        for frame in &self.frames {
+            bytes.extend(frame.time.to_le_bytes());
+            bytes.extend(frame.delta.to_le_bytes());
+            bytes.extend((frame.events.len() as u32).to_le_bytes());
+            for event in &frame.events {
+                encode_event(&mut bytes, event);
+            }
+        }
+
+        bytes
+    }
+
+    /// None if the bytes aren't a recording of this version
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        if reader.array()? != MAGIC || reader.u8()? != VERSION {
+            return None;
+        }
+
+        let count = reader.u32()?;
+        let mut frames = Vec::with_capacity(count.min(1 << 16) as usize);
+        for _ in 0..count {
+            let time = reader.f64()?;
+            let [delta] = reader.f32s()?;
+            let events = (0..reader.u32()?)
+                .map(|_| decode_event(&mut reader))
+                .collect::<Option<_>>()?;
+            frames.push(RecordedFrame {
+                time,
+                delta,
+                events,
+            });
+        }
+
+        reader.0.is_empty().then_some(Self { frames })
+    }
+}
+
+/// collects the frames of a ``Recording`` while the application runs
+pub struct FrameRecorder {
+    start: Instant,
+    last_frame: Instant,
+    frames: Vec<RecordedFrame>,
+}
+
+impl Default for FrameRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameRecorder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            last_frame: Instant::now(),
+            frames: vec![],
+        }
+    }
+
+    /// record the state of the world after the tasks ran, the voxel writes since the last frame are taken from it
+    /// the time step is fixed for this update so it is exactly the one that is played back
+    pub fn record(&mut self, world: &mut World) {
+        let now = Instant::now();
+        let delta = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        world.fixed_delta = Some(delta);
+
+        let mut events = vec![
+            FrameEvent::Input(world.input),
+            FrameEvent::Camera(world.camera.transform),
+        ];
+        events.extend(world.take_voxel_writes().into_iter().map(FrameEvent::Voxel));
+
+        self.frames.push(RecordedFrame {
+            time: now.duration_since(self.start).as_secs_f64(),
+            delta,
+            events,
+        });
+    }
+
+    #[must_use]
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[must_use]
+    pub fn finish(self) -> Recording {
+        Recording {
+            frames: self.frames,
+        }
+    }
+}
+
+/// plays a ``Recording`` back, one recorded frame per rendered frame
+pub struct FramePlayback {
+    recording: Recording,
+    next: usize,
+}
+
+impl FramePlayback {
+    #[must_use]
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, next: 0 }
+    }
+
+    /// the frame that is played in this frame, None once every frame was played
+    #[must_use]
+    pub fn current(&self) -> Option<&RecordedFrame> {
+        self.recording.frames.get(self.next)
+    }
+
+    pub fn advance(&mut self) {
+        self.next += 1;
+    }
+
+    #[must_use]
+    pub fn finished(&self) -> bool {
+        self.next >= self.recording.frames.len()
+    }
+
+    /// how many frames were played and how many there are
+    #[must_use]
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            self.next.min(self.recording.frames.len()),
+            self.recording.frames.len(),
+        )
+    }
+}
+
+fn encode_event(bytes: &mut Vec<u8>, event: &FrameEvent) {
+    match event {
+        FrameEvent::Input(input) => {
+            bytes.push(0);
+            for value in [input.cursor, input.viewport] {
+                bytes.extend(value.x.to_le_bytes());
+                bytes.extend(value.y.to_le_bytes());
+            }
+            let buttons = input
+                .mouse_buttons
+                .iter()
+                .enumerate()
+                .fold(0u8, |mask, (i, &pressed)| mask | (u8::from(pressed) << i));
+            bytes.push(buttons);
+        }
+        FrameEvent::Camera(transform) => {
+            bytes.push(1);
+            let values = transform
+                .translation
+                .to_array()
+                .into_iter()
+                .chain(transform.rotation.to_array())
+                .chain(transform.scale.to_array());
+            for value in values {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        FrameEvent::Voxel(write) => {
+            bytes.push(2);
+            bytes.extend(write.octree.to_le_bytes());
+            for value in write.pos.to_array() {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.push(write.color);
+            bytes.push(write.layer);
+        }
+    }
+}
+
+fn decode_event(reader: &mut Reader) -> Option<FrameEvent> {
+    Some(match reader.u8()? {
+        0 => {
+            let [x, y, width, height] = reader.f32s()?;
+            let buttons = reader.u8()?;
+            FrameEvent::Input(Input {
+                cursor: Vec2::new(x, y),
+                viewport: Vec2::new(width, height),
+                mouse_buttons: std::array::from_fn(|i| buttons & (1 << i) != 0),
+            })
+        }
+        1 => {
+            let [tx, ty, tz, rx, ry, rz, rw, sx, sy, sz] = reader.f32s()?;
+            FrameEvent::Camera(Transform {
+                translation: Vec3::new(tx, ty, tz),
+                rotation: Quat::from_xyzw(rx, ry, rz, rw),
+                scale: Vec3::new(sx, sy, sz),
+            })
+        }
+        2 => FrameEvent::Voxel(VoxelWrite {
+            octree: reader.u32()?,
+            pos: DVec3::new(reader.f64()?, reader.f64()?, reader.f64()?),
+            color: reader.u8()?,
+            layer: reader.u8()?,
+        }),
+        _ => return None,
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (bytes, rest) = self.0.split_first_chunk::<N>()?;
+        self.0 = rest;
+        Some(*bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.array().map(u8::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn f32s<const N: usize>(&mut self) -> Option<[f32; N]> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = self.array().map(f32::from_le_bytes)?;
+        }
+        Some(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> Recording {
+        Recording {
+            frames: vec![
+                RecordedFrame {
+                    time: 0.0,
+                    delta: 1.0 / 60.0,
+                    events: vec![
+                        FrameEvent::Input(Input {
+                            cursor: Vec2::new(10.5, 20.0),
+                            viewport: Vec2::new(1280.0, 720.0),
+                            mouse_buttons: [true, false, true],
+                        }),
+                        FrameEvent::Camera(Transform::from_xyz(1.0, 2.0, 3.0)),
+                    ],
+                },
+                RecordedFrame {
+                    time: 0.016,
+                    delta: 0.016,
+                    events: vec![FrameEvent::Voxel(VoxelWrite {
+                        octree: 2,
+                        pos: DVec3::new(-0.5, 0.25, 0.125),
+                        color: 7,
+                        layer: 5,
+                    })],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn recordings_round_trip() {
+        let recording = recording();
+        assert_eq!(Recording::decode(&recording.encode()), Some(recording));
+    }
+
+    #[test]
+    fn truncated_recordings_are_rejected() {
+        let bytes = recording().encode();
+        assert_eq!(Recording::decode(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Recording::decode(b"PDLX"), None);
+    }
+
+    #[test]
+    fn playback_runs_out() {
+        let mut playback = FramePlayback::new(recording());
+        assert_eq!(playback.progress(), (0, 2));
+        playback.advance();
+        assert!(playback.current().is_some());
+        playback.advance();
+        assert!(playback.finished());
+        assert_eq!(playback.progress(), (2, 2));
+    }
+}
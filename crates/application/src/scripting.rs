@@ -0,0 +1,461 @@
+// gameplay logic in lua scripts, so it can be changed without compiling again
+// a script registers tasks that run every frame and handlers for named events:
+//
+//     register_task(function(world) world.set_translation(0, world.time, 0, 0) end)
+//     on("jump", function(world, height) ... end)
+//
+// every script runs in its own environment, the standard library is limited to math, string and table,
+// and the world is only reachable through the ``world`` table passed to the callbacks
+// the files are loaded through the ``AssetRegistry``, so a changed file is run again by ``poll_changes``
+
+use std::{
+    cell::RefCell,
+    fmt,
+    path::Path,
+    rc::{Rc, Weak},
+    time::Instant,
+};
+
+use math::{Quat, Transform, Vec3};
+use mlua::{Function, Lua, LuaOptions, MultiValue, RegistryKey, StdLib, Table};
+
+use crate::{
+    assets::{AssetHandle, AssetRegistry},
+    world::{
+        hierarchy::{Entities, Entity},
+        World,
+    },
+};
+
+/// turns ``register_task`` and ``on`` in to functions that fill the tables of the script
+const PRELUDE: &str = r"
+local tasks, handlers = ...
+return function(task) table.insert(tasks, task) end,
+    function(event, handler)
+        handlers[event] = handlers[event] or {}
+        table.insert(handlers[event], handler)
+    end
+";
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(std::io::Error),
+    Lua(mlua::Error),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read script: {err}"),
+            Self::Lua(err) => write!(f, "script error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<std::io::Error> for ScriptError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(err: mlua::Error) -> Self {
+        Self::Lua(err)
+    }
+}
+
+/// the code of a script file
+pub struct ScriptSource {
+    pub code: String,
+}
+
+impl ScriptSource {
+    /// # Errors
+    /// if the file can't be read or isn't utf-8
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            code: std::fs::read_to_string(path)?,
+        })
+    }
+}
+
+struct Script {
+    name: String,
+    /// None for scripts that weren't loaded from a file
+    source: Option<AssetHandle<ScriptSource>>,
+    /// the functions passed to ``register_task``
+    tasks: RegistryKey,
+    /// the functions passed to ``on``, by event name
+    handlers: RegistryKey,
+}
+
+pub struct Scripts {
+    lua: Lua,
+    scripts: Vec<Script>,
+    /// filled by the reload hook of the asset registry
+    reloaded: Rc<RefCell<Vec<AssetHandle<ScriptSource>>>>,
+    last_update: Instant,
+}
+
+impl Scripts {
+    /// # Errors
+    /// if the lua state can't be created
+    pub fn new(assets: &mut AssetRegistry) -> Result<Self, ScriptError> {
+        let lua = Lua::new_with(
+            StdLib::MATH | StdLib::STRING | StdLib::TABLE,
+            LuaOptions::default(),
+        )?;
+
+        // the base library is always loaded, but scripts shouldn't read files
+        for name in ["dofile", "loadfile", "load", "require"] {
+            lua.globals().set(name, mlua::Nil)?;
+        }
+
+        let reloaded = Rc::new(RefCell::new(vec![]));
+        let weak = Rc::downgrade(&reloaded);
+        assets.on_reload(move |handle, _: &ScriptSource| {
+            if let Some(reloaded) = Weak::upgrade(&weak) {
+                reloaded.borrow_mut().push(handle);
+            }
+        });
+
+        Ok(Self {
+            lua,
+            scripts: vec![],
+            reloaded,
+            last_update: Instant::now(),
+        })
+    }
+
+    /// load a script file and run it, it's run again when the file changes
+    /// returns the index of the script
+    /// # Errors
+    /// if the file can't be read or the script fails
+    pub fn load(
+        &mut self,
+        assets: &mut AssetRegistry,
+        path: impl AsRef<Path>,
+    ) -> Result<usize, ScriptError> {
+        let path = path.as_ref();
+        let handle = assets.load(path, ScriptSource::load)?;
+        let code = &assets.get(handle).expect("the script was just loaded").code;
+
+        let name = path.to_string_lossy().into_owned();
+        let (tasks, handlers) = self.run_script(&name, code)?;
+        self.scripts.push(Script {
+            name,
+            source: Some(handle),
+            tasks,
+            handlers,
+        });
+        Ok(self.scripts.len() - 1)
+    }
+
+    /// run a script that isn't in a file, returns the index of the script
+    /// # Errors
+    /// if the script fails
+    pub fn add(&mut self, name: &str, code: &str) -> Result<usize, ScriptError> {
+        let (tasks, handlers) = self.run_script(name, code)?;
+        self.scripts.push(Script {
+            name: name.into(),
+            source: None,
+            tasks,
+            handlers,
+        });
+        Ok(self.scripts.len() - 1)
+    }
+
+    /// run the scripts whose file changed again and then the tasks of every script
+    /// errors are logged with ``tracing``, a script that fails to reload keeps its old tasks
+    pub fn update(&mut self, world: &mut World) {
+        let _span = tracing::info_span!("scripts").entered();
+
+        let reloaded = std::mem::take(&mut *self.reloaded.borrow_mut());
+        for handle in reloaded {
+            let Some(code) = world.assets.get(handle).map(|source| &source.code) else {
+                continue;
+            };
+            for index in 0..self.scripts.len() {
+                if self.scripts[index].source != Some(handle) {
+                    continue;
+                }
+                let name = self.scripts[index].name.clone();
+                match self.run_script(&name, code) {
+                    Ok((tasks, handlers)) => {
+                        let script = &mut self.scripts[index];
+                        script.tasks = tasks;
+                        script.handlers = handlers;
+                    }
+                    Err(err) => tracing::error!("failed to reload script {name}: {err}"),
+                }
+            }
+        }
+
+        let dt = self.last_update.elapsed().as_secs_f64();
+        self.last_update = Instant::now();
+        let time = world.start_time.elapsed().as_secs_f64();
+        self.run_tasks(&mut world.entities, time, dt);
+    }
+
+    /// call the handlers the scripts registered for the event with ``on``
+    /// errors are logged with ``tracing``
+    pub fn emit(&mut self, world: &mut World, event: &str, args: &[f64]) {
+        let time = world.start_time.elapsed().as_secs_f64();
+        self.emit_with(&mut world.entities, time, event, args);
+    }
+
+    /// run the script in a new environment, returns its tasks and handlers
+    fn run_script(&self, name: &str, code: &str) -> mlua::Result<(RegistryKey, RegistryKey)> {
+        let lua = &self.lua;
+        let tasks = lua.create_table()?;
+        let handlers = lua.create_table()?;
+
+        let (register_task, on): (Function, Function) = lua
+            .load(PRELUDE)
+            .set_name("prelude")
+            .call((tasks.clone(), handlers.clone()))?;
+
+        // everything that isn't set by the script comes from the restricted globals
+        let env = lua.create_table()?;
+        let meta = lua.create_table()?;
+        meta.set("__index", lua.globals())?;
+        env.set_metatable(Some(meta));
+        env.set("register_task", register_task)?;
+        env.set("on", on)?;
+
+        lua.load(code).set_name(name).set_environment(env).exec()?;
+
+        Ok((
+            lua.create_registry_value(tasks)?,
+            lua.create_registry_value(handlers)?,
+        ))
+    }
+
+    fn run_tasks(&self, entities: &mut Entities, time: f64, dt: f64) {
+        let result = with_api(&self.lua, entities, time, dt, |api| {
+            for script in &self.scripts {
+                let tasks: Table = self.lua.registry_value(&script.tasks)?;
+                for task in tasks.sequence_values::<Function>() {
+                    if let Err(err) = task?.call::<_, ()>(api.clone()) {
+                        tracing::error!("task of script {} failed: {err}", script.name);
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            tracing::error!("failed to run the script tasks: {err}");
+        }
+    }
+
+    fn emit_with(&self, entities: &mut Entities, time: f64, event: &str, args: &[f64]) {
+        let result = with_api(&self.lua, entities, time, 0.0, |api| {
+            for script in &self.scripts {
+                let handlers: Table = self.lua.registry_value(&script.handlers)?;
+                let Some(handlers) = handlers.get::<_, Option<Table>>(event)? else {
+                    continue;
+                };
+
+                for handler in handlers.sequence_values::<Function>() {
+                    let args = std::iter::once(mlua::Value::Table(api.clone()))
+                        .chain(args.iter().map(|arg| mlua::Value::Number(*arg)));
+                    if let Err(err) = handler?.call::<_, ()>(MultiValue::from_iter(args)) {
+                        tracing::error!(
+                            "handler for {event} of script {} failed: {err}",
+                            script.name
+                        );
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            tracing::error!("failed to emit {event}: {err}");
+        }
+    }
+}
+
+/// build the ``world`` table the callbacks get, it's only valid during ``f``
+/// entities are passed as their index
+fn with_api<R>(
+    lua: &Lua,
+    entities: &mut Entities,
+    time: f64,
+    dt: f64,
+    f: impl FnOnce(&Table) -> mlua::Result<R>,
+) -> mlua::Result<R> {
+    let entities = RefCell::new(entities);
+
+    let get = |entities: &Entities, index: u32| {
+        let entity = Entity::from_index(index);
+        if entities.contains(entity) {
+            Ok(entity)
+        } else {
+            Err(mlua::Error::runtime(format!(
+                "entity {index} doesn't exist"
+            )))
+        }
+    };
+
+    lua.scope(|scope| {
+        let api = lua.create_table()?;
+        api.set("time", time)?;
+        api.set("dt", dt)?;
+
+        api.set(
+            "entities",
+            scope.create_function(|_, ()| {
+                Ok(entities
+                    .borrow()
+                    .iter()
+                    .map(Entity::index)
+                    .collect::<Vec<_>>())
+            })?,
+        )?;
+        api.set(
+            "spawn",
+            scope.create_function(|_, (x, y, z): (f32, f32, f32)| {
+                let mut entities = entities.borrow_mut();
+                Ok(entities.spawn(Transform::from_xyz(x, y, z)).index())
+            })?,
+        )?;
+        api.set(
+            "despawn",
+            scope.create_function(|_, index: u32| {
+                let mut entities = entities.borrow_mut();
+                let entity = get(&entities, index)?;
+                entities.despawn(entity);
+                Ok(())
+            })?,
+        )?;
+        api.set(
+            "parent",
+            scope.create_function(|_, index: u32| {
+                let entities = entities.borrow();
+                let entity = get(&entities, index)?;
+                Ok(entities.parent(entity).map(Entity::index))
+            })?,
+        )?;
+
+        api.set(
+            "translation",
+            scope.create_function(|_, index: u32| {
+                let entities = entities.borrow();
+                let t = entities.transform(get(&entities, index)?).translation;
+                Ok((t.x, t.y, t.z))
+            })?,
+        )?;
+        api.set(
+            "set_translation",
+            scope.create_function(|_, (index, x, y, z): (u32, f32, f32, f32)| {
+                let mut entities = entities.borrow_mut();
+                let entity = get(&entities, index)?;
+                entities.transform_mut(entity).translation = Vec3::new(x, y, z);
+                Ok(())
+            })?,
+        )?;
+        api.set(
+            "rotation",
+            scope.create_function(|_, index: u32| {
+                let entities = entities.borrow();
+                let r = entities.transform(get(&entities, index)?).rotation;
+                Ok((r.x, r.y, r.z, r.w))
+            })?,
+        )?;
+        api.set(
+            "set_rotation",
+            scope.create_function(|_, (index, x, y, z, w): (u32, f32, f32, f32, f32)| {
+                let mut entities = entities.borrow_mut();
+                let entity = get(&entities, index)?;
+                entities.transform_mut(entity).rotation = Quat::from_xyzw(x, y, z, w).normalize();
+                Ok(())
+            })?,
+        )?;
+        api.set(
+            "scale",
+            scope.create_function(|_, index: u32| {
+                let entities = entities.borrow();
+                let s = entities.transform(get(&entities, index)?).scale;
+                Ok((s.x, s.y, s.z))
+            })?,
+        )?;
+        api.set(
+            "set_scale",
+            scope.create_function(|_, (index, x, y, z): (u32, f32, f32, f32)| {
+                let mut entities = entities.borrow_mut();
+                let entity = get(&entities, index)?;
+                entities.transform_mut(entity).scale = Vec3::new(x, y, z);
+                Ok(())
+            })?,
+        )?;
+
+        f(&api)
+    })
+}
+
+/// a task that runs ``World::scripts``
+pub fn script_task(world: &mut World) {
+    let Some(mut scripts) = world.scripts.take() else {
+        return;
+    };
+    scripts.update(world);
+    world.scripts = Some(scripts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_and_handlers_move_entities() {
+        let mut assets = AssetRegistry::default();
+        let mut scripts = Scripts::new(&mut assets).unwrap();
+        let mut entities = Entities::new();
+        let entity = entities.spawn(Transform::IDENTITY);
+
+        scripts
+            .add(
+                "mover",
+                r#"
+                local speed = 2
+                register_task(function(world)
+                    for _, entity in ipairs(world.entities()) do
+                        local x, y, z = world.translation(entity)
+                        world.set_translation(entity, x + speed * world.dt, y, z)
+                    end
+                end)
+                on("jump", function(world, height)
+                    local x, y, z = world.translation(0)
+                    world.set_translation(0, x, y + height, z)
+                end)
+                "#,
+            )
+            .unwrap();
+
+        scripts.run_tasks(&mut entities, 0.0, 0.5);
+        scripts.emit_with(&mut entities, 0.0, "jump", &[3.0]);
+        scripts.emit_with(&mut entities, 0.0, "unknown", &[]);
+        assert_eq!(
+            entities.transform(entity).translation,
+            Vec3::new(1.0, 3.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn scripts_are_restricted() {
+        let mut assets = AssetRegistry::default();
+        let mut scripts = Scripts::new(&mut assets).unwrap();
+
+        assert!(scripts.add("io", "io.open('file')").is_err());
+        assert!(scripts.add("load", "dofile('file')").is_err());
+        assert!(scripts.add("syntax", "register_task(").is_err());
+
+        // the environment of one script doesn't leak in to another
+        scripts.add("a", "shared = 1").unwrap();
+        scripts.add("b", "assert(shared == nil)").unwrap();
+    }
+}
@@ -0,0 +1,530 @@
+// settings that can be changed without recompiling
+// they are read from a toml file first, then overridden by environment variables and command line arguments
+//
+// puddle.toml:
+//     resolution = [1280, 720]
+//     fullscreen = false
+//     present_mode = "mailbox"
+//     msaa = 4
+//     validation = "errors"
+//     fps_cap = 144
+//     asset_root = "assets"
+//     breadcrumbs = false
+//     workers = "auto"
+//     seed = 0
+//     gi = false
+//     gpu = "auto"
+//     record = "flythrough.rec"
+//     replay = "flythrough.rec"
+//     internal_resolution = [480, 270]
+//     upscale_filter = "nearest"
+//     background = 10
+//     reuse_scene = false
+//
+// every key can be overridden with ``PUDDLE_<KEY>=value`` or ``--<key> value``,
+// the resolutions are written as ``1280x720`` there
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use rendering::{
+    handler::{config::RendererConfig, upscale::UpscaleFilter},
+    vulkan::{AdapterSelection, PresentMode, ValidationLevel},
+};
+
+/// the file that is loaded if no other one is given with ``--config`` or ``PUDDLE_CONFIG``
+pub const DEFAULT_CONFIG_FILE: &str = "puddle.toml";
+
+const ENV_PREFIX: &str = "PUDDLE_";
+
+/// the keys ``Settings::set`` accepts
+const KEYS: [&str; 18] = [
+    "resolution",
+    "fullscreen",
+    "present_mode",
+    "msaa",
+    "validation",
+    "fps_cap",
+    "asset_root",
+    "breadcrumbs",
+    "workers",
+    "seed",
+    "gi",
+    "gpu",
+    "record",
+    "replay",
+    "internal_resolution",
+    "upscale_filter",
+    "background",
+    "reuse_scene",
+];
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(PathBuf, std::io::Error),
+    Toml(toml::de::Error),
+    UnknownKey(String),
+    InvalidValue {
+        key: String,
+        value: String,
+    },
+    /// a command line flag without a value
+    MissingValue(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "failed to read {}: {err}", path.display()),
+            Self::Toml(err) => write!(f, "invalid config file: {err}"),
+            Self::UnknownKey(key) => write!(f, "unknown setting {key:?}"),
+            Self::InvalidValue { key, value } => write!(f, "invalid value {value:?} for {key}"),
+            Self::MissingValue(key) => write!(f, "--{key} needs a value"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// what the application does while its window isn't focused or is minimized, see ``Settings::background``
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundPolicy {
+    /// run like in the foreground
+    #[default]
+    Continue,
+    /// render at most this many frames per second, ``fps_cap`` still applies if it's lower
+    Throttle(u32),
+    /// run the tasks and update the world without rendering
+    PauseRendering,
+    /// only wait for the window events until the window is in front again, the time of the world stands still
+    PauseAll,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    /// the size of the window, or the video mode in fullscreen
+    pub resolution: [u32; 2],
+    /// use the whole primary monitor
+    pub fullscreen: bool,
+    pub present_mode: PresentMode,
+    /// samples per pixel, 1 disables msaa
+    pub msaa: u32,
+    /// ignored in release builds
+    pub validation: ValidationLevel,
+    /// the most frames that are rendered per second, none renders as fast as the present mode allows
+    pub fps_cap: Option<u32>,
+    /// the directory relative asset paths are resolved against
+    pub asset_root: PathBuf,
+    /// log which pass the gpu crashed in when the device is lost
+    pub breadcrumbs: bool,
+    /// the threads of the job system, none uses one less than there are cores
+    pub workers: Option<usize>,
+    /// seeds ``World::rng``, the same seed generates the same content
+    pub seed: u64,
+    /// the experimental voxel global illumination, see ``RendererConfig::gi``
+    pub gi: bool,
+    /// ``auto``, ``discrete``, ``integrated``, the index of the gpu or a part of its name
+    /// lets laptops with two gpus pick one, see ``VulkanDevice::enumerate_adapters``
+    pub gpu: AdapterSelection,
+    /// write the frames to this file when the application closes, see ``replay::FrameRecorder``
+    pub record: Option<PathBuf>,
+    /// play the frames of this recording back and close once it ran out, see ``replay::FramePlayback``
+    pub replay: Option<PathBuf>,
+    /// render at this size and scale it in to the window with black bars, ``off`` renders at the window size
+    /// see ``RendererConfig::internal_resolution``
+    pub internal_resolution: Option<[u32; 2]>,
+    /// ``linear`` or ``nearest`` for pixel art
+    pub upscale_filter: UpscaleFilter,
+    /// ``continue``, the frames per second to throttle to, ``pause_rendering`` or ``pause``
+    /// to save battery while the window is in the background
+    pub background: BackgroundPolicy,
+    /// only draw the ui again in frames where the scene didn't change, see ``World::take_scene_changed``
+    /// tasks that write to the gpu buffers themselves have to call ``World::mark_scene_changed``
+    pub reuse_scene: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            resolution: [800, 600],
+            fullscreen: false,
+            present_mode: PresentMode::default(),
+            msaa: 1,
+            validation: ValidationLevel::default(),
+            fps_cap: None,
+            asset_root: PathBuf::from("assets"),
+            breadcrumbs: false,
+            workers: None,
+            seed: 0,
+            gi: false,
+            gpu: AdapterSelection::Auto,
+            record: None,
+            replay: None,
+            internal_resolution: None,
+            upscale_filter: UpscaleFilter::default(),
+            background: BackgroundPolicy::default(),
+            reuse_scene: false,
+        }
+    }
+}
+
+impl Settings {
+    /// load the settings from the config file, the environment and the command line arguments
+    /// the config file is optional if it wasn't given explicitly
+    /// command line arguments that aren't settings are ignored, so the application can use them
+    /// # Errors
+    /// if the config file can't be read or a setting has an invalid value
+    pub fn load() -> Result<Self, SettingsError> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let args = parse_args(&args)?;
+        let env: Vec<(String, String)> = std::env::vars()
+            .filter_map(|(key, value)| {
+                let key = key.strip_prefix(ENV_PREFIX)?.to_lowercase();
+                Some((key, value))
+            })
+            .collect();
+
+        let explicit_file = args
+            .iter()
+            .chain(&env)
+            .find(|(key, _)| key == "config")
+            .map(|(_, path)| PathBuf::from(path));
+
+        let mut settings = match explicit_file {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(DEFAULT_CONFIG_FILE)?
+            }
+            None => Self::default(),
+        };
+
+        // other programs might use the same prefix, so unknown variables are skipped
+        for (key, value) in &env {
+            match settings.set(key, value) {
+                Err(SettingsError::UnknownKey(_)) => {}
+                result => result?,
+            }
+        }
+
+        // command line arguments win over environment variables
+        for (key, value) in args.iter().filter(|(key, _)| key != "config") {
+            settings.set(key, value)?;
+        }
+
+        Ok(settings)
+    }
+
+    /// # Errors
+    /// if the file can't be read or isn't valid
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| SettingsError::Io(path.to_path_buf(), err))?;
+        Self::from_toml(&text)
+    }
+
+    /// # Errors
+    /// if the text isn't valid toml or contains unknown settings
+    pub fn from_toml(text: &str) -> Result<Self, SettingsError> {
+        let table: toml::Table = text.parse().map_err(SettingsError::Toml)?;
+        let mut settings = Self::default();
+
+        for (key, value) in &table {
+            let text = match value {
+                // the resolution is an array of the width and height
+                toml::Value::Array(values) => values
+                    .iter()
+                    .map(toml_scalar)
+                    .collect::<Option<Vec<_>>>()
+                    .map(|values| values.join("x")),
+                value => toml_scalar(value),
+            };
+
+            let text = text.ok_or_else(|| SettingsError::InvalidValue {
+                key: key.clone(),
+                value: format!("{value:?}"),
+            })?;
+            settings.set(key, &text)?;
+        }
+
+        Ok(settings)
+    }
+
+    /// change a single setting, the keys are the names of the fields
+    /// # Errors
+    /// if the key doesn't exist or the value can't be parsed
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingsError> {
+        let invalid = || SettingsError::InvalidValue {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+
+        match key {
+            "resolution" => self.resolution = parse_size(value).ok_or_else(invalid)?,
+            "fullscreen" => self.fullscreen = value.parse().map_err(|_| invalid())?,
+            "present_mode" => {
+                self.present_mode = match value {
+                    "fifo" | "vsync" => PresentMode::Fifo,
+                    "mailbox" => PresentMode::Mailbox,
+                    "immediate" => PresentMode::Immediate,
+                    _ => return Err(invalid()),
+                };
+            }
+            "msaa" => {
+                self.msaa = value.parse().map_err(|_| invalid())?;
+                if !self.msaa.is_power_of_two() {
+                    return Err(invalid());
+                }
+            }
+            "validation" => {
+                self.validation = match value {
+                    "off" => ValidationLevel::Off,
+                    "errors" => ValidationLevel::Errors,
+                    "full" => ValidationLevel::Full,
+                    _ => return Err(invalid()),
+                };
+            }
+            "fps_cap" => {
+                self.fps_cap = match value {
+                    "off" | "0" => None,
+                    value => Some(value.parse().map_err(|_| invalid())?),
+                };
+            }
+            "asset_root" => self.asset_root = PathBuf::from(value),
+            "breadcrumbs" => self.breadcrumbs = value.parse().map_err(|_| invalid())?,
+            "workers" => {
+                self.workers = match value {
+                    "auto" | "0" => None,
+                    value => Some(value.parse().map_err(|_| invalid())?),
+                };
+            }
+            "seed" => self.seed = value.parse().map_err(|_| invalid())?,
+            "gi" => self.gi = value.parse().map_err(|_| invalid())?,
+            "gpu" => self.gpu = value.parse().map_err(|_| invalid())?,
+            "record" => self.record = (!value.is_empty()).then(|| PathBuf::from(value)),
+            "replay" => self.replay = (!value.is_empty()).then(|| PathBuf::from(value)),
+            "internal_resolution" => {
+                self.internal_resolution = match value {
+                    "off" | "" => None,
+                    value => Some(
+                        parse_size(value)
+                            .filter(|size| !size.contains(&0))
+                            .ok_or_else(invalid)?,
+                    ),
+                };
+            }
+            "upscale_filter" => {
+                self.upscale_filter = match value {
+                    "linear" => UpscaleFilter::Linear,
+                    "nearest" => UpscaleFilter::Nearest,
+                    _ => return Err(invalid()),
+                };
+            }
+            "background" => {
+                self.background = match value {
+                    "continue" => BackgroundPolicy::Continue,
+                    "pause_rendering" => BackgroundPolicy::PauseRendering,
+                    "pause" => BackgroundPolicy::PauseAll,
+                    value => BackgroundPolicy::Throttle(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|&fps| fps > 0)
+                            .ok_or_else(invalid)?,
+                    ),
+                };
+            }
+            "reuse_scene" => self.reuse_scene = value.parse().map_err(|_| invalid())?,
+            _ => return Err(SettingsError::UnknownKey(key.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// resolve a path relative to the asset root, absolute paths are returned as they are
+    #[must_use]
+    pub fn asset_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.asset_root.join(path)
+    }
+
+    #[must_use]
+    pub fn renderer_config(&self) -> RendererConfig {
+        RendererConfig {
+            present_mode: self.present_mode,
+            msaa_samples: self.msaa,
+            validation: self.validation,
+            breadcrumbs: self.breadcrumbs,
+            workers: self.workers,
+            gi: self.gi,
+            adapter: self.gpu.clone(),
+            internal_resolution: self.internal_resolution,
+            upscale_filter: self.upscale_filter,
+            ..Default::default()
+        }
+    }
+}
+
+/// ``1280x720``
+fn parse_size(value: &str) -> Option<[u32; 2]> {
+    let (width, height) = value.split_once(['x', 'X'])?;
+    Some([width.trim().parse().ok()?, height.trim().parse().ok()?])
+}
+
+/// the text form of a toml value, the same as it would be written on the command line
+fn toml_scalar(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// the settings flags of the command line arguments as ``(key, value)`` pairs
+/// ``--present-mode fifo`` and ``--present_mode=fifo`` both become ``("present_mode", "fifo")``
+/// the flags that aren't settings are skipped
+fn parse_args(args: &[String]) -> Result<Vec<(String, String)>, SettingsError> {
+    let mut settings = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            continue;
+        };
+
+        let (key, value) = match flag.split_once('=') {
+            Some((key, value)) => (key.replace('-', "_"), Some(value.to_owned())),
+            None => (flag.replace('-', "_"), None),
+        };
+
+        if key != "config" && !KEYS.contains(&key.as_str()) {
+            continue;
+        }
+
+        let value = match value {
+            Some(value) => value,
+            None => args
+                .next()
+                .cloned()
+                .ok_or_else(|| SettingsError::MissingValue(key.clone()))?,
+        };
+
+        settings.push((key, value));
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn toml_file() {
+        let settings = Settings::from_toml(
+            r#"
+            resolution = [1920, 1080]
+            fullscreen = true
+            present_mode = "fifo"
+            msaa = 4
+            validation = "errors"
+            fps_cap = 60
+            asset_root = "data"
+            breadcrumbs = true
+            workers = 3
+            seed = 12345
+            gi = true
+            gpu = "radeon"
+            record = "flythrough.rec"
+            internal_resolution = [480, 270]
+            upscale_filter = "nearest"
+            background = 10
+            reuse_scene = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings,
+            Settings {
+                resolution: [1920, 1080],
+                fullscreen: true,
+                present_mode: PresentMode::Fifo,
+                msaa: 4,
+                validation: ValidationLevel::Errors,
+                fps_cap: Some(60),
+                asset_root: PathBuf::from("data"),
+                breadcrumbs: true,
+                workers: Some(3),
+                seed: 12345,
+                gi: true,
+                gpu: AdapterSelection::Name("radeon".to_owned()),
+                record: Some(PathBuf::from("flythrough.rec")),
+                replay: None,
+                internal_resolution: Some([480, 270]),
+                upscale_filter: UpscaleFilter::Nearest,
+                background: BackgroundPolicy::Throttle(10),
+                reuse_scene: true,
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert!(matches!(
+            Settings::from_toml("refresh_rate = 60"),
+            Err(SettingsError::UnknownKey(_))
+        ));
+        assert!(matches!(
+            Settings::from_toml("msaa = 3"),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Settings::from_toml("resolution = 1280"),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Settings::from_toml("internal_resolution = [0, 270]"),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Settings::from_toml("background = 0"),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn command_line_flags() {
+        let parsed = parse_args(&args(&[
+            "model.vox",
+            "--resolution",
+            "1280x720",
+            "--present-mode=immediate",
+            "--verbose",
+            "--config",
+            "other.toml",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            parsed,
+            [
+                ("resolution".to_owned(), "1280x720".to_owned()),
+                ("present_mode".to_owned(), "immediate".to_owned()),
+                ("config".to_owned(), "other.toml".to_owned()),
+            ]
+        );
+
+        assert!(matches!(
+            parse_args(&args(&["--msaa"])),
+            Err(SettingsError::MissingValue(_))
+        ));
+    }
+}
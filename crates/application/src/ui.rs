@@ -0,0 +1,357 @@
+// the glue between glfw and egui
+// window events are turned in to ``egui::RawInput``, the platform output of egui is applied to the window
+//
+// GLFW doesn't report IME composition or take the position of the candidate window,
+// text composed with an IME only arrives once it's committed and is sent to egui like typed text
+//
+// egui viewports are always embedded in the main window (the default of ``egui::Context``),
+// detached os windows need a swapchain per window, but ``VulkanDevice`` only owns the surface of the main window
+
+use std::{io::Cursor, time::Instant};
+
+use ash::{prelude::VkResult, vk};
+use egui::{ClippedPrimitive, TexturesDelta};
+use glfw::{Action, PWindow, WindowEvent};
+use rendering::handler::RenderHandler;
+
+/// what egui needs to be painted, see ``RenderHandler::paint_ui``
+pub struct UiOutput {
+    pub textures_delta: TexturesDelta,
+    pub primitives: Vec<ClippedPrimitive>,
+    pub pixels_per_point: f32,
+}
+
+pub struct UiLayer {
+    pub ctx: egui::Context,
+    raw_input: egui::RawInput,
+    start: Instant,
+    modifiers: egui::Modifiers,
+    /// in points
+    pointer_pos: egui::Pos2,
+    pixels_per_point: f32,
+    cursor_icon: egui::CursorIcon,
+}
+
+impl UiLayer {
+    /// enables the window events egui needs
+    pub fn new(window: &mut PWindow) -> Self {
+        window.set_char_polling(true);
+        window.set_mouse_button_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_cursor_enter_polling(true);
+        window.set_scroll_polling(true);
+        window.set_focus_polling(true);
+        window.set_content_scale_polling(true);
+
+        Self {
+            ctx: egui::Context::default(),
+            raw_input: egui::RawInput::default(),
+            start: Instant::now(),
+            modifiers: egui::Modifiers::default(),
+            pointer_pos: egui::Pos2::ZERO,
+            pixels_per_point: window.get_content_scale().0,
+            cursor_icon: egui::CursorIcon::Default,
+        }
+    }
+
+    /// load ``shaders/ui.spv`` and set it as the ui shader of the renderer
+    /// the shader is read at runtime, as it has to be compiled with ``build.sh`` first
+    /// # Errors
+    /// if the pipelines couldn't be created
+    pub fn set_shaders(renderer: &mut RenderHandler) -> VkResult<()> {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/ui.spv");
+        let Ok(code) = std::fs::read(path) else {
+            eprintln!(
+                "{path} is missing, the ui isn't drawn until the shaders are built with build.sh"
+            );
+            return Ok(());
+        };
+
+        let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+            .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+        let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+        let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+        let stage = |name, stage| {
+            vk::PipelineShaderStageCreateInfo::default()
+                .name(name)
+                .stage(stage)
+                .module(module)
+        };
+
+        let result = renderer.set_ui_shaders(
+            &[
+                stage(c"vs_ui", vk::ShaderStageFlags::VERTEX),
+                stage(c"fs_ui", vk::ShaderStageFlags::FRAGMENT),
+            ],
+            &[
+                stage(c"vs_composite", vk::ShaderStageFlags::VERTEX),
+                stage(c"fs_composite", vk::ShaderStageFlags::FRAGMENT),
+            ],
+        );
+
+        // the pipelines don't need the module anymore
+        unsafe { renderer.device.destroy_shader_module(module, None) };
+        result
+    }
+
+    /// turn a window event in to egui input, it's used by egui on the next ``run``
+    pub fn on_event(&mut self, window: &mut PWindow, event: &WindowEvent) {
+        let events = &mut self.raw_input.events;
+
+        match *event {
+            WindowEvent::CursorPos(x, y) => {
+                self.pointer_pos = window_to_points(window, x, y, self.pixels_per_point);
+                events.push(egui::Event::PointerMoved(self.pointer_pos));
+            }
+            WindowEvent::CursorEnter(false) => events.push(egui::Event::PointerGone),
+            WindowEvent::MouseButton(button, action, _) => {
+                let button = match button {
+                    glfw::MouseButton::Button1 => egui::PointerButton::Primary,
+                    glfw::MouseButton::Button2 => egui::PointerButton::Secondary,
+                    glfw::MouseButton::Button3 => egui::PointerButton::Middle,
+                    glfw::MouseButton::Button4 => egui::PointerButton::Extra1,
+                    glfw::MouseButton::Button5 => egui::PointerButton::Extra2,
+                    _ => return,
+                };
+
+                events.push(egui::Event::PointerButton {
+                    pos: self.pointer_pos,
+                    button,
+                    pressed: action != Action::Release,
+                    modifiers: self.modifiers,
+                });
+            }
+            WindowEvent::Scroll(x, y) => events.push(egui::Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Line,
+                delta: egui::vec2(x as f32, y as f32),
+                modifiers: self.modifiers,
+            }),
+            WindowEvent::Focus(focused) => {
+                self.raw_input.focused = focused;
+                events.push(egui::Event::WindowFocused(focused));
+            }
+            WindowEvent::ContentScale(scale, _) => self.pixels_per_point = scale,
+            WindowEvent::Char(c) if !c.is_control() => {
+                events.push(egui::Event::Text(c.to_string()));
+            }
+            WindowEvent::Key(key, _, action, mods) => {
+                self.modifiers = modifiers(mods);
+                let pressed = action != Action::Release;
+
+                // the clipboard shortcuts are sent as their own events, like egui expects
+                if pressed && self.modifiers.command {
+                    match key {
+                        glfw::Key::C => return events.push(egui::Event::Copy),
+                        glfw::Key::X => return events.push(egui::Event::Cut),
+                        glfw::Key::V => {
+                            if let Some(text) = window.get_clipboard_string() {
+                                events.push(egui::Event::Paste(text));
+                            }
+                            return;
+                        }
+                        _ => {}
+                    }
+                }
+
+                if let Some(key) = egui_key(key) {
+                    events.push(egui::Event::Key {
+                        key,
+                        physical_key: None,
+                        pressed,
+                        repeat: action == Action::Repeat,
+                        modifiers: self.modifiers,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// run egui with the input since the last call and apply its clipboard and cursor output to the window
+    pub fn run(&mut self, window: &mut PWindow, run_ui: impl FnMut(&egui::Context)) -> UiOutput {
+        let (width, height) = window.get_framebuffer_size();
+        let ppp = self.pixels_per_point;
+
+        self.raw_input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(width as f32, height as f32) / ppp,
+        ));
+        self.raw_input.time = Some(self.start.elapsed().as_secs_f64());
+        self.raw_input.modifiers = self.modifiers;
+        self.raw_input
+            .viewports
+            .entry(self.raw_input.viewport_id)
+            .or_default()
+            .native_pixels_per_point = Some(ppp);
+
+        let _span = tracing::info_span!("ui").entered();
+        let output = self.ctx.run(self.raw_input.take(), run_ui);
+        self.apply_platform_output(window, output.platform_output);
+
+        UiOutput {
+            primitives: self.ctx.tessellate(output.shapes, output.pixels_per_point),
+            textures_delta: output.textures_delta,
+            pixels_per_point: output.pixels_per_point,
+        }
+    }
+
+    fn apply_platform_output(&mut self, window: &mut PWindow, output: egui::PlatformOutput) {
+        for command in output.commands {
+            match command {
+                egui::OutputCommand::CopyText(text) => window.set_clipboard_string(&text),
+                egui::OutputCommand::CopyImage(_) => {
+                    eprintln!("copying images to the clipboard isn't supported");
+                }
+                egui::OutputCommand::OpenUrl(url) => eprintln!("open {}", url.url),
+            }
+        }
+
+        if output.cursor_icon != self.cursor_icon {
+            self.cursor_icon = output.cursor_icon;
+            set_cursor_icon(window, output.cursor_icon);
+        }
+    }
+}
+
+/// GLFW only has a few standard cursors, the other icons use the closest one
+fn set_cursor_icon(window: &mut PWindow, icon: egui::CursorIcon) {
+    use egui::CursorIcon;
+    use glfw::StandardCursor;
+
+    if icon == CursorIcon::None {
+        window.set_cursor_mode(glfw::CursorMode::Hidden);
+        return;
+    }
+    window.set_cursor_mode(glfw::CursorMode::Normal);
+
+    let cursor = match icon {
+        CursorIcon::Text | CursorIcon::VerticalText => StandardCursor::IBeam,
+        CursorIcon::Crosshair | CursorIcon::Cell => StandardCursor::Crosshair,
+        CursorIcon::PointingHand | CursorIcon::Grab | CursorIcon::Grabbing => StandardCursor::Hand,
+        CursorIcon::ResizeHorizontal
+        | CursorIcon::ResizeEast
+        | CursorIcon::ResizeWest
+        | CursorIcon::ResizeColumn => StandardCursor::HResize,
+        CursorIcon::ResizeVertical
+        | CursorIcon::ResizeNorth
+        | CursorIcon::ResizeSouth
+        | CursorIcon::ResizeRow => StandardCursor::VResize,
+        _ => StandardCursor::Arrow,
+    };
+
+    window.set_cursor(Some(glfw::Cursor::standard(cursor)));
+}
+
+/// the cursor position is in window coordinates, which are not always pixels
+fn window_to_points(window: &PWindow, x: f64, y: f64, pixels_per_point: f32) -> egui::Pos2 {
+    let (window_width, window_height) = window.get_size();
+    let (width, height) = window.get_framebuffer_size();
+
+    let scale_x = width as f32 / window_width.max(1) as f32;
+    let scale_y = height as f32 / window_height.max(1) as f32;
+
+    egui::pos2(x as f32 * scale_x, y as f32 * scale_y) / pixels_per_point
+}
+
+fn modifiers(mods: glfw::Modifiers) -> egui::Modifiers {
+    let ctrl = mods.contains(glfw::Modifiers::Control);
+    let mac_cmd = cfg!(target_os = "macos") && mods.contains(glfw::Modifiers::Super);
+
+    egui::Modifiers {
+        alt: mods.contains(glfw::Modifiers::Alt),
+        ctrl,
+        shift: mods.contains(glfw::Modifiers::Shift),
+        mac_cmd,
+        command: if cfg!(target_os = "macos") {
+            mac_cmd
+        } else {
+            ctrl
+        },
+    }
+}
+
+fn egui_key(key: glfw::Key) -> Option<egui::Key> {
+    use egui::Key as E;
+    use glfw::Key as G;
+
+    Some(match key {
+        G::Down => E::ArrowDown,
+        G::Left => E::ArrowLeft,
+        G::Right => E::ArrowRight,
+        G::Up => E::ArrowUp,
+        G::Escape => E::Escape,
+        G::Tab => E::Tab,
+        G::Backspace => E::Backspace,
+        G::Enter | G::KpEnter => E::Enter,
+        G::Space => E::Space,
+        G::Insert => E::Insert,
+        G::Delete => E::Delete,
+        G::Home => E::Home,
+        G::End => E::End,
+        G::PageUp => E::PageUp,
+        G::PageDown => E::PageDown,
+        G::Comma => E::Comma,
+        G::Backslash => E::Backslash,
+        G::Slash | G::KpDivide => E::Slash,
+        G::LeftBracket => E::OpenBracket,
+        G::RightBracket => E::CloseBracket,
+        G::GraveAccent => E::Backtick,
+        G::Minus | G::KpSubtract => E::Minus,
+        G::Period | G::KpDecimal => E::Period,
+        G::KpAdd => E::Plus,
+        G::Equal | G::KpEqual => E::Equals,
+        G::Semicolon => E::Semicolon,
+        G::Apostrophe => E::Quote,
+        G::Num0 | G::Kp0 => E::Num0,
+        G::Num1 | G::Kp1 => E::Num1,
+        G::Num2 | G::Kp2 => E::Num2,
+        G::Num3 | G::Kp3 => E::Num3,
+        G::Num4 | G::Kp4 => E::Num4,
+        G::Num5 | G::Kp5 => E::Num5,
+        G::Num6 | G::Kp6 => E::Num6,
+        G::Num7 | G::Kp7 => E::Num7,
+        G::Num8 | G::Kp8 => E::Num8,
+        G::Num9 | G::Kp9 => E::Num9,
+        G::A => E::A,
+        G::B => E::B,
+        G::C => E::C,
+        G::D => E::D,
+        G::E => E::E,
+        G::F => E::F,
+        G::G => E::G,
+        G::H => E::H,
+        G::I => E::I,
+        G::J => E::J,
+        G::K => E::K,
+        G::L => E::L,
+        G::M => E::M,
+        G::N => E::N,
+        G::O => E::O,
+        G::P => E::P,
+        G::Q => E::Q,
+        G::R => E::R,
+        G::S => E::S,
+        G::T => E::T,
+        G::U => E::U,
+        G::V => E::V,
+        G::W => E::W,
+        G::X => E::X,
+        G::Y => E::Y,
+        G::Z => E::Z,
+        G::F1 => E::F1,
+        G::F2 => E::F2,
+        G::F3 => E::F3,
+        G::F4 => E::F4,
+        G::F5 => E::F5,
+        G::F6 => E::F6,
+        G::F7 => E::F7,
+        G::F8 => E::F8,
+        G::F9 => E::F9,
+        G::F10 => E::F10,
+        G::F11 => E::F11,
+        G::F12 => E::F12,
+        _ => return None,
+    })
+}
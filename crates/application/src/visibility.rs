@@ -0,0 +1,256 @@
+// answers "why is my mesh invisible" without a gpu capture
+// the corners of the bounds of a batch are projected on the cpu with the matrix the camera gives the shaders,
+// then tested against the planes of the clip space, the same way the gpu clips triangles
+//
+// only the box is known here, the batch can still be invisible because of its shaders, face culling or the depth test
+
+use std::fmt;
+
+use math::{Camera, Vec2, Vec3, Vec4};
+use rendering::handler::render_batch::RenderBatch;
+
+/// tells if a corner in clip space is on the outer side of a plane
+type OutsidePlane = fn(&Vec4) -> bool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// at least a part of the box is in the view
+    Visible,
+    /// hidden with ``RenderBatch::set_visible``
+    Hidden,
+    NoDraws,
+    NoMaterial,
+    /// ``RenderBatch::set_bounds`` was never called, so nothing is known about where it is
+    NoBounds,
+    BehindCamera,
+    LeftOfView,
+    RightOfView,
+    AboveView,
+    BelowView,
+    /// closer to the camera than the near plane
+    BeforeNearPlane,
+    BeyondFarPlane,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Visible => "in the view",
+            Self::Hidden => "hidden",
+            Self::NoDraws => "no draw calls",
+            Self::NoMaterial => "no material",
+            Self::NoBounds => "no bounds set",
+            Self::BehindCamera => "behind the camera",
+            Self::LeftOfView => "left of the view",
+            Self::RightOfView => "right of the view",
+            Self::AboveView => "above the view",
+            Self::BelowView => "below the view",
+            Self::BeforeNearPlane => "closer than the near plane",
+            Self::BeyondFarPlane => "beyond the far plane",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisibilityReport {
+    pub verdict: Verdict,
+    /// how many of the 8 corners are in front of the camera
+    pub corners_in_front: usize,
+    /// the part of the screen the corners in front cover, in normalized device coordinates with y pointing down
+    /// clamped to the screen, from -1 to 1
+    pub screen: Option<[Vec2; 2]>,
+    /// the lowest and highest depth of the corners in front, what the depth test compares
+    pub depth: Option<[f32; 2]>,
+}
+
+impl VisibilityReport {
+    fn without_box(verdict: Verdict) -> Self {
+        Self {
+            verdict,
+            corners_in_front: 0,
+            screen: None,
+            depth: None,
+        }
+    }
+}
+
+/// project the box with the unjittered view projection of the camera
+#[must_use]
+pub fn check_box(camera: &Camera, min: Vec3, max: Vec3) -> VisibilityReport {
+    let view_proj = camera.build_unjittered_proj();
+    let clip: Vec<Vec4> = (0..8)
+        .map(|i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+            view_proj * corner.extend(1.0)
+        })
+        .collect();
+
+    let in_front: Vec<Vec3> = clip
+        .iter()
+        .filter(|clip| clip.w > 0.0)
+        .map(|clip| clip.truncate() / clip.w)
+        .collect();
+
+    let screen = (!in_front.is_empty()).then(|| {
+        let (min, max) = in_front.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), ndc| (min.min(ndc.truncate()), max.max(ndc.truncate())),
+        );
+        [
+            min.clamp(Vec2::NEG_ONE, Vec2::ONE),
+            max.clamp(Vec2::NEG_ONE, Vec2::ONE),
+        ]
+    });
+    let depth = (!in_front.is_empty()).then(|| {
+        in_front
+            .iter()
+            .fold([f32::MAX, f32::MIN], |[min, max], ndc| {
+                [min.min(ndc.z), max.max(ndc.z)]
+            })
+    });
+
+    // with reverse z the depth is 1 at the near plane and 0 at the far one
+    let reverse_z = camera.projection.near_depth() == 1.0;
+    let (low_depth, high_depth) = if reverse_z {
+        (Verdict::BeyondFarPlane, Verdict::BeforeNearPlane)
+    } else {
+        (Verdict::BeforeNearPlane, Verdict::BeyondFarPlane)
+    };
+
+    // the box can only be skipped if all corners are outside of the same plane
+    let planes: [(Verdict, OutsidePlane); 6] = [
+        (Verdict::LeftOfView, |c| c.x < -c.w),
+        (Verdict::RightOfView, |c| c.x > c.w),
+        (Verdict::AboveView, |c| c.y < -c.w),
+        (Verdict::BelowView, |c| c.y > c.w),
+        (low_depth, |c| c.z < 0.0),
+        (high_depth, |c| c.z > c.w),
+    ];
+
+    let verdict = if in_front.is_empty() {
+        Verdict::BehindCamera
+    } else {
+        planes
+            .iter()
+            .find(|(_, outside)| clip.iter().all(outside))
+            .map_or(Verdict::Visible, |(verdict, _)| *verdict)
+    };
+
+    VisibilityReport {
+        verdict,
+        corners_in_front: in_front.len(),
+        screen,
+        depth,
+    }
+}
+
+/// check why a batch might not be visible, the state of the batch is checked before its bounds
+#[must_use]
+pub fn check_batch(camera: &Camera, batch: &RenderBatch) -> VisibilityReport {
+    if !batch.is_visible() {
+        return VisibilityReport::without_box(Verdict::Hidden);
+    }
+    if batch.draw_count() == 0 {
+        return VisibilityReport::without_box(Verdict::NoDraws);
+    }
+    if !batch.has_material() {
+        return VisibilityReport::without_box(Verdict::NoMaterial);
+    }
+
+    let Some(bounds) = batch.bounds() else {
+        return VisibilityReport::without_box(Verdict::NoBounds);
+    };
+
+    check_box(camera, bounds.min, bounds.max)
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{Projection, Transform};
+
+    use super::*;
+
+    fn verdict(camera: &Camera, center: Vec3) -> Verdict {
+        check_box(camera, center - 0.001, center + 0.001).verdict
+    }
+
+    #[test]
+    fn boxes_around_the_camera() {
+        // looks along -z, with a near plane at 0.01 and a far plane at 100
+        let mut camera = Camera::new(Transform::IDENTITY, 1.0, Projection::default());
+
+        let visible = check_box(
+            &camera,
+            Vec3::new(-1.0, -1.0, -6.0),
+            Vec3::new(1.0, 1.0, -4.0),
+        );
+        assert_eq!(visible.verdict, Verdict::Visible);
+        assert_eq!(visible.corners_in_front, 8);
+        let [near, far] = visible.depth.unwrap();
+        assert!(0.0 < near && near < far && far < 1.0);
+
+        assert_eq!(
+            verdict(&camera, Vec3::new(0.0, 0.0, 5.0)),
+            Verdict::BehindCamera
+        );
+        assert_eq!(
+            verdict(&camera, Vec3::new(-50.0, 0.0, -5.0)),
+            Verdict::LeftOfView
+        );
+        assert_eq!(
+            verdict(&camera, Vec3::new(50.0, 0.0, -5.0)),
+            Verdict::RightOfView
+        );
+        // y points up in world space
+        assert_eq!(
+            verdict(&camera, Vec3::new(0.0, 50.0, -5.0)),
+            Verdict::AboveView
+        );
+        assert_eq!(
+            verdict(&camera, Vec3::new(0.0, -50.0, -5.0)),
+            Verdict::BelowView
+        );
+        assert_eq!(
+            verdict(&camera, Vec3::new(0.0, 0.0, -500.0)),
+            Verdict::BeyondFarPlane
+        );
+        assert_eq!(
+            verdict(&camera, Vec3::new(0.0, 0.0, -0.005)),
+            Verdict::BeforeNearPlane
+        );
+
+        // a box around the camera is visible, even though some corners are behind it
+        let around = check_box(&camera, Vec3::splat(-1.0), Vec3::splat(1.0));
+        assert_eq!(around.verdict, Verdict::Visible);
+        assert_eq!(around.corners_in_front, 4);
+
+        camera.projection = Projection::Perspective {
+            fovy: 70.0,
+            znear: 0.01,
+            zfar: 100.0,
+            infinite_reverse_z: true,
+        };
+        assert_eq!(
+            verdict(&camera, Vec3::new(0.0, 0.0, -500.0)),
+            Verdict::Visible
+        );
+        assert_eq!(
+            verdict(&camera, Vec3::new(0.0, 0.0, -0.005)),
+            Verdict::BeforeNearPlane
+        );
+    }
+
+    #[test]
+    fn batch_state_comes_first() {
+        let camera = Camera::new(Transform::IDENTITY, 1.0, Projection::default());
+        let mut batch = RenderBatch::default();
+        assert_eq!(check_batch(&camera, &batch).verdict, Verdict::NoDraws);
+
+        batch.set_visible(false);
+        assert_eq!(check_batch(&camera, &batch).verdict, Verdict::Hidden);
+    }
+}
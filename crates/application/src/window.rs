@@ -1,37 +1,233 @@
-use glfw::{Glfw, GlfwReceiver, PWindow, WindowEvent};
+use glfw::{Glfw, GlfwReceiver, Monitor, PWindow, WindowEvent};
+
+use crate::settings::Settings;
+
+/// a resolution and refresh rate a monitor supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoMode {
+    pub size: [u32; 2],
+    pub refresh_rate: u32,
+}
+
+impl From<glfw::VidMode> for VideoMode {
+    fn from(value: glfw::VidMode) -> Self {
+        Self {
+            size: [value.width, value.height],
+            refresh_rate: value.refresh_rate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// the index used by ``DisplayMode::Exclusive``
+    pub index: usize,
+    pub name: Option<String>,
+    /// the position of the top left corner on the virtual desktop
+    pub position: [i32; 2],
+    pub current_mode: Option<VideoMode>,
+    pub modes: Vec<VideoMode>,
+    /// the ratio between the pixels and the size the os expects things to be, 2.0 on most high dpi screens
+    pub content_scale: [f32; 2],
+}
+
+/// how the window is shown, see ``AppWindow::set_display_mode``
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    /// covers the monitor the window is on without changing its video mode
+    Borderless,
+    /// takes over the monitor with the index and switches it to the video mode
+    Exclusive(usize, VideoMode),
+}
 
 pub struct AppWindow {
     pub glfw_ctx: Glfw,
     pub window: PWindow,
     pub glfw_events: GlfwReceiver<(f64, WindowEvent)>,
+    display_mode: DisplayMode,
+    /// the position and size to go back to when leaving fullscreen
+    windowed_rect: ([i32; 2], [u32; 2]),
 }
 
-
 impl AppWindow {
-    pub fn new() -> Self {
+    pub fn new(settings: &Settings) -> Self {
         let mut glfw_ctx = glfw::init(glfw::fail_on_errors).unwrap();
 
+        let [width, height] = settings.resolution;
         let (mut window, glfw_events) = glfw_ctx
-            .create_window(800, 600, "Puddle triangle", glfw::WindowMode::Windowed)
+            .create_window(width, height, "Puddle triangle", glfw::WindowMode::Windowed)
             .unwrap();
 
         window.set_size_polling(true);
+        window.set_framebuffer_size_polling(true);
+        window.set_key_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_mouse_button_polling(true);
+        // for ``Settings::background``
+        window.set_focus_polling(true);
+        window.set_iconify_polling(true);
 
-        Self {
+        let windowed_rect = (window.get_pos().into(), settings.resolution);
+
+        let mut app_window = Self {
             glfw_ctx,
             window,
             glfw_events,
+            display_mode: DisplayMode::Windowed,
+            windowed_rect,
+        };
+
+        if settings.fullscreen {
+            let mode =
+                app_window
+                    .monitors()
+                    .into_iter()
+                    .next()
+                    .map_or(DisplayMode::Windowed, |monitor| {
+                        let refresh_rate =
+                            monitor.current_mode.map_or(60, |mode| mode.refresh_rate);
+                        DisplayMode::Exclusive(
+                            monitor.index,
+                            VideoMode {
+                                size: settings.resolution,
+                                refresh_rate,
+                            },
+                        )
+                    });
+            app_window.set_display_mode(mode);
         }
+
+        app_window
     }
 
+    /// the size of the framebuffer in pixels, this is what the renderer draws to
+    /// it differs from the window size on high dpi screens on some platforms
     pub fn get_size(&self) -> [u32; 2] {
-        let v = self.window.get_size();
+        let v = self.window.get_framebuffer_size();
         [v.0 as u32, v.1 as u32]
     }
+
+    /// the connected monitors, the primary one is first
+    pub fn monitors(&mut self) -> Vec<MonitorInfo> {
+        self.glfw_ctx.with_connected_monitors(|_, monitors| {
+            monitors
+                .iter()
+                .enumerate()
+                .map(|(index, monitor)| MonitorInfo {
+                    index,
+                    name: monitor.get_name(),
+                    position: monitor.get_pos().into(),
+                    current_mode: monitor.get_video_mode().map(VideoMode::from),
+                    modes: monitor
+                        .get_video_modes()
+                        .into_iter()
+                        .map(VideoMode::from)
+                        .collect(),
+                    content_scale: monitor.get_content_scale().into(),
+                })
+                .collect()
+        })
+    }
+
+    /// the dpi scale of the monitor the window is on
+    pub fn content_scale(&self) -> [f32; 2] {
+        self.window.get_content_scale().into()
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    /// switch between windowed and fullscreen
+    /// the new size is sent as a ``WindowEvent::FramebufferSize`` event,
+    /// it can take a few frames until the os applied it
+    /// nothing changes if the monitor of ``DisplayMode::Exclusive`` doesn't exist
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        if mode == self.display_mode {
+            return;
+        }
+
+        if self.display_mode == DisplayMode::Windowed {
+            let (x, y) = self.window.get_pos();
+            let (width, height) = self.window.get_size();
+            self.windowed_rect = ([x, y], [width as u32, height as u32]);
+        }
+
+        let window = &mut self.window;
+        let applied = match mode {
+            DisplayMode::Windowed => {
+                let ([x, y], [width, height]) = self.windowed_rect;
+                window.set_monitor(glfw::WindowMode::Windowed, x, y, width, height, None);
+                true
+            }
+            DisplayMode::Borderless => {
+                let position = window.get_pos();
+                self.glfw_ctx.with_connected_monitors(|_, monitors| {
+                    let Some(monitor) = monitor_at(monitors, position) else {
+                        return false;
+                    };
+                    let Some(video_mode) = monitor.get_video_mode() else {
+                        return false;
+                    };
+
+                    // a fullscreen window with the current video mode of the monitor doesn't change the mode
+                    window.set_monitor(
+                        glfw::WindowMode::FullScreen(monitor),
+                        0,
+                        0,
+                        video_mode.width,
+                        video_mode.height,
+                        Some(video_mode.refresh_rate),
+                    );
+                    true
+                })
+            }
+            DisplayMode::Exclusive(index, video_mode) => {
+                self.glfw_ctx.with_connected_monitors(|_, monitors| {
+                    let Some(monitor) = monitors.get(index) else {
+                        return false;
+                    };
+
+                    let [width, height] = video_mode.size;
+                    window.set_monitor(
+                        glfw::WindowMode::FullScreen(monitor),
+                        0,
+                        0,
+                        width,
+                        height,
+                        Some(video_mode.refresh_rate),
+                    );
+                    true
+                })
+            }
+        };
+
+        if applied {
+            self.display_mode = mode;
+        } else {
+            eprintln!("can't switch to {mode:?}, the monitor doesn't exist");
+        }
+    }
+}
+
+/// the monitor that contains the position, or the primary one if none does
+fn monitor_at<'a>(monitors: &'a [&'a mut Monitor], (x, y): (i32, i32)) -> Option<&'a Monitor> {
+    let contains = |monitor: &Monitor| {
+        let (left, top, width, height) = monitor.get_workarea();
+        (left..left + width).contains(&x) && (top..top + height).contains(&y)
+    };
+
+    monitors
+        .iter()
+        .map(|monitor| &**monitor)
+        .find(|monitor| contains(monitor))
+        .or_else(|| monitors.first().map(|monitor| &**monitor))
 }
 
 impl Default for AppWindow {
     fn default() -> Self {
-        Self::new()
+        Self::new(&Settings::default())
     }
 }
@@ -0,0 +1,642 @@
+// skeletal animation, the clips of a character are sampled and blended in to a pose of its skeleton
+// and the matrix of every joint is written to a storage buffer every frame, the palette
+// ``shaders/skinned.slang`` moves every vertex by the palette matrices of up to 4 joints
+//
+// skeletons, clips and skinned vertices are loaded from glTF files, see ``gltf::load``
+
+use std::{io::Cursor, sync::Arc};
+
+use ash::{prelude::VkResult, vk};
+use math::{Mat4, Quat, Transform, Vec4};
+use rendering::{
+    handler::{render_batch::DrawData, RenderHandler},
+    types::{CullingMode, Material, MaterialCreateInfo, VertexInput, VertexLayout, ViewportMode},
+    vulkan::Buffer,
+};
+
+use super::{gltf::SkinnedModel, hierarchy::Entity, picking::Picked};
+
+/// the layout needs to match ``SkinnedVertex`` in the shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// indices in to the joints of the skeleton
+    pub joints: [u32; 4],
+    /// how much every joint moves the vertex, should add up to 1
+    pub weights: [f32; 4],
+}
+
+impl SkinnedVertex {
+    /// the vertex buffer is binding 0, the instance buffer with a ``SkinnedInstance`` binding 1
+    #[must_use]
+    pub fn vertex_input() -> VertexInput {
+        let vertex = VertexLayout::new()
+            .attr::<[f32; 3]>("position")
+            .attr::<[f32; 3]>("normal")
+            .attr::<[u32; 4]>("joints")
+            .attr::<[f32; 4]>("weights");
+
+        // the columns of both matrices and then the palette
+        let mut instance = VertexLayout::per_instance();
+        for _ in 0..8 {
+            instance = instance.attr::<[f32; 4]>("matrix column");
+        }
+        let instance = instance.attr::<u32>("palette").skip(12);
+
+        let mut input = VertexInput::default();
+        let location = input.add_layout(0, 0, &vertex);
+        input.add_layout(1, location, &instance);
+        input
+    }
+}
+
+/// the layout needs to match ``SkinnedInstance`` in the shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkinnedInstance {
+    pub model: Mat4,
+    /// ``model`` of the last frame, to compute the velocity
+    pub prev_model: Mat4,
+    /// the storage buffer index of the palette
+    pub palette: u32,
+    _padding: [u32; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    /// None for the roots of the skeleton
+    pub parent: Option<usize>,
+    /// the transform relative to the parent when nothing is animated
+    pub rest: Transform,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    pub joints: Vec<Joint>,
+    /// moves a vertex from model space in to the space of the joint
+    pub inverse_bind: Vec<Mat4>,
+    /// the transform of everything above the roots, like the armature of blender
+    pub root: Mat4,
+    /// every joint comes after its parent
+    order: Vec<usize>,
+}
+
+impl Skeleton {
+    /// # Panics
+    /// if there isn't an inverse bind matrix for every joint
+    #[must_use]
+    pub fn new(joints: Vec<Joint>, inverse_bind: Vec<Mat4>, root: Mat4) -> Self {
+        assert_eq!(joints.len(), inverse_bind.len());
+
+        let depth = |mut joint: usize| {
+            let mut depth = 0;
+            // the limit stops cycles in broken files
+            while let Some(parent) = joints[joint].parent.filter(|_| depth < joints.len()) {
+                joint = parent;
+                depth += 1;
+            }
+            depth
+        };
+
+        let mut order: Vec<usize> = (0..joints.len()).collect();
+        order.sort_by_key(|&joint| depth(joint));
+
+        Self {
+            joints,
+            inverse_bind,
+            root,
+            order,
+        }
+    }
+
+    #[must_use]
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            locals: self.joints.iter().map(|joint| joint.rest).collect(),
+        }
+    }
+
+    /// the matrix of every joint in the pose, the shader multiplies the vertices with them
+    pub fn palette(&self, pose: &Pose, palette: &mut Vec<Mat4>) {
+        let mut globals = vec![Mat4::IDENTITY; self.joints.len()];
+
+        for &joint in &self.order {
+            let local = pose.locals[joint].compute_matrix();
+            globals[joint] = match self.joints[joint].parent {
+                Some(parent) => globals[parent] * local,
+                None => self.root * local,
+            };
+        }
+
+        palette.clear();
+        palette.extend(
+            globals
+                .iter()
+                .zip(&self.inverse_bind)
+                .map(|(global, inverse_bind)| *global * *inverse_bind),
+        );
+    }
+
+    #[must_use]
+    pub fn find_joint(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+}
+
+/// the transform of every joint relative to its parent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    pub locals: Vec<Transform>,
+}
+
+impl Pose {
+    /// move every joint ``weight`` of the way to ``other``
+    pub fn blend(&mut self, other: &Self, weight: f32) {
+        for (local, other) in self.locals.iter_mut().zip(&other.locals) {
+            local.translation = local.translation.lerp(other.translation, weight);
+            local.rotation = local.rotation.slerp(other.rotation, weight);
+            local.scale = local.scale.lerp(other.scale, weight);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// keep the value of a keyframe until the next one
+    Step,
+    Linear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelTarget {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+/// the keyframes of one part of a joint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub joint: usize,
+    pub target: ChannelTarget,
+    pub interpolation: Interpolation,
+    /// in seconds, ascending
+    pub times: Vec<f32>,
+    /// a value for every time, translations and scales only use xyz, rotations are quaternions
+    pub values: Vec<Vec4>,
+}
+
+impl Channel {
+    /// the value at the time, clamped to the first and last keyframe
+    #[must_use]
+    pub fn sample(&self, time: f32) -> Vec4 {
+        let next = self.times.partition_point(|&t| t <= time);
+
+        if next == 0 {
+            return self.values[0];
+        }
+        if next == self.times.len() || self.interpolation == Interpolation::Step {
+            return self.values[next - 1];
+        }
+
+        let (start, end) = (self.times[next - 1], self.times[next]);
+        let factor = (time - start) / (end - start);
+        let (from, to) = (self.values[next - 1], self.values[next]);
+
+        match self.target {
+            ChannelTarget::Rotation => Quat::from_vec4(from)
+                .slerp(Quat::from_vec4(to), factor)
+                .into(),
+            _ => from.lerp(to, factor),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    /// the time of the last keyframe
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    #[must_use]
+    pub fn new(name: impl Into<String>, channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last())
+            .fold(0.0, |duration: f32, &time| duration.max(time));
+
+        Self {
+            name: name.into(),
+            duration,
+            channels,
+        }
+    }
+
+    /// set the joints the clip animates to their value at the time, the others are left alone
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        for channel in self
+            .channels
+            .iter()
+            .filter(|channel| !channel.times.is_empty())
+        {
+            let Some(local) = pose.locals.get_mut(channel.joint) else {
+                continue;
+            };
+
+            let value = channel.sample(time);
+            match channel.target {
+                ChannelTarget::Translation => local.translation = value.truncate(),
+                ChannelTarget::Rotation => local.rotation = Quat::from_vec4(value).normalize(),
+                ChannelTarget::Scale => local.scale = value.truncate(),
+            }
+        }
+    }
+}
+
+/// a clip that is playing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationLayer {
+    /// the index in ``Animator::clips``
+    pub clip: usize,
+    /// in seconds
+    pub time: f32,
+    pub speed: f32,
+    /// how much the layer replaces the layers below it, 0 to 1
+    pub weight: f32,
+    /// start over at the end, otherwise the last keyframe is held
+    pub looping: bool,
+}
+
+impl AnimationLayer {
+    #[must_use]
+    pub fn new(clip: usize) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            weight: 1.0,
+            looping: true,
+        }
+    }
+}
+
+/// plays the clips of a skeleton
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animator {
+    pub skeleton: Skeleton,
+    pub clips: Vec<AnimationClip>,
+    /// blended over the rest pose in order
+    pub layers: Vec<AnimationLayer>,
+}
+
+impl Animator {
+    #[must_use]
+    pub fn new(skeleton: Skeleton, clips: Vec<AnimationClip>) -> Self {
+        Self {
+            skeleton,
+            clips,
+            layers: vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn find_clip(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+
+    /// move the time of every layer forward
+    pub fn advance(&mut self, dt: f32) {
+        for layer in &mut self.layers {
+            let Some(clip) = self.clips.get(layer.clip) else {
+                continue;
+            };
+
+            layer.time += dt * layer.speed;
+            layer.time = if layer.looping && clip.duration > 0.0 {
+                layer.time.rem_euclid(clip.duration)
+            } else {
+                layer.time.clamp(0.0, clip.duration)
+            };
+        }
+    }
+
+    /// the layers blended at their current time
+    #[must_use]
+    pub fn pose(&self) -> Pose {
+        let rest = self.skeleton.rest_pose();
+        let mut pose = rest.clone();
+
+        for layer in &self.layers {
+            let Some(clip) = self.clips.get(layer.clip) else {
+                continue;
+            };
+
+            let mut sampled = rest.clone();
+            clip.sample(layer.time, &mut sampled);
+            pose.blend(&sampled, layer.weight.clamp(0.0, 1.0));
+        }
+
+        pose
+    }
+}
+
+/// an animated character, its palette and instance are written by ``World::update``
+pub struct SkinnedMesh {
+    /// the global transform of the entity places the mesh in the world
+    pub entity: Entity,
+    pub animator: Animator,
+    /// the storage buffer index the palette is bound to
+    pub palette_index: usize,
+    palette_buffer: Arc<Buffer>,
+    /// the current palette followed by the one of the last frame
+    palette: Vec<Mat4>,
+    instance_buffer: Arc<Buffer>,
+    prev_model: Option<Mat4>,
+    vertex_buffer: Arc<Buffer>,
+    index_buffer: Option<Arc<Buffer>>,
+    vertex_count: u32,
+    index_count: u32,
+}
+
+impl SkinnedMesh {
+    /// upload the vertices of the model and bind its palette to the storage buffer ``palette_index``
+    /// # Errors
+    /// if there is no space left to allocate the buffers
+    pub fn new(
+        renderer: &mut RenderHandler,
+        model: SkinnedModel,
+        entity: Entity,
+        palette_index: usize,
+    ) -> VkResult<Self> {
+        let buffer = |size: usize, usage| {
+            Buffer::new(
+                renderer.device.clone(),
+                size.max(1) as u64,
+                usage,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            )
+        };
+
+        let vertex_buffer = buffer(
+            size_of_val(model.vertices.as_slice()),
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        vertex_buffer.write(0, &model.vertices);
+
+        let index_buffer = if model.indices.is_empty() {
+            None
+        } else {
+            let index_buffer = buffer(
+                size_of_val(model.indices.as_slice()),
+                vk::BufferUsageFlags::INDEX_BUFFER,
+            )?;
+            index_buffer.write(0, &model.indices);
+            Some(index_buffer)
+        };
+
+        let palette_buffer = buffer(
+            size_of::<Mat4>() * model.skeleton.joints.len() * 2,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+        )?;
+        let instance_buffer = buffer(
+            size_of::<SkinnedInstance>(),
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        let palette = renderer.import_buffer(palette_buffer.clone());
+        renderer.bind_storage_buffer(palette, Some(palette_index));
+
+        Ok(Self {
+            entity,
+            animator: Animator::new(model.skeleton, model.clips),
+            palette_index,
+            palette_buffer,
+            palette: vec![],
+            instance_buffer,
+            prev_model: None,
+            vertex_buffer,
+            index_buffer,
+            vertex_count: model.vertices.len() as u32,
+            index_count: model.indices.len() as u32,
+        })
+    }
+
+    /// advance the animation and write the palette and the instance for the next frame
+    pub fn update(&mut self, dt: f32, model: Mat4) {
+        self.animator.advance(dt);
+
+        let joints = self.animator.skeleton.joints.len();
+        let mut palette = Vec::with_capacity(joints * 2);
+        self.animator
+            .skeleton
+            .palette(&self.animator.pose(), &mut palette);
+
+        // the first frame has no previous palette, so it doesn't move
+        let prev = self.palette.get(..joints).unwrap_or(&palette).to_vec();
+        palette.extend(prev);
+        self.palette_buffer.write(0, &palette);
+        self.palette = palette;
+
+        self.instance_buffer.write(
+            0,
+            &[SkinnedInstance {
+                model,
+                prev_model: self.prev_model.unwrap_or(model),
+                palette: self.palette_index as u32,
+                _padding: [0; 3],
+            }],
+        );
+        self.prev_model = Some(model);
+    }
+
+    /// the draw for a batch with the material from ``load_material``, it can be picked as its entity
+    #[must_use]
+    pub fn draw_data(&self) -> DrawData {
+        DrawData {
+            vertex_buffer: Some(self.vertex_buffer.clone()),
+            instance_buffer: Some(self.instance_buffer.clone()),
+            index_buffer: self.index_buffer.clone(),
+            index_type: vk::IndexType::UINT32,
+            index_count: self.index_count,
+            vertex_count: self.vertex_count,
+            instance_count: 1,
+            object_id: Picked::Entity(self.entity).object_id(),
+            ..Default::default()
+        }
+    }
+}
+
+/// load ``shaders/skinned.spv`` and create the material skinned meshes are drawn with,
+/// None if the shader hasn't been built with ``build.sh`` yet
+/// # Errors
+/// if the shader module couldn't be created
+pub fn load_material(renderer: &mut RenderHandler) -> VkResult<Option<Arc<Material>>> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/skinned.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!("{path} is missing, skinned meshes can't be drawn until the shaders are built with build.sh");
+        return Ok(None);
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stage = |name, stage| {
+        vk::PipelineShaderStageCreateInfo::default()
+            .name(name)
+            .stage(stage)
+            .module(module)
+    };
+
+    let material = renderer.create_material(MaterialCreateInfo {
+        cull_mode: CullingMode::Back,
+        viewport: ViewportMode::default(),
+        vertex_input: SkinnedVertex::vertex_input(),
+        object_id: true,
+        shaders: vec![
+            stage(c"vertex_main", vk::ShaderStageFlags::VERTEX),
+            stage(c"fragment_main", vk::ShaderStageFlags::FRAGMENT),
+        ],
+        ..Default::default()
+    });
+
+    // the pipeline doesn't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+
+    Ok(renderer.material(material))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use math::Vec3;
+
+    /// a root at the origin and a child one unit above it
+    fn skeleton() -> Skeleton {
+        let joint = |name: &str, parent, rest| Joint {
+            name: name.to_owned(),
+            parent,
+            rest,
+        };
+
+        Skeleton::new(
+            vec![
+                joint("arm", Some(1), Transform::from_xyz(0.0, 1.0, 0.0)),
+                joint("root", None, Transform::IDENTITY),
+            ],
+            vec![Mat4::from_translation(-Vec3::Y), Mat4::IDENTITY],
+            Mat4::IDENTITY,
+        )
+    }
+
+    fn channel(target: ChannelTarget, values: Vec<Vec4>) -> Channel {
+        Channel {
+            joint: 0,
+            target,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            values,
+        }
+    }
+
+    #[test]
+    fn vertex_input_matches_the_types() {
+        let input = SkinnedVertex::vertex_input();
+        assert_eq!(input.validate(), Ok(()));
+        assert_eq!(
+            input.bindings[0].stride as usize,
+            size_of::<SkinnedVertex>()
+        );
+        assert_eq!(
+            input.bindings[1].stride as usize,
+            size_of::<SkinnedInstance>()
+        );
+        // the palette index after the two matrices
+        assert_eq!(input.attributes[12].location, 12);
+        assert_eq!(input.attributes[12].offset as usize, size_of::<[Mat4; 2]>());
+    }
+
+    #[test]
+    fn rest_pose_has_an_identity_palette() {
+        let skeleton = skeleton();
+        let mut palette = vec![];
+        skeleton.palette(&skeleton.rest_pose(), &mut palette);
+
+        for matrix in palette {
+            assert!(matrix.abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        }
+    }
+
+    #[test]
+    fn children_follow_their_parent() {
+        let skeleton = skeleton();
+        let mut pose = skeleton.rest_pose();
+        pose.locals[1].translation = Vec3::X;
+
+        let mut palette = vec![];
+        skeleton.palette(&pose, &mut palette);
+
+        // a vertex at the arm moves with the root
+        let vertex = palette[0].transform_point3(Vec3::Y);
+        assert!(vertex.abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-6));
+    }
+
+    #[test]
+    fn sample_keyframes() {
+        let mut translation = channel(
+            ChannelTarget::Translation,
+            vec![Vec4::ZERO, Vec4::new(2.0, 0.0, 0.0, 0.0)],
+        );
+
+        assert_eq!(translation.sample(-1.0), Vec4::ZERO);
+        assert_eq!(translation.sample(0.25).x, 0.5);
+        assert_eq!(translation.sample(3.0).x, 2.0);
+
+        translation.interpolation = Interpolation::Step;
+        assert_eq!(translation.sample(0.75), Vec4::ZERO);
+
+        let quarter_turn = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        let rotation = channel(
+            ChannelTarget::Rotation,
+            vec![Quat::IDENTITY.into(), quarter_turn.into()],
+        );
+        let half_way = Quat::from_vec4(rotation.sample(0.5));
+        assert!(half_way.abs_diff_eq(Quat::from_rotation_y(std::f32::consts::FRAC_PI_4), 1e-6));
+    }
+
+    #[test]
+    fn layers_blend_over_the_rest_pose() {
+        let clip = AnimationClip::new(
+            "slide",
+            vec![channel(
+                ChannelTarget::Translation,
+                vec![Vec4::ZERO, Vec4::new(4.0, 0.0, 0.0, 0.0)],
+            )],
+        );
+        assert_eq!(clip.duration, 1.0);
+
+        let mut animator = Animator::new(skeleton(), vec![clip]);
+        let mut layer = AnimationLayer::new(0);
+        layer.weight = 0.5;
+        animator.layers.push(layer);
+
+        animator.advance(1.5);
+        assert_eq!(animator.layers[0].time, 0.5);
+        // half way between the rest translation and the sampled one
+        assert_eq!(
+            animator.pose().locals[0].translation,
+            Vec3::new(1.0, 0.5, 0.0)
+        );
+
+        animator.layers[0].looping = false;
+        animator.advance(2.0);
+        assert_eq!(animator.layers[0].time, 1.0);
+    }
+}
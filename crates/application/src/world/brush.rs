@@ -0,0 +1,445 @@
+// editing the octrees with a brush under the cursor, like in a sculpting tool
+// ``brush_task`` casts a ray from the cursor through the octrees and applies ``World::brush`` where it hits
+//
+// every edit is a ``VoxelPatch``, the writes that were made and the ones that undo them,
+// in the same format the frame recorder and the network use, see ``VoxelWrite``
+// all edits while the mouse button is held are one patch, so a stroke is undone at once
+
+use math::{Camera, DVec3, I64Vec3};
+
+use super::{physics, rng::Rng, svo::Octree, World};
+use crate::{input::Input, replay::VoxelWrite};
+
+/// the half size of the box the octrees are drawn in, needs to match ``OCTREE_SCALE`` in ``shaders/octree.slang``
+pub const OCTREE_SCALE: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BrushShape {
+    #[default]
+    Sphere,
+    Cube,
+    /// fills the holes and removes the bumps of the surface,
+    /// a cell becomes solid if most of its neighbors are and empty otherwise
+    Smooth,
+}
+
+impl BrushShape {
+    pub const ALL: [Self; 3] = [Self::Sphere, Self::Cube, Self::Smooth];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sphere => "sphere",
+            Self::Cube => "cube",
+            Self::Smooth => "smooth",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Brush {
+    pub shape: BrushShape,
+    /// in octree space, the octrees go from -1 to 1
+    pub radius: f64,
+    /// from 0 to 1, the chance that a cell under the brush is changed every time it's applied,
+    /// lower values spray the color or smooth slowly
+    pub strength: f32,
+    /// the color index sphere and cube brushes write, 0 erases
+    pub color: u8,
+    /// the layer of the cells that are written, see ``Octree::write``
+    pub layer: usize,
+    /// decides which cells are changed with a strength below 1
+    rng: Rng,
+    /// the button was held in the last frame, so the edits are added to the last patch
+    stroking: bool,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            shape: BrushShape::default(),
+            radius: 0.1,
+            strength: 1.0,
+            color: 1,
+            layer: 6,
+            rng: Rng::default().fork("brush"),
+            stroking: false,
+        }
+    }
+}
+
+impl Brush {
+    /// the size of a cell on ``layer``
+    #[must_use]
+    pub fn cell_size(&self) -> f64 {
+        2.0 / (1u64 << self.layer) as f64
+    }
+
+    /// the centers of the cells under the brush, the cell containing ``center`` is always included
+    /// cells outside of the octree are left out
+    #[must_use]
+    pub fn cells(&self, center: DVec3) -> Vec<DVec3> {
+        let size = self.cell_size();
+        let cells = 1i64 << self.layer;
+        let cell = |pos: DVec3| ((pos + 1.0) / size).floor().as_i64vec3();
+
+        let min = cell(center - self.radius).max(I64Vec3::ZERO);
+        let max = cell(center + self.radius).min(I64Vec3::splat(cells - 1));
+        let center_cell = cell(center);
+
+        let mut positions = vec![];
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let index = I64Vec3::new(x, y, z);
+                    let pos = (index.as_dvec3() + 0.5) * size - 1.0;
+                    let offset = (pos - center).abs();
+
+                    let inside = match self.shape {
+                        BrushShape::Sphere | BrushShape::Smooth => offset.length() <= self.radius,
+                        BrushShape::Cube => offset.max_element() <= self.radius,
+                    };
+                    if inside || index == center_cell {
+                        positions.push(pos);
+                    }
+                }
+            }
+        }
+        positions
+    }
+
+    /// the cells the brush would change with full strength as (position, old color, new color)
+    /// only the ones that get a different color, the octree isn't changed
+    #[must_use]
+    pub fn changes(&self, octree: &Octree, center: DVec3) -> Vec<(DVec3, u8, u8)> {
+        let size = self.cell_size();
+
+        self.cells(center)
+            .into_iter()
+            .filter_map(|pos| {
+                let old = octree.sample(pos, self.layer);
+                let new = match self.shape {
+                    BrushShape::Sphere | BrushShape::Cube => self.color,
+                    BrushShape::Smooth => smoothed(octree, pos, size, self.layer, old),
+                };
+                (new != old).then_some((pos, old, new))
+            })
+            .collect()
+    }
+
+    /// the cells the brush changes as (position, color), a part of ``changes`` if the strength is below 1
+    /// the octree isn't changed, so the old colors can still be captured
+    pub fn writes(&mut self, octree: &Octree, center: DVec3) -> Vec<(DVec3, u8)> {
+        let mut changes = self.changes(octree, center);
+        if self.strength < 1.0 {
+            changes.retain(|_| self.rng.chance(self.strength));
+        }
+        changes
+            .into_iter()
+            .map(|(pos, _, new)| (pos, new))
+            .collect()
+    }
+
+    /// the octree and the center in octree space the brush is applied at, where the cursor ray hits the octrees
+    #[must_use]
+    pub fn target(
+        &self,
+        octrees: &[Octree],
+        camera: &Camera,
+        input: &Input,
+    ) -> Option<(usize, DVec3)> {
+        let ray = camera.screen_to_ray(input.cursor, input.viewport);
+        let origin = ray.origin.as_dvec3() / OCTREE_SCALE;
+        let (octree, pos, normal) = raycast(octrees, origin, ray.dir.as_dvec3())?;
+
+        // add in front of the surface and erase behind it
+        let offset = if self.color == 0 || self.shape == BrushShape::Smooth {
+            -0.5
+        } else {
+            0.5
+        };
+        Some((octree, pos + normal * self.cell_size() * offset))
+    }
+}
+
+/// the color of the cell after smoothing, by the majority of the 3x3x3 cells around it
+fn smoothed(octree: &Octree, pos: DVec3, size: f64, layer: usize, old: u8) -> u8 {
+    let mut counts = [0u8; 256];
+    let mut solid = 0;
+
+    for z in -1..=1 {
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let neighbor = pos + DVec3::new(f64::from(x), f64::from(y), f64::from(z)) * size;
+                if neighbor.abs().max_element() >= 1.0 {
+                    continue;
+                }
+
+                let color = octree.sample(neighbor, layer);
+                if color != 0 {
+                    counts[color as usize] += 1;
+                    solid += 1;
+                }
+            }
+        }
+    }
+
+    match (old, solid > 13) {
+        (0, true) => (1..=255)
+            .max_by_key(|&color| counts[color as usize])
+            .unwrap_or(0),
+        (_, false) => 0,
+        (old, true) => old,
+    }
+}
+
+/// the writes of one edit and the ones that undo it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VoxelPatch {
+    pub after: Vec<VoxelWrite>,
+    /// applied in order, they restore everything ``after`` changed
+    pub before: Vec<VoxelWrite>,
+}
+
+impl VoxelPatch {
+    /// add an edit that was made after this one
+    fn extend(&mut self, later: Self) {
+        self.after.extend(later.after);
+        // the later edit has to be undone first
+        let before = std::mem::replace(&mut self.before, later.before);
+        self.before.extend(before);
+    }
+}
+
+/// the patches that can be undone and redone, the newest ones last
+#[derive(Debug, Clone)]
+pub struct EditHistory {
+    undo: Vec<VoxelPatch>,
+    redo: Vec<VoxelPatch>,
+    /// the oldest patches are dropped once there are more
+    pub limit: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            undo: vec![],
+            redo: vec![],
+            limit: 100,
+        }
+    }
+}
+
+impl EditHistory {
+    /// a new edit, everything that was undone can't be redone anymore
+    /// ``merge`` adds it to the last patch instead, for the edits of one stroke
+    pub fn push(&mut self, patch: VoxelPatch, merge: bool) {
+        self.redo.clear();
+
+        match self.undo.last_mut() {
+            Some(last) if merge => last.extend(patch),
+            _ => self.undo.push(patch),
+        }
+
+        if self.undo.len() > self.limit {
+            let excess = self.undo.len() - self.limit;
+            self.undo.drain(..excess);
+        }
+    }
+
+    /// the patch whose ``before`` has to be applied, it can be redone afterwards
+    pub fn undo(&mut self) -> Option<&VoxelPatch> {
+        let patch = self.undo.pop()?;
+        self.redo.push(patch);
+        self.redo.last()
+    }
+
+    /// the patch whose ``after`` has to be applied, it can be undone again afterwards
+    pub fn redo(&mut self) -> Option<&VoxelPatch> {
+        let patch = self.redo.pop()?;
+        self.undo.push(patch);
+        self.undo.last()
+    }
+
+    #[must_use]
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    #[must_use]
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// apply ``World::brush`` under the cursor while the left mouse button is held, add it with ``Application::add_task``
+/// does nothing while ``World::brush`` is None
+pub fn brush_task(world: &mut World) {
+    let Some(brush) = &mut world.brush else {
+        return;
+    };
+    if !world.input.left_pressed() {
+        brush.stroking = false;
+        return;
+    }
+
+    let Some((octree, center)) = brush.target(&world.voxel_octrees, &world.camera, &world.input)
+    else {
+        return;
+    };
+
+    let merge = std::mem::replace(&mut brush.stroking, true);
+    world.apply_brush(octree, center, merge);
+}
+
+/// the closest hit of all octrees as (octree, position, normal)
+fn raycast(octrees: &[Octree], origin: DVec3, dir: DVec3) -> Option<(usize, DVec3, DVec3)> {
+    octrees
+        .iter()
+        .enumerate()
+        .filter_map(|(index, octree)| {
+            Some((index, physics::raycast(octree, origin, dir, f64::MAX)?))
+        })
+        .min_by(|(_, a), (_, b)| a.time.total_cmp(&b.time))
+        .map(|(index, hit)| (index, origin + dir * hit.time, hit.normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use math::dvec3;
+
+    use super::*;
+
+    fn write(color: u8) -> VoxelWrite {
+        VoxelWrite {
+            octree: 0,
+            pos: DVec3::ZERO,
+            color,
+            layer: 1,
+        }
+    }
+
+    #[test]
+    fn cells_follow_the_shape() {
+        let mut brush = Brush {
+            radius: 0.25,
+            layer: 3,
+            ..Default::default()
+        };
+        // the cells are 0.25 wide, the center lies on the corner of 8 of them
+        assert_eq!(brush.cells(DVec3::ZERO).len(), 8);
+
+        brush.shape = BrushShape::Cube;
+        brush.radius = 0.4;
+        assert_eq!(brush.cells(DVec3::ZERO).len(), 64);
+
+        // a tiny brush still hits its own cell, cells outside of the octree are left out
+        brush.radius = 0.01;
+        assert_eq!(
+            brush.cells(dvec3(0.9, 0.9, 0.9)),
+            [dvec3(0.875, 0.875, 0.875)]
+        );
+        brush.radius = 0.3;
+        assert_eq!(brush.cells(dvec3(0.9, 0.9, 0.9)).len(), 8);
+    }
+
+    #[test]
+    fn only_changes_are_written() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.1, 0.1, 0.1), 3, 3);
+
+        let mut brush = Brush {
+            radius: 0.01,
+            layer: 3,
+            color: 3,
+            ..Default::default()
+        };
+        assert!(brush.writes(&octree, dvec3(0.1, 0.1, 0.1)).is_empty());
+
+        brush.color = 0;
+        assert_eq!(
+            brush.writes(&octree, dvec3(0.1, 0.1, 0.1)),
+            [(dvec3(0.125, 0.125, 0.125), 0)]
+        );
+    }
+
+    #[test]
+    fn smoothing_removes_lone_voxels() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.1, 0.1, 0.1), 3, 3);
+
+        let mut brush = Brush {
+            shape: BrushShape::Smooth,
+            radius: 0.5,
+            layer: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            brush.writes(&octree, DVec3::ZERO),
+            [(dvec3(0.125, 0.125, 0.125), 0)]
+        );
+
+        // a full layer 1 octant with a hole in it, the cells on its edges would be removed too
+        octree.write(dvec3(0.5, 0.5, 0.5), 2, 1);
+        octree.write(dvec3(0.4, 0.4, 0.4), 0, 3);
+        brush.radius = 0.01;
+        assert_eq!(
+            brush.writes(&octree, dvec3(0.4, 0.4, 0.4)),
+            [(dvec3(0.375, 0.375, 0.375), 2)]
+        );
+    }
+
+    #[test]
+    fn history_undoes_the_newest_patch_first() {
+        let mut history = EditHistory::default();
+        let patch = |color| VoxelPatch {
+            after: vec![write(color)],
+            before: vec![write(0)],
+        };
+
+        history.push(patch(1), false);
+        history.push(patch(2), false);
+        assert_eq!(history.undo().unwrap().after, [write(2)]);
+        assert_eq!(history.redo_len(), 1);
+
+        // a new edit drops what was undone
+        history.push(patch(3), false);
+        assert_eq!(history.redo_len(), 0);
+        assert!(history.redo().is_none());
+
+        history.limit = 1;
+        history.push(patch(4), false);
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(history.undo().unwrap().after, [write(4)]);
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn strokes_are_one_patch() {
+        let mut history = EditHistory::default();
+        history.push(
+            VoxelPatch {
+                after: vec![write(1)],
+                before: vec![write(0)],
+            },
+            false,
+        );
+        history.push(
+            VoxelPatch {
+                after: vec![write(2)],
+                before: vec![write(1)],
+            },
+            true,
+        );
+
+        let patch = history.undo().unwrap();
+        assert_eq!(patch.after, [write(1), write(2)]);
+        assert_eq!(patch.before, [write(1), write(0)]);
+        assert_eq!(history.undo_len(), 0);
+    }
+}
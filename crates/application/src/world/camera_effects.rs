@@ -0,0 +1,244 @@
+// effects that make the camera feel like it belongs to the game: shake, smooth follow and fov kick
+// ``World::update`` advances them with the scaled time step and uploads the view of ``CameraEffects::apply``,
+// so the tasks keep setting ``World::camera`` like before and the effects are added on top
+//
+// the shake is trauma based: hits add trauma, which decays over time, and the shake grows with its square,
+// so small hits barely move the camera and big ones shake it hard
+// the shake follows a smooth noise instead of random numbers, so the same time always gives the same shake
+
+use math::{Camera, EulerRot, Projection, Quat, Vec3};
+
+use super::{
+    hierarchy::{Entities, Entity},
+    rng::splitmix,
+};
+
+/// move ``World::camera`` towards an entity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Follow {
+    pub target: Entity,
+    /// added to the position of the target, in world space
+    pub offset: Vec3,
+    /// the seconds it takes to cover about two thirds of the distance, 0 sticks to the target
+    pub smoothing: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraEffects {
+    /// 0 to 1, see ``add_trauma``
+    pub trauma: f32,
+    /// how much trauma is lost every second
+    pub trauma_decay: f32,
+    /// how far the view moves at full trauma, in world units
+    pub max_offset: f32,
+    /// how far the view turns around every axis at full trauma, in degrees
+    pub max_angle: f32,
+    /// how often the shake changes direction every second
+    pub frequency: f32,
+    pub follow: Option<Follow>,
+    /// the degrees added to the field of view, see ``kick_fov``
+    pub fov_kick: f32,
+    /// the seconds it takes for the kick to lose about two thirds
+    pub fov_recovery: f32,
+    /// the seconds the effects advanced, the shake is sampled at this time
+    time: f32,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            trauma_decay: 0.8,
+            max_offset: 0.1,
+            max_angle: 3.0,
+            frequency: 15.0,
+            follow: None,
+            fov_kick: 0.0,
+            fov_recovery: 0.15,
+            time: 0.0,
+        }
+    }
+}
+
+impl CameraEffects {
+    /// shake the camera, for hits and explosions, the trauma is clamped to 0..1
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// widen the field of view for a moment, for dashes and boosts, negative degrees zoom in
+    pub fn kick_fov(&mut self, degrees: f32) {
+        self.fov_kick += degrees;
+    }
+
+    /// how strong the shake is right now, 0 to 1
+    #[must_use]
+    pub fn shake(&self) -> f32 {
+        self.trauma * self.trauma
+    }
+
+    /// let the trauma and the fov kick decay and move the camera towards the followed entity
+    /// a despawned target is ignored
+    pub fn update(&mut self, dt: f32, camera: &mut Camera, entities: &Entities) {
+        self.time += dt;
+        self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+        self.fov_kick *= decay(dt, self.fov_recovery);
+
+        let Some(follow) = self
+            .follow
+            .filter(|follow| entities.contains(follow.target))
+        else {
+            return;
+        };
+        let target = entities.global_transform(follow.target).translation() + follow.offset;
+        let translation = &mut camera.transform.translation;
+        *translation = target.lerp(*translation, decay(dt, follow.smoothing));
+    }
+
+    /// the camera the frame is rendered with, ``camera`` with the shake and the fov kick added
+    #[must_use]
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        let mut camera = camera.clone();
+        let shake = self.shake();
+
+        if shake > 0.0 {
+            let t = self.time * self.frequency;
+            let channel = |index: u32| noise(index, t) * shake;
+
+            let transform = &mut camera.transform;
+            let offset = Vec3::new(channel(0), channel(1), channel(2)) * self.max_offset;
+            transform.translation += transform.rotation * offset;
+
+            let [yaw, pitch, roll] =
+                [3, 4, 5].map(|index| (channel(index) * self.max_angle).to_radians());
+            transform.rotation *= Quat::from_euler(EulerRot::YXZ, yaw, pitch, roll);
+        }
+
+        if let Projection::Perspective { fovy, .. } = &mut camera.projection {
+            *fovy = (*fovy + self.fov_kick).clamp(1.0, 179.0);
+        }
+        camera
+    }
+}
+
+/// the fraction of a value that is left after ``dt`` seconds, if it loses about two thirds every ``seconds``
+fn decay(dt: f32, seconds: f32) -> f32 {
+    if seconds <= 0.0 {
+        0.0
+    } else {
+        (-dt / seconds).exp()
+    }
+}
+
+/// smooth noise from -1 to 1, a random value at every whole ``t`` and smoothly blended in between
+fn noise(channel: u32, t: f32) -> f32 {
+    let value = |index: f32| {
+        let hash = splitmix((u64::from(channel) << 32) | u64::from(index as i32 as u32));
+        (hash >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+    };
+
+    let start = t.floor();
+    let blend = t - start;
+    let blend = blend * blend * (3.0 - 2.0 * blend);
+    value(start) + (value(start + 1.0) - value(start)) * blend
+}
+
+#[cfg(test)]
+mod tests {
+    use math::Transform;
+
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera::new(Transform::IDENTITY, 1.0, Projection::default())
+    }
+
+    #[test]
+    fn no_effects_keep_the_camera() {
+        let effects = CameraEffects::default();
+        let camera = camera();
+        let applied = effects.apply(&camera);
+        assert_eq!(applied.transform, camera.transform);
+        assert_eq!(applied.projection, camera.projection);
+    }
+
+    #[test]
+    fn trauma_shakes_and_decays() {
+        let mut effects = CameraEffects::default();
+        let mut camera = camera();
+        let entities = Entities::new();
+
+        effects.add_trauma(2.0);
+        assert_eq!(effects.trauma, 1.0);
+        effects.update(0.1, &mut camera, &entities);
+
+        let shaken = effects.apply(&camera);
+        let moved = shaken.transform.translation.length();
+        assert!(moved > 0.0 && moved <= effects.max_offset * 3f32.sqrt());
+        // the camera itself stays where the tasks put it
+        assert_eq!(camera.transform, Transform::IDENTITY);
+
+        effects.update(2.0, &mut camera, &entities);
+        assert_eq!(effects.trauma, 0.0);
+        assert_eq!(effects.apply(&camera).transform, camera.transform);
+    }
+
+    #[test]
+    fn fov_kick_recovers() {
+        let mut effects = CameraEffects::default();
+        let mut camera = camera();
+        effects.kick_fov(10.0);
+
+        let Projection::Perspective { fovy, .. } = effects.apply(&camera).projection else {
+            unreachable!()
+        };
+        assert_eq!(fovy, 80.0);
+
+        effects.update(1.0, &mut camera, &Entities::new());
+        assert!(effects.fov_kick < 0.1);
+    }
+
+    #[test]
+    fn follow_moves_towards_the_target() {
+        let mut entities = Entities::new();
+        let target = entities.spawn(Transform::from_xyz(10.0, 0.0, 0.0));
+        entities.propagate();
+
+        let mut effects = CameraEffects {
+            follow: Some(Follow {
+                target,
+                offset: Vec3::new(0.0, 2.0, 0.0),
+                smoothing: 0.5,
+            }),
+            ..Default::default()
+        };
+        let mut camera = camera();
+
+        effects.update(0.5, &mut camera, &entities);
+        let x = camera.transform.translation.x;
+        assert!(x > 6.0 && x < 7.0, "{x}");
+
+        effects.update(10.0, &mut camera, &entities);
+        assert!(camera
+            .transform
+            .translation
+            .abs_diff_eq(Vec3::new(10.0, 2.0, 0.0), 1e-3));
+
+        entities.despawn(target);
+        effects.update(1.0, &mut camera, &entities);
+        assert!(camera.transform.translation.x > 9.9);
+    }
+
+    #[test]
+    fn noise_is_smooth() {
+        for channel in 0..3 {
+            let mut last = noise(channel, 0.0);
+            for step in 1..100 {
+                let value = noise(channel, step as f32 * 0.01);
+                assert!((-1.0..=1.0).contains(&value));
+                assert!((value - last).abs() < 0.1);
+                last = value;
+            }
+        }
+    }
+}
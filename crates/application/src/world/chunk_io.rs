@@ -0,0 +1,473 @@
+// loads and saves the chunks of a ``VoxelWorld`` on the job system
+// every chunk is stored as a flattened octree in its own file
+// the jobs of a chunk depend on the one before, so a save is finished before the chunk is loaded again
+// the octrees are flattened and unflattened in the jobs, unless the chunk stays loaded while it's saved
+//
+// besides its own budget for the octrees, the streaming gives way when the gpu runs out of memory,
+// see ``ChunkIo::set_gpu_pressure``, distant chunks are dropped instead of letting allocations fail
+//
+// a chunk that fails to save is kept with its octree in ``ChunkIo::take_failed_saves``,
+// for unloaded chunks that is the only copy left
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+};
+
+use jobs::{JobHandle, JobSystem};
+use math::{DVec3, IVec3};
+
+use super::{
+    chunks::{Chunk, VoxelWorld},
+    svo::{FlatOctree, Octree},
+};
+
+pub struct ChunkIoConfig {
+    /// the directory the chunk files are stored in, it's created if it doesn't exist
+    pub dir: PathBuf,
+    /// when the octrees use more memory than this, no new chunks are loaded
+    /// and chunks outside of the load radius are unloaded, in bytes
+    pub memory_budget: usize,
+}
+
+impl ChunkIoConfig {
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            memory_budget: 512 * 1024 * 1024,
+        }
+    }
+}
+
+enum Response {
+    /// ``None`` if the chunk was never saved
+    Loaded(IVec3, io::Result<Option<Octree>>),
+    /// the octree that couldn't be written is sent back
+    Saved(IVec3, Result<(), (io::Error, FlatOctree)>),
+}
+
+/// a chunk that couldn't be written, see ``ChunkIo::take_failed_saves``
+#[derive(Debug)]
+pub struct FailedSave {
+    pub coord: IVec3,
+    pub error: io::Error,
+    /// what should have been written
+    pub octree: FlatOctree,
+}
+
+pub struct ChunkIo {
+    dir: PathBuf,
+    memory_budget: usize,
+    jobs: Arc<JobSystem>,
+    response_sender: Sender<Response>,
+    responses: Receiver<Response>,
+    /// the newest job of every chunk, the next one waits for it
+    last_jobs: HashMap<IVec3, JobHandle<()>>,
+    /// the chunks that are being loaded
+    loading: HashSet<IVec3>,
+    /// the amount of saves that haven't finished for every chunk
+    saving: HashMap<IVec3, usize>,
+    /// where the chunks are loaded around, from the last ``update``
+    center: DVec3,
+    /// the bytes the gpu is over its budget, from ``set_gpu_pressure``
+    gpu_pressure: usize,
+    failed_saves: Vec<FailedSave>,
+}
+
+impl ChunkIo {
+    /// # Errors
+    /// if the directory can't be created
+    pub fn new(config: ChunkIoConfig, jobs: Arc<JobSystem>) -> io::Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+
+        let (response_sender, responses) = mpsc::channel();
+
+        Ok(Self {
+            dir: config.dir,
+            memory_budget: config.memory_budget,
+            jobs,
+            response_sender,
+            responses,
+            last_jobs: HashMap::new(),
+            loading: HashSet::new(),
+            saving: HashMap::new(),
+            center: DVec3::ZERO,
+            gpu_pressure: 0,
+            failed_saves: vec![],
+        })
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// the amount of chunks that are being loaded
+    #[must_use]
+    pub fn pending_loads(&self) -> usize {
+        self.loading.len()
+    }
+
+    /// the saves that failed since the last call
+    /// chunks that are still loaded are dirty again, so they are saved again with the next ``flush`` or when they are unloaded,
+    /// unloaded chunks are only left in ``FailedSave::octree``
+    pub fn take_failed_saves(&mut self) -> Vec<FailedSave> {
+        std::mem::take(&mut self.failed_saves)
+    }
+
+    /// the bytes the device local memory of the gpu is over its budget, usually ``MemoryReport::over_budget``
+    /// should be set every frame before ``update``, as long as it isn't 0 no chunks are loaded
+    /// and chunks outside of the load radius are unloaded until the octrees shrank by that much
+    pub fn set_gpu_pressure(&mut self, over_budget: u64) {
+        self.gpu_pressure = usize::try_from(over_budget).unwrap_or(usize::MAX);
+    }
+
+    /// the memory the octrees can use this frame, less than the budget when the gpu is under pressure
+    fn budget(&self, world: &VoxelWorld) -> usize {
+        if self.gpu_pressure == 0 {
+            return self.memory_budget;
+        }
+        world
+            .memory()
+            .saturating_sub(self.gpu_pressure)
+            .min(self.memory_budget)
+    }
+
+    /// stream the chunks around the position, should be called every frame
+    /// unloaded chunks are saved if they are dirty, finished loads are added to the world
+    /// the octrees of loaded chunks haven't been uploaded yet, so an ``OctreeLayout`` created for them uploads everything
+    pub fn update(&mut self, world: &mut VoxelWorld, pos: DVec3) {
+        self.center = pos;
+        self.receive(world);
+        self.last_jobs.retain(|_, job| !job.is_done());
+
+        let budget = self.budget(world);
+        let update = world.update_around(pos);
+
+        for (coord, chunk) in update.unloaded {
+            self.save_unloaded(coord, chunk);
+        }
+
+        if self.gpu_pressure == 0 && world.memory() < budget {
+            for coord in update.load {
+                if self.loading.insert(coord) {
+                    let dir = self.dir.clone();
+                    self.send(coord, move || {
+                        let octree =
+                            read(&dir, coord).map(|flat| flat.map(|flat| flat.unflatten()));
+                        Response::Loaded(coord, octree)
+                    });
+                }
+            }
+        }
+
+        self.enforce_budget(world, budget);
+    }
+
+    /// save the chunk if it's dirty
+    /// the octree is flattened right away because the chunk stays in the world
+    pub fn save(&mut self, coord: IVec3, chunk: &mut Chunk) {
+        if !chunk.dirty {
+            return;
+        }
+
+        chunk.dirty = false;
+        let flat = chunk.octree.flatten();
+        let dir = self.dir.clone();
+        *self.saving.entry(coord).or_default() += 1;
+        self.send(coord, move || {
+            Response::Saved(coord, write(&dir, coord, &flat).map_err(|err| (err, flat)))
+        });
+    }
+
+    /// save a chunk that was removed from the world if it's dirty, it's flattened in the job
+    fn save_unloaded(&mut self, coord: IVec3, chunk: Chunk) {
+        if !chunk.dirty {
+            return;
+        }
+
+        let dir = self.dir.clone();
+        *self.saving.entry(coord).or_default() += 1;
+        self.send(coord, move || {
+            let flat = chunk.octree.flatten();
+            Response::Saved(coord, write(&dir, coord, &flat).map_err(|err| (err, flat)))
+        });
+    }
+
+    /// save all dirty chunks and wait until everything is written
+    /// should be called before shutting down
+    pub fn flush(&mut self, world: &mut VoxelWorld) {
+        let dirty: Vec<IVec3> = world
+            .chunks()
+            .filter(|(_, chunk)| chunk.dirty)
+            .map(|(coord, _)| coord)
+            .collect();
+
+        for coord in dirty {
+            self.save(coord, world.chunk_mut(coord).unwrap());
+        }
+
+        while !self.saving.is_empty() || !self.loading.is_empty() {
+            let Ok(response) = self.responses.recv() else {
+                break;
+            };
+            self.handle(world, response);
+        }
+    }
+
+    /// run the job after the other jobs of the chunk
+    fn send(&mut self, coord: IVec3, job: impl FnOnce() -> Response + Send + 'static) {
+        let dependencies: Vec<_> = self
+            .last_jobs
+            .get(&coord)
+            .map(JobHandle::id)
+            .into_iter()
+            .collect();
+        let responses = self.response_sender.clone();
+
+        let handle = self.jobs.spawn_after(&dependencies, move || {
+            // the responses are dropped if the chunk io is gone by then
+            let _ = responses.send(job());
+        });
+        self.last_jobs.insert(coord, handle);
+    }
+
+    fn receive(&mut self, world: &mut VoxelWorld) {
+        while let Ok(response) = self.responses.try_recv() {
+            self.handle(world, response);
+        }
+    }
+
+    fn handle(&mut self, world: &mut VoxelWorld, response: Response) {
+        match response {
+            Response::Loaded(coord, result) => {
+                self.loading.remove(&coord);
+
+                // the camera might have moved away in the meantime
+                let center = world.chunk_coord(self.center);
+                let radius = world.unload_radius as i32;
+                if (coord - center).length_squared() > radius * radius {
+                    return;
+                }
+
+                let octree = match result {
+                    Ok(Some(octree)) => octree,
+                    Ok(None) => Octree::new(),
+                    Err(err) => {
+                        tracing::error!("failed to load chunk {coord}: {err}");
+                        return;
+                    }
+                };
+
+                if world.chunk(coord).is_none() {
+                    world.insert(coord, Chunk::new(octree));
+                }
+            }
+            Response::Saved(coord, result) => {
+                if let Some(count) = self.saving.get_mut(&coord) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.saving.remove(&coord);
+                    }
+                }
+
+                if let Err((error, octree)) = result {
+                    tracing::error!("failed to save chunk {coord}: {error}");
+                    if let Some(chunk) = world.chunk_mut(coord) {
+                        chunk.dirty = true;
+                    }
+                    self.failed_saves.push(FailedSave {
+                        coord,
+                        error,
+                        octree,
+                    });
+                }
+            }
+        }
+    }
+
+    /// unload the furthest chunks outside of the load radius until the world fits in the budget
+    fn enforce_budget(&mut self, world: &mut VoxelWorld, budget: usize) {
+        if world.memory() <= budget {
+            return;
+        }
+
+        let center = world.chunk_coord(self.center);
+        let load_radius = (world.load_radius * world.load_radius) as i32;
+
+        let mut far: Vec<IVec3> = world
+            .chunks()
+            .map(|(coord, _)| coord)
+            .filter(|coord| (*coord - center).length_squared() > load_radius)
+            .collect();
+        far.sort_by_key(|coord| std::cmp::Reverse((*coord - center).length_squared()));
+
+        for coord in far {
+            if world.memory() <= budget {
+                break;
+            }
+            if let Some(chunk) = world.remove(coord) {
+                self.save_unloaded(coord, chunk);
+            }
+        }
+    }
+}
+
+impl Drop for ChunkIo {
+    /// the last job of a chunk waits for the ones before it, so this finishes every save
+    fn drop(&mut self) {
+        for (_, job) in self.last_jobs.drain() {
+            job.wait();
+        }
+    }
+}
+
+fn chunk_path(dir: &Path, coord: IVec3) -> PathBuf {
+    dir.join(format!("{}_{}_{}.octree", coord.x, coord.y, coord.z))
+}
+
+fn read(dir: &Path, coord: IVec3) -> io::Result<Option<FlatOctree>> {
+    let bytes = match std::fs::read(chunk_path(dir, coord)) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let flat = FlatOctree::from_bytes(&bytes);
+    if !flat.is_valid() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the file isn't a valid octree",
+        ));
+    }
+
+    Ok(Some(flat))
+}
+
+/// write to a temporary file first, so a crash doesn't leave a broken chunk behind
+fn write(dir: &Path, coord: IVec3, flat: &FlatOctree) -> io::Result<()> {
+    let path = chunk_path(dir, coord);
+    let tmp = path.with_extension("tmp");
+
+    std::fs::write(&tmp, flat.as_bytes())?;
+    std::fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{dvec3, ivec3};
+
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("puddle_chunk_io_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut world = VoxelWorld::new(1.0, 0, 1);
+        let jobs = Arc::new(JobSystem::new(2));
+        let mut io = ChunkIo::new(ChunkIoConfig::new(&dir), jobs).unwrap();
+
+        io.update(&mut world, dvec3(0.5, 0.5, 0.5));
+        io.flush(&mut world);
+        assert_eq!(world.len(), 1);
+
+        assert!(world.write(dvec3(0.3, 0.3, 0.3), 9, 4));
+        io.flush(&mut world);
+        assert!(chunk_path(&dir, ivec3(0, 0, 0)).exists());
+
+        // move away, so the chunk is unloaded, and back again
+        io.update(&mut world, dvec3(5.5, 0.5, 0.5));
+        io.flush(&mut world);
+        assert!(world.chunk(ivec3(0, 0, 0)).is_none());
+
+        io.update(&mut world, dvec3(0.5, 0.5, 0.5));
+        io.flush(&mut world);
+        assert_eq!(world.sample(dvec3(0.3, 0.3, 0.3), 4), 9);
+        assert!(!world.chunk(ivec3(0, 0, 0)).unwrap().dirty);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evict_under_gpu_pressure() {
+        let dir =
+            std::env::temp_dir().join(format!("puddle_chunk_io_pressure_{}", std::process::id()));
+
+        let mut world = VoxelWorld::new(1.0, 0, 3);
+        let jobs = Arc::new(JobSystem::new(2));
+        let mut io = ChunkIo::new(ChunkIoConfig::new(&dir), jobs).unwrap();
+
+        for x in [1, 2, 3] {
+            world.insert(ivec3(x, 0, 0), Chunk::new(Octree::new()));
+        }
+        let chunk_memory = world.memory() / 3;
+
+        // the chunk at the center isn't loaded while the gpu is over its budget
+        io.set_gpu_pressure(chunk_memory as u64);
+        io.update(&mut world, dvec3(0.5, 0.5, 0.5));
+        assert_eq!(io.pending_loads(), 0);
+        assert!(world.chunk(ivec3(3, 0, 0)).is_none());
+        assert!(world.chunk(ivec3(2, 0, 0)).is_some());
+
+        io.set_gpu_pressure(0);
+        io.update(&mut world, dvec3(0.5, 0.5, 0.5));
+        io.flush(&mut world);
+        assert_eq!(world.len(), 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_saves_are_kept() {
+        let dir =
+            std::env::temp_dir().join(format!("puddle_chunk_io_failed_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut world = VoxelWorld::new(1.0, 0, 1);
+        let jobs = Arc::new(JobSystem::new(2));
+        let mut io = ChunkIo::new(ChunkIoConfig::new(&dir), jobs).unwrap();
+        io.update(&mut world, dvec3(0.5, 0.5, 0.5));
+        io.flush(&mut world);
+
+        // the temporary file can't be written over a directory
+        let coord = ivec3(0, 0, 0);
+        std::fs::create_dir_all(chunk_path(&dir, coord).with_extension("tmp")).unwrap();
+        assert!(world.write(dvec3(0.3, 0.3, 0.3), 9, 4));
+        io.flush(&mut world);
+
+        let failed = io.take_failed_saves();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].coord, coord);
+        assert!(failed[0].octree.is_valid());
+        assert!(world.chunk(coord).unwrap().dirty);
+        assert!(io.take_failed_saves().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reject_invalid_files() {
+        let dir =
+            std::env::temp_dir().join(format!("puddle_chunk_io_invalid_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // a node pointing to itself
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.5, 0.5, 0.5), 1, 2);
+        let mut bytes = octree.flatten().as_bytes().to_vec();
+        bytes[8..12].copy_from_slice(&(1u32 << 24).to_le_bytes());
+        std::fs::write(chunk_path(&dir, ivec3(1, 2, 3)), bytes).unwrap();
+
+        assert!(read(&dir, ivec3(1, 2, 3)).is_err());
+        assert!(read(&dir, ivec3(0, 0, 0)).unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
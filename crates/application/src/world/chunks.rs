@@ -0,0 +1,281 @@
+// a world made out of many octrees
+// every chunk is an octree covering a cube of ``chunk_size`` world units,
+// chunk (0, 0, 0) goes from the origin to ``chunk_size`` on every axis
+
+use std::collections::HashMap;
+
+use math::{DVec3, I64Vec3, IVec3};
+
+use super::svo::Octree;
+
+pub struct Chunk {
+    pub octree: Octree,
+    /// the octree changed since it was loaded, so it needs to be saved before unloading
+    pub dirty: bool,
+}
+
+impl Chunk {
+    #[must_use]
+    pub fn new(octree: Octree) -> Self {
+        Self {
+            octree,
+            dirty: false,
+        }
+    }
+}
+
+/// what ``VoxelWorld::update_around`` wants to change
+#[derive(Default)]
+pub struct ChunkUpdate {
+    /// chunks that should be loaded, the closest ones first
+    pub load: Vec<IVec3>,
+    /// chunks that were removed because they are too far away
+    pub unloaded: Vec<(IVec3, Chunk)>,
+}
+
+/// a sparse map of chunks
+pub struct VoxelWorld {
+    chunks: HashMap<IVec3, Chunk>,
+    /// the length of a chunk edge in world units
+    chunk_size: f64,
+    /// chunks closer than this to the camera are loaded, in chunks
+    pub load_radius: u32,
+    /// chunks further away than this are unloaded, in chunks
+    /// it's bigger than ``load_radius``, so chunks at the border don't load and unload all the time
+    pub unload_radius: u32,
+}
+
+impl VoxelWorld {
+    /// # Panics
+    /// if ``unload_radius`` is smaller than ``load_radius``
+    #[must_use]
+    pub fn new(chunk_size: f64, load_radius: u32, unload_radius: u32) -> Self {
+        assert!(unload_radius >= load_radius);
+
+        Self {
+            chunks: HashMap::new(),
+            chunk_size,
+            load_radius,
+            unload_radius,
+        }
+    }
+
+    #[must_use]
+    pub fn chunk_size(&self) -> f64 {
+        self.chunk_size
+    }
+
+    /// the chunk that contains the position
+    #[must_use]
+    pub fn chunk_coord(&self, pos: DVec3) -> IVec3 {
+        (pos / self.chunk_size).floor().as_ivec3()
+    }
+
+    /// the chunk containing the position and the position in octree space of that chunk
+    #[must_use]
+    pub fn to_local(&self, pos: DVec3) -> (IVec3, DVec3) {
+        let coord = self.chunk_coord(pos);
+        (coord, self.to_chunk(coord, pos))
+    }
+
+    /// a world space position in octree space of the chunk, it can be outside of the chunk
+    #[must_use]
+    pub fn to_chunk(&self, coord: IVec3, pos: DVec3) -> DVec3 {
+        (pos / self.chunk_size - coord.as_dvec3()) * 2.0 - 1.0
+    }
+
+    /// convert a position in octree space of a chunk to world space
+    #[must_use]
+    pub fn to_world(&self, coord: IVec3, local: DVec3) -> DVec3 {
+        (coord.as_dvec3() + (local + 1.0) * 0.5) * self.chunk_size
+    }
+
+    #[must_use]
+    pub fn chunk(&self, coord: IVec3) -> Option<&Chunk> {
+        self.chunks.get(&coord)
+    }
+
+    /// marks the chunk as dirty
+    pub fn chunk_mut(&mut self, coord: IVec3) -> Option<&mut Chunk> {
+        let chunk = self.chunks.get_mut(&coord)?;
+        chunk.dirty = true;
+        Some(chunk)
+    }
+
+    /// returns the chunk that was there before
+    pub fn insert(&mut self, coord: IVec3, chunk: Chunk) -> Option<Chunk> {
+        self.chunks.insert(coord, chunk)
+    }
+
+    pub fn remove(&mut self, coord: IVec3) -> Option<Chunk> {
+        self.chunks.remove(&coord)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (IVec3, &Chunk)> {
+        self.chunks.iter().map(|(coord, chunk)| (*coord, chunk))
+    }
+
+    /// the amount of loaded chunks
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// the memory used by the octrees of all chunks in bytes
+    #[must_use]
+    pub fn memory(&self) -> usize {
+        self.chunks
+            .values()
+            .map(|chunk| chunk.octree.memory())
+            .sum()
+    }
+
+    /// sample the world at a position, see ``Octree::sample``
+    /// positions in chunks that aren't loaded are empty
+    #[must_use]
+    pub fn sample(&self, pos: DVec3, layer: usize) -> u8 {
+        let (coord, local) = self.to_local(pos);
+        self.chunk(coord)
+            .map_or(0, |chunk| chunk.octree.sample(local, layer))
+    }
+
+    /// write to the chunk containing the position, see ``Octree::write``
+    /// returns false if the chunk isn't loaded
+    pub fn write(&mut self, pos: DVec3, color: u8, layer: usize) -> bool {
+        let (coord, local) = self.to_local(pos);
+        let Some(chunk) = self.chunk_mut(coord) else {
+            return false;
+        };
+        chunk.octree.write(local, color, layer);
+        true
+    }
+
+    /// sample a cell on a grid that goes through all chunks
+    /// every chunk is ``2^layer`` cells wide, cell (0, 0, 0) is the first cell of chunk (0, 0, 0)
+    ///
+    /// used to look at the neighbors of a cell without caring about chunk borders,
+    /// if the neighbor chunk has less detail, its coarser voxel is returned, so there are no cracks
+    #[must_use]
+    pub fn sample_cell(&self, cell: I64Vec3, layer: usize) -> u8 {
+        let cells = 1i64 << layer;
+        let coord = cell.div_euclid(I64Vec3::splat(cells)).as_ivec3();
+        let local = cell.rem_euclid(I64Vec3::splat(cells)).as_dvec3();
+
+        let pos = (local + 0.5) / cells as f64 * 2.0 - 1.0;
+        self.chunk(coord)
+            .map_or(0, |chunk| chunk.octree.sample(pos, layer))
+    }
+
+    /// the cell of ``sample_cell`` containing the position
+    #[must_use]
+    pub fn cell(&self, pos: DVec3, layer: usize) -> I64Vec3 {
+        let cells = (1i64 << layer) as f64;
+        (pos / self.chunk_size * cells).floor().as_i64vec3()
+    }
+
+    /// remove the chunks that are too far away from the position
+    /// and find the ones that should be loaded
+    /// the caller loads the chunks and saves the dirty ones that were unloaded
+    pub fn update_around(&mut self, pos: DVec3) -> ChunkUpdate {
+        let center = self.chunk_coord(pos);
+        let distance = |coord: IVec3| (coord - center).length_squared();
+
+        let unload = (self.unload_radius * self.unload_radius) as i32;
+        let far: Vec<IVec3> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|coord| distance(*coord) > unload)
+            .collect();
+
+        let unloaded = far
+            .into_iter()
+            .filter_map(|coord| Some((coord, self.chunks.remove(&coord)?)))
+            .collect();
+
+        let radius = self.load_radius as i32;
+        let mut load = vec![];
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                for z in -radius..=radius {
+                    let coord = center + IVec3::new(x, y, z);
+                    if distance(coord) <= radius * radius && !self.chunks.contains_key(&coord) {
+                        load.push(coord);
+                    }
+                }
+            }
+        }
+        load.sort_by_key(|coord| distance(*coord));
+
+        ChunkUpdate { load, unloaded }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{dvec3, i64vec3, ivec3};
+
+    use super::super::svo::OctreeNode;
+    use super::*;
+
+    #[test]
+    fn coords() {
+        let world = VoxelWorld::new(16.0, 1, 2);
+
+        assert_eq!(world.chunk_coord(dvec3(1.0, -1.0, 17.0)), ivec3(0, -1, 1));
+        let (coord, local) = world.to_local(dvec3(4.0, -4.0, 24.0));
+        assert_eq!(coord, ivec3(0, -1, 1));
+        assert_eq!(local, dvec3(-0.5, 0.5, 0.0));
+        assert_eq!(world.to_world(coord, local), dvec3(4.0, -4.0, 24.0));
+    }
+
+    #[test]
+    fn sample_across_chunks() {
+        let mut world = VoxelWorld::new(1.0, 1, 2);
+        world.insert(ivec3(0, 0, 0), Chunk::new(Octree::new()));
+
+        // the neighbor only has voxels on the first layer
+        let mut coarse = Octree::new();
+        for pos in OctreeNode::NODE_POS {
+            coarse.write(pos * 0.5, 7, 1);
+        }
+        world.insert(ivec3(-1, 0, 0), Chunk::new(coarse));
+
+        assert!(world.write(dvec3(0.1, 0.1, 0.1), 3, 3));
+        assert!(world.chunk(ivec3(0, 0, 0)).unwrap().dirty);
+
+        let cell = world.cell(dvec3(0.1, 0.1, 0.1), 3);
+        assert_eq!(cell, i64vec3(0, 0, 0));
+        assert_eq!(world.sample_cell(cell, 3), 3);
+        assert_eq!(world.sample_cell(cell + i64vec3(-1, 0, 0), 3), 7);
+        assert_eq!(world.sample_cell(cell + i64vec3(0, -1, 0), 3), 0);
+        assert_eq!(world.sample(dvec3(-0.9, 0.1, 0.1), 3), 7);
+    }
+
+    #[test]
+    fn load_with_hysteresis() {
+        let mut world = VoxelWorld::new(1.0, 1, 2);
+
+        let update = world.update_around(dvec3(0.5, 0.5, 0.5));
+        assert_eq!(update.load.len(), 7);
+        assert_eq!(update.load[0], ivec3(0, 0, 0));
+
+        for coord in update.load {
+            world.insert(coord, Chunk::new(Octree::new()));
+        }
+
+        // moving one chunk doesn't unload anything yet
+        let update = world.update_around(dvec3(1.5, 0.5, 0.5));
+        assert!(update.unloaded.is_empty());
+        assert_eq!(update.load.len(), 5);
+
+        let update = world.update_around(dvec3(4.5, 0.5, 0.5));
+        assert_eq!(update.unloaded.len(), 7);
+        assert!(world.is_empty());
+    }
+}
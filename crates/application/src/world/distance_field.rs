@@ -0,0 +1,174 @@
+// a coarse distance field of every octree, so the raymarching can jump over empty space
+// before it walks the octree, see ``trace_ray`` in ``shaders/octree.slang``
+//
+// the octree is split in to a grid of ``DISTANCE_FIELD_RESOLUTION`` cells, every cell stores how many cells
+// around it are empty in every direction, so a ray can move that far from any point inside the cell
+// without hitting anything, cells next to a voxel store 0 and the octree is walked from there
+//
+// the field is baked on the cpu and stored in a small 3d texture, ``World::sync_renderer`` bakes it again
+// once an octree that changed was uploaded
+
+use std::collections::VecDeque;
+
+use ash::vk;
+use rendering::{
+    assets::texture::{TextureError, TextureHandle},
+    handler::{sampler::SamplerDesc, RenderHandler},
+    vulkan::Texture,
+};
+
+use super::svo::{Octree, Visit};
+
+/// the cells of the grid along every axis, needs to match ``DISTANCE_FIELD_RESOLUTION`` in ``shaders/octree.slang``
+pub const DISTANCE_FIELD_RESOLUTION: u32 = 32;
+
+/// the index passed to the shader if an octree has no distance field yet
+pub const NO_DISTANCE_FIELD: u32 = u32::MAX;
+
+/// the empty cells around every cell, x changing fastest, then y
+/// the octree is in the space from -1 to 1
+#[must_use]
+pub fn bake(octree: &Octree) -> Vec<u8> {
+    let resolution = DISTANCE_FIELD_RESOLUTION as usize;
+    let cell_size = 2.0 / resolution as f64;
+    let index = |[x, y, z]: [usize; 3]| x + (y + z * resolution) * resolution;
+
+    let mut distances = vec![u32::MAX; resolution * resolution * resolution];
+    let mut queue = VecDeque::new();
+
+    octree.visit(|octant| {
+        // something inside is filled if it has children, looking closer doesn't change the cells it covers
+        if !octant.is_leaf() && octant.size > cell_size {
+            return Visit::Continue;
+        }
+
+        let half = octant.size * 0.5;
+        let cell = |pos: f64| ((pos + 1.0) / cell_size).clamp(0.0, resolution as f64);
+        let min = octant
+            .center
+            .to_array()
+            .map(|pos| cell(pos - half).floor() as usize);
+        let max = octant
+            .center
+            .to_array()
+            .map(|pos| cell(pos + half).ceil() as usize);
+
+        for z in min[2]..max[2] {
+            for y in min[1]..max[1] {
+                for x in min[0]..max[0] {
+                    if std::mem::replace(&mut distances[index([x, y, z])], 0) != 0 {
+                        queue.push_back([x, y, z]);
+                    }
+                }
+            }
+        }
+        Visit::SkipChildren
+    });
+
+    // the chessboard distance to the closest filled cell, every neighbor including the diagonal ones is 1 away
+    while let Some(cell) = queue.pop_front() {
+        let distance = distances[index(cell)] + 1;
+
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let neighbor = [
+                        cell[0].checked_add_signed(dx),
+                        cell[1].checked_add_signed(dy),
+                        cell[2].checked_add_signed(dz),
+                    ];
+                    let [Some(x), Some(y), Some(z)] = neighbor else {
+                        continue;
+                    };
+                    if x >= resolution || y >= resolution || z >= resolution {
+                        continue;
+                    }
+
+                    let neighbor = &mut distances[index([x, y, z])];
+                    if distance < *neighbor {
+                        *neighbor = distance;
+                        queue.push_back([x, y, z]);
+                    }
+                }
+            }
+        }
+    }
+
+    // the cells up to the one before the filled one are empty, an empty octree can be skipped completely
+    distances
+        .into_iter()
+        .map(|distance| distance.saturating_sub(1).min(255) as u8)
+        .collect()
+}
+
+/// upload a baked field to a new texture, the shader reads it with ``GetTexture3D``
+/// # Errors
+/// if there is no space to allocate the texture or no free texture slot
+pub fn create_texture(
+    renderer: &mut RenderHandler,
+    field: &[u8],
+) -> Result<TextureHandle, TextureError> {
+    let texture = Texture::new_3d(
+        renderer.device.clone(),
+        [DISTANCE_FIELD_RESOLUTION; 3],
+        vk::Format::R8_UNORM,
+        vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+    )?;
+    texture.upload(&[field])?;
+
+    // the cells are read with ``Load``, the sampler only has to exist
+    let sampler = renderer.get_sampler(SamplerDesc {
+        address_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        ..SamplerDesc::NEAREST
+    })?;
+
+    renderer
+        .add_texture(texture, sampler)
+        .ok_or(TextureError::NoFreeSlot)
+}
+
+#[cfg(test)]
+mod tests {
+    use math::DVec3;
+
+    use super::*;
+
+    fn at(field: &[u8], [x, y, z]: [usize; 3]) -> u8 {
+        let resolution = DISTANCE_FIELD_RESOLUTION as usize;
+        field[x + (y + z * resolution) * resolution]
+    }
+
+    #[test]
+    fn empty_octrees_can_be_skipped() {
+        let field = bake(&Octree::new());
+        assert!(field.iter().all(|&distance| distance == 255));
+    }
+
+    #[test]
+    fn distance_grows_away_from_voxels() {
+        let mut octree = Octree::new();
+        // a voxel on layer 6 is 2 / 64 wide, half a cell, in the cell at index 16 on every axis
+        octree.write(DVec3::splat(0.01), 3, 6);
+
+        let field = bake(&octree);
+        assert_eq!(at(&field, [16, 16, 16]), 0);
+        // the neighbors touch the filled cell
+        assert_eq!(at(&field, [15, 17, 16]), 0);
+        assert_eq!(at(&field, [18, 16, 16]), 1);
+        assert_eq!(at(&field, [20, 12, 16]), 3);
+        assert_eq!(at(&field, [0, 0, 0]), 15);
+    }
+
+    #[test]
+    fn big_leaves_fill_every_cell_they_cover() {
+        let mut octree = Octree::new();
+        // the octant from 0 to 1 on every axis
+        octree.write(DVec3::splat(0.5), 7, 1);
+
+        let field = bake(&octree);
+        assert_eq!(at(&field, [16, 16, 16]), 0);
+        assert_eq!(at(&field, [31, 31, 31]), 0);
+        assert_eq!(at(&field, [15, 15, 15]), 0);
+        assert_eq!(at(&field, [0, 31, 31]), 15);
+    }
+}
@@ -0,0 +1,104 @@
+// turns the fluid colors of the octrees in to the grid of the volumetric pass, see ``rendering::handler::volumetric``
+// a fluid is a palette color that is drawn by marching through it instead of as a solid voxel,
+// the grid is rebuilt by ``World::sync_renderer`` when an octree, the palette or the fluids changed
+//
+// fluids should use a transparent palette color, so their faces don't end the march at the surface
+
+use jobs::JobSystem;
+use math::DVec3;
+use rendering::handler::volumetric::{FluidVolume, FLUID_RESOLUTION};
+
+use super::{palette::VoxelPalette, svo::Octree};
+
+/// a palette color that is rendered as a fluid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FluidType {
+    pub color: u8,
+    /// from 0 to 1, scales how much light the fluid absorbs and scatters
+    pub density: f32,
+}
+
+/// the rgba8 color of every cell with the density of its fluid in alpha, one z slice per job
+/// cells that aren't a fluid stay empty, the octrees are in the space from -1 to 1, like for the global illumination
+#[must_use]
+pub fn voxelize(
+    jobs: &JobSystem,
+    octrees: &[Octree],
+    palette: &VoxelPalette,
+    fluids: &[FluidType],
+    volume: FluidVolume,
+) -> Vec<[u8; 4]> {
+    let resolution = FLUID_RESOLUTION as usize;
+    let mut voxels = vec![[0; 4]; resolution * resolution * resolution];
+
+    let mut densities = [0; 256];
+    for fluid in fluids {
+        densities[usize::from(fluid.color)] = (fluid.density.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+
+    let cell_size = f64::from(volume.cell_size);
+    let origin = DVec3::from_array(volume.origin.map(f64::from));
+    // a cell on layer n of an octree is 2 / 2^n wide
+    let layer = (2.0 / cell_size).log2().round().max(1.0) as usize;
+
+    jobs.scope(|scope| {
+        for (z, slice) in voxels.chunks_mut(resolution * resolution).enumerate() {
+            let densities = &densities;
+            scope.spawn(move || {
+                for (i, voxel) in slice.iter_mut().enumerate() {
+                    let cell =
+                        DVec3::new((i % resolution) as f64, (i / resolution) as f64, z as f64);
+                    let pos = origin + (cell + 0.5) * cell_size;
+                    if pos.abs().max_element() >= 1.0 {
+                        continue;
+                    }
+
+                    let color = octrees
+                        .iter()
+                        .map(|octree| octree.sample(pos, layer))
+                        .find(|&color| color != 0);
+
+                    if let Some(color) = color.filter(|&color| densities[usize::from(color)] != 0) {
+                        let [r, g, b, _] = palette.colors[usize::from(color)];
+                        *voxel = [r, g, b, densities[usize::from(color)]];
+                    }
+                }
+            });
+        }
+    });
+
+    voxels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_fluid_colors_are_voxelized() {
+        let mut octree = Octree::new();
+        // a layer 2 cell covers 0.5 to 1 on every axis
+        octree.write(DVec3::splat(0.75), 7, 2);
+        octree.write(DVec3::splat(-0.75), 9, 2);
+
+        let jobs = JobSystem::new(2);
+        let palette = VoxelPalette::grayscale();
+        let fluids = [FluidType {
+            color: 7,
+            density: 0.5,
+        }];
+        let voxels = voxelize(&jobs, &[octree], &palette, &fluids, FluidVolume::default());
+
+        let resolution = FLUID_RESOLUTION as usize;
+        let index = |[x, y, z]: [usize; 3]| x + (y + z * resolution) * resolution;
+
+        let last = resolution - 1;
+        assert_eq!(voxels[index([last, last, last])], [7, 7, 7, 128]);
+        // the solid voxel isn't a fluid
+        assert_eq!(voxels[index([0, 0, 0])], [0; 4]);
+        assert_eq!(voxels.iter().filter(|voxel| voxel[3] != 0).count(), {
+            let quarter = resolution / 4;
+            quarter * quarter * quarter
+        });
+    }
+}
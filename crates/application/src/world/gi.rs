@@ -0,0 +1,86 @@
+// turns the octrees in to the low resolution voxel grid of the experimental global illumination
+// see ``rendering::handler::gi``, the grid is rebuilt by ``World::sync_renderer`` when an octree changed
+//
+// every cell samples the octrees at the layer whose cells have about the size of a grid cell,
+// so merged nodes give the color they were last written with
+
+use jobs::JobSystem;
+use math::DVec3;
+use rendering::handler::gi::{GiVolume, GI_RESOLUTION};
+
+use super::{palette::VoxelPalette, svo::Octree};
+
+/// the rgba8 albedo of every cell with 255 alpha where a voxel is, one z slice per job
+/// the octrees are in the space from -1 to 1, like for the physics, cells outside of it stay empty
+#[must_use]
+pub fn voxelize(
+    jobs: &JobSystem,
+    octrees: &[Octree],
+    palette: &VoxelPalette,
+    volume: GiVolume,
+) -> Vec<[u8; 4]> {
+    let resolution = GI_RESOLUTION as usize;
+    let mut voxels = vec![[0; 4]; resolution * resolution * resolution];
+
+    let cell_size = f64::from(volume.cell_size);
+    let origin = DVec3::from_array(volume.origin.map(f64::from));
+    // a cell on layer n of an octree is 2 / 2^n wide
+    let layer = (2.0 / cell_size).log2().round().max(1.0) as usize;
+
+    jobs.scope(|scope| {
+        for (z, slice) in voxels.chunks_mut(resolution * resolution).enumerate() {
+            scope.spawn(move || {
+                for (i, voxel) in slice.iter_mut().enumerate() {
+                    let cell =
+                        DVec3::new((i % resolution) as f64, (i / resolution) as f64, z as f64);
+                    let pos = origin + (cell + 0.5) * cell_size;
+                    if pos.abs().max_element() >= 1.0 {
+                        continue;
+                    }
+
+                    let color = octrees
+                        .iter()
+                        .map(|octree| octree.sample(pos, layer))
+                        .find(|&color| color != 0);
+
+                    if let Some(color) = color {
+                        let [r, g, b, _] = palette.colors[color as usize];
+                        *voxel = [r, g, b, 255];
+                    }
+                }
+            });
+        }
+    });
+
+    voxels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voxels_land_in_their_cell() {
+        let mut octree = Octree::new();
+        // a layer 2 cell covers 0.5 to 1 on every axis
+        octree.write(DVec3::splat(0.75), 7, 2);
+
+        let jobs = JobSystem::new(2);
+        let palette = VoxelPalette::grayscale();
+        let voxels = voxelize(&jobs, &[octree], &palette, GiVolume::default());
+
+        let resolution = GI_RESOLUTION as usize;
+        let index = |[x, y, z]: [usize; 3]| x + (y + z * resolution) * resolution;
+
+        let last = resolution - 1;
+        let inside = resolution * 3 / 4;
+        assert_eq!(voxels[index([last, last, last])], [7, 7, 7, 255]);
+        assert_eq!(voxels[index([inside, inside, inside])], [7, 7, 7, 255]);
+        assert_eq!(voxels[index([0, 0, 0])], [0; 4]);
+        assert_eq!(voxels[index([inside - 1, last, last])], [0; 4]);
+        assert_eq!(
+            voxels.iter().filter(|voxel| voxel[3] != 0).count(),
+            (resolution / 4).pow(3)
+        );
+    }
+}
@@ -0,0 +1,595 @@
+// handles to move, rotate and scale an entity in the editor
+// the handles are hit tested on the screen against the cursor, dragging one follows the cursor ray
+// along the axis, plane or ring of the handle and applies the difference to the ``Transform`` of the entity
+//
+// the handles are drawn as screen sprites on top of everything, they are updated by ``gizmo_task``,
+// which has to be added to the application for the gizmo to react to the mouse
+
+use std::{f32::consts::TAU, io::Cursor};
+
+use ash::{prelude::VkResult, vk};
+use math::{Camera, Color, Quat, Ray, Transform, Vec2, Vec3};
+use rendering::{
+    handler::{
+        sprites::{Sprite, SpriteMode, UNTEXTURED},
+        RenderHandler,
+    },
+    types::{MaterialCreateInfo, Transparency, ViewportMode},
+};
+
+use crate::input::Input;
+
+use super::{
+    hierarchy::{Entities, Entity},
+    World,
+};
+
+/// how long the handles are on the screen, in pixels
+const HANDLE_PIXELS: f32 = 100.0;
+/// how close the cursor has to be to a handle to grab it, in pixels
+const GRAB_PIXELS: f32 = 8.0;
+const LINE_PIXELS: f32 = 3.0;
+const RING_SEGMENTS: usize = 48;
+
+const AXIS_COLORS: [Color; 3] = [
+    Color::rgb(0.9, 0.2, 0.2),
+    Color::rgb(0.2, 0.8, 0.2),
+    Color::rgb(0.2, 0.4, 0.9),
+];
+const UNIFORM_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const ACTIVE_COLOR: Color = Color::rgb(1.0, 0.85, 0.1);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// a part of the gizmo that can be dragged, the index is the axis, 0 is x
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoHandle {
+    /// move along the axis or scale the local axis
+    Axis(usize),
+    /// move along the plane the axis is the normal of
+    Plane(usize),
+    /// rotate around the axis
+    Ring(usize),
+    /// scale all axes at once
+    Uniform,
+}
+
+/// the steps a drag snaps to, None drags freely
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Snapping {
+    /// in world units
+    pub translation: Option<f32>,
+    /// in radians
+    pub rotation: Option<f32>,
+    /// a step of the scale factor, 0.1 snaps the factor to 0.9, 1.0, 1.1 and so on
+    pub scale: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    handle: GizmoHandle,
+    /// the local transform of the entity when the drag started
+    start: Transform,
+    /// the global position of the entity when the drag started
+    center: Vec3,
+    /// where the cursor ray hit the axis or plane of the handle when the drag started
+    grab: Vec3,
+    /// used by ``GizmoHandle::Uniform``, which is dragged on the plane facing the camera
+    view_normal: Vec3,
+    /// the global transform of the parent, to move the world space changes in to the local transform
+    parent: Transform,
+}
+
+/// the handles of the selected entity
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    /// the entity the handles are on, nothing is shown while it's None
+    pub target: Option<Entity>,
+    pub snapping: Snapping,
+    hovered: Option<GizmoHandle>,
+    drag: Option<Drag>,
+    /// the left mouse button was pressed during the last update, drags only start when it's pressed down
+    was_pressed: bool,
+    /// the sprite batch the handles are drawn with, None if ``shaders/sprites.spv`` is missing
+    batch: Option<usize>,
+}
+
+impl Gizmo {
+    /// # Errors
+    /// if the shader module couldn't be created
+    pub fn new(renderer: &mut RenderHandler) -> VkResult<Self> {
+        Ok(Self {
+            mode: GizmoMode::default(),
+            target: None,
+            snapping: Snapping::default(),
+            hovered: None,
+            drag: None,
+            was_pressed: false,
+            batch: load_batch(renderer, "gizmo")?,
+        })
+    }
+
+    /// create the sprite batch again after ``RenderHandler::reinitialize``
+    /// # Errors
+    /// if the shader module couldn't be created
+    pub fn recreate(&mut self, renderer: &mut RenderHandler) -> VkResult<()> {
+        self.batch = load_batch(renderer, "gizmo")?;
+        Ok(())
+    }
+
+    /// the handle under the cursor, or the one that is dragged
+    #[must_use]
+    pub fn hovered(&self) -> Option<GizmoHandle> {
+        self.drag.map(|drag| drag.handle).or(self.hovered)
+    }
+
+    #[must_use]
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// hit test the handles and apply the drag to the transform of the target
+    pub fn update(&mut self, entities: &mut Entities, camera: &Camera, input: &Input) {
+        let pressed = input.left_pressed();
+        let just_pressed = pressed && !self.was_pressed;
+        self.was_pressed = pressed;
+
+        let Some(target) = self.target.filter(|target| entities.contains(*target)) else {
+            self.hovered = None;
+            self.drag = None;
+            return;
+        };
+
+        let ray = camera.screen_to_ray(input.cursor, input.viewport);
+
+        if let Some(drag) = self.drag {
+            if !pressed {
+                self.drag = None;
+            } else if let Some(point) = constraint_point(
+                drag.handle,
+                drag.center,
+                drag.view_normal,
+                ray.origin,
+                ray.dir,
+            ) {
+                *entities.transform_mut(target) = self.apply(&drag, point);
+            }
+            return;
+        }
+
+        let center = entities.global_transform(target).translation();
+        self.hovered = self.hit_test(camera, input.viewport, center, input.cursor);
+
+        let Some(handle) = self.hovered.filter(|_| just_pressed) else {
+            return;
+        };
+
+        let view_normal = camera.transform.forward();
+        let Some(grab) = constraint_point(handle, center, view_normal, ray.origin, ray.dir) else {
+            return;
+        };
+
+        let parent = entities
+            .parent(target)
+            .map_or(Transform::IDENTITY, |parent| {
+                entities.global_transform(parent).compute_transform()
+            });
+
+        self.drag = Some(Drag {
+            handle,
+            start: *entities.transform(target),
+            center,
+            grab,
+            view_normal,
+            parent,
+        });
+    }
+
+    /// the local transform of the target with the drag to ``point`` applied
+    fn apply(&self, drag: &Drag, point: Vec3) -> Transform {
+        let mut transform = drag.start;
+        let to_local = drag.parent.compute_matrix().inverse();
+
+        match (self.mode, drag.handle) {
+            (GizmoMode::Translate, GizmoHandle::Axis(axis)) => {
+                let axis = Vec3::AXES[axis];
+                let offset = snap((point - drag.grab).dot(axis), self.snapping.translation);
+                transform.translation = to_local.transform_point3(drag.center + axis * offset);
+            }
+            (GizmoMode::Translate, GizmoHandle::Plane(normal)) => {
+                let moved = point - drag.grab;
+                let offset: Vec3 = (0..3)
+                    .filter(|&axis| axis != normal)
+                    .map(|axis| {
+                        Vec3::AXES[axis]
+                            * snap(moved.dot(Vec3::AXES[axis]), self.snapping.translation)
+                    })
+                    .sum();
+                transform.translation = to_local.transform_point3(drag.center + offset);
+            }
+            (GizmoMode::Rotate, GizmoHandle::Ring(axis)) => {
+                let axis = Vec3::AXES[axis];
+                let from = drag.grab - drag.center;
+                let to = point - drag.center;
+                let angle = axis.dot(from.cross(to)).atan2(from.dot(to));
+
+                let rotation = Quat::from_axis_angle(axis, snap(angle, self.snapping.rotation));
+                let parent = drag.parent.rotation;
+                transform.rotation =
+                    (parent.inverse() * rotation * parent * drag.start.rotation).normalize();
+            }
+            (GizmoMode::Scale, GizmoHandle::Axis(axis)) => {
+                let direction = Vec3::AXES[axis];
+                let from = (drag.grab - drag.center).dot(direction);
+                if from.abs() > f32::EPSILON {
+                    let factor = (point - drag.center).dot(direction) / from;
+                    transform.scale[axis] =
+                        drag.start.scale[axis] * snap_factor(factor, self.snapping.scale);
+                }
+            }
+            (GizmoMode::Scale, GizmoHandle::Uniform) => {
+                let from = drag.grab.distance(drag.center);
+                if from > f32::EPSILON {
+                    let factor = point.distance(drag.center) / from;
+                    transform.scale = drag.start.scale * snap_factor(factor, self.snapping.scale);
+                }
+            }
+            _ => {}
+        }
+
+        transform
+    }
+
+    /// the handles of the mode as lines on the screen, a handle is skipped if a part of it is behind the camera
+    /// the bool is true for handles that are grabbed anywhere inside of their outline
+    fn handles(
+        &self,
+        camera: &Camera,
+        viewport: Vec2,
+        center: Vec3,
+    ) -> Vec<(GizmoHandle, Vec<Vec2>, bool)> {
+        // keep the handles the same size on the screen
+        let right = camera.transform.right();
+        let up = camera.transform.up();
+        let pixels_per_unit = match (
+            camera.world_to_screen(center, viewport),
+            camera.world_to_screen(center + right, viewport),
+        ) {
+            (Some(a), Some(b)) => a.distance(b),
+            _ => return vec![],
+        };
+        let length = HANDLE_PIXELS / pixels_per_unit.max(f32::EPSILON);
+
+        let square = |corner: Vec3, u: Vec3, v: Vec3, size: f32| {
+            vec![
+                corner,
+                corner + u * size,
+                corner + (u + v) * size,
+                corner + v * size,
+                corner,
+            ]
+        };
+
+        let mut handles: Vec<(GizmoHandle, Vec<Vec3>, bool)> = vec![];
+
+        match self.mode {
+            GizmoMode::Translate => {
+                for axis in 0..3 {
+                    let (u, v) = (Vec3::AXES[(axis + 1) % 3], Vec3::AXES[(axis + 2) % 3]);
+                    handles.push((
+                        GizmoHandle::Axis(axis),
+                        vec![center, center + Vec3::AXES[axis] * length],
+                        false,
+                    ));
+                    handles.push((
+                        GizmoHandle::Plane(axis),
+                        square(center + (u + v) * length * 0.25, u, v, length * 0.2),
+                        true,
+                    ));
+                }
+            }
+            GizmoMode::Rotate => {
+                for axis in 0..3 {
+                    let (u, v) = (Vec3::AXES[(axis + 1) % 3], Vec3::AXES[(axis + 2) % 3]);
+                    let ring = (0..=RING_SEGMENTS)
+                        .map(|i| {
+                            let angle = i as f32 / RING_SEGMENTS as f32 * TAU;
+                            center + (u * angle.cos() + v * angle.sin()) * length
+                        })
+                        .collect();
+                    handles.push((GizmoHandle::Ring(axis), ring, false));
+                }
+            }
+            GizmoMode::Scale => {
+                for axis in 0..3 {
+                    handles.push((
+                        GizmoHandle::Axis(axis),
+                        vec![center, center + Vec3::AXES[axis] * length],
+                        false,
+                    ));
+                }
+                let corner = center - (right + up) * length * 0.1;
+                handles.push((
+                    GizmoHandle::Uniform,
+                    square(corner, right, up, length * 0.2),
+                    true,
+                ));
+            }
+        }
+
+        handles
+            .into_iter()
+            .filter_map(|(handle, points, filled)| {
+                let points = points
+                    .into_iter()
+                    .map(|point| camera.world_to_screen(point, viewport))
+                    .collect::<Option<Vec<_>>>()?;
+                Some((handle, points, filled))
+            })
+            .collect()
+    }
+
+    /// the closest handle within ``GRAB_PIXELS`` of the cursor
+    fn hit_test(
+        &self,
+        camera: &Camera,
+        viewport: Vec2,
+        center: Vec3,
+        cursor: Vec2,
+    ) -> Option<GizmoHandle> {
+        self.handles(camera, viewport, center)
+            .into_iter()
+            .map(|(handle, points, filled)| {
+                let distance = if filled && inside(&points, cursor) {
+                    0.0
+                } else {
+                    points
+                        .windows(2)
+                        .map(|line| segment_distance(line[0], line[1], cursor))
+                        .fold(f32::INFINITY, f32::min)
+                };
+                (handle, distance)
+            })
+            .filter(|(_, distance)| *distance <= GRAB_PIXELS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(handle, _)| handle)
+    }
+
+    /// replace the sprites of the batch with the handles of the target
+    pub fn draw(&self, renderer: &mut RenderHandler, entities: &Entities, camera: &Camera) {
+        let extent = renderer.get_swapchain_resolution();
+        let viewport = Vec2::new(extent.width as f32, extent.height as f32);
+
+        let Some(batch) = self
+            .batch
+            .and_then(|batch| renderer.get_sprite_batch_mut(batch))
+        else {
+            return;
+        };
+        batch.clear();
+
+        let Some(target) = self.target.filter(|target| entities.contains(*target)) else {
+            return;
+        };
+        let center = entities.global_transform(target).translation();
+        let active = self.hovered();
+
+        for (handle, points, _) in self.handles(camera, viewport, center) {
+            let color = match handle {
+                _ if Some(handle) == active => ACTIVE_COLOR,
+                GizmoHandle::Axis(axis) | GizmoHandle::Plane(axis) | GizmoHandle::Ring(axis) => {
+                    AXIS_COLORS[axis]
+                }
+                GizmoHandle::Uniform => UNIFORM_COLOR,
+            };
+
+            for line in points.windows(2) {
+                let middle = (line[0] + line[1]) * 0.5;
+                let delta = line[1] - line[0];
+
+                batch.push(Sprite {
+                    position: [middle.x, middle.y, 0.0],
+                    // the sprite rotates counter clockwise with y going up
+                    rotation: (-delta.y).atan2(delta.x),
+                    size: [delta.length(), LINE_PIXELS],
+                    color,
+                    page: UNTEXTURED,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
+
+/// move and rotate the gizmo target of the world with the mouse, add it with ``Application::add_task``
+/// does nothing while ``World::gizmo`` is None
+pub fn gizmo_task(world: &mut World) {
+    if let Some(gizmo) = &mut world.gizmo {
+        gizmo.update(&mut world.entities, &world.camera, &world.input);
+    }
+}
+
+/// where the cursor ray hits the axis or plane the handle is dragged along
+fn constraint_point(
+    handle: GizmoHandle,
+    center: Vec3,
+    view_normal: Vec3,
+    origin: Vec3,
+    direction: Vec3,
+) -> Option<Vec3> {
+    match handle {
+        GizmoHandle::Axis(axis) => closest_on_line(center, Vec3::AXES[axis], origin, direction),
+        GizmoHandle::Plane(axis) | GizmoHandle::Ring(axis) => {
+            intersect_plane(center, Vec3::AXES[axis], origin, direction)
+        }
+        GizmoHandle::Uniform => intersect_plane(center, view_normal, origin, direction),
+    }
+}
+
+/// the point on the line that is closest to the ray, None if they are parallel
+fn closest_on_line(point: Vec3, axis: Vec3, origin: Vec3, direction: Vec3) -> Option<Vec3> {
+    let offset = point - origin;
+    let cos = axis.dot(direction);
+    let denominator = 1.0 - cos * cos;
+    if denominator < 1e-6 {
+        return None;
+    }
+
+    let along = (cos * direction.dot(offset) - axis.dot(offset)) / denominator;
+    Some(point + axis * along)
+}
+
+/// None if the ray is parallel to the plane or points away from it
+fn intersect_plane(point: Vec3, normal: Vec3, origin: Vec3, direction: Vec3) -> Option<Vec3> {
+    let ray = Ray::new(origin, direction);
+    ray.intersect_plane(point, normal)
+        .map(|distance| ray.at(distance))
+}
+
+fn segment_distance(start: Vec2, end: Vec2, point: Vec2) -> f32 {
+    let line = end - start;
+    let along =
+        ((point - start).dot(line) / line.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    point.distance(start + line * along)
+}
+
+/// if the point is inside the convex outline, the first and last point of the outline are the same
+fn inside(outline: &[Vec2], point: Vec2) -> bool {
+    let sides = outline
+        .windows(2)
+        .map(|line| (line[1] - line[0]).perp_dot(point - line[0]));
+    let (mut positive, mut negative) = (false, false);
+    for side in sides {
+        positive |= side > 0.0;
+        negative |= side < 0.0;
+    }
+    !(positive && negative)
+}
+
+fn snap(value: f32, step: Option<f32>) -> f32 {
+    step.filter(|step| *step > 0.0)
+        .map_or(value, |step| (value / step).round() * step)
+}
+
+/// snap how far the factor is from 1
+fn snap_factor(factor: f32, step: Option<f32>) -> f32 {
+    1.0 + snap(factor - 1.0, step)
+}
+
+/// load ``shaders/sprites.spv`` and add a screen sprite batch, for the handles and the brush preview
+/// None if the shader hasn't been built with ``build.sh`` yet, ``name`` is what stays invisible
+pub(super) fn load_batch(renderer: &mut RenderHandler, name: &str) -> VkResult<Option<usize>> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/sprites.spv");
+    let Ok(code) = std::fs::read(path) else {
+        eprintln!(
+            "{path} is missing, the {name} is invisible until the shaders are built with build.sh"
+        );
+        return Ok(None);
+    };
+
+    let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+        .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+    let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+    let stage = |name, stage| {
+        vk::PipelineShaderStageCreateInfo::default()
+            .name(name)
+            .stage(stage)
+            .module(module)
+    };
+
+    let material = MaterialCreateInfo {
+        viewport: ViewportMode::default(),
+        skip_temporal_aa: true,
+        transparency: Transparency::Sorted,
+        shaders: vec![
+            stage(c"vertex_main", vk::ShaderStageFlags::VERTEX),
+            stage(c"fragment_main", vk::ShaderStageFlags::FRAGMENT),
+        ],
+        ..Default::default()
+    };
+    let batch = renderer.add_sprite_batch(material, SpriteMode::Screen);
+
+    // the pipeline doesn't need the module anymore
+    unsafe { renderer.device.destroy_shader_module(module, None) };
+
+    Ok(Some(batch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_hit_axes_and_planes() {
+        // a ray going down the z axis, one unit above the x axis
+        let origin = Vec3::new(2.0, 1.0, 5.0);
+        let direction = -Vec3::Z;
+
+        let on_x = closest_on_line(Vec3::ZERO, Vec3::X, origin, direction).unwrap();
+        assert!(on_x.abs_diff_eq(Vec3::new(2.0, 0.0, 0.0), 1e-5));
+        assert!(closest_on_line(Vec3::ZERO, Vec3::Z, origin, direction).is_none());
+
+        let on_xy = intersect_plane(Vec3::ZERO, Vec3::Z, origin, direction).unwrap();
+        assert!(on_xy.abs_diff_eq(Vec3::new(2.0, 1.0, 0.0), 1e-5));
+        assert!(intersect_plane(Vec3::ZERO, Vec3::Z, origin, Vec3::Z).is_none());
+    }
+
+    #[test]
+    fn outlines_and_lines_on_the_screen() {
+        let square = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y, Vec2::ZERO];
+        assert!(inside(&square, Vec2::splat(0.5)));
+        assert!(!inside(&square, Vec2::new(1.5, 0.5)));
+
+        assert_eq!(
+            segment_distance(Vec2::ZERO, Vec2::X * 4.0, Vec2::new(2.0, 3.0)),
+            3.0
+        );
+        assert_eq!(
+            segment_distance(Vec2::ZERO, Vec2::X * 4.0, Vec2::new(7.0, 4.0)),
+            5.0
+        );
+    }
+
+    #[test]
+    fn drags_snap() {
+        assert_eq!(snap(0.74, Some(0.5)), 0.5);
+        assert_eq!(snap(0.74, None), 0.74);
+        assert!((snap_factor(1.26, Some(0.25)) - 1.25).abs() < 1e-6);
+
+        let gizmo = Gizmo {
+            mode: GizmoMode::Translate,
+            target: None,
+            snapping: Snapping {
+                translation: Some(1.0),
+                ..Default::default()
+            },
+            hovered: None,
+            drag: None,
+            was_pressed: false,
+            batch: None,
+        };
+        let drag = Drag {
+            handle: GizmoHandle::Axis(0),
+            start: Transform::IDENTITY,
+            center: Vec3::ZERO,
+            grab: Vec3::X * 0.5,
+            view_normal: -Vec3::Z,
+            // the parent is moved up, so the local position stays below the global one
+            parent: Transform::from_xyz(0.0, 2.0, 0.0),
+        };
+
+        let moved = gizmo.apply(&drag, Vec3::new(2.2, 5.0, 0.0));
+        assert!(moved
+            .translation
+            .abs_diff_eq(Vec3::new(2.0, -2.0, 0.0), 1e-5));
+    }
+}
@@ -0,0 +1,935 @@
+// a reader for skinned glTF 2.0 models, .gltf with external or embedded buffers and binary .glb files
+// only the first skin, the primitive drawn with it and the animations of its joints are read,
+// materials, cameras, morph targets and the rest of the scene are ignored
+// <https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html>
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use math::{Mat4, Quat, Transform, Vec3, Vec4};
+
+use super::animation::{
+    AnimationClip, Channel, ChannelTarget, Interpolation, Joint, Skeleton, SkinnedVertex,
+};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const JSON_CHUNK: u32 = 0x4E4F_534A;
+const BIN_CHUNK: u32 = 0x004E_4942;
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io(std::io::Error),
+    /// the file isn't valid json or glTF
+    Invalid(String),
+    /// the file is valid, but doesn't contain what was asked for
+    Missing(&'static str),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read gltf file: {err}"),
+            Self::Invalid(err) => write!(f, "invalid gltf file: {err}"),
+            Self::Missing(what) => write!(f, "the gltf file has no {what}"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl From<std::io::Error> for GltfError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+fn invalid(what: impl Into<String>) -> GltfError {
+    GltfError::Invalid(what.into())
+}
+
+/// everything needed to draw and animate a character
+#[derive(Debug, Clone)]
+pub struct SkinnedModel {
+    pub skeleton: Skeleton,
+    pub clips: Vec<AnimationClip>,
+    pub vertices: Vec<SkinnedVertex>,
+    /// empty if the primitive isn't indexed
+    pub indices: Vec<u32>,
+}
+
+/// read and parse a skinned .gltf or .glb file
+/// # Errors
+/// if the file or its buffers can't be read, or it has no skinned mesh
+pub fn load(path: impl AsRef<Path>) -> Result<SkinnedModel, GltfError> {
+    let path = path.as_ref();
+    parse(&std::fs::read(path)?, path.parent())
+}
+
+/// # Errors
+/// if the data isn't a valid gltf or glb file with a skinned mesh,
+/// or a buffer can't be read from ``dir``
+pub fn parse(data: &[u8], dir: Option<&Path>) -> Result<SkinnedModel, GltfError> {
+    let (json, bin) = if data.starts_with(GLB_MAGIC) {
+        split_glb(data)?
+    } else {
+        (data, None)
+    };
+
+    let text = std::str::from_utf8(json).map_err(|_| invalid("the json isn't utf-8"))?;
+    let gltf = Document::new(Json::parse(text)?, bin, dir)?;
+    gltf.skinned_model()
+}
+
+/// the json and the binary chunk of a glb file
+fn split_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    let u32_at = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or_else(|| invalid("unexpected end of the glb file"))
+    };
+
+    let mut offset = 12;
+    let mut json = None;
+    let mut bin = None;
+
+    while offset < data.len() {
+        let length = u32_at(offset)? as usize;
+        let kind = u32_at(offset + 4)?;
+        let chunk = data
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| invalid("a glb chunk is out of bounds"))?;
+
+        match kind {
+            JSON_CHUNK => json = Some(chunk),
+            BIN_CHUNK => bin = Some(chunk),
+            _ => {}
+        }
+
+        offset += 8 + length;
+    }
+
+    Ok((json.ok_or_else(|| invalid("missing json chunk"))?, bin))
+}
+
+/// a parsed json value, objects keep the order of their keys
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// # Errors
+    /// if the text isn't valid json
+    pub fn parse(text: &str) -> Result<Self, GltfError> {
+        let mut parser = JsonParser {
+            bytes: text.as_bytes(),
+            pos: 0,
+        };
+
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(invalid("trailing characters after the json"));
+        }
+        Ok(value)
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|number| *number >= 0.0 && number.fract() == 0.0)
+            .map(|number| number as usize)
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// an empty slice for everything that isn't an array
+    #[must_use]
+    pub fn items(&self) -> &[Self] {
+        match self {
+            Self::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    fn floats<const N: usize>(&self) -> Option<[f32; N]> {
+        let items = self.items();
+        if items.len() != N {
+            return None;
+        }
+
+        let mut floats = [0.0; N];
+        for (float, item) in floats.iter_mut().zip(items) {
+            *float = item.as_f64()? as f32;
+        }
+        Some(floats)
+    }
+}
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), GltfError> {
+        if self.peek() != Some(byte) {
+            return Err(invalid(format!(
+                "expected '{}' at byte {}",
+                byte as char, self.pos
+            )));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &str, value: Json) -> Result<Json, GltfError> {
+        if !self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            return Err(invalid(format!("unexpected token at byte {}", self.pos)));
+        }
+        self.pos += literal.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, GltfError> {
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(invalid(format!("unexpected token at byte {}", self.pos))),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, GltfError> {
+        self.expect(b'{')?;
+        let mut entries = vec![];
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            entries.push((key, self.value()?));
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                _ => break,
+            }
+        }
+
+        self.expect(b'}')?;
+        Ok(Json::Object(entries))
+    }
+
+    fn array(&mut self) -> Result<Json, GltfError> {
+        self.expect(b'[')?;
+        let mut items = vec![];
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                _ => break,
+            }
+        }
+
+        self.expect(b']')?;
+        Ok(Json::Array(items))
+    }
+
+    fn string(&mut self) -> Result<String, GltfError> {
+        self.expect(b'"')?;
+        let mut string = Vec::new();
+
+        loop {
+            let byte = *self
+                .bytes
+                .get(self.pos)
+                .ok_or_else(|| invalid("unterminated string"))?;
+            self.pos += 1;
+
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self
+                        .bytes
+                        .get(self.pos)
+                        .ok_or_else(|| invalid("unterminated string"))?;
+                    self.pos += 1;
+
+                    let unescaped = match escape {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let hex = self
+                                .bytes
+                                .get(self.pos..self.pos + 4)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .ok_or_else(|| invalid("invalid unicode escape"))?;
+                            self.pos += 4;
+                            // surrogate pairs don't appear in the names of a model
+                            char::from_u32(hex).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        other => other as char,
+                    };
+
+                    let mut buffer = [0; 4];
+                    string.extend_from_slice(unescaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => string.push(byte),
+            }
+        }
+
+        String::from_utf8(string).map_err(|_| invalid("a string isn't utf-8"))
+    }
+
+    fn number(&mut self) -> Result<Json, GltfError> {
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|byte| matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+        {
+            self.pos += 1;
+        }
+
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .map(Json::Number)
+            .ok_or_else(|| invalid(format!("invalid number at byte {start}")))
+    }
+}
+
+/// decode the base64 of a data uri
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let value = |byte: u8| match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let digits = text.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+
+    for chunk in digits.chunks(4) {
+        let mut bits = 0u32;
+        for (i, &digit) in chunk.iter().enumerate() {
+            bits |= u32::from(value(digit)?) << (18 - 6 * i);
+        }
+
+        let [_, a, b, c] = bits.to_be_bytes();
+        bytes.extend_from_slice(&[a, b, c][..chunk.len().saturating_sub(1)]);
+    }
+
+    Some(bytes)
+}
+
+struct Document {
+    json: Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Document {
+    fn new(json: Json, bin: Option<&[u8]>, dir: Option<&Path>) -> Result<Self, GltfError> {
+        let buffers = json
+            .get("buffers")
+            .map(Json::items)
+            .unwrap_or_default()
+            .iter()
+            .map(|buffer| match buffer.get("uri").and_then(Json::as_str) {
+                None => bin
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| invalid("a buffer has no uri and there is no glb chunk")),
+                Some(uri) if uri.starts_with("data:") => uri
+                    .split_once(";base64,")
+                    .and_then(|(_, data)| decode_base64(data))
+                    .ok_or_else(|| invalid("invalid data uri")),
+                Some(uri) => {
+                    let path = dir.map_or_else(|| PathBuf::from(uri), |dir| dir.join(uri));
+                    Ok(std::fs::read(path)?)
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { json, buffers })
+    }
+
+    fn list(&self, key: &str) -> &[Json] {
+        self.json.get(key).map(Json::items).unwrap_or_default()
+    }
+
+    /// the components of every element of an accessor as floats, normalized integers are mapped to 0..1 or -1..1
+    fn read_floats(&self, index: usize) -> Result<(Vec<f32>, usize), GltfError> {
+        let accessor = self
+            .list("accessors")
+            .get(index)
+            .ok_or_else(|| invalid("accessor out of bounds"))?;
+
+        let count = accessor
+            .get("count")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| invalid("accessor without count"))?;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4" | "MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => return Err(invalid("unknown accessor type")),
+        };
+        let component_type = accessor
+            .get("componentType")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| invalid("accessor without component type"))?;
+        let normalized = accessor.get("normalized") == Some(&Json::Bool(true));
+
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(invalid("unknown component type")),
+        };
+
+        // sparse accessors and accessors without a buffer view are zeroed
+        let Some(view_index) = accessor.get("bufferView").and_then(Json::as_usize) else {
+            return Ok((vec![0.0; count * components], components));
+        };
+
+        let view = self
+            .list("bufferViews")
+            .get(view_index)
+            .ok_or_else(|| invalid("buffer view out of bounds"))?;
+        let buffer = view
+            .get("buffer")
+            .and_then(Json::as_usize)
+            .and_then(|buffer| self.buffers.get(buffer))
+            .ok_or_else(|| invalid("buffer out of bounds"))?;
+
+        let offset = |value: &Json| {
+            value
+                .get("byteOffset")
+                .and_then(Json::as_usize)
+                .unwrap_or(0)
+        };
+        let start = offset(view) + offset(accessor);
+        let stride = view
+            .get("byteStride")
+            .and_then(Json::as_usize)
+            .unwrap_or(components * size);
+
+        let mut floats = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let at = start + element * stride + component * size;
+                let bytes = buffer
+                    .get(at..at + size)
+                    .ok_or_else(|| invalid("accessor out of the bounds of its buffer"))?;
+
+                let value = match (component_type, normalized) {
+                    (5120, false) => f32::from(bytes[0] as i8),
+                    (5120, true) => (f32::from(bytes[0] as i8) / 127.0).max(-1.0),
+                    (5121, false) => f32::from(bytes[0]),
+                    (5121, true) => f32::from(bytes[0]) / 255.0,
+                    (5122, normalized) => {
+                        let value = f32::from(i16::from_le_bytes([bytes[0], bytes[1]]));
+                        if normalized {
+                            (value / 32767.0).max(-1.0)
+                        } else {
+                            value
+                        }
+                    }
+                    (5123, normalized) => {
+                        let value = f32::from(u16::from_le_bytes([bytes[0], bytes[1]]));
+                        if normalized {
+                            value / 65535.0
+                        } else {
+                            value
+                        }
+                    }
+                    (5125, _) => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+                    _ => f32::from_le_bytes(bytes.try_into().unwrap()),
+                };
+                floats.push(value);
+            }
+        }
+
+        Ok((floats, components))
+    }
+
+    fn read_vec<const N: usize>(&self, index: usize) -> Result<Vec<[f32; N]>, GltfError> {
+        let (floats, components) = self.read_floats(index)?;
+        if components != N {
+            return Err(invalid(format!(
+                "expected {N} components, the accessor has {components}"
+            )));
+        }
+
+        Ok(floats
+            .chunks_exact(N)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect())
+    }
+
+    fn node_transform(node: &Json) -> Transform {
+        if let Some(matrix) = node.get("matrix").and_then(Json::floats::<16>) {
+            return Transform::from_matrix(Mat4::from_cols_array(&matrix));
+        }
+
+        Transform {
+            translation: node
+                .get("translation")
+                .and_then(Json::floats::<3>)
+                .map_or(Vec3::ZERO, Vec3::from_array),
+            rotation: node
+                .get("rotation")
+                .and_then(Json::floats::<4>)
+                .map_or(Quat::IDENTITY, Quat::from_array),
+            scale: node
+                .get("scale")
+                .and_then(Json::floats::<3>)
+                .map_or(Vec3::ONE, Vec3::from_array),
+        }
+    }
+
+    fn skinned_model(&self) -> Result<SkinnedModel, GltfError> {
+        let nodes = self.list("nodes");
+        let skin = self
+            .list("skins")
+            .first()
+            .ok_or(GltfError::Missing("skin"))?;
+
+        let mut parents = HashMap::new();
+        for (index, node) in nodes.iter().enumerate() {
+            for child in node.get("children").map(Json::items).unwrap_or_default() {
+                if let Some(child) = child.as_usize() {
+                    parents.insert(child, index);
+                }
+            }
+        }
+
+        let joint_nodes: Vec<usize> = skin
+            .get("joints")
+            .map(Json::items)
+            .unwrap_or_default()
+            .iter()
+            .map(|joint| joint.as_usize().filter(|&joint| joint < nodes.len()))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("invalid joint node"))?;
+        let joint_of_node: HashMap<usize, usize> = joint_nodes
+            .iter()
+            .enumerate()
+            .map(|(joint, &node)| (node, joint))
+            .collect();
+
+        let joints: Vec<Joint> = joint_nodes
+            .iter()
+            .map(|&node| Joint {
+                name: nodes[node]
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap_or_default()
+                    .to_owned(),
+                parent: parents
+                    .get(&node)
+                    .and_then(|parent| joint_of_node.get(parent))
+                    .copied(),
+                rest: Self::node_transform(&nodes[node]),
+            })
+            .collect();
+
+        // the nodes above the skeleton, like the armature node of blender
+        let mut root = Mat4::IDENTITY;
+        if let Some(&first) = joint_nodes
+            .iter()
+            .find(|node| joints[joint_of_node[node]].parent.is_none())
+        {
+            let mut parent = parents.get(&first);
+            while let Some(&node) = parent {
+                root = Self::node_transform(&nodes[node]).compute_matrix() * root;
+                parent = parents.get(&node);
+            }
+        }
+
+        let inverse_bind = match skin.get("inverseBindMatrices").and_then(Json::as_usize) {
+            Some(accessor) => self
+                .read_vec::<16>(accessor)?
+                .iter()
+                .map(Mat4::from_cols_array)
+                .collect(),
+            None => vec![Mat4::IDENTITY; joints.len()],
+        };
+        if inverse_bind.len() != joints.len() {
+            return Err(invalid("every joint needs an inverse bind matrix"));
+        }
+
+        let skeleton = Skeleton::new(joints, inverse_bind, root);
+
+        let clips = self
+            .list("animations")
+            .iter()
+            .map(|animation| self.clip(animation, &joint_of_node))
+            .collect::<Result<_, _>>()?;
+
+        let (vertices, indices) = self.skinned_primitive()?;
+
+        Ok(SkinnedModel {
+            skeleton,
+            clips,
+            vertices,
+            indices,
+        })
+    }
+
+    fn clip(
+        &self,
+        animation: &Json,
+        joint_of_node: &HashMap<usize, usize>,
+    ) -> Result<AnimationClip, GltfError> {
+        let samplers = animation
+            .get("samplers")
+            .map(Json::items)
+            .unwrap_or_default();
+        let mut channels = vec![];
+
+        for channel in animation
+            .get("channels")
+            .map(Json::items)
+            .unwrap_or_default()
+        {
+            let Some(target) = channel.get("target") else {
+                continue;
+            };
+            let Some(&joint) = target
+                .get("node")
+                .and_then(Json::as_usize)
+                .and_then(|node| joint_of_node.get(&node))
+            else {
+                continue;
+            };
+
+            let (target, components) = match target.get("path").and_then(Json::as_str) {
+                Some("translation") => (ChannelTarget::Translation, 3),
+                Some("rotation") => (ChannelTarget::Rotation, 4),
+                Some("scale") => (ChannelTarget::Scale, 3),
+                // morph target weights
+                _ => continue,
+            };
+
+            let sampler = channel
+                .get("sampler")
+                .and_then(Json::as_usize)
+                .and_then(|sampler| samplers.get(sampler))
+                .ok_or_else(|| invalid("channel without sampler"))?;
+            let accessor = |key| {
+                sampler
+                    .get(key)
+                    .and_then(Json::as_usize)
+                    .ok_or_else(|| invalid("sampler without input or output"))
+            };
+
+            let (times, _) = self.read_floats(accessor("input")?)?;
+            let (values, value_components) = self.read_floats(accessor("output")?)?;
+            if value_components != components {
+                return Err(invalid("the output doesn't match the channel target"));
+            }
+
+            let interpolation = match sampler.get("interpolation").and_then(Json::as_str) {
+                Some("STEP") => Interpolation::Step,
+                _ => Interpolation::Linear,
+            };
+
+            let mut values: Vec<Vec4> = values
+                .chunks_exact(components)
+                .map(|value| {
+                    let mut padded = [0.0; 4];
+                    padded[..components].copy_from_slice(value);
+                    Vec4::from_array(padded)
+                })
+                .collect();
+
+            // cubic splines store an in and out tangent around every value, only the values are used
+            if matches!(
+                sampler.get("interpolation").and_then(Json::as_str),
+                Some("CUBICSPLINE")
+            ) {
+                values = values.chunks_exact(3).map(|keyframe| keyframe[1]).collect();
+            }
+
+            if values.len() != times.len() {
+                return Err(invalid("every keyframe needs a value"));
+            }
+
+            channels.push(Channel {
+                joint,
+                target,
+                interpolation,
+                times,
+                values,
+            });
+        }
+
+        Ok(AnimationClip::new(
+            animation
+                .get("name")
+                .and_then(Json::as_str)
+                .unwrap_or_default(),
+            channels,
+        ))
+    }
+
+    /// the first primitive of the mesh that is drawn with the skin
+    fn skinned_primitive(&self) -> Result<(Vec<SkinnedVertex>, Vec<u32>), GltfError> {
+        let node = self
+            .list("nodes")
+            .iter()
+            .find(|node| node.get("skin").is_some() && node.get("mesh").is_some())
+            .ok_or(GltfError::Missing("skinned mesh"))?;
+
+        let primitive = node
+            .get("mesh")
+            .and_then(Json::as_usize)
+            .and_then(|mesh| self.list("meshes").get(mesh))
+            .and_then(|mesh| mesh.get("primitives"))
+            .and_then(|primitives| primitives.items().first())
+            .ok_or_else(|| invalid("the skinned mesh has no primitive"))?;
+
+        let attributes = primitive
+            .get("attributes")
+            .ok_or_else(|| invalid("primitive without attributes"))?;
+        let attribute = |name| attributes.get(name).and_then(Json::as_usize);
+
+        let positions = self
+            .read_vec::<3>(attribute("POSITION").ok_or(GltfError::Missing("vertex positions"))?)?;
+        let normals = match attribute("NORMAL") {
+            Some(accessor) => self.read_vec::<3>(accessor)?,
+            None => vec![[0.0, 1.0, 0.0]; positions.len()],
+        };
+        let joints =
+            self.read_vec::<4>(attribute("JOINTS_0").ok_or(GltfError::Missing("joint indices"))?)?;
+        let weights =
+            self.read_vec::<4>(attribute("WEIGHTS_0").ok_or(GltfError::Missing("joint weights"))?)?;
+
+        let count = positions.len();
+        if normals.len() != count || joints.len() != count || weights.len() != count {
+            return Err(invalid("the vertex attributes have different lengths"));
+        }
+
+        let vertices = (0..count)
+            .map(|i| SkinnedVertex {
+                position: positions[i],
+                normal: normals[i],
+                joints: joints[i].map(|joint| joint as u32),
+                weights: weights[i],
+            })
+            .collect();
+
+        let indices = match primitive.get("indices").and_then(Json::as_usize) {
+            Some(accessor) => self
+                .read_floats(accessor)?
+                .0
+                .into_iter()
+                .map(|index| index as u32)
+                .collect(),
+            None => vec![],
+        };
+
+        Ok((vertices, indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_json() {
+        let json =
+            Json::parse(r#" { "a": [1, -2.5e1, true, null], "b": "x\"é" , "c": {} } "#).unwrap();
+
+        assert_eq!(
+            json.get("a").unwrap().items(),
+            [
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Null
+            ]
+        );
+        assert_eq!(json.get("b").and_then(Json::as_str), Some("x\"é"));
+        assert_eq!(json.get("c"), Some(&Json::Object(vec![])));
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{} x").is_err());
+    }
+
+    #[test]
+    fn decode_data_uris() {
+        assert_eq!(decode_base64("AAECAw==").unwrap(), [0, 1, 2, 3]);
+        assert_eq!(decode_base64("aGk=").unwrap(), b"hi");
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert!(decode_base64("a*c=").is_none());
+    }
+
+    /// a triangle with two joints and an animation that rotates the second one
+    fn model() -> String {
+        let floats = |values: &[f32]| {
+            values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<u8>>()
+        };
+
+        let mut buffer = floats(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        // joints as u8 and weights as floats
+        buffer.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        buffer.extend(floats(&[1.0, 0.0, 0.0, 0.0].repeat(3)));
+        // keyframe times and rotations
+        buffer.extend(floats(&[0.0, 1.0]));
+        buffer.extend(floats(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0]));
+
+        let data = base64(&buffer);
+
+        format!(
+            r#"{{
+            "asset": {{ "version": "2.0" }},
+            "nodes": [
+                {{ "name": "armature", "translation": [0, 2, 0], "children": [1, 3] }},
+                {{ "name": "root", "children": [2] }},
+                {{ "name": "arm", "translation": [1, 0, 0] }},
+                {{ "mesh": 0, "skin": 0 }}
+            ],
+            "skins": [{{ "joints": [2, 1] }}],
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2 }} }}] }}],
+            "animations": [{{
+                "name": "wave",
+                "samplers": [{{ "input": 3, "output": 4 }}],
+                "channels": [{{ "sampler": 0, "target": {{ "node": 2, "path": "rotation" }} }}]
+            }}],
+            "buffers": [{{ "byteLength": {len}, "uri": "data:application/octet-stream;base64,{data}" }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 12 }},
+                {{ "buffer": 0, "byteOffset": 48, "byteLength": 48 }},
+                {{ "buffer": 0, "byteOffset": 96, "byteLength": 8 }},
+                {{ "buffer": 0, "byteOffset": 104, "byteLength": 32 }}
+            ],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+                {{ "bufferView": 1, "componentType": 5121, "count": 3, "type": "VEC4" }},
+                {{ "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4" }},
+                {{ "bufferView": 3, "componentType": 5126, "count": 2, "type": "SCALAR" }},
+                {{ "bufferView": 4, "componentType": 5126, "count": 2, "type": "VEC4" }}
+            ]
+        }}"#,
+            len = buffer.len()
+        )
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const DIGITS: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            let mut padded = [0; 3];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            let bits = u32::from_be_bytes([0, padded[0], padded[1], padded[2]]);
+
+            for i in 0..=chunk.len() {
+                text.push(DIGITS[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn read_skinned_model() {
+        let model = parse(model().as_bytes(), None).unwrap();
+
+        let joints = &model.skeleton.joints;
+        assert_eq!(joints[0].name, "arm");
+        assert_eq!(joints[0].parent, Some(1));
+        assert_eq!(joints[1].parent, None);
+        assert_eq!(joints[0].rest.translation, Vec3::X);
+        assert_eq!(model.skeleton.root, Mat4::from_translation(Vec3::Y * 2.0));
+
+        assert_eq!(model.vertices.len(), 3);
+        assert_eq!(model.vertices[1].position, [1.0, 0.0, 0.0]);
+        assert_eq!(model.vertices[1].joints, [1, 0, 0, 0]);
+        assert_eq!(model.vertices[2].weights, [1.0, 0.0, 0.0, 0.0]);
+        assert!(model.indices.is_empty());
+
+        let clip = &model.clips[0];
+        assert_eq!(clip.name, "wave");
+        assert_eq!(clip.duration, 1.0);
+        assert_eq!(clip.channels[0].joint, 0);
+        assert_eq!(clip.channels[0].target, ChannelTarget::Rotation);
+    }
+}
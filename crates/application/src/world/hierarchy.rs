@@ -0,0 +1,335 @@
+// entities with a transform that can be parented to each other
+// the global transforms are computed once per frame by ``Entities::propagate``
+
+use allocators::{GrowablePool, GrowthPolicy, PoolHandle};
+use math::{GlobalTransform, Transform};
+
+/// points to an entity in ``Entities``
+/// the index is reused after the entity is despawned, so don't keep it around after that
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity(PoolHandle);
+
+impl Entity {
+    /// the slot of the entity in ``Entities``, unique among the entities that exist at the same time
+    #[must_use]
+    pub fn index(self) -> u32 {
+        self.0.index()
+    }
+
+    /// the entity in the slot, it might not exist anymore
+    pub(crate) fn from_index(index: u32) -> Self {
+        Self(PoolHandle::from_index(index))
+    }
+}
+
+struct EntityData {
+    transform: Transform,
+    global: GlobalTransform,
+    parent: Option<Entity>,
+    children: Vec<Entity>,
+    /// the transform or the parent changed since the last propagation
+    changed: bool,
+    /// the propagation that last changed the global transform
+    changed_in: u64,
+}
+
+pub struct Entities {
+    data: GrowablePool<EntityData>,
+    /// how often ``propagate`` was called
+    propagations: u64,
+}
+
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Entities {
+    const CHUNK_SIZE: usize = 256;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            data: GrowablePool::new(Self::CHUNK_SIZE, GrowthPolicy::Linear),
+            propagations: 0,
+        }
+    }
+
+    /// the global transform is computed by the next ``propagate``
+    pub fn spawn(&mut self, transform: Transform) -> Entity {
+        let data = EntityData {
+            transform,
+            global: GlobalTransform::from(transform),
+            parent: None,
+            children: vec![],
+            changed: true,
+            changed_in: 0,
+        };
+
+        // the pool grows linearly, so this never fails
+        Entity(self.data.insert(data).unwrap())
+    }
+
+    /// spawn an entity whose transform is relative to the parent
+    pub fn spawn_child(&mut self, parent: Entity, transform: Transform) -> Entity {
+        let child = self.spawn(transform);
+        self.set_parent(child, Some(parent));
+        child
+    }
+
+    /// remove the entity and all of its children
+    pub fn despawn(&mut self, entity: Entity) {
+        if let Some(parent) = self.parent(entity) {
+            self.get_mut(parent)
+                .children
+                .retain(|child| *child != entity);
+        }
+
+        let mut stack = vec![entity];
+        while let Some(entity) = stack.pop() {
+            if let Some(data) = self.data.remove(entity.0) {
+                stack.extend(data.children);
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.data.get(entity.0).is_some()
+    }
+
+    /// the amount of entities
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// all entities, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.data.iter().map(|(handle, _)| Entity(handle))
+    }
+
+    /// the entities without a parent
+    pub fn roots(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.data
+            .iter()
+            .filter(|(_, data)| data.parent.is_none())
+            .map(|(handle, _)| Entity(handle))
+    }
+
+    /// # Panics
+    /// if the entity was despawned
+    #[must_use]
+    pub fn transform(&self, entity: Entity) -> &Transform {
+        &self.get(entity).transform
+    }
+
+    /// marks the entity as changed, so it's global transform is updated by the next ``propagate``
+    /// # Panics
+    /// if the entity was despawned
+    pub fn transform_mut(&mut self, entity: Entity) -> &mut Transform {
+        let data = self.get_mut(entity);
+        data.changed = true;
+        &mut data.transform
+    }
+
+    /// the global transform from the last ``propagate``
+    /// # Panics
+    /// if the entity was despawned
+    #[must_use]
+    pub fn global_transform(&self, entity: Entity) -> &GlobalTransform {
+        &self.get(entity).global
+    }
+
+    /// the number of the last ``propagate``, to check what changed after it with ``changed_since``
+    #[must_use]
+    pub fn propagation(&self) -> u64 {
+        self.propagations
+    }
+
+    /// the global transform was changed by a ``propagate`` after the one numbered ``propagation``
+    /// # Panics
+    /// if the entity was despawned
+    #[must_use]
+    pub fn changed_since(&self, entity: Entity, propagation: u64) -> bool {
+        self.get(entity).changed_in > propagation
+    }
+
+    /// # Panics
+    /// if the entity was despawned
+    #[must_use]
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.get(entity).parent
+    }
+
+    /// # Panics
+    /// if the entity was despawned
+    #[must_use]
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        &self.get(entity).children
+    }
+
+    /// change the parent of the entity, the transform stays the same but is now relative to the new parent
+    /// # Panics
+    /// if one of the entities was despawned or the parent is the entity itself or one of its children
+    pub fn set_parent(&mut self, entity: Entity, parent: Option<Entity>) {
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(current) = ancestor {
+                assert!(current != entity, "an entity can't be its own ancestor");
+                ancestor = self.parent(current);
+            }
+            self.get_mut(parent).children.push(entity);
+        }
+
+        let data = self.get_mut(entity);
+        let old_parent = std::mem::replace(&mut data.parent, parent);
+        data.changed = true;
+
+        if let Some(old_parent) = old_parent {
+            self.get_mut(old_parent)
+                .children
+                .retain(|child| *child != entity);
+        }
+    }
+
+    /// compute the global transforms of all entities whose transform or whose parents transform changed
+    /// returns true if a global transform changed
+    pub fn propagate(&mut self) -> bool {
+        self.propagations += 1;
+        let propagation = self.propagations;
+        let roots: Vec<Entity> = self.roots().collect();
+
+        // (entity, the global transform of the parent, the parent changed)
+        let mut stack: Vec<(Entity, GlobalTransform, bool)> = roots
+            .into_iter()
+            .map(|root| (root, GlobalTransform::IDENTITY, false))
+            .collect();
+
+        let mut any_changed = false;
+        while let Some((entity, parent_global, parent_changed)) = stack.pop() {
+            let data = self.get_mut(entity);
+            let changed = data.changed || parent_changed;
+
+            if changed {
+                data.global = parent_global * data.transform;
+                data.changed = false;
+                data.changed_in = propagation;
+                any_changed = true;
+            }
+
+            let global = data.global;
+            stack.extend(data.children.iter().map(|child| (*child, global, changed)));
+        }
+        any_changed
+    }
+
+    fn get(&self, entity: Entity) -> &EntityData {
+        self.data.get(entity.0).expect("the entity was despawned")
+    }
+
+    fn get_mut(&mut self, entity: Entity) -> &mut EntityData {
+        self.data
+            .get_mut(entity.0)
+            .expect("the entity was despawned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{vec3, Quat, Transform, Vec3};
+
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn propagate() {
+        let mut entities = Entities::new();
+
+        let parent = entities.spawn(
+            Transform::from_xyz(1.0, 0.0, 0.0)
+                .with_rotation(Quat::from_rotation_y(90f32.to_radians())),
+        );
+        let child = entities.spawn_child(parent, Transform::from_xyz(0.0, 0.0, 2.0));
+        let grandchild = entities.spawn_child(child, Transform::from_xyz(0.0, 1.0, 0.0));
+
+        assert!(entities.propagate());
+        assert!(!entities.propagate());
+        assert_near(
+            entities.global_transform(child).translation(),
+            vec3(3.0, 0.0, 0.0),
+        );
+        assert_near(
+            entities.global_transform(grandchild).translation(),
+            vec3(3.0, 1.0, 0.0),
+        );
+
+        // only changing the parent also moves the children
+        entities.transform_mut(parent).translation.y = 5.0;
+        assert!(entities.propagate());
+        assert_near(
+            entities.global_transform(grandchild).translation(),
+            vec3(3.0, 6.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn reparent_and_despawn() {
+        let mut entities = Entities::new();
+
+        let a = entities.spawn(Transform::from_xyz(1.0, 0.0, 0.0));
+        let b = entities.spawn(Transform::from_xyz(0.0, 1.0, 0.0));
+        let child = entities.spawn_child(a, Transform::from_xyz(0.0, 0.0, 1.0));
+
+        entities.set_parent(child, Some(b));
+        assert!(entities.children(a).is_empty());
+        assert_eq!(entities.children(b), [child]);
+
+        entities.propagate();
+        assert_near(
+            entities.global_transform(child).translation(),
+            vec3(0.0, 1.0, 1.0),
+        );
+
+        entities.despawn(b);
+        assert!(!entities.contains(child));
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn changed_since() {
+        let mut entities = Entities::new();
+        let parent = entities.spawn(Transform::IDENTITY);
+        let child = entities.spawn_child(parent, Transform::IDENTITY);
+        let other = entities.spawn(Transform::IDENTITY);
+
+        entities.propagate();
+        let propagation = entities.propagation();
+        assert!(entities.changed_since(child, propagation - 1));
+        assert!(!entities.changed_since(child, propagation));
+
+        // moving the parent also changes the child
+        entities.transform_mut(parent).translation.x = 1.0;
+        entities.propagate();
+        assert!(entities.changed_since(child, propagation));
+        assert!(!entities.changed_since(other, propagation));
+    }
+
+    #[test]
+    #[should_panic = "ancestor"]
+    fn no_cycles() {
+        let mut entities = Entities::new();
+        let a = entities.spawn(Transform::IDENTITY);
+        let b = entities.spawn_child(a, Transform::IDENTITY);
+        entities.set_parent(a, Some(b));
+    }
+}
@@ -0,0 +1,592 @@
+// turns the leaves of an octree in to triangles, for gpus that draw dense chunks faster as meshes than raymarched
+// every leaf gets a quad for each side that isn't covered by a filled neighbor
+//
+// with ``VK_EXT_mesh_shader`` the mesh is split in to meshlets, a task shader culls them against the frustum
+// and the mesh shader emits the triangles of the visible ones, see ``shaders/voxel_mesh.slang``
+// without it the same vertices are drawn with an index buffer
+//
+// the chunks of a ``VoxelWorld`` are meshed in parallel on the job system, see ``mesh_chunks``
+//
+// the faces of transparent colors go in to a separate mesh that is drawn after the opaque ones,
+// its index buffer is sorted back to front on the cpu, but only when the direction from the chunk to the camera
+// crossed one of the ``SORT_STEPS`` thresholds, see ``ChunkSortKey``
+// the transparent chunks themselves have to be drawn back to front as well, see ``back_to_front``
+
+use std::{io::Cursor, sync::Arc};
+
+use ash::{prelude::VkResult, vk};
+use jobs::JobSystem;
+use math::{DVec3, IVec3, Vec3};
+use rendering::{
+    handler::{
+        render_batch::{DrawData, PulledBuffers},
+        RenderHandler,
+    },
+    types::{
+        CullingMode, Material, MaterialCreateInfo, Meshlet, MeshletMesh, Transparency, ViewportMode,
+    },
+    vulkan::Buffer,
+};
+
+use super::{chunks::VoxelWorld, palette::VoxelPalette, svo::Octree};
+
+/// the meshlets one task shader work group culls, needs to match ``TASK_GROUP_SIZE`` in the shader
+pub const TASK_GROUP_SIZE: u32 = 32;
+
+/// the steps the direction to the camera is rounded to on every axis, the transparent faces are sorted
+/// again when the rounded direction changes, so about every ``90 / SORT_STEPS`` degrees
+pub const SORT_STEPS: f64 = 4.0;
+
+/// the layout needs to match ``VoxelVertex`` in the shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelVertex {
+    pub position: [f32; 3],
+    /// the color index in the first 8 bits and the face in the next 3,
+    /// the faces are -x, +x, -y, +y, -z, +z
+    pub data: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VoxelMesh {
+    pub vertices: Vec<VoxelVertex>,
+    /// a triangle list, counter clockwise when looking at the front of a face
+    pub indices: Vec<u32>,
+}
+
+/// the faces of a chunk split by the transparency of their color
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ChunkMesh {
+    pub opaque: VoxelMesh,
+    /// every face is 4 vertices and 6 indices that follow each other, so the faces can be sorted
+    pub transparent: VoxelMesh,
+}
+
+/// mesh the loaded chunks of the coords with one job each, like ``mesh_octree``
+/// coords of chunks that aren't loaded are skipped
+#[must_use]
+pub fn mesh_chunks(
+    jobs: &JobSystem,
+    world: &VoxelWorld,
+    coords: &[IVec3],
+    max_depth: usize,
+    palette: &VoxelPalette,
+) -> Vec<(IVec3, ChunkMesh)> {
+    let mut meshes: Vec<_> = coords
+        .iter()
+        .filter_map(|&coord| Some((coord, &world.chunk(coord)?.octree, ChunkMesh::default())))
+        .collect();
+
+    jobs.scope(|scope| {
+        for (_, octree, mesh) in &mut meshes {
+            let octree = *octree;
+            scope.spawn(move || *mesh = mesh_octree(octree, max_depth, palette));
+        }
+    });
+
+    meshes
+        .into_iter()
+        .map(|(coord, _, mesh)| (coord, mesh))
+        .collect()
+}
+
+/// the start of the chunk buffer, needs to match ``ChunkHeader`` in the shader
+/// the offsets are in bytes from the start of the buffer
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ChunkHeader {
+    meshlet_count: u32,
+    meshlets: u32,
+    vertex_indices: u32,
+    triangles: u32,
+}
+
+/// mesh the filled leaves of the octree up to ``max_depth``
+/// faces covered by an opaque cell are skipped, transparent cells only cover the faces of cells with the same color
+/// neighbors are sampled at ``max_depth``, so a big leaf next to a partially filled cell only checks the cell at the center of its face
+#[must_use]
+pub fn mesh_octree(octree: &Octree, max_depth: usize, palette: &VoxelPalette) -> ChunkMesh {
+    let mut chunk = ChunkMesh::default();
+
+    for (center, size, color) in octree.iter_leaves(max_depth) {
+        let mesh = if palette.is_transparent(color) {
+            &mut chunk.transparent
+        } else {
+            &mut chunk.opaque
+        };
+
+        for face in 0..6 {
+            let axis = face / 2;
+            let sign = if face % 2 == 0 { -1.0 } else { 1.0 };
+
+            let mut normal = DVec3::ZERO;
+            normal[axis] = sign;
+
+            let neighbor = center + normal * size;
+            let outside = neighbor.abs().max_element() > 1.0;
+            if !outside {
+                let neighbor = octree.sample(neighbor, max_depth);
+                if neighbor != 0 && (neighbor == color || !palette.is_transparent(neighbor)) {
+                    continue;
+                }
+            }
+
+            let half = size * 0.5;
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+            let mut corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+            if sign < 0.0 {
+                corners.reverse();
+            }
+
+            let first = mesh.vertices.len() as u32;
+            for (cu, cv) in corners {
+                let mut position = center + normal * half;
+                position[u] += cu * half;
+                position[v] += cv * half;
+
+                mesh.vertices.push(VoxelVertex {
+                    position: position.as_vec3().to_array(),
+                    data: u32::from(color) | (face as u32) << 8,
+                });
+            }
+
+            mesh.indices
+                .extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+        }
+    }
+
+    chunk
+}
+
+/// the direction from the center of a chunk to the camera, rounded to ``SORT_STEPS``
+/// the transparent faces only need to be sorted again when it changes
+/// inside of the chunk the position of the camera is rounded instead, as the direction changes too fast there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkSortKey {
+    Outside([i8; 3]),
+    Inside([i8; 3]),
+}
+
+impl ChunkSortKey {
+    /// ``camera`` is in octree space of the chunk, see ``VoxelWorld::to_chunk``
+    #[must_use]
+    pub fn new(camera: DVec3) -> Self {
+        if camera.abs().max_element() <= 1.0 {
+            let cell = (camera * SORT_STEPS).round().as_ivec3();
+            return Self::Inside(cell.to_array().map(|v| v as i8));
+        }
+
+        let dir = (camera.normalize() * SORT_STEPS).round().as_ivec3();
+        Self::Outside(dir.to_array().map(|v| v as i8))
+    }
+}
+
+/// the indices of the transparent faces sorted back to front, the faces are quads like in ``ChunkMesh::transparent``
+/// ``centers`` are the centers of the faces, ``camera`` is in the same space
+#[must_use]
+pub fn sort_faces(centers: &[Vec3], camera: Vec3) -> Vec<u32> {
+    let mut order: Vec<_> = (0..centers.len() as u32).collect();
+    order.sort_by(|&a, &b| {
+        let a = centers[a as usize].distance_squared(camera);
+        let b = centers[b as usize].distance_squared(camera);
+        b.total_cmp(&a)
+    });
+
+    order
+        .into_iter()
+        .flat_map(|face| [0, 1, 2, 0, 2, 3].map(|index| face * 4 + index))
+        .collect()
+}
+
+/// sort the coords of chunks so the one furthest away from the camera comes first,
+/// the order the transparent chunks have to be drawn in
+pub fn back_to_front(world: &VoxelWorld, coords: &mut [IVec3], camera: DVec3) {
+    coords.sort_by(|a, b| {
+        let a = world.to_world(*a, DVec3::ZERO).distance_squared(camera);
+        let b = world.to_world(*b, DVec3::ZERO).distance_squared(camera);
+        b.total_cmp(&a)
+    });
+}
+
+/// the transparent faces of a chunk, the faces are sorted in place in the index buffer
+pub struct TransparentChunk {
+    pulled_buffers: PulledBuffers,
+    index_buffer: Arc<Buffer>,
+    index_count: u32,
+    centers: Vec<Vec3>,
+    /// the key of the last sort, None if it hasn't been sorted yet
+    key: Option<ChunkSortKey>,
+}
+
+impl TransparentChunk {
+    /// the draw for a batch with ``VoxelMeshMaterials::transparent``
+    #[must_use]
+    pub fn draw(&self) -> DrawData {
+        DrawData {
+            pulled_buffers: Some(self.pulled_buffers),
+            index_buffer: Some(self.index_buffer.clone()),
+            index_type: vk::IndexType::UINT32,
+            index_count: self.index_count,
+            instance_count: 1,
+            ..Default::default()
+        }
+    }
+
+    /// sort the faces back to front if the camera crossed a threshold since the last sort,
+    /// ``camera`` is in octree space of the chunk, see ``VoxelWorld::to_chunk``
+    /// returns if the faces were sorted
+    pub fn sort(&mut self, camera: DVec3) -> bool {
+        let key = ChunkSortKey::new(camera);
+        if self.key == Some(key) {
+            return false;
+        }
+
+        // the frames in flight might draw a mix of the old and new order, which is only visible for a frame
+        self.index_buffer
+            .write(0, &sort_faces(&self.centers, camera.as_vec3()));
+        self.key = Some(key);
+        true
+    }
+}
+
+/// the materials chunk meshes are drawn with
+pub struct VoxelMeshMaterials {
+    /// only there if the device supports mesh shaders
+    pub mesh_shading: Option<Arc<Material>>,
+    pub standard: Arc<Material>,
+    /// draws the faces of a ``TransparentChunk``, blended in the order the batches and draws were added
+    pub transparent: Arc<Material>,
+}
+
+impl VoxelMeshMaterials {
+    /// load ``shaders/voxel_mesh.spv`` and create the materials,
+    /// None if the shader hasn't been built with ``build.sh`` yet
+    /// # Errors
+    /// if the shader module couldn't be created
+    pub fn load(renderer: &mut RenderHandler) -> VkResult<Option<Self>> {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/voxel_mesh.spv");
+        let Ok(code) = std::fs::read(path) else {
+            eprintln!("{path} is missing, chunks can't be meshed until the shaders are built with build.sh");
+            return Ok(None);
+        };
+
+        let byte_code = ash::util::read_spv(&mut Cursor::new(code))
+            .map_err(|_| vk::Result::ERROR_INVALID_SHADER_NV)?;
+
+        let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+        let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+        let stage = |name, stage| {
+            vk::PipelineShaderStageCreateInfo::default()
+                .name(name)
+                .stage(stage)
+                .module(module)
+        };
+
+        let mesh_shading = renderer.device.features.mesh_shader.then(|| {
+            renderer.create_material(MaterialCreateInfo {
+                viewport: ViewportMode::default(),
+                shaders: vec![
+                    stage(c"task_main", vk::ShaderStageFlags::TASK_EXT),
+                    stage(c"mesh_main", vk::ShaderStageFlags::MESH_EXT),
+                    stage(c"fragment_main", vk::ShaderStageFlags::FRAGMENT),
+                ],
+                ..Default::default()
+            })
+        });
+
+        let standard = renderer.create_material(MaterialCreateInfo {
+            cull_mode: CullingMode::None,
+            viewport: ViewportMode::default(),
+            vertex_pulling: true,
+            shaders: vec![
+                stage(c"vertex_main", vk::ShaderStageFlags::VERTEX),
+                stage(c"fragment_main", vk::ShaderStageFlags::FRAGMENT),
+            ],
+            ..Default::default()
+        });
+
+        let transparent = renderer.create_material(MaterialCreateInfo {
+            cull_mode: CullingMode::None,
+            viewport: ViewportMode::default(),
+            vertex_pulling: true,
+            transparency: Transparency::Sorted,
+            shaders: vec![
+                stage(c"vertex_main", vk::ShaderStageFlags::VERTEX),
+                stage(c"fragment_main", vk::ShaderStageFlags::FRAGMENT),
+            ],
+            ..Default::default()
+        });
+
+        // the pipelines don't need the module anymore
+        unsafe { renderer.device.destroy_shader_module(module, None) };
+
+        Ok(Some(Self {
+            mesh_shading: mesh_shading.and_then(|material| renderer.material(material)),
+            standard: renderer.material(standard).unwrap(),
+            transparent: renderer.material(transparent).unwrap(),
+        }))
+    }
+
+    /// the material the draws from ``upload`` have to be drawn with
+    #[must_use]
+    pub fn material(&self) -> &Arc<Material> {
+        self.mesh_shading.as_ref().unwrap_or(&self.standard)
+    }
+
+    /// upload the mesh and return the draw for it, uses mesh shaders if they are supported
+    /// None if the mesh is empty or there are no free storage buffer slots left
+    /// # Errors
+    /// if there is no space left to allocate the buffers
+    pub fn upload(
+        &self,
+        renderer: &mut RenderHandler,
+        mesh: &VoxelMesh,
+    ) -> VkResult<Option<DrawData>> {
+        if mesh.indices.is_empty() {
+            return Ok(None);
+        }
+
+        let meshlets = self.mesh_shading.as_ref().map(|_| {
+            let positions: Vec<_> = mesh.vertices.iter().map(|v| v.position).collect();
+            MeshletMesh::build(&positions, &mesh.indices)
+        });
+
+        let mut data = vec![0; size_of::<ChunkHeader>()];
+        data.extend_from_slice(as_bytes(&mesh.vertices));
+
+        let mut header = ChunkHeader {
+            meshlet_count: 0,
+            meshlets: 0,
+            vertex_indices: 0,
+            triangles: 0,
+        };
+
+        if let Some(meshlets) = &meshlets {
+            header.meshlet_count = meshlets.meshlets.len() as u32;
+            header.meshlets = data.len() as u32;
+            data.extend_from_slice(as_bytes::<Meshlet>(&meshlets.meshlets));
+            header.vertex_indices = data.len() as u32;
+            data.extend_from_slice(as_bytes(&meshlets.vertices));
+            header.triangles = data.len() as u32;
+            data.extend_from_slice(as_bytes(&meshlets.triangles));
+        }
+
+        data[..size_of::<ChunkHeader>()].copy_from_slice(as_bytes(&[header]));
+
+        let chunk_buffer = Buffer::new(
+            renderer.device.clone(),
+            data.len() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        chunk_buffer.write(0, &data);
+
+        let Some(pulled_buffers) = renderer.push_pulled_buffers(chunk_buffer, None) else {
+            return Ok(None);
+        };
+
+        if meshlets.is_some() {
+            return Ok(Some(DrawData {
+                pulled_buffers: Some(pulled_buffers),
+                instance_count: 1,
+                mesh_tasks: Some(header.meshlet_count.div_ceil(TASK_GROUP_SIZE)),
+                ..Default::default()
+            }));
+        }
+
+        let index_buffer = Buffer::new(
+            renderer.device.clone(),
+            size_of_val(mesh.indices.as_slice()) as u64,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        index_buffer.write(0, &mesh.indices);
+
+        Ok(Some(DrawData {
+            pulled_buffers: Some(pulled_buffers),
+            index_buffer: Some(index_buffer),
+            index_type: vk::IndexType::UINT32,
+            index_count: mesh.indices.len() as u32,
+            instance_count: 1,
+            ..Default::default()
+        }))
+    }
+
+    /// upload the transparent faces of a chunk, they are always drawn with an index buffer, also with mesh shaders
+    /// the faces have to be sorted before the first draw, see ``TransparentChunk::sort``
+    /// None if the mesh is empty or there are no free storage buffer slots left
+    /// # Errors
+    /// if there is no space left to allocate the buffers
+    pub fn upload_transparent(
+        &self,
+        renderer: &mut RenderHandler,
+        mesh: &VoxelMesh,
+    ) -> VkResult<Option<TransparentChunk>> {
+        if mesh.indices.is_empty() {
+            return Ok(None);
+        }
+
+        // a header without meshlets
+        let mut data = vec![0; size_of::<ChunkHeader>()];
+        data.extend_from_slice(as_bytes(&mesh.vertices));
+
+        let chunk_buffer = Buffer::new(
+            renderer.device.clone(),
+            data.len() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+        chunk_buffer.write(0, &data);
+
+        let Some(pulled_buffers) = renderer.push_pulled_buffers(chunk_buffer, None) else {
+            return Ok(None);
+        };
+
+        let index_buffer = Buffer::new(
+            renderer.device.clone(),
+            size_of_val(mesh.indices.as_slice()) as u64,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        let centers = mesh
+            .vertices
+            .chunks_exact(4)
+            .map(|face| face.iter().map(|v| Vec3::from(v.position)).sum::<Vec3>() / 4.0)
+            .collect();
+
+        Ok(Some(TransparentChunk {
+            pulled_buffers,
+            index_buffer,
+            index_count: mesh.indices.len() as u32,
+            centers,
+            key: None,
+        }))
+    }
+}
+
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    // every type here is repr(C) without padding
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), size_of_val(data)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mesh_chunks, mesh_octree, sort_faces, ChunkSortKey};
+    use crate::world::{
+        chunks::{Chunk, VoxelWorld},
+        palette::VoxelPalette,
+        svo::Octree,
+    };
+    use jobs::JobSystem;
+    use math::{dvec3, ivec3, vec3, Color};
+
+    #[test]
+    fn single_voxel_has_every_face() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(-0.75, -0.75, -0.75), 3, 2);
+
+        let mesh = mesh_octree(&octree, 2, &VoxelPalette::default()).opaque;
+        assert_eq!(mesh.vertices.len(), 6 * 4);
+        assert_eq!(mesh.indices.len(), 6 * 6);
+        assert!(mesh.vertices.iter().all(|v| v.data & 0xFF == 3));
+
+        // the faces are on the sides of the cell
+        for vertex in &mesh.vertices {
+            for axis in vertex.position {
+                assert!(axis == -1.0 || axis == -0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn faces_between_filled_voxels_are_skipped() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(-0.75, -0.75, -0.75), 3, 2);
+        octree.write(dvec3(-0.25, -0.75, -0.75), 5, 2);
+
+        let mesh = mesh_octree(&octree, 2, &VoxelPalette::default()).opaque;
+        assert_eq!(mesh.indices.len(), 10 * 6);
+
+        // no face lies on the plane between the two cells
+        let shared = mesh
+            .vertices
+            .chunks_exact(4)
+            .filter(|face| face.iter().all(|v| v.position[0] == -0.5));
+        assert_eq!(shared.count(), 0);
+    }
+
+    #[test]
+    fn chunks_are_meshed_like_their_octrees() {
+        let mut world = VoxelWorld::new(1.0, 1, 1);
+        for coord in [ivec3(0, 0, 0), ivec3(1, 0, 0), ivec3(0, -1, 2)] {
+            let mut octree = Octree::new();
+            octree.write(dvec3(-0.75, -0.75, -0.75), 3, 2);
+            octree.write(dvec3(0.25, -0.25, 0.75), coord.x as u8 + 1, 2);
+            world.insert(coord, Chunk::new(octree));
+        }
+
+        let jobs = JobSystem::new(2);
+        let coords = [ivec3(0, -1, 2), ivec3(5, 5, 5), ivec3(1, 0, 0)];
+        let palette = VoxelPalette::default();
+        let meshes = mesh_chunks(&jobs, &world, &coords, 2, &palette);
+
+        // the chunk that isn't loaded is skipped
+        assert_eq!(meshes.len(), 2);
+        for (coord, mesh) in meshes {
+            let octree = &world.chunk(coord).unwrap().octree;
+            assert_eq!(mesh, mesh_octree(octree, 2, &palette));
+        }
+    }
+
+    #[test]
+    fn transparent_faces_are_meshed_separately() {
+        let mut palette = VoxelPalette::default();
+        palette.set_color(4, Color::rgba(0.2, 0.4, 1.0, 0.5));
+
+        let mut octree = Octree::new();
+        octree.write(dvec3(-0.75, -0.75, -0.75), 3, 2);
+        octree.write(dvec3(-0.25, -0.75, -0.75), 4, 2);
+        octree.write(dvec3(0.25, -0.75, -0.75), 4, 2);
+
+        let mesh = mesh_octree(&octree, 2, &palette);
+        // the opaque voxel can be seen through the glass next to it
+        assert_eq!(mesh.opaque.indices.len(), 6 * 6);
+        // the faces between the two glass voxels and the one covered by the opaque voxel are skipped
+        assert_eq!(mesh.transparent.indices.len(), 9 * 6);
+        assert!(mesh.transparent.vertices.iter().all(|v| v.data & 0xFF == 4));
+    }
+
+    #[test]
+    fn sort_keys_change_at_thresholds() {
+        let far = ChunkSortKey::new(dvec3(10.0, 0.0, 0.0));
+        // a small move doesn't change the direction enough
+        assert_eq!(far, ChunkSortKey::new(dvec3(10.0, 0.5, 0.0)));
+        assert_ne!(far, ChunkSortKey::new(dvec3(10.0, 5.0, 0.0)));
+
+        assert!(matches!(
+            ChunkSortKey::new(dvec3(0.5, 0.0, 0.0)),
+            ChunkSortKey::Inside(_)
+        ));
+        assert_ne!(
+            ChunkSortKey::new(dvec3(0.1, 0.0, 0.0)),
+            ChunkSortKey::new(dvec3(0.9, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn faces_are_sorted_back_to_front() {
+        let centers = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, 2.0),
+        ];
+        let indices = sort_faces(&centers, vec3(0.0, 0.0, -1.0));
+        assert_eq!(indices.len(), 3 * 6);
+        assert_eq!(&indices[..6], &[4, 5, 6, 4, 6, 7]);
+        assert_eq!(&indices[6..12], &[8, 9, 10, 8, 10, 11]);
+        assert_eq!(&indices[12..], &[0, 1, 2, 0, 2, 3]);
+    }
+}
@@ -1,36 +1,244 @@
-use ash::vk;
-use std::{io::Cursor, sync::Arc, time::Instant};
-use svo::OctreeNode;
+use crate::{
+    assets::{AssetRegistry, Mesh},
+    input::Input,
+    replay::VoxelWrite,
+};
+use animation::SkinnedMesh;
+use ash::{prelude::VkResult, vk};
+use brush::{Brush, EditHistory, VoxelPatch};
+use camera_effects::CameraEffects;
+use distance_field::NO_DISTANCE_FIELD;
+use fluids::FluidType;
+use gizmo::Gizmo;
+use jobs::JobSystem;
+use palette::VoxelPalette;
+use physics::{Aabb, MoveResult};
+use picking::Picked;
+use preview::BrushPreview;
+use rng::Rng;
+use selection::Selection;
+use std::{io::Cursor, ops::Range, sync::Arc, time::Instant};
+use svo::{FlatOctreeNode, Octree, OctreeLayout};
 
-use camera::Camera;
-use math::{vec4, Mat4, Transform, Vec4};
+use hierarchy::{Entities, Entity};
+use math::{vec4, Camera, DVec3, Mat4, Projection, Transform, Vec2, Vec3, Vec4};
 use rendering::{
+    assets::texture::TextureHandle,
     handler::{
-        render_batch::{DrawData, RenderBatch},
+        environment::Environment,
+        gi::GiVolume,
+        particles::{EmitterConfig, ParticleSystemCreateInfo},
+        picking::PickResult,
+        probes::{ProbeCamera, ProbeView},
+        render_batch::{BatchUsage, DrawData, RenderBatch},
+        volumetric::FluidVolume,
         RenderHandler,
     },
-    types::{Material, MaterialCreateInfo, UDim2, VertexInput},
+    types::{Material, MaterialCreateInfo, VertexLayout, ViewportMode},
     vulkan::Buffer,
 };
 
-mod camera;
+pub mod animation;
+pub mod brush;
+pub mod camera_effects;
+pub mod chunk_io;
+pub mod chunks;
+pub mod distance_field;
+pub mod fluids;
+pub mod gi;
+pub mod gizmo;
+pub mod gltf;
+pub mod hierarchy;
+pub mod meshing;
+pub mod palette;
+pub mod physics;
+pub mod picking;
+pub mod preview;
+pub mod rng;
+pub mod selection;
 pub mod svo;
+pub mod vox;
 
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct UniformData {
+    /// jittered for temporal anti aliasing
     view_proj: Mat4,
     cam_pos: Vec4,
     time: f32,
+    /// the texture of the first octree's ``distance_field``, ``NO_DISTANCE_FIELD`` until it's baked
+    distance_field: u32,
+    _padding: [f32; 2],
+    /// without jitter, used to compute the velocity
+    unjittered_view_proj: Mat4,
+    /// ``unjittered_view_proj`` of the last frame
+    prev_view_proj: Mat4,
+    /// the inverse of ``view_proj``, used to get world positions back from the depth target
+    inverse_view_proj: Mat4,
+}
+rendering::shader_struct!(
+    UniformData,
+    Std140 {
+        view_proj,
+        cam_pos,
+        time,
+        distance_field,
+        unjittered_view_proj,
+        prev_view_proj,
+        inverse_view_proj,
+    }
+);
+
+/// a particle emitter, changes to the config are applied to the renderer every frame
+pub struct ParticleEmitter {
+    /// the index of the particle system in the renderer
+    pub system: usize,
+    pub config: EmitterConfig,
+    /// kept to create the particle system again after the renderer was reinitialized
+    shader_code: Vec<u8>,
+    capacity: u32,
+}
+
+/// the storage buffer index of the voxel palette
+pub const PALETTE_INDEX: usize = 1;
+
+/// something that collides with the voxels, like a character
+/// uses the same space as the octrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Body {
+    pub position: DVec3,
+    /// half the size of the collision box
+    pub half_extents: DVec3,
+}
+
+impl Body {
+    #[must_use]
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_center(self.position, self.half_extents)
+    }
+}
+
+/// entities that are drawn with one instanced draw call
+/// the global transforms of the entities are written to the buffer when one of them or the list changed
+pub struct InstanceGroup {
+    pub entities: Vec<Entity>,
+    /// the instance buffer of the draw call, it needs space for a ``Mat4`` for every entity
+    /// see ``VertexInput::add_instance_matrix``
+    pub buffer: Arc<Buffer>,
+    /// the entities and the ``Entities::propagation`` the buffer was last written with
+    written: Option<(Vec<Entity>, u64)>,
+}
+
+impl InstanceGroup {
+    #[must_use]
+    pub fn new(entities: Vec<Entity>, buffer: Arc<Buffer>) -> Self {
+        Self {
+            entities,
+            buffer,
+            written: None,
+        }
+    }
+
+    fn needs_write(&self, entities: &Entities) -> bool {
+        self.written.as_ref().is_none_or(|(written, propagation)| {
+            *written != self.entities
+                || self
+                    .entities
+                    .iter()
+                    .any(|entity| entities.changed_since(*entity, *propagation))
+        })
+    }
 }
 
 pub struct World {
     pub camera: Camera,
+    /// shake, follow and fov kick, added to ``camera`` for the frame in ``update``
+    pub camera_effects: CameraEffects,
     pub start_time: Instant,
     pub uniform_buffer: Arc<Buffer>,
     pub material: Arc<Material>,
-    pub voxel_octrees: Vec<OctreeNode>,
+    pub voxel_octrees: Vec<Octree>,
     pub voxel_buffers: Vec<Arc<Buffer>>,
+    /// the storage buffer index every octree buffer is bound to
+    pub voxel_storage_indices: Vec<usize>,
+    /// tracks which nodes of ``voxel_octrees`` need to be copied to ``voxel_buffers``
+    pub voxel_layouts: Vec<OctreeLayout>,
+    /// the ``distance_field`` of every octree, None until ``sync_renderer`` baked it
+    pub voxel_distance_fields: Vec<Option<TextureHandle>>,
+    /// the colors of all octrees, bound as storage buffer ``PALETTE_INDEX``
+    pub palette_buffer: Arc<Buffer>,
+    palette: VoxelPalette,
+    pub particle_emitters: Vec<ParticleEmitter>,
+    /// moved with ``move_and_collide``
+    pub bodies: Vec<Body>,
+    /// their global transforms are updated every frame
+    pub entities: Entities,
+    pub instance_groups: Vec<InstanceGroup>,
+    /// animated every frame, their draws have to be added to a batch with ``animation::load_material``
+    pub skinned_meshes: Vec<SkinnedMesh>,
+    /// lighting and fog, applied to the renderer every frame
+    pub environment: Environment,
+    /// the shared meshes, textures, materials, shaders and octrees, changed files are reloaded every frame
+    pub assets: AssetRegistry,
+    /// the workers of the renderer, for meshing, chunk io and other background work
+    pub jobs: Arc<JobSystem>,
+    /// seeded from ``Settings::seed``, systems use their own ``Rng::fork`` of it
+    /// so generated content is the same every run
+    pub rng: Rng,
+    /// the mouse, updated from the window events before the tasks run
+    pub input: Input,
+    /// the time step of the next ``update`` instead of the time since the last one
+    /// set by the frame recorder and the playback, so a replay advances the animations the same way
+    pub fixed_delta: Option<f32>,
+    /// multiplies the time step of ``update``, see ``Application::set_time_scale``
+    pub time_scale: f32,
+    /// the tasks don't run and ``update`` doesn't advance the time, see ``Application::set_paused``
+    pub paused: bool,
+    /// run one frame while ``paused``, see ``step_once``
+    step: bool,
+    /// the scaled time step of the last ``update``
+    delta: f32,
+    /// the scaled time of all ``update`` calls, it stands still while ``paused``
+    time: f64,
+    /// the transform handles of the editor, moved with ``gizmo::gizmo_task``
+    pub gizmo: Option<Gizmo>,
+    /// the selected and hovered things, they are drawn with an outline
+    pub selection: Selection,
+    /// the brush ``brush::brush_task`` edits the octrees with, None while the brush isn't used
+    pub brush: Option<Brush>,
+    /// the edits of the brush, see ``undo_edit`` and ``redo_edit``
+    pub edits: EditHistory,
+    /// the ghost of the cells ``brush`` would change under the cursor, drawn in ``sync_renderer``
+    pub preview: Option<BrushPreview>,
+    /// None if there is no output device, see ``Application::new``
+    #[cfg(feature = "audio")]
+    pub audio: Option<crate::audio::Audio>,
+    /// the connection to the other worlds, updated by ``net::net_task``
+    #[cfg(feature = "net")]
+    pub net: Option<crate::net::Net>,
+    /// the lua scripts, run by ``scripting::script_task``
+    #[cfg(feature = "lua")]
+    pub scripts: Option<crate::scripting::Scripts>,
+    /// the pixel ``sync_renderer`` asks the renderer for
+    pick_request: Option<[u32; 2]>,
+    /// the newest pick the renderer answered
+    last_pick: Option<PickResult>,
+    /// the unjittered view projection of the last ``update``
+    prev_view_proj: Mat4,
+    /// when ``update`` was called the last time, to advance the animations
+    last_update: Instant,
+    /// the writes of ``write_voxel`` since the last ``update``, taken by the frame recorder
+    voxel_writes: Vec<VoxelWrite>,
+    /// the global illumination voxels are rebuilt in ``sync_renderer``, set when an octree or the palette changed
+    gi_dirty: bool,
+    /// the palette colors that are rendered as fluids, see ``set_fluids``
+    fluids: Vec<FluidType>,
+    /// the fluid voxels are rebuilt in ``sync_renderer``, set when an octree, the palette or the fluids changed
+    fluids_dirty: bool,
+    /// the octrees whose distance field is baked again in ``sync_renderer``, set when they were added or uploaded
+    distance_fields_dirty: Vec<bool>,
+    /// something the scene is drawn from changed since the last ``take_scene_changed``
+    scene_changed: bool,
 }
 
 impl World {
@@ -39,18 +247,17 @@ impl World {
     pub fn new(renderer: &mut RenderHandler) -> Self {
         let image_res = renderer.get_swapchain_resolution();
 
-        let camera = Camera {
-            transform: Transform::IDENTITY,
-            aspect: image_res.width as f32 / image_res.height as f32,
-            fovy: 70.0,
-            znear: 0.01,
-            zfar: 100.0,
-        };
+        let camera = Camera::new(
+            Transform::IDENTITY,
+            image_res.width as f32 / image_res.height as f32,
+            Projection::default(),
+        );
 
         let uniform_buffer = Buffer::new(
             renderer.device.clone(),
             std::mem::size_of::<UniformData>() as u64,
-            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            // the environment probes replace the camera on the gpu
+            vk::BufferUsageFlags::UNIFORM_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
             vk::MemoryPropertyFlags::DEVICE_LOCAL | vk::MemoryPropertyFlags::HOST_VISIBLE,
         )
         .unwrap();
@@ -66,24 +273,46 @@ impl World {
         vertex_buffer.write(0, &CUBE_VERTECIES);
 
         let mut batch = RenderBatch::default();
+        batch.set_name("voxels");
+        // the octrees are read from storage buffers, the draw itself never changes
+        batch.set_usage(BatchUsage::Static);
+        // the octrees fill the space from -1 to 1
+        batch.set_bounds(math::Aabb::new(Vec3::NEG_ONE, Vec3::ONE));
 
-        renderer.set_uniform_buffer(uniform_buffer.clone(), 0);
+        let uniform_handle = renderer.import_buffer(uniform_buffer.clone());
+        renderer.bind_uniform_buffer(uniform_handle, Some(0));
 
+        let start_time = Instant::now();
+        renderer.set_probe_camera(ProbeCamera {
+            buffer: uniform_buffer.clone(),
+            write: Box::new(move |view| probe_uniforms(view, start_time)),
+        });
+
+        let palette_buffer = Buffer::new(
+            renderer.device.clone(),
+            std::mem::size_of::<VoxelPalette>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .unwrap();
+
+        palette_buffer.write(0, VoxelPalette::grayscale().as_bytes());
+        let palette_handle = renderer.import_buffer(palette_buffer.clone());
+        renderer.bind_storage_buffer(palette_handle, Some(PALETTE_INDEX));
+
+        // the shader traces the first octree
         let cube_draw = DrawData {
             vertex_count: CUBE_VERTECIES.len() as u32,
             vertex_buffer: Some(vertex_buffer),
+            object_id: Picked::Octree(0).object_id(),
             ..Default::default()
         };
 
         batch.add_draw_call(cube_draw);
 
-        let vertex_input = VertexInput {
-            attributes: vec![vk::VertexInputAttributeDescription::default()
-                .format(vk::Format::R32G32B32A32_SFLOAT)],
-            bindings: vec![vk::VertexInputBindingDescription::default()
-                .input_rate(vk::VertexInputRate::VERTEX)
-                .stride(std::mem::size_of::<[f32; 4]>() as u32)],
-        };
+        let vertex_input = VertexLayout::new()
+            .attr::<[f32; 4]>("position")
+            .vertex_input();
 
         let mut code = Cursor::new(include_bytes!("../../shaders/shader.spv"));
         let byte_code = ash::util::read_spv(&mut code).unwrap();
@@ -93,11 +322,11 @@ impl World {
 
         let material_info = MaterialCreateInfo {
             cull_mode: rendering::types::CullingMode::Front,
-            viewport: UDim2 {
-                scale: [1.0, 1.0],
-                offset: [0.0, 0.0],
-            },
+            // the raymarched voxels keep their hard edges
+            skip_temporal_aa: true,
+            viewport: ViewportMode::default(),
             vertex_input,
+            object_id: true,
             shaders: vec![
                 vk::PipelineShaderStageCreateInfo::default()
                     .name(c"main")
@@ -108,38 +337,663 @@ impl World {
                     .stage(vk::ShaderStageFlags::FRAGMENT)
                     .module(module),
             ],
+            ..Default::default()
         };
 
-        let material = renderer.load_material(material_info);
+        let material = renderer.create_material(material_info);
+        let material = renderer.material(material).unwrap();
 
         batch.set_material(material.clone());
 
         renderer.add_render_batch(batch);
 
+        let jobs = renderer.jobs().clone();
+        let mut assets = AssetRegistry::default();
+        assets.set_jobs(jobs.clone());
+
         Self {
+            prev_view_proj: camera.build_unjittered_proj(),
             camera,
+            camera_effects: CameraEffects::default(),
             uniform_buffer,
             material,
-            start_time: Instant::now(),
+            start_time,
             voxel_buffers: vec![],
+            voxel_storage_indices: vec![],
             voxel_octrees: vec![],
+            voxel_layouts: vec![],
+            voxel_distance_fields: vec![],
+            palette_buffer,
+            palette: VoxelPalette::grayscale(),
+            particle_emitters: vec![],
+            bodies: vec![],
+            entities: Entities::new(),
+            instance_groups: vec![],
+            skinned_meshes: vec![],
+            environment: Environment::default(),
+            assets,
+            jobs,
+            rng: Rng::default(),
+            input: Input {
+                viewport: Vec2::new(image_res.width as f32, image_res.height as f32),
+                ..Default::default()
+            },
+            fixed_delta: None,
+            time_scale: 1.0,
+            paused: false,
+            step: false,
+            delta: 0.0,
+            time: 0.0,
+            gizmo: None,
+            selection: Selection::default(),
+            brush: None,
+            edits: EditHistory::default(),
+            preview: None,
+            #[cfg(feature = "audio")]
+            audio: None,
+            #[cfg(feature = "net")]
+            net: None,
+            #[cfg(feature = "lua")]
+            scripts: None,
+            pick_request: None,
+            last_pick: None,
+            last_update: Instant::now(),
+            voxel_writes: vec![],
+            gi_dirty: true,
+            fluids: vec![],
+            fluids_dirty: true,
+            distance_fields_dirty: vec![],
+            scene_changed: true,
+        }
+    }
+
+    /// add an octree that is rendered from the given buffer
+    /// the whole octree is copied to the buffer, later changes are uploaded with ``upload_octree``
+    /// ``storage_index`` is the storage buffer index the buffer is bound to
+    /// returns the index in ``voxel_octrees``
+    pub fn add_octree(
+        &mut self,
+        mut octree: Octree,
+        buffer: Arc<Buffer>,
+        storage_index: usize,
+    ) -> usize {
+        let layout = OctreeLayout::new(&mut octree);
+        buffer.write(0, layout.nodes());
+
+        self.voxel_octrees.push(octree);
+        self.voxel_buffers.push(buffer);
+        self.voxel_storage_indices.push(storage_index);
+        self.voxel_layouts.push(layout);
+        self.voxel_distance_fields.push(None);
+        self.distance_fields_dirty.push(true);
+        self.gi_dirty = true;
+        self.fluids_dirty = true;
+        self.scene_changed = true;
+        self.voxel_octrees.len() - 1
+    }
+
+    /// write a voxel to an octree, see ``Octree::write``
+    /// the write is kept for the frame recorder, the octree still has to be uploaded with ``upload_octree``
+    /// # Panics
+    /// if there is no octree with that index
+    pub fn write_voxel(&mut self, octree: usize, pos: DVec3, color: u8, layer: usize) {
+        self.voxel_octrees[octree].write(pos, color, layer);
+        self.voxel_writes.push(VoxelWrite {
+            octree: octree as u32,
+            pos,
+            color,
+            layer: layer as u8,
+        });
+    }
+
+    /// apply ``brush`` around ``center`` in octree space and upload the octree
+    /// the edit is added to ``edits``, ``merge`` adds it to the last edit instead, for the edits of one stroke
+    /// returns false if nothing changed
+    /// # Panics
+    /// if there is no brush or no octree with that index
+    pub fn apply_brush(&mut self, octree: usize, center: DVec3, merge: bool) -> bool {
+        let brush = self.brush.as_mut().expect("there is no brush");
+        let layer = brush.layer;
+        let writes = brush.writes(&self.voxel_octrees[octree], center);
+        if writes.is_empty() {
+            return false;
+        }
+
+        let to_write = |(pos, color, layer): (DVec3, u8, usize)| VoxelWrite {
+            octree: octree as u32,
+            pos,
+            color,
+            layer: layer as u8,
+        };
+        let mut patch = VoxelPatch::default();
+        for (pos, color) in writes {
+            patch.before.extend(
+                self.voxel_octrees[octree]
+                    .capture(pos, layer)
+                    .into_iter()
+                    .map(to_write),
+            );
+            patch.after.push(to_write((pos, color, layer)));
+            self.write_voxel(octree, pos, color, layer);
+        }
+
+        self.upload_octree(octree);
+        self.edits.push(patch, merge);
+        true
+    }
+
+    /// undo the newest edit in ``edits`` and upload the octrees it changed
+    /// returns false if there was nothing to undo
+    pub fn undo_edit(&mut self) -> bool {
+        let Some(patch) = self.edits.undo() else {
+            return false;
+        };
+        let writes = patch.before.clone();
+        self.apply_writes(&writes);
+        true
+    }
+
+    /// redo the newest edit that was undone and upload the octrees it changed
+    /// returns false if there was nothing to redo
+    pub fn redo_edit(&mut self) -> bool {
+        let Some(patch) = self.edits.redo() else {
+            return false;
+        };
+        let writes = patch.after.clone();
+        self.apply_writes(&writes);
+        true
+    }
+
+    fn apply_writes(&mut self, writes: &[VoxelWrite]) {
+        let mut touched = vec![];
+        for write in writes {
+            let octree = write.octree as usize;
+            self.write_voxel(octree, write.pos, write.color, write.layer as usize);
+            if !touched.contains(&octree) {
+                touched.push(octree);
+            }
+        }
+        for octree in touched {
+            self.upload_octree(octree);
+        }
+    }
+
+    /// the writes of ``write_voxel`` since the last call or ``update``
+    pub fn take_voxel_writes(&mut self) -> Vec<VoxelWrite> {
+        std::mem::take(&mut self.voxel_writes)
+    }
+
+    /// copy the nodes that changed since the last upload to the buffer of the octree
+    /// returns the ranges of nodes that were written, see ``OctreeLayout::update``
+    /// # Panics
+    /// if the buffer is too small for the octree
+    pub fn upload_octree(&mut self, index: usize) -> Vec<Range<usize>> {
+        let _span = tracing::info_span!("upload octree", index).entered();
+
+        let layout = &mut self.voxel_layouts[index];
+        let buffer = &self.voxel_buffers[index];
+
+        let patches = layout.update(&mut self.voxel_octrees[index]);
+        self.gi_dirty |= !patches.is_empty();
+        self.fluids_dirty |= !patches.is_empty();
+        self.distance_fields_dirty[index] |= !patches.is_empty();
+        self.scene_changed |= !patches.is_empty();
+
+        let size = layout.len() * std::mem::size_of::<FlatOctreeNode>();
+        assert!(
+            size as u64 <= buffer.size(),
+            "the octree needs {size} bytes, but the buffer only has {}",
+            buffer.size()
+        );
+
+        for range in &patches {
+            buffer.write(range.start, &layout.nodes()[range.clone()]);
+        }
+        patches
+    }
+
+    /// move a body and slide along the solid voxels of all octrees it hits
+    /// use ``MoveResult::remaining`` divided by ``dt`` as the new velocity
+    /// # Panics
+    /// if there is no body at that index
+    pub fn move_and_collide(&mut self, body: usize, velocity: DVec3, dt: f64) -> MoveResult {
+        let body = &mut self.bodies[body];
+        let result = physics::move_and_slide(&self.voxel_octrees, &body.aabb(), velocity * dt);
+        body.position += result.motion;
+        result
+    }
+
+    /// replace the colors used by all octrees
+    pub fn set_palette(&mut self, palette: &VoxelPalette) {
+        self.palette_buffer.write(0, palette.as_bytes());
+        self.palette = palette.clone();
+        self.gi_dirty = true;
+        self.fluids_dirty = true;
+        self.scene_changed = true;
+    }
+
+    /// replace the palette colors that are rendered as fluids by the volumetric pass
+    /// the voxels of these colors should be transparent, see ``fluids``
+    pub fn set_fluids(&mut self, fluids: &[FluidType]) {
+        self.fluids = fluids.to_vec();
+        self.fluids_dirty = true;
+        self.scene_changed = true;
+    }
+
+    #[must_use]
+    pub fn fluids(&self) -> &[FluidType] {
+        &self.fluids
+    }
+
+    /// add a new particle emitter
+    /// ``shader_code`` is the compiled ``shaders/particles.slang``
+    /// returns the index in ``particle_emitters``
+    /// # Errors
+    /// if there is no space left to allocate the particles
+    /// # Panics
+    /// if the shader code isn't valid spir-v
+    pub fn add_particle_emitter(
+        &mut self,
+        renderer: &mut RenderHandler,
+        shader_code: &[u8],
+        config: EmitterConfig,
+        capacity: u32,
+    ) -> VkResult<usize> {
+        let mut code = Cursor::new(shader_code);
+        let byte_code = ash::util::read_spv(&mut code).unwrap();
+
+        let module_info = vk::ShaderModuleCreateInfo::default().code(&byte_code);
+        let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+        let compute_stage = |name| {
+            vk::PipelineShaderStageCreateInfo::default()
+                .name(name)
+                .stage(vk::ShaderStageFlags::COMPUTE)
+                .module(module)
+        };
+
+        let info = ParticleSystemCreateInfo {
+            capacity,
+            emitter: config,
+            seed: self
+                .rng
+                .fork(&format!("particles {}", self.particle_emitters.len()))
+                .next_u32(),
+            spawn_shader: compute_stage(c"spawn"),
+            simulate_shader: compute_stage(c"simulate"),
+            finalize_shader: compute_stage(c"finalize"),
+        };
+
+        // the shader module is destroyed together with the material
+        let material = MaterialCreateInfo {
+            viewport: ViewportMode::default(),
+            // the particles move on their own, they have no velocity to reproject with
+            skip_temporal_aa: true,
+            shaders: vec![
+                vk::PipelineShaderStageCreateInfo::default()
+                    .name(c"vs_particle")
+                    .stage(vk::ShaderStageFlags::VERTEX)
+                    .module(module),
+                vk::PipelineShaderStageCreateInfo::default()
+                    .name(c"fs_particle")
+                    .stage(vk::ShaderStageFlags::FRAGMENT)
+                    .module(module),
+            ],
+            ..Default::default()
+        };
+
+        let system = renderer.add_particle_system(&info, material)?;
+
+        self.particle_emitters.push(ParticleEmitter {
+            system,
+            config,
+            shader_code: shader_code.to_vec(),
+            capacity,
+        });
+        Ok(self.particle_emitters.len() - 1)
+    }
+
+    /// create all gpu resources again after ``RenderHandler::reinitialize``
+    /// the octrees are copied to new buffers of the same size and the particle emitters are recreated,
+    /// the instance groups and skinned meshes are removed because their buffers belong to the old device,
+    /// the gizmo keeps its target, it and the brush preview draw with new sprite batches,
+    /// the assets keep their handles, textures loaded in the background are loaded again and meshes have to be inserted again
+    /// # Errors
+    /// if there is no space to allocate the buffers
+    pub fn recreate_gpu_resources(&mut self, renderer: &mut RenderHandler) -> VkResult<()> {
+        let mut new = Self::new(renderer);
+
+        new.set_palette(&self.palette);
+        new.set_fluids(&self.fluids);
+
+        // allocate everything first, so nothing is lost if it fails
+        let buffers = self
+            .voxel_buffers
+            .iter()
+            .map(|buffer| {
+                Buffer::new(
+                    renderer.device.clone(),
+                    buffer.size(),
+                    vk::BufferUsageFlags::STORAGE_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE,
+                )
+            })
+            .collect::<VkResult<Vec<_>>>()?;
+
+        // the emitters get the same seeds again
+        new.rng = self.rng.clone();
+        for emitter in &self.particle_emitters {
+            new.add_particle_emitter(
+                renderer,
+                &emitter.shader_code,
+                emitter.config,
+                emitter.capacity,
+            )?;
+        }
+
+        let octrees = self.voxel_octrees.drain(..);
+        for ((octree, buffer), storage_index) in
+            octrees.zip(buffers).zip(&self.voxel_storage_indices)
+        {
+            let handle = renderer.import_buffer(buffer.clone());
+            renderer.bind_storage_buffer(handle, Some(*storage_index));
+            new.add_octree(octree, buffer, *storage_index);
+        }
+
+        new.camera = self.camera.clone();
+        new.camera_effects = self.camera_effects.clone();
+        new.start_time = self.start_time;
+        new.time_scale = self.time_scale;
+        new.paused = self.paused;
+        new.time = self.time;
+        new.bodies = std::mem::take(&mut self.bodies);
+        new.entities = std::mem::take(&mut self.entities);
+        new.environment = self.environment;
+        new.assets = std::mem::take(&mut self.assets);
+        new.assets.set_jobs(new.jobs.clone());
+        new.assets.drop_gpu_assets::<Mesh>();
+        new.assets.reload_gpu_assets::<TextureHandle>();
+        new.input = self.input;
+        new.gizmo = self.gizmo.take();
+        new.selection = std::mem::take(&mut self.selection);
+        new.brush = self.brush.take();
+        new.edits = std::mem::take(&mut self.edits);
+        new.preview = self.preview.take();
+        if let Some(preview) = &mut new.preview {
+            preview.recreate(renderer)?;
+        }
+        if let Some(gizmo) = &mut new.gizmo {
+            gizmo.recreate(renderer)?;
+        }
+        #[cfg(feature = "audio")]
+        {
+            new.audio = self.audio.take();
+        }
+        #[cfg(feature = "net")]
+        {
+            new.net = self.net.take();
+        }
+        #[cfg(feature = "lua")]
+        {
+            new.scripts = self.scripts.take();
+        }
+
+        *self = new;
+        Ok(())
+    }
+
+    /// find out what is drawn under ``screen_pos``, in pixels from the top left corner
+    /// the renderer copies the object id of the pixel in the next frame and the answer shows up in ``picked``
+    /// a few frames later, a newer pick replaces the one that wasn't sent to the renderer yet
+    pub fn pick(&mut self, screen_pos: Vec2) {
+        if screen_pos.cmpge(Vec2::ZERO).all() {
+            self.pick_request = Some([screen_pos.x as u32, screen_pos.y as u32]);
+        }
+    }
+
+    /// the answer to the newest ``pick`` that arrived, with the pixel it was asked for
+    /// the outer None means no answer arrived yet, the inner one means nothing was drawn at the pixel
+    /// despawned entities aren't returned
+    #[must_use]
+    pub fn picked(&self) -> Option<([u32; 2], Option<Picked>)> {
+        let pick = self.last_pick?;
+        let picked = Picked::from_object_id(pick.object_id).filter(|picked| match picked {
+            Picked::Entity(entity) => self.entities.contains(*entity),
+            Picked::Octree(index) => *index < self.voxel_octrees.len(),
+        });
+        Some((pick.pixel, picked))
+    }
+
+    /// apply the changes made by tasks to the renderer
+    /// like the environment and the emitter configs
+    /// the global illumination and fluid voxels and the distance fields are rebuilt after an octree was added or uploaded
+    pub fn sync_renderer(&mut self, renderer: &mut RenderHandler) {
+        // changing the environment draws the scene again
+        if *renderer.environment() != self.environment {
+            *renderer.environment_mut() = self.environment;
+        }
+
+        if let Some(pixel) = self.pick_request.take() {
+            renderer.request_pick(pixel);
+        }
+        if let Some(pick) = renderer.take_pick_result() {
+            self.last_pick = Some(pick);
+        }
+
+        for emitter in &self.particle_emitters {
+            if let Some(system) = renderer.get_particle_system_mut(emitter.system) {
+                system.emitter = emitter.config;
+            }
+        }
+
+        if let Some(gizmo) = &self.gizmo {
+            gizmo.draw(renderer, &self.entities, &self.camera);
         }
+
+        if let Some(preview) = &self.preview {
+            preview.draw(
+                renderer,
+                self.brush.as_ref(),
+                &self.voxel_octrees,
+                &self.palette,
+                &self.camera,
+                &self.input,
+            );
+        }
+
+        if renderer.gi_enabled() && std::mem::take(&mut self.gi_dirty) {
+            let _span = tracing::info_span!("voxelize gi").entered();
+            let volume = GiVolume::default();
+            let voxels = gi::voxelize(&self.jobs, &self.voxel_octrees, &self.palette, volume);
+            renderer.set_gi_voxels(volume, &voxels);
+        }
+
+        if std::mem::take(&mut self.fluids_dirty) {
+            let _span = tracing::info_span!("voxelize fluids").entered();
+            let volume = FluidVolume::default();
+            let voxels = if self.fluids.is_empty() {
+                vec![]
+            } else {
+                fluids::voxelize(
+                    &self.jobs,
+                    &self.voxel_octrees,
+                    &self.palette,
+                    &self.fluids,
+                    volume,
+                )
+            };
+            renderer.set_fluid_voxels(volume, &voxels);
+        }
+
+        for (index, dirty) in self.distance_fields_dirty.iter_mut().enumerate() {
+            if !std::mem::take(dirty) {
+                continue;
+            }
+
+            let _span = tracing::info_span!("bake distance field", index).entered();
+            let field = distance_field::bake(&self.voxel_octrees[index]);
+            match distance_field::create_texture(renderer, &field) {
+                Ok(handle) => {
+                    if let Some(old) = self.voxel_distance_fields[index].replace(handle) {
+                        renderer.destroy(old);
+                    }
+                }
+                // the octree is still traced, only slower
+                Err(err) => {
+                    eprintln!("failed to create the distance field of octree {index}: {err}")
+                }
+            }
+        }
+
+        renderer.set_outlines(
+            &self
+                .selection
+                .outlines(&self.entities, self.voxel_octrees.len()),
+            self.camera.projection.near_depth(),
+        );
+    }
+
+    /// draw the scene again in the next frame, for tasks that write to the gpu buffers themselves
+    /// the changes made through the world are noticed on their own, see ``take_scene_changed``
+    pub fn mark_scene_changed(&mut self) {
+        self.scene_changed = true;
+    }
+
+    /// if the camera, the entities, the octrees or anything else the scene is drawn from changed since the last call
+    /// while nothing changed, the renderer can reuse the last scene, see ``RenderHandler::reuse_scene``
+    pub fn take_scene_changed(&mut self) -> bool {
+        std::mem::take(&mut self.scene_changed)
+    }
+
+    /// run the tasks and advance the time for one frame while ``paused``
+    pub fn step_once(&mut self) {
+        self.step = true;
+    }
+
+    /// if the tasks run and ``update`` advances the time in this frame
+    #[must_use]
+    pub fn is_advancing(&self) -> bool {
+        !self.paused || self.step
+    }
+
+    /// start the time step of the next ``update`` now, the time since the last one is skipped
+    pub fn reset_clock(&mut self) {
+        self.last_update = Instant::now();
     }
 
-    pub fn update(&self) {
-        let cam_pos = self.camera.transform.translation;
+    /// the time step of the last ``update`` multiplied with ``time_scale``, 0 while ``paused``
+    /// the tasks run before ``update``, so for them it's the step of the last frame
+    #[must_use]
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// the seconds the world advanced, unlike ``start_time`` it follows ``time_scale`` and ``paused``
+    #[must_use]
+    pub fn time(&self) -> f64 {
+        self.time
+    }
 
-        self.uniform_buffer.write(
+    /// propagate the transforms and write everything the shaders need to the buffers
+    pub fn update(&mut self) {
+        self.scene_changed |= self.entities.propagate();
+
+        let real_dt = self
+            .fixed_delta
+            .take()
+            .unwrap_or_else(|| self.last_update.elapsed().as_secs_f32());
+        self.last_update = Instant::now();
+
+        let dt = if self.is_advancing() {
+            real_dt * self.time_scale
+        } else {
+            0.0
+        };
+        self.step = false;
+        self.delta = dt;
+        self.time += f64::from(dt);
+        // nobody recorded them, so they would only pile up
+        self.voxel_writes.clear();
+
+        for mesh in &mut self.skinned_meshes {
+            let model = self.entities.global_transform(mesh.entity).compute_matrix();
+            mesh.update(dt, model);
+        }
+        // the animations only stand still while the time does
+        self.scene_changed |= !self.skinned_meshes.is_empty() && dt != 0.0;
+
+        self.camera_effects
+            .update(dt, &mut self.camera, &self.entities);
+
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &mut self.audio {
+            audio.update(&self.entities, &self.camera);
+        }
+
+        let propagation = self.entities.propagation();
+        for group in &mut self.instance_groups {
+            if !group.needs_write(&self.entities) {
+                continue;
+            }
+            let matrices: Vec<Mat4> = group
+                .entities
+                .iter()
+                .map(|entity| self.entities.global_transform(*entity).compute_matrix())
+                .collect();
+            group.buffer.write(0, &matrices);
+            group.written = Some((group.entities.clone(), propagation));
+        }
+
+        let camera = self.camera_effects.apply(&self.camera);
+        let cam_pos = camera.transform.translation;
+        let view_proj = camera.build_proj();
+        let unjittered_view_proj = camera.build_unjittered_proj();
+
+        self.uniform_buffer.write_shader(
             0,
             &[UniformData {
-                view_proj: self.camera.build_proj(),
+                view_proj,
                 cam_pos: vec4(cam_pos.x, cam_pos.y, cam_pos.z, 1.0),
                 time: self.start_time.elapsed().as_secs_f32(),
+                distance_field: self
+                    .voxel_distance_fields
+                    .first()
+                    .copied()
+                    .flatten()
+                    .map_or(NO_DISTANCE_FIELD, |handle| handle.index as u32),
+                _padding: [0.0; 2],
+                unjittered_view_proj,
+                prev_view_proj: self.prev_view_proj,
+                inverse_view_proj: view_proj.inverse(),
             }],
         );
+        // the jitter changes every frame, but the scene only moves with the camera
+        self.scene_changed |= unjittered_view_proj != self.prev_view_proj;
+        self.prev_view_proj = unjittered_view_proj;
     }
 }
 
+/// the camera of a face of an environment probe, it doesn't move, so there is no velocity
+/// the probes trace the octree without its distance field
+fn probe_uniforms(view: &ProbeView, start_time: Instant) -> Vec<u8> {
+    let view_proj = view.view_proj();
+    let data = UniformData {
+        view_proj,
+        cam_pos: view.position.extend(1.0),
+        time: start_time.elapsed().as_secs_f32(),
+        distance_field: NO_DISTANCE_FIELD,
+        _padding: [0.0; 2],
+        unjittered_view_proj: view_proj,
+        prev_view_proj: view_proj,
+        inverse_view_proj: view_proj.inverse(),
+    };
+
+    let bytes = unsafe {
+        std::slice::from_raw_parts((&raw const data).cast::<u8>(), size_of::<UniformData>())
+    };
+    bytes.to_vec()
+}
+
 const CUBE_VERTECIES: [[f32; 4]; 36] = [
     // Vorderseite (CCW)
     [-0.5, -0.5, 0.5, 1.0], // unten links
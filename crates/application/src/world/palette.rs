@@ -0,0 +1,82 @@
+use math::Color;
+
+/// the lookup table for the colors stored in octrees
+/// the color of a voxel is used as index, 0 is always empty
+/// the colors are stored as rgba8, this is also the layout used by the shaders
+#[derive(Clone, PartialEq, Eq)]
+pub struct VoxelPalette {
+    pub colors: [[u8; 4]; 256],
+}
+
+impl VoxelPalette {
+    /// maps every color to a gray value of the same brightness
+    #[must_use]
+    pub fn grayscale() -> Self {
+        let mut colors = [[0; 4]; 256];
+        for (i, color) in colors.iter_mut().enumerate().skip(1) {
+            let v = i as u8;
+            *color = [v, v, v, 255];
+        }
+        Self { colors }
+    }
+
+    /// the palette MagicaVoxel uses when a file doesn't contain one
+    /// a 6x6x6 color cube followed by red, green, blue and gray ramps
+    #[must_use]
+    pub fn magica_voxel() -> Self {
+        const CUBE: [u8; 6] = [0xFF, 0xCC, 0x99, 0x66, 0x33, 0x00];
+        const RAMP: [u8; 10] = [0xEE, 0xDD, 0xBB, 0xAA, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+        let cube = CUBE.iter().flat_map(|&r| {
+            CUBE.iter()
+                .flat_map(move |&g| CUBE.iter().map(move |&b| [r, g, b, 255]))
+        });
+
+        // the cube ends with black, which is left out
+        let ramps = [[1, 0, 0], [0, 1, 0], [0, 0, 1], [1, 1, 1]]
+            .into_iter()
+            .flat_map(|[r, g, b]| RAMP.iter().map(move |&v| [v * r, v * g, v * b, 255]));
+
+        let mut colors = [[0; 4]; 256];
+        for (color, value) in colors[1..].iter_mut().zip(cube.take(215).chain(ramps)) {
+            *color = value;
+        }
+        Self { colors }
+    }
+
+    /// the color of an index, the shaders read the bytes as linear
+    #[must_use]
+    pub fn color(&self, index: u8) -> Color {
+        Color::from_rgba8(self.colors[usize::from(index)])
+    }
+
+    /// if the color lets light through, its faces are meshed separately and drawn sorted, see ``meshing``
+    /// 0 is empty and never transparent
+    #[must_use]
+    pub fn is_transparent(&self, index: u8) -> bool {
+        index != 0 && self.colors[usize::from(index)][3] < 255
+    }
+
+    /// set the color of an index, 0 stays empty no matter its color
+    pub fn set_color(&mut self, index: u8, color: Color) {
+        self.colors[usize::from(index)] = color.to_rgba8();
+    }
+
+    /// the palette as it's stored on the gpu, one u32 per color
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.colors.as_flattened()
+    }
+}
+
+impl Default for VoxelPalette {
+    fn default() -> Self {
+        Self::magica_voxel()
+    }
+}
+
+impl std::fmt::Debug for VoxelPalette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VoxelPalette").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,425 @@
+// collision queries against octrees
+// everything is in octree space, where the octree fills the cube from -1 to 1
+// a voxel is solid if it's a leaf with a color other than 0
+
+use math::DVec3;
+
+use super::svo::{Octant, Octree, Visit};
+
+/// how far bodies stay away from voxels after moving
+/// without it, rounding errors would let them end up inside of the voxels they touch
+pub const SKIN: f64 = 1e-6;
+
+/// how often ``move_and_slide`` changes the direction after hitting something
+const MAX_SLIDES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: DVec3,
+    pub max: DVec3,
+}
+
+impl Aabb {
+    #[must_use]
+    pub fn new(min: DVec3, max: DVec3) -> Self {
+        Self { min, max }
+    }
+
+    #[must_use]
+    pub fn from_center(center: DVec3, half_extents: DVec3) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    fn from_octant(octant: &Octant) -> Self {
+        Self::from_center(octant.center, DVec3::splat(octant.size * 0.5))
+    }
+
+    #[must_use]
+    pub fn center(&self) -> DVec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    #[must_use]
+    pub fn half_extents(&self) -> DVec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    #[must_use]
+    pub fn translate(&self, offset: DVec3) -> Self {
+        Self::new(self.min + offset, self.max + offset)
+    }
+
+    /// the smallest box containing both boxes
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// touching boxes don't intersect
+    #[must_use]
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.cmplt(other.max).all() && self.max.cmpgt(other.min).all()
+    }
+
+    #[must_use]
+    pub fn closest_point(&self, point: DVec3) -> DVec3 {
+        point.clamp(self.min, self.max)
+    }
+
+    /// the distances along the ray where it enters and leaves the box
+    /// ``inv_dir`` is ``1.0 / dir``, the distances are in units of ``dir``
+    fn ray_intersection(&self, origin: DVec3, inv_dir: DVec3) -> Option<(f64, f64, DVec3)> {
+        let mut enter = f64::NEG_INFINITY;
+        let mut exit = f64::INFINITY;
+        let mut normal = DVec3::ZERO;
+
+        for axis in 0..3 {
+            if inv_dir[axis].is_infinite() {
+                // parallel to the slab, touching it doesn't count as inside
+                if origin[axis] <= self.min[axis] || origin[axis] >= self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let t0 = (self.min[axis] - origin[axis]) * inv_dir[axis];
+            let t1 = (self.max[axis] - origin[axis]) * inv_dir[axis];
+            let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            if near > enter {
+                enter = near;
+                normal = DVec3::ZERO;
+                normal[axis] = -inv_dir[axis].signum();
+            }
+            exit = exit.min(far);
+        }
+
+        (enter < exit).then_some((enter, exit, normal))
+    }
+}
+
+/// where a ray or a sweep hit a voxel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// for rays the distance, for sweeps the fraction of the motion from 0 to 1
+    pub time: f64,
+    /// the normal of the surface that was hit
+    pub normal: DVec3,
+    /// the color of the voxel that was hit
+    pub color: u8,
+}
+
+/// all solid voxels that intersect the region
+/// octants that don't intersect are skipped with all of their children
+#[must_use]
+pub fn solid_boxes(octree: &Octree, region: &Aabb) -> Vec<(Aabb, u8)> {
+    let mut boxes = vec![];
+    octree.visit(|octant| {
+        let aabb = Aabb::from_octant(octant);
+        if !aabb.intersects(region) {
+            return Visit::SkipChildren;
+        }
+
+        if octant.is_leaf() {
+            boxes.push((aabb, octant.color));
+        }
+        Visit::Continue
+    });
+    boxes
+}
+
+/// the first solid voxel along the ray
+/// ``dir`` doesn't need to be normalized, the time of the hit is in units of ``dir``
+#[must_use]
+pub fn raycast(octree: &Octree, origin: DVec3, dir: DVec3, max_time: f64) -> Option<Hit> {
+    let inv_dir = dir.recip();
+    let mut closest: Option<Hit> = None;
+
+    octree.visit(|octant| {
+        let max_time = closest.map_or(max_time, |hit| hit.time);
+
+        let Some((enter, exit, normal)) =
+            Aabb::from_octant(octant).ray_intersection(origin, inv_dir)
+        else {
+            return Visit::SkipChildren;
+        };
+        if exit < 0.0 || enter > max_time {
+            return Visit::SkipChildren;
+        }
+
+        if octant.is_leaf() {
+            closest = Some(Hit {
+                time: enter.max(0.0),
+                normal,
+                color: octant.color,
+            });
+        }
+        Visit::Continue
+    });
+
+    closest
+}
+
+/// tells if any solid voxel intersects the box
+#[must_use]
+pub fn overlaps_aabb(octree: &Octree, aabb: &Aabb) -> bool {
+    let mut overlaps = false;
+    octree.visit(|octant| {
+        if !Aabb::from_octant(octant).intersects(aabb) {
+            return Visit::SkipChildren;
+        }
+        if octant.is_leaf() {
+            overlaps = true;
+            return Visit::Stop;
+        }
+        Visit::Continue
+    });
+    overlaps
+}
+
+/// tells if any solid voxel intersects the sphere
+#[must_use]
+pub fn overlaps_sphere(octree: &Octree, center: DVec3, radius: f64) -> bool {
+    let mut overlaps = false;
+    octree.visit(|octant| {
+        let aabb = Aabb::from_octant(octant);
+        if aabb.closest_point(center).distance_squared(center) >= radius * radius {
+            return Visit::SkipChildren;
+        }
+        if octant.is_leaf() {
+            overlaps = true;
+            return Visit::Stop;
+        }
+        Visit::Continue
+    });
+    overlaps
+}
+
+/// move the box along ``motion`` and find the first voxel it hits
+/// voxels that already intersect the box at the start are ignored, so stuck bodies can move out
+#[must_use]
+pub fn sweep_aabb(octree: &Octree, aabb: &Aabb, motion: DVec3) -> Option<Hit> {
+    let region = aabb.union(&aabb.translate(motion));
+    let half_extents = aabb.half_extents();
+    let origin = aabb.center();
+    let inv_dir = motion.recip();
+
+    let mut closest: Option<Hit> = None;
+
+    for (voxel, color) in solid_boxes(octree, &region) {
+        // grow the voxel by the size of the box, so the box can be treated as a point
+        let expanded = Aabb::new(voxel.min - half_extents, voxel.max + half_extents);
+
+        let Some((enter, _, normal)) = expanded.ray_intersection(origin, inv_dir) else {
+            continue;
+        };
+
+        if (0.0..=1.0).contains(&enter) && closest.is_none_or(|hit| enter < hit.time) {
+            closest = Some(Hit {
+                time: enter,
+                normal,
+                color,
+            });
+        }
+    }
+
+    closest
+}
+
+/// move the sphere along ``motion`` and find the first voxel it hits
+/// the motion is checked in steps of half the radius, so thin voxels can't be skipped
+/// returns ``None`` if the sphere already intersects a voxel at the start
+#[must_use]
+pub fn sweep_sphere(octree: &Octree, center: DVec3, radius: f64, motion: DVec3) -> Option<Hit> {
+    if overlaps_sphere(octree, center, radius) {
+        return None;
+    }
+
+    let steps = (motion.length() / (radius * 0.5)).ceil().max(1.0) as usize;
+    let step = 1.0 / steps as f64;
+
+    let (mut free, mut blocked) = (0.0, None);
+    for i in 1..=steps {
+        let t = i as f64 * step;
+        if overlaps_sphere(octree, center + motion * t, radius) {
+            blocked = Some(t);
+            break;
+        }
+        free = t;
+    }
+    let mut blocked = blocked?;
+
+    // find the exact time of the hit
+    for _ in 0..16 {
+        let t = (free + blocked) * 0.5;
+        if overlaps_sphere(octree, center + motion * t, radius) {
+            blocked = t;
+        } else {
+            free = t;
+        }
+    }
+
+    let contact = center + motion * blocked;
+    let region = Aabb::from_center(contact, DVec3::splat(radius));
+    let (voxel, color) = solid_boxes(octree, &region)
+        .into_iter()
+        .min_by(|(a, _), (b, _)| {
+            let a = a.closest_point(contact).distance_squared(contact);
+            let b = b.closest_point(contact).distance_squared(contact);
+            a.total_cmp(&b)
+        })?;
+
+    let normal = (contact - voxel.closest_point(contact))
+        .try_normalize()
+        .unwrap_or(-motion.normalize_or_zero());
+
+    Some(Hit {
+        time: free,
+        normal,
+        color,
+    })
+}
+
+/// the result of ``move_and_slide``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveResult {
+    /// how far the box actually moved
+    pub motion: DVec3,
+    /// the motion with the parts that pushed in to voxels removed
+    /// use it as the new velocity, so gravity doesn't build up while standing
+    pub remaining: DVec3,
+    /// the box hit a surface facing up
+    pub grounded: bool,
+    /// the box hit anything at all
+    pub collided: bool,
+}
+
+/// move the box as far as possible and slide along the voxels it hits
+/// collides with all given octrees
+#[must_use]
+pub fn move_and_slide(octrees: &[Octree], aabb: &Aabb, motion: DVec3) -> MoveResult {
+    let mut result = MoveResult {
+        motion: DVec3::ZERO,
+        remaining: motion,
+        grounded: false,
+        collided: false,
+    };
+    let mut left = motion;
+    let mut aabb = *aabb;
+
+    for _ in 0..MAX_SLIDES {
+        if left == DVec3::ZERO {
+            break;
+        }
+
+        let hit = octrees
+            .iter()
+            .filter_map(|octree| sweep_aabb(octree, &aabb, left))
+            .min_by(|a, b| a.time.total_cmp(&b.time));
+
+        let Some(hit) = hit else {
+            result.motion += left;
+            break;
+        };
+
+        // stop just before the surface
+        let length = left.length();
+        let time = (hit.time - SKIN / length).max(0.0);
+        let moved = left * time;
+
+        aabb = aabb.translate(moved);
+        result.motion += moved;
+        result.collided = true;
+        result.grounded |= hit.normal.y > 0.5;
+
+        // slide along the surface with what's left
+        left -= moved;
+        left -= hit.normal * left.dot(hit.normal);
+        result.remaining -= hit.normal * result.remaining.dot(hit.normal);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use math::dvec3;
+
+    use super::*;
+
+    /// a floor filling the lower half of the octree
+    fn floor() -> Octree {
+        let mut octree = Octree::new();
+        for x in [-0.5, 0.5] {
+            for z in [-0.5, 0.5] {
+                octree.write(dvec3(x, -0.5, z), 1, 1);
+            }
+        }
+        octree
+    }
+
+    #[test]
+    fn raycast_floor() {
+        let octree = floor();
+
+        let hit = raycast(&octree, dvec3(0.2, 0.8, 0.3), dvec3(0.0, -1.0, 0.0), 10.0).unwrap();
+        assert!((hit.time - 0.8).abs() < 1e-9);
+        assert_eq!(hit.normal, dvec3(0.0, 1.0, 0.0));
+        assert_eq!(hit.color, 1);
+
+        assert!(raycast(&octree, dvec3(0.2, 0.8, 0.3), dvec3(0.0, 1.0, 0.0), 10.0).is_none());
+        assert!(raycast(&octree, dvec3(0.2, 0.8, 0.3), dvec3(0.0, -1.0, 0.0), 0.5).is_none());
+    }
+
+    #[test]
+    fn overlaps() {
+        let octree = floor();
+
+        assert!(overlaps_aabb(
+            &octree,
+            &Aabb::from_center(dvec3(0.0, 0.05, 0.0), DVec3::splat(0.1))
+        ));
+        // touching isn't overlapping
+        assert!(!overlaps_aabb(
+            &octree,
+            &Aabb::from_center(dvec3(0.0, 0.1, 0.0), DVec3::splat(0.1))
+        ));
+
+        assert!(overlaps_sphere(&octree, dvec3(0.3, 0.05, 0.3), 0.1));
+        assert!(!overlaps_sphere(&octree, dvec3(0.3, 0.15, 0.3), 0.1));
+    }
+
+    #[test]
+    fn sweeps() {
+        let octree = floor();
+
+        let aabb = Aabb::from_center(dvec3(0.0, 0.5, 0.0), DVec3::splat(0.1));
+        let hit = sweep_aabb(&octree, &aabb, dvec3(0.0, -0.8, 0.0)).unwrap();
+        assert!((hit.time - 0.5).abs() < 1e-9);
+        assert_eq!(hit.normal, dvec3(0.0, 1.0, 0.0));
+
+        assert!(sweep_aabb(&octree, &aabb, dvec3(0.3, 0.0, 0.0)).is_none());
+
+        let hit = sweep_sphere(&octree, dvec3(0.0, 0.5, 0.0), 0.1, dvec3(0.0, -0.8, 0.0)).unwrap();
+        assert!((hit.time - 0.5).abs() < 1e-3);
+        assert!(hit.normal.abs_diff_eq(dvec3(0.0, 1.0, 0.0), 1e-9));
+    }
+
+    #[test]
+    fn slide_along_floor() {
+        let octrees = [floor()];
+        let aabb = Aabb::from_center(dvec3(0.0, 0.2, 0.0), DVec3::splat(0.1));
+
+        let result = move_and_slide(&octrees, &aabb, dvec3(0.3, -0.4, 0.0));
+        assert!(result.collided && result.grounded);
+        assert!((result.motion.x - 0.3).abs() < 1e-9);
+        assert!((result.motion.y + 0.1).abs() < 1e-5);
+        assert_eq!(result.remaining, dvec3(0.3, 0.0, 0.0));
+
+        let moved = aabb.translate(result.motion);
+        assert!(!overlaps_aabb(&octrees[0], &moved));
+    }
+}
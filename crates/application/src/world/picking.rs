@@ -0,0 +1,56 @@
+// what the object ids in the object id target of the renderer stand for
+// the highest bit tells octrees apart from entities, the rest is the index, 0 is nothing
+
+use super::hierarchy::Entity;
+
+const OCTREE_BIT: u32 = 1 << 31;
+
+/// the thing under a pixel, see ``World::pick``
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Picked {
+    /// the index in ``World::voxel_octrees``
+    Octree(usize),
+    Entity(Entity),
+}
+
+impl Picked {
+    /// the id the draws of it write, see ``DrawData::object_id``
+    #[must_use]
+    pub fn object_id(self) -> u32 {
+        match self {
+            Self::Octree(index) => OCTREE_BIT | index as u32,
+            Self::Entity(entity) => entity.index() + 1,
+        }
+    }
+
+    /// None for 0, the entity might have been despawned since the id was written
+    #[must_use]
+    pub fn from_object_id(id: u32) -> Option<Self> {
+        match id {
+            0 => None,
+            id if id & OCTREE_BIT != 0 => Some(Self::Octree((id & !OCTREE_BIT) as usize)),
+            id => Some(Self::Entity(Entity::from_index(id - 1))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::Transform;
+
+    use super::*;
+    use crate::world::hierarchy::Entities;
+
+    #[test]
+    fn object_ids_round_trip() {
+        let mut entities = Entities::new();
+        let _ = entities.spawn(Transform::IDENTITY);
+        let entity = entities.spawn(Transform::IDENTITY);
+
+        for picked in [Picked::Octree(0), Picked::Octree(3), Picked::Entity(entity)] {
+            assert_ne!(picked.object_id(), 0);
+            assert_eq!(Picked::from_object_id(picked.object_id()), Some(picked));
+        }
+        assert_eq!(Picked::from_object_id(0), None);
+    }
+}
@@ -0,0 +1,176 @@
+// a ghost of the voxels the brush would change, so the edit can be seen before the mouse button is pressed
+// ``World::sync_renderer`` asks the brush for its changes under the cursor every frame, see ``Brush::changes``,
+// the octrees aren't touched until ``brush::brush_task`` applies the brush
+//
+// the cells are drawn as the outlines of cubes with screen sprites, like the gizmo handles,
+// cells that are added get their palette color and removed ones ``BrushPreview::remove_color``
+
+use ash::prelude::VkResult;
+use math::{Camera, Color, Vec2, Vec3};
+use rendering::handler::{
+    sprites::{Sprite, UNTEXTURED},
+    RenderHandler,
+};
+
+use crate::input::Input;
+
+use super::{
+    brush::{Brush, OCTREE_SCALE},
+    gizmo::load_batch,
+    palette::VoxelPalette,
+    svo::Octree,
+};
+
+const LINE_PIXELS: f32 = 1.5;
+/// more cells aren't drawn, a big brush on a small layer would need too many sprites
+const MAX_CELLS: usize = 2048;
+
+/// the corners of a cube that are connected by an edge, as bits of the corner index
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+pub struct BrushPreview {
+    pub visible: bool,
+    /// the color of the cells the brush would remove
+    pub remove_color: Color,
+    /// the alpha of the outlines
+    pub opacity: f32,
+    /// the sprite batch the cells are drawn with, None if ``shaders/sprites.spv`` is missing
+    batch: Option<usize>,
+}
+
+impl BrushPreview {
+    /// # Errors
+    /// if the shader module couldn't be created
+    pub fn new(renderer: &mut RenderHandler) -> VkResult<Self> {
+        Ok(Self {
+            visible: true,
+            remove_color: Color::rgb(0.9, 0.2, 0.2),
+            opacity: 0.6,
+            batch: load_batch(renderer, "brush preview")?,
+        })
+    }
+
+    /// create the sprite batch again after ``RenderHandler::reinitialize``
+    /// # Errors
+    /// if the shader module couldn't be created
+    pub fn recreate(&mut self, renderer: &mut RenderHandler) -> VkResult<()> {
+        self.batch = load_batch(renderer, "brush preview")?;
+        Ok(())
+    }
+
+    /// replace the sprites of the batch with the cells the brush would change under the cursor
+    /// nothing is drawn without a brush or if the cursor isn't over an octree
+    pub fn draw(
+        &self,
+        renderer: &mut RenderHandler,
+        brush: Option<&Brush>,
+        octrees: &[Octree],
+        palette: &VoxelPalette,
+        camera: &Camera,
+        input: &Input,
+    ) {
+        let extent = renderer.get_swapchain_resolution();
+        let viewport = Vec2::new(extent.width as f32, extent.height as f32);
+
+        let Some(batch) = self
+            .batch
+            .and_then(|batch| renderer.get_sprite_batch_mut(batch))
+        else {
+            return;
+        };
+        batch.clear();
+
+        let Some(brush) = brush.filter(|_| self.visible) else {
+            return;
+        };
+        let Some((octree, center)) = brush.target(octrees, camera, input) else {
+            return;
+        };
+
+        let half = (brush.cell_size() * 0.5 * OCTREE_SCALE) as f32;
+        let changes = brush.changes(&octrees[octree], center);
+
+        for (pos, _, new) in changes.into_iter().take(MAX_CELLS) {
+            let mut color = if new == 0 {
+                self.remove_color
+            } else {
+                palette.color(new)
+            };
+            color.a = self.opacity;
+
+            let center = (pos * OCTREE_SCALE).as_vec3();
+            for [start, end] in cube_edges(center, half) {
+                let (Some(start), Some(end)) = (
+                    camera.world_to_screen(start, viewport),
+                    camera.world_to_screen(end, viewport),
+                ) else {
+                    continue;
+                };
+                let middle = (start + end) * 0.5;
+                let delta = end - start;
+
+                batch.push(Sprite {
+                    position: [middle.x, middle.y, 0.0],
+                    // the sprite rotates counter clockwise with y going up
+                    rotation: (-delta.y).atan2(delta.x),
+                    size: [delta.length(), LINE_PIXELS],
+                    color,
+                    page: UNTEXTURED,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
+
+/// the start and end of the 12 edges of the cube
+fn cube_edges(center: Vec3, half: f32) -> [[Vec3; 2]; 12] {
+    let corner = |index: usize| {
+        let bit = |axis: usize| {
+            if (index >> axis) & 1 == 0 {
+                -half
+            } else {
+                half
+            }
+        };
+        center + Vec3::new(bit(0), bit(1), bit(2))
+    };
+    EDGES.map(|(start, end)| [corner(start), corner(end)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges_go_along_one_axis() {
+        let edges = cube_edges(Vec3::new(1.0, 2.0, 3.0), 0.5);
+
+        for axis in 0..3 {
+            let along = edges
+                .iter()
+                .filter(|[start, end]| (*end - *start) == Vec3::AXES[axis])
+                .count();
+            assert_eq!(along, 4);
+        }
+
+        // every corner has 3 edges
+        for [start, _] in edges {
+            let touching = edges.iter().filter(|edge| edge.contains(&start)).count();
+            assert_eq!(touching, 3);
+        }
+    }
+}
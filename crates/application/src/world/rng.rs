@@ -0,0 +1,216 @@
+// a small seeded random number generator, the same seed gives the same numbers on every platform
+// it's a pcg32 (XSH RR), see https://www.pcg-random.org
+//
+// ``World::rng`` is seeded from ``Settings::seed``, systems fork their own stream from it by name,
+// so adding a system or drawing more numbers in one doesn't change the numbers of the others
+
+use std::ops::Range;
+
+use math::Vec3;
+
+const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+    /// selects the stream, always odd
+    increment: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Rng {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0)
+    }
+
+    /// generators with the same seed but a different stream give unrelated numbers
+    #[must_use]
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// a new generator for a system, it only depends on the name and the state of this one
+    /// this one isn't advanced, so the order the systems fork in doesn't matter
+    #[must_use]
+    pub fn fork(&self, name: &str) -> Self {
+        let hash = fnv1a(name.as_bytes());
+        Self::with_stream(splitmix(self.state ^ hash), hash)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        (u64::from(self.next_u32()) << 32) | u64::from(self.next_u32())
+    }
+
+    /// between 0 and 1, 1 excluded
+    pub fn f32(&mut self) -> f32 {
+        // the 24 bits a f32 can hold exactly
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// between 0 and 1, 1 excluded
+    pub fn f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// a number below ``bound`` without the bias of a plain modulo
+    /// # Panics
+    /// if ``bound`` is 0
+    pub fn below(&mut self, bound: u32) -> u32 {
+        assert!(bound > 0, "the bound has to be at least 1");
+
+        // the numbers above the last full multiple of bound would be picked more often
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next_u32();
+            if value >= threshold {
+                return value % bound;
+            }
+        }
+    }
+
+    /// # Panics
+    /// if the range is empty
+    pub fn range(&mut self, range: Range<i32>) -> i32 {
+        assert!(!range.is_empty(), "the range is empty");
+        let len = range.end.abs_diff(range.start);
+        range.start.wrapping_add_unsigned(self.below(len))
+    }
+
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        range.start + (range.end - range.start) * self.f32()
+    }
+
+    /// true with the given chance between 0 and 1
+    pub fn chance(&mut self, chance: f32) -> bool {
+        self.f32() < chance
+    }
+
+    /// a random point inside of a sphere with a radius of 1
+    pub fn in_sphere(&mut self) -> Vec3 {
+        loop {
+            let point = Vec3::new(
+                self.range_f32(-1.0..1.0),
+                self.range_f32(-1.0..1.0),
+                self.range_f32(-1.0..1.0),
+            );
+            if point.length_squared() <= 1.0 {
+                return point;
+            }
+        }
+    }
+
+    /// pick an element, None if the slice is empty
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u32) as usize)
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u32 + 1) as usize);
+        }
+    }
+}
+
+/// spreads the bits of similar seeds, so neighboring seeds don't start with similar states
+pub(super) fn splitmix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// a hash that doesn't change between rust versions, unlike ``DefaultHasher``
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        // the reference numbers of the pcg32 demo with seed 42 and stream 54
+        let mut rng = Rng::with_stream(42, 54);
+        let numbers: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            numbers,
+            [
+                0xa15c_02b7,
+                0x7b47_f409,
+                0xba1d_3330,
+                0x83d2_f293,
+                0xbfa4_784b,
+                0xcbed_606e
+            ]
+        );
+
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let mut c = Rng::new(8);
+        let a: Vec<u32> = (0..16).map(|_| a.next_u32()).collect();
+        assert_eq!(a, (0..16).map(|_| b.next_u32()).collect::<Vec<_>>());
+        assert_ne!(a, (0..16).map(|_| c.next_u32()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn forks_are_independent_of_order() {
+        let root = Rng::new(3);
+
+        let mut terrain = root.fork("terrain");
+        let mut particles = root.fork("particles");
+        let first = terrain.next_u64();
+        particles.next_u64();
+
+        // forking again in another order gives the same streams
+        let mut particles_again = root.fork("particles");
+        let mut terrain_again = root.fork("terrain");
+        particles_again.next_u64();
+        assert_eq!(terrain_again.next_u64(), first);
+        assert_ne!(root.fork("terrain"), root.fork("particles"));
+    }
+
+    #[test]
+    fn values_stay_in_range() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&rng.f32()));
+            assert!((0.0..1.0).contains(&rng.f64()));
+            assert!(rng.below(3) < 3);
+            assert!((-5..5).contains(&rng.range(-5..5)));
+            assert!(rng.in_sphere().length() <= 1.0);
+        }
+
+        let mut items = [1, 2, 3, 4, 5];
+        rng.shuffle(&mut items);
+        items.sort_unstable();
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+        assert_eq!(rng.pick::<u8>(&[]), None);
+    }
+}
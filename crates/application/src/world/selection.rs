@@ -0,0 +1,140 @@
+// the selected and hovered things of the editor, drawn with an outline by the renderer
+// ``World::sync_renderer`` passes the outlines every frame, see ``rendering::handler::outline``
+//
+// octrees have one object id, so a hovered voxel outlines the whole octree it's in
+
+use math::Color;
+use rendering::handler::outline::{Outline, OutlineMode, OutlineStyle};
+
+use super::{hierarchy::Entities, picking::Picked};
+
+#[derive(Debug, Clone)]
+pub struct Selection {
+    /// in the order they were selected
+    pub selected: Vec<Picked>,
+    /// usually what ``World::picked`` returned under the mouse
+    pub hovered: Option<Picked>,
+    pub selected_style: OutlineStyle,
+    pub hovered_style: OutlineStyle,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Self {
+            selected: vec![],
+            hovered: None,
+            selected_style: OutlineStyle::default(),
+            hovered_style: OutlineStyle {
+                color: Color::WHITE.with_alpha(0.6),
+                width: 1,
+                mode: OutlineMode::XRay,
+            },
+        }
+    }
+}
+
+impl Selection {
+    /// adds it to the selection, if it isn't selected yet
+    pub fn select(&mut self, picked: Picked) {
+        if !self.is_selected(picked) {
+            self.selected.push(picked);
+        }
+    }
+
+    pub fn deselect(&mut self, picked: Picked) {
+        self.selected.retain(|selected| *selected != picked);
+    }
+
+    /// select it if it isn't selected, deselect it otherwise
+    pub fn toggle(&mut self, picked: Picked) {
+        if self.is_selected(picked) {
+            self.deselect(picked);
+        } else {
+            self.selected.push(picked);
+        }
+    }
+
+    /// deselects everything, the hovered thing stays
+    pub fn clear(&mut self) {
+        self.selected.clear();
+    }
+
+    #[must_use]
+    pub fn is_selected(&self, picked: Picked) -> bool {
+        self.selected.contains(&picked)
+    }
+
+    /// the outlines for the renderer, despawned entities and removed octrees are skipped
+    /// the hovered thing gets the selected style if it's selected as well
+    #[must_use]
+    pub fn outlines(&self, entities: &Entities, octree_count: usize) -> Vec<Outline> {
+        let exists = |picked: &Picked| match picked {
+            Picked::Entity(entity) => entities.contains(*entity),
+            Picked::Octree(index) => *index < octree_count,
+        };
+
+        let hovered = self
+            .hovered
+            .filter(|hovered| !self.is_selected(*hovered))
+            .map(|hovered| (hovered, self.hovered_style));
+        let selected = self
+            .selected
+            .iter()
+            .map(|selected| (*selected, self.selected_style));
+
+        selected
+            .chain(hovered)
+            .filter(|(picked, _)| exists(picked))
+            .map(|(picked, style)| Outline {
+                object_id: picked.object_id(),
+                style,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::Transform;
+
+    use super::*;
+
+    #[test]
+    fn outlines_skip_what_is_gone() {
+        let mut entities = Entities::new();
+        let kept = entities.spawn(Transform::IDENTITY);
+        let despawned = entities.spawn(Transform::IDENTITY);
+
+        let mut selection = Selection::default();
+        selection.select(Picked::Entity(kept));
+        selection.select(Picked::Entity(kept));
+        selection.toggle(Picked::Entity(despawned));
+        selection.select(Picked::Octree(4));
+        selection.hovered = Some(Picked::Octree(0));
+        entities.despawn(despawned);
+
+        let outlines = selection.outlines(&entities, 2);
+        assert_eq!(
+            outlines,
+            vec![
+                Outline {
+                    object_id: Picked::Entity(kept).object_id(),
+                    style: selection.selected_style,
+                },
+                Outline {
+                    object_id: Picked::Octree(0).object_id(),
+                    style: selection.hovered_style,
+                },
+            ]
+        );
+
+        // a selected thing under the mouse keeps its selected outline
+        selection.hovered = Some(Picked::Entity(kept));
+        assert_eq!(selection.outlines(&entities, 2).len(), 1);
+
+        selection.toggle(Picked::Entity(kept));
+        selection.clear();
+        assert_eq!(selection.outlines(&entities, 2).len(), 1);
+        assert!(!selection.is_selected(Picked::Octree(4)));
+    }
+}
@@ -1,14 +1,18 @@
 #![allow(clippy::cast_lossless, clippy::cast_possible_truncation)]
 
-use std::{collections::VecDeque, fmt::Debug, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    ops::Range,
+    sync::Arc,
+};
 
+use allocators::{GrowablePool, GrowthPolicy, PoolHandle};
 use math::{dvec3, DVec3};
 
 /// 64 bit of color data
 /// every voxel has 8 bits for colors => 255 colors for every octree
-///
-/// TODO: the colors are later stored in a lookup-table using the color as index
-/// this isn't implemented at the moment
+/// the colors are indices in to a ``VoxelPalette``
 #[repr(transparent)]
 #[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub struct ColorData(u64);
@@ -48,17 +52,27 @@ const fn get_index(pos: DVec3, center: DVec3) -> u8 {
     | (((pos.z > center.z) as u8) << 2)
 }
 
+/// marks that a node has no child at that index
+const NO_CHILD: u32 = u32::MAX;
+
 /// one node of an octree
 /// every node has up to 8 child nodes
 /// if the child node is None and the color is anything except 0, then its considered a leaf node
-/// next nodes are stored as a Box, after some testing this didn't really make a difference
-/// compared to raw pointers
-/// in future this might use the ``PoolAllocator`` in the ``allocators`` crate
-/// but this would make resizing more complicated so i haven't implemented it right now
-#[derive(Default)]
+/// nodes don't own their children, they are stored as indices in to the node pool of the ``Octree``
+/// this keeps the nodes close together in memory and avoids an allocation for every node
+#[derive(Clone, Copy)]
 pub struct OctreeNode {
     colors: ColorData,
-    children: [Option<Box<OctreeNode>>; 8],
+    children: [u32; 8],
+}
+
+impl Default for OctreeNode {
+    fn default() -> Self {
+        Self {
+            colors: ColorData::default(),
+            children: [NO_CHILD; 8],
+        }
+    }
 }
 
 impl OctreeNode {
@@ -79,47 +93,286 @@ impl OctreeNode {
     pub fn get_valid_mask(&self) -> u8 {
         let mut valid_mask = 0u8;
         for i in 0..8 {
-            valid_mask |= (self.children[i].is_some() as u8) << i;
+            valid_mask |= ((self.children[i] != NO_CHILD) as u8) << i;
         }
         valid_mask
     }
 
+    /// the child node at the given index, if there is one
+    #[must_use]
+    pub fn child(&self, index: usize) -> Option<PoolHandle> {
+        let child = self.children[index];
+        (child != NO_CHILD).then(|| PoolHandle::from_index(child))
+    }
+
+    #[must_use]
+    pub fn colors(&self) -> ColorData {
+        self.colors
+    }
+}
+
+/// a cube shaped region of the octree, one of the 8 children of a node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Octant {
+    pub center: DVec3,
+    /// the length of an edge
+    pub size: f64,
+    /// the layer as used in ``Octree::write``, the children of the root are on layer 1
+    pub layer: usize,
+    /// if the octant has children, this is the color it was last written with,
+    /// it can be used as the color for LOD's
+    pub color: u8,
+    /// the node that subdivides this octant
+    pub child: Option<PoolHandle>,
+}
+
+impl Octant {
+    #[must_use]
+    pub fn is_leaf(&self) -> bool {
+        self.child.is_none()
+    }
+}
+
+/// tells ``Octree::visit`` how to continue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    /// visit the children of this octant
+    Continue,
+    /// don't visit anything inside this octant
+    SkipChildren,
+    /// stop visiting completely
+    Stop,
+}
+
+/// a sparse voxel octree
+/// all nodes are stored in a pool, so writing only allocates once the pool needs to grow
+pub struct Octree {
+    nodes: GrowablePool<OctreeNode>,
+    root: PoolHandle,
+    /// the nodes that were changed or added since the last ``OctreeLayout::update``
+    changed: HashSet<u32>,
+}
+
+impl Default for Octree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Octree {
+    /// how many nodes are allocated at once when the pool is full
+    const CHUNK_SIZE: usize = 1024;
+
+    #[must_use]
+    pub fn new() -> Self {
+        let mut nodes = GrowablePool::new(Self::CHUNK_SIZE, GrowthPolicy::Linear);
+        let root = nodes.insert(OctreeNode::default()).unwrap();
+        Self {
+            nodes,
+            root,
+            changed: HashSet::from([root.index()]),
+        }
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &OctreeNode {
+        self.node(self.root)
+    }
+
+    /// # Panics
+    /// if the handle doesn't point to a node of this octree
+    #[must_use]
+    pub fn node(&self, handle: PoolHandle) -> &OctreeNode {
+        self.nodes
+            .get(handle)
+            .expect("the handle doesn't point to a node")
+    }
+
+    fn node_mut(&mut self, index: u32) -> &mut OctreeNode {
+        self.changed.insert(index);
+        self.nodes
+            .get_mut(PoolHandle::from_index(index))
+            .expect("the index doesn't point to a node")
+    }
+
+    /// iterate over the child nodes that exist, in index order
+    pub fn children<'a>(&'a self, node: &'a OctreeNode) -> impl Iterator<Item = &'a OctreeNode> {
+        (0..8)
+            .filter_map(|i| node.child(i))
+            .map(|child| self.node(child))
+    }
+
+    /// the amount of nodes, including the root
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// the memory allocated for nodes in bytes
+    #[must_use]
+    pub fn memory(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<OctreeNode>()
+    }
+
+    fn insert_node(&mut self, node: OctreeNode) -> u32 {
+        // the pool grows linearly, so this never fails
+        let index = self.nodes.insert(node).unwrap().index();
+        self.changed.insert(index);
+        index
+    }
+
+    /// remove a node and all of its children from the pool
+    fn free_subtree(&mut self, index: u32) {
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            if let Some(node) = self.nodes.remove(PoolHandle::from_index(index)) {
+                stack.extend(node.children.iter().filter(|c| **c != NO_CHILD));
+            }
+        }
+    }
+
+    /// the 8 octants of a node, ``center`` and ``size`` are the ones of the node itself
+    fn octants(
+        &self,
+        node: &OctreeNode,
+        center: DVec3,
+        size: f64,
+        layer: usize,
+    ) -> impl DoubleEndedIterator<Item = Octant> {
+        let colors = node.colors;
+        let children = node.children;
+
+        (0..8).map(move |i| Octant {
+            center: center + OctreeNode::NODE_POS[i] * size * 0.25,
+            size: size * 0.5,
+            layer,
+            color: colors.get_color(i as u8),
+            child: (children[i] != NO_CHILD).then(|| PoolHandle::from_index(children[i])),
+        })
+    }
+
+    /// the octants of the root, in reverse order so they are popped from a stack in order
+    fn root_stack(&self) -> Vec<Octant> {
+        self.octants(self.root(), DVec3::ZERO, 2.0, 1)
+            .rev()
+            .collect()
+    }
+
+    /// walk through the octree depth first
+    /// completely empty octants (no children and color 0) are skipped
+    /// the visitor decides if the children of an octant should be visited as well
+    pub fn visit(&self, mut visitor: impl FnMut(&Octant) -> Visit) {
+        let mut stack = self.root_stack();
+
+        while let Some(octant) = stack.pop() {
+            if octant.is_leaf() && octant.color == 0 {
+                continue;
+            }
+
+            match visitor(&octant) {
+                Visit::Stop => return,
+                Visit::SkipChildren => {}
+                Visit::Continue => {
+                    if let Some(child) = octant.child {
+                        let node = self.node(child);
+                        stack.extend(
+                            self.octants(node, octant.center, octant.size, octant.layer + 1)
+                                .rev(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// iterate over all filled leaves as (center, size, color)
+    /// octants on ``max_depth`` are treated as leaves even if they have children,
+    /// they use the color they were last written with
+    pub fn iter_leaves(&self, max_depth: usize) -> impl Iterator<Item = (DVec3, f64, u8)> + '_ {
+        let mut stack = self.root_stack();
+
+        std::iter::from_fn(move || {
+            while let Some(octant) = stack.pop() {
+                match octant.child {
+                    Some(child) if octant.layer < max_depth => {
+                        let node = self.node(child);
+                        stack.extend(
+                            self.octants(node, octant.center, octant.size, octant.layer + 1)
+                                .rev(),
+                        );
+                    }
+                    _ if octant.color != 0 => {
+                        return Some((octant.center, octant.size, octant.color));
+                    }
+                    _ => {}
+                }
+            }
+            None
+        })
+    }
+
     /// write once to the octree
     /// position must contain values between -1 and 1
-    /// this calls a function recursively and might cause a ``stack_overflow``
-    /// but shouldn't happen as a layer of 15 is already so small that you cant see it anymore
     /// ``layer`` is how deep it should go in to the tree
+    ///
+    /// the whole cell at that layer gets the color, including everything below it
+    /// afterwards nodes whose children are all leaves with the same color are merged in to their parent
     pub fn write(&mut self, pos: DVec3, color: u8, layer: usize) {
-        let mut node: &mut OctreeNode = self;
+        let mut node = self.root.index();
         let mut center = DVec3::ZERO;
         let mut scale = 1.0;
 
+        // the nodes we went through and the index of the child we took
+        let mut path = Vec::with_capacity(layer);
+
         for _ in 1..layer {
             let index = get_index(pos, center) as usize;
 
             scale *= 0.5;
-            center += Self::NODE_POS[index] * scale;
-
-            node.colors.set_color(index as u8, color);
-
-            if let Some(child) = &mut node.children[index] {
-                if child.colors.are_equal()
-                    && child.children[get_index(pos, center) as usize].is_none()
-                {
-                    // NOTE: this doesn't work because the leaf node is set after the loop
-                    // so checking before it ended doesn't always work, fix this
-                    node.children[index] = None;
-                    break;
-                }
-            }
+            center += OctreeNode::NODE_POS[index] * scale;
+
+            let parent = self.node_mut(node);
+            let old_color = parent.colors.get_color(index as u8);
+            let child = parent.children[index];
+            parent.colors.set_color(index as u8, color);
+
+            path.push((node, index));
 
-            node = node.children[index]
-                .get_or_insert_with(|| Box::new(OctreeNode::default()))
-                .as_mut();
+            node = if child == NO_CHILD {
+                // the child was a leaf, so the rest of the new node keeps its color
+                let mut new_node = OctreeNode::default();
+                new_node.colors.set_all_colors(old_color);
+
+                let child = self.insert_node(new_node);
+                self.node_mut(node).children[index] = child;
+                child
+            } else {
+                child
+            };
         }
 
-        let index = get_index(pos, center);
-        node.colors.set_color(index, color);
+        let index = get_index(pos, center) as usize;
+        let leaf = self.node_mut(node);
+        leaf.colors.set_color(index as u8, color);
+
+        // everything inside the written cell is replaced
+        let replaced = std::mem::replace(&mut leaf.children[index], NO_CHILD);
+        if replaced != NO_CHILD {
+            self.free_subtree(replaced);
+        }
+
+        // merge bottom up, the color in the parent has already been set on the way down
+        let mut child = node;
+        while let Some((parent, index)) = path.pop() {
+            let child_node = self.node_mut(child);
+            if child_node.get_valid_mask() != 0 || !child_node.colors.are_equal() {
+                break;
+            }
+
+            self.nodes.remove(PoolHandle::from_index(child));
+            self.node_mut(parent).children[index] = NO_CHILD;
+            child = parent;
+        }
     }
 
     /// sample one value in the octree
@@ -128,18 +381,17 @@ impl OctreeNode {
     /// writing to the tree, this can be used for LOD's
     #[must_use]
     pub fn sample(&self, pos: DVec3, layer: usize) -> u8 {
-        let mut node: &OctreeNode = self;
+        let mut node = self.root();
         let mut center = DVec3::splat(0.0);
         let mut scale = 1.0;
 
         for _ in 1..layer {
-            let index = get_index(pos, center);
+            let index = get_index(pos, center) as usize;
 
             scale *= 0.5;
-            let next_node = &node.children[index as usize];
-            if let Some(next_node) = next_node {
-                center += scale * Self::NODE_POS[index as usize];
-                node = next_node.as_ref();
+            if let Some(next_node) = node.child(index) {
+                center += scale * OctreeNode::NODE_POS[index];
+                node = self.node(next_node);
             } else {
                 break;
             }
@@ -149,23 +401,56 @@ impl OctreeNode {
         node.colors.get_color(index)
     }
 
+    /// the writes as (position, color, layer) that bring the cell back to what it is now,
+    /// after anything was written in to it, the first one clears the whole cell
+    /// ``pos`` and ``layer`` are the same as for ``write``
+    #[must_use]
+    pub fn capture(&self, pos: DVec3, layer: usize) -> Vec<(DVec3, u8, usize)> {
+        let size = 2.0 / (1u64 << layer) as f64;
+        let center = ((pos + 1.0) / size).floor() * size - 1.0 + size * 0.5;
+        let mut writes = vec![(center, 0, layer)];
+
+        self.visit(|octant| {
+            // the cells line up, so an octant either contains the cell, is inside of it or doesn't touch it
+            let reach = (octant.size + size) * 0.5;
+            if (octant.center - center)
+                .abs()
+                .cmpge(DVec3::splat(reach))
+                .any()
+            {
+                return Visit::SkipChildren;
+            }
+
+            if octant.size > size {
+                if octant.is_leaf() {
+                    writes[0].1 = octant.color;
+                }
+            } else if octant.is_leaf() {
+                writes.push((octant.center, octant.color, octant.layer));
+            }
+            Visit::Continue
+        });
+        writes
+    }
+
     /// flatten the octree
     /// compress the octree in to a linear format
     /// this is used to store it in a file or a buffer for the GPU
     #[must_use]
     pub fn flatten(&self) -> FlatOctree {
-        let mut stack: VecDeque<&Box<OctreeNode>> = VecDeque::new();
+        let mut stack: VecDeque<&OctreeNode> = VecDeque::new();
         let mut flat_tree = vec![];
 
+        let root = self.root();
         let mut flat_root = FlatOctreeNode {
-            colors: self.colors,
+            colors: root.colors,
             ..Default::default()
         };
-        flat_root.set_valid_mask(self.get_valid_mask());
+        flat_root.set_valid_mask(root.get_valid_mask());
         flat_root.set_child_ptr(1); // root always has child_ptr = 1
         flat_tree.push(flat_root);
 
-        stack.extend(self.children.iter().filter_map(|v| v.as_ref()));
+        stack.extend(self.children(root));
 
         while let Some(node) = stack.pop_front() {
             let mut flat_node = FlatOctreeNode {
@@ -179,7 +464,7 @@ impl OctreeNode {
 
             flat_tree.push(flat_node);
 
-            stack.extend(node.children.iter().filter_map(|v| v.as_ref()));
+            stack.extend(self.children(node));
         }
 
         FlatOctree {
@@ -188,6 +473,206 @@ impl OctreeNode {
     }
 }
 
+/// where a node is stored in an ``OctreeLayout``
+#[derive(Clone, Copy)]
+struct NodeLayout {
+    /// the index of the node itself
+    entry: u32,
+    /// the index of the first child, all children are stored next to each other
+    block: u32,
+    /// the children of the node when the layout was last updated
+    children: [u32; 8],
+}
+
+impl Default for NodeLayout {
+    fn default() -> Self {
+        Self {
+            entry: 0,
+            block: 0,
+            children: [NO_CHILD; 8],
+        }
+    }
+}
+
+fn child_count(children: &[u32; 8]) -> u32 {
+    children.iter().filter(|c| **c != NO_CHILD).count() as u32
+}
+
+/// a flat octree that is kept in sync with an ``Octree``
+/// unlike ``Octree::flatten`` the nodes keep their place in the array when the octree is edited,
+/// so only the nodes that changed need to be copied to the gpu instead of the whole octree
+///
+/// removed nodes leave holes behind that are reused by later edits
+pub struct OctreeLayout {
+    data: Vec<FlatOctreeNode>,
+    nodes: HashMap<u32, NodeLayout>,
+    /// blocks of children that aren't used anymore, indexed by their length - 1
+    free_blocks: [Vec<u32>; 8],
+}
+
+impl OctreeLayout {
+    /// create the layout for all nodes of the octree
+    /// the whole array should be uploaded afterwards
+    pub fn new(octree: &mut Octree) -> Self {
+        let mut layout = Self {
+            data: vec![FlatOctreeNode::default()],
+            nodes: HashMap::from([(octree.root.index(), NodeLayout::default())]),
+            free_blocks: Default::default(),
+        };
+
+        let all = octree.nodes.iter().map(|(handle, _)| handle.index());
+        octree.changed.extend(all.collect::<Vec<_>>());
+        layout.update(octree);
+
+        layout
+    }
+
+    /// apply the changes made to the octree since the last update
+    /// returns the sorted ranges of nodes that have been written, these need to be copied to the gpu
+    /// the array may have grown, so the buffer needs space for ``len`` nodes
+    pub fn update(&mut self, octree: &mut Octree) -> Vec<Range<usize>> {
+        let changed = std::mem::take(&mut octree.changed);
+
+        // free the removed nodes first, the pool might have reused their indices for new nodes
+        for &index in &changed {
+            let Some(node) = octree.nodes.get(PoolHandle::from_index(index)) else {
+                continue;
+            };
+            let Some(layout) = self.nodes.get(&index) else {
+                continue;
+            };
+
+            for (old, new) in layout.children.into_iter().zip(node.children) {
+                if old != NO_CHILD && old != new {
+                    self.free_subtree(old);
+                }
+            }
+        }
+
+        // nodes whose children changed get a new block, so all children have to be written again
+        let mut written = Vec::with_capacity(changed.len());
+        for &index in &changed {
+            let Some(node) = octree.nodes.get(PoolHandle::from_index(index)) else {
+                continue;
+            };
+            let layout = *self.nodes.entry(index).or_default();
+
+            if layout.children != node.children {
+                let old_len = child_count(&layout.children);
+                let new_len = child_count(&node.children);
+
+                let block = if old_len == new_len {
+                    layout.block
+                } else {
+                    self.free_block(layout.block, old_len);
+                    self.alloc_block(new_len)
+                };
+
+                let children = node.children.iter().filter(|c| **c != NO_CHILD);
+                for (rank, child) in children.enumerate() {
+                    self.nodes.entry(*child).or_default().entry = block + rank as u32;
+                    written.push(*child);
+                }
+
+                let layout = self.nodes.get_mut(&index).unwrap();
+                layout.block = block;
+                layout.children = node.children;
+            }
+
+            written.push(index);
+        }
+
+        let mut ranges: Vec<Range<usize>> = vec![];
+        let mut entries: Vec<usize> = written
+            .into_iter()
+            .map(|index| self.write_node(octree, index))
+            .collect();
+        entries.sort_unstable();
+        entries.dedup();
+
+        for entry in entries {
+            match ranges.last_mut() {
+                Some(range) if range.end == entry => range.end += 1,
+                _ => ranges.push(entry..entry + 1),
+            }
+        }
+
+        ranges
+    }
+
+    /// the amount of nodes in the array, including the holes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// all nodes, this is the data that should be in the gpu buffer
+    #[must_use]
+    pub fn nodes(&self) -> &[FlatOctreeNode] {
+        &self.data
+    }
+
+    /// copy the current state to a ``FlatOctree``
+    #[must_use]
+    pub fn to_flat(&self) -> FlatOctree {
+        FlatOctree {
+            data: self.data.as_slice().into(),
+        }
+    }
+
+    /// update the flat node at its entry, returns the entry
+    fn write_node(&mut self, octree: &Octree, index: u32) -> usize {
+        let node = octree.nodes.get(PoolHandle::from_index(index)).unwrap();
+        let layout = self.nodes[&index];
+
+        let flat = &mut self.data[layout.entry as usize];
+        *flat = FlatOctreeNode {
+            colors: node.colors,
+            ..Default::default()
+        };
+        flat.set_valid_mask(node.get_valid_mask());
+        flat.set_child_ptr(layout.block);
+
+        layout.entry as usize
+    }
+
+    fn alloc_block(&mut self, len: u32) -> u32 {
+        if len == 0 {
+            return 0;
+        }
+
+        if let Some(block) = self.free_blocks[len as usize - 1].pop() {
+            return block;
+        }
+
+        let block = self.data.len() as u32;
+        self.data
+            .resize(self.data.len() + len as usize, FlatOctreeNode::default());
+        block
+    }
+
+    fn free_block(&mut self, block: u32, len: u32) {
+        if len > 0 {
+            self.free_blocks[len as usize - 1].push(block);
+        }
+    }
+
+    fn free_subtree(&mut self, index: u32) {
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            if let Some(layout) = self.nodes.remove(&index) {
+                self.free_block(layout.block, child_count(&layout.children));
+                stack.extend(layout.children.iter().filter(|c| **c != NO_CHILD));
+            }
+        }
+    }
+}
+
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct FlatOctree {
     data: Arc<[FlatOctreeNode]>,
@@ -197,52 +682,32 @@ impl FlatOctree {
     /// convert a flat octree back to a normal octree
     /// for example after loading it from a file
     #[must_use]
-    pub fn unflatten(&self) -> OctreeNode {
-        struct StackNode {
-            ptr: *mut OctreeNode,
-            index: usize, // the index of this node in the flat array
-        }
+    pub fn unflatten(&self) -> Octree {
+        let mut octree = Octree::new();
+        let root = octree.root.index();
+        octree.node_mut(root).colors = self.data[0].colors;
 
-        let mut root = OctreeNode {
-            colors: self.data[0].colors,
-            ..Default::default()
-        };
+        // (the index of the node in the pool, the index of this node in the flat array)
+        let mut stack = vec![(root, 0)];
 
-        let mut stack = vec![StackNode {
-            ptr: &mut root,
-            index: 0,
-        }];
-
-        while let Some(stack_node) = stack.pop() {
-            let flat_node = &self.data[stack_node.index];
+        while let Some((node, flat_index)) = stack.pop() {
+            let flat_node = &self.data[flat_index];
             let valid_mask = flat_node.get_valid_mask();
 
             for (i, j) in (0..8).filter(|i| valid_mask & (1 << i) != 0).enumerate() {
                 let child_index = flat_node.get_child_ptr() as usize + i;
-                let child = &self.data[child_index];
 
-                let node = OctreeNode {
-                    colors: child.colors,
+                let child = octree.insert_node(OctreeNode {
+                    colors: self.data[child_index].colors,
                     ..Default::default()
-                };
-
-                let boxed_node = Box::new(node);
-                unsafe { (*stack_node.ptr).children[j] = Some(boxed_node) };
-
-                let mem_ptr = unsafe {
-                    // we need a pointer to that box after we moved it in to the vector
-                    // because we just wrote to index j, we don't need to check if its really Some
-                    Box::as_mut_ptr((*stack_node.ptr).children[j].as_mut().unwrap_unchecked())
-                };
-
-                stack.push(StackNode {
-                    index: child_index,
-                    ptr: mem_ptr,
                 });
+                octree.node_mut(node).children[j] = child;
+
+                stack.push((child, child_index));
             }
         }
 
-        root
+        octree
     }
 
     /// convert a flat octree to its raw unsafe format
@@ -255,14 +720,36 @@ impl FlatOctree {
     }
 
     /// convert the raw data back to an flat octree
-    /// TODO: add safety
+    /// the bytes are copied, so they don't need to be aligned
+    /// use ``is_valid`` before unflattening data that was read from a file
     #[must_use]
     pub fn from_bytes(bytes: &[u8]) -> Self {
         let node_count = bytes.len() / std::mem::size_of::<FlatOctreeNode>();
-        let ptr = bytes.as_ptr().cast();
-        Self {
-            data: unsafe { std::slice::from_raw_parts(ptr, node_count) }.into(),
+        let mut data = vec![FlatOctreeNode::default(); node_count];
+
+        // every bit pattern is a valid node
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                data.as_mut_ptr().cast::<u8>(),
+                node_count * std::mem::size_of::<FlatOctreeNode>(),
+            );
         }
+
+        Self { data: data.into() }
+    }
+
+    /// tells if the data has the layout created by ``Octree::flatten``
+    /// every child pointer has to point after its node and stay inside of the array,
+    /// ``unflatten`` can panic or never finish otherwise
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        !self.data.is_empty()
+            && self.data.iter().enumerate().all(|(i, node)| {
+                let count = node.get_valid_mask().count_ones() as usize;
+                let child_ptr = node.get_child_ptr() as usize;
+                count == 0 || (child_ptr > i && child_ptr + count <= self.data.len())
+            })
     }
 }
 
@@ -271,7 +758,7 @@ impl FlatOctree {
 /// |  64 bit   |    8 bit      |    24 bit   |
 ///    colors      valid mask      child ptr
 #[repr(C)]
-#[derive(Default, Clone, PartialEq, Eq)]
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub struct FlatOctreeNode {
     colors: ColorData,
     /// contains the ``valid_mask`` and the ``child_pointer``
@@ -303,6 +790,24 @@ impl FlatOctreeNode {
     pub fn get_child_ptr(&self) -> u32 {
         self.child_descriptor & 0xFFF_FFF
     }
+
+    /// the node in the byte order of the gpu buffer, little endian
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.colors.0.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.child_descriptor.to_le_bytes());
+        bytes
+    }
+
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self {
+            colors: ColorData(u64::from_le_bytes(bytes[..8].try_into().unwrap())),
+            child_descriptor: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            _padding: 0,
+        }
+    }
 }
 
 #[allow(clippy::missing_fields_in_debug)]
@@ -325,7 +830,8 @@ impl Debug for FlatOctreeNode {
 
 #[cfg(test)]
 mod tests {
-    use super::{FlatOctree, FlatOctreeNode, OctreeNode};
+    use super::{FlatOctree, FlatOctreeNode, Octree, OctreeLayout, OctreeNode, Visit};
+    use crate::world::rng::Rng;
     use math::dvec3;
 
     #[test]
@@ -353,9 +859,191 @@ mod tests {
         }
     }
 
+    #[test]
+    fn merge_uniform_region() {
+        let mut octree = Octree::new();
+
+        // fill every child of the first octant with the same color
+        for pos in OctreeNode::NODE_POS {
+            octree.write(dvec3(-0.5, -0.5, -0.5) + pos * 0.25, 3, 2);
+        }
+
+        assert_eq!(octree.node_count(), 1);
+        assert_eq!(octree.root().get_valid_mask(), 0);
+        assert_eq!(octree.sample(dvec3(-0.9, -0.1, -0.6), 10), 3);
+    }
+
+    #[test]
+    fn merge_after_deep_write() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.3, 0.2, 0.1), 5, 8);
+        assert_eq!(octree.node_count(), 8);
+
+        // writing the same color as the surrounding space merges everything again
+        octree.write(dvec3(0.3, 0.2, 0.1), 0, 8);
+        assert_eq!(octree.node_count(), 1);
+        assert_eq!(octree.sample(dvec3(0.3, 0.2, 0.1), 8), 0);
+    }
+
+    #[test]
+    fn write_inside_merged_region() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.5, 0.5, 0.5), 7, 1);
+
+        octree.write(dvec3(0.1, 0.1, 0.1), 2, 4);
+
+        // the rest of the octant keeps its color
+        assert_eq!(octree.sample(dvec3(0.1, 0.1, 0.1), 4), 2);
+        assert_eq!(octree.sample(dvec3(0.9, 0.9, 0.9), 4), 7);
+        assert_eq!(octree.sample(dvec3(0.2, 0.1, 0.1), 4), 7);
+        assert_eq!(octree.sample(dvec3(-0.5, 0.5, 0.5), 4), 0);
+    }
+
+    #[test]
+    fn coarse_write_replaces_children() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.1, 0.1, 0.1), 2, 6);
+        octree.write(dvec3(0.5, 0.5, 0.5), 4, 1);
+
+        assert_eq!(octree.node_count(), 1);
+        assert_eq!(octree.sample(dvec3(0.1, 0.1, 0.1), 6), 4);
+    }
+
+    #[test]
+    fn iter_leaves() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.5, 0.5, 0.5), 1, 1);
+        octree.write(dvec3(-0.1, -0.1, -0.1), 2, 3);
+
+        let leaves: Vec<_> = octree.iter_leaves(10).collect();
+        assert_eq!(
+            leaves,
+            [
+                (dvec3(-0.125, -0.125, -0.125), 0.25, 2),
+                (dvec3(0.5, 0.5, 0.5), 1.0, 1)
+            ]
+        );
+
+        // the deeper leaf is represented by its parent octant
+        let lod: Vec<_> = octree.iter_leaves(1).map(|(_, size, _)| size).collect();
+        assert_eq!(lod, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn visit() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(-0.1, -0.1, -0.1), 2, 4);
+        octree.write(dvec3(0.5, 0.5, 0.5), 1, 1);
+
+        let mut layers = vec![];
+        octree.visit(|octant| {
+            layers.push(octant.layer);
+            Visit::Continue
+        });
+        assert_eq!(layers, [1, 2, 3, 4, 1]);
+
+        let mut count = 0;
+        octree.visit(|_| {
+            count += 1;
+            Visit::SkipChildren
+        });
+        assert_eq!(count, 2);
+
+        count = 0;
+        octree.visit(|_| {
+            count += 1;
+            Visit::Stop
+        });
+        assert_eq!(count, 1);
+    }
+
+    /// every cell on the layer of both octrees has the same color
+    fn assert_same(a: &Octree, b: &Octree, layer: usize) {
+        let cells = 1 << layer;
+        for x in 0..cells {
+            for y in 0..cells {
+                for z in 0..cells {
+                    let pos =
+                        (dvec3(x as f64, y as f64, z as f64) + 0.5) / cells as f64 * 2.0 - 1.0;
+                    assert_eq!(a.sample(pos, layer), b.sample(pos, layer), "{pos}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn capture_restores_the_cell() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.3, 0.3, 0.3), 4, 3);
+        octree.write(dvec3(0.1, 0.1, 0.1), 5, 5);
+        octree.write(dvec3(-0.6, 0.6, 0.6), 6, 1);
+        let original = octree.flatten().unflatten();
+
+        for (pos, layer) in [(dvec3(0.2, 0.2, 0.2), 2), (dvec3(-0.4, 0.6, 0.6), 3)] {
+            let writes = octree.capture(pos, layer);
+            octree.write(pos, 9, layer);
+
+            for (pos, color, layer) in writes {
+                octree.write(pos, color, layer);
+            }
+            assert_same(&octree, &original, 5);
+        }
+    }
+
+    #[test]
+    fn layout_updates() {
+        let mut octree = Octree::new();
+        octree.write(dvec3(0.3, 0.3, 0.3), 4, 3);
+
+        let mut layout = OctreeLayout::new(&mut octree);
+        let mut gpu = layout.nodes().to_vec();
+
+        // seeded, so the edits are the same every time
+        let mut rng = Rng::new(0x2545_f491);
+
+        for _ in 0..500 {
+            let pos = dvec3(
+                rng.f64() * 2.0 - 1.0,
+                rng.f64() * 2.0 - 1.0,
+                rng.f64() * 2.0 - 1.0,
+            );
+            let color = rng.below(3) as u8;
+            let layer = rng.below(4) as usize + 1;
+            octree.write(pos, color, layer);
+
+            // apply the patches like they would be uploaded to the gpu
+            let patches = layout.update(&mut octree);
+            gpu.resize(layout.len(), FlatOctreeNode::default());
+            for range in patches {
+                gpu[range.clone()].copy_from_slice(&layout.nodes()[range]);
+            }
+
+            let uploaded = FlatOctree {
+                data: gpu.as_slice().into(),
+            };
+            assert_same(&octree, &uploaded.unflatten(), 4);
+        }
+    }
+
+    #[test]
+    fn layout_only_writes_changes() {
+        let mut octree = Octree::new();
+        for i in 0..8 {
+            octree.write(dvec3(-0.9 + i as f64 * 0.25, 0.1, 0.1), 1, 4);
+        }
+        let mut layout = OctreeLayout::new(&mut octree);
+
+        // only the written node and its parents change
+        octree.write(dvec3(0.9, 0.9, 0.9), 2, 2);
+        let written: usize = layout.update(&mut octree).iter().map(|r| r.len()).sum();
+        assert_eq!(written, 2);
+
+        assert!(layout.update(&mut octree).is_empty());
+    }
+
     #[test]
     fn flatten() {
-        let mut node = OctreeNode::default();
+        let mut node = Octree::new();
 
         for x in 0..10 {
             let y = (x as f64 / 3.0).sin() / 2.0;
@@ -371,7 +1059,7 @@ mod tests {
 
     #[test]
     fn flatten_bytes() {
-        let mut node = OctreeNode::default();
+        let mut node = Octree::new();
 
         for x in 0..10 {
             let y = (x as f64 / 3.0).sin() / 2.0;
@@ -0,0 +1,288 @@
+// a reader for MagicaVoxel .vox files
+// only the models and the palette are read, the scene graph, materials and layers are ignored
+// <https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt>
+
+use std::{fmt, path::Path};
+
+use math::DVec3;
+
+use super::{palette::VoxelPalette, svo::Octree};
+
+const MAGIC: &[u8; 4] = b"VOX ";
+
+#[derive(Debug)]
+pub enum VoxError {
+    Io(std::io::Error),
+    /// the file isn't a valid .vox file
+    Invalid(String),
+}
+
+impl fmt::Display for VoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read vox file: {err}"),
+            Self::Invalid(err) => write!(f, "invalid vox file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for VoxError {}
+
+impl From<std::io::Error> for VoxError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// one model of a .vox file
+/// the coordinates are converted to y up, MagicaVoxel uses z up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxModel {
+    pub size: [u32; 3],
+    /// the position and the color index of every filled voxel
+    pub voxels: Vec<([u8; 3], u8)>,
+}
+
+impl VoxModel {
+    /// the octree layer on which every voxel gets its own cell
+    #[must_use]
+    pub fn layer(&self) -> usize {
+        let max = self.size.into_iter().max().unwrap_or(1).max(2);
+        (u32::BITS - (max - 1).leading_zeros()) as usize
+    }
+
+    /// write the model in to a new octree
+    /// the model is centered and scaled so its longest side fills the octree
+    /// the colors are indices in to the palette of the file
+    #[must_use]
+    pub fn to_octree(&self) -> Octree {
+        let mut octree = Octree::new();
+
+        let layer = self.layer();
+        let cells = 1u32 << layer;
+        let cell_size = 2.0 / f64::from(cells);
+
+        // whole cells, so every voxel stays aligned to the grid
+        let offset = self.size.map(|size| (cells - size) / 2);
+
+        for &(pos, color) in &self.voxels {
+            let cell = DVec3::from_array(
+                [0, 1, 2].map(|axis| f64::from(u32::from(pos[axis]) + offset[axis])),
+            );
+            let pos = (cell + 0.5) * cell_size - 1.0;
+            octree.write(pos, color, layer);
+        }
+
+        octree
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    /// the palette stored in the file or the default one of MagicaVoxel
+    pub palette: VoxelPalette,
+}
+
+/// read and parse a .vox file
+/// # Errors
+/// if the file can't be read or isn't a valid .vox file
+pub fn load(path: impl AsRef<Path>) -> Result<VoxFile, VoxError> {
+    parse(&std::fs::read(path)?)
+}
+
+/// # Errors
+/// if the data isn't a valid .vox file
+pub fn parse(data: &[u8]) -> Result<VoxFile, VoxError> {
+    if !data.starts_with(MAGIC) {
+        return Err(VoxError::Invalid("missing \"VOX \" identifier".into()));
+    }
+
+    let mut reader = Reader { data, offset: 8 };
+
+    let (id, content, children) = reader.chunk()?;
+    if id != *b"MAIN" {
+        return Err(VoxError::Invalid("the first chunk isn't MAIN".into()));
+    }
+    reader.skip(content)?;
+
+    let end = reader.offset + children;
+    let mut models = vec![];
+    let mut palette = VoxelPalette::default();
+    let mut size = None;
+
+    while reader.offset < end {
+        let (id, content, children) = reader.chunk()?;
+        let mut chunk = Reader {
+            data: reader.bytes(content)?,
+            offset: 0,
+        };
+        reader.skip(children)?;
+
+        match &id {
+            b"SIZE" => {
+                let [x, y, z] = [chunk.u32()?, chunk.u32()?, chunk.u32()?];
+                if [x, y, z].iter().any(|v| !(1..=256).contains(v)) {
+                    return Err(VoxError::Invalid(format!(
+                        "model size {x}x{y}x{z} is out of range"
+                    )));
+                }
+                size = Some([x, y, z]);
+            }
+            b"XYZI" => {
+                let Some([x, y, z]) = size.take() else {
+                    return Err(VoxError::Invalid("XYZI chunk without SIZE".into()));
+                };
+
+                let count = chunk.u32()? as usize;
+                let voxels = chunk
+                    .bytes(count.saturating_mul(4))?
+                    .chunks_exact(4)
+                    .filter(|v| u32::from(v[0]) < x && u32::from(v[1]) < y && u32::from(v[2]) < z)
+                    // z up to y up, flipping the new z axis keeps the model from being mirrored
+                    .map(|v| ([v[0], v[2], (y - 1) as u8 - v[1]], v[3]))
+                    .filter(|(_, color)| *color != 0)
+                    .collect();
+
+                models.push(VoxModel {
+                    size: [x, z, y],
+                    voxels,
+                });
+            }
+            b"RGBA" => {
+                // the palette starts at color index 1, the last entry is unused
+                let colors = chunk.bytes(256 * 4)?;
+                for (color, rgba) in palette.colors[1..].iter_mut().zip(colors.chunks_exact(4)) {
+                    color.copy_from_slice(rgba);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(VoxFile { models, palette })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], VoxError> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or_else(|| VoxError::Invalid("file is truncated".into()))?;
+
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), VoxError> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// returns the id, the content size and the size of the children
+    fn chunk(&mut self) -> Result<([u8; 4], usize, usize), VoxError> {
+        let id = self.bytes(4)?.try_into().unwrap();
+        Ok((id, self.u32()? as usize, self.u32()? as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::dvec3;
+
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((content.len() as u32).to_le_bytes());
+        chunk.extend((children.len() as u32).to_le_bytes());
+        chunk.extend(content);
+        chunk.extend(children);
+        chunk
+    }
+
+    fn build(size: [u32; 3], voxels: &[[u8; 4]], palette: Option<[u8; 4]>) -> Vec<u8> {
+        let mut children = chunk(b"SIZE", &size.map(u32::to_le_bytes).concat(), &[]);
+
+        let mut xyzi = (voxels.len() as u32).to_le_bytes().to_vec();
+        xyzi.extend(voxels.as_flattened());
+        children.extend(chunk(b"XYZI", &xyzi, &[]));
+
+        if let Some(color) = palette {
+            children.extend(chunk(b"RGBA", &[color; 256].concat(), &[]));
+        }
+
+        let mut file = MAGIC.to_vec();
+        file.extend(150u32.to_le_bytes());
+        file.extend(chunk(b"MAIN", &[], &children));
+        file
+    }
+
+    #[test]
+    fn parse_model() {
+        let file = build([2, 3, 4], &[[0, 0, 0, 1], [1, 2, 3, 7]], None);
+        let vox = parse(&file).unwrap();
+
+        assert_eq!(vox.models.len(), 1);
+        assert_eq!(vox.models[0].size, [2, 4, 3]);
+        assert_eq!(vox.models[0].voxels, [([0, 0, 2], 1), ([1, 3, 0], 7)]);
+        assert_eq!(vox.palette, VoxelPalette::magica_voxel());
+    }
+
+    #[test]
+    fn parse_palette() {
+        let file = build([1, 1, 1], &[[0, 0, 0, 1]], Some([1, 2, 3, 4]));
+        let vox = parse(&file).unwrap();
+
+        assert_eq!(vox.palette.colors[0], [0; 4]);
+        assert_eq!(vox.palette.colors[1], [1, 2, 3, 4]);
+        assert_eq!(vox.palette.colors[255], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        assert!(parse(b"not a vox file").is_err());
+
+        let mut file = build([2, 2, 2], &[[0, 0, 0, 1]], None);
+        file.truncate(file.len() - 2);
+        assert!(parse(&file).is_err());
+
+        assert!(parse(&build([0, 2, 2], &[], None)).is_err());
+    }
+
+    #[test]
+    fn default_palette() {
+        let palette = VoxelPalette::magica_voxel();
+        assert_eq!(palette.colors[0], [0; 4]);
+        assert_eq!(palette.colors[1], [255, 255, 255, 255]);
+        assert_eq!(palette.colors[2], [255, 255, 204, 255]);
+        assert_eq!(palette.colors[215], [0, 0, 51, 255]);
+        assert_eq!(palette.colors[216], [238, 0, 0, 255]);
+        assert_eq!(palette.colors[255], [17, 17, 17, 255]);
+    }
+
+    #[test]
+    fn to_octree() {
+        let model = VoxModel {
+            size: [4, 2, 1],
+            voxels: vec![([0, 0, 0], 3), ([3, 1, 0], 5)],
+        };
+        assert_eq!(model.layer(), 2);
+
+        let octree = model.to_octree();
+        // centered on the y and z axis
+        assert_eq!(octree.sample(dvec3(-0.75, -0.25, -0.25), 2), 3);
+        assert_eq!(octree.sample(dvec3(0.75, 0.25, -0.25), 2), 5);
+        assert_eq!(octree.iter_leaves(usize::MAX).count(), 2);
+    }
+}
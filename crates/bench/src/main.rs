@@ -0,0 +1,178 @@
+// runs the standardized scenes along a fixed camera path and writes the cpu and gpu timings of every frame,
+// so a change that is supposed to be faster has numbers from before and after it
+//
+//     cargo run --release -p puddle-bench -- --present-mode immediate --format csv --out before.csv
+//
+// --scene      draw_calls, chunks or transparency, all of them if it isn't given
+// --frames     the frames that are measured per scene, 1000 by default
+// --warmup     the frames rendered before measuring, so the pipelines and caches are warm, 60 by default
+// --format     csv or json, csv by default
+// --out        the file the timings are written to, stdout if it isn't given
+//
+// the other flags are the usual settings, see ``application::settings``
+// the present mode should be immediate, otherwise vsync hides the differences
+// the scenes are still rendered to a window, so the numbers include presenting and depend on the compositor
+// running headless needs the RenderHandler to draw to an offscreen target, see rendering/tests/offscreen.rs
+
+use std::error::Error;
+
+use application::{Application, Settings};
+use report::{Format, Sample};
+use scenes::Scene;
+
+mod report;
+mod scenes;
+
+/// the time step of every frame, the scenes don't depend on the time between frames
+const FRAME_DELTA: f32 = 1.0 / 60.0;
+
+struct Options {
+    scenes: Vec<Scene>,
+    frames: u32,
+    warmup: u32,
+    format: Format,
+    out: Option<String>,
+}
+
+impl Options {
+    /// the settings flags are skipped, ``Settings::load`` reads them
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self {
+            scenes: Scene::ALL.to_vec(),
+            frames: 1000,
+            warmup: 60,
+            format: Format::Csv,
+            out: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                continue;
+            };
+            let (key, value) = match flag.split_once('=') {
+                Some((key, value)) => (key, Some(value.to_owned())),
+                None => (flag, None),
+            };
+            if !["scene", "frames", "warmup", "format", "out"].contains(&key) {
+                continue;
+            }
+
+            let value = match value {
+                Some(value) => value,
+                None => args
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| format!("--{key} needs a value"))?,
+            };
+            let invalid = || format!("invalid value {value:?} for --{key}");
+
+            match key {
+                "scene" => options.scenes = vec![Scene::from_name(&value).ok_or_else(invalid)?],
+                "frames" => options.frames = value.parse().map_err(|_| invalid())?,
+                "warmup" => options.warmup = value.parse().map_err(|_| invalid())?,
+                "format" => options.format = Format::from_name(&value).ok_or_else(invalid)?,
+                _ => options.out = Some(value),
+            }
+        }
+
+        if options.frames == 0 {
+            return Err("--frames needs to be at least 1".to_owned());
+        }
+        Ok(options)
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options::parse(&args)?;
+
+    let mut settings = Settings::load()?;
+    // the recording would replace the camera path
+    settings.record = None;
+    settings.replay = None;
+
+    let mut app = Application::new(settings)?;
+    scenes::add_base_octree(&mut app)?;
+
+    let mut samples = vec![];
+    for scene in &options.scenes {
+        let Some(mut loaded) = scene.load(&mut app)? else {
+            eprintln!("skipping {}, its shaders aren't built", scene.name());
+            continue;
+        };
+        scenes::show_only(&mut app.renderer, &loaded.batches);
+
+        for frame in 0..options.warmup + options.frames {
+            if app.window.window.should_close() {
+                eprintln!("the window was closed, the results are incomplete");
+                break;
+            }
+
+            let measured = frame.checked_sub(options.warmup);
+            app.world.camera.transform = scene.camera(measured.unwrap_or(0), options.frames);
+            app.world.fixed_delta = Some(FRAME_DELTA);
+            loaded.update(&app.world);
+            app.frame();
+
+            if let Some(frame) = measured {
+                let stats = app.renderer.frame_stats();
+                samples.push(Sample {
+                    scene: scene.name(),
+                    frame,
+                    cpu: app.cpu_timings(),
+                    frame_time: stats.frame_time,
+                    gpu_time: stats.gpu_time,
+                });
+            }
+        }
+    }
+
+    let output = match options.format {
+        Format::Csv => report::csv(&samples),
+        Format::Json => report::json(&samples),
+    };
+    match &options.out {
+        Some(path) => std::fs::write(path, output)?,
+        None => print!("{output}"),
+    }
+
+    eprint!("{}", report::table(&report::summarize(&samples)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
+
+    #[test]
+    fn bench_flags_and_settings_mix() {
+        let options = Options::parse(&args(&[
+            "--present-mode",
+            "immediate",
+            "--scene=chunks",
+            "--frames",
+            "500",
+            "--format",
+            "json",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.scenes, [Scene::Chunks]);
+        assert_eq!(options.frames, 500);
+        assert_eq!(options.warmup, 60);
+        assert_eq!(options.format, Format::Json);
+        assert_eq!(options.out, None);
+    }
+
+    #[test]
+    fn invalid_flags() {
+        assert!(Options::parse(&args(&["--scene", "triangles"])).is_err());
+        assert!(Options::parse(&args(&["--frames", "0"])).is_err());
+        assert!(Options::parse(&args(&["--out"])).is_err());
+    }
+}
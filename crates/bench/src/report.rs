@@ -0,0 +1,281 @@
+// the timings of the measured frames, written as csv or json
+// every frame is one row, the summary with the percentiles per scene is added to the json and printed at the end
+
+use std::{fmt::Write, time::Duration};
+
+use application::CpuTimings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// the timings of a single measured frame
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub scene: &'static str,
+    /// counted from the first measured frame of the scene
+    pub frame: u32,
+    pub cpu: CpuTimings,
+    /// see ``FrameStats::frame_time``
+    pub frame_time: Duration,
+    /// see ``FrameStats::gpu_time``, None if the queue has no timestamps
+    pub gpu_time: Option<Duration>,
+}
+
+/// in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl Stats {
+    /// None if there are no values
+    #[must_use]
+    pub fn new(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+
+        // nearest rank, so the percentiles are always one of the measured values
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+            values[rank.clamp(1, values.len()) - 1]
+        };
+
+        Some(Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub scene: &'static str,
+    pub frames: usize,
+    pub frame_time: Stats,
+    pub cpu_render: Stats,
+    /// None if no frame of the scene has a gpu time
+    pub gpu_time: Option<Stats>,
+}
+
+/// the stats of every scene, in the order the scenes were run
+#[must_use]
+pub fn summarize(samples: &[Sample]) -> Vec<Summary> {
+    let mut scenes: Vec<&'static str> = vec![];
+    for sample in samples {
+        if !scenes.contains(&sample.scene) {
+            scenes.push(sample.scene);
+        }
+    }
+
+    scenes
+        .into_iter()
+        .filter_map(|scene| {
+            let samples: Vec<_> = samples.iter().filter(|s| s.scene == scene).collect();
+            Some(Summary {
+                scene,
+                frames: samples.len(),
+                frame_time: Stats::new(samples.iter().map(|s| ms(s.frame_time)).collect())?,
+                cpu_render: Stats::new(samples.iter().map(|s| ms(s.cpu.render)).collect())?,
+                gpu_time: Stats::new(samples.iter().filter_map(|s| s.gpu_time.map(ms)).collect()),
+            })
+        })
+        .collect()
+}
+
+const CSV_HEADER: &str = "scene,frame,frame_ms,gpu_ms,tasks_ms,update_ms,ui_ms,render_ms,events_ms";
+
+/// a row per frame, frames without a gpu time have an empty ``gpu_ms``
+#[must_use]
+pub fn csv(samples: &[Sample]) -> String {
+    let mut out = format!("{CSV_HEADER}\n");
+    for sample in samples {
+        let gpu = sample.gpu_time.map(|t| format!("{:.3}", ms(t)));
+        let _ = writeln!(
+            out,
+            "{},{},{:.3},{},{}",
+            sample.scene,
+            sample.frame,
+            ms(sample.frame_time),
+            gpu.unwrap_or_default(),
+            cpu_values(&sample.cpu).map(|v| format!("{v:.3}")).join(","),
+        );
+    }
+    out
+}
+
+/// ``{"summary": [...], "frames": [...]}``, missing gpu times are null
+#[must_use]
+pub fn json(samples: &[Sample]) -> String {
+    let summaries: Vec<String> = summarize(samples)
+        .iter()
+        .map(|summary| {
+            format!(
+                r#"{{"scene":"{}","frames":{},"frame_ms":{},"cpu_render_ms":{},"gpu_ms":{}}}"#,
+                summary.scene,
+                summary.frames,
+                stats_json(Some(&summary.frame_time)),
+                stats_json(Some(&summary.cpu_render)),
+                stats_json(summary.gpu_time.as_ref()),
+            )
+        })
+        .collect();
+
+    let frames: Vec<String> = samples
+        .iter()
+        .map(|sample| {
+            let [tasks, update, ui, render, events] = cpu_values(&sample.cpu);
+            format!(
+                r#"{{"scene":"{}","frame":{},"frame_ms":{:.3},"gpu_ms":{},"tasks_ms":{tasks:.3},"update_ms":{update:.3},"ui_ms":{ui:.3},"render_ms":{render:.3},"events_ms":{events:.3}}}"#,
+                sample.scene,
+                sample.frame,
+                ms(sample.frame_time),
+                sample
+                    .gpu_time
+                    .map_or_else(|| "null".to_owned(), |t| format!("{:.3}", ms(t))),
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"summary\":[{}],\"frames\":[{}]}}\n",
+        summaries.join(","),
+        frames.join(",")
+    )
+}
+
+/// a table for the terminal
+#[must_use]
+pub fn table(summaries: &[Summary]) -> String {
+    let mut out = format!(
+        "{:<14}{:>8}{:>12}{:>12}{:>12}{:>12}\n",
+        "scene", "frames", "frame p50", "frame p99", "gpu p50", "gpu p99"
+    );
+    for summary in summaries {
+        let gpu = |value: fn(&Stats) -> f64| {
+            summary
+                .gpu_time
+                .as_ref()
+                .map_or_else(|| "-".to_owned(), |stats| format!("{:.3}", value(stats)))
+        };
+        let _ = writeln!(
+            out,
+            "{:<14}{:>8}{:>12.3}{:>12.3}{:>12}{:>12}",
+            summary.scene,
+            summary.frames,
+            summary.frame_time.p50,
+            summary.frame_time.p99,
+            gpu(|stats| stats.p50),
+            gpu(|stats| stats.p99),
+        );
+    }
+    out
+}
+
+fn stats_json(stats: Option<&Stats>) -> String {
+    stats.map_or_else(
+        || "null".to_owned(),
+        |stats| {
+            format!(
+                r#"{{"mean":{:.3},"p50":{:.3},"p95":{:.3},"p99":{:.3}}}"#,
+                stats.mean, stats.p50, stats.p95, stats.p99
+            )
+        },
+    )
+}
+
+fn cpu_values(cpu: &CpuTimings) -> [f64; 5] {
+    [cpu.tasks, cpu.update, cpu.ui, cpu.render, cpu.events].map(ms)
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(scene: &'static str, frame: u32, gpu_time: Option<Duration>) -> Sample {
+        Sample {
+            scene,
+            frame,
+            cpu: CpuTimings::default(),
+            frame_time: Duration::from_millis(u64::from(frame) + 1),
+            gpu_time,
+        }
+    }
+
+    #[test]
+    fn percentiles_are_measured_values() {
+        let stats = Stats::new((1..=100).map(f64::from).collect()).unwrap();
+        assert_eq!(stats.mean, 50.5);
+        assert_eq!(stats.p50, 50.0);
+        assert_eq!(stats.p95, 95.0);
+        assert_eq!(stats.p99, 99.0);
+
+        let single = Stats::new(vec![3.0]).unwrap();
+        assert_eq!((single.p50, single.p99), (3.0, 3.0));
+        assert_eq!(Stats::new(vec![]), None);
+    }
+
+    #[test]
+    fn summaries_keep_the_scene_order() {
+        let samples = [
+            sample("chunks", 0, None),
+            sample("draw_calls", 0, Some(Duration::from_millis(2))),
+            sample("chunks", 1, None),
+        ];
+        let summaries = summarize(&samples);
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!((summaries[0].scene, summaries[0].frames), ("chunks", 2));
+        assert_eq!(summaries[0].gpu_time, None);
+        assert_eq!(summaries[1].gpu_time.unwrap().p50, 2.0);
+    }
+
+    #[test]
+    fn csv_has_a_row_per_frame() {
+        let samples = [
+            sample("chunks", 0, None),
+            sample("chunks", 1, Some(Duration::from_micros(1500))),
+        ];
+        let csv = csv(&samples);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("chunks,0,1.000,,"));
+        assert!(lines[2].starts_with("chunks,1,2.000,1.500,"));
+        assert!(lines
+            .iter()
+            .all(|line| line.split(',').count() == CSV_HEADER.split(',').count()));
+    }
+
+    #[test]
+    fn json_writes_null_without_gpu_times() {
+        let json = json(&[sample("transparency", 0, None)]);
+        assert!(json.starts_with(r#"{"summary":[{"scene":"transparency","frames":1,"#));
+        assert!(json.contains(r#""gpu_ms":null"#));
+    }
+}
@@ -0,0 +1,368 @@
+// the standardized scenes, the camera only depends on the frame index so every run draws the same frames
+//
+// draw_calls:   1000 small raymarched cubes, each its own draw call, the batch is recorded every frame
+// chunks:       100 voxel chunks in a 10 by 10 grid, meshed on the job system
+// transparency: 16 stacked sheets of transparent voxels, sorted back to front
+//
+// the meshed scenes need shaders/voxel_mesh.spv, they are skipped if it wasn't built with build.sh
+
+use std::f32::consts::TAU;
+
+use application::{
+    world::{
+        chunks::{Chunk, VoxelWorld},
+        meshing::{mesh_chunks, mesh_octree, TransparentChunk, VoxelMesh, VoxelMeshMaterials},
+        palette::VoxelPalette,
+        svo::Octree,
+        World,
+    },
+    Application,
+};
+use ash::{prelude::VkResult, vk};
+use math::{dvec3, Color, DVec3, IVec3, Transform, Vec3};
+use rendering::{
+    handler::{
+        render_batch::{BatchUsage, DrawData, RenderBatch},
+        RenderHandler,
+    },
+    vulkan::Buffer,
+};
+
+/// how deep the chunks are filled, 32 voxels on every side
+const CHUNK_DEPTH: usize = 5;
+/// the chunks of the ``Chunks`` scene are this big in world units
+const CHUNK_SIZE: f64 = 0.4;
+const GRID: i32 = 10;
+/// the first palette index of the transparent sheets
+const GLASS: u8 = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    DrawCalls,
+    Chunks,
+    Transparency,
+}
+
+/// what a scene added to the renderer
+pub struct LoadedScene {
+    /// the indices of the batches, the other batches are hidden while the scene runs
+    pub batches: Vec<usize>,
+    transparent: Option<TransparentChunk>,
+}
+
+impl LoadedScene {
+    /// sort the transparent faces for the camera of this frame
+    pub fn update(&mut self, world: &World) {
+        if let Some(chunk) = &mut self.transparent {
+            // the sheets are a single octree at the origin, so world space is octree space
+            chunk.sort(world.camera.transform.translation.as_dvec3());
+        }
+    }
+}
+
+impl Scene {
+    pub const ALL: [Self; 3] = [Self::DrawCalls, Self::Chunks, Self::Transparency];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DrawCalls => "draw_calls",
+            Self::Chunks => "chunks",
+            Self::Transparency => "transparency",
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scene| scene.name() == name)
+    }
+
+    /// add the batches of the scene to the renderer
+    /// None if the scene can't be drawn, because a shader is missing
+    /// # Errors
+    /// if there is no space left to allocate the buffers
+    pub fn load(self, app: &mut Application) -> VkResult<Option<LoadedScene>> {
+        let first = app.renderer.render_batches().len();
+        let mut transparent = None;
+
+        match self {
+            Self::DrawCalls => draw_calls(app)?,
+            Self::Chunks => {
+                let Some(materials) = VoxelMeshMaterials::load(&mut app.renderer)? else {
+                    return Ok(None);
+                };
+                if !chunks(app, &materials)? {
+                    return Ok(None);
+                }
+            }
+            Self::Transparency => {
+                let Some(materials) = VoxelMeshMaterials::load(&mut app.renderer)? else {
+                    return Ok(None);
+                };
+                let Some(chunk) = sheets(app, &materials)? else {
+                    return Ok(None);
+                };
+                transparent = Some(chunk);
+            }
+        }
+
+        Ok(Some(LoadedScene {
+            batches: (first..app.renderer.render_batches().len()).collect(),
+            transparent,
+        }))
+    }
+
+    /// one orbit around the scene over ``frames``
+    #[must_use]
+    pub fn camera(self, frame: u32, frames: u32) -> Transform {
+        let (center, radius, height) = match self {
+            Self::DrawCalls => (Vec3::ZERO, 2.5, 1.0),
+            Self::Chunks => {
+                let half = (CHUNK_SIZE * f64::from(GRID) * 0.5) as f32;
+                (Vec3::new(half, 0.0, half), half * 1.5, 1.5)
+            }
+            Self::Transparency => (Vec3::ZERO, 2.0, 0.6),
+        };
+
+        let angle = frame as f32 / frames.max(1) as f32 * TAU;
+        let position = center + Vec3::new(angle.cos() * radius, height, angle.sin() * radius);
+        Transform::from_translation(position).looking_at(center, Vec3::Y)
+    }
+}
+
+/// show the batches of the scene and hide all others
+pub fn show_only(renderer: &mut RenderHandler, batches: &[usize]) {
+    for index in 0..renderer.render_batches().len() {
+        if let Some(batch) = renderer.render_batch_mut(index) {
+            batch.set_visible(batches.contains(&index));
+        }
+    }
+}
+
+/// the octree the voxel shader traces, it has to be in storage buffer 0
+/// # Errors
+/// if there is no space left to allocate the buffer
+pub fn add_base_octree(app: &mut Application) -> VkResult<()> {
+    let mut octree = Octree::new();
+    fill_terrain(&mut octree, 0.0);
+    let size = octree.flatten().as_bytes().len();
+
+    let buffer = Buffer::new(
+        app.renderer.device.clone(),
+        size as u64,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+
+    let handle = app.renderer.import_buffer(buffer.clone());
+    app.renderer.bind_storage_buffer(handle, Some(0));
+    app.world.add_octree(octree, buffer, 0);
+    Ok(())
+}
+
+/// rolling hills, ``seed`` moves them so neighboring chunks look different
+fn fill_terrain(octree: &mut Octree, seed: f64) {
+    let cells = 1 << CHUNK_DEPTH;
+    let size = 2.0 / f64::from(cells);
+    let center = |i: i32| -1.0 + (f64::from(i) + 0.5) * size;
+
+    for x in 0..cells {
+        for z in 0..cells {
+            let (px, pz) = (center(x), center(z));
+            let height = (px * 3.0 + seed).sin() * (pz * 2.0 + seed * 0.7).cos() * 0.4 - 0.2;
+            let color = 64 + ((x / 4 + z / 4) % 8) as u8 * 16;
+
+            for y in (0..cells).take_while(|&y| center(y) < height) {
+                octree.write(dvec3(px, center(y), pz), color, CHUNK_DEPTH);
+            }
+        }
+    }
+}
+
+/// a ``GRID`` cubed grid of small cubes, drawn with the voxel material of the world
+fn draw_calls(app: &mut Application) -> VkResult<()> {
+    let mut batch = RenderBatch::default();
+    batch.set_name("bench draw calls");
+    batch.set_material(app.world.material.clone());
+    // recorded every frame, that is what is measured
+    batch.set_usage(BatchUsage::Dynamic);
+
+    let step = 2.0 / GRID as f32;
+    for x in 0..GRID {
+        for y in 0..GRID {
+            for z in 0..GRID {
+                let center = Vec3::new(x as f32, y as f32, z as f32) * step - 1.0 + step * 0.5;
+                let vertices = cube_vertices(center, step * 0.4);
+
+                let buffer = Buffer::new(
+                    app.renderer.device.clone(),
+                    size_of_val(&vertices) as u64,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE,
+                )?;
+                buffer.write(0, &vertices);
+
+                batch.add_draw_call(DrawData {
+                    vertex_count: vertices.len() as u32,
+                    vertex_buffer: Some(buffer),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    app.renderer.add_render_batch(batch);
+    Ok(())
+}
+
+/// the triangles of a cube, counter clockwise from the outside like ``meshing::mesh_octree``
+fn cube_vertices(center: Vec3, half: f32) -> [[f32; 4]; 36] {
+    let mut vertices = [[0.0; 4]; 36];
+
+    for face in 0..6 {
+        let axis = face / 2;
+        let sign = if face % 2 == 0 { -1.0 } else { 1.0 };
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        let mut corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        if sign < 0.0 {
+            corners.reverse();
+        }
+
+        let corners = corners.map(|(cu, cv)| {
+            let mut position = center;
+            position[axis] += sign * half;
+            position[u] += cu * half;
+            position[v] += cv * half;
+            position.extend(1.0).to_array()
+        });
+
+        for (i, index) in [0, 1, 2, 0, 2, 3].into_iter().enumerate() {
+            vertices[face * 6 + i] = corners[index];
+        }
+    }
+
+    vertices
+}
+
+/// the chunks are merged in to one draw, there aren't enough storage buffer slots for a draw per chunk
+/// false if the mesh couldn't be uploaded
+fn chunks(app: &mut Application, materials: &VoxelMeshMaterials) -> VkResult<bool> {
+    let palette = VoxelPalette::grayscale();
+    app.world.set_palette(&palette);
+
+    let mut world = VoxelWorld::new(CHUNK_SIZE, GRID as u32, GRID as u32);
+    let mut coords = vec![];
+    for x in 0..GRID {
+        for z in 0..GRID {
+            let coord = IVec3::new(x, 0, z);
+            let mut octree = Octree::new();
+            fill_terrain(&mut octree, f64::from(x * GRID + z));
+            world.insert(coord, Chunk::new(octree));
+            coords.push(coord);
+        }
+    }
+
+    let mut merged = VoxelMesh::default();
+    for (coord, mesh) in mesh_chunks(&app.world.jobs, &world, &coords, CHUNK_DEPTH, &palette) {
+        let first = merged.vertices.len() as u32;
+        merged
+            .vertices
+            .extend(mesh.opaque.vertices.into_iter().map(|mut vertex| {
+                let local = DVec3::from(vertex.position.map(f64::from));
+                vertex.position = world.to_world(coord, local).as_vec3().to_array();
+                vertex
+            }));
+        merged
+            .indices
+            .extend(mesh.opaque.indices.into_iter().map(|index| first + index));
+    }
+
+    let Some(draw) = materials.upload(&mut app.renderer, &merged)? else {
+        return Ok(false);
+    };
+
+    let mut batch = RenderBatch::default();
+    batch.set_name("bench chunks");
+    batch.set_material(materials.material().clone());
+    batch.set_usage(BatchUsage::Static);
+    batch.add_draw_call(draw);
+    app.renderer.add_render_batch(batch);
+    Ok(true)
+}
+
+/// every other layer of an octree filled with a transparent color, so every pixel is covered many times
+fn sheets(
+    app: &mut Application,
+    materials: &VoxelMeshMaterials,
+) -> VkResult<Option<TransparentChunk>> {
+    let mut palette = VoxelPalette::grayscale();
+    for i in 0..8 {
+        let color = Color::hsv(i as f32 * 45.0, 0.6, 1.0).with_alpha(0.15);
+        palette.set_color(GLASS + i, color);
+    }
+    app.world.set_palette(&palette);
+
+    let cells = 1 << CHUNK_DEPTH;
+    let size = 2.0 / f64::from(cells);
+    let center = |i: i32| -1.0 + (f64::from(i) + 0.5) * size;
+
+    let mut octree = Octree::new();
+    for y in (0..cells).step_by(2) {
+        let color = GLASS + (y / 2 % 8) as u8;
+        for x in 0..cells {
+            for z in 0..cells {
+                octree.write(dvec3(center(x), center(y), center(z)), color, CHUNK_DEPTH);
+            }
+        }
+    }
+
+    let mesh = mesh_octree(&octree, CHUNK_DEPTH, &palette);
+    let Some(mut chunk) = materials.upload_transparent(&mut app.renderer, &mesh.transparent)?
+    else {
+        return Ok(None);
+    };
+    chunk.sort(app.world.camera.transform.translation.as_dvec3());
+
+    let mut batch = RenderBatch::default();
+    batch.set_name("bench transparency");
+    batch.set_material(materials.transparent.clone());
+    batch.add_draw_call(chunk.draw());
+    app.renderer.add_render_batch(batch);
+    Ok(Some(chunk))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for scene in Scene::ALL {
+            assert_eq!(Scene::from_name(scene.name()), Some(scene));
+        }
+        assert_eq!(Scene::from_name("triangles"), None);
+    }
+
+    #[test]
+    fn camera_path_is_a_closed_orbit() {
+        let start = Scene::Chunks.camera(0, 1000);
+        let end = Scene::Chunks.camera(1000, 1000);
+        assert!(start.translation.distance(end.translation) < 1e-4);
+        assert_eq!(
+            Scene::Chunks.camera(250, 1000),
+            Scene::Chunks.camera(250, 1000)
+        );
+    }
+
+    #[test]
+    fn cube_faces_point_outwards() {
+        let vertices = cube_vertices(Vec3::ZERO, 1.0);
+        for triangle in vertices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|v| Vec3::from_slice(&v));
+            let normal = (b - a).cross(c - a);
+            let center = (a + b + c) / 3.0;
+            assert!(normal.dot(center) > 0.0);
+        }
+    }
+}
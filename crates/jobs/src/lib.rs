@@ -0,0 +1,406 @@
+// one pool of worker threads for the background work of the renderer and the world
+// jobs can wait for other jobs, they are only queued once everything they depend on is done
+//
+// waiting on a job runs other queued jobs in the meantime, so a job can wait on its dependencies
+// without blocking a worker, see ``JobHandle::wait``
+mod scope;
+
+pub use scope::Scope;
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// the jobs never panic while holding a lock, the panics are caught around the task
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Default)]
+struct NodeState {
+    done: bool,
+    /// queued once this job is done, if they don't wait on anything else
+    dependents: Vec<Arc<JobNode>>,
+}
+
+struct JobNode {
+    task: Mutex<Option<Task>>,
+    /// the dependencies that aren't done yet
+    waiting_on: AtomicUsize,
+    state: Mutex<NodeState>,
+    finished: Condvar,
+}
+
+impl JobNode {
+    fn is_done(&self) -> bool {
+        lock(&self.state).done
+    }
+}
+
+struct Queue {
+    jobs: VecDeque<Arc<JobNode>>,
+    stopping: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+impl Shared {
+    fn push(&self, node: Arc<JobNode>) {
+        lock(&self.queue).jobs.push_back(node);
+        self.available.notify_one();
+    }
+
+    fn try_pop(&self) -> Option<Arc<JobNode>> {
+        lock(&self.queue).jobs.pop_front()
+    }
+
+    /// run the task and queue the jobs that only waited on it
+    fn run(&self, node: &JobNode) {
+        let task = lock(&node.task).take();
+        if let Some(task) = task {
+            task();
+        }
+
+        let dependents = {
+            let mut state = lock(&node.state);
+            state.done = true;
+            std::mem::take(&mut state.dependents)
+        };
+        node.finished.notify_all();
+
+        for dependent in dependents {
+            if dependent.waiting_on.fetch_sub(1, Ordering::AcqRel) == 1 {
+                self.push(dependent);
+            }
+        }
+    }
+
+    /// block until the job is done, runs queued jobs in the meantime
+    fn wait(&self, node: &JobNode) {
+        loop {
+            if node.is_done() {
+                return;
+            }
+            if let Some(other) = self.try_pop() {
+                self.run(&other);
+                continue;
+            }
+
+            let state = lock(&node.state);
+            if state.done {
+                return;
+            }
+            // a worker has the job, or it waits on a job a worker has
+            drop(
+                node.finished
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner),
+            );
+        }
+    }
+}
+
+/// points to a job so other jobs can depend on it, see ``JobSystem::spawn_after``
+#[derive(Clone)]
+pub struct JobId(Arc<JobNode>);
+
+impl JobId {
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.0.is_done()
+    }
+}
+
+/// the result of a job, see ``JobSystem::spawn``
+/// dropping the handle doesn't cancel the job
+pub struct JobHandle<T> {
+    node: Arc<JobNode>,
+    shared: Arc<Shared>,
+    result: Arc<Mutex<Option<thread::Result<T>>>>,
+}
+
+impl<T> JobHandle<T> {
+    /// for the dependencies of other jobs
+    #[must_use]
+    pub fn id(&self) -> JobId {
+        JobId(self.node.clone())
+    }
+
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.node.is_done()
+    }
+
+    /// block until the job is done and take its result, queued jobs are run in the meantime
+    /// # Panics
+    /// if the job panicked, with the same payload
+    pub fn wait(self) -> T {
+        self.shared.wait(&self.node);
+        match lock(&self.result).take() {
+            Some(Ok(value)) => value,
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => unreachable!("the result is set before the job is done"),
+        }
+    }
+
+    /// the result if the job is done, the handle is given back otherwise
+    /// # Errors
+    /// if the job isn't done yet
+    /// # Panics
+    /// if the job panicked, with the same payload
+    pub fn try_wait(self) -> Result<T, Self> {
+        if self.is_done() {
+            Ok(self.wait())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+pub struct JobSystem {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Default for JobSystem {
+    /// one worker less than there are cores, the main thread helps while it waits
+    fn default() -> Self {
+        Self::new(Self::default_workers())
+    }
+}
+
+impl JobSystem {
+    /// start the worker threads, at least one is started
+    /// # Panics
+    /// if a thread can't be spawned
+    #[must_use]
+    pub fn new(workers: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                jobs: VecDeque::new(),
+                stopping: false,
+            }),
+            available: Condvar::new(),
+        });
+
+        let workers = (0..workers.max(1))
+            .map(|i| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("job worker {i}"))
+                    .spawn(move || worker(&shared))
+                    .expect("failed to spawn a job worker")
+            })
+            .collect();
+
+        Self { shared, workers }
+    }
+
+    /// one less than the cores of the cpu, at least one
+    #[must_use]
+    pub fn default_workers() -> usize {
+        thread::available_parallelism().map_or(1, |cores| cores.get().saturating_sub(1).max(1))
+    }
+
+    #[must_use]
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// run the function on a worker
+    pub fn spawn<T, F>(&self, f: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.spawn_after(&[], f)
+    }
+
+    /// run the function on a worker once all dependencies are done
+    pub fn spawn_after<T, F>(&self, dependencies: &[JobId], f: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let result = Arc::new(Mutex::new(None));
+        let slot = result.clone();
+        let task: Task = Box::new(move || {
+            let value = panic::catch_unwind(AssertUnwindSafe(f));
+            *lock(&slot) = Some(value);
+        });
+
+        let node = self.submit(dependencies, task);
+        JobHandle {
+            node,
+            shared: self.shared.clone(),
+            result,
+        }
+    }
+
+    /// spawn jobs that can borrow from the stack, they are all done when ``scope`` returns
+    /// # Panics
+    /// if one of the jobs panicked, after all of them are done
+    pub fn scope<'env, R>(
+        &self,
+        f: impl for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+    ) -> R {
+        scope::run(self, f)
+    }
+
+    fn submit(&self, dependencies: &[JobId], task: Task) -> Arc<JobNode> {
+        // the extra count keeps the job from being queued while the dependencies are added
+        let node = Arc::new(JobNode {
+            task: Mutex::new(Some(task)),
+            waiting_on: AtomicUsize::new(1),
+            state: Mutex::new(NodeState::default()),
+            finished: Condvar::new(),
+        });
+
+        for JobId(dependency) in dependencies {
+            let mut state = lock(&dependency.state);
+            if !state.done {
+                node.waiting_on.fetch_add(1, Ordering::AcqRel);
+                state.dependents.push(node.clone());
+            }
+        }
+
+        if node.waiting_on.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.push(node.clone());
+        }
+        node
+    }
+
+    fn wait(&self, id: &JobId) {
+        self.shared.wait(&id.0);
+    }
+}
+
+impl Drop for JobSystem {
+    /// the queued jobs are finished first
+    fn drop(&mut self) {
+        lock(&self.shared.queue).stopping = true;
+        self.shared.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker(shared: &Shared) {
+    loop {
+        let node = {
+            let mut queue = lock(&shared.queue);
+            loop {
+                if let Some(node) = queue.jobs.pop_front() {
+                    break node;
+                }
+                if queue.stopping {
+                    return;
+                }
+                queue = shared
+                    .available
+                    .wait(queue)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        };
+        shared.run(&node);
+    }
+}
+
+/// the message of a panic payload, for reporting panics of jobs
+#[must_use]
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn jobs_return_their_result() {
+        let jobs = JobSystem::new(2);
+        let handles: Vec<_> = (0..16).map(|i| jobs.spawn(move || i * 2)).collect();
+        let results: Vec<i32> = handles.into_iter().map(JobHandle::wait).collect();
+        assert_eq!(results, (0..16).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn dependencies_run_first() {
+        let jobs = JobSystem::new(4);
+        let log = Arc::new(Mutex::new(vec![]));
+
+        let push = |value| {
+            let log = log.clone();
+            move || lock(&log).push(value)
+        };
+
+        let slow = jobs.spawn({
+            let push = push(0);
+            move || {
+                thread::sleep(std::time::Duration::from_millis(20));
+                push();
+            }
+        });
+        let middle = jobs.spawn_after(&[slow.id()], push(1));
+        let last = jobs.spawn_after(&[middle.id(), slow.id()], push(2));
+
+        last.wait();
+        assert!(middle.is_done());
+        assert_eq!(*lock(&log), vec![0, 1, 2]);
+
+        // a dependency that is already done doesn't hold the job back
+        jobs.spawn_after(&[slow.id()], push(3)).wait();
+        assert_eq!(lock(&log).len(), 4);
+    }
+
+    #[test]
+    fn waiting_runs_queued_jobs() {
+        // the only worker is blocked until a job that is queued after it ran
+        let jobs = JobSystem::new(1);
+        let released = Arc::new(AtomicBool::new(false));
+
+        let blocker = jobs.spawn({
+            let released = released.clone();
+            move || {
+                while !released.load(Ordering::Acquire) {
+                    thread::yield_now();
+                }
+            }
+        });
+        // give the worker time to pick up the blocker
+        thread::sleep(std::time::Duration::from_millis(20));
+
+        let release = jobs.spawn(move || released.store(true, Ordering::Release));
+        release.wait();
+        blocker.wait();
+    }
+
+    #[test]
+    fn panics_are_passed_to_the_waiter() {
+        let jobs = JobSystem::new(1);
+        let handle = jobs.spawn(|| panic!("job failed"));
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| handle.wait())).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "job failed");
+
+        // the worker survived
+        assert_eq!(jobs.spawn(|| 1).wait(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+// jobs that can borrow from the stack of the caller, like ``std::thread::scope``
+// used for work that is split up and joined within a frame, for example meshing all chunks that changed
+
+use std::{
+    any::Any,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+
+use super::{lock, JobId, JobSystem, Task};
+
+type Panic = Box<dyn Any + Send>;
+
+/// see ``JobSystem::scope``
+pub struct Scope<'scope, 'env: 'scope> {
+    jobs: &'scope JobSystem,
+    spawned: Mutex<Vec<JobId>>,
+    /// the first panic of a job, passed on once all jobs are done
+    panic: Arc<Mutex<Option<Panic>>>,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope> Scope<'scope, '_> {
+    /// run the function on a worker, it can borrow anything that outlives the scope
+    pub fn spawn<F>(&'scope self, f: F) -> JobId
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        self.spawn_after(&[], f)
+    }
+
+    /// run the function on a worker once all dependencies are done
+    pub fn spawn_after<F>(&'scope self, dependencies: &[JobId], f: F) -> JobId
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        let panic = self.panic.clone();
+        let task: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+                lock(&panic).get_or_insert(payload);
+            }
+        });
+
+        // the scope waits for every job before the borrows end, even if it unwinds
+        let task = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Task>(task) };
+
+        let id = JobId(self.jobs.submit(dependencies, task));
+        lock(&self.spawned).push(id.clone());
+        id
+    }
+
+    /// jobs can spawn more jobs, so this runs until nothing new was spawned
+    fn wait_all(&self) {
+        loop {
+            let spawned = std::mem::take(&mut *lock(&self.spawned));
+            if spawned.is_empty() {
+                return;
+            }
+            for id in &spawned {
+                self.jobs.wait(id);
+            }
+        }
+    }
+}
+
+pub(super) fn run<'env, R>(
+    jobs: &JobSystem,
+    f: impl for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+) -> R {
+    struct WaitAll<'a, 'scope, 'env>(&'a Scope<'scope, 'env>);
+
+    impl Drop for WaitAll<'_, '_, '_> {
+        fn drop(&mut self) {
+            self.0.wait_all();
+        }
+    }
+
+    let scope = Scope {
+        jobs,
+        spawned: Mutex::new(vec![]),
+        panic: Arc::new(Mutex::new(None)),
+        scope: PhantomData,
+        env: PhantomData,
+    };
+
+    let result = {
+        let _wait = WaitAll(&scope);
+        f(&scope)
+    };
+
+    if let Some(payload) = lock(&scope.panic).take() {
+        panic::resume_unwind(payload);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn scoped_jobs_borrow_and_finish() {
+        let jobs = JobSystem::new(3);
+        let mut values = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let sum = AtomicUsize::new(0);
+
+        jobs.scope(|scope| {
+            for chunk in values.chunks_mut(3) {
+                let sum = &sum;
+                let doubled = scope.spawn(move || {
+                    for value in chunk.iter_mut() {
+                        *value *= 2;
+                    }
+                });
+                // jobs spawned by jobs are waited on as well
+                scope.spawn_after(&[doubled], move || {
+                    scope.spawn(move || {
+                        sum.fetch_add(1, Ordering::Relaxed);
+                    });
+                });
+            }
+        });
+
+        assert_eq!(values, vec![2, 4, 6, 8, 10, 12, 14, 16]);
+        assert_eq!(sum.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn panics_are_passed_on_after_all_jobs() {
+        let jobs = JobSystem::new(2);
+        let finished = AtomicUsize::new(0);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            jobs.scope(|scope| {
+                scope.spawn(|| panic!("scoped job failed"));
+                for _ in 0..4 {
+                    scope.spawn(|| {
+                        finished.fetch_add(1, Ordering::Relaxed);
+                    });
+                }
+            });
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(finished.load(Ordering::Relaxed), 4);
+    }
+}
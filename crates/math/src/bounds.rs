@@ -0,0 +1,272 @@
+use glam::{Mat3, Mat4, Vec3};
+
+use crate::Transform;
+
+/// an axis aligned box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    #[must_use]
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    #[must_use]
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+
+    /// the smallest box containing all points, ``None`` if there are none
+    #[must_use]
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb: Option<Self>, point| {
+            Some(aabb.map_or(Self::new(point, point), |aabb| {
+                Self::new(aabb.min.min(point), aabb.max.max(point))
+            }))
+        })
+    }
+
+    #[must_use]
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    #[must_use]
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    #[must_use]
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// the 8 corners, bit 0 of the index picks the x of ``max``, bit 1 the y and bit 2 the z
+    #[must_use]
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|i| {
+            Vec3::select(
+                glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                self.max,
+                self.min,
+            )
+        })
+    }
+
+    /// the smallest box containing both boxes
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// the points on the border are inside
+    #[must_use]
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    /// touching boxes intersect
+    #[must_use]
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    /// the smallest axis aligned box around the transformed box, works with any affine matrix
+    /// the box only grows when rotating it, so it should be transformed from the local bounds every time
+    #[must_use]
+    pub fn transform(&self, matrix: Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        let linear = Mat3::from_mat4(matrix);
+        let abs = Mat3::from_cols(
+            linear.x_axis.abs(),
+            linear.y_axis.abs(),
+            linear.z_axis.abs(),
+        );
+        Self::from_center(center, abs * self.half_extents())
+    }
+
+    #[must_use]
+    pub fn transform_by(&self, transform: &Transform) -> Self {
+        self.transform(transform.compute_matrix())
+    }
+
+    /// min then max, little endian
+    #[must_use]
+    pub fn to_le_bytes(&self) -> [u8; 24] {
+        let mut bytes = [0; 24];
+        let values = self.min.to_array().into_iter().chain(self.max.to_array());
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[must_use]
+    pub fn from_le_bytes(bytes: [u8; 24]) -> Self {
+        let values: [f32; 6] = std::array::from_fn(|i| {
+            f32::from_le_bytes([
+                bytes[i * 4],
+                bytes[i * 4 + 1],
+                bytes[i * 4 + 2],
+                bytes[i * 4 + 3],
+            ])
+        });
+        Self::new(
+            Vec3::from_slice(&values[..3]),
+            Vec3::from_slice(&values[3..]),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    #[must_use]
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// a sphere around the center of the bounding box of the points, not the smallest possible one
+    /// ``None`` if there are no points
+    #[must_use]
+    pub fn from_points(points: &[Vec3]) -> Option<Self> {
+        let center = Aabb::from_points(points.iter().copied())?.center();
+        let radius = points
+            .iter()
+            .map(|point| point.distance_squared(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+        Some(Self::new(center, radius))
+    }
+
+    /// the sphere going through the corners of the box
+    #[must_use]
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        Self::new(aabb.center(), aabb.half_extents().length())
+    }
+
+    /// the smallest sphere containing both spheres
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        let offset = other.center - self.center;
+        let distance = offset.length();
+
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+
+        let radius = (distance + self.radius + other.radius) * 0.5;
+        let center = self.center + offset * ((radius - self.radius) / distance);
+        Self::new(center, radius)
+    }
+
+    /// the points on the surface are inside
+    #[must_use]
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        point.distance_squared(self.center) <= self.radius * self.radius
+    }
+
+    #[must_use]
+    pub fn intersects(&self, other: &Self) -> bool {
+        let radius = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= radius * radius
+    }
+
+    /// the radius is scaled by the longest axis, so the sphere still contains everything with a non uniform scale
+    #[must_use]
+    pub fn transform(&self, matrix: Mat4) -> Self {
+        let scale = [matrix.x_axis, matrix.y_axis, matrix.z_axis]
+            .map(|axis| axis.truncate().length_squared())
+            .into_iter()
+            .fold(0.0, f32::max)
+            .sqrt();
+        Self::new(matrix.transform_point3(self.center), self.radius * scale)
+    }
+
+    #[must_use]
+    pub fn transform_by(&self, transform: &Transform) -> Self {
+        self.transform(transform.compute_matrix())
+    }
+
+    /// the center then the radius, little endian
+    #[must_use]
+    pub fn to_le_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        let values = self.center.extend(self.radius).to_array();
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[must_use]
+    pub fn from_le_bytes(bytes: [u8; 16]) -> Self {
+        let [x, y, z, radius]: [f32; 4] = std::array::from_fn(|i| {
+            f32::from_le_bytes([
+                bytes[i * 4],
+                bytes[i * 4 + 1],
+                bytes[i * 4 + 2],
+                bytes[i * 4 + 3],
+            ])
+        });
+        Self::new(Vec3::new(x, y, z), radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+
+    #[test]
+    fn boxes() {
+        let aabb = Aabb::from_points([Vec3::ZERO, Vec3::new(2.0, -1.0, 1.0), Vec3::Y]).unwrap();
+        assert_eq!(
+            aabb,
+            Aabb::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(2.0, 1.0, 1.0))
+        );
+        assert_eq!(Aabb::from_points([]), None);
+        assert_eq!(Aabb::from_le_bytes(aabb.to_le_bytes()), aabb);
+
+        // rotated by 90 degrees around z and scaled non uniformly, the box still fits exactly
+        let transform = Transform::from_xyz(1.0, 0.0, 0.0)
+            .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
+            .with_scale(Vec3::new(2.0, 1.0, 1.0));
+        let moved = aabb.transform_by(&transform);
+        let expected = Aabb::from_points(
+            aabb.corners()
+                .map(|corner| transform.compute_matrix().transform_point3(corner)),
+        )
+        .unwrap();
+        assert!(moved.min.abs_diff_eq(expected.min, 1e-5));
+        assert!(moved.max.abs_diff_eq(expected.max, 1e-5));
+    }
+
+    #[test]
+    fn spheres() {
+        let points = [Vec3::new(-1.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0)];
+        let sphere = BoundingSphere::from_points(&points).unwrap();
+        assert_eq!(sphere, BoundingSphere::new(Vec3::X, 2.0));
+        assert_eq!(BoundingSphere::from_le_bytes(sphere.to_le_bytes()), sphere);
+
+        let other = BoundingSphere::new(Vec3::new(6.0, 0.0, 0.0), 1.0);
+        let union = sphere.union(&other);
+        assert_eq!(union, BoundingSphere::new(Vec3::new(3.0, 0.0, 0.0), 4.0));
+        assert_eq!(sphere.union(&BoundingSphere::new(Vec3::X, 0.5)), sphere);
+
+        let scaled = sphere.transform(Mat4::from_scale(Vec3::new(1.0, 3.0, 1.0)));
+        assert_eq!(scaled.radius, 6.0);
+    }
+}
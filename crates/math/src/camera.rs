@@ -0,0 +1,260 @@
+use glam::{Mat4, Vec2, Vec3, Vec4Swizzles};
+
+use crate::{Ray, Transform};
+
+/// how the view space is projected on to the screen
+/// all projections produce vulkan clip space, so depth goes from 0 to 1 and y points down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// the vertical field of view in degrees
+        fovy: f32,
+        znear: f32,
+        /// ignored if ``infinite_reverse_z`` is set
+        zfar: f32,
+        /// there is no far plane and the depth goes from 1 at the near plane to 0 at infinity,
+        /// gives a lot more depth precision for far away things, the depth test has to use ``GREATER``
+        infinite_reverse_z: bool,
+    },
+    Orthographic {
+        /// the height of the view in world units, the width follows from the aspect ratio
+        height: f32,
+        znear: f32,
+        zfar: f32,
+    },
+    /// used as is, the aspect ratio is ignored
+    Custom(Mat4),
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::Perspective {
+            fovy: 70.0,
+            znear: 0.01,
+            zfar: 100.0,
+            infinite_reverse_z: false,
+        }
+    }
+}
+
+impl Projection {
+    #[must_use]
+    pub fn matrix(&self, aspect: f32) -> Mat4 {
+        let proj = match *self {
+            Self::Perspective {
+                fovy,
+                znear,
+                infinite_reverse_z: true,
+                ..
+            } => Mat4::perspective_infinite_reverse_rh(fovy.to_radians(), aspect, znear),
+            Self::Perspective {
+                fovy, znear, zfar, ..
+            } => Mat4::perspective_rh(fovy.to_radians(), aspect, znear, zfar),
+            Self::Orthographic {
+                height,
+                znear,
+                zfar,
+            } => {
+                let (x, y) = (height * aspect / 2.0, height / 2.0);
+                Mat4::orthographic_rh(-x, x, -y, y, znear, zfar)
+            }
+            Self::Custom(proj) => return proj,
+        };
+
+        // vulkan's y axis points down
+        Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0)) * proj
+    }
+
+    /// the depth of the near plane in normalized device coordinates
+    #[must_use]
+    pub fn near_depth(&self) -> f32 {
+        match self {
+            Self::Perspective {
+                infinite_reverse_z: true,
+                ..
+            } => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub transform: Transform,
+    /// width / height of the viewport
+    pub aspect: f32,
+    pub projection: Projection,
+    /// moves the projection by a fraction of a pixel, in normalized device coordinates
+    /// used for temporal anti aliasing, it has to be set to a new offset every frame
+    pub jitter: Vec2,
+}
+
+impl Camera {
+    #[must_use]
+    pub fn new(transform: Transform, aspect: f32, projection: Projection) -> Self {
+        Self {
+            transform,
+            aspect,
+            projection,
+            jitter: Vec2::ZERO,
+        }
+    }
+
+    #[must_use]
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_to_rh(
+            self.transform.translation,
+            self.transform.forward(),
+            self.transform.up(),
+        )
+    }
+
+    #[must_use]
+    pub fn proj(&self) -> Mat4 {
+        self.projection.matrix(self.aspect)
+    }
+
+    /// the view projection matrix, moved by ``jitter``
+    #[must_use]
+    pub fn build_proj(&self) -> Mat4 {
+        Mat4::from_translation(self.jitter.extend(0.0)) * self.build_unjittered_proj()
+    }
+
+    /// the view projection matrix without ``jitter``, used to compute how far things moved on the screen
+    #[must_use]
+    pub fn build_unjittered_proj(&self) -> Mat4 {
+        self.proj() * self.view()
+    }
+
+    /// the ray going from the camera through a point on the screen
+    /// ``screen`` is in pixels from the top left corner of a viewport with the size ``size``
+    /// the origin is on the near plane and the direction is normalized
+    #[must_use]
+    pub fn screen_to_ray(&self, screen: Vec2, size: Vec2) -> Ray {
+        let ndc = screen / size * 2.0 - 1.0;
+        let inverse = self.build_unjittered_proj().inverse();
+
+        let near = inverse.project_point3(ndc.extend(self.projection.near_depth()));
+        // halfway in to the depth range, still finite for an infinite far plane
+        let further = inverse.project_point3(ndc.extend(0.5));
+
+        Ray::new(near, (further - near).normalize())
+    }
+
+    /// where a point in world space ends up on the screen, in pixels from the top left corner
+    /// ``None`` if the point is behind the camera
+    #[must_use]
+    pub fn world_to_screen(&self, pos: Vec3, size: Vec2) -> Option<Vec2> {
+        let clip = self.build_unjittered_proj() * pos.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        let ndc = clip.xy() / clip.w;
+        Some((ndc + 1.0) / 2.0 * size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Vec2 = Vec2::new(1600.0, 900.0);
+
+    fn camera(projection: Projection) -> Camera {
+        let transform =
+            Transform::from_xyz(1.0, 2.0, 3.0).looking_at(Vec3::new(0.0, 0.0, -5.0), Vec3::Y);
+        Camera::new(transform, SIZE.x / SIZE.y, projection)
+    }
+
+    fn projections() -> [Projection; 3] {
+        let perspective = |infinite_reverse_z| Projection::Perspective {
+            fovy: 70.0,
+            znear: 0.1,
+            zfar: 100.0,
+            infinite_reverse_z,
+        };
+        let orthographic = Projection::Orthographic {
+            height: 10.0,
+            znear: 0.1,
+            zfar: 100.0,
+        };
+        [perspective(false), perspective(true), orthographic]
+    }
+
+    #[test]
+    fn screen_round_trip() {
+        for projection in projections() {
+            let camera = camera(projection);
+
+            for point in [
+                Vec3::new(0.0, 0.0, -5.0),
+                Vec3::new(1.5, -0.5, -4.0),
+                Vec3::new(-2.0, 3.0, -20.0),
+            ] {
+                let screen = camera.world_to_screen(point, SIZE).unwrap();
+                let ray = camera.screen_to_ray(screen, SIZE);
+
+                assert!((ray.dir.length() - 1.0).abs() < 1e-5);
+                // the origin is on the near plane
+                let near =
+                    (ray.origin - camera.transform.translation).dot(camera.transform.forward());
+                assert!((near - 0.1).abs() < 1e-4, "{projection:?}: {near}");
+
+                let distance = (point - ray.origin).dot(ray.dir);
+                assert!(distance > 0.0);
+                let miss = ray.at(distance).distance(point);
+                assert!(miss < 1e-3, "{projection:?} misses {point} by {miss}");
+            }
+        }
+    }
+
+    #[test]
+    fn center_of_the_screen_looks_forward() {
+        for projection in projections() {
+            let camera = camera(projection);
+            let ray = camera.screen_to_ray(SIZE / 2.0, SIZE);
+            assert!(
+                ray.dir.distance(camera.transform.forward()) < 1e-4,
+                "{projection:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn depth_range() {
+        let depth = |camera: &Camera, distance: f32| {
+            let point = camera.transform.translation + camera.transform.forward() * distance;
+            camera.build_unjittered_proj().project_point3(point).z
+        };
+
+        for projection in projections() {
+            let camera = camera(projection);
+            let near = depth(&camera, 0.1);
+            assert!(
+                (near - projection.near_depth()).abs() < 1e-4,
+                "{projection:?}: {near}"
+            );
+
+            let far = depth(&camera, 100.0);
+            if let Projection::Perspective {
+                infinite_reverse_z: true,
+                ..
+            } = projection
+            {
+                // there is no far plane, the depth only gets closer to 0
+                assert!(far > 0.0 && far < 0.01, "{far}");
+                assert!(depth(&camera, 1e6) < far);
+            } else {
+                assert!((far - 1.0).abs() < 1e-4, "{projection:?}: {far}");
+            }
+        }
+    }
+
+    #[test]
+    fn behind_the_camera() {
+        let camera = camera(Projection::default());
+        let behind = camera.transform.translation + camera.transform.back();
+        assert_eq!(camera.world_to_screen(behind, SIZE), None);
+    }
+}
@@ -0,0 +1,243 @@
+use glam::Vec4;
+
+/// a linear color with straight alpha, the layout of a ``[f32; 4]``
+/// colors picked in image editors or color pickers are srgb, they have to be created with ``Color::srgb``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Self = Self::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::rgb(1.0, 1.0, 1.0);
+    pub const TRANSPARENT: Self = Self::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const RED: Self = Self::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Self = Self::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Self = Self::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Self = Self::rgb(1.0, 1.0, 0.0);
+    pub const CYAN: Self = Self::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Self = Self::rgb(1.0, 0.0, 1.0);
+    /// looks half as bright as white, 128 in srgb bytes
+    pub const GRAY: Self = Self::rgb(0.215_86, 0.215_86, 0.215_86);
+
+    /// linear, opaque
+    #[must_use]
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    /// linear
+    #[must_use]
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// from srgb values from 0 to 1, opaque
+    #[must_use]
+    pub fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::srgba(r, g, b, 1.0)
+    }
+
+    /// from srgb values from 0 to 1, the alpha is always linear
+    #[must_use]
+    pub fn srgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::rgba(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+    }
+
+    /// from linear bytes, the inverse of ``to_rgba8``
+    #[must_use]
+    pub fn from_rgba8(bytes: [u8; 4]) -> Self {
+        Self::from(bytes.map(|v| f32::from(v) / 255.0))
+    }
+
+    /// from srgb bytes, like the colors of textures and image editors
+    #[must_use]
+    pub fn from_srgba8(bytes: [u8; 4]) -> Self {
+        let [r, g, b, a] = bytes.map(|v| f32::from(v) / 255.0);
+        Self::srgba(r, g, b, a)
+    }
+
+    /// from a hex code like ``0xff8000``, in srgb and opaque
+    #[must_use]
+    pub fn hex(rgb: u32) -> Self {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        Self::from_srgba8([r, g, b, 255])
+    }
+
+    /// ``hue`` in degrees, ``saturation`` and ``value`` from 0 to 1
+    /// like in color pickers the values are srgb
+    #[must_use]
+    pub fn hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let min = value - chroma;
+        Self::srgb(r + min, g + min, b + min)
+    }
+
+    /// ``[hue, saturation, value]``, see ``Color::hsv``
+    #[must_use]
+    pub fn to_hsv(&self) -> [f32; 3] {
+        let [r, g, b] = self.to_srgb();
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / chroma + 2.0
+        } else {
+            (r - g) / chroma + 4.0
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+        [hue * 60.0, saturation, max]
+    }
+
+    #[must_use]
+    pub const fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    /// mixes in linear space, ``t`` of 0 is ``self``
+    #[must_use]
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        Self::from(Vec4::from(self).lerp(Vec4::from(other), t))
+    }
+
+    /// the color channels in srgb from 0 to 1
+    #[must_use]
+    pub fn to_srgb(&self) -> [f32; 3] {
+        [self.r, self.g, self.b].map(linear_to_srgb)
+    }
+
+    #[must_use]
+    pub const fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// linear bytes, for ``R8G8B8A8_UNORM``
+    #[must_use]
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        self.to_array().map(|v| unorm(v, 255.0) as u8)
+    }
+
+    /// srgb bytes with linear alpha, for ``R8G8B8A8_SRGB``
+    #[must_use]
+    pub fn to_srgba8(&self) -> [u8; 4] {
+        let [r, g, b] = self.to_srgb();
+        [r, g, b, self.a].map(|v| unorm(v, 255.0) as u8)
+    }
+
+    /// ``to_rgba8`` as one u32, red in the lowest byte
+    #[must_use]
+    pub fn pack_rgba8(&self) -> u32 {
+        u32::from_le_bytes(self.to_rgba8())
+    }
+
+    /// linear, for ``A2B10G10R10_UNORM_PACK32``, red in the lowest bits
+    #[must_use]
+    pub fn pack_rgb10a2(&self) -> u32 {
+        let [r, g, b] = [self.r, self.g, self.b].map(|v| unorm(v, 1023.0));
+        r | g << 10 | b << 20 | unorm(self.a, 3.0) << 30
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+impl From<Vec4> for Color {
+    fn from(v: Vec4) -> Self {
+        Self::from(v.to_array())
+    }
+}
+
+impl From<Color> for Vec4 {
+    fn from(color: Color) -> Self {
+        Self::from_array(color.to_array())
+    }
+}
+
+/// a value from 0 to 1 scaled to ``max`` and rounded
+fn unorm(value: f32, max: f32) -> u32 {
+    (value.clamp(0.0, 1.0) * max).round() as u32
+}
+
+/// convert one srgb channel from 0 to 1 to linear
+#[must_use]
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// convert one linear channel from 0 to 1 to srgb
+#[must_use]
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Color, b: Color) -> bool {
+        Vec4::from(a).abs_diff_eq(Vec4::from(b), 1e-5)
+    }
+
+    #[test]
+    fn conversions() {
+        let orange = Color::hex(0xff_80_00);
+        assert_eq!(orange.to_srgba8(), [255, 128, 0, 255]);
+        assert!((orange.g - 0.216).abs() < 0.001);
+        assert_eq!(Color::from_srgba8([128; 4]).to_srgba8(), [128; 4]);
+        assert_eq!(Color::GRAY.to_srgba8(), [128, 128, 128, 255]);
+
+        let [hue, saturation, value] = orange.to_hsv();
+        assert!((hue - 30.0).abs() < 0.5);
+        assert!((saturation - 1.0).abs() < 1e-5 && (value - 1.0).abs() < 1e-5);
+        assert!(close(Color::hsv(240.0, 1.0, 1.0), Color::BLUE));
+        assert!(close(Color::hsv(-120.0, 1.0, 1.0), Color::BLUE));
+        assert!(close(Color::hsv(0.0, 0.0, 0.0), Color::BLACK));
+
+        assert_eq!(Color::from_rgba8([1, 2, 3, 4]).to_rgba8(), [1, 2, 3, 4]);
+        assert_eq!(Color::RED.pack_rgba8(), 0xff_00_00_ff);
+        assert_eq!(Color::RED.with_alpha(0.0).pack_rgb10a2(), 0x3ff);
+        assert_eq!(Color::BLUE.pack_rgb10a2(), 0x3ff << 20 | 3 << 30);
+    }
+}
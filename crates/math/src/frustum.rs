@@ -0,0 +1,108 @@
+use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
+
+use crate::{Aabb, Camera};
+
+/// the volume a view projection matrix can see, as 6 planes facing inwards
+/// the planes are ``xyz`` for the normal and ``w`` for the distance, a point is inside if ``normal.dot(p) + w >= 0``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// left, right, top, bottom, depth 0 and depth 1
+    /// with reverse z depth 0 is the far plane, for an infinite one it doesn't cull anything
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// extract the planes from a matrix producing vulkan clip space, where the depth goes from 0 to 1 and y points down
+    #[must_use]
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+                let length = plane.xyz().length();
+                if length > 0.0 {
+                    plane / length
+                } else {
+                    plane
+                }
+            }),
+        }
+    }
+
+    /// the signed distance of the point to each plane, negative outside
+    fn distances(&self, point: Vec3) -> impl Iterator<Item = f32> + '_ {
+        self.planes
+            .iter()
+            .map(move |plane| plane.xyz().dot(point) + plane.w)
+    }
+
+    #[must_use]
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.distances(point).all(|distance| distance >= 0.0)
+    }
+
+    /// can return true for spheres close to the corners of the frustum that are outside
+    #[must_use]
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.distances(center).all(|distance| distance >= -radius)
+    }
+
+    /// can return true for big boxes close to the corners of the frustum that are outside
+    #[must_use]
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner that is furthest in the direction of the normal
+            let corner = Vec3::select(plane.xyz().cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            plane.xyz().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+impl Camera {
+    /// the frustum of ``build_unjittered_proj``
+    #[must_use]
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(self.build_unjittered_proj())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Projection, Transform};
+
+    #[test]
+    fn camera_frustum() {
+        // looks along -z, with a near plane at 0.01 and a far plane at 100
+        let camera = Camera::new(Transform::IDENTITY, 1.0, Projection::default());
+        let frustum = camera.frustum();
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -5.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 5.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -500.0)));
+        assert!(!frustum.contains_point(Vec3::new(50.0, 0.0, -5.0)));
+
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, 1.0), 2.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 5.0), 2.0));
+
+        assert!(frustum.intersects_aabb(&Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0))));
+        assert!(!frustum.intersects_aabb(&Aabb::new(
+            Vec3::new(20.0, -1.0, -6.0),
+            Vec3::new(22.0, 1.0, -4.0)
+        )));
+
+        let infinite = Camera::new(
+            Transform::IDENTITY,
+            1.0,
+            Projection::Perspective {
+                fovy: 70.0,
+                znear: 0.01,
+                zfar: 100.0,
+                infinite_reverse_z: true,
+            },
+        );
+        let frustum = infinite.frustum();
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -5000.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.005)));
+    }
+}
@@ -0,0 +1,138 @@
+use std::ops::Mul;
+
+use glam::{Affine3A, Mat4, Quat, Vec3};
+
+use crate::Transform;
+
+// credits : bevyengine
+
+/// The position of an entity relative to the reference frame.
+///
+/// It's computed from the [`Transform`] of the entity and the [`GlobalTransform`] of its parent,
+/// so it should only be read, changes to it are overwritten by the next propagation.
+///
+/// Unlike [`Transform`] it's stored as an affine matrix, so shear from a non-uniform scale
+/// of a parent combined with the rotation of a child is kept.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GlobalTransform(Affine3A);
+
+impl GlobalTransform {
+    /// An identity [`GlobalTransform`] that maps all points in space to themselves.
+    pub const IDENTITY: Self = Self(Affine3A::IDENTITY);
+
+    #[inline]
+    pub fn from_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::from_translation(Vec3::new(x, y, z))
+    }
+
+    #[inline]
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self(Affine3A::from_translation(translation))
+    }
+
+    /// Returns the 3d affine transformation matrix as a [`Mat4`].
+    #[inline]
+    #[must_use]
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from(self.0)
+    }
+
+    /// Returns the 3d affine transformation matrix as an [`Affine3A`].
+    #[inline]
+    #[must_use]
+    pub fn affine(&self) -> Affine3A {
+        self.0
+    }
+
+    /// Returns the transformation as a [`Transform`].
+    ///
+    /// The transform is expected to be non-degenerate and without shearing, or the output
+    /// will be invalid.
+    #[inline]
+    #[must_use]
+    pub fn compute_transform(&self) -> Transform {
+        let (scale, rotation, translation) = self.0.to_scale_rotation_translation();
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Get the translation as a [`Vec3`].
+    #[inline]
+    #[must_use]
+    pub fn translation(&self) -> Vec3 {
+        self.0.translation.into()
+    }
+
+    /// Get the rotation as a [`Quat`].
+    ///
+    /// The transform is expected to be non-degenerate and without shearing, or the output
+    /// will be invalid.
+    #[inline]
+    #[must_use]
+    pub fn rotation(&self) -> Quat {
+        self.compute_transform().rotation
+    }
+
+    /// Transforms the given point from local space to global space, applying shear, scale,
+    /// rotation and translation.
+    #[inline]
+    #[must_use]
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.0.transform_point3(point)
+    }
+
+    /// Multiplies `self` with `transform` component by component, returning the
+    /// resulting [`GlobalTransform`]
+    ///
+    /// This is how the [`GlobalTransform`] of a child is computed from its parent.
+    #[inline]
+    #[must_use]
+    pub fn mul_transform(&self, transform: Transform) -> Self {
+        Self(self.0 * transform.compute_affine())
+    }
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<Transform> for GlobalTransform {
+    fn from(transform: Transform) -> Self {
+        Self(transform.compute_affine())
+    }
+}
+
+impl From<Affine3A> for GlobalTransform {
+    fn from(affine: Affine3A) -> Self {
+        Self(affine)
+    }
+}
+
+impl From<Mat4> for GlobalTransform {
+    fn from(matrix: Mat4) -> Self {
+        Self(Affine3A::from_mat4(matrix))
+    }
+}
+
+impl Mul<Transform> for GlobalTransform {
+    type Output = GlobalTransform;
+
+    #[inline]
+    fn mul(self, transform: Transform) -> Self::Output {
+        self.mul_transform(transform)
+    }
+}
+
+impl Mul<GlobalTransform> for GlobalTransform {
+    type Output = GlobalTransform;
+
+    #[inline]
+    fn mul(self, global_transform: GlobalTransform) -> Self::Output {
+        Self(self.0 * global_transform.0)
+    }
+}
@@ -1,3 +1,15 @@
+mod bounds;
+mod camera;
+mod color;
+mod frustum;
+mod global_transform;
+mod ray;
 mod transform;
-pub use transform::Transform;
+pub use bounds::{Aabb, BoundingSphere};
+pub use camera::{Camera, Projection};
+pub use color::{linear_to_srgb, srgb_to_linear, Color};
+pub use frustum::Frustum;
 pub use glam::*;
+pub use global_transform::GlobalTransform;
+pub use ray::Ray;
+pub use transform::Transform;
@@ -0,0 +1,153 @@
+use glam::Vec3;
+
+use crate::Aabb;
+
+/// a half line, the distances along it are in units of ``dir``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub dir: Vec3,
+}
+
+impl Ray {
+    #[must_use]
+    pub fn new(origin: Vec3, dir: Vec3) -> Self {
+        Self { origin, dir }
+    }
+
+    /// the point at the distance
+    #[must_use]
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.dir * distance
+    }
+
+    /// the distance where the ray hits the plane, both sides of the plane count
+    /// ``None`` if the ray is parallel to the plane or points away from it
+    #[must_use]
+    pub fn intersect_plane(&self, point: Vec3, normal: Vec3) -> Option<f32> {
+        let facing = self.dir.dot(normal);
+        if facing.abs() < 1e-6 {
+            return None;
+        }
+
+        let distance = (point - self.origin).dot(normal) / facing;
+        (distance >= 0.0).then_some(distance)
+    }
+
+    /// the distances where the ray enters and leaves the box
+    /// the enter distance is negative if the origin is inside of the box
+    #[must_use]
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<(f32, f32)> {
+        // a direction of 0 gives infinite distances, which the min and max handle
+        let inv_dir = self.dir.recip();
+        let t0 = (aabb.min - self.origin) * inv_dir;
+        let t1 = (aabb.max - self.origin) * inv_dir;
+
+        let enter = t0.min(t1).max_element();
+        let exit = t0.max(t1).min_element();
+
+        (enter <= exit && exit >= 0.0).then_some((enter, exit))
+    }
+
+    /// the distance to the first point on the sphere in front of the origin, 0 if the origin is inside
+    #[must_use]
+    pub fn intersect_sphere(&self, center: Vec3, radius: f32) -> Option<f32> {
+        let offset = self.origin - center;
+        let a = self.dir.length_squared();
+        let b = offset.dot(self.dir);
+        let c = offset.length_squared() - radius * radius;
+
+        if c <= 0.0 {
+            return Some(0.0);
+        }
+
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 || a == 0.0 {
+            return None;
+        }
+
+        let distance = (-b - discriminant.sqrt()) / a;
+        (distance >= 0.0).then_some(distance)
+    }
+
+    /// the distance to the triangle, both sides of it count
+    #[must_use]
+    pub fn intersect_triangle(&self, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+        // Möller–Trumbore
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.dir.cross(edge2);
+        let det = edge1.dot(p);
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let offset = self.origin - a;
+        let u = offset.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = offset.cross(edge1);
+        let v = self.dir.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inv_det;
+        (distance >= 0.0).then_some(distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+
+        assert_eq!(ray.intersect_plane(Vec3::ZERO, Vec3::Z), Some(5.0));
+        assert_eq!(ray.intersect_plane(Vec3::ZERO, Vec3::X), None);
+
+        assert_eq!(
+            ray.intersect_aabb(&Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0))),
+            Some((4.0, 6.0))
+        );
+        // parallel to the x axis and outside of the slab
+        assert_eq!(
+            ray.intersect_aabb(&Aabb::new(
+                Vec3::new(1.0, -1.0, -1.0),
+                Vec3::new(2.0, 1.0, 1.0)
+            )),
+            None
+        );
+        // behind the origin
+        assert_eq!(
+            ray.intersect_aabb(&Aabb::new(
+                Vec3::new(-1.0, -1.0, 6.0),
+                Vec3::new(1.0, 1.0, 7.0)
+            )),
+            None
+        );
+
+        assert_eq!(ray.intersect_sphere(Vec3::ZERO, 1.0), Some(4.0));
+        assert_eq!(
+            ray.intersect_sphere(Vec3::new(0.0, 0.0, 5.0), 1.0),
+            Some(0.0)
+        );
+        assert_eq!(ray.intersect_sphere(Vec3::new(3.0, 0.0, 0.0), 1.0), None);
+
+        let triangle = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        assert_eq!(ray.intersect_triangle(triangle), Some(5.0));
+        let beside = Ray::new(Vec3::new(2.0, 0.0, 5.0), Vec3::NEG_Z);
+        assert_eq!(beside.intersect_triangle(triangle), None);
+        let away = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::Z);
+        assert_eq!(away.intersect_triangle(triangle), None);
+    }
+}
@@ -382,8 +382,16 @@ impl Transform {
     /// * if `direction` is parallel with `up`, an orthogonal vector is used as the "right" direction
     #[inline]
     pub fn look_to(&mut self, direction: impl TryInto<Vec3>, up: impl TryInto<Vec3>) {
-        let back = -direction.try_into().unwrap_or(Vec3::NEG_Z);
-        let up = up.try_into().unwrap_or(Vec3::Y);
+        let back = -direction
+            .try_into()
+            .ok()
+            .and_then(Vec3::try_normalize)
+            .unwrap_or(Vec3::NEG_Z);
+        let up = up
+            .try_into()
+            .ok()
+            .and_then(Vec3::try_normalize)
+            .unwrap_or(Vec3::Y);
         let right = up
             .cross(back.into())
             .try_normalize()
@@ -496,4 +504,3 @@ impl Transform {
         self.translation.is_finite() && self.rotation.is_finite() && self.scale.is_finite()
     }
 }
-
@@ -1,4 +1,4 @@
-use math::{Mat4, Transform, Vec3};
+use math::{Camera, Mat4, Projection, Transform, Vec3};
 use std::{sync::Arc, time::Instant};
 
 use ash::vk;
@@ -15,31 +15,6 @@ pub struct DefaultMaterial {
     shaders: [vk::ShaderEXT; 2],
 }
 
-pub struct Camera {
-    transform: Transform,
-    aspect: f32,
-    fovy: f32,
-    znear: f32,
-    zfar: f32,
-}
-
-impl Camera {
-    #[must_use]
-    pub fn build_proj(&self) -> Mat4 {
-        let view = Mat4::look_at_rh(
-            self.transform.translation,
-            self.transform.forward(),
-            self.transform.down(),
-        );
-
-        let mut proj =
-            Mat4::perspective_rh_gl(self.fovy.to_radians(), self.aspect, self.znear, self.zfar);
-
-        proj.x_axis.x *= -1.0;
-        proj * view
-    }
-}
-
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct UniformData {
@@ -106,15 +81,17 @@ fn main() {
     )
     .unwrap();
 
-    let uniform_buffer = Buffer::new(
-        handler.device.clone(),
-        std::mem::size_of::<UniformData>() as u64,
-        vk::BufferUsageFlags::UNIFORM_BUFFER,
-        vk::MemoryPropertyFlags::HOST_VISIBLE,
-    )
-    .unwrap();
-
-    let _uniform_handle = handler.set_uniform_buffer(uniform_buffer.clone());
+    let uniform_handle = handler
+        .create_buffer(
+            std::mem::size_of::<UniformData>() as u64,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .unwrap();
+    let uniform_buffer = handler.buffer(uniform_handle).unwrap().clone();
+    handler
+        .bind_uniform_buffer(uniform_handle, Some(0))
+        .unwrap();
 
     let material = Arc::new(DefaultMaterial {
         shaders: [vertex_shader, fragment_shader],
@@ -138,13 +115,16 @@ fn main() {
     window.set_all_polling(true);
     let start = Instant::now();
 
-    let mut camera = Camera {
-        transform: Transform::from_xyz(2.0, 2.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
-        fovy: 70.0,
-        aspect: window_size[0] as f32 / window_size[1] as f32,
-        znear: 0.001,
-        zfar: 100.0,
-    };
+    let mut camera = Camera::new(
+        Transform::from_xyz(2.0, 2.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+        window_size[0] as f32 / window_size[1] as f32,
+        Projection::Perspective {
+            fovy: 70.0,
+            znear: 0.001,
+            zfar: 100.0,
+            infinite_reverse_z: false,
+        },
+    );
 
     vertex_buffer.write(0, &QUAD);
 
@@ -0,0 +1,176 @@
+// many small textures of the same size in the layers of one texture array, like the faces of voxels
+// the whole array only takes a single bindless slot, shaders sample it with ``GetTextureArray``
+// and use the layer as the third texture coordinate
+
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    handler::{sampler::SamplerDesc, RenderHandler},
+    vulkan::Texture,
+};
+
+use super::texture::{decode_image, TextureError, TextureHandle};
+
+pub struct TextureAtlas {
+    texture: Arc<Texture>,
+    handle: TextureHandle,
+    /// the layers that have been written, new tiles go to the next one
+    len: u32,
+}
+
+impl TextureAtlas {
+    /// an atlas of square ``R8G8B8A8_SRGB`` tiles, every tile gets its own mip levels if the gpu can blit the format
+    /// # Errors
+    /// if there is no space to allocate the texture or no free texture slot
+    pub fn new(
+        renderer: &mut RenderHandler,
+        tile_size: u32,
+        capacity: u32,
+        sampler: SamplerDesc,
+    ) -> Result<Self, TextureError> {
+        let device = renderer.device.clone();
+        let format = vk::Format::R8G8B8A8_SRGB;
+
+        let generate = Texture::can_generate_mips(&device, format);
+        let (mip_levels, mut usage) = if generate {
+            (
+                Texture::max_mip_levels([tile_size; 2]),
+                vk::ImageUsageFlags::TRANSFER_SRC,
+            )
+        } else {
+            (1, vk::ImageUsageFlags::empty())
+        };
+        usage |= vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST;
+
+        let texture = Arc::new(Texture::new_array(
+            device,
+            [tile_size; 2],
+            format,
+            mip_levels,
+            capacity,
+            usage,
+        )?);
+
+        let sampler = renderer.get_sampler(sampler)?;
+        let handle = renderer
+            .add_shared_texture(texture.clone(), sampler)
+            .ok_or(TextureError::NoFreeSlot)?;
+
+        Ok(Self {
+            texture,
+            handle,
+            len: 0,
+        })
+    }
+
+    /// upload a tile of ``tile_size * tile_size`` rgba texels to the next free layer and generate its mips
+    /// returns the layer of the tile
+    /// this blocks until the upload is done
+    /// # Panics
+    /// if ``rgba`` doesn't have 4 bytes for every texel of a tile
+    /// # Errors
+    /// if the atlas is full, or there is no space to allocate the staging buffer
+    pub fn insert(&mut self, rgba: &[u8]) -> Result<u32, TextureError> {
+        let tile_size = self.tile_size() as usize;
+        assert_eq!(
+            rgba.len(),
+            tile_size * tile_size * 4,
+            "the tile doesn't have the size of the atlas"
+        );
+
+        if self.len == self.capacity() {
+            return Err(TextureError::AtlasFull);
+        }
+
+        let layer = self.len;
+        self.texture.upload_layer(layer, &[rgba])?;
+        self.len += 1;
+        Ok(layer)
+    }
+
+    /// decode a png or jpeg file and insert it, see ``insert``
+    /// # Errors
+    /// if the file can't be decoded or doesn't have the size of a tile, the atlas is full,
+    /// or there is no space to allocate the staging buffer
+    pub fn insert_encoded(&mut self, data: &[u8]) -> Result<u32, TextureError> {
+        let (extent, rgba) = decode_image(data)?;
+        if extent != [self.tile_size(); 2] {
+            return Err(TextureError::Unsupported(format!(
+                "a {}x{} image in an atlas of {}x{} tiles",
+                extent[0],
+                extent[1],
+                self.tile_size(),
+                self.tile_size(),
+            )));
+        }
+        self.insert(&rgba)
+    }
+
+    /// the handle of the whole array, the same for every tile
+    #[must_use]
+    pub fn handle(&self) -> TextureHandle {
+        self.handle
+    }
+
+    #[must_use]
+    pub fn tile_size(&self) -> u32 {
+        self.texture.extent[0]
+    }
+
+    /// the number of tiles that have been inserted
+    #[must_use]
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the number of tiles that fit in to the atlas
+    #[must_use]
+    pub fn capacity(&self) -> u32 {
+        self.texture.layers
+    }
+}
+
+/// the texture coordinate of a point on a tile, ``uv`` goes from 0 to 1 over a single tile
+/// bigger values repeat the tile if the sampler uses ``REPEAT``, like on merged faces
+#[must_use]
+pub fn tile_uv(layer: u32, uv: [f32; 2]) -> [f32; 3] {
+    [uv[0], uv[1], layer as f32]
+}
+
+/// the texture coordinates of the corners of a face that is ``size`` tiles big, with the tile repeated on it
+/// in the order ``[0, 0], [1, 0], [1, 1], [0, 1]``
+#[must_use]
+pub fn face_uvs(layer: u32, size: [f32; 2]) -> [[f32; 3]; 4] {
+    [
+        [0.0, 0.0],
+        [size[0], 0.0],
+        [size[0], size[1]],
+        [0.0, size[1]],
+    ]
+    .map(|uv| tile_uv(layer, uv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_faces_repeat_the_tile() {
+        assert_eq!(
+            face_uvs(3, [2.0, 1.0]),
+            [
+                [0.0, 0.0, 3.0],
+                [2.0, 0.0, 3.0],
+                [2.0, 1.0, 3.0],
+                [0.0, 1.0, 3.0],
+            ]
+        );
+    }
+}
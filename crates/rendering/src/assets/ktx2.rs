@@ -0,0 +1,167 @@
+// a minimal reader for KTX2 containers
+// only plain 2D textures without supercompression are supported,
+// the data is expected to already be in a format the gpu understands (BCn, ASTC, RGBA8, ...)
+// <https://registry.khronos.org/KTX/specs/2.0/ktxspec.v2.html>
+
+use ash::vk;
+
+use super::texture::TextureError;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// identifier + 9 u32 header fields + 4 u32 and 2 u64 index fields
+const LEVEL_INDEX_OFFSET: usize = 80;
+const LEVEL_INDEX_SIZE: usize = 24;
+
+pub struct Ktx2<'a> {
+    pub format: vk::Format,
+    pub extent: [u32; 2],
+    /// the data of every mip level in the file, starting with the biggest one
+    /// if the file doesn't contain mips, the rest should be generated
+    pub levels: Vec<&'a [u8]>,
+}
+
+/// tells if the data starts with the KTX2 identifier
+#[must_use]
+pub fn is_ktx2(data: &[u8]) -> bool {
+    data.starts_with(&IDENTIFIER)
+}
+
+/// # Errors
+/// if the data isn't a valid KTX2 file or uses features that aren't supported
+pub fn parse(data: &[u8]) -> Result<Ktx2<'_>, TextureError> {
+    if !is_ktx2(data) {
+        return Err(TextureError::Decode("missing KTX2 identifier".into()));
+    }
+
+    let read_u32 = |offset: usize| -> Result<u32, TextureError> {
+        data.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| TextureError::Decode("KTX2 file is truncated".into()))
+    };
+    let read_u64 = |offset: usize| -> Result<u64, TextureError> {
+        data.get(offset..offset + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| TextureError::Decode("KTX2 file is truncated".into()))
+    };
+
+    let format = read_u32(12)?;
+    let width = read_u32(20)?;
+    let height = read_u32(24)?;
+    let depth = read_u32(28)?;
+    let layer_count = read_u32(32)?;
+    let face_count = read_u32(36)?;
+    let level_count = read_u32(40)?;
+    let supercompression = read_u32(44)?;
+
+    if format == 0 {
+        return Err(TextureError::Unsupported(
+            "KTX2 files without a vulkan format (basis universal)".into(),
+        ));
+    }
+    if supercompression != 0 {
+        return Err(TextureError::Unsupported(format!(
+            "KTX2 supercompression scheme {supercompression}"
+        )));
+    }
+    if width == 0 || height == 0 || depth > 1 || layer_count > 1 || face_count != 1 {
+        return Err(TextureError::Unsupported(
+            "KTX2 files that aren't a single 2D texture".into(),
+        ));
+    }
+
+    let max_levels = 32 - width.max(height).leading_zeros();
+    if level_count > max_levels {
+        return Err(TextureError::Decode(format!(
+            "KTX2 file has {level_count} levels, but a {width}x{height} texture can only have {max_levels}"
+        )));
+    }
+
+    // a level count of 0 means that the mips should be generated
+    let levels = (0..level_count.max(1) as usize)
+        .map(|level| {
+            let index = LEVEL_INDEX_OFFSET + level * LEVEL_INDEX_SIZE;
+            let offset = read_u64(index)?;
+            let length = read_u64(index + 8)?;
+
+            usize::try_from(offset)
+                .ok()
+                .zip(usize::try_from(length).ok())
+                .and_then(|(offset, length)| data.get(offset..offset.checked_add(length)?))
+                .filter(|level| !level.is_empty())
+                .ok_or_else(|| TextureError::Decode(format!("KTX2 level {level} is out of bounds")))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Ktx2 {
+        format: vk::Format::from_raw(format as i32),
+        extent: [width, height],
+        levels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// builds a KTX2 file with the given levels, the level data is stored in order
+    fn build(format: u32, extent: [u32; 2], level_count: u32, levels: &[&[u8]]) -> Vec<u8> {
+        let mut header = IDENTIFIER.to_vec();
+        for v in [format, 1, extent[0], extent[1], 0, 0, 1, level_count, 0] {
+            header.extend(v.to_le_bytes());
+        }
+        // no data format descriptor, key values or supercompression data
+        header.extend([0; 32]);
+
+        let mut offset = (LEVEL_INDEX_OFFSET + levels.len() * LEVEL_INDEX_SIZE) as u64;
+        for level in levels {
+            let len = level.len() as u64;
+            for v in [offset, len, len] {
+                header.extend(v.to_le_bytes());
+            }
+            offset += len;
+        }
+
+        for level in levels {
+            header.extend(*level);
+        }
+        header
+    }
+
+    #[test]
+    fn parse_levels() {
+        let format = vk::Format::BC7_SRGB_BLOCK.as_raw() as u32;
+        let file = build(format, [8, 4], 2, &[&[1; 32], &[2; 16]]);
+
+        let ktx = parse(&file).unwrap();
+        assert_eq!(ktx.format, vk::Format::BC7_SRGB_BLOCK);
+        assert_eq!(ktx.extent, [8, 4]);
+        assert_eq!(ktx.levels, [&[1; 32][..], &[2; 16][..]]);
+    }
+
+    #[test]
+    fn zero_levels_means_generate() {
+        let format = vk::Format::R8G8B8A8_UNORM.as_raw() as u32;
+        let file = build(format, [2, 2], 0, &[&[7; 16]]);
+
+        let ktx = parse(&file).unwrap();
+        assert_eq!(ktx.levels.len(), 1);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let format = vk::Format::R8G8B8A8_UNORM.as_raw() as u32;
+
+        assert!(parse(b"definitely not a ktx file").is_err());
+        // basis universal
+        assert!(parse(&build(0, [2, 2], 1, &[&[0; 16]])).is_err());
+        // too many levels for the size
+        assert!(parse(&build(format, [2, 2], 3, &[&[0; 16], &[0; 4], &[0; 4]])).is_err());
+
+        let mut file = build(format, [2, 2], 1, &[&[0; 16]]);
+        file.truncate(file.len() - 1);
+        assert!(parse(&file).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod atlas;
+pub mod ktx2;
+pub mod texture;
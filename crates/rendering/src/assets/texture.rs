@@ -0,0 +1,224 @@
+use std::{fmt, path::Path};
+
+use ash::vk;
+
+use crate::{
+    handler::{sampler::SamplerDesc, RenderHandler},
+    vulkan::{compression_family, Texture},
+};
+
+use super::ktx2;
+
+/// points to a texture in the bindless texture array
+/// the index can be passed to shaders, see ``GetTexture`` in ``bindless.slang``
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle {
+    pub index: usize,
+    /// the generation of the slot when the texture was added, the renderer ignores the handle once the slot is reused
+    /// shaders only get the index
+    pub generation: u32,
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(std::io::Error),
+    /// the file isn't a valid image
+    Decode(String),
+    /// the file is valid, but uses something that can't be loaded
+    Unsupported(String),
+    Vulkan(vk::Result),
+    /// all texture slots are in use
+    NoFreeSlot,
+    /// every layer of the ``TextureAtlas`` is used
+    AtlasFull,
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read texture: {err}"),
+            Self::Decode(err) => write!(f, "failed to decode texture: {err}"),
+            Self::Unsupported(what) => write!(f, "unsupported texture: {what}"),
+            Self::Vulkan(err) => write!(f, "failed to upload texture: {err}"),
+            Self::NoFreeSlot => write!(f, "there are no free texture slots left"),
+            Self::AtlasFull => write!(f, "there are no free layers left in the atlas"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<std::io::Error> for TextureError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<vk::Result> for TextureError {
+    fn from(err: vk::Result) -> Self {
+        Self::Vulkan(err)
+    }
+}
+
+/// how much gpu memory all loaded textures use
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextureMemoryStats {
+    pub size: u64,
+    /// how much memory they would use as uncompressed ``R8G8B8A8``
+    pub uncompressed_size: u64,
+}
+
+impl TextureMemoryStats {
+    /// the bytes saved by using compressed formats
+    #[must_use]
+    pub fn saved(&self) -> u64 {
+        self.uncompressed_size.saturating_sub(self.size)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextureOptions {
+    /// png and jpeg files are treated as srgb color data
+    /// turn this off for data textures like normal maps
+    /// ktx2 files always use the format stored in the file
+    pub srgb: bool,
+    /// generate the mip levels if the file doesn't contain them
+    pub generate_mips: bool,
+    /// can be changed later with ``RenderHandler::set_texture_sampler``
+    pub sampler: SamplerDesc,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            generate_mips: true,
+            sampler: SamplerDesc::default(),
+        }
+    }
+}
+
+/// load a png, jpeg or ktx2 file and upload it to the gpu
+/// this blocks until the upload is done
+/// # Errors
+/// if the file can't be read or decoded, or there is no space to allocate the texture
+pub fn load(
+    renderer: &mut RenderHandler,
+    path: impl AsRef<Path>,
+) -> Result<TextureHandle, TextureError> {
+    load_with_options(renderer, path, TextureOptions::default())
+}
+
+/// # Errors
+/// if the file can't be read or decoded, or there is no space to allocate the texture
+pub fn load_with_options(
+    renderer: &mut RenderHandler,
+    path: impl AsRef<Path>,
+    options: TextureOptions,
+) -> Result<TextureHandle, TextureError> {
+    let data = std::fs::read(path)?;
+    let texture = create_texture(renderer, &data, options)?;
+    let sampler = renderer.get_sampler(options.sampler)?;
+
+    renderer
+        .add_texture(texture, sampler)
+        .ok_or(TextureError::NoFreeSlot)
+}
+
+/// load the first of the given files whose format is supported by the gpu
+/// used to ship the same texture in multiple compressed formats with an uncompressed fallback,
+/// for example ``["stone.bc7.ktx2", "stone.astc.ktx2", "stone.png"]``
+/// # Errors
+/// the error of the last file if none of them could be loaded
+pub fn load_variants<P: AsRef<Path>>(
+    renderer: &mut RenderHandler,
+    paths: &[P],
+    options: TextureOptions,
+) -> Result<TextureHandle, TextureError> {
+    let mut last_err = TextureError::Unsupported("no texture variants given".into());
+
+    for path in paths {
+        match load_with_options(renderer, path, options) {
+            Ok(handle) => return Ok(handle),
+            // try the next variant, the gpu might support that format
+            Err(err @ TextureError::Unsupported(_)) => last_err = err,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_err)
+}
+
+/// decode an image from memory and upload it to the gpu
+/// # Errors
+/// if the data can't be decoded, or there is no space to allocate the texture
+pub fn create_texture(
+    renderer: &RenderHandler,
+    data: &[u8],
+    options: TextureOptions,
+) -> Result<Texture, TextureError> {
+    let decoded;
+    let (format, extent, levels) = if ktx2::is_ktx2(data) {
+        let ktx = ktx2::parse(data)?;
+        (ktx.format, ktx.extent, ktx.levels)
+    } else {
+        decoded = decode_image(data)?;
+        let format = if options.srgb {
+            vk::Format::R8G8B8A8_SRGB
+        } else {
+            vk::Format::R8G8B8A8_UNORM
+        };
+        (format, decoded.0, vec![decoded.1.as_slice()])
+    };
+
+    let device = renderer.device.clone();
+
+    if !Texture::is_format_supported(&device, format) {
+        return Err(TextureError::Unsupported(format!(
+            "{format:?} can't be sampled on this gpu"
+        )));
+    }
+
+    // block compressed formats can't be blitted, so their mips need to be in the file
+    let generate = options.generate_mips && Texture::can_generate_mips(&device, format);
+    let mip_levels = if generate {
+        Texture::max_mip_levels(extent)
+    } else {
+        levels.len() as u32
+    };
+
+    let mut usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST;
+    if generate {
+        usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+    }
+
+    let texture = Texture::new(device, extent, format, mip_levels, usage)?;
+    texture.upload(&levels)?;
+
+    if compression_family(format).is_some() {
+        log::debug!(
+            "loaded {format:?} texture, {} KiB instead of {} KiB uncompressed",
+            texture.size / 1024,
+            texture.uncompressed_size() / 1024,
+        );
+    }
+
+    Ok(texture)
+}
+
+/// decode a png or jpeg to rgba8
+#[cfg(feature = "image")]
+pub(super) fn decode_image(data: &[u8]) -> Result<([u32; 2], Vec<u8>), TextureError> {
+    let image = image::load_from_memory(data)
+        .map_err(|err| TextureError::Decode(err.to_string()))?
+        .to_rgba8();
+
+    Ok((image.dimensions().into(), image.into_raw()))
+}
+
+#[cfg(not(feature = "image"))]
+pub(super) fn decode_image(_data: &[u8]) -> Result<([u32; 2], Vec<u8>), TextureError> {
+    Err(TextureError::Unsupported(
+        "only ktx2 files can be loaded without the \"image\" feature".into(),
+    ))
+}
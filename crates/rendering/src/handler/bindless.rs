@@ -1,13 +1,29 @@
-use std::sync::Arc;
+use std::{cell::Cell, sync::Arc};
 
+use allocators::ScopedArena;
 use ash::{prelude::VkResult, vk};
 
-use crate::vulkan::{Buffer, VulkanDevice};
+use crate::vulkan::{Buffer, GpuDevice, Texture, VulkanDevice};
 
-#[derive(Debug, Clone, Copy)]
+use super::DestroyResource;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BindlessResourceHandle {
     pub index: usize,
     pub ty: BindlessResourceType,
+    /// changes every time the slot is released, handles to an older generation are stale
+    pub generation: u32,
+}
+
+impl BindlessResourceHandle {
+    /// a handle to a slot the renderer reserved for itself, reserved slots are never released
+    pub const fn reserved(index: usize, ty: BindlessResourceType) -> Self {
+        Self {
+            index,
+            ty,
+            generation: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,6 +31,7 @@ pub enum BindlessResourceType {
     UniformBuffer,
     StorageBuffer,
     StorageImage,
+    Texture,
 }
 
 impl BindlessResourceType {
@@ -23,22 +40,28 @@ impl BindlessResourceType {
             Self::UniformBuffer => BindlessHandler::UNIFORM_BUFFER_BINDING,
             Self::StorageBuffer => BindlessHandler::STORAGE_BUFFER_BINDING,
             Self::StorageImage => BindlessHandler::STORAGE_IMAGE_BINDING,
+            Self::Texture => BindlessHandler::TEXTURE_BINDING,
         }
     }
 
+    /// the index of the type in per type arrays
+    fn array_index(self) -> usize {
+        self.binding() as usize
+    }
+
     pub fn desc_type(self) -> vk::DescriptorType {
         match self {
             Self::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
             Self::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
             Self::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+            Self::Texture => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
         }
     }
 }
 
-#[allow(unused)]
 enum UpdateResourceTask {
     UpdateBuffer(Arc<Buffer>),
-    UpdateImageView(vk::ImageView),
+    UpdateTexture(Arc<Texture>, vk::Sampler),
 }
 
 /// basically just an Option but with 3 states
@@ -49,6 +72,8 @@ pub enum ResourceSlot<T> {
     Submited,
     /// the resource is ready to be used
     Written(T),
+    /// the slot is used by the renderer itself and can't be taken
+    Reserved,
 }
 
 impl<T> ResourceSlot<T> {
@@ -57,6 +82,7 @@ impl<T> ResourceSlot<T> {
             Self::Written(_) => std::mem::replace(self, Self::Empty),
             Self::Submited => Self::Submited,
             Self::Empty => Self::Empty,
+            Self::Reserved => Self::Reserved,
         }
     }
 
@@ -81,16 +107,150 @@ pub struct BindlessHandler {
     pub uniform_buffers: [ResourceSlot<Arc<Buffer>>; Self::POOL_SIZE],
     pub storage_buffers: [ResourceSlot<Arc<Buffer>>; Self::POOL_SIZE],
     pub storage_images: [ResourceSlot<vk::ImageView>; Self::POOL_SIZE],
+    pub textures: [ResourceSlot<Arc<Texture>>; Self::POOL_SIZE],
     update_resource_queue: Vec<(usize, BindlessResourceHandle, UpdateResourceTask)>,
+    /// reused by ``update_descriptor_set`` for the infos of the queued writes
+    buffer_infos: Vec<vk::DescriptorBufferInfo>,
+    image_infos: Vec<vk::DescriptorImageInfo>,
+    /// counts the writes to every descriptor set, command buffers that bound a set are invalid after it's written
+    set_writes: [Cell<u64>; super::FLYING_FRAMES],
+    /// the generation of every slot, per resource type
+    generations: [[u32; Self::POOL_SIZE]; 4],
+    /// the slots that can be given out, per resource type, the last one is taken first
+    /// might contain slots that were taken by index since, they are skipped
+    free_slots: [Vec<usize>; 4],
 }
 
 impl BindlessHandler {
     pub const UNIFORM_BUFFER_BINDING: u32 = 0;
     pub const STORAGE_BUFFER_BINDING: u32 = 1;
     pub const STORAGE_IMAGE_BINDING: u32 = 2;
+    pub const TEXTURE_BINDING: u32 = 3;
 
     pub const POOL_SIZE: usize = 100;
 
+    /// the uniform buffer slot that contains the ``Environment``
+    pub const ENVIRONMENT_SLOT: usize = Self::POOL_SIZE - 1;
+
+    /// the storage buffer slot that contains the parameters of the ``MaterialInstance``s
+    pub const MATERIAL_PARAMS_SLOT: usize = Self::POOL_SIZE - 1;
+
+    /// the storage buffer slot that contains the outlined objects of the frame
+    pub const OUTLINE_SLOT: usize = Self::MATERIAL_PARAMS_SLOT - 1;
+
+    /// the storage buffer slot that contains the parameters of the global illumination grid
+    pub const GI_SLOT: usize = Self::OUTLINE_SLOT - 1;
+
+    /// the storage buffer slot that contains the voxels of the global illumination grid
+    pub const GI_VOXELS_SLOT: usize = Self::GI_SLOT - 1;
+
+    /// the storage buffer slot that contains the parameters of the volumetric fluids
+    pub const FLUID_SLOT: usize = Self::GI_VOXELS_SLOT - 1;
+
+    /// the storage buffer slot that contains the voxels of the volumetric fluids
+    pub const FLUID_VOXELS_SLOT: usize = Self::FLUID_SLOT - 1;
+
+    /// the storage buffer slot that contains the environment probes
+    pub const PROBES_SLOT: usize = Self::FLUID_VOXELS_SLOT - 1;
+
+    /// the storage buffer slot that contains the cameras of a multiview pass, see ``views.rs``
+    pub const VIEWS_SLOT: usize = Self::PROBES_SLOT - 1;
+
+    /// the storage image slots that contain the hdr targets, one for every swapchain image
+    /// ``HDR_TARGET_SLOT + image_index``
+    pub const HDR_TARGET_SLOT: usize = Self::POOL_SIZE - Self::MAX_SWAPCHAIN_IMAGES;
+    pub const MAX_SWAPCHAIN_IMAGES: usize = 8;
+
+    /// the storage image slots that contain the ui targets, ``UI_TARGET_SLOT + image_index``
+    pub const UI_TARGET_SLOT: usize = Self::HDR_TARGET_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// the storage image slots that contain the velocity targets, ``VELOCITY_TARGET_SLOT + image_index``
+    pub const VELOCITY_TARGET_SLOT: usize = Self::UI_TARGET_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// the two storage image slots of the temporal anti aliasing history, they swap every frame
+    pub const TAA_HISTORY_SLOT: usize = Self::VELOCITY_TARGET_SLOT - 2;
+
+    /// the storage image slots that contain the normal targets, ``NORMAL_TARGET_SLOT + image_index``
+    pub const NORMAL_TARGET_SLOT: usize = Self::TAA_HISTORY_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// the storage image slots that contain the depth targets, ``DEPTH_TARGET_SLOT + image_index``
+    pub const DEPTH_TARGET_SLOT: usize = Self::NORMAL_TARGET_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// the two storage image slots of the ambient occlusion, the raw one and the blurred one
+    pub const SSAO_SLOT: usize = Self::DEPTH_TARGET_SLOT - 2;
+
+    /// the storage image slots that contain the transparency accumulation targets, ``OIT_ACCUM_SLOT + image_index``
+    pub const OIT_ACCUM_SLOT: usize = Self::SSAO_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// the storage image slots that contain the revealage targets, ``OIT_REVEALAGE_SLOT + image_index``
+    pub const OIT_REVEALAGE_SLOT: usize = Self::OIT_ACCUM_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// the storage image slots that contain the object id targets, ``OBJECT_ID_TARGET_SLOT + image_index``
+    pub const OBJECT_ID_TARGET_SLOT: usize = Self::OIT_REVEALAGE_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// the two storage image slots of the global illumination radiance grid, they swap every frame
+    pub const GI_RADIANCE_SLOT: usize = Self::OBJECT_ID_TARGET_SLOT - 2;
+
+    /// the storage image slots that contain the swapchain images, ``SWAPCHAIN_TARGET_SLOT + image_index``
+    /// only written with ``RendererConfig::compute_present``
+    pub const SWAPCHAIN_TARGET_SLOT: usize = Self::GI_RADIANCE_SLOT - Self::MAX_SWAPCHAIN_IMAGES;
+
+    /// push constants are available in every shader stage
+    pub const PUSH_CONSTANT_SIZE: u32 = 128;
+
+    /// a slang module with the bindings, the pool size and the reserved slots as constants,
+    /// see ``RenderHandler::bindless_shader_header``
+    #[must_use]
+    pub fn shader_header() -> String {
+        let constants = [
+            (
+                "UNIFORM_BUFFER_BINDING",
+                Self::UNIFORM_BUFFER_BINDING as usize,
+            ),
+            (
+                "STORAGE_BUFFER_BINDING",
+                Self::STORAGE_BUFFER_BINDING as usize,
+            ),
+            (
+                "STORAGE_IMAGE_BINDING",
+                Self::STORAGE_IMAGE_BINDING as usize,
+            ),
+            ("TEXTURE_BINDING", Self::TEXTURE_BINDING as usize),
+            ("POOL_SIZE", Self::POOL_SIZE),
+            ("MAX_SWAPCHAIN_IMAGES", Self::MAX_SWAPCHAIN_IMAGES),
+            ("PUSH_CONSTANT_SIZE", Self::PUSH_CONSTANT_SIZE as usize),
+            ("ENVIRONMENT_SLOT", Self::ENVIRONMENT_SLOT),
+            ("MATERIAL_PARAMS_SLOT", Self::MATERIAL_PARAMS_SLOT),
+            ("OUTLINE_SLOT", Self::OUTLINE_SLOT),
+            ("GI_SLOT", Self::GI_SLOT),
+            ("GI_VOXELS_SLOT", Self::GI_VOXELS_SLOT),
+            ("FLUID_SLOT", Self::FLUID_SLOT),
+            ("FLUID_VOXELS_SLOT", Self::FLUID_VOXELS_SLOT),
+            ("PROBES_SLOT", Self::PROBES_SLOT),
+            ("VIEWS_SLOT", Self::VIEWS_SLOT),
+            ("HDR_TARGET_SLOT", Self::HDR_TARGET_SLOT),
+            ("UI_TARGET_SLOT", Self::UI_TARGET_SLOT),
+            ("VELOCITY_TARGET_SLOT", Self::VELOCITY_TARGET_SLOT),
+            ("TAA_HISTORY_SLOT", Self::TAA_HISTORY_SLOT),
+            ("NORMAL_TARGET_SLOT", Self::NORMAL_TARGET_SLOT),
+            ("DEPTH_TARGET_SLOT", Self::DEPTH_TARGET_SLOT),
+            ("SSAO_SLOT", Self::SSAO_SLOT),
+            ("OIT_ACCUM_SLOT", Self::OIT_ACCUM_SLOT),
+            ("OIT_REVEALAGE_SLOT", Self::OIT_REVEALAGE_SLOT),
+            ("OBJECT_ID_TARGET_SLOT", Self::OBJECT_ID_TARGET_SLOT),
+            ("GI_RADIANCE_SLOT", Self::GI_RADIANCE_SLOT),
+            ("SWAPCHAIN_TARGET_SLOT", Self::SWAPCHAIN_TARGET_SLOT),
+        ];
+
+        let mut header = String::from(
+            "// generated by ``RenderHandler::bindless_shader_header``, don't change it by hand\n\n",
+        );
+        for (name, value) in constants {
+            header += &format!("static const uint {name} = {value};\n");
+        }
+        header
+    }
+
     pub fn new(device: &VulkanDevice) -> VkResult<Self> {
         let descriptor_count = (Self::POOL_SIZE * super::FLYING_FRAMES) as u32;
         let pool_sizes = [
@@ -106,6 +266,10 @@ impl BindlessHandler {
                 ty: vk::DescriptorType::STORAGE_IMAGE,
                 descriptor_count,
             },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count,
+            },
         ];
 
         let pool_create_info = vk::DescriptorPoolCreateInfo::default()
@@ -139,43 +303,213 @@ impl BindlessHandler {
             .try_into()
             .unwrap();
 
-        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts);
-        // TODO: .push_constant_ranges(push_constant_ranges);
+        let push_constant_ranges = [vk::PushConstantRange::default()
+            .stage_flags(vk::ShaderStageFlags::ALL)
+            .offset(0)
+            .size(Self::PUSH_CONSTANT_SIZE)];
+
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default()
+            .set_layouts(&layouts)
+            .push_constant_ranges(&push_constant_ranges);
 
         let pipeline_layout =
             unsafe { device.create_pipeline_layout(&pipeline_layout_info, None) }?;
 
-        Ok(Self {
-            descriptor_pool: pool,
-            descriptor_layout: layout,
+        Ok(Self::from_handles(
+            pool,
+            layout,
+            pipeline_layout,
+            descriptor_sets,
+        ))
+    }
+
+    /// the slot bookkeeping for already created descriptor sets
+    fn from_handles(
+        descriptor_pool: vk::DescriptorPool,
+        descriptor_layout: vk::DescriptorSetLayout,
+        pipeline_layout: vk::PipelineLayout,
+        descriptor_sets: [vk::DescriptorSet; super::FLYING_FRAMES],
+    ) -> Self {
+        let mut uniform_buffers = [const { ResourceSlot::Empty }; Self::POOL_SIZE];
+        uniform_buffers[Self::ENVIRONMENT_SLOT] = ResourceSlot::Reserved;
+
+        let mut storage_buffers = [const { ResourceSlot::Empty }; Self::POOL_SIZE];
+        for slot in &mut storage_buffers[Self::VIEWS_SLOT..] {
+            *slot = ResourceSlot::Reserved;
+        }
+
+        let mut storage_images = [const { ResourceSlot::Empty }; Self::POOL_SIZE];
+        for slot in &mut storage_images[Self::SWAPCHAIN_TARGET_SLOT..] {
+            *slot = ResourceSlot::Reserved;
+        }
+
+        let textures = [const { ResourceSlot::Empty }; Self::POOL_SIZE];
+
+        // the lowest slots are at the end, so they're given out first
+        fn free_slots<T>(slots: &[ResourceSlot<T>]) -> Vec<usize> {
+            (0..slots.len())
+                .rev()
+                .filter(|&i| slots[i].is_empty())
+                .collect()
+        }
+        let free_slots = [
+            free_slots(&uniform_buffers),
+            free_slots(&storage_buffers),
+            free_slots(&storage_images),
+            free_slots(&textures),
+        ];
+
+        Self {
+            descriptor_pool,
+            descriptor_layout,
             descriptor_sets,
             pipeline_layout,
-            uniform_buffers: [const { ResourceSlot::Empty }; Self::POOL_SIZE],
-            storage_images: [const { ResourceSlot::Empty }; Self::POOL_SIZE],
-            storage_buffers: [const { ResourceSlot::Empty }; Self::POOL_SIZE],
+            uniform_buffers,
+            storage_images,
+            storage_buffers,
+            textures,
             update_resource_queue: vec![],
-        })
+            buffer_infos: vec![],
+            image_infos: vec![],
+            set_writes: Default::default(),
+            generations: [[0; Self::POOL_SIZE]; 4],
+            free_slots,
+        }
     }
 
-    pub fn update_descriptor_set(&mut self, device: &VulkanDevice, frame_index: usize) {
-        let mut i = 0;
-        while i < self.update_resource_queue.len() {
-            let (_, handle, resource) = &self.update_resource_queue[i];
+    /// take a free slot of the type, the slot stays empty until something is written to it
+    /// returns none if every slot is used
+    pub fn allocate(&mut self, ty: BindlessResourceType) -> Option<BindlessResourceHandle> {
+        while let Some(index) = self.free_slots[ty.array_index()].pop() {
+            if self.slot_is_empty(ty, index) {
+                return Some(self.handle(index, ty));
+            }
+        }
+        None
+    }
+
+    /// the number of slots of the type that ``allocate`` can still give out
+    pub fn free_count(&self, ty: BindlessResourceType) -> usize {
+        (0..Self::POOL_SIZE)
+            .filter(|&i| self.slot_is_empty(ty, i))
+            .count()
+    }
+
+    /// a handle to the slot with its current generation
+    pub fn handle(&self, index: usize, ty: BindlessResourceType) -> BindlessResourceHandle {
+        BindlessResourceHandle {
+            index,
+            ty,
+            generation: self.generations[ty.array_index()][index],
+        }
+    }
+
+    /// if the slot of the handle hasn't been released since the handle was given out
+    pub fn is_current(&self, handle: &BindlessResourceHandle) -> bool {
+        self.generations[handle.ty.array_index()][handle.index] == handle.generation
+    }
+
+    fn slot_is_empty(&self, ty: BindlessResourceType, index: usize) -> bool {
+        match ty {
+            BindlessResourceType::UniformBuffer => self.uniform_buffers[index].is_empty(),
+            BindlessResourceType::StorageBuffer => self.storage_buffers[index].is_empty(),
+            BindlessResourceType::StorageImage => self.storage_images[index].is_empty(),
+            BindlessResourceType::Texture => self.textures[index].is_empty(),
+        }
+    }
 
+    /// make every handle to the slot stale and give it out again
+    /// the slot must already be empty
+    pub fn free(&mut self, ty: BindlessResourceType, index: usize) {
+        let generation = &mut self.generations[ty.array_index()][index];
+        *generation = generation.wrapping_add(1);
+        self.free_slots[ty.array_index()].push(index);
+    }
+
+    /// changes every time the descriptor set is written, a command buffer recorded with an older version is invalid
+    pub fn set_version(&self, set_index: usize) -> u64 {
+        self.set_writes[set_index].get()
+    }
+
+    /// write every queued resource to the descriptor set of the frame, all in one call
+    /// the writes are allocated in the scratch memory of the frame, if it's full they go to the heap
+    pub fn update_descriptor_set(
+        &mut self,
+        device: &dyn GpuDevice,
+        frame_index: usize,
+        arena: &ScopedArena,
+    ) {
+        if self.update_resource_queue.is_empty() {
+            return;
+        }
+
+        // the slot was given to something else since the write was queued
+        let generations = &self.generations;
+        self.update_resource_queue.retain(|(_, handle, _)| {
+            generations[handle.ty.array_index()][handle.index] == handle.generation
+        });
+
+        // the infos are kept between frames, so their memory is only allocated once
+        let mut buffer_infos = std::mem::take(&mut self.buffer_infos);
+        let mut image_infos = std::mem::take(&mut self.image_infos);
+        buffer_infos.clear();
+        image_infos.clear();
+
+        for (_, _, resource) in &self.update_resource_queue {
             match resource {
-                UpdateResourceTask::UpdateBuffer(b) => {
-                    self.upload_buffer_intern(
-                        device,
-                        b.handle(),
-                        handle.ty.desc_type(),
-                        handle.ty.binding(),
-                        handle.index as u32,
-                        frame_index,
-                    );
-                }
-                UpdateResourceTask::UpdateImageView(_) => unimplemented!(),
+                UpdateResourceTask::UpdateBuffer(b) => buffer_infos.push(
+                    vk::DescriptorBufferInfo::default()
+                        .buffer(b.handle())
+                        .offset(0)
+                        .range(vk::WHOLE_SIZE),
+                ),
+                UpdateResourceTask::UpdateTexture(t, sampler) => image_infos.push(
+                    vk::DescriptorImageInfo::default()
+                        .image_view(t.view)
+                        .sampler(*sampler)
+                        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                ),
             }
+        }
 
+        {
+            let (mut buffer_info, mut image_info) = (buffer_infos.iter(), image_infos.iter());
+            let queue = &self.update_resource_queue;
+            // called in order, so the infos line up with the queue
+            let mut write = |i: usize| {
+                let (_, handle, resource) = &queue[i];
+                let write = vk::WriteDescriptorSet::default()
+                    .dst_set(self.descriptor_sets[frame_index])
+                    .dst_binding(handle.ty.binding())
+                    .descriptor_type(handle.ty.desc_type())
+                    .dst_array_element(handle.index as u32)
+                    .descriptor_count(1);
+                match resource {
+                    UpdateResourceTask::UpdateBuffer(_) => {
+                        write.buffer_info(std::slice::from_ref(buffer_info.next().unwrap()))
+                    }
+                    UpdateResourceTask::UpdateTexture(..) => {
+                        write.image_info(std::slice::from_ref(image_info.next().unwrap()))
+                    }
+                }
+            };
+
+            let heap: Vec<_>;
+            let writes = match arena.alloc_slice_fill_with(queue.len(), &mut write) {
+                Some(writes) => &*writes,
+                None => {
+                    heap = (0..queue.len()).map(write).collect();
+                    &heap
+                }
+            };
+            self.write_sets(device, writes, frame_index);
+        }
+
+        self.buffer_infos = buffer_infos;
+        self.image_infos = image_infos;
+
+        let mut i = 0;
+        while i < self.update_resource_queue.len() {
             if self.update_resource_queue[i].0 == frame_index {
                 let (_, handle, resource) = self.update_resource_queue.swap_remove(i);
                 match resource {
@@ -186,7 +520,9 @@ impl BindlessHandler {
                             self.storage_buffers[handle.index] = ResourceSlot::Written(b);
                         }
                     }
-                    UpdateResourceTask::UpdateImageView(_) => unimplemented!(),
+                    UpdateResourceTask::UpdateTexture(t, _) => {
+                        self.textures[handle.index] = ResourceSlot::Written(t);
+                    }
                 }
             } else {
                 i += 1;
@@ -207,9 +543,150 @@ impl BindlessHandler {
         ));
     }
 
+    /// the buffer at the slot, also if it hasn't been written to the descriptors yet
+    /// returns none if the handle is stale
+    pub fn buffer(&self, handle: &BindlessResourceHandle) -> Option<Arc<Buffer>> {
+        if !self.is_current(handle) {
+            return None;
+        }
+
+        // the newest queued buffer replaces the one that is written at the moment
+        let queued =
+            self.update_resource_queue
+                .iter()
+                .rev()
+                .find_map(|(_, queued, task)| match task {
+                    UpdateResourceTask::UpdateBuffer(buffer)
+                        if queued.index == handle.index && queued.ty == handle.ty =>
+                    {
+                        Some(buffer.clone())
+                    }
+                    _ => None,
+                });
+        if queued.is_some() {
+            return queued;
+        }
+
+        let slots = match handle.ty {
+            BindlessResourceType::UniformBuffer => &self.uniform_buffers,
+            BindlessResourceType::StorageBuffer => &self.storage_buffers,
+            BindlessResourceType::StorageImage | BindlessResourceType::Texture => return None,
+        };
+
+        match &slots[handle.index] {
+            ResourceSlot::Written(buffer) => Some(buffer.clone()),
+            _ => None,
+        }
+    }
+
+    /// empty the slot and forget the writes queued for it, so it can be given out again
+    /// returns the resources that were bound there, the descriptors of the frames in flight still point to them
+    /// stale handles are ignored, the slot already belongs to something else
+    pub fn release(&mut self, handle: &BindlessResourceHandle) -> Vec<DestroyResource> {
+        if !self.is_current(handle) {
+            return vec![];
+        }
+
+        let mut released = vec![];
+        self.update_resource_queue.retain(|(_, queued, task)| {
+            if queued.index != handle.index || queued.ty != handle.ty {
+                return true;
+            }
+            released.push(match task {
+                UpdateResourceTask::UpdateBuffer(buffer) => {
+                    DestroyResource::SharedBuffer(buffer.clone())
+                }
+                UpdateResourceTask::UpdateTexture(texture, _) => {
+                    DestroyResource::Texture(texture.clone())
+                }
+            });
+            false
+        });
+
+        let written = match handle.ty {
+            BindlessResourceType::UniformBuffer => {
+                release_slot(&mut self.uniform_buffers[handle.index])
+                    .map(DestroyResource::SharedBuffer)
+            }
+            BindlessResourceType::StorageBuffer => {
+                release_slot(&mut self.storage_buffers[handle.index])
+                    .map(DestroyResource::SharedBuffer)
+            }
+            BindlessResourceType::Texture => {
+                release_slot(&mut self.textures[handle.index]).map(DestroyResource::Texture)
+            }
+            // storage images are only used by the renderer itself
+            BindlessResourceType::StorageImage => None,
+        };
+
+        if self.slot_is_empty(handle.ty, handle.index) {
+            self.free(handle.ty, handle.index);
+        }
+
+        released.extend(written);
+        released
+    }
+
+    pub fn upload_texture(
+        &mut self,
+        texture: Arc<Texture>,
+        sampler: vk::Sampler,
+        handle: BindlessResourceHandle,
+        set_index: usize,
+    ) {
+        self.update_resource_queue.push((
+            set_index,
+            handle,
+            UpdateResourceTask::UpdateTexture(texture, sampler),
+        ));
+    }
+
+    /// write a different buffer to every descriptor set
+    /// used for data that is written every frame, so the cpu doesn't write to a buffer that is in use
+    /// the slot should be ``ResourceSlot::Reserved``, as it isn't tracked
+    pub fn set_per_frame_buffer(
+        &self,
+        device: &dyn GpuDevice,
+        buffers: [vk::Buffer; super::FLYING_FRAMES],
+        handle: BindlessResourceHandle,
+    ) {
+        for (set_index, buffer) in buffers.into_iter().enumerate() {
+            self.upload_buffer_intern(
+                device,
+                buffer,
+                handle.ty.desc_type(),
+                handle.ty.binding(),
+                handle.index as u32,
+                set_index,
+            );
+        }
+    }
+
+    /// write a storage image in ``GENERAL`` layout to every descriptor set
+    /// none of the descriptor sets must currently be in use
+    pub fn set_storage_image_all_sets(
+        &self,
+        device: &dyn GpuDevice,
+        image_view: vk::ImageView,
+        index: usize,
+    ) {
+        for set_index in 0..super::FLYING_FRAMES {
+            self.upload_image_intern(
+                device,
+                image_view,
+                vk::ImageLayout::GENERAL,
+                vk::Sampler::null(),
+                vk::DescriptorType::STORAGE_IMAGE,
+                Self::STORAGE_IMAGE_BINDING,
+                index as u32,
+                set_index,
+            );
+        }
+    }
+
     fn upload_buffer_intern(
         &self,
-        device: &VulkanDevice,
+        device: &dyn GpuDevice,
         buffer: vk::Buffer,
         ty: vk::DescriptorType,
         binding: u32,
@@ -229,14 +706,13 @@ impl BindlessHandler {
             .buffer_info(&buffer_info)
             .descriptor_count(1);
 
-        unsafe { device.update_descriptor_sets(&[write_set], &[]) };
+        self.write_sets(device, &[write_set], set_index);
     }
 
-    #[allow(unused)]
     #[allow(clippy::too_many_arguments)]
     fn upload_image_intern(
         &self,
-        device: &VulkanDevice,
+        device: &dyn GpuDevice,
         image_view: vk::ImageView,
         image_layout: vk::ImageLayout,
         sampler: vk::Sampler,
@@ -258,7 +734,18 @@ impl BindlessHandler {
             .image_info(&image_info)
             .descriptor_count(1);
 
-        unsafe { device.update_descriptor_sets(&[write_set], &[]) };
+        self.write_sets(device, &[write_set], set_index);
+    }
+
+    /// all writes must go to the set at ``set_index``
+    fn write_sets(
+        &self,
+        device: &dyn GpuDevice,
+        writes: &[vk::WriteDescriptorSet<'_>],
+        set_index: usize,
+    ) {
+        device.write_descriptor_sets(writes);
+        self.set_writes[set_index].set(self.set_writes[set_index].get() + 1);
     }
 
     pub unsafe fn destroy(&self, device: &VulkanDevice) {
@@ -268,8 +755,183 @@ impl BindlessHandler {
     }
 }
 
-/// gets the first value that is None in the array
-/// used find a free slot in the bindless array
-pub fn get_free_slot<T>(input: &[ResourceSlot<T>]) -> Option<usize> {
-    input.iter().position(ResourceSlot::is_empty)
+/// empty a slot the application used, reserved slots stay as they are
+fn release_slot<T>(slot: &mut ResourceSlot<T>) -> Option<T> {
+    match std::mem::replace(slot, ResourceSlot::Empty) {
+        ResourceSlot::Written(resource) => Some(resource),
+        ResourceSlot::Reserved => {
+            *slot = ResourceSlot::Reserved;
+            None
+        }
+        ResourceSlot::Empty | ResourceSlot::Submited => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use allocators::StackAllocator;
+    use ash::vk::Handle;
+
+    use super::*;
+    use crate::vulkan::mock::{DeviceCall, MockDevice};
+
+    fn handler() -> BindlessHandler {
+        BindlessHandler::from_handles(
+            vk::DescriptorPool::null(),
+            vk::DescriptorSetLayout::null(),
+            vk::PipelineLayout::null(),
+            std::array::from_fn(|i| vk::DescriptorSet::from_raw(i as u64 + 1)),
+        )
+    }
+
+    #[test]
+    fn reserved_slots_are_never_free() {
+        let mut bindless = handler();
+
+        for slot in &mut bindless.uniform_buffers[..BindlessHandler::ENVIRONMENT_SLOT] {
+            *slot = ResourceSlot::Submited;
+        }
+        assert_eq!(bindless.allocate(BindlessResourceType::UniformBuffer), None);
+
+        for slot in &mut bindless.storage_buffers[..BindlessHandler::VIEWS_SLOT] {
+            *slot = ResourceSlot::Submited;
+        }
+        assert_eq!(bindless.allocate(BindlessResourceType::StorageBuffer), None);
+
+        let free = bindless
+            .allocate(BindlessResourceType::StorageImage)
+            .unwrap();
+        assert!(free.index < BindlessHandler::SWAPCHAIN_TARGET_SLOT);
+        assert!(matches!(
+            bindless.storage_images[BindlessHandler::HDR_TARGET_SLOT],
+            ResourceSlot::Reserved
+        ));
+    }
+
+    #[test]
+    fn released_slots_are_given_out_again_with_a_new_generation() {
+        let mut bindless = handler();
+        let first = bindless.allocate(BindlessResourceType::Texture).unwrap();
+        bindless.textures[first.index] = ResourceSlot::Submited;
+        let second = bindless.allocate(BindlessResourceType::Texture).unwrap();
+        bindless.textures[second.index] = ResourceSlot::Submited;
+        assert_eq!((first.index, second.index), (0, 1));
+
+        bindless.release(&first);
+        assert!(!bindless.is_current(&first));
+
+        let reused = bindless.allocate(BindlessResourceType::Texture).unwrap();
+        assert_eq!(reused.index, first.index);
+        assert_ne!(reused.generation, first.generation);
+    }
+
+    #[test]
+    fn stale_handles_dont_touch_the_new_resource() {
+        let mut bindless = handler();
+        let stale = bindless
+            .allocate(BindlessResourceType::StorageBuffer)
+            .unwrap();
+        bindless.storage_buffers[stale.index] = ResourceSlot::Submited;
+        bindless.release(&stale);
+
+        let current = bindless
+            .allocate(BindlessResourceType::StorageBuffer)
+            .unwrap();
+        bindless.storage_buffers[current.index] = ResourceSlot::Submited;
+
+        assert!(bindless.release(&stale).is_empty());
+        assert!(matches!(
+            bindless.storage_buffers[current.index],
+            ResourceSlot::Submited
+        ));
+        assert!(bindless.buffer(&stale).is_none());
+    }
+
+    #[test]
+    fn storage_images_are_written_to_every_set() {
+        let device = MockDevice::default();
+        let bindless = handler();
+
+        bindless.set_storage_image_all_sets(&device, vk::ImageView::null(), 7);
+
+        let expected: Vec<_> = bindless
+            .descriptor_sets
+            .iter()
+            .map(|&set| DeviceCall::WriteDescriptor {
+                set,
+                binding: BindlessHandler::STORAGE_IMAGE_BINDING,
+                index: 7,
+                ty: vk::DescriptorType::STORAGE_IMAGE,
+            })
+            .collect();
+        assert_eq!(device.take_calls(), expected);
+    }
+
+    #[test]
+    fn per_frame_buffers_go_to_their_own_set() {
+        let device = MockDevice::default();
+        let bindless = handler();
+        let handle = BindlessResourceHandle::reserved(
+            BindlessHandler::ENVIRONMENT_SLOT,
+            BindlessResourceType::UniformBuffer,
+        );
+
+        bindless.set_per_frame_buffer(
+            &device,
+            std::array::from_fn(|i| vk::Buffer::from_raw(i as u64 + 10)),
+            handle,
+        );
+
+        let sets: Vec<_> = device
+            .take_calls()
+            .into_iter()
+            .map(|call| match call {
+                DeviceCall::WriteDescriptor { set, index, .. } => {
+                    assert_eq!(index as usize, BindlessHandler::ENVIRONMENT_SLOT);
+                    set
+                }
+                call => panic!("unexpected call {call:?}"),
+            })
+            .collect();
+        assert_eq!(sets, bindless.descriptor_sets);
+    }
+
+    #[test]
+    fn nothing_queued_doesnt_touch_the_set() {
+        let device = MockDevice::default();
+        let mut bindless = handler();
+        let before = bindless.set_version(0);
+
+        // nothing is queued, so the arena isn't used
+        let mut scratch = StackAllocator::new(std::ptr::null_mut(), 0);
+        bindless.update_descriptor_set(&device, 0, &scratch.scope());
+
+        assert!(device.take_calls().is_empty());
+        assert_eq!(bindless.set_version(0), before);
+    }
+
+    #[test]
+    fn writes_change_the_version_of_their_set() {
+        let device = MockDevice::default();
+        let bindless = handler();
+        let versions = |bindless: &BindlessHandler| -> Vec<u64> {
+            (0..crate::handler::FLYING_FRAMES)
+                .map(|set| bindless.set_version(set))
+                .collect()
+        };
+        let before = versions(&bindless);
+
+        bindless.upload_buffer_intern(
+            &device,
+            vk::Buffer::null(),
+            vk::DescriptorType::STORAGE_BUFFER,
+            BindlessHandler::STORAGE_BUFFER_BINDING,
+            3,
+            1,
+        );
+
+        let after = versions(&bindless);
+        assert_eq!(after[0], before[0]);
+        assert_ne!(after[1], before[1]);
+    }
 }
@@ -0,0 +1,227 @@
+// breadcrumbs tell which pass the gpu was working on when the device got lost
+// every pass of a frame leaves a marker in the command buffer, how they are read back depends on the device:
+// - with ``VK_NV_device_diagnostic_checkpoints`` the driver reports the last marker every pipeline stage reached
+// - with ``VK_AMD_buffer_marker`` the gpu writes the marker in to a host visible buffer when a pass starts and ends
+// - otherwise only the names recorded on the cpu are known, the crash happened somewhere in the frames in flight
+//
+// nothing is recorded unless ``RendererConfig::breadcrumbs`` is set
+
+use std::{fmt::Write, sync::Arc};
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Buffer, VulkanDevice};
+
+/// the markers of one ``FrameContext``
+pub(crate) struct Breadcrumbs {
+    enabled: bool,
+    /// the index of the frame, so the checkpoints of different frames can be told apart
+    frame: usize,
+    /// the name of every marker recorded this frame, in order
+    names: Vec<String>,
+    /// the last marker the gpu started and the last one it finished, only used with ``VK_AMD_buffer_marker``
+    markers: Option<Arc<Buffer>>,
+}
+
+impl Breadcrumbs {
+    /// # Errors
+    /// if there is no space left to allocate the marker buffer
+    pub fn new(device: &Arc<VulkanDevice>, frame: usize, enabled: bool) -> VkResult<Self> {
+        let use_buffer = enabled && device.checkpoints.is_none() && device.buffer_marker.is_some();
+
+        let markers = if use_buffer {
+            Some(Buffer::new(
+                device.clone(),
+                size_of::<[u32; 2]>() as u64,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?)
+        } else {
+            None
+        };
+
+        let breadcrumbs = Self {
+            enabled,
+            frame,
+            names: vec![],
+            markers,
+        };
+
+        breadcrumbs.write_markers();
+        Ok(breadcrumbs)
+    }
+
+    /// forget the markers of the last time the frame was recorded
+    /// the frame must not be executing
+    pub fn reset(&mut self) {
+        self.names.clear();
+        self.write_markers();
+    }
+
+    fn write_markers(&self) {
+        if let Some(markers) = &self.markers {
+            markers.write(0, &[0u32; 2]);
+        }
+    }
+
+    /// leave a marker in front of the commands of a pass
+    /// the name is only evaluated if breadcrumbs are enabled
+    pub unsafe fn mark(
+        &mut self,
+        device: &VulkanDevice,
+        cmd: vk::CommandBuffer,
+        name: impl FnOnce() -> String,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        self.names.push(name());
+        let index = self.names.len() - 1;
+
+        if let Some(checkpoints) = &device.checkpoints {
+            let marker = encode_checkpoint(self.frame, index);
+            checkpoints.cmd_set_checkpoint(cmd, marker as *const std::ffi::c_void);
+        } else if let (Some(buffer_marker), Some(markers)) = (&device.buffer_marker, &self.markers)
+        {
+            // the gpu reached this marker, and everything in front of it is done
+            let value = index as u32 + 1;
+            buffer_marker.cmd_write_buffer_marker(
+                cmd,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                markers.handle(),
+                0,
+                value,
+            );
+            buffer_marker.cmd_write_buffer_marker(
+                cmd,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                markers.handle(),
+                size_of::<u32>() as u64,
+                value,
+            );
+        }
+    }
+}
+
+/// describe where the gpu was when the device got lost, one line per finding
+/// returns None if breadcrumbs are disabled
+pub(crate) fn crash_report(device: &VulkanDevice, frames: &[Breadcrumbs]) -> Option<String> {
+    if !frames.iter().any(|frame| frame.enabled) {
+        return None;
+    }
+
+    let mut report = String::new();
+
+    if let Some(checkpoints) = &device.checkpoints {
+        let queue = device.queues.graphics.1;
+        let data = unsafe {
+            let len = checkpoints.get_queue_checkpoint_data_len(queue);
+            let mut data = vec![vk::CheckpointDataNV::default(); len];
+            checkpoints.get_queue_checkpoint_data(queue, &mut data);
+            data
+        };
+
+        for checkpoint in data {
+            let (frame, index) = decode_checkpoint(checkpoint.p_checkpoint_marker as usize);
+            let name = frames
+                .get(frame)
+                .and_then(|frame| frame.names.get(index))
+                .map_or("unknown", String::as_str);
+
+            let _ = writeln!(
+                report,
+                "{:?} reached \"{name}\" of frame {frame}",
+                checkpoint.stage
+            );
+        }
+    } else {
+        for frame in frames {
+            if let Some(markers) = &frame.markers {
+                let &[started, finished] = markers.read::<u32>() else {
+                    continue;
+                };
+                let _ = writeln!(
+                    report,
+                    "frame {}: {}",
+                    frame.frame,
+                    describe_markers(&frame.names, started, finished)
+                );
+            } else {
+                let _ = writeln!(
+                    report,
+                    "frame {} recorded: {}",
+                    frame.frame,
+                    frame.names.join(", ")
+                );
+            }
+        }
+    }
+
+    Some(report)
+}
+
+/// zero is never a valid marker, so the index is stored one higher
+fn encode_checkpoint(frame: usize, index: usize) -> usize {
+    (frame << 16) | (index + 1)
+}
+
+fn decode_checkpoint(marker: usize) -> (usize, usize) {
+    (marker >> 16, (marker & 0xffff).wrapping_sub(1))
+}
+
+/// the values written by ``VK_AMD_buffer_marker``
+/// the gpu writes ``started`` when it reaches a marker and ``finished`` once everything in front of it is done
+fn describe_markers(names: &[String], started: u32, finished: u32) -> String {
+    let name = |marker: u32| {
+        names
+            .get(marker as usize - 1)
+            .map_or("unknown", String::as_str)
+    };
+
+    let last_finished = match finished {
+        0 | 1 => "nothing finished".to_owned(),
+        _ => format!("finished \"{}\"", name(finished - 1)),
+    };
+
+    match started {
+        0 => format!("{last_finished}, nothing started"),
+        _ => format!("{last_finished}, started \"{}\"", name(started)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoints_round_trip() {
+        for (frame, index) in [(0, 0), (1, 0), (1, 41), (3, 0xfffe)] {
+            let marker = encode_checkpoint(frame, index);
+            assert_ne!(marker, 0);
+            assert_eq!(decode_checkpoint(marker), (frame, index));
+        }
+    }
+
+    #[test]
+    fn buffer_markers_name_the_passes() {
+        let names = ["buffer updates", "main pass", "tonemap"].map(String::from);
+
+        assert_eq!(
+            describe_markers(&names, 0, 0),
+            "nothing finished, nothing started"
+        );
+        assert_eq!(
+            describe_markers(&names, 2, 1),
+            "nothing finished, started \"main pass\""
+        );
+        assert_eq!(
+            describe_markers(&names, 3, 2),
+            "finished \"buffer updates\", started \"tonemap\""
+        );
+        assert_eq!(
+            describe_markers(&names, 3, 3),
+            "finished \"main pass\", started \"tonemap\""
+        );
+    }
+}
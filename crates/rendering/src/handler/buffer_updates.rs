@@ -0,0 +1,301 @@
+// writes to buffers that are recorded at the start of the next frame, see ``RenderHandler::update_buffer``
+// small writes are stored in the command buffer with ``vkCmdUpdateBuffer``,
+// bigger ones are copied from a staging buffer
+//
+// the writes happen before anything else in the frame, so the barriers wait for the previous frames
+// that use the buffer and make the new data visible to every stage that can read it,
+// which stages that are is known from the usage the buffer was created with
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Buffer, VulkanDevice};
+
+/// the biggest write that is stored in the command buffer, the limit of ``vkCmdUpdateBuffer``
+pub const MAX_INLINE_UPDATE_SIZE: usize = 65536;
+
+enum UpdateSource {
+    Inline(Vec<u8>),
+    Staging(Buffer),
+}
+
+struct BufferUpdate {
+    buffer: Arc<Buffer>,
+    offset: u64,
+    size: u64,
+    source: UpdateSource,
+}
+
+impl BufferUpdate {
+    fn stage(&self) -> vk::PipelineStageFlags2 {
+        match self.source {
+            UpdateSource::Inline(_) => vk::PipelineStageFlags2::CLEAR,
+            UpdateSource::Staging(_) => vk::PipelineStageFlags2::COPY,
+        }
+    }
+
+    /// a barrier for the region of the buffer that is written
+    fn barrier(
+        &self,
+        (src_stage, src_access): (vk::PipelineStageFlags2, vk::AccessFlags2),
+        (dst_stage, dst_access): (vk::PipelineStageFlags2, vk::AccessFlags2),
+    ) -> vk::BufferMemoryBarrier2<'static> {
+        vk::BufferMemoryBarrier2::default()
+            .src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(dst_stage)
+            .dst_access_mask(dst_access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.buffer.handle())
+            .offset(self.offset)
+            .size(self.size)
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.buffer.handle() == other.buffer.handle()
+            && self.offset < other.offset + other.size
+            && other.offset < self.offset + self.size
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct BufferUpdates {
+    pending: Vec<BufferUpdate>,
+    /// staging buffers of recorded updates, they have to live until the frame has finished
+    recorded: Vec<Buffer>,
+}
+
+impl BufferUpdates {
+    /// # Panics
+    /// if the data doesn't fit into the buffer at that offset
+    /// or the buffer wasn't created with ``BufferUsageFlags::TRANSFER_DST``
+    /// # Errors
+    /// if there is no space left to allocate the staging buffer
+    pub fn push(
+        &mut self,
+        device: &Arc<VulkanDevice>,
+        buffer: Arc<Buffer>,
+        offset: u64,
+        data: &[u8],
+    ) -> VkResult<()> {
+        let size = data.len() as u64;
+        assert!(
+            offset + size <= buffer.size(),
+            "the update doesn't fit into the buffer"
+        );
+        assert!(
+            buffer.usage().contains(vk::BufferUsageFlags::TRANSFER_DST),
+            "the buffer needs TRANSFER_DST usage to be updated"
+        );
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let source = if is_inline(offset, data.len()) {
+            UpdateSource::Inline(data.to_vec())
+        } else {
+            let staging = Buffer::new(
+                device.clone(),
+                size,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            staging.write(0, data);
+
+            // nothing else holds the staging buffer
+            UpdateSource::Staging(Arc::into_inner(staging).unwrap())
+        };
+
+        self.pending.push(BufferUpdate {
+            buffer,
+            offset,
+            size,
+            source,
+        });
+
+        Ok(())
+    }
+
+    /// record the pending updates, must be recorded before anything else in the frame uses the buffers
+    /// # Safety
+    /// the command buffer must be recording and not be inside a render pass
+    pub unsafe fn record(&mut self, device: &VulkanDevice, cmd: vk::CommandBuffer) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let write = |update: &BufferUpdate| (update.stage(), vk::AccessFlags2::TRANSFER_WRITE);
+
+        // wait for the previous frames to stop using the region,
+        // they could also have written it with an update or a shader
+        let before: Vec<_> = self
+            .pending
+            .iter()
+            .map(|update| {
+                let (stages, access) = buffer_scope(update.buffer.usage());
+                let previous = (
+                    stages | vk::PipelineStageFlags2::CLEAR | vk::PipelineStageFlags2::COPY,
+                    (access & vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                        | vk::AccessFlags2::TRANSFER_WRITE,
+                );
+                update.barrier(previous, write(update))
+            })
+            .collect();
+
+        device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().buffer_memory_barriers(&before),
+        );
+
+        for (i, update) in self.pending.iter().enumerate() {
+            // writes to the same bytes have to happen in the order they were made
+            if let Some(previous) = self.pending[..i].iter().rev().find(|v| v.overlaps(update)) {
+                let barrier = [update.barrier(write(previous), write(update))];
+                device.pipeline_barrier(
+                    cmd,
+                    &vk::DependencyInfo::default().buffer_memory_barriers(&barrier),
+                );
+            }
+
+            match &update.source {
+                UpdateSource::Inline(data) => {
+                    device.cmd_update_buffer(cmd, update.buffer.handle(), update.offset, data);
+                }
+                UpdateSource::Staging(staging) => {
+                    let region = vk::BufferCopy::default()
+                        .dst_offset(update.offset)
+                        .size(update.size);
+                    device.cmd_copy_buffer(
+                        cmd,
+                        staging.handle(),
+                        update.buffer.handle(),
+                        &[region],
+                    );
+                }
+            }
+        }
+
+        let after: Vec<_> = self
+            .pending
+            .iter()
+            .map(|update| update.barrier(write(update), buffer_scope(update.buffer.usage())))
+            .collect();
+
+        device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().buffer_memory_barriers(&after),
+        );
+
+        for update in self.pending.drain(..) {
+            if let UpdateSource::Staging(staging) = update.source {
+                self.recorded.push(staging);
+            }
+        }
+    }
+
+    /// if there are updates that weren't recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// the staging buffers of the updates that have been recorded since the last call
+    pub fn take_recorded(&mut self) -> Vec<Buffer> {
+        std::mem::take(&mut self.recorded)
+    }
+}
+
+/// ``vkCmdUpdateBuffer`` needs the offset and size to be a multiple of 4
+fn is_inline(offset: u64, size: usize) -> bool {
+    size <= MAX_INLINE_UPDATE_SIZE && offset.is_multiple_of(4) && size.is_multiple_of(4)
+}
+
+/// the stages that can use a buffer with this usage and how they access it
+fn buffer_scope(usage: vk::BufferUsageFlags) -> (vk::PipelineStageFlags2, vk::AccessFlags2) {
+    let shaders = vk::PipelineStageFlags2::VERTEX_SHADER
+        | vk::PipelineStageFlags2::FRAGMENT_SHADER
+        | vk::PipelineStageFlags2::COMPUTE_SHADER;
+
+    let uses = [
+        (
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            shaders,
+            vk::AccessFlags2::UNIFORM_READ,
+        ),
+        (
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            shaders,
+            vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+        ),
+        (
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT,
+            vk::AccessFlags2::VERTEX_ATTRIBUTE_READ,
+        ),
+        (
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            vk::PipelineStageFlags2::INDEX_INPUT,
+            vk::AccessFlags2::INDEX_READ,
+        ),
+        (
+            vk::BufferUsageFlags::INDIRECT_BUFFER,
+            vk::PipelineStageFlags2::DRAW_INDIRECT,
+            vk::AccessFlags2::INDIRECT_COMMAND_READ,
+        ),
+        (
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::PipelineStageFlags2::COPY,
+            vk::AccessFlags2::TRANSFER_READ,
+        ),
+    ];
+
+    uses.into_iter()
+        .filter(|(flag, ..)| usage.contains(*flag))
+        .fold(
+            (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE),
+            |(stages, access), (_, stage, read)| (stages | stage, access | read),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_aligned_updates_are_inline() {
+        assert!(is_inline(0, 64));
+        assert!(is_inline(256, MAX_INLINE_UPDATE_SIZE));
+        assert!(!is_inline(0, MAX_INLINE_UPDATE_SIZE + 4));
+        assert!(!is_inline(2, 64));
+        assert!(!is_inline(0, 6));
+    }
+
+    #[test]
+    fn scope_follows_the_usage() {
+        let (stages, access) = buffer_scope(
+            vk::BufferUsageFlags::VERTEX_BUFFER
+                | vk::BufferUsageFlags::INDEX_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+        );
+        assert_eq!(
+            stages,
+            vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT | vk::PipelineStageFlags2::INDEX_INPUT
+        );
+        assert_eq!(
+            access,
+            vk::AccessFlags2::VERTEX_ATTRIBUTE_READ | vk::AccessFlags2::INDEX_READ
+        );
+
+        let (stages, access) = buffer_scope(vk::BufferUsageFlags::STORAGE_BUFFER);
+        assert!(stages.contains(vk::PipelineStageFlags2::COMPUTE_SHADER));
+        assert!(access.contains(vk::AccessFlags2::SHADER_STORAGE_WRITE));
+
+        // a buffer that is only written by updates isn't read anywhere
+        assert_eq!(
+            buffer_scope(vk::BufferUsageFlags::TRANSFER_DST),
+            (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE)
+        );
+    }
+}
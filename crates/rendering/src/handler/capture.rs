@@ -0,0 +1,43 @@
+// frame captures with RenderDoc, only with the ``renderdoc`` feature
+// RenderDoc has to be loaded before the vulkan device is created,
+// so this only works if the application was started from RenderDoc
+
+#[cfg(feature = "renderdoc")]
+use renderdoc::{RenderDoc, V141};
+
+pub(crate) struct FrameCapture {
+    #[cfg(feature = "renderdoc")]
+    api: Option<RenderDoc<V141>>,
+}
+
+impl FrameCapture {
+    /// needs to be created before the device
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "renderdoc")]
+            api: RenderDoc::new()
+                .inspect_err(|err| log::info!("renderdoc isn't loaded: {err}"))
+                .ok(),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        return self.api.is_some();
+
+        #[cfg(not(feature = "renderdoc"))]
+        false
+    }
+
+    /// capture the next ``frames`` frames that are presented
+    pub fn trigger(&mut self, frames: u32) -> bool {
+        #[cfg(feature = "renderdoc")]
+        if let Some(api) = &mut self.api {
+            api.trigger_multi_frame_capture(frames);
+            return true;
+        }
+
+        let _ = frames;
+        false
+    }
+}
@@ -0,0 +1,319 @@
+// writes the swapchain image with a compute shader instead of the tone mapping, enabled with ``RendererConfig::compute_present``
+// for renderers that raymarch everything themselves and don't need the raster passes,
+// the main pass still runs, but it only clears the targets if there are no batches
+//
+// the swapchain images are bound as storage images at ``BindlessHandler::SWAPCHAIN_TARGET_SLOT + image_index``,
+// they are moved to ``GENERAL`` for the shader and to ``PRESENT_SRC_KHR`` after it,
+// so the outline and ui passes after it work like after the tone mapping
+//
+// surfaces that don't allow storage usage get an intermediate image per swapchain image instead,
+// see ``SwapchainStorage::Intermediate``, it's bound at the same slot and blitted on to the swapchain image
+//
+// the shader gets the target, the hdr target and the size in its push constants, see ``ComputePresentPushConstants``
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Swapchain, SwapchainStorage, VulkanDevice};
+
+use super::bindless::BindlessHandler;
+
+/// the size of the work groups the shader has to use in x and y
+pub const COMPUTE_PRESENT_GROUP_SIZE: u32 = 8;
+
+/// the push constants the compute present shader gets
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ComputePresentPushConstants {
+    /// the storage image slot of the swapchain image
+    pub target_image: u32,
+    /// the storage image slot of the hdr target the main pass drew in to
+    pub hdr_image: u32,
+    pub extent: [u32; 2],
+    /// 1 if the gpu converts the written colors to srgb, 2.2 otherwise, like for the tone mapping
+    pub gamma: f32,
+}
+
+pub(crate) struct ComputePresentPass {
+    device: Arc<VulkanDevice>,
+    /// how the swapchain was created, the pass is disabled with ``SwapchainStorage::None``
+    storage: SwapchainStorage,
+    pipeline: Option<vk::Pipeline>,
+}
+
+impl ComputePresentPass {
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        swapchain: &Swapchain,
+        bindless: &BindlessHandler,
+    ) -> Self {
+        let pass = Self {
+            device,
+            storage: swapchain.storage,
+            pipeline: None,
+        };
+
+        pass.on_resize(swapchain, bindless);
+        pass
+    }
+
+    /// write the new swapchain images or their intermediate images to the bindless descriptors, if the pass is enabled
+    /// the descriptor sets must not be in use
+    /// # Panics
+    /// if the swapchain has more images than ``BindlessHandler::MAX_SWAPCHAIN_IMAGES``
+    pub fn on_resize(&self, swapchain: &Swapchain, bindless: &BindlessHandler) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        assert!(
+            swapchain.images.len() <= BindlessHandler::MAX_SWAPCHAIN_IMAGES,
+            "too many swapchain images"
+        );
+
+        for (i, image) in swapchain.images.iter().enumerate() {
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                image
+                    .storage_target
+                    .as_ref()
+                    .map_or(image.main_view, |target| target.view),
+                BindlessHandler::SWAPCHAIN_TARGET_SLOT + i,
+            );
+        }
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.storage != SwapchainStorage::None
+    }
+
+    /// set the compute shader that writes the swapchain image
+    /// the shader module is not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_shader(
+        &mut self,
+        stage: vk::PipelineShaderStageCreateInfo,
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let create_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+
+        let pipeline = unsafe {
+            self.device
+                .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, err)| err)?[0]
+        };
+
+        if let Some(old) = self.pipeline.replace(pipeline) {
+            unsafe { self.device.destroy_pipeline(old, None) };
+        }
+
+        Ok(())
+    }
+
+    /// dispatch the shader over the swapchain image and leave it in ``PRESENT_SRC_KHR``
+    /// returns false if nothing was recorded, because the pass is disabled or has no shader,
+    /// then the swapchain image has to be written by the tone mapping
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        layout: vk::PipelineLayout,
+    ) -> bool {
+        let Some(pipeline) = self.pipeline.filter(|_| self.is_enabled()) else {
+            return false;
+        };
+
+        let device = &self.device;
+        let image = &swapchain.images[image_index as usize];
+        let extent = swapchain.get_image_extent();
+
+        let subresource_range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+
+        // the passes before wrote the hdr target
+        let hdr_barrier = vk::ImageMemoryBarrier2::default()
+            .image(image.hdr_image)
+            .old_layout(vk::ImageLayout::GENERAL)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .src_stage_mask(
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::COPY,
+            )
+            .src_access_mask(
+                vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE
+                    | vk::AccessFlags2::TRANSFER_WRITE,
+            )
+            .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_READ)
+            .subresource_range(subresource_range);
+
+        // the submit waits for the image to be acquired at the color attachment output stage,
+        // the intermediate image stays in ``GENERAL``, it was last read by the blit of an earlier frame
+        let to_general = match &image.storage_target {
+            Some(target) => vk::ImageMemoryBarrier2::default()
+                .image(target.image)
+                .old_layout(vk::ImageLayout::GENERAL)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_READ),
+            None => vk::ImageMemoryBarrier2::default()
+                .image(image.main_image)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT),
+        }
+        .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+        .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+        .subresource_range(subresource_range);
+
+        let barriers = [hdr_barrier, to_general];
+        device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+        );
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline);
+
+        let push_constants = ComputePresentPushConstants {
+            target_image: (BindlessHandler::SWAPCHAIN_TARGET_SLOT + image_index as usize) as u32,
+            hdr_image: (BindlessHandler::HDR_TARGET_SLOT + image_index as usize) as u32,
+            extent: [extent.width, extent.height],
+            gamma: if swapchain.is_srgb() { 1.0 } else { 2.2 },
+        };
+
+        let data = std::slice::from_raw_parts(
+            std::ptr::from_ref(&push_constants).cast::<u8>(),
+            size_of::<ComputePresentPushConstants>(),
+        );
+        device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+
+        device.cmd_dispatch(
+            cmd,
+            extent.width.div_ceil(COMPUTE_PRESENT_GROUP_SIZE),
+            extent.height.div_ceil(COMPUTE_PRESENT_GROUP_SIZE),
+            1,
+        );
+
+        let (old_layout, src_stage, src_access) = match &image.storage_target {
+            Some(target) => {
+                self.blit_target(
+                    cmd,
+                    target.image,
+                    image.main_image,
+                    extent,
+                    subresource_range,
+                );
+                (
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::PipelineStageFlags2::BLIT,
+                    vk::AccessFlags2::TRANSFER_WRITE,
+                )
+            }
+            None => (
+                vk::ImageLayout::GENERAL,
+                vk::PipelineStageFlags2::COMPUTE_SHADER,
+                vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            ),
+        };
+
+        // the outline and ui passes load the image, the submit signals the present semaphore after all of them
+        let to_present = vk::ImageMemoryBarrier2::default()
+            .image(image.main_image)
+            .old_layout(old_layout)
+            .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            )
+            .subresource_range(subresource_range);
+
+        device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&[to_present]),
+        );
+
+        true
+    }
+
+    /// copy the intermediate image the shader wrote on to the swapchain image and leave it in ``TRANSFER_DST_OPTIMAL``
+    /// the gpu converts the colors to the format of the swapchain
+    unsafe fn blit_target(
+        &self,
+        cmd: vk::CommandBuffer,
+        target: vk::Image,
+        swapchain_image: vk::Image,
+        extent: vk::Extent2D,
+        subresource_range: vk::ImageSubresourceRange,
+    ) {
+        let device = &self.device;
+
+        let barriers = [
+            vk::ImageMemoryBarrier2::default()
+                .image(target)
+                .old_layout(vk::ImageLayout::GENERAL)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+                .subresource_range(subresource_range),
+            // the submit waits for the image to be acquired at the color attachment output stage
+            vk::ImageMemoryBarrier2::default()
+                .image(swapchain_image)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .subresource_range(subresource_range),
+        ];
+        device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+        );
+
+        let subresource = vk::ImageSubresourceLayers::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .layer_count(1);
+        let corner = vk::Offset3D {
+            x: extent.width as i32,
+            y: extent.height as i32,
+            z: 1,
+        };
+        let region = vk::ImageBlit::default()
+            .src_subresource(subresource)
+            .src_offsets([vk::Offset3D::default(), corner])
+            .dst_subresource(subresource)
+            .dst_offsets([vk::Offset3D::default(), corner]);
+
+        device.cmd_blit_image(
+            cmd,
+            target,
+            vk::ImageLayout::GENERAL,
+            swapchain_image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+            vk::Filter::NEAREST,
+        );
+    }
+}
+
+impl Drop for ComputePresentPass {
+    fn drop(&mut self) {
+        if let Some(pipeline) = self.pipeline {
+            unsafe { self.device.destroy_pipeline(pipeline, None) };
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::vulkan::{AdapterSelection, AdapterWorkaround, PresentMode, ValidationLevel};
+
+use super::upscale::UpscaleFilter;
+
+/// settings that are fixed when the renderer is created
+#[derive(Debug, Clone)]
+pub struct RendererConfig {
+    /// the highest anisotropy any sampler can use
+    /// clamped to what the gpu supports, 1.0 disables anisotropic filtering
+    pub max_anisotropy: f32,
+    pub present_mode: PresentMode,
+    /// the samples per pixel of the main view targets
+    /// clamped to what the gpu supports, 1 disables msaa
+    pub msaa_samples: u32,
+    pub validation: ValidationLevel,
+    /// panic after a frame that caused a validation error, so tests fail on them, see ``VulkanDevice::check_validation``
+    pub panic_on_validation_error: bool,
+    /// leave markers between the passes of every frame, so the pass the gpu crashed in
+    /// can be logged when the device is lost, see ``RenderHandler::crash_report``
+    pub breadcrumbs: bool,
+    /// the threads of the job system, none uses one less than there are cores
+    pub workers: Option<usize>,
+    /// experimental global illumination from a voxel grid, see ``RenderHandler::set_gi_voxels``
+    pub gi: bool,
+    /// write the swapchain images with the compute shader of ``RenderHandler::set_compute_present_shader``
+    /// instead of tone mapping the hdr target, the swapchain images get storage usage for it,
+    /// or an intermediate image that is blitted on to them if the surface doesn't allow it
+    pub compute_present: bool,
+    /// the gpu to render on, the first discrete one by default
+    pub adapter: AdapterSelection,
+    /// turn off features that are broken on the chosen gpu, see ``AdapterInfo::vendor``
+    pub adapter_workaround: Option<AdapterWorkaround>,
+    /// render the six faces of an environment probe in one pass if ``DeviceFeatures::multiview`` is supported,
+    /// only materials with ``MaterialCreateInfo::multiview`` are drawn in to the probes then
+    pub multiview: bool,
+    /// render at this size instead of the window size and scale it in to the window, keeping the aspect ratio
+    /// with black bars around it, ignored with ``compute_present``
+    pub internal_resolution: Option<[u32; 2]>,
+    pub upscale_filter: UpscaleFilter,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            max_anisotropy: 16.0,
+            present_mode: PresentMode::default(),
+            msaa_samples: 1,
+            validation: ValidationLevel::default(),
+            panic_on_validation_error: false,
+            breadcrumbs: false,
+            workers: None,
+            gi: false,
+            compute_present: false,
+            adapter: AdapterSelection::Auto,
+            adapter_workaround: None,
+            multiview: false,
+            internal_resolution: None,
+            upscale_filter: UpscaleFilter::default(),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+
+use ash::{prelude::VkResult, vk};
+
+use super::{DestroyResource, FLYING_FRAMES};
+use crate::vulkan::GpuDevice;
+
+/// resources that might still be used by a frame on the gpu
+/// every resource remembers the last submission that could have used it,
+/// and is dropped once ``FLYING_FRAMES`` more frames were submitted, as every frame waits for the one
+/// that used its resources before
+/// frames that fail before they are submitted, like when the swapchain is out of date, don't count
+pub(crate) struct DestroyQueue<T = DestroyResource> {
+    /// the number of submitted frames, the next submission can still use everything that is pushed
+    frame: u64,
+    /// ordered by frame, resources are only ever pushed for the current frame
+    queue: VecDeque<(u64, T)>,
+}
+
+impl<T> DestroyQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            frame: 0,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// the resource is dropped once the next submitted frame has finished on the gpu
+    pub fn push(&mut self, resource: T) {
+        self.queue.push_back((self.frame, resource));
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// wait for the fence of the frame that used the same frame resources, then drop what is no longer used
+    /// called at the start of every frame
+    pub fn begin_frame(&mut self, device: &dyn GpuDevice, fence: vk::Fence) -> VkResult<()> {
+        device.wait_for_fence(fence)?;
+        self.retire();
+        Ok(())
+    }
+
+    /// record and submit a frame, it's only counted if ``submit`` succeeds
+    /// resources that ``submit`` pushes to the queue can still be used by the frame
+    pub fn submit<R>(&mut self, submit: impl FnOnce(&mut Self) -> VkResult<R>) -> VkResult<R> {
+        let result = submit(self)?;
+        self.submitted();
+        Ok(result)
+    }
+
+    /// a frame was submitted, must only be called after ``queue_submit`` succeeded
+    /// frames whose present failed after the submit aren't counted, that only keeps their resources a frame longer
+    fn submitted(&mut self) {
+        self.frame += 1;
+    }
+
+    /// drop every resource, only after waiting until the device is idle
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// drop the resources whose frames have finished, ``begin_frame`` also does this
+    pub fn retire(&mut self) {
+        while let Some(&(frame, _)) = self.queue.front() {
+            if frame + FLYING_FRAMES as u64 > self.frame {
+                break;
+            }
+            self.queue.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::Handle;
+
+    use super::*;
+    use crate::vulkan::mock::{DeviceCall, MockDevice};
+
+    #[test]
+    fn resources_wait_for_the_frames_in_flight() {
+        let mut queue = DestroyQueue::new();
+        queue.push("texture");
+
+        for _ in 0..FLYING_FRAMES {
+            queue.retire();
+            assert_eq!(queue.len(), 1);
+            queue.submitted();
+        }
+
+        queue.retire();
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn every_finished_resource_is_dropped() {
+        let mut queue = DestroyQueue::new();
+        // resources right after each other were skipped when they were removed by index
+        queue.push("buffer");
+        queue.push("image");
+        queue.push("view");
+        queue.submitted();
+        queue.push("pipeline");
+
+        for _ in 1..FLYING_FRAMES {
+            queue.submitted();
+        }
+        queue.retire();
+        assert_eq!(queue.len(), 1);
+
+        queue.submitted();
+        queue.retire();
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn retiring_without_a_submission_keeps_resources() {
+        let mut queue = DestroyQueue::new();
+        queue.push("buffer");
+
+        // frames that fail to acquire an image are retried without submitting anything
+        for _ in 0..FLYING_FRAMES * 2 {
+            queue.retire();
+        }
+        assert_eq!(queue.len(), 1);
+
+        queue.clear();
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn only_submitted_frames_retire_resources() {
+        let device = MockDevice::default();
+        let fence = vk::Fence::from_raw(1);
+        let mut queue = DestroyQueue::new();
+        queue.push("texture");
+
+        // the swapchain is out of date, the frames are recorded again without submitting anything
+        for _ in 0..FLYING_FRAMES {
+            queue.begin_frame(&device, fence).unwrap();
+            let result = queue.submit(|_| Err::<(), _>(vk::Result::ERROR_OUT_OF_DATE_KHR));
+            assert_eq!(result, Err(vk::Result::ERROR_OUT_OF_DATE_KHR));
+        }
+        assert_eq!(queue.len(), 1);
+
+        for submitted in 0..FLYING_FRAMES {
+            queue.begin_frame(&device, fence).unwrap();
+            assert_eq!(queue.len(), 1 + submitted);
+            queue
+                .submit(|queue| {
+                    queue.push("staging buffer");
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        // the texture and the staging buffer of the first frame are dropped, the newer frames might still be running
+        queue.begin_frame(&device, fence).unwrap();
+        assert_eq!(queue.len(), FLYING_FRAMES - 1);
+
+        // every frame waited for the fence before dropping anything
+        assert_eq!(
+            device.take_calls(),
+            vec![DeviceCall::WaitForFence(fence); FLYING_FRAMES * 2 + 1]
+        );
+    }
+}
@@ -0,0 +1,377 @@
+// copies a target of the renderer back to the cpu and writes it to an image file,
+// so the depth, the normals or the other intermediate targets can be looked at without a graphics debugger
+// the target is copied at the end of the next frame in to a buffer of its own,
+// the file is written once the fence of the frame has been waited on the next time it's used
+//
+// ``.exr`` files keep the values as they are, ``.png`` files clamp them to 0..1, both need the ``image`` feature
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{
+    Buffer, Swapchain, SwapchainImage, VulkanDevice, DEPTH_FORMAT, HDR_FORMAT, NORMAL_FORMAT,
+    OBJECT_ID_FORMAT, OIT_ACCUM_FORMAT, OIT_REVEALAGE_FORMAT, VELOCITY_FORMAT,
+};
+
+use super::FLYING_FRAMES;
+
+/// a target every swapchain image has, see ``RenderHandler::dump_image``
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpTarget {
+    /// the frame before tone mapping
+    Hdr,
+    Normal,
+    Depth,
+    Velocity,
+    OitAccum,
+    OitRevealage,
+    /// every id gets a color of its own, 0 is black
+    ObjectId,
+}
+
+impl DumpTarget {
+    pub const ALL: [Self; 7] = [
+        Self::Hdr,
+        Self::Normal,
+        Self::Depth,
+        Self::Velocity,
+        Self::OitAccum,
+        Self::OitRevealage,
+        Self::ObjectId,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hdr => "hdr",
+            Self::Normal => "normal",
+            Self::Depth => "depth",
+            Self::Velocity => "velocity",
+            Self::OitAccum => "oit_accum",
+            Self::OitRevealage => "oit_revealage",
+            Self::ObjectId => "object_id",
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|target| target.name() == name)
+    }
+
+    fn format(self) -> vk::Format {
+        match self {
+            Self::Hdr => HDR_FORMAT,
+            Self::Normal => NORMAL_FORMAT,
+            Self::Depth => DEPTH_FORMAT,
+            Self::Velocity => VELOCITY_FORMAT,
+            Self::OitAccum => OIT_ACCUM_FORMAT,
+            Self::OitRevealage => OIT_REVEALAGE_FORMAT,
+            Self::ObjectId => OBJECT_ID_FORMAT,
+        }
+    }
+
+    fn image(self, image: &SwapchainImage) -> vk::Image {
+        match self {
+            Self::Hdr => image.hdr_image,
+            Self::Normal => image.normal_image,
+            Self::Depth => image.depth_image,
+            Self::Velocity => image.velocity_image,
+            Self::OitAccum => image.accum_image,
+            Self::OitRevealage => image.revealage_image,
+            Self::ObjectId => image.object_id_image,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DumpError {
+    /// the file doesn't end with ``.png`` or ``.exr``
+    UnknownFileType(PathBuf),
+    /// the renderer was built without the ``image`` feature
+    NoImageSupport,
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFileType(path) => {
+                write!(
+                    f,
+                    "can't write {}, only png and exr are supported",
+                    path.display()
+                )
+            }
+            Self::NoImageSupport => write!(f, "images can't be written without the image feature"),
+        }
+    }
+}
+
+impl std::error::Error for DumpError {}
+
+/// a copy that is in flight
+struct Dump {
+    target: DumpTarget,
+    path: PathBuf,
+    extent: [u32; 2],
+    buffer: Arc<Buffer>,
+}
+
+pub(crate) struct ImageDumper {
+    device: Arc<VulkanDevice>,
+    /// copied by the next frame
+    requests: Vec<(DumpTarget, PathBuf)>,
+    in_flight: [Vec<Dump>; FLYING_FRAMES],
+}
+
+impl ImageDumper {
+    pub fn new(device: Arc<VulkanDevice>) -> Self {
+        Self {
+            device,
+            requests: vec![],
+            in_flight: std::array::from_fn(|_| vec![]),
+        }
+    }
+
+    /// # Errors
+    /// if the file type can't be written
+    pub fn request(&mut self, target: DumpTarget, path: PathBuf) -> Result<(), DumpError> {
+        check_file_type(&path)?;
+        self.requests.push((target, path));
+        Ok(())
+    }
+
+    /// write the files of the copies the frame recorded the last time it was used
+    /// the fence of the frame has to be signaled
+    pub fn collect(&mut self, frame_index: usize) {
+        for dump in self.in_flight[frame_index].drain(..) {
+            let [width, height] = dump.extent;
+            let size = width as usize * height as usize * format_size(dump.target.format());
+            let rgba = to_rgba(dump.target.format(), &dump.buffer.read::<u8>()[..size]);
+
+            match write_image(&dump.path, dump.extent, rgba) {
+                Ok(()) => log::info!(
+                    "wrote the {} target to {}",
+                    dump.target.name(),
+                    dump.path.display()
+                ),
+                Err(err) => log::error!("failed to write {}: {err}", dump.path.display()),
+            }
+        }
+    }
+
+    /// needs to be recorded at the end of the frame, every target is in the general layout then
+    /// # Errors
+    /// if there is no space left to allocate the buffers
+    pub unsafe fn record(
+        &mut self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        frame_index: usize,
+    ) -> VkResult<()> {
+        if self.requests.is_empty() {
+            return Ok(());
+        }
+
+        let extent = swapchain.get_image_extent();
+        let image = &swapchain.images[image_index as usize];
+
+        let range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+
+        // any pass of the frame might have written the targets
+        self.device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)
+                .src_access_mask(vk::AccessFlags2::MEMORY_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_READ),
+        );
+
+        for (target, path) in std::mem::take(&mut self.requests) {
+            let size = u64::from(extent.width)
+                * u64::from(extent.height)
+                * format_size(target.format()) as u64;
+            let buffer = Buffer::new(
+                self.device.clone(),
+                size,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+
+            let region = vk::BufferImageCopy::default()
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(range.aspect_mask)
+                        .layer_count(1),
+                )
+                .image_extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                });
+
+            self.device.cmd_copy_image_to_buffer(
+                cmd,
+                target.image(image),
+                vk::ImageLayout::GENERAL,
+                buffer.handle(),
+                &[region],
+            );
+
+            self.in_flight[frame_index].push(Dump {
+                target,
+                path,
+                extent: [extent.width, extent.height],
+                buffer,
+            });
+        }
+
+        self.device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+                .dst_access_mask(vk::AccessFlags2::HOST_READ),
+        );
+
+        Ok(())
+    }
+}
+
+fn check_file_type(path: &Path) -> Result<(), DumpError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png" | "exr") if cfg!(feature = "image") => Ok(()),
+        Some("png" | "exr") => Err(DumpError::NoImageSupport),
+        _ => Err(DumpError::UnknownFileType(path.to_path_buf())),
+    }
+}
+
+/// the bytes of a texel
+fn format_size(format: vk::Format) -> usize {
+    match format {
+        vk::Format::R32G32B32A32_SFLOAT => 16,
+        vk::Format::R16G16B16A16_SFLOAT => 8,
+        vk::Format::R32_SFLOAT | vk::Format::R32_UINT => 4,
+        vk::Format::R16_SFLOAT => 2,
+        format => unreachable!("{format:?} isn't the format of a target"),
+    }
+}
+
+/// the texels as rgba floats, single channel targets are gray
+fn to_rgba(format: vk::Format, bytes: &[u8]) -> Vec<f32> {
+    let texels = bytes.chunks_exact(format_size(format));
+    let f16 = |bytes: &[u8]| f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]));
+    let f32 = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    texels
+        .flat_map(|texel| match format {
+            vk::Format::R32G32B32A32_SFLOAT => [0, 4, 8, 12].map(|offset| f32(&texel[offset..])),
+            vk::Format::R16G16B16A16_SFLOAT => [0, 2, 4, 6].map(|offset| f16(&texel[offset..])),
+            vk::Format::R32_SFLOAT => {
+                let value = f32(texel);
+                [value, value, value, 1.0]
+            }
+            vk::Format::R16_SFLOAT => {
+                let value = f16(texel);
+                [value, value, value, 1.0]
+            }
+            vk::Format::R32_UINT => {
+                id_color(u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]))
+            }
+            format => unreachable!("{format:?} isn't the format of a target"),
+        })
+        .collect()
+}
+
+/// a color that is different for ids next to each other
+fn id_color(id: u32) -> [f32; 4] {
+    if id == 0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let hash = id.wrapping_mul(0x9E37_79B9);
+    let channel = |shift: u32| ((hash >> shift) & 0xFF) as f32 / 255.0;
+    [channel(24), channel(16), channel(8), 1.0]
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1F);
+    let mantissa = f32::from(bits & 0x3FF);
+
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1F if mantissa == 0.0 => f32::INFINITY,
+        0x1F => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(feature = "image")]
+fn write_image(path: &Path, extent: [u32; 2], rgba: Vec<f32>) -> Result<(), image::ImageError> {
+    let image = image::Rgba32FImage::from_raw(extent[0], extent[1], rgba)
+        .expect("the buffer has the size of the image");
+
+    if path.extension().is_some_and(|ext| ext == "exr") {
+        image.save(path)
+    } else {
+        image::DynamicImage::ImageRgba32F(image)
+            .into_rgba8()
+            .save(path)
+    }
+}
+
+/// ``request`` doesn't accept any file without the feature
+#[cfg(not(feature = "image"))]
+fn write_image(_: &Path, _: [u32; 2], _: Vec<f32>) -> Result<(), DumpError> {
+    Err(DumpError::NoImageSupport)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(f16_to_f32(0x3C00), 1.0);
+        assert_eq!(f16_to_f32(0xC000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x7C00), f32::INFINITY);
+        assert!(f16_to_f32(0x7E00).is_nan());
+    }
+
+    #[test]
+    fn single_channel_targets_are_gray() {
+        let bytes: Vec<u8> = [0.5f32, 2.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(
+            to_rgba(DEPTH_FORMAT, &bytes),
+            [0.5, 0.5, 0.5, 1.0, 2.0, 2.0, 2.0, 1.0]
+        );
+
+        let ids: Vec<u8> = [0u32, 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let rgba = to_rgba(OBJECT_ID_FORMAT, &ids);
+        assert_eq!(rgba[..4], [0.0, 0.0, 0.0, 1.0]);
+        assert_ne!(rgba[4..7], [0.0; 3]);
+    }
+
+    #[test]
+    fn targets_round_trip_their_names() {
+        for target in DumpTarget::ALL {
+            assert_eq!(DumpTarget::from_name(target.name()), Some(target));
+        }
+        assert!(matches!(
+            check_file_type(Path::new("depth.bmp")),
+            Err(DumpError::UnknownFileType(_))
+        ));
+    }
+}
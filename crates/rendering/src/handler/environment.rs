@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::vulkan::{Buffer, VulkanDevice};
+
+use super::{
+    bindless::{BindlessHandler, BindlessResourceHandle, BindlessResourceType},
+    FLYING_FRAMES,
+};
+
+/// scene wide lighting and fog settings
+/// this is uploaded every frame to the uniform buffer at ``BindlessHandler::ENVIRONMENT_SLOT``
+/// the layout needs to match ``shaders/environment.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Environment {
+    /// the direction the sun light travels in, w is unused
+    pub sun_direction: [f32; 4],
+    /// w is the intensity
+    pub sun_color: [f32; 4],
+    /// light that reaches every surface, w is the intensity
+    pub ambient_color: [f32; 4],
+    /// w is the density of the fog
+    pub fog_color: [f32; 4],
+    /// below this height the fog has its full density
+    pub fog_height: f32,
+    /// how fast the fog fades out above ``fog_height``
+    pub fog_falloff: f32,
+    _padding: [f32; 2],
+}
+crate::shader_struct!(
+    Environment,
+    Std140 {
+        sun_direction,
+        sun_color,
+        ambient_color,
+        fog_color,
+        fog_height,
+        fog_falloff,
+    }
+);
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            sun_direction: [-0.4, -0.6, -0.3, 0.0],
+            sun_color: [1.0, 0.95, 0.9, 1.0],
+            ambient_color: [0.5, 0.6, 0.7, 0.2],
+            fog_color: [0.6, 0.7, 0.8, 0.0],
+            fog_height: 0.0,
+            fog_falloff: 1.0,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// holds one uniform buffer for every frame in flight
+/// so we never write to a buffer the gpu is still reading from
+pub(crate) struct EnvironmentHandler {
+    pub environment: Environment,
+    buffers: [Arc<Buffer>; FLYING_FRAMES],
+}
+
+impl EnvironmentHandler {
+    /// # Panics
+    /// if there is no space to allocate the buffers
+    pub fn new(device: &Arc<VulkanDevice>, bindless: &BindlessHandler) -> Self {
+        let buffers: [Arc<Buffer>; FLYING_FRAMES] = std::array::from_fn(|_| {
+            Buffer::new(
+                device.clone(),
+                size_of::<Environment>() as u64,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            )
+            .unwrap()
+        });
+
+        let handle = BindlessResourceHandle::reserved(
+            BindlessHandler::ENVIRONMENT_SLOT,
+            BindlessResourceType::UniformBuffer,
+        );
+
+        bindless.set_per_frame_buffer(&**device, buffers.each_ref().map(|v| v.handle()), handle);
+
+        Self {
+            environment: Environment::default(),
+            buffers,
+        }
+    }
+
+    /// write the environment to the buffer of this frame
+    /// the frame must not be executing on the gpu
+    pub fn upload(&self, frame_index: usize) {
+        self.buffers[frame_index].write_shader(0, &[self.environment]);
+    }
+}
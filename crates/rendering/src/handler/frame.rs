@@ -1,10 +1,38 @@
-use super::{bindless::BindlessHandler, material::MaterialHandler, render_batch::RenderBatch};
+use super::{
+    bindless::BindlessHandler,
+    breadcrumbs::Breadcrumbs,
+    buffer_updates::BufferUpdates,
+    compute_present::ComputePresentPass,
+    dump::ImageDumper,
+    gi::GiPass,
+    material::MaterialHandler,
+    oit::OitResolve,
+    outline::OutlinePass,
+    particles::ParticleSystem,
+    picking::ObjectPicker,
+    probes::ProbePass,
+    raw_pass::{RawPasses, RecordCtx, SwapchainInfo},
+    render_batch::{record_batches, RenderBatch},
+    scene_cache::SceneCache,
+    sprites::SpriteBatch,
+    ssao::Ssao,
+    static_batches::{begin_secondary, StaticBatches, StaticTarget},
+    taa::TemporalAa,
+    tonemap::Tonemapper,
+    ui::UiPainter,
+    upscale::Upscaler,
+    volumetric::VolumetricPass,
+};
 use crate::vulkan::{Swapchain, VulkanDevice};
+use allocators::StackAllocator;
 use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
 
+/// the size of the cpu side scratch memory every frame has, in bytes
+const FRAME_SCRATCH_SIZE: usize = 64 * 1024;
+
 pub struct FrameContext {
     /// tells if this ``FrameContext`` is currently executing
     pub is_executing_fence: vk::Fence,
@@ -12,9 +40,26 @@ pub struct FrameContext {
     image_available_semaphore: vk::Semaphore,
     /// tells when the render has finished and is ready to be presented
     render_finished_semaphore: vk::Semaphore,
+    /// chain the submits of the frame when the work moves to another queue, see ``SubmitBuilder``
+    /// they are created when a frame needs more than before and reused after that
+    chain_semaphores: Vec<vk::Semaphore>,
 
     command_pool: vk::CommandPool,
     command_buffer: vk::CommandBuffer,
+    /// the main pass without the static batches, only used if there are static batches, see ``StaticBatches``
+    secondary_command_buffer: vk::CommandBuffer,
+
+    /// a timestamp at the start and end of the command buffer
+    query_pool: vk::QueryPool,
+    /// nanoseconds per timestamp tick, ``None`` if the queue doesn't support timestamps
+    timestamp_period: Option<f32>,
+    /// the timestamps are only valid once the frame has been submitted
+    timestamps_written: bool,
+
+    /// temporary cpu data used while recording, like sort keys
+    /// everything is freed once the frame has been recorded
+    scratch: StackAllocator,
+    scratch_memory: *mut u8,
 }
 
 impl FrameContext {
@@ -38,24 +83,83 @@ impl FrameContext {
 
         let command_buffer = device.allocate_command_buffers(&command_buffer_info)?[0];
 
+        let secondary_info = command_buffer_info.level(vk::CommandBufferLevel::SECONDARY);
+        let secondary_command_buffer = device.allocate_command_buffers(&secondary_info)?[0];
+
         // device.begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::default())?;
         // device.end_command_buffer(command_buffer)?;
 
+        let query_info = vk::QueryPoolCreateInfo::default()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(2);
+        let query_pool = device.create_query_pool(&query_info, None)?;
+
+        let scratch_memory = std::alloc::alloc(Self::scratch_layout());
+        assert!(
+            !scratch_memory.is_null(),
+            "failed to allocate frame scratch memory"
+        );
+
         Ok(Self {
             is_executing_fence,
             image_available_semaphore,
             render_finished_semaphore,
+            chain_semaphores: vec![],
             command_pool,
             command_buffer,
+            secondary_command_buffer,
+            query_pool,
+            timestamp_period: timestamp_period(device),
+            timestamps_written: false,
+            scratch: StackAllocator::new(scratch_memory.cast(), FRAME_SCRATCH_SIZE),
+            scratch_memory,
         })
     }
 
+    fn scratch_layout() -> std::alloc::Layout {
+        std::alloc::Layout::from_size_align(FRAME_SCRATCH_SIZE, 16).unwrap()
+    }
+
+    /// the cpu scratch memory of the frame, for data that is only needed before the frame is recorded
+    pub(crate) fn scratch(&mut self) -> &mut StackAllocator {
+        &mut self.scratch
+    }
+
     pub unsafe fn destroy(&self, device: &VulkanDevice) {
         let _ = device.wait_for_fences(&[self.is_executing_fence], true, u64::MAX);
         device.destroy_fence(self.is_executing_fence, None);
         device.destroy_semaphore(self.image_available_semaphore, None);
         device.destroy_semaphore(self.render_finished_semaphore, None);
+        for &semaphore in &self.chain_semaphores {
+            device.destroy_semaphore(semaphore, None);
+        }
         device.destroy_command_pool(self.command_pool, None);
+        device.destroy_query_pool(self.query_pool, None);
+        std::alloc::dealloc(self.scratch_memory, Self::scratch_layout());
+    }
+
+    /// how long the gpu took for the last submission of this frame
+    /// the fence of the frame has to be signaled
+    pub unsafe fn gpu_time(&self, device: &VulkanDevice) -> Option<std::time::Duration> {
+        let period = self.timestamp_period?;
+        if !self.timestamps_written {
+            return None;
+        }
+
+        let mut timestamps = [0u64; 2];
+        device
+            .get_query_pool_results(
+                self.query_pool,
+                0,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+            .ok()?;
+
+        let ticks = timestamps[1].wrapping_sub(timestamps[0]);
+        Some(std::time::Duration::from_nanos(
+            (ticks as f64 * f64::from(period)) as u64,
+        ))
     }
 
     unsafe fn request_image_index(&self, swapchain: &Swapchain) -> VkResult<(u32, bool)> {
@@ -68,31 +172,48 @@ impl FrameContext {
     }
 
     unsafe fn submit(
-        &self,
+        &mut self,
         device: &VulkanDevice,
         swapchain: &Swapchain,
         image_index: u32,
+        present_id: u64,
     ) -> VkResult<()> {
-        let wait_semaphores = [self.image_available_semaphore];
-        let signal_semaphores = [self.render_finished_semaphore];
-        let command_buffers = [self.command_buffer];
+        let mut submits = SubmitBuilder::default();
 
-        let submits = [vk::SubmitInfo::default()
-            .command_buffers(&command_buffers)
-            .wait_semaphores(&wait_semaphores)
-            .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
-            .signal_semaphores(&signal_semaphores)];
+        // the swapchain image is either written by the tonemap pass or a blit
+        submits.wait(
+            self.image_available_semaphore,
+            vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags2::TRANSFER,
+        );
+        submits.add(
+            device.queues.graphics.1,
+            self.command_buffer,
+            vk::PipelineStageFlags2::ALL_COMMANDS,
+        );
+        submits.signal(
+            self.render_finished_semaphore,
+            vk::PipelineStageFlags2::ALL_COMMANDS,
+        );
 
-        device.queue_submit(device.queues.graphics.1, &submits, self.is_executing_fence)?;
+        submits.submit(device, &mut self.chain_semaphores, self.is_executing_fence)?;
 
+        let signal_semaphores = [self.render_finished_semaphore];
         let swapchains = [swapchain.handle];
         let image_indices = [image_index];
 
-        let present_info = vk::PresentInfoKHR::default()
+        let present_ids = [present_id];
+        let mut present_id_info = vk::PresentIdKHR::default().present_ids(&present_ids);
+
+        let mut present_info = vk::PresentInfoKHR::default()
             .wait_semaphores(&signal_semaphores)
             .swapchains(&swapchains)
             .image_indices(&image_indices);
 
+        // present ids can only be used if present wait is enabled
+        if swapchain.present_wait.is_some() {
+            present_info = present_info.push_next(&mut present_id_info);
+        }
+
         swapchain
             .loader
             .queue_present(device.queues.graphics.1, &present_info)?;
@@ -100,88 +221,306 @@ impl FrameContext {
         Ok(())
     }
 
+    /// returns true if the frame reused the scene, see ``SceneCache``
+    #[allow(clippy::too_many_arguments)]
     pub unsafe fn execute(
-        &self,
+        &mut self,
         device: &VulkanDevice,
         materials: &MaterialHandler,
         swapchain: &mut Swapchain,
         batches: &[RenderBatch],
+        particles: &[ParticleSystem],
+        sprites: &[SpriteBatch],
+        picker: &mut ObjectPicker,
+        dumper: &mut ImageDumper,
         bindless_handler: &BindlessHandler,
+        oit: &OitResolve,
+        ssao: &Ssao,
+        taa: &TemporalAa,
+        gi: &GiPass,
+        probes: &mut ProbePass,
+        volumetric: &VolumetricPass,
+        tonemapper: &Tonemapper,
+        compute_present: &ComputePresentPass,
+        outline: &OutlinePass,
+        upscaler: &Upscaler,
+        ui: &UiPainter,
+        raw_passes: &RawPasses,
+        static_batches: &mut StaticBatches,
+        buffer_updates: &mut BufferUpdates,
+        scene_cache: &mut SceneCache,
+        breadcrumbs: &mut Breadcrumbs,
         frame_index: usize,
-    ) -> VkResult<()> {
-        // wait for the commandbuffer to finish executing before resetting it
-        device.wait_for_fences(&[self.is_executing_fence], true, u64::MAX)?;
+        present_id: u64,
+    ) -> VkResult<bool> {
+        let _span = tracing::info_span!("execute frame").entered();
 
-        let (image_index, _suboptimal) = self.request_image_index(swapchain)?;
+        let image_index = {
+            let _span = tracing::info_span!("acquire image").entered();
 
-        // if there is still being rendered to the image, then we need to wait
-        let wait_fence = &mut swapchain.images[image_index as usize].available;
-        if !wait_fence.is_null() {
-            device.wait_for_fences(&[*wait_fence], true, u64::MAX)?;
-        }
-        *wait_fence = self.is_executing_fence;
+            // wait for the commandbuffer to finish executing before resetting it
+            device.wait_for_fences(&[self.is_executing_fence], true, u64::MAX)?;
+
+            let (image_index, _suboptimal) = self.request_image_index(swapchain)?;
+
+            // if there is still being rendered to the image, then we need to wait
+            let wait_fence = &mut swapchain.images[image_index as usize].available;
+            if !wait_fence.is_null() {
+                device.wait_for_fences(&[*wait_fence], true, u64::MAX)?;
+            }
+            *wait_fence = self.is_executing_fence;
+            image_index
+        };
 
         device.reset_fences(&[self.is_executing_fence])?;
         device.reset_command_buffer(self.command_buffer, vk::CommandBufferResetFlags::empty())?;
 
+        let reuse_scene = scene_cache.begin_frame(image_index);
+
         self.record_command_buffer(
             device,
             materials,
             swapchain,
             image_index,
             batches,
+            particles,
+            sprites,
+            picker,
+            dumper,
             bindless_handler,
+            oit,
+            ssao,
+            taa,
+            gi,
+            probes,
+            volumetric,
+            tonemapper,
+            compute_present,
+            outline,
+            upscaler,
+            ui,
+            raw_passes,
+            static_batches,
+            buffer_updates,
+            breadcrumbs,
             frame_index,
+            reuse_scene,
         )?;
 
-        self.submit(device, swapchain, image_index)?;
-        Ok(())
+        let _span = tracing::info_span!("submit").entered();
+        self.submit(device, swapchain, image_index, present_id)?;
+        self.timestamps_written = true;
+        Ok(reuse_scene)
     }
 
     #[allow(clippy::too_many_arguments)]
     unsafe fn record_command_buffer(
-        &self,
+        &mut self,
         device: &VulkanDevice,
         materials: &MaterialHandler,
         swapchain: &Swapchain,
         image_index: u32,
         batches: &[RenderBatch],
+        particles: &[ParticleSystem],
+        sprites: &[SpriteBatch],
+        picker: &mut ObjectPicker,
+        dumper: &mut ImageDumper,
         bindless_handler: &BindlessHandler,
+        oit: &OitResolve,
+        ssao: &Ssao,
+        taa: &TemporalAa,
+        gi: &GiPass,
+        probes: &mut ProbePass,
+        volumetric: &VolumetricPass,
+        tonemapper: &Tonemapper,
+        compute_present: &ComputePresentPass,
+        outline: &OutlinePass,
+        upscaler: &Upscaler,
+        ui: &UiPainter,
+        raw_passes: &RawPasses,
+        static_batches: &mut StaticBatches,
+        buffer_updates: &mut BufferUpdates,
+        breadcrumbs: &mut Breadcrumbs,
         frame_index: usize,
+        reuse_scene: bool,
     ) -> VkResult<()> {
+        let _span = tracing::info_span!("record commands").entered();
+
         let command_buffer = self.command_buffer;
+        let layout = bindless_handler.pipeline_layout;
 
         device.begin_command_buffer(self.command_buffer, &vk::CommandBufferBeginInfo::default())?;
+        breadcrumbs.reset();
+
+        if self.timestamp_period.is_some() {
+            device.cmd_reset_query_pool(command_buffer, self.query_pool, 0, 2);
+            device.write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags2::TOP_OF_PIPE,
+                self.query_pool,
+                0,
+            );
+        }
 
         // bind bindless descriptor set
-        device.cmd_bind_descriptor_sets(
-            self.command_buffer,
+        for bind_point in [
             vk::PipelineBindPoint::GRAPHICS,
-            bindless_handler.pipeline_layout,
-            0,
-            &[bindless_handler.descriptor_sets[frame_index]],
-            &[],
-        );
+            vk::PipelineBindPoint::COMPUTE,
+        ] {
+            device.cmd_bind_descriptor_sets(
+                self.command_buffer,
+                bind_point,
+                layout,
+                0,
+                &[bindless_handler.descriptor_sets[frame_index]],
+                &[],
+            );
+        }
+
+        let image = &swapchain.images[image_index as usize];
+        let ctx = RecordCtx {
+            device,
+            command_buffer,
+            frame_index,
+            pipeline_layout: layout,
+            descriptor_set: bindless_handler.descriptor_sets[frame_index],
+            swapchain: SwapchainInfo {
+                image_index,
+                image: image.main_image,
+                view: image.main_view,
+                format: swapchain.image_format(),
+                extent: swapchain.present_extent(),
+                render_extent: swapchain.get_image_extent(),
+                hdr_image: image.hdr_image,
+                hdr_view: image.hdr_view,
+            },
+        };
+
+        // everything after this sees the new data
+        breadcrumbs.mark(device, command_buffer, || "buffer updates".to_owned());
+        buffer_updates.record(device, command_buffer);
+        raw_passes.record("buffer updates", &ctx, breadcrumbs);
+
+        if reuse_scene {
+            // the hdr target still holds the scene from the last frame that rendered to this image
+            breadcrumbs.mark(device, command_buffer, || "reused scene".to_owned());
+        } else {
+            self.record_scene(
+                &ctx,
+                materials,
+                swapchain,
+                batches,
+                particles,
+                sprites,
+                bindless_handler,
+                gi,
+                probes,
+                raw_passes,
+                static_batches,
+                breadcrumbs,
+            )?;
+        }
+
+        breadcrumbs.mark(device, command_buffer, || "object picking".to_owned());
+        picker.record(command_buffer, swapchain, image_index, frame_index);
+        raw_passes.record("object picking", &ctx, breadcrumbs);
+        let ao_image = if reuse_scene {
+            ssao.output()
+        } else {
+            breadcrumbs.mark(device, command_buffer, || "oit resolve".to_owned());
+            oit.record(
+                command_buffer,
+                swapchain,
+                image_index,
+                layout,
+                batches,
+                materials.view_layers,
+            );
+            raw_passes.record("oit resolve", &ctx, breadcrumbs);
+            breadcrumbs.mark(device, command_buffer, || "volumetrics".to_owned());
+            volumetric.record(command_buffer, swapchain, image_index, layout);
+            raw_passes.record("volumetrics", &ctx, breadcrumbs);
+            breadcrumbs.mark(device, command_buffer, || "ssao".to_owned());
+            let ao_image = ssao.record(command_buffer, image_index, layout);
+            raw_passes.record("ssao", &ctx, breadcrumbs);
+            breadcrumbs.mark(device, command_buffer, || "taa".to_owned());
+            taa.record(command_buffer, swapchain, image_index, layout);
+            raw_passes.record("taa", &ctx, breadcrumbs);
+            ao_image
+        };
+        breadcrumbs.mark(device, command_buffer, || "tonemap".to_owned());
+        // the compute present shader replaces the tone mapping once it's set
+        if !compute_present.record(command_buffer, swapchain, image_index, layout) {
+            tonemapper.record(command_buffer, swapchain, image_index, layout, ao_image);
+        }
+        raw_passes.record("tonemap", &ctx, breadcrumbs);
+        breadcrumbs.mark(device, command_buffer, || "outline".to_owned());
+        outline.record(command_buffer, swapchain, image_index, layout);
+        raw_passes.record("outline", &ctx, breadcrumbs);
+        breadcrumbs.mark(device, command_buffer, || "upscale".to_owned());
+        upscaler.record(command_buffer, swapchain, image_index);
+        raw_passes.record("upscale", &ctx, breadcrumbs);
+        breadcrumbs.mark(device, command_buffer, || "ui".to_owned());
+        ui.record(command_buffer, swapchain, image_index, frame_index, layout);
+        raw_passes.record("ui", &ctx, breadcrumbs);
+        breadcrumbs.mark(device, command_buffer, || "image dump".to_owned());
+        dumper.record(command_buffer, swapchain, image_index, frame_index)?;
+
+        // everything in front of this marker finished
+        breadcrumbs.mark(device, command_buffer, || "end of frame".to_owned());
+
+        if self.timestamp_period.is_some() {
+            device.write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+                self.query_pool,
+                1,
+            );
+        }
+
+        device.end_command_buffer(self.command_buffer)?;
+        Ok(())
+    }
+
+    /// the compute passes the main pass depends on and the main pass itself, they draw the scene to the hdr target
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn record_scene(
+        &mut self,
+        ctx: &RecordCtx,
+        materials: &MaterialHandler,
+        swapchain: &Swapchain,
+        batches: &[RenderBatch],
+        particles: &[ParticleSystem],
+        sprites: &[SpriteBatch],
+        bindless_handler: &BindlessHandler,
+        gi: &GiPass,
+        probes: &mut ProbePass,
+        raw_passes: &RawPasses,
+        static_batches: &mut StaticBatches,
+        breadcrumbs: &mut Breadcrumbs,
+    ) -> VkResult<()> {
+        let device = ctx.device;
+        let command_buffer = ctx.command_buffer;
+        let layout = ctx.pipeline_layout;
+        let frame_index = ctx.frame_index;
+        let image_index = ctx.swapchain.image_index;
+
+        // compute work can't be done inside a render pass
+        breadcrumbs.mark(device, command_buffer, || "particle update".to_owned());
+        for system in particles {
+            system.record_update(device, command_buffer, layout);
+        }
+        raw_passes.record("particle update", ctx, breadcrumbs);
+        breadcrumbs.mark(device, command_buffer, || "gi propagation".to_owned());
+        gi.record(command_buffer);
+        raw_passes.record("gi propagation", ctx, breadcrumbs);
+        breadcrumbs.mark(device, command_buffer, || "environment probes".to_owned());
+        probes.record(command_buffer, layout, materials, batches);
+        raw_passes.record("environment probes", ctx, breadcrumbs);
 
         let render_area = vk::Rect2D::default().extent(swapchain.get_image_extent());
 
-        let clear_values = [
-            vk::ClearValue {
-                color: vk::ClearColorValue {
-                    float32: [0.1, 0.1, 0.1, 0.0],
-                },
-            },
-            vk::ClearValue {
-                color: vk::ClearColorValue {
-                    float32: [0.0, 0.0, 0.0, 0.0],
-                },
-            },
-            vk::ClearValue {
-                color: vk::ClearColorValue {
-                    float32: [0.0, 0.0, 0.0, 0.0],
-                },
-            },
-        ];
+        let clear_values = materials.clear_values();
 
         let begin_info = vk::RenderPassBeginInfo::default()
             .render_pass(materials.main_renderpass)
@@ -189,14 +528,323 @@ impl FrameContext {
             .render_area(render_area)
             .clear_values(&clear_values);
 
-        device.cmd_begin_render_pass(command_buffer, &begin_info, vk::SubpassContents::INLINE);
+        let static_target = StaticTarget {
+            render_pass: materials.main_renderpass,
+            extent: render_area.extent,
+            layout,
+            descriptor_set: bindless_handler.descriptor_sets[frame_index],
+            set_version: bindless_handler.set_version(frame_index),
+            frame_index,
+            layers: materials.view_layers,
+        };
+        let mut static_commands = static_batches.prepare(batches, &static_target)?;
+
+        // with secondary command buffers the primary one can't record anything else inside of the render pass
+        breadcrumbs.mark(device, command_buffer, || {
+            let names: Vec<_> = batches
+                .iter()
+                .filter(|batch| batch.is_visible_in(materials.view_layers))
+                .map(|batch| batch.name().unwrap_or("unnamed"))
+                .collect();
+            format!("main pass ({})", names.join(", "))
+        });
+
+        let pass_commands = if static_commands.is_empty() {
+            device.cmd_begin_render_pass(command_buffer, &begin_info, vk::SubpassContents::INLINE);
+            command_buffer
+        } else {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &begin_info,
+                vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
+            );
+            begin_secondary(device, self.secondary_command_buffer, &static_target)?;
+            self.secondary_command_buffer
+        };
+
+        // sort the batches so every pipeline only needs to be bound once
+        let arena = self.scratch.scope();
+        let order = arena.alloc_slice_fill_with(batches.len(), |_| (0, 0));
+        record_batches(
+            device,
+            pass_commands,
+            layout,
+            batches,
+            order,
+            render_area.extent,
+            materials.view_layers,
+        );
+
+        breadcrumbs.mark(device, pass_commands, || "particle draw".to_owned());
+        for system in particles {
+            system.record_draw(device, pass_commands, layout, render_area.extent);
+        }
+
+        breadcrumbs.mark(device, pass_commands, || "sprites".to_owned());
+        for batch in sprites {
+            batch.record(
+                device,
+                pass_commands,
+                layout,
+                render_area.extent,
+                frame_index,
+            );
+        }
 
-        for batch in batches {
-            batch.execute(device, command_buffer);
+        if pass_commands != command_buffer {
+            device.end_command_buffer(pass_commands)?;
+            // the dynamic batches come last, so the sorted transparent ones are drawn over everything
+            static_commands.push(pass_commands);
+            device.cmd_execute_commands(command_buffer, &static_commands);
         }
 
         device.cmd_end_render_pass(command_buffer);
-        device.end_command_buffer(self.command_buffer)?;
+        raw_passes.record("main pass", ctx, breadcrumbs);
+
         Ok(())
     }
 }
+
+/// command buffers that are submitted together
+#[derive(Debug, Default)]
+struct SubmitBatch {
+    queue: vk::Queue,
+    waits: Vec<vk::SemaphoreSubmitInfo<'static>>,
+    command_buffers: Vec<vk::CommandBufferSubmitInfo<'static>>,
+    signals: Vec<vk::SemaphoreSubmitInfo<'static>>,
+}
+
+/// collects the command buffers of a frame in the order they have to execute
+/// and submits them with as few ``vkQueueSubmit2`` calls as possible, see ``VulkanDevice::submit``
+///
+/// command buffers that follow each other on the same queue go into the same batch,
+/// the queue executes them in order so they only need barriers between them
+/// when the work moves to another queue, the batches are chained with a semaphore
+/// all batches of a queue that follow each other are submitted with a single call
+#[derive(Debug, Default)]
+pub(crate) struct SubmitBuilder {
+    batches: Vec<SubmitBatch>,
+    /// the waits of the next command buffer
+    pending_waits: Vec<vk::SemaphoreSubmitInfo<'static>>,
+    /// the batches that wait on the batch before them and the stage that waits
+    chained: Vec<(usize, vk::PipelineStageFlags2)>,
+}
+
+impl SubmitBuilder {
+    /// the next command buffer waits for the semaphore before it starts ``stage``
+    pub fn wait(&mut self, semaphore: vk::Semaphore, stage: vk::PipelineStageFlags2) {
+        self.pending_waits.push(
+            vk::SemaphoreSubmitInfo::default()
+                .semaphore(semaphore)
+                .stage_mask(stage),
+        );
+    }
+
+    /// ``stage`` is the first stage that depends on the work before it,
+    /// it's only used when the work before ran on another queue
+    pub fn add(
+        &mut self,
+        queue: vk::Queue,
+        command_buffer: vk::CommandBuffer,
+        stage: vk::PipelineStageFlags2,
+    ) {
+        let info = vk::CommandBufferSubmitInfo::default().command_buffer(command_buffer);
+
+        // a batch that signals is done, the work after it must not be part of the signal
+        if let Some(last) = self.batches.last_mut() {
+            if last.queue == queue && last.signals.is_empty() && self.pending_waits.is_empty() {
+                last.command_buffers.push(info);
+                return;
+            }
+
+            if last.queue != queue {
+                self.chained.push((self.batches.len(), stage));
+            }
+        }
+
+        self.batches.push(SubmitBatch {
+            queue,
+            waits: std::mem::take(&mut self.pending_waits),
+            command_buffers: vec![info],
+            signals: vec![],
+        });
+    }
+
+    /// the semaphore is signaled once all command buffers added until now have finished ``stage``
+    /// # Panics
+    /// if no command buffer was added
+    pub fn signal(&mut self, semaphore: vk::Semaphore, stage: vk::PipelineStageFlags2) {
+        let last = self
+            .batches
+            .last_mut()
+            .expect("there is nothing to signal after");
+
+        last.signals.push(
+            vk::SemaphoreSubmitInfo::default()
+                .semaphore(semaphore)
+                .stage_mask(stage),
+        );
+    }
+
+    /// put the semaphores between the batches that run on different queues
+    /// ``semaphores`` needs at least one semaphore for every link of the chain
+    fn link(&mut self, semaphores: &[vk::Semaphore]) {
+        for (&(index, stage), &semaphore) in self.chained.iter().zip(semaphores) {
+            self.batches[index - 1].signals.push(
+                vk::SemaphoreSubmitInfo::default()
+                    .semaphore(semaphore)
+                    .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS),
+            );
+            self.batches[index].waits.push(
+                vk::SemaphoreSubmitInfo::default()
+                    .semaphore(semaphore)
+                    .stage_mask(stage),
+            );
+        }
+    }
+
+    /// the batches grouped into the ``vkQueueSubmit2`` calls
+    fn calls(&self) -> impl Iterator<Item = &[SubmitBatch]> {
+        self.batches.chunk_by(|a, b| a.queue == b.queue)
+    }
+
+    /// ``semaphores`` are reused for the chain, more are created if they aren't enough
+    /// they must not be in use by the gpu anymore
+    /// the fence is signaled once the last call has finished
+    pub unsafe fn submit(
+        mut self,
+        device: &VulkanDevice,
+        semaphores: &mut Vec<vk::Semaphore>,
+        fence: vk::Fence,
+    ) -> VkResult<()> {
+        while semaphores.len() < self.chained.len() {
+            semaphores.push(device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?);
+        }
+        self.link(semaphores);
+
+        let call_count = self.calls().count();
+        for (i, batches) in self.calls().enumerate() {
+            let submits: Vec<_> = batches
+                .iter()
+                .map(|batch| {
+                    vk::SubmitInfo2::default()
+                        .wait_semaphore_infos(&batch.waits)
+                        .command_buffer_infos(&batch.command_buffers)
+                        .signal_semaphore_infos(&batch.signals)
+                })
+                .collect();
+
+            let fence = if i + 1 == call_count {
+                fence
+            } else {
+                vk::Fence::null()
+            };
+            device.submit(batches[0].queue, &submits, fence)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ``None`` if the graphics queue can't write timestamps
+unsafe fn timestamp_period(device: &VulkanDevice) -> Option<f32> {
+    let props = device
+        .instance
+        .get_physical_device_properties(device.pdevice);
+    let queue_families = device
+        .instance
+        .get_physical_device_queue_family_properties(device.pdevice);
+
+    let valid_bits = queue_families
+        .get(device.queues.graphics.0 as usize)
+        .map_or(0, |family| family.timestamp_valid_bits);
+
+    (valid_bits > 0).then_some(props.limits.timestamp_period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(raw: u64) -> vk::Queue {
+        vk::Queue::from_raw(raw)
+    }
+
+    fn cmd(raw: u64) -> vk::CommandBuffer {
+        vk::CommandBuffer::from_raw(raw)
+    }
+
+    fn semaphores(infos: &[vk::SemaphoreSubmitInfo]) -> Vec<u64> {
+        infos.iter().map(|info| info.semaphore.as_raw()).collect()
+    }
+
+    #[test]
+    fn same_queue_is_one_batch() {
+        let mut submits = SubmitBuilder::default();
+        for raw in 1..=3 {
+            submits.add(queue(1), cmd(raw), vk::PipelineStageFlags2::ALL_COMMANDS);
+        }
+
+        let calls: Vec<_> = submits.calls().collect();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].len(), 1);
+        assert_eq!(calls[0][0].command_buffers.len(), 3);
+        assert!(submits.chained.is_empty());
+    }
+
+    #[test]
+    fn queues_are_chained_with_semaphores() {
+        let transfer = queue(1);
+        let graphics = queue(2);
+        let acquired = vk::Semaphore::from_raw(100);
+
+        let mut submits = SubmitBuilder::default();
+        submits.add(transfer, cmd(1), vk::PipelineStageFlags2::ALL_COMMANDS);
+        submits.add(transfer, cmd(2), vk::PipelineStageFlags2::ALL_COMMANDS);
+        submits.add(graphics, cmd(3), vk::PipelineStageFlags2::VERTEX_INPUT);
+        // waiting on a semaphore splits the batch, but not the call
+        submits.wait(acquired, vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT);
+        submits.add(graphics, cmd(4), vk::PipelineStageFlags2::ALL_COMMANDS);
+        submits.add(transfer, cmd(5), vk::PipelineStageFlags2::TRANSFER);
+
+        submits.link(&[10, 11].map(vk::Semaphore::from_raw));
+
+        let calls: Vec<_> = submits.calls().collect();
+        assert_eq!(calls.iter().map(|c| c.len()).collect::<Vec<_>>(), [1, 2, 1]);
+
+        let [transfer_batch] = calls[0] else { panic!() };
+        let [first, second] = calls[1] else { panic!() };
+        let [last] = calls[2] else { panic!() };
+
+        assert_eq!(transfer_batch.command_buffers.len(), 2);
+        assert_eq!(semaphores(&transfer_batch.signals), [10]);
+
+        assert_eq!(semaphores(&first.waits), [10]);
+        assert_eq!(
+            first.waits[0].stage_mask,
+            vk::PipelineStageFlags2::VERTEX_INPUT
+        );
+        assert!(first.signals.is_empty());
+
+        assert_eq!(semaphores(&second.waits), [100]);
+        assert_eq!(semaphores(&second.signals), [11]);
+        assert_eq!(semaphores(&last.waits), [11]);
+    }
+
+    #[test]
+    fn nothing_is_added_to_a_signaling_batch() {
+        let mut submits = SubmitBuilder::default();
+        submits.add(queue(1), cmd(1), vk::PipelineStageFlags2::ALL_COMMANDS);
+        submits.signal(
+            vk::Semaphore::from_raw(5),
+            vk::PipelineStageFlags2::ALL_COMMANDS,
+        );
+        submits.add(queue(1), cmd(2), vk::PipelineStageFlags2::ALL_COMMANDS);
+
+        let calls: Vec<_> = submits.calls().collect();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].len(), 2);
+        assert_eq!(semaphores(&calls[0][0].signals), [5]);
+        assert!(calls[0][1].signals.is_empty());
+    }
+}
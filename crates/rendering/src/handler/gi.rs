@@ -0,0 +1,430 @@
+// experimental global illumination from a low resolution voxel grid, enabled with ``RendererConfig::gi``
+// the application voxelizes its scene in to albedo and occupancy with ``RenderHandler::set_gi_voxels``,
+// then a compute shader propagates light through the grid once per frame, before the main pass
+//
+// every cell takes the average light of its neighbors from the last frame, so light spreads one cell per frame
+// filled cells add the sun and reflect what reaches them with their albedo, the edges of the grid see the sky
+//
+// the radiance ping pongs between two 3d storage images, materials read the one written this frame,
+// the parameters are in a storage buffer at ``BindlessHandler::GI_SLOT``, see ``shaders/gi.slang`` in the application
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Buffer, MemoryBlock, VulkanDevice};
+
+use super::{
+    bindless::{BindlessHandler, BindlessResourceHandle, BindlessResourceType},
+    FLYING_FRAMES,
+};
+
+/// the cells of the grid along every axis
+pub const GI_RESOLUTION: u32 = 64;
+/// the amount of cells in the grid
+pub const GI_CELLS: usize = (GI_RESOLUTION * GI_RESOLUTION * GI_RESOLUTION) as usize;
+
+/// needs to match ``WORK_GROUP_SIZE`` in ``shaders/gi.slang``
+const WORK_GROUP_SIZE: u32 = 4;
+
+const RADIANCE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GiSettings {
+    /// scales the indirect light materials get from the grid
+    pub intensity: f32,
+    /// how much of the light that reaches a filled cell is passed on, below 1 so the light fades out
+    pub bounce: f32,
+}
+
+impl Default for GiSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            bounce: 0.8,
+        }
+    }
+}
+
+/// the box in world space the grid covers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GiVolume {
+    /// the corner with the lowest coordinates
+    pub origin: [f32; 3],
+    /// the size of one cell, the grid is ``GI_RESOLUTION * cell_size`` wide
+    pub cell_size: f32,
+}
+
+impl Default for GiVolume {
+    /// the box from -1 to 1, the space an octree covers
+    fn default() -> Self {
+        Self {
+            origin: [-1.0; 3],
+            cell_size: 2.0 / GI_RESOLUTION as f32,
+        }
+    }
+}
+
+/// needs to match ``GiParams`` in ``shaders/gi.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct GpuGiParams {
+    origin: [f32; 3],
+    cell_size: f32,
+    resolution: u32,
+    /// the storage image written this frame, materials read it
+    radiance_image: u32,
+    /// the storage image written last frame
+    previous_image: u32,
+    /// 0 until the first voxels were set
+    enabled: u32,
+    intensity: f32,
+    bounce: f32,
+    _padding: [f32; 2],
+}
+
+struct RadianceVolume {
+    /// freed after the image, when the pass is dropped
+    _memory: MemoryBlock,
+    image: vk::Image,
+    view: vk::ImageView,
+}
+
+/// the grid and the buffers that only exist with ``RendererConfig::gi``
+struct GiResources {
+    radiance: [RadianceVolume; 2],
+    /// the voxels as rgba8 albedo with the occupancy in alpha, one buffer per frame in flight
+    voxel_buffers: [Arc<Buffer>; FLYING_FRAMES],
+    voxels: Vec<u32>,
+    /// the frames whose voxel buffer is older than ``voxels``
+    dirty: [bool; FLYING_FRAMES],
+}
+
+pub(crate) struct GiPass {
+    device: Arc<VulkanDevice>,
+    pub settings: GiSettings,
+    volume: GiVolume,
+    pipeline: Option<vk::Pipeline>,
+    params_buffers: [Arc<Buffer>; FLYING_FRAMES],
+    resources: Option<GiResources>,
+    /// the radiance image written this frame
+    target: usize,
+}
+
+impl GiPass {
+    /// the parameters are always created, so materials can check if there is any indirect light
+    /// # Errors
+    /// if there is no space to allocate the buffers or the grid
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        bindless: &BindlessHandler,
+        enabled: bool,
+    ) -> VkResult<Self> {
+        let params_buffer = || {
+            Buffer::new(
+                device.clone(),
+                size_of::<GpuGiParams>() as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            )
+        };
+        let params_buffers = [params_buffer()?, params_buffer()?];
+
+        let handle = BindlessResourceHandle::reserved(
+            BindlessHandler::GI_SLOT,
+            BindlessResourceType::StorageBuffer,
+        );
+        bindless.set_per_frame_buffer(
+            &*device,
+            params_buffers.each_ref().map(|v| v.handle()),
+            handle,
+        );
+
+        let resources = if enabled {
+            Some(create_resources(&device, bindless)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            device,
+            settings: GiSettings::default(),
+            volume: GiVolume::default(),
+            pipeline: None,
+            params_buffers,
+            resources,
+            target: 0,
+        })
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.resources.is_some()
+    }
+
+    /// set the compute shader that propagates the light, it's dispatched once per frame
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_shader(
+        &mut self,
+        stage: vk::PipelineShaderStageCreateInfo,
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let create_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+
+        let pipeline = unsafe {
+            self.device
+                .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, err)| err)?[0]
+        };
+
+        if let Some(old) = self.pipeline.replace(pipeline) {
+            unsafe { self.device.destroy_pipeline(old, None) };
+        }
+
+        Ok(())
+    }
+
+    /// replace the voxels of the grid, ``voxels`` has ``GI_CELLS`` rgba8 colors with x changing fastest, then y
+    /// the alpha is the occupancy, 0 is empty
+    /// does nothing without ``RendererConfig::gi``
+    /// # Panics
+    /// if there aren't ``GI_CELLS`` voxels
+    pub fn set_voxels(&mut self, volume: GiVolume, voxels: &[[u8; 4]]) {
+        assert_eq!(voxels.len(), GI_CELLS, "the grid needs GI_CELLS voxels");
+
+        let Some(resources) = &mut self.resources else {
+            return;
+        };
+
+        self.volume = volume;
+        resources.voxels = voxels
+            .iter()
+            .map(|&voxel| u32::from_le_bytes(voxel))
+            .collect();
+        resources.dirty = [true; FLYING_FRAMES];
+    }
+
+    /// write the parameters and the voxels that changed to the buffers of this frame
+    /// the fence of the frame has to be signaled
+    pub fn upload(&mut self, frame_index: usize) {
+        let mut params = GpuGiParams {
+            intensity: self.settings.intensity,
+            bounce: self.settings.bounce,
+            ..Default::default()
+        };
+
+        if let Some(resources) = &mut self.resources {
+            if std::mem::take(&mut resources.dirty[frame_index]) {
+                resources.voxel_buffers[frame_index].write(0, &resources.voxels);
+            }
+
+            params = GpuGiParams {
+                origin: self.volume.origin,
+                cell_size: self.volume.cell_size,
+                resolution: GI_RESOLUTION,
+                radiance_image: (BindlessHandler::GI_RADIANCE_SLOT + self.target) as u32,
+                previous_image: (BindlessHandler::GI_RADIANCE_SLOT + 1 - self.target) as u32,
+                enabled: u32::from(!resources.voxels.is_empty() && self.pipeline.is_some()),
+                ..params
+            };
+        }
+
+        self.params_buffers[frame_index].write(0, &[params]);
+    }
+
+    /// needs to be recorded before the main pass, which reads the grid
+    pub unsafe fn record(&self, cmd: vk::CommandBuffer) {
+        let (Some(pipeline), Some(resources)) = (self.pipeline, &self.resources) else {
+            return;
+        };
+        if resources.voxels.is_empty() {
+            return;
+        }
+
+        let device = &self.device;
+
+        // the image written now was read by the materials two frames ago
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(
+                    vk::PipelineStageFlags2::FRAGMENT_SHADER
+                        | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                )
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ),
+        );
+
+        // the shader gets everything from the bindless buffers, there are no push constants
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline);
+        let groups = GI_RESOLUTION.div_ceil(WORK_GROUP_SIZE);
+        device.cmd_dispatch(cmd, groups, groups, groups);
+
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(
+                    vk::PipelineStageFlags2::FRAGMENT_SHADER
+                        | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                )
+                .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_READ),
+        );
+    }
+
+    /// swap the radiance images, called once the frame is submitted
+    pub fn advance(&mut self) {
+        if self.resources.is_some() {
+            self.target = 1 - self.target;
+        }
+    }
+}
+
+impl Drop for GiPass {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(pipeline) = self.pipeline {
+                self.device.destroy_pipeline(pipeline, None);
+            }
+            if let Some(resources) = &self.resources {
+                for volume in &resources.radiance {
+                    self.device.destroy_image_view(volume.view, None);
+                    self.device.destroy_image(volume.image, None);
+                }
+            }
+        }
+    }
+}
+
+fn create_resources(
+    device: &Arc<VulkanDevice>,
+    bindless: &BindlessHandler,
+) -> VkResult<GiResources> {
+    let radiance = [
+        create_volume(device, RADIANCE_FORMAT)?,
+        create_volume(device, RADIANCE_FORMAT)?,
+    ];
+
+    let range = vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1);
+
+    // the first frame reads the previous image, so both start out black
+    let barriers = radiance.each_ref().map(|volume| {
+        vk::ImageMemoryBarrier2::default()
+            .image(volume.image)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .dst_stage_mask(vk::PipelineStageFlags2::CLEAR)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .subresource_range(range)
+    });
+
+    unsafe {
+        device.immediate_submit(|cmd| {
+            device.pipeline_barrier(
+                cmd,
+                &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+            );
+            for volume in &radiance {
+                device.cmd_clear_color_image(
+                    cmd,
+                    volume.image,
+                    vk::ImageLayout::GENERAL,
+                    &vk::ClearColorValue::default(),
+                    &[range],
+                );
+            }
+        })?;
+    }
+
+    for (i, volume) in radiance.iter().enumerate() {
+        bindless.set_storage_image_all_sets(
+            &**device,
+            volume.view,
+            BindlessHandler::GI_RADIANCE_SLOT + i,
+        );
+    }
+
+    let voxel_buffer = || {
+        Buffer::new(
+            device.clone(),
+            (GI_CELLS * size_of::<u32>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+    };
+    let voxel_buffers = [voxel_buffer()?, voxel_buffer()?];
+
+    let handle = BindlessResourceHandle::reserved(
+        BindlessHandler::GI_VOXELS_SLOT,
+        BindlessResourceType::StorageBuffer,
+    );
+    bindless.set_per_frame_buffer(
+        &**device,
+        voxel_buffers.each_ref().map(|v| v.handle()),
+        handle,
+    );
+
+    Ok(GiResources {
+        radiance,
+        voxel_buffers,
+        voxels: vec![],
+        dirty: [false; FLYING_FRAMES],
+    })
+}
+
+/// a ``GI_RESOLUTION`` cube that is used as a storage image
+fn create_volume(device: &Arc<VulkanDevice>, format: vk::Format) -> VkResult<RadianceVolume> {
+    let image_info = vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_3D)
+        .format(format)
+        .extent(vk::Extent3D {
+            width: GI_RESOLUTION,
+            height: GI_RESOLUTION,
+            depth: GI_RESOLUTION,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_DST);
+
+    unsafe {
+        let image = device.create_image(&image_info, None)?;
+
+        let memory_requirements = device.get_image_memory_requirements(image);
+        let memory = MemoryBlock::new(
+            device.clone(),
+            memory_requirements,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        device.bind_image_memory(image, memory.handle(), 0)?;
+
+        let view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_3D)
+            .format(format)
+            .subresource_range(
+                vk::ImageSubresourceRange::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .level_count(1)
+                    .layer_count(1),
+            );
+        let view = device.create_image_view(&view_info, None)?;
+
+        Ok(RadianceVolume {
+            _memory: memory,
+            image,
+            view,
+        })
+    }
+}
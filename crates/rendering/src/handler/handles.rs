@@ -0,0 +1,185 @@
+// opaque handles to resources the ``RenderHandler`` owns, they are destroyed with ``RenderHandler::destroy``
+// the renderer keeps the resources alive until the frames that used them have finished,
+// so a handle can be destroyed at any time, everything left is destroyed with the renderer
+//
+// a handle is an index and a generation, when a resource is destroyed its index is reused
+// with the next generation, so an old handle can't point to the new resource
+
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::{assets::texture::TextureHandle, vulkan::Buffer};
+
+use super::bindless::BindlessResourceHandle;
+
+macro_rules! handle {
+    ($(#[$meta:meta])* $name:ident, $variant:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name {
+            index: u32,
+            generation: u32,
+        }
+
+        impl Handle for $name {
+            fn new(index: u32, generation: u32) -> Self {
+                Self { index, generation }
+            }
+
+            fn index(self) -> usize {
+                self.index as usize
+            }
+
+            fn generation(self) -> u32 {
+                self.generation
+            }
+        }
+
+        impl From<$name> for ResourceHandle {
+            fn from(handle: $name) -> Self {
+                Self::$variant(handle)
+            }
+        }
+    };
+}
+
+handle!(
+    /// a buffer created with ``RenderHandler::create_buffer`` or ``RenderHandler::import_buffer``
+    BufferHandle,
+    Buffer
+);
+handle!(
+    /// a shader module created with ``RenderHandler::load_shader``
+    ShaderHandle,
+    Shader
+);
+handle!(
+    /// a material created with ``RenderHandler::create_material``
+    MaterialHandle,
+    Material
+);
+
+/// any resource that can be given to ``RenderHandler::destroy``
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceHandle {
+    Buffer(BufferHandle),
+    Texture(TextureHandle),
+    Shader(ShaderHandle),
+    Material(MaterialHandle),
+}
+
+impl From<TextureHandle> for ResourceHandle {
+    fn from(handle: TextureHandle) -> Self {
+        Self::Texture(handle)
+    }
+}
+
+pub(crate) trait Handle: Copy {
+    fn new(index: u32, generation: u32) -> Self;
+    fn index(self) -> usize;
+    fn generation(self) -> u32;
+}
+
+/// a buffer owned by the renderer and the bindless slots it's bound to
+pub(crate) struct OwnedBuffer {
+    pub buffer: Arc<Buffer>,
+    pub bindings: Vec<BindlessResourceHandle>,
+}
+
+struct Entry<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// a list of values that are found by a handle, removed indices are reused with the next generation
+pub(crate) struct HandleMap<H, T> {
+    entries: Vec<Entry<T>>,
+    free: Vec<u32>,
+    marker: PhantomData<H>,
+}
+
+impl<H: Handle, T> HandleMap<H, T> {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![],
+            free: vec![],
+            marker: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> H {
+        if let Some(index) = self.free.pop() {
+            let entry = &mut self.entries[index as usize];
+            entry.value = Some(value);
+            return H::new(index, entry.generation);
+        }
+
+        self.entries.push(Entry {
+            generation: 0,
+            value: Some(value),
+        });
+        H::new(self.entries.len() as u32 - 1, 0)
+    }
+
+    pub fn get(&self, handle: H) -> Option<&T> {
+        self.entries
+            .get(handle.index())
+            .filter(|entry| entry.generation == handle.generation())?
+            .value
+            .as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: H) -> Option<&mut T> {
+        self.entries
+            .get_mut(handle.index())
+            .filter(|entry| entry.generation == handle.generation())?
+            .value
+            .as_mut()
+    }
+
+    /// none if the handle was already removed
+    pub fn remove(&mut self, handle: H) -> Option<T> {
+        let entry = self
+            .entries
+            .get_mut(handle.index())
+            .filter(|entry| entry.generation == handle.generation())?;
+        let value = entry.value.take()?;
+
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free.push(handle.index() as u32);
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() - self.free.len()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().filter_map(|entry| entry.value.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_handles_stay_invalid() {
+        let mut map = HandleMap::<BufferHandle, _>::new();
+        let first = map.insert("first");
+        let second = map.insert("second");
+
+        assert_eq!(map.remove(first), Some("first"));
+        assert_eq!(map.remove(first), None);
+        assert_eq!(map.get(first), None);
+
+        // the index is reused, the old handle doesn't point to the new value
+        let third = map.insert("third");
+        assert_eq!(third.index(), first.index());
+        assert_ne!(third, first);
+        assert_eq!(map.get(first), None);
+        assert_eq!(map.get(third), Some(&"third"));
+        assert_eq!(map.get(second), Some(&"second"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.values().count(), 2);
+    }
+}
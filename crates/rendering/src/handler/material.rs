@@ -1,176 +1,362 @@
 use std::{io::Cursor, sync::Arc};
 
 use ash::{prelude::VkResult, vk};
+use math::Color;
 
 use crate::{
     types::{Material, MaterialCreateInfo},
-    vulkan::{Swapchain, VulkanDevice},
+    vulkan::{
+        Swapchain, VulkanDevice, DEPTH_FORMAT, HDR_FORMAT, MAX_VIEWS, NORMAL_FORMAT,
+        OBJECT_ID_FORMAT, OIT_ACCUM_FORMAT, OIT_REVEALAGE_FORMAT, VELOCITY_FORMAT,
+    },
 };
 
+use super::{render_batch::ALL_LAYERS, views::view_mask};
+
+/// what happens with the contents of a render target at the start of the frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LoadOp {
+    /// fill it with the clear color
+    #[default]
+    Clear,
+    /// keep what was rendered to it, useful for incremental rendering
+    /// every swapchain image has its own targets, so this is the frame that last used the same swapchain image
+    Load,
+    /// the contents are undefined, the fastest option if everything is drawn over anyway
+    DontCare,
+}
+
+impl From<LoadOp> for vk::AttachmentLoadOp {
+    fn from(value: LoadOp) -> Self {
+        match value {
+            LoadOp::Clear => Self::CLEAR,
+            LoadOp::Load => Self::LOAD,
+            LoadOp::DontCare => Self::DONT_CARE,
+        }
+    }
+}
+
+/// how the targets of the main view are loaded, see ``RenderHandler::set_load_ops``
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ViewLoadOps {
+    /// the hdr color target
+    pub color: LoadOp,
+    /// the depth and normal targets, they are always cleared to 0
+    pub depth: LoadOp,
+}
+
+/// how many targets the main pass draws to, the object id target is only attached if it's supported
+pub(crate) fn main_pass_target_count(device: &VulkanDevice) -> usize {
+    if device.features.object_ids {
+        7
+    } else {
+        6
+    }
+}
+
 pub(crate) struct MaterialHandler {
     device: Arc<VulkanDevice>,
     pub main_renderpass: vk::RenderPass,
+    /// the main pass with a view for every face of a cube, for the environment probes
+    /// only exists with ``RendererConfig::multiview``, materials with ``MaterialCreateInfo::multiview`` get a pipeline for it
+    pub multiview_renderpass: Option<vk::RenderPass>,
     pub framebuffers: Vec<vk::Framebuffer>,
     pub materials: Vec<Arc<Material>>,
+    /// the color the hdr target is cleared with
+    pub clear_color: Color,
+    pub load_ops: ViewLoadOps,
+    /// the main view only draws the batches on these layers, see ``RenderBatch::set_layers``
+    pub view_layers: u32,
 }
 
 impl MaterialHandler {
-    pub fn new(device: Arc<VulkanDevice>, swapchain: &Swapchain) -> VkResult<Self> {
-        let attachment_desc = vk::AttachmentDescription::default()
-            .load_op(vk::AttachmentLoadOp::CLEAR)
-            .store_op(vk::AttachmentStoreOp::STORE)
-            .format(vk::Format::R32G32B32A32_SFLOAT)
-            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-            .samples(vk::SampleCountFlags::TYPE_1);
-
-        let attachments = [
-            vk::AttachmentDescription {
-                initial_layout: vk::ImageLayout::UNDEFINED,
-                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
-                format: swapchain.image_format(),
-                ..attachment_desc
-            },
-            vk::AttachmentDescription {
-                initial_layout: vk::ImageLayout::UNDEFINED,
-                final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-                ..attachment_desc
-            },
-            vk::AttachmentDescription {
-                initial_layout: vk::ImageLayout::UNDEFINED,
-                final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-                format: vk::Format::R32_SFLOAT,
-                ..attachment_desc
-            },
-        ];
+    /// ``multiview`` is ignored if the device doesn't support it
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        swapchain: &Swapchain,
+        multiview: bool,
+    ) -> VkResult<Self> {
+        let load_ops = ViewLoadOps::default();
+        let main_renderpass =
+            unsafe { create_render_pass(&device, load_ops, swapchain.samples, 0)? };
+        let framebuffers = unsafe { create_framebuffers(&device, main_renderpass, swapchain) };
 
-        let color_attachments_ref = [
-            vk::AttachmentReference {
-                attachment: 0,
-                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            },
-            vk::AttachmentReference {
-                attachment: 1,
-                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            },
-            vk::AttachmentReference {
-                attachment: 2,
-                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            },
-        ];
-
-        let subpass_dependencies = [vk::SubpassDependency::default()
-            .src_subpass(vk::SUBPASS_EXTERNAL)
-            .dst_subpass(0)
-            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-            .src_access_mask(vk::AccessFlags::NONE)
-            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)];
-
-        let subpasses = [vk::SubpassDescription::default()
-            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-            .color_attachments(&color_attachments_ref)];
-
-        let renderpass_info = vk::RenderPassCreateInfo::default()
-            .attachments(&attachments)
-            .dependencies(&subpass_dependencies)
-            .subpasses(&subpasses);
-
-        let swapchain_res = swapchain.get_image_extent();
-
-        let main_renderpass = unsafe { device.create_render_pass(&renderpass_info, None)? };
-
-        let framebuffer_info = vk::FramebufferCreateInfo::default()
-            .render_pass(main_renderpass)
-            .width(swapchain_res.width)
-            .height(swapchain_res.height)
-            .layers(1);
-
-        let framebuffers = unsafe {
-            swapchain
-                .images
-                .iter()
-                .map(|v| {
-                    let attachments = [v.main_view, v.normal_view, v.depth_view];
-                    device
-                        .create_framebuffer(
-                            &vk::FramebufferCreateInfo {
-                                p_attachments: attachments.as_ptr(),
-                                attachment_count: attachments.len() as u32,
-                                ..framebuffer_info
-                            },
-                            None,
-                        )
-                        .unwrap()
-                })
-                .collect()
+        let multiview_renderpass = if multiview && device.features.multiview {
+            let mask = view_mask(MAX_VIEWS);
+            Some(unsafe { create_render_pass(&device, load_ops, swapchain.samples, mask)? })
+        } else {
+            None
         };
 
         Ok(Self {
             device,
             main_renderpass,
+            multiview_renderpass,
             framebuffers,
             materials: vec![],
+            clear_color: Color::rgba(0.1, 0.1, 0.1, 0.0),
+            load_ops,
+            view_layers: ALL_LAYERS,
         })
     }
 
-    pub fn on_resize(&mut self, swapchain: &Swapchain, layout: vk::PipelineLayout) {
-        let new_size = swapchain.create_info.image_extent;
-
+    pub fn on_resize(&mut self, swapchain: &Swapchain) {
         for buffer in self.framebuffers.drain(..) {
             unsafe { self.device.destroy_framebuffer(buffer, None) };
         }
 
-        let framebuffer_info = vk::FramebufferCreateInfo::default()
-            .render_pass(self.main_renderpass)
-            .width(new_size.width)
-            .height(new_size.height)
-            .layers(1);
-
-        self.framebuffers = unsafe {
-            swapchain
-                .images
-                .iter()
-                .map(|v| {
-                    let attachments = [v.main_view, v.normal_view, v.depth_view];
-                    self.device
-                        .create_framebuffer(
-                            &vk::FramebufferCreateInfo {
-                                p_attachments: attachments.as_ptr(),
-                                attachment_count: attachments.len() as u32,
-                                ..framebuffer_info
-                            },
-                            None,
-                        )
-                        .unwrap()
-                })
-                .collect()
-        };
+        self.framebuffers =
+            unsafe { create_framebuffers(&self.device, self.main_renderpass, swapchain) };
+    }
 
-        for p_material in &mut self.materials {
-            // if the size is absolute then we don't need to recreate it
-            if p_material.info.viewport.scale != [0.0, 0.0] {
-                let material = unsafe { Arc::get_mut_unchecked(p_material) };
-                unsafe { self.device.destroy_pipeline(material.pipeline, None) };
-
-                let new = material.info.build(
-                    &self.device,
-                    self.main_renderpass,
-                    layout,
-                    [new_size.width, new_size.height],
-                );
+    /// recreate the render pass with different load ops
+    /// render passes that only differ in load ops are compatible, so the materials don't need to be rebuilt
+    /// # Safety
+    /// the render pass must not be in use
+    pub unsafe fn set_load_ops(
+        &mut self,
+        load_ops: ViewLoadOps,
+        swapchain: &Swapchain,
+    ) -> VkResult<()> {
+        let renderpass = create_render_pass(&self.device, load_ops, swapchain.samples, 0)?;
 
-                *material = new;
-            }
+        for buffer in self.framebuffers.drain(..) {
+            self.device.destroy_framebuffer(buffer, None);
         }
+        self.device.destroy_render_pass(self.main_renderpass, None);
+
+        self.main_renderpass = renderpass;
+        self.framebuffers = create_framebuffers(&self.device, renderpass, swapchain);
+        self.load_ops = load_ops;
+        Ok(())
+    }
+
+    /// the clear values of the main pass targets, in the order they are attached
+    pub fn clear_values(&self) -> [vk::ClearValue; 7] {
+        [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: self.clear_color.to_array(),
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            // nothing covers the background yet
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [1.0, 0.0, 0.0, 0.0],
+                },
+            },
+            // no object
+            vk::ClearValue {
+                color: vk::ClearColorValue { uint32: [0; 4] },
+            },
+        ]
     }
 }
 
+/// with msaa the batches are drawn in to multisampled targets,
+/// which are resolved in to the normal ones at the end of the pass
+/// a ``view_mask`` other than 0 draws in to every layer of the targets it has a bit set for, see ``view_mask``
+pub(crate) unsafe fn create_render_pass(
+    device: &VulkanDevice,
+    load_ops: ViewLoadOps,
+    samples: vk::SampleCountFlags,
+    view_mask: u32,
+) -> VkResult<vk::RenderPass> {
+    let attachment_desc = vk::AttachmentDescription::default()
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .format(vk::Format::R32G32B32A32_SFLOAT)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .samples(vk::SampleCountFlags::TYPE_1);
+
+    // loaded targets are still in the layout of the last frame,
+    // the swapchain puts them in that layout when they are created
+    let initial_layout = |load_op: LoadOp, layout| match load_op {
+        LoadOp::Load => layout,
+        LoadOp::Clear | LoadOp::DontCare => vk::ImageLayout::UNDEFINED,
+    };
+
+    let formats = [
+        HDR_FORMAT,
+        NORMAL_FORMAT,
+        DEPTH_FORMAT,
+        VELOCITY_FORMAT,
+        OIT_ACCUM_FORMAT,
+        OIT_REVEALAGE_FORMAT,
+        OBJECT_ID_FORMAT,
+    ];
+    // the post processing passes read every target as a storage image
+    let final_layouts = [vk::ImageLayout::GENERAL; 7];
+    // the velocity, transparency and object id targets only describe the current frame, so they're always cleared
+    let load_ops = [
+        load_ops.color,
+        load_ops.depth,
+        load_ops.depth,
+        LoadOp::Clear,
+        LoadOp::Clear,
+        LoadOp::Clear,
+        LoadOp::Clear,
+    ];
+
+    let msaa = samples != vk::SampleCountFlags::TYPE_1;
+    let count = main_pass_target_count(device);
+
+    let mut attachments: Vec<_> = (0..count)
+        .map(|i| {
+            // the multisampled targets stay in the attachment layout
+            let final_layout = if msaa {
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+            } else {
+                final_layouts[i]
+            };
+
+            vk::AttachmentDescription {
+                load_op: load_ops[i].into(),
+                initial_layout: initial_layout(load_ops[i], final_layout),
+                final_layout,
+                format: formats[i],
+                samples,
+                ..attachment_desc
+            }
+        })
+        .collect();
+
+    if msaa {
+        // the resolve targets are overwritten completely
+        attachments.extend((0..count).map(|i| vk::AttachmentDescription {
+            load_op: vk::AttachmentLoadOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout: final_layouts[i],
+            format: formats[i],
+            ..attachment_desc
+        }));
+    }
+
+    let attachment_ref = |attachment| vk::AttachmentReference {
+        attachment,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+
+    let color_attachments_ref: Vec<_> = (0..count as u32).map(attachment_ref).collect();
+    // integer targets are resolved by picking one of the samples
+    let resolve_attachments_ref: Vec<_> = (count as u32..count as u32 * 2)
+        .map(attachment_ref)
+        .collect();
+
+    let subpass_dependencies = [vk::SubpassDependency::default()
+        .src_subpass(vk::SUBPASS_EXTERNAL)
+        .dst_subpass(0)
+        .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .src_access_mask(vk::AccessFlags::NONE)
+        .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)];
+
+    let mut subpass = vk::SubpassDescription::default()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_attachments_ref);
+
+    if msaa {
+        subpass = subpass.resolve_attachments(&resolve_attachments_ref);
+    }
+
+    let subpasses = [subpass];
+
+    // the views are rendered from close positions, so they are likely to see the same things
+    let view_masks = [view_mask];
+    let mut multiview_info = vk::RenderPassMultiviewCreateInfo::default()
+        .view_masks(&view_masks)
+        .correlation_masks(&view_masks);
+
+    let mut renderpass_info = vk::RenderPassCreateInfo::default()
+        .attachments(&attachments)
+        .dependencies(&subpass_dependencies)
+        .subpasses(&subpasses);
+
+    if view_mask != 0 {
+        renderpass_info = renderpass_info.push_next(&mut multiview_info);
+    }
+
+    device.create_render_pass(&renderpass_info, None)
+}
+
+unsafe fn create_framebuffers(
+    device: &VulkanDevice,
+    renderpass: vk::RenderPass,
+    swapchain: &Swapchain,
+) -> Vec<vk::Framebuffer> {
+    let size = swapchain.get_image_extent();
+    let count = main_pass_target_count(device);
+
+    let framebuffer_info = vk::FramebufferCreateInfo::default()
+        .render_pass(renderpass)
+        .width(size.width)
+        .height(size.height)
+        .layers(1);
+
+    swapchain
+        .images
+        .iter()
+        .map(|v| {
+            let mut attachments = vec![
+                v.hdr_view,
+                v.normal_view,
+                v.depth_view,
+                v.velocity_view,
+                v.accum_view,
+                v.revealage_view,
+                v.object_id_view,
+            ];
+            attachments.truncate(count);
+            if let Some(msaa) = &v.msaa {
+                let resolve = std::mem::replace(
+                    &mut attachments,
+                    msaa.targets().map(|target| target.view)[..count].to_vec(),
+                );
+                attachments.extend(resolve);
+            }
+
+            device
+                .create_framebuffer(
+                    &vk::FramebufferCreateInfo {
+                        p_attachments: attachments.as_ptr(),
+                        attachment_count: attachments.len() as u32,
+                        ..framebuffer_info
+                    },
+                    None,
+                )
+                .unwrap()
+        })
+        .collect()
+}
+
 impl Drop for MaterialHandler {
     fn drop(&mut self) {
         unsafe {
             for mat in &self.materials {
                 self.device.destroy_pipeline(mat.pipeline, None);
+                if let Some(pipeline) = mat.multiview_pipeline {
+                    self.device.destroy_pipeline(pipeline, None);
+                }
                 self.device
                     .destroy_shader_module(mat.info.shaders[0].module, None);
             }
@@ -178,6 +364,9 @@ impl Drop for MaterialHandler {
                 self.device.destroy_framebuffer(*frame, None);
             }
             self.device.destroy_render_pass(self.main_renderpass, None);
+            if let Some(renderpass) = self.multiview_renderpass {
+                self.device.destroy_render_pass(renderpass, None);
+            }
         }
     }
 }
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    types::{MaterialInstance, MATERIAL_BLOCK_SIZE},
+    vulkan::{Buffer, VulkanDevice},
+};
+
+use super::{
+    bindless::{BindlessHandler, BindlessResourceHandle, BindlessResourceType},
+    FLYING_FRAMES,
+};
+
+/// how many material instances can exist at the same time
+pub const MAX_MATERIAL_INSTANCES: usize = 256;
+
+/// points to a block in the material parameter buffer
+/// the index can be passed to shaders, see ``GetMaterialParams`` in ``material.slang``
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialInstanceHandle {
+    pub index: usize,
+}
+
+/// holds one parameter buffer for every frame in flight, like the ``EnvironmentHandler``
+/// the buffer is bound to the storage buffer at ``BindlessHandler::MATERIAL_PARAMS_SLOT``
+pub(crate) struct MaterialInstanceHandler {
+    instances: Vec<Option<MaterialInstance>>,
+    buffers: [Arc<Buffer>; FLYING_FRAMES],
+}
+
+impl MaterialInstanceHandler {
+    /// # Panics
+    /// if there is no space to allocate the buffers
+    pub fn new(device: &Arc<VulkanDevice>, bindless: &BindlessHandler) -> Self {
+        let buffers: [Arc<Buffer>; FLYING_FRAMES] = std::array::from_fn(|_| {
+            Buffer::new(
+                device.clone(),
+                (MAX_MATERIAL_INSTANCES * MATERIAL_BLOCK_SIZE) as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            )
+            .unwrap()
+        });
+
+        let handle = BindlessResourceHandle::reserved(
+            BindlessHandler::MATERIAL_PARAMS_SLOT,
+            BindlessResourceType::StorageBuffer,
+        );
+
+        bindless.set_per_frame_buffer(&**device, buffers.each_ref().map(|v| v.handle()), handle);
+
+        Self {
+            instances: vec![],
+            buffers,
+        }
+    }
+
+    /// returns none if all blocks are used
+    pub fn insert(&mut self, instance: MaterialInstance) -> Option<MaterialInstanceHandle> {
+        let index = match self.instances.iter().position(Option::is_none) {
+            Some(index) => index,
+            None if self.instances.len() < MAX_MATERIAL_INSTANCES => {
+                self.instances.push(None);
+                self.instances.len() - 1
+            }
+            None => return None,
+        };
+
+        self.instances[index] = Some(instance);
+        Some(MaterialInstanceHandle { index })
+    }
+
+    /// the block can be reused right away, every frame has its own buffer
+    /// and a new instance is written to it before the frame is recorded
+    pub fn remove(&mut self, handle: MaterialInstanceHandle) -> Option<MaterialInstance> {
+        self.instances.get_mut(handle.index)?.take()
+    }
+
+    pub fn get_mut(&mut self, handle: MaterialInstanceHandle) -> Option<&mut MaterialInstance> {
+        self.instances.get_mut(handle.index)?.as_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.iter().flatten().count()
+    }
+
+    /// write the instances that changed to the buffer of this frame
+    /// the frame must not be executing on the gpu
+    pub fn upload(&mut self, frame_index: usize) {
+        let buffer = &self.buffers[frame_index];
+
+        for (index, instance) in self.instances.iter_mut().enumerate() {
+            if let Some(data) = instance.as_mut().and_then(|v| v.take_dirty(frame_index)) {
+                buffer.write(index * MATERIAL_BLOCK_SIZE, data);
+            }
+        }
+    }
+}
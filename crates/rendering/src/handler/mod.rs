@@ -1,32 +1,147 @@
 use crate::{
-    types::{Material, MaterialCreateInfo},
-    vulkan::{Buffer, Swapchain, VulkanDevice},
+    assets::texture::{TextureHandle, TextureMemoryStats},
+    types::{Material, MaterialCreateInfo, MaterialInstance, VariantValues},
+    vulkan::{Buffer, Swapchain, Texture, VulkanDevice},
 };
 use ash::{prelude::VkResult, vk};
-use bindless::{get_free_slot, BindlessHandler, BindlessResourceHandle, ResourceSlot};
+use bindless::{BindlessHandler, BindlessResourceHandle, ResourceSlot};
+use breadcrumbs::Breadcrumbs;
+use buffer_updates::BufferUpdates;
+use capture::FrameCapture;
+use compute_present::ComputePresentPass;
+use config::RendererConfig;
+use destroy_queue::DestroyQueue;
+use dump::{DumpError, DumpTarget, ImageDumper};
+use environment::{Environment, EnvironmentHandler};
 use frame::FrameContext;
-use material::MaterialHandler;
-use render_batch::RenderBatch;
-use std::sync::Arc;
+use gi::{GiPass, GiSettings, GiVolume};
+use handles::{BufferHandle, HandleMap, MaterialHandle, OwnedBuffer, ResourceHandle, ShaderHandle};
+use jobs::JobSystem;
+use material::{MaterialHandler, ViewLoadOps};
+use material_instances::{MaterialInstanceHandle, MaterialInstanceHandler};
+use math::Color;
+use oit::OitResolve;
+use outline::{Outline, OutlinePass};
+use pacing::{FramePacer, FrameStats, LatencyMode};
+use particles::{ParticleCounters, ParticleSystem, ParticleSystemCreateInfo};
+use picking::{ObjectPicker, PickResult};
+use probes::{ProbeCamera, ProbeDesc, ProbePass};
+use raw_pass::{RawPassError, RawPasses, RecordFn};
+use render_batch::{PulledBuffers, RenderBatch};
+use sampler::{SamplerCache, SamplerDesc};
+use scene_cache::SceneCache;
+use sprites::{SpriteBatch, SpriteMode};
+use ssao::{Ssao, SsaoSettings};
+use static_batches::StaticBatches;
+use stats::{BindlessUsage, MemoryReport, ResourceCounts, SlotUsage};
+use std::{ffi::CStr, path::PathBuf, sync::Arc};
+use taa::{TaaSettings, TemporalAa};
+use tonemap::{TonemapOperator, TonemapSettings, Tonemapper};
+use ui::UiPainter;
+use upscale::{UpscaleFilter, Upscaler};
+use volumetric::{FluidVolume, VolumetricPass, VolumetricSettings};
 
 mod bindless;
+mod breadcrumbs;
+mod buffer_updates;
+mod capture;
+pub mod compute_present;
+pub mod config;
+mod destroy_queue;
+pub mod dump;
+pub mod environment;
 mod frame;
+pub mod gi;
+pub mod handles;
 pub mod material;
+pub mod material_instances;
+mod oit;
+pub mod outline;
+pub mod pacing;
+pub mod particles;
+pub mod picking;
+pub mod probes;
+pub mod raw_pass;
 pub mod render_batch;
+pub mod sampler;
+mod scene_cache;
+pub mod sprites;
+pub mod ssao;
+mod static_batches;
+pub mod stats;
+pub mod taa;
+pub mod tonemap;
+mod ui;
+pub mod upscale;
+mod views;
+pub mod volumetric;
 
 /// max frames that can be Prerecorded, makes the render smoother but more delayed
 pub const FLYING_FRAMES: usize = 2;
 
+/// something the application has to react to, see ``RenderHandler::poll_events``
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderEvent {
+    /// the gpu was reset or removed, for example by a driver crash or a gpu switch on a laptop
+    DeviceLost,
+    /// the window surface was destroyed
+    SurfaceLost,
+}
+
+/// the fields are dropped in order, after ``Drop`` waited for the device,
+/// everything that renders to the swapchain comes first, then the swapchain and at last the device
 pub struct RenderHandler {
-    pub device: Arc<VulkanDevice>,
-    swapchain: Swapchain,
     materials: MaterialHandler,
+    material_instances: MaterialInstanceHandler,
+    buffer_updates: BufferUpdates,
     frames: [FrameContext; FLYING_FRAMES],
+    breadcrumbs: [Breadcrumbs; FLYING_FRAMES],
     batches: Vec<RenderBatch>,
+    particle_systems: Vec<ParticleSystem>,
+    sprite_batches: Vec<SpriteBatch>,
+    picker: ObjectPicker,
+    dumper: ImageDumper,
     bindless_handler: BindlessHandler,
+    samplers: SamplerCache,
+    environment: EnvironmentHandler,
+    oit: OitResolve,
+    ssao: Ssao,
+    taa: TemporalAa,
+    gi: GiPass,
+    probes: ProbePass,
+    volumetric: VolumetricPass,
+    tonemapper: Tonemapper,
+    compute_present: ComputePresentPass,
+    outline: OutlinePass,
+    upscaler: Upscaler,
+    ui: UiPainter,
+    raw_passes: RawPasses,
+    static_batches: StaticBatches,
+    /// which swapchain images still hold the scene, see ``RenderHandler::reuse_scene``
+    scene_cache: SceneCache,
+    buffers: HandleMap<BufferHandle, OwnedBuffer>,
+    shaders: HandleMap<ShaderHandle, vk::ShaderModule>,
+    owned_materials: HandleMap<MaterialHandle, Arc<Material>>,
+    config: RendererConfig,
+    /// shared with the world, see ``RenderHandler::jobs``
+    jobs: Arc<JobSystem>,
     frame_index: usize,
-    // a queue of resources that are supposed to be destroyed but need to wait for a fence
-    destroy_queue: Vec<(vk::Fence, DestroyResource)>,
+    pacer: FramePacer,
+    // resources that are supposed to be destroyed but might still be used by a frame in flight
+    destroy_queue: DestroyQueue,
+    events: Vec<RenderEvent>,
+    capture: FrameCapture,
+    /// nothing is rendered until ``reinitialize`` is called
+    lost: Option<RenderEvent>,
+    /// the window has no area, nothing is rendered until it's resized again
+    minimized: bool,
+    /// where the gpu was when the device got lost, see ``RenderHandler::crash_report``
+    crash_report: Option<String>,
+    /// the shader variant features of the main view, see ``MaterialVariants``
+    view_variants: VariantValues,
+    swapchain: Swapchain,
+    /// destroys the surface and the instance once the last clone is dropped, after the swapchain
+    pub device: Arc<VulkanDevice>,
 }
 
 impl RenderHandler {
@@ -36,127 +151,1325 @@ impl RenderHandler {
     where
         T: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
     {
-        let device = unsafe { Arc::new(VulkanDevice::new(window)?) };
+        Self::with_config(window, window_size, RendererConfig::default())
+    }
+
+    /// # Errors
+    /// # Panics
+    pub fn with_config<T>(
+        window: &T,
+        window_size: [u32; 2],
+        config: RendererConfig,
+    ) -> VkResult<Self>
+    where
+        T: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
+    {
+        // renderdoc needs to be loaded before the device is created
+        let capture = FrameCapture::new();
+
+        let device = unsafe {
+            Arc::new(VulkanDevice::with_adapter(
+                window,
+                config.validation,
+                &config.adapter,
+                config.adapter_workaround,
+            )?)
+        };
+        device.set_panic_on_validation_error(config.panic_on_validation_error);
 
-        let swapchain = unsafe { Swapchain::new(device.clone(), window_size) }?;
+        let samples = device.max_sample_count(config.msaa_samples);
+        // the compute present shader writes the swapchain image at its own size
+        let internal_resolution = config.internal_resolution.filter(|_| {
+            if config.compute_present {
+                log::warn!("the internal resolution is ignored with compute present");
+            }
+            !config.compute_present
+        });
+        let swapchain = unsafe {
+            Swapchain::new(
+                device.clone(),
+                window_size,
+                config.present_mode,
+                samples,
+                config.compute_present,
+                internal_resolution,
+            )
+        }?;
 
-        let materials = MaterialHandler::new(device.clone(), &swapchain)?;
+        let materials = MaterialHandler::new(device.clone(), &swapchain, config.multiview)?;
 
         let frames = std::array::from_fn(|_| unsafe { FrameContext::new(&device).unwrap() });
 
+        let breadcrumbs = (0..FLYING_FRAMES)
+            .map(|i| Breadcrumbs::new(&device, i, config.breadcrumbs))
+            .collect::<VkResult<Vec<_>>>()?;
+        let Ok(breadcrumbs) = breadcrumbs.try_into() else {
+            unreachable!("there are breadcrumbs for every frame")
+        };
+
         let bindless_handler = BindlessHandler::new(&device)?;
 
+        let samplers = SamplerCache::new(device.clone(), &config);
+
+        let environment = EnvironmentHandler::new(&device, &bindless_handler);
+
+        let material_instances = MaterialInstanceHandler::new(&device, &bindless_handler);
+
+        let oit = OitResolve::new(device.clone(), &swapchain, &bindless_handler);
+
+        let ssao = Ssao::new(device.clone(), &swapchain, &bindless_handler)?;
+
+        let taa = TemporalAa::new(device.clone(), &swapchain, &bindless_handler)?;
+
+        let gi = GiPass::new(device.clone(), &bindless_handler, config.gi)?;
+
+        let probes = ProbePass::new(
+            device.clone(),
+            &bindless_handler,
+            materials.multiview_renderpass.is_some(),
+        )?;
+
+        let volumetric = VolumetricPass::new(device.clone(), &bindless_handler)?;
+
+        let tonemapper = Tonemapper::new(device.clone(), &swapchain, &bindless_handler)?;
+
+        let compute_present =
+            ComputePresentPass::new(device.clone(), &swapchain, &bindless_handler);
+
+        let outline = OutlinePass::new(device.clone(), &swapchain, &bindless_handler)?;
+
+        let upscaler = Upscaler::new(device.clone(), &swapchain, config.upscale_filter);
+
+        let ui = UiPainter::new(device.clone(), &swapchain)?;
+
+        let pacer = FramePacer::new(&device);
+
+        let picker = ObjectPicker::new(device.clone())?;
+        let dumper = ImageDumper::new(device.clone());
+
+        let static_batches = StaticBatches::new(device.clone())?;
+
+        let mut view_variants = VariantValues::default();
+        set_builtin_variants(&mut view_variants, &device, &swapchain);
+
         Ok(Self {
             device,
             swapchain,
             materials,
+            material_instances,
+            buffer_updates: BufferUpdates::default(),
             frames,
+            breadcrumbs,
             batches: vec![],
+            particle_systems: vec![],
+            sprite_batches: vec![],
+            picker,
+            dumper,
             bindless_handler,
+            samplers,
+            environment,
+            oit,
+            ssao,
+            taa,
+            gi,
+            probes,
+            volumetric,
+            tonemapper,
+            compute_present,
+            outline,
+            upscaler,
+            ui,
+            raw_passes: RawPasses::default(),
+            static_batches,
+            scene_cache: SceneCache::default(),
+            buffers: HandleMap::new(),
+            shaders: HandleMap::new(),
+            owned_materials: HandleMap::new(),
+            jobs: Arc::new(
+                config
+                    .workers
+                    .map_or_else(JobSystem::default, JobSystem::new),
+            ),
+            config,
             frame_index: 0,
-            destroy_queue: vec![],
+            pacer,
+            destroy_queue: DestroyQueue::new(),
+            events: vec![],
+            capture,
+            lost: None,
+            minimized: false,
+            crash_report: None,
+            view_variants,
         })
     }
 
     #[inline]
     pub fn add_render_batch(&mut self, batch: RenderBatch) {
+        self.scene_cache.invalidate();
         self.batches.push(batch);
     }
 
-    /// sets the given index in the array to be this buffer
-    pub fn set_uniform_buffer(
+    /// create a buffer that is owned by the renderer, see ``destroy``
+    /// # Errors
+    /// if there is no space left to allocate
+    pub fn create_buffer(
         &mut self,
-        buffer: Arc<Buffer>,
-        index: usize,
-    ) -> BindlessResourceHandle {
-        let handle = BindlessResourceHandle {
-            index,
-            ty: bindless::BindlessResourceType::UniformBuffer,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        property_flags: vk::MemoryPropertyFlags,
+    ) -> VkResult<BufferHandle> {
+        let buffer = Buffer::new(self.device.clone(), size, usage, property_flags)?;
+        Ok(self.import_buffer(buffer))
+    }
+
+    /// let the renderer own a buffer that was created somewhere else, see ``destroy``
+    /// other clones of the buffer keep it alive, but it's only bound while the handle exists
+    pub fn import_buffer(&mut self, buffer: Arc<Buffer>) -> BufferHandle {
+        self.buffers.insert(OwnedBuffer {
+            buffer,
+            bindings: vec![],
+        })
+    }
+
+    /// to write to the buffer or give it to a ``DrawData``
+    #[must_use]
+    pub fn buffer(&self, handle: BufferHandle) -> Option<&Arc<Buffer>> {
+        self.buffers.get(handle).map(|owned| &owned.buffer)
+    }
+
+    /// put the buffer in to the uniform buffer array at the index, or at the first free index if it's none
+    /// the slot is freed again when the buffer is destroyed
+    /// returns none if the handle was destroyed or there is no free slot
+    pub fn bind_uniform_buffer(
+        &mut self,
+        handle: BufferHandle,
+        index: Option<usize>,
+    ) -> Option<BindlessResourceHandle> {
+        self.scene_cache.invalidate();
+        self.bind_buffer(handle, bindless::BindlessResourceType::UniformBuffer, index)
+    }
+
+    /// put the buffer in to the storage buffer array at the index, or at the first free index if it's none
+    /// the slot is freed again when the buffer is destroyed
+    /// returns none if the handle was destroyed or there is no free slot
+    pub fn bind_storage_buffer(
+        &mut self,
+        handle: BufferHandle,
+        index: Option<usize>,
+    ) -> Option<BindlessResourceHandle> {
+        self.scene_cache.invalidate();
+        self.bind_buffer(handle, bindless::BindlessResourceType::StorageBuffer, index)
+    }
+
+    fn bind_buffer(
+        &mut self,
+        handle: BufferHandle,
+        ty: bindless::BindlessResourceType,
+        index: Option<usize>,
+    ) -> Option<BindlessResourceHandle> {
+        let owned = self.buffers.get_mut(handle)?;
+        let binding = match index {
+            Some(index) => self.bindless_handler.handle(index, ty),
+            None => self.bindless_handler.allocate(ty)?,
         };
 
+        owned.bindings.push(binding);
+        let buffer = owned.buffer.clone();
+        self.write_buffer_slot(buffer, binding);
+        Some(binding)
+    }
+
+    fn write_buffer_slot(&mut self, buffer: Arc<Buffer>, handle: BindlessResourceHandle) {
         self.bindless_handler
             .upload_buffer(buffer, handle, self.frame_index);
 
-        self.bindless_handler.uniform_buffers[index] = ResourceSlot::Submited;
+        match handle.ty {
+            bindless::BindlessResourceType::UniformBuffer => {
+                self.bindless_handler.uniform_buffers[handle.index] = ResourceSlot::Submited;
+            }
+            _ => self.bindless_handler.storage_buffers[handle.index] = ResourceSlot::Submited,
+        }
+    }
 
+    /// sets the given index in the array to be this buffer
+    #[deprecated = "the buffer is never unbound, use ``create_buffer`` and ``bind_uniform_buffer``"]
+    pub fn set_uniform_buffer(
+        &mut self,
+        buffer: Arc<Buffer>,
+        index: usize,
+    ) -> BindlessResourceHandle {
+        self.scene_cache.invalidate();
+        let handle = self
+            .bindless_handler
+            .handle(index, bindless::BindlessResourceType::UniformBuffer);
+        self.write_buffer_slot(buffer, handle);
         handle
     }
 
     /// sets the first free index to be this buffer
+    #[deprecated = "the buffer is never unbound, use ``create_buffer`` and ``bind_uniform_buffer``"]
     pub fn push_uniform_buffer(&mut self, buffer: Arc<Buffer>) -> Option<BindlessResourceHandle> {
-        let index = get_free_slot(&self.bindless_handler.uniform_buffers)?;
-        Some(self.set_uniform_buffer(buffer, index))
+        self.scene_cache.invalidate();
+        let handle = self
+            .bindless_handler
+            .allocate(bindless::BindlessResourceType::UniformBuffer)?;
+        self.write_buffer_slot(buffer, handle);
+        Some(handle)
     }
 
     /// sets the given index in the array to be this buffer
+    #[deprecated = "the buffer is never unbound, use ``create_buffer`` and ``bind_storage_buffer``"]
     pub fn set_storage_buffer(
         &mut self,
         buffer: Arc<Buffer>,
         index: usize,
     ) -> BindlessResourceHandle {
-        let handle = BindlessResourceHandle {
-            index,
-            ty: bindless::BindlessResourceType::StorageBuffer,
+        self.scene_cache.invalidate();
+        let handle = self
+            .bindless_handler
+            .handle(index, bindless::BindlessResourceType::StorageBuffer);
+        self.write_buffer_slot(buffer, handle);
+        handle
+    }
+
+    /// sets the first free index to be this buffer
+    #[deprecated = "the buffer is never unbound, use ``create_buffer`` and ``bind_storage_buffer``"]
+    pub fn push_storage_buffer(&mut self, buffer: Arc<Buffer>) -> Option<BindlessResourceHandle> {
+        self.scene_cache.invalidate();
+        self.push_storage_slot(buffer)
+    }
+
+    fn push_storage_slot(&mut self, buffer: Arc<Buffer>) -> Option<BindlessResourceHandle> {
+        let handle = self
+            .bindless_handler
+            .allocate(bindless::BindlessResourceType::StorageBuffer)?;
+        self.write_buffer_slot(buffer, handle);
+        Some(handle)
+    }
+
+    /// put the buffers of a draw into the storage buffer array so a material with ``vertex_pulling`` can read them
+    /// the result is set as ``DrawData::pulled_buffers``
+    /// returns none if there aren't enough free storage buffer slots
+    /// # Panics
+    /// if a buffer wasn't created with ``BufferUsageFlags::STORAGE_BUFFER``
+    pub fn push_pulled_buffers(
+        &mut self,
+        vertices: Arc<Buffer>,
+        instances: Option<Arc<Buffer>>,
+    ) -> Option<PulledBuffers> {
+        self.scene_cache.invalidate();
+        let buffers = std::iter::once(&vertices).chain(&instances);
+        for buffer in buffers.clone() {
+            assert!(
+                buffer
+                    .usage()
+                    .contains(vk::BufferUsageFlags::STORAGE_BUFFER),
+                "pulled buffers need STORAGE_BUFFER usage"
+            );
+        }
+
+        // don't take the vertex slot if the instances don't fit anymore
+        let free = self
+            .bindless_handler
+            .free_count(bindless::BindlessResourceType::StorageBuffer);
+        if free < buffers.count() {
+            return None;
+        }
+
+        let vertices = self.push_storage_slot(vertices)?;
+        let instances = match instances {
+            Some(instances) => self.push_storage_slot(instances)?.index as u32,
+            None => u32::MAX,
         };
 
+        Some(PulledBuffers {
+            vertices: vertices.index as u32,
+            instances,
+        })
+    }
+
+    /// get a sampler that can be used for textures
+    /// samplers are cached, so this returns the same sampler for the same description
+    /// # Errors
+    /// if there is no memory left to create the sampler
+    pub fn get_sampler(&mut self, desc: SamplerDesc) -> VkResult<vk::Sampler> {
+        self.samplers.get(desc)
+    }
+
+    /// put the texture in to the first free index of the texture array, the renderer owns it until it's destroyed, see ``destroy``
+    /// the texture needs to be in ``SHADER_READ_ONLY_OPTIMAL`` layout
+    /// returns none if there is no free slot
+    pub fn add_texture(&mut self, texture: Texture, sampler: vk::Sampler) -> Option<TextureHandle> {
+        self.scene_cache.invalidate();
+        let slot = self
+            .bindless_handler
+            .allocate(bindless::BindlessResourceType::Texture)?;
+        Some(self.write_texture_slot(Arc::new(texture), sampler, slot.index))
+    }
+
+    /// the same as ``add_texture``, but the caller keeps a reference to upload to the texture later,
+    /// like the layers of a ``TextureAtlas``
+    /// returns none if there is no free slot
+    pub fn add_shared_texture(
+        &mut self,
+        texture: Arc<Texture>,
+        sampler: vk::Sampler,
+    ) -> Option<TextureHandle> {
+        self.scene_cache.invalidate();
+        let slot = self
+            .bindless_handler
+            .allocate(bindless::BindlessResourceType::Texture)?;
+        Some(self.write_texture_slot(texture, sampler, slot.index))
+    }
+
+    /// sets the given index in the texture array to be this texture
+    /// the texture needs to be in ``SHADER_READ_ONLY_OPTIMAL`` layout
+    #[deprecated = "the texture is never unbound, use ``add_texture``"]
+    pub fn set_texture(
+        &mut self,
+        texture: Arc<Texture>,
+        sampler: vk::Sampler,
+        index: usize,
+    ) -> TextureHandle {
+        self.scene_cache.invalidate();
+        self.write_texture_slot(texture, sampler, index)
+    }
+
+    fn write_texture_slot(
+        &mut self,
+        texture: Arc<Texture>,
+        sampler: vk::Sampler,
+        index: usize,
+    ) -> TextureHandle {
+        let handle = self
+            .bindless_handler
+            .handle(index, bindless::BindlessResourceType::Texture);
+
         self.bindless_handler
-            .upload_buffer(buffer, handle, self.frame_index);
+            .upload_texture(texture, sampler, handle, self.frame_index);
 
-        self.bindless_handler.uniform_buffers[index] = ResourceSlot::Submited;
+        self.bindless_handler.textures[index] = ResourceSlot::Submited;
 
-        handle
+        TextureHandle {
+            index,
+            generation: handle.generation,
+        }
     }
 
-    /// sets the first free index to be this buffer
-    pub fn push_storage_buffer(&mut self, buffer: Arc<Buffer>) -> Option<BindlessResourceHandle> {
-        let index = get_free_slot(&self.bindless_handler.storage_buffers)?;
-        Some(self.set_storage_buffer(buffer, index))
+    /// sets the first free index to be this texture
+    #[deprecated = "the texture is never unbound, use ``add_texture``"]
+    pub fn push_texture(
+        &mut self,
+        texture: Arc<Texture>,
+        sampler: vk::Sampler,
+    ) -> Option<TextureHandle> {
+        self.scene_cache.invalidate();
+        let slot = self
+            .bindless_handler
+            .allocate(bindless::BindlessResourceType::Texture)?;
+        Some(self.write_texture_slot(texture, sampler, slot.index))
+    }
+
+    /// change how an already uploaded texture is sampled
+    /// # Panics
+    /// if the handle doesn't point to a texture that has finished uploading or the texture was destroyed
+    pub fn set_texture_sampler(&mut self, handle: TextureHandle, sampler: vk::Sampler) {
+        self.scene_cache.invalidate();
+        assert!(
+            self.bindless_handler.is_current(&texture_binding(handle)),
+            "the given handle is stale, the texture was destroyed"
+        );
+        let ResourceSlot::Written(texture) = &self.bindless_handler.textures[handle.index] else {
+            panic!("the given handle is invalid and doesnt point to a texture");
+        };
+
+        self.write_texture_slot(texture.clone(), sampler, handle.index);
+    }
+
+    /// the memory used by all textures and how much has been saved by compression
+    #[must_use]
+    pub fn texture_memory(&self) -> TextureMemoryStats {
+        let mut stats = TextureMemoryStats::default();
+        for slot in &self.bindless_handler.textures {
+            if let ResourceSlot::Written(texture) = slot {
+                stats.size += texture.size;
+                stats.uncompressed_size += texture.uncompressed_size();
+            }
+        }
+        stats
     }
 
     // TODO
     // pub fn set_storage_image() {}
 
+    /// a size of 0, like when the window is minimized, stops the rendering until the window gets a size again
+    /// the swapchain is clamped to the sizes the surface supports
     /// # Errors
     /// if there was an issue creating a new swapchain
     /// for example if there is no memory left
     pub fn on_window_resize(&mut self, new_size: [u32; 2]) -> VkResult<()> {
+        if self.lost.is_some() {
+            return Ok(());
+        }
+
+        if new_size.contains(&0) {
+            self.minimized = true;
+            return Ok(());
+        }
+
+        let result = self.resize(new_size);
+        self.check_lost(result)
+    }
+
+    /// the window has no area, ``on_render`` does nothing until it's resized
+    #[must_use]
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// wait until the gpu finished every frame and drop the resources that waited for them
+    /// called before shutting down, so the buffers and textures that are dropped afterwards aren't in use anymore
+    /// # Errors
+    /// if the device couldn't be waited for, the resources are only dropped if the device was lost
+    pub fn wait_idle(&mut self) -> VkResult<()> {
+        let result = unsafe { self.device.device_wait_idle() };
+        // a lost device can't be waited for, but nothing runs on it anymore either
+        if matches!(result, Ok(()) | Err(vk::Result::ERROR_DEVICE_LOST)) {
+            self.destroy_queue.clear();
+        }
+        result
+    }
+
+    fn resize(&mut self, new_size: [u32; 2]) -> VkResult<()> {
         unsafe {
             self.device.device_wait_idle()?;
-            self.swapchain.recreate(self.device.clone(), new_size)?;
-            self.materials
-                .on_resize(&self.swapchain, self.bindless_handler.pipeline_layout);
+            // the surface can still have no area, even if the window has a size
+            self.minimized = !self.swapchain.recreate(self.device.clone(), new_size)?;
+            if self.minimized {
+                return Ok(());
+            }
+            self.pacer.reset();
+            self.scene_cache.clear();
+            // the materials set their viewport when they are bound, so they don't need to be rebuilt
+            self.materials.on_resize(&self.swapchain);
+            self.oit.on_resize(&self.swapchain, &self.bindless_handler);
+            self.ssao
+                .on_resize(&self.swapchain, &self.bindless_handler)?;
+            self.taa
+                .on_resize(&self.swapchain, &self.bindless_handler)?;
+            self.tonemapper
+                .on_resize(&self.swapchain, &self.bindless_handler);
+            self.compute_present
+                .on_resize(&self.swapchain, &self.bindless_handler);
+            self.outline
+                .on_resize(&self.swapchain, &self.bindless_handler)?;
+            self.ui.on_resize(&self.swapchain, &self.bindless_handler)?;
         }
 
         Ok(())
     }
 
-    /// # Safety
+    /// tell the renderer that the scene of the next frame looks like the last one, for frames where only the ui changed
+    /// the passes up to the tone mapping are skipped, the hdr target of the swapchain image still holds the scene
+    /// and only gets tone mapped, outlined and painted over again, it has to be called before every frame
+    ///
+    /// only call it if nothing the scene is drawn from changed, like the camera or the buffers the shaders read,
+    /// changes that go through the renderer draw the scene again anyway, like batches, textures and settings,
+    /// and so do particle systems, buffer updates, changed sprites and raw passes before ``tonemap``
+    pub fn reuse_scene(&mut self) {
+        self.scene_cache.request_reuse();
+    }
+
+    /// does nothing while the device or surface is lost or the window is minimized
     /// # Errors
+    /// if vulkan returned an error while recording or submitting the frame
+    /// if it was ``ERROR_DEVICE_LOST`` or ``ERROR_SURFACE_LOST_KHR`` a ``RenderEvent`` is emitted
     pub fn on_render(&mut self) -> VkResult<()> {
+        if self.lost.is_some() || self.minimized {
+            return Ok(());
+        }
+
+        let _span = tracing::info_span!("render").entered();
+        let result = self.render();
+        self.check_lost(result)
+    }
+
+    fn render(&mut self) -> VkResult<()> {
+        let frame_start = self.pacer.begin_frame();
+
         self.frame_index = (self.frame_index + 1) % FLYING_FRAMES;
 
+        let arena = self.frames[self.frame_index].scratch().scope();
         self.bindless_handler
-            .update_descriptor_set(&self.device, self.frame_index);
+            .update_descriptor_set(&*self.device, self.frame_index, &arena);
+        drop(arena);
+
+        let delta_time = self.pacer.stats.frame_time.as_secs_f32();
+        for system in &mut self.particle_systems {
+            system.prepare(delta_time, self.pacer.next_present_id());
+        }
+
+        let frame = &mut self.frames[self.frame_index];
+        let fence = frame.is_executing_fence;
+
+        let reused;
+        unsafe {
+            {
+                let _span = tracing::info_span!("frame pacing").entered();
+                self.pacer
+                    .wait_for_previous(&self.device, &self.swapchain)?;
+                self.pacer.poll(&self.device, &self.swapchain, fence)?;
+
+                // the buffer of this frame might still be read by the gpu
+                self.destroy_queue.begin_frame(&*self.device, fence)?;
+                self.pacer.stats.gpu_time = frame.gpu_time(&self.device);
+            }
+            self.picker.collect(self.frame_index);
+            self.dumper.collect(self.frame_index);
+            self.environment.upload(self.frame_index);
+            self.material_instances.upload(self.frame_index);
+            self.outline.upload(self.frame_index);
+            self.gi.upload(self.frame_index);
+            self.probes.upload(self.frame_index);
+            self.volumetric.upload(self.frame_index);
+            self.ui.upload(self.frame_index)?;
+            for batch in &mut self.sprite_batches {
+                batch.upload(self.frame_index)?;
+            }
+
+            // the application can't know about these, the particles move every frame
+            if !self.particle_systems.is_empty()
+                || !self.buffer_updates.is_empty()
+                || self.sprite_batches.iter().any(SpriteBatch::changed)
+                || self.raw_passes.any_before("tonemap")
+            {
+                self.scene_cache.invalidate();
+            }
+
+            reused = self.destroy_queue.submit(|destroy_queue| {
+                let reused = frame.execute(
+                    &self.device,
+                    &self.materials,
+                    &mut self.swapchain,
+                    &self.batches,
+                    &self.particle_systems,
+                    &self.sprite_batches,
+                    &mut self.picker,
+                    &mut self.dumper,
+                    &self.bindless_handler,
+                    &self.oit,
+                    &self.ssao,
+                    &self.taa,
+                    &self.gi,
+                    &mut self.probes,
+                    &self.volumetric,
+                    &self.tonemapper,
+                    &self.compute_present,
+                    &self.outline,
+                    &self.upscaler,
+                    &self.ui,
+                    &self.raw_passes,
+                    &mut self.static_batches,
+                    &mut self.buffer_updates,
+                    &mut self.scene_cache,
+                    &mut self.breadcrumbs[self.frame_index],
+                    self.frame_index,
+                    self.pacer.next_present_id(),
+                )?;
+
+                for staging in self.buffer_updates.take_recorded() {
+                    destroy_queue.push(DestroyResource::Buffer(staging));
+                }
+                Ok(reused)
+            })?;
+        }
+
+        // the simulation of this frame has been recorded, the next one reads what it wrote
+        for system in &mut self.particle_systems {
+            system.advance();
+        }
+
+        // a reused scene didn't use the jitter or the gi volume of this frame
+        if reused {
+            self.pacer.stats.reused_scenes += 1;
+        } else {
+            self.taa.advance();
+            self.gi.advance();
+        }
+
+        self.pacer.submitted(fence, frame_start);
+        self.device.check_validation();
+
+        Ok(())
+    }
+
+    /// emit an event and stop rendering if the error means the device or surface is gone
+    fn check_lost(&mut self, result: VkResult<()>) -> VkResult<()> {
+        let event = match result {
+            Err(vk::Result::ERROR_DEVICE_LOST) => RenderEvent::DeviceLost,
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR) => RenderEvent::SurfaceLost,
+            _ => return result,
+        };
+
+        if self.lost.is_none() {
+            self.lost = Some(event);
+            self.events.push(event);
+
+            if event == RenderEvent::DeviceLost {
+                self.crash_report = breadcrumbs::crash_report(&self.device, &self.breadcrumbs);
+                if let Some(report) = &self.crash_report {
+                    log::error!("device lost, last breadcrumbs:\n{report}");
+                }
+            }
+        }
+
+        result
+    }
+
+    /// which passes the gpu reached before the device got lost
+    /// only set after a ``RenderEvent::DeviceLost`` with ``RendererConfig::breadcrumbs`` enabled
+    #[must_use]
+    pub fn crash_report(&self) -> Option<&str> {
+        self.crash_report.as_deref()
+    }
+
+    /// the events that happened since the last call
+    pub fn poll_events(&mut self) -> std::vec::Drain<'_, RenderEvent> {
+        self.events.drain(..)
+    }
+
+    /// capture the next frame with RenderDoc, the capture is saved where RenderDoc puts its captures
+    /// returns false if RenderDoc isn't loaded or the ``renderdoc`` feature is disabled
+    pub fn trigger_capture(&mut self) -> bool {
+        self.capture.trigger(1)
+    }
+
+    /// like ``trigger_capture`` but captures multiple frames in a row
+    pub fn trigger_multi_frame_capture(&mut self, frames: u32) -> bool {
+        self.capture.trigger(frames)
+    }
+
+    /// if the application was started from RenderDoc and frames can be captured
+    #[must_use]
+    pub fn can_capture(&self) -> bool {
+        self.capture.is_available()
+    }
 
-        self.clean_resources();
+    /// why the renderer stopped rendering, ``None`` if it's working
+    #[must_use]
+    pub fn lost(&self) -> Option<RenderEvent> {
+        self.lost
+    }
 
+    /// recreate the device and the swapchain after the device or surface was lost
+    /// the config, environment, tonemap and latency settings are kept
+    ///
+    /// everything the application created with the old device is invalid now, that means
+    /// buffers, textures, materials, render batches, particle systems, sprite batches, environment probes and their camera
+    /// and the post processing and ui shaders,
+    /// they have to be created and set again
+    /// the egui textures are gone as well, so egui has to send its textures again
+    /// # Errors
+    /// if the new device or swapchain couldn't be created, the renderer stays lost and this can be tried again
+    pub fn reinitialize<T>(&mut self, window: &T, window_size: [u32; 2]) -> VkResult<()>
+    where
+        T: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
+    {
+        // only one swapchain can exist for a window
         unsafe {
-            self.frames[self.frame_index].execute(
-                &self.device,
-                &self.materials,
-                &mut self.swapchain,
-                &self.batches,
-                &self.bindless_handler,
-                self.frame_index,
-            )?;
+            let _ = self.device.device_wait_idle();
+            self.swapchain.destroy();
         }
 
+        let mut new = Self::with_config(window, window_size, self.config.clone())?;
+        new.environment.environment = self.environment.environment;
+        new.ssao.settings = self.ssao.settings;
+        new.taa.settings = self.taa.settings;
+        new.gi.settings = self.gi.settings;
+        new.volumetric.settings = self.volumetric.settings;
+        new.tonemapper.settings = self.tonemapper.settings;
+        new.upscaler.filter = self.upscaler.filter;
+        new.pacer.mode = self.pacer.mode;
+        new.materials.clear_color = self.materials.clear_color;
+        new.materials.view_layers = self.materials.view_layers;
+        // the new device might support other features
+        new.view_variants = std::mem::take(&mut self.view_variants);
+        set_builtin_variants(&mut new.view_variants, &new.device, &new.swapchain);
+        new.set_load_ops(self.materials.load_ops)?;
+        new.minimized = window_size.contains(&0);
 
+        *self = new;
         Ok(())
     }
 
+    /// the color the hdr target is cleared with at the start of every frame, if its load op is ``LoadOp::Clear``
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.scene_cache.invalidate();
+        self.materials.clear_color = color;
+    }
+
+    #[must_use]
+    pub fn clear_color(&self) -> Color {
+        self.materials.clear_color
+    }
+
+    /// the main view only draws the batches with one of these layers, every layer by default
+    /// for example ``!EDITOR`` hides the batches on an editor layer in a game build, see ``RenderBatch::set_layers``
+    pub fn set_view_layers(&mut self, layers: u32) {
+        self.scene_cache.invalidate();
+        self.materials.view_layers = layers;
+    }
+
+    #[must_use]
+    pub fn view_layers(&self) -> u32 {
+        self.materials.view_layers
+    }
+
+    /// choose if the targets of the main view are cleared or keep their contents between frames
+    /// # Errors
+    /// if the render pass couldn't be created
+    pub fn set_load_ops(&mut self, load_ops: ViewLoadOps) -> VkResult<()> {
+        self.scene_cache.invalidate();
+        if self.materials.load_ops == load_ops {
+            return Ok(());
+        }
+
+        unsafe {
+            self.device.device_wait_idle()?;
+            self.materials.set_load_ops(load_ops, &self.swapchain)
+        }
+    }
+
+    #[must_use]
+    pub fn load_ops(&self) -> ViewLoadOps {
+        self.materials.load_ops
+    }
+
+    /// the options the materials drawn in the main view are built with, if the draw doesn't pick one
+    /// ``oit`` and ``msaa`` are set by the renderer, see ``MaterialVariants::get``
+    #[must_use]
+    pub fn view_variants(&self) -> &VariantValues {
+        &self.view_variants
+    }
+
+    /// set the option of a feature for every material drawn in the main view
+    pub fn set_view_variant(&mut self, name: impl Into<String>, value: impl Into<u32>) {
+        self.scene_cache.invalidate();
+        self.view_variants.set(name, value);
+    }
+
+    /// the lighting and fog settings, uploaded at the start of every frame
+    #[must_use]
+    pub fn environment(&self) -> &Environment {
+        &self.environment.environment
+    }
+
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        self.scene_cache.invalidate();
+        &mut self.environment.environment
+    }
+
+    /// the hdr colors are multiplied by this before tone mapping
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.tonemapper.settings.exposure = exposure;
+    }
+
+    /// the gamma applied after tone mapping
+    /// defaults to 1.0 if the swapchain is srgb, else 2.2
+    pub fn set_gamma(&mut self, gamma: f32) {
+        self.tonemapper.settings.gamma = gamma;
+    }
+
+    pub fn set_tonemap_operator(&mut self, operator: TonemapOperator) {
+        self.tonemapper.settings.operator = operator;
+    }
+
+    #[must_use]
+    pub fn tonemap_settings(&self) -> TonemapSettings {
+        self.tonemapper.settings
+    }
+
+    /// how the internal resolution is scaled to the window, see ``RendererConfig::internal_resolution``
+    pub fn set_upscale_filter(&mut self, filter: UpscaleFilter) {
+        self.upscaler.filter = filter;
+    }
+
+    #[must_use]
+    pub fn upscale_filter(&self) -> UpscaleFilter {
+        self.upscaler.filter
+    }
+
+    /// set the fullscreen shader that maps the hdr target to the swapchain
+    /// see ``shaders/tonemap.slang`` in the application
+    /// until this is set, the hdr target is copied without tone mapping
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_tonemap_shader(
+        &mut self,
+        stages: &[vk::PipelineShaderStageCreateInfo],
+    ) -> VkResult<()> {
+        self.tonemapper
+            .set_shader(stages, self.bindless_handler.pipeline_layout)
+    }
+
+    /// set the compute shader that composites weighted blended transparency on to the hdr target
+    /// see ``shaders/oit.slang`` in the application and ``Transparency::WeightedBlended``
+    /// until this is set, weighted blended materials are drawn but never visible
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_oit_shader(&mut self, stage: vk::PipelineShaderStageCreateInfo) -> VkResult<()> {
+        self.scene_cache.invalidate();
+        self.oit
+            .set_shader(stage, self.bindless_handler.pipeline_layout)
+    }
+
+    /// turn ambient occlusion on or off and change its radius and intensity
+    pub fn set_ssao_settings(&mut self, settings: SsaoSettings) {
+        self.scene_cache.invalidate();
+        self.ssao.settings = settings;
+    }
+
+    #[must_use]
+    pub fn ssao_settings(&self) -> SsaoSettings {
+        self.ssao.settings
+    }
+
+    /// set the compute shaders that compute the ambient occlusion at half resolution and blur it
+    /// see ``shaders/ssao.slang`` in the application, the tonemap shader applies the result
+    /// until this is set, there is no ambient occlusion
+    /// # Errors
+    /// if there was an issue creating the pipelines
+    pub fn set_ssao_shaders(
+        &mut self,
+        ao_stage: vk::PipelineShaderStageCreateInfo,
+        blur_stage: vk::PipelineShaderStageCreateInfo,
+    ) -> VkResult<()> {
+        self.scene_cache.invalidate();
+        self.ssao
+            .set_shaders(ao_stage, blur_stage, self.bindless_handler.pipeline_layout)
+    }
+
+    /// turn temporal anti aliasing on or off and change how much history it keeps
+    pub fn set_taa_settings(&mut self, settings: TaaSettings) {
+        self.scene_cache.invalidate();
+        self.taa.settings = settings;
+    }
+
+    #[must_use]
+    pub fn taa_settings(&self) -> TaaSettings {
+        self.taa.settings
+    }
+
+    /// the offset the camera projection needs for the next frame, in normalized device coordinates
+    /// the frames are jittered by a fraction of a pixel so taa can blend them, see ``Camera::jitter`` in the math crate
+    /// 0 while taa is off or has no shader
+    #[must_use]
+    pub fn taa_jitter(&self) -> [f32; 2] {
+        self.taa.jitter(self.swapchain.get_image_extent())
+    }
+
+    /// set the compute shader that blends every frame with the previous ones
+    /// see ``shaders/taa.slang`` in the application
+    /// until this is set, there is no temporal anti aliasing
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_taa_shader(&mut self, stage: vk::PipelineShaderStageCreateInfo) -> VkResult<()> {
+        self.scene_cache.invalidate();
+        self.taa
+            .set_shader(stage, self.bindless_handler.pipeline_layout)
+    }
+
+    /// tells if the renderer was created with ``RendererConfig::gi``
+    #[must_use]
+    pub fn gi_enabled(&self) -> bool {
+        self.gi.is_enabled()
+    }
+
+    pub fn set_gi_settings(&mut self, settings: GiSettings) {
+        self.scene_cache.invalidate();
+        self.gi.settings = settings;
+    }
+
+    #[must_use]
+    pub fn gi_settings(&self) -> GiSettings {
+        self.gi.settings
+    }
+
+    /// replace the voxels light is propagated through, the grid covers ``volume``
+    /// ``voxels`` has ``GI_CELLS`` rgba8 colors with x changing fastest, then y, the alpha is the occupancy
+    /// does nothing without ``RendererConfig::gi``
+    /// # Panics
+    /// if there aren't ``GI_CELLS`` voxels
+    pub fn set_gi_voxels(&mut self, volume: GiVolume, voxels: &[[u8; 4]]) {
+        self.scene_cache.invalidate();
+        self.gi.set_voxels(volume, voxels);
+    }
+
+    /// set the compute shader that propagates the light through the voxel grid
+    /// see ``shaders/gi.slang`` in the application, materials read the result with ``IndirectLight``
+    /// until this is set, materials get no indirect light
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_gi_shader(&mut self, stage: vk::PipelineShaderStageCreateInfo) -> VkResult<()> {
+        self.scene_cache.invalidate();
+        self.gi
+            .set_shader(stage, self.bindless_handler.pipeline_layout)
+    }
+
+    /// render the scene from ``desc.position`` in to a cube, materials can reflect it with ``Probes`` from ``shaders/environment.slang``
+    /// it's rendered in the next frame, but only once ``set_probe_camera`` was called
+    /// returns the index of the probe
+    /// # Errors
+    /// ``ERROR_OUT_OF_POOL_MEMORY`` if there are already ``MAX_PROBES`` or no texture slot is free,
+    /// or if there is no space to allocate the cube
+    pub fn add_environment_probe(&mut self, desc: ProbeDesc) -> VkResult<usize> {
+        self.scene_cache.invalidate();
+        let Some(cube) = self.probes.create_cube(self.swapchain.samples)? else {
+            return Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY);
+        };
+
+        let sampler = self.samplers.get(SamplerDesc {
+            address_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            ..SamplerDesc::LINEAR
+        })?;
+        let handle = self
+            .add_shared_texture(cube.clone(), sampler)
+            .ok_or(vk::Result::ERROR_OUT_OF_POOL_MEMORY)?;
+
+        Ok(self.probes.add(desc, cube, handle))
+    }
+
+    /// the probe isn't used any more and its cube is destroyed once the frames have finished
+    pub fn remove_environment_probe(&mut self, index: usize) {
+        self.scene_cache.invalidate();
+        if let Some(handle) = self.probes.remove(index) {
+            self.destroy(handle);
+        }
+    }
+
+    /// change where a probe is or when it's rendered, it isn't rendered again until it's due
+    #[must_use]
+    pub fn environment_probe_mut(&mut self, index: usize) -> Option<&mut ProbeDesc> {
+        self.scene_cache.invalidate();
+        self.probes.desc_mut(index)
+    }
+
+    /// render the probe again in the next frame, for ``ProbeRefresh::OnDemand`` probes after the scene changed
+    pub fn refresh_probe(&mut self, index: usize) {
+        self.scene_cache.invalidate();
+        self.probes.refresh(index);
+    }
+
+    /// the uniform buffer the materials read the camera from, it's replaced while the probes are rendered
+    /// # Panics
+    /// if the buffer wasn't created with ``BufferUsageFlags::TRANSFER_DST``
+    pub fn set_probe_camera(&mut self, camera: ProbeCamera) {
+        self.scene_cache.invalidate();
+        self.probes.set_camera(camera);
+    }
+
+    pub fn set_volumetric_settings(&mut self, settings: VolumetricSettings) {
+        self.scene_cache.invalidate();
+        self.volumetric.settings = settings;
+    }
+
+    #[must_use]
+    pub fn volumetric_settings(&self) -> VolumetricSettings {
+        self.volumetric.settings
+    }
+
+    /// replace the fluids that are marched through, the grid covers ``volume``
+    /// ``voxels`` has ``FLUID_CELLS`` rgba8 colors with x changing fastest, then y, the alpha is the density
+    /// an empty slice removes every fluid
+    /// # Panics
+    /// if there are voxels but not ``FLUID_CELLS``
+    pub fn set_fluid_voxels(&mut self, volume: FluidVolume, voxels: &[[u8; 4]]) {
+        self.scene_cache.invalidate();
+        self.volumetric.set_voxels(volume, voxels);
+    }
+
+    /// set the compute shader that marches through the fluids and composites them on to the hdr target
+    /// see ``shaders/volumetric.slang`` in the application
+    /// until this is set, fluids are invisible
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_volumetric_shader(
+        &mut self,
+        stage: vk::PipelineShaderStageCreateInfo,
+    ) -> VkResult<()> {
+        self.scene_cache.invalidate();
+        self.volumetric
+            .set_shader(stage, self.bindless_handler.pipeline_layout)
+    }
+
+    /// tells if the renderer was created with ``RendererConfig::compute_present``,
+    /// see ``Swapchain::storage`` for how the swapchain images are written
+    #[must_use]
+    pub fn compute_present_enabled(&self) -> bool {
+        self.compute_present.is_enabled()
+    }
+
+    /// set the compute shader that writes the swapchain images instead of the tone mapping
+    /// it gets ``ComputePresentPushConstants`` and has to use ``COMPUTE_PRESENT_GROUP_SIZE`` work groups
+    /// does nothing visible without ``RendererConfig::compute_present``, the hdr target is tone mapped until this is set
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_compute_present_shader(
+        &mut self,
+        stage: vk::PipelineShaderStageCreateInfo,
+    ) -> VkResult<()> {
+        self.compute_present
+            .set_shader(stage, self.bindless_handler.pipeline_layout)
+    }
+
+    /// set the fullscreen shader that draws the outlines of the objects passed to ``set_outlines``
+    /// see ``shaders/outline.slang`` in the application
+    /// until this is set, no outlines are drawn
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_outline_shader(
+        &mut self,
+        stages: &[vk::PipelineShaderStageCreateInfo],
+    ) -> VkResult<()> {
+        self.outline
+            .set_shader(stages, self.bindless_handler.pipeline_layout)
+    }
+
+    /// draw outlines around these objects, until this is called again
+    /// ``near_depth`` is the depth of the near plane of the camera, see ``Projection::near_depth`` in the math crate
+    /// needs the object id target, so nothing is drawn if the gpu can't write object ids
+    pub fn set_outlines(&mut self, outlines: &[Outline], near_depth: f32) {
+        self.outline.set_outlines(outlines, near_depth);
+    }
+
+    /// set the shaders that draw the ui and composite it on to the swapchain image
+    /// see ``shaders/ui.slang`` in the application, the ui shader gets ``UiVertex`` as vertex input
+    /// and the composite shader is a fullscreen shader drawn with 3 vertices
+    /// until this is set, no ui is drawn
+    /// # Errors
+    /// if there was an issue creating the pipelines or the ui targets
+    pub fn set_ui_shaders(
+        &mut self,
+        ui_stages: &[vk::PipelineShaderStageCreateInfo],
+        composite_stages: &[vk::PipelineShaderStageCreateInfo],
+    ) -> VkResult<()> {
+        // the ui targets are written to every descriptor set
+        unsafe { self.device.device_wait_idle()? };
+
+        self.ui.set_shaders(
+            ui_stages,
+            composite_stages,
+            self.bindless_handler.pipeline_layout,
+            &self.swapchain,
+            &self.bindless_handler,
+        )
+    }
+
+    /// draw the output of egui on top of every frame, until this is called again
+    /// the ``textures_delta`` of every egui frame has to be passed, as egui only sends the textures that changed
+    /// ``TextureId::User`` is the index of a texture in the bindless texture array, see ``TextureHandle``
+    /// # Errors
+    /// if there is no space to allocate a texture or there are no free texture slots left
+    #[cfg(feature = "egui")]
+    pub fn paint_ui(
+        &mut self,
+        textures_delta: &egui::TexturesDelta,
+        primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) -> VkResult<()> {
+        // the textures freed last time aren't used by this frame anymore
+        let pending_free = std::mem::take(&mut self.ui.pending_free);
+        for id in pending_free {
+            self.free_ui_texture(id);
+        }
+
+        for (id, delta) in &textures_delta.set {
+            if let egui::TextureId::Managed(id) = *id {
+                self.set_ui_texture(id, delta)?;
+            }
+        }
+
+        self.ui.set_primitives(
+            primitives,
+            pixels_per_point,
+            self.swapchain.get_image_extent(),
+        );
+
+        // egui frees textures after the frame that used them for the last time
+        for id in &textures_delta.free {
+            if let egui::TextureId::Managed(id) = *id {
+                self.ui.pending_free.push(id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// egui textures are srgb and sampled, so the gpu converts them to linear when they are read
+    #[cfg(feature = "egui")]
+    fn set_ui_texture(&mut self, id: u64, delta: &egui::epaint::ImageDelta) -> VkResult<()> {
+        let egui::ImageData::Color(image) = &delta.image;
+        let data: Vec<u8> = image.pixels.iter().flat_map(|c| c.to_array()).collect();
+        let extent = [image.width() as u32, image.height() as u32];
+
+        if let Some([x, y]) = delta.pos {
+            if let Some(ui_texture) = self.ui.textures.get(&id) {
+                ui_texture
+                    .texture
+                    .upload_region([x as u32, y as u32], extent, &data)?;
+            }
+            return Ok(());
+        }
+
+        let texture = Arc::new(Texture::new(
+            self.device.clone(),
+            extent,
+            vk::Format::R8G8B8A8_SRGB,
+            1,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        )?);
+        texture.upload(&[&data])?;
+
+        let sampler = self.get_sampler(ui::sampler_desc(delta.options))?;
+
+        let handle = match self.ui.textures.remove(&id) {
+            Some(old) => {
+                self.destroy_queue
+                    .push(DestroyResource::Texture(old.texture));
+                self.write_texture_slot(texture.clone(), sampler, old.handle.index)
+            }
+            None => {
+                let slot = self
+                    .bindless_handler
+                    .allocate(bindless::BindlessResourceType::Texture)
+                    .ok_or(vk::Result::ERROR_OUT_OF_POOL_MEMORY)?;
+                self.write_texture_slot(texture.clone(), sampler, slot.index)
+            }
+        };
+
+        self.ui
+            .textures
+            .insert(id, ui::UiTexture { handle, texture });
+        Ok(())
+    }
+
+    /// give the slot of an egui texture back, the texture is destroyed once the gpu is done with it
+    /// if the texture hasn't been written to the descriptors yet, this is tried again next time
+    #[cfg(feature = "egui")]
+    fn free_ui_texture(&mut self, id: u64) {
+        let Some(ui_texture) = self.ui.textures.get(&id) else {
+            return;
+        };
+
+        let index = ui_texture.handle.index;
+        let slot = &mut self.bindless_handler.textures[index];
+        if matches!(slot, ResourceSlot::Submited) {
+            self.ui.pending_free.push(id);
+            return;
+        }
+        *slot = ResourceSlot::Empty;
+        self.bindless_handler
+            .free(bindless::BindlessResourceType::Texture, index);
+
+        let ui_texture = self.ui.textures.remove(&id).unwrap();
+        self.destroy_queue
+            .push(DestroyResource::Texture(ui_texture.texture));
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &RendererConfig {
+        &self.config
+    }
+
+    /// the workers for background work like uploads, meshing and octree flattening
+    #[must_use]
+    pub fn jobs(&self) -> &Arc<JobSystem> {
+        &self.jobs
+    }
+
+    /// switch between low latency and throughput mode
+    /// see ``LatencyMode``
+    pub fn set_latency_mode(&mut self, mode: LatencyMode) {
+        self.pacer.mode = mode;
+    }
+
+    #[must_use]
+    pub fn latency_mode(&self) -> LatencyMode {
+        self.pacer.mode
+    }
+
+    /// frame time and latency of the last presented frame
+    #[must_use]
+    pub fn frame_stats(&self) -> FrameStats {
+        FrameStats {
+            validation: self.device.validation_counts(),
+            ..self.pacer.stats
+        }
+    }
+
+    /// the budget and usage of the gpu memory heaps, queried from the driver every call
+    /// without ``VK_EXT_memory_budget`` the budget is the heap size and only the allocations of the renderer are counted
+    #[must_use]
+    pub fn memory_report(&self) -> MemoryReport {
+        MemoryReport {
+            heaps: self.device.memory_heaps(),
+        }
+    }
+
+    /// how many slots of the bindless arrays are used
+    #[must_use]
+    pub fn bindless_usage(&self) -> BindlessUsage {
+        let bindless = &self.bindless_handler;
+        BindlessUsage {
+            uniform_buffers: SlotUsage::count(&bindless.uniform_buffers),
+            storage_buffers: SlotUsage::count(&bindless.storage_buffers),
+            storage_images: SlotUsage::count(&bindless.storage_images),
+            textures: SlotUsage::count(&bindless.textures),
+        }
+    }
+
+    #[must_use]
+    pub fn resource_counts(&self) -> ResourceCounts {
+        ResourceCounts {
+            render_batches: self.batches.len(),
+            draw_calls: self.batches.iter().map(RenderBatch::draw_count).sum(),
+            materials: self.materials.materials.len() + self.owned_materials.len(),
+            material_instances: self.material_instances.len(),
+            particle_systems: self.particle_systems.len(),
+            sprite_batches: self.sprite_batches.len(),
+            pending_destroys: self.destroy_queue.len(),
+        }
+    }
+
+    #[must_use]
+    pub fn render_batches(&self) -> &[RenderBatch] {
+        &self.batches
+    }
+
+    /// change a batch after it was added, for example to hide it
+    pub fn render_batch_mut(&mut self, index: usize) -> Option<&mut RenderBatch> {
+        self.scene_cache.invalidate();
+        self.batches.get_mut(index)
+    }
+
+    /// the size the frame is rendered at, the internal resolution if there is one, otherwise the window size
     pub fn get_swapchain_resolution(&self) -> vk::Extent2D {
-        self.swapchain.create_info.image_extent
+        self.swapchain.get_image_extent()
+    }
+
+    /// map a pixel of the window to the pixel of the internal resolution it shows,
+    /// the result is outside of the resolution on the bars, without an internal resolution it's unchanged
+    #[must_use]
+    pub fn window_to_render(&self, pos: [f32; 2]) -> [f32; 2] {
+        let Some(rect) = self.swapchain.letterbox() else {
+            return pos;
+        };
+        let extent = self.swapchain.get_image_extent();
+
+        [
+            (pos[0] - rect.offset.x as f32) * extent.width as f32 / rect.extent.width as f32,
+            (pos[1] - rect.offset.y as f32) * extent.height as f32 / rect.extent.height as f32,
+        ]
     }
 
     /// resizes a buffer buffer that bound
@@ -166,11 +1479,17 @@ impl RenderHandler {
     /// if the handle doesn't point to a valid resource
     /// # Errors
     /// if there is no space to allocate
+    /// or ``ERROR_VALIDATION_FAILED_EXT`` if the slot of the handle was released since or the handle isn't a buffer
     pub fn resize_buffer(
         &mut self,
         handle: &BindlessResourceHandle,
         new_size: u64,
     ) -> VkResult<Arc<Buffer>> {
+        self.scene_cache.invalidate();
+        if !self.bindless_handler.is_current(handle) {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+
         // pull the buffer out of the bindless array
         let buffer = match handle.ty {
             bindless::BindlessResourceType::StorageBuffer => {
@@ -179,7 +1498,10 @@ impl RenderHandler {
             bindless::BindlessResourceType::UniformBuffer => {
                 self.bindless_handler.uniform_buffers[handle.index].take()
             }
-            bindless::BindlessResourceType::StorageImage => unimplemented!(),
+            bindless::BindlessResourceType::StorageImage
+            | bindless::BindlessResourceType::Texture => {
+                return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT)
+            }
         }
         .expect("the given handle is invalid and doesnt point to a resource");
 
@@ -190,67 +1512,375 @@ impl RenderHandler {
 
         let new_buffer = buffer_owned.resize(self.device.clone(), new_size)?;
 
-        match handle.ty {
-            bindless::BindlessResourceType::StorageBuffer => {
-                self.set_storage_buffer(new_buffer.clone(), handle.index)
-            }
-            bindless::BindlessResourceType::UniformBuffer => {
-                self.set_uniform_buffer(new_buffer.clone(), handle.index)
-            }
-            bindless::BindlessResourceType::StorageImage => unimplemented!(),
-        };
+        // only buffers get here
+        self.write_buffer_slot(new_buffer.clone(), *handle);
 
         // we need to wait until the last frame using the old buffer is finished executing
-        let wait_for_fence = &self.frames[self.frame_index].is_executing_fence;
-
         self.destroy_queue
-            .push((*wait_for_fence, DestroyResource::Buffer(buffer_owned)));
+            .push(DestroyResource::Buffer(buffer_owned));
 
         Ok(new_buffer)
     }
 
-    pub fn clean_resources(&mut self) {
-        unsafe {
-            let mut i = 0;
-            while let Some((fence, _)) = self.destroy_queue.get(i) {
-                if self.device.wait_for_fences(&[*fence], true, 0).is_ok() {
-                    self.destroy_queue.remove(i);
-                }
-
-                i += 1;
-            }
+    /// write to a part of a buffer that is bound, the write is done on the gpu at the start of the next frame
+    /// so it also works for device local buffers and the frames that are still in flight keep the old data
+    /// small writes are recorded into the command buffer, bigger ones go through a staging buffer
+    /// # Panics
+    /// if the handle doesn't point to a buffer, the data doesn't fit into the buffer at that offset
+    /// or the buffer wasn't created with ``BufferUsageFlags::TRANSFER_DST``
+    /// # Errors
+    /// if there is no space to allocate the staging buffer
+    /// or ``ERROR_VALIDATION_FAILED_EXT`` if the slot of the handle was released since
+    pub fn update_buffer(
+        &mut self,
+        handle: &BindlessResourceHandle,
+        offset: u64,
+        bytes: &[u8],
+    ) -> VkResult<()> {
+        if !self.bindless_handler.is_current(handle) {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
         }
+
+        let buffer = self
+            .bindless_handler
+            .buffer(handle)
+            .expect("the given handle is invalid and doesnt point to a buffer");
+
+        self.buffer_updates
+            .push(&self.device, buffer, offset, bytes)
     }
 
+    /// drop the destroyed resources whose frames have finished on the gpu, this also happens at the start of every frame
+    pub fn clean_resources(&mut self) {
+        self.destroy_queue.retire();
+    }
+
+    /// # Panics
+    /// if ``MaterialCreateInfo::vertex_input`` isn't valid, see ``VertexInput::validate``
+    #[deprecated = "the material is never destroyed, use ``create_material``"]
     pub fn load_material(&mut self, info: MaterialCreateInfo) -> Arc<Material> {
-        let swapchain_res = self.swapchain.get_image_extent();
+        let material = self.build_material(info);
+        self.materials.materials.push(material.clone());
+        material
+    }
 
-        let material = Arc::new(info.build(
+    fn build_material(&self, info: MaterialCreateInfo) -> Arc<Material> {
+        Arc::new(info.build(
             &self.device,
             self.materials.main_renderpass,
+            self.materials.multiview_renderpass,
             self.bindless_handler.pipeline_layout,
-            [swapchain_res.width, swapchain_res.height],
-        ));
+            self.swapchain.samples,
+        ))
+    }
+
+    /// create a material that is owned by the renderer, see ``destroy``
+    /// the shader modules aren't owned by the material, they can be destroyed right after this
+    /// # Panics
+    /// if ``MaterialCreateInfo::vertex_input`` isn't valid, see ``VertexInput::validate``
+    pub fn create_material(&mut self, info: MaterialCreateInfo) -> MaterialHandle {
+        let material = self.build_material(info);
+        self.owned_materials.insert(material)
+    }
+
+    /// to give it to a ``RenderBatch``, none if the handle was destroyed
+    #[must_use]
+    pub fn material(&self, handle: MaterialHandle) -> Option<Arc<Material>> {
+        self.owned_materials.get(handle).cloned()
+    }
+
+    /// a slang module with the descriptor bindings and the reserved bindless slots of the renderer as constants,
+    /// shaders import it instead of copying the numbers
+    #[must_use]
+    pub fn bindless_shader_header() -> String {
+        BindlessHandler::shader_header()
+    }
 
+    /// create a shader module from spir-v, see ``destroy``
+    /// # Errors
+    /// if the code isn't valid spir-v or there is no memory left
+    pub fn load_shader(&mut self, code: &[u32]) -> VkResult<ShaderHandle> {
+        let info = vk::ShaderModuleCreateInfo::default().code(code);
+        let module = unsafe { self.device.create_shader_module(&info, None) }?;
+        Ok(self.shaders.insert(module))
+    }
+
+    /// a stage for ``MaterialCreateInfo::shaders`` with the entry point ``entry``
+    /// none if the handle was destroyed
+    #[must_use]
+    pub fn shader_stage(
+        &self,
+        handle: ShaderHandle,
+        stage: vk::ShaderStageFlags,
+        entry: &'static CStr,
+    ) -> Option<vk::PipelineShaderStageCreateInfo<'static>> {
+        let module = *self.shaders.get(handle)?;
+        Some(
+            vk::PipelineShaderStageCreateInfo::default()
+                .name(entry)
+                .stage(stage)
+                .module(module),
+        )
+    }
+
+    /// destroy a resource, it's kept alive until the frames that used it have finished
+    /// destroyed handles are ignored, their indices are reused without pointing to the new resource
+    /// # Panics
+    /// if a material is still used somewhere else, for example by a ``RenderBatch``
+    pub fn destroy(&mut self, handle: impl Into<ResourceHandle>) {
+        self.scene_cache.invalidate();
+        match handle.into() {
+            ResourceHandle::Buffer(handle) => {
+                let Some(owned) = self.buffers.remove(handle) else {
+                    return;
+                };
+                for binding in &owned.bindings {
+                    for resource in self.bindless_handler.release(binding) {
+                        self.destroy_queue.push(resource);
+                    }
+                }
+                self.destroy_queue
+                    .push(DestroyResource::SharedBuffer(owned.buffer));
+            }
+            ResourceHandle::Texture(handle) => {
+                for resource in self.bindless_handler.release(&texture_binding(handle)) {
+                    self.destroy_queue.push(resource);
+                }
+            }
+            // shader modules aren't used by the frames, only while creating the pipelines
+            ResourceHandle::Shader(handle) => {
+                if let Some(module) = self.shaders.remove(handle) {
+                    unsafe { self.device.destroy_shader_module(module, None) };
+                }
+            }
+            ResourceHandle::Material(handle) => {
+                let Some(material) = self.owned_materials.remove(handle) else {
+                    return;
+                };
+                let material = Arc::into_inner(material)
+                    .expect("the material is still being used somewhere else");
+                for pipeline in
+                    std::iter::once(material.pipeline).chain(material.multiview_pipeline)
+                {
+                    self.destroy_queue
+                        .push(DestroyResource::Pipeline(self.device.clone(), pipeline));
+                }
+            }
+        }
+    }
+
+    /// create a new set of parameter values for the material, all values start zeroed
+    /// the index of the handle is passed to the shader to read them, see ``GetMaterialParams`` in ``material.slang``
+    /// returns none if there are already ``MAX_MATERIAL_INSTANCES``
+    pub fn create_material_instance(
+        &mut self,
+        material: Arc<Material>,
+    ) -> Option<MaterialInstanceHandle> {
+        self.material_instances
+            .insert(MaterialInstance::new(material))
+    }
+
+    /// change the parameters of an instance, only instances that changed are uploaded again
+    pub fn material_instance_mut(
+        &mut self,
+        handle: MaterialInstanceHandle,
+    ) -> Option<&mut MaterialInstance> {
+        self.scene_cache.invalidate();
+        self.material_instances.get_mut(handle)
+    }
+
+    /// the index of the handle can be given to a new instance right away
+    pub fn remove_material_instance(&mut self, handle: MaterialInstanceHandle) {
+        self.scene_cache.invalidate();
+        self.material_instances.remove(handle);
+    }
+
+    /// create a new particle system that is simulated and drawn every frame
+    /// ``material`` is used to draw the particles, it gets no vertex input
+    /// returns the index of the particle system
+    /// # Errors
+    /// if there is no space to allocate the buffers
+    /// or there are no free storage buffer slots left
+    pub fn add_particle_system(
+        &mut self,
+        info: &ParticleSystemCreateInfo,
+        material: MaterialCreateInfo,
+    ) -> VkResult<usize> {
+        let particle_size = ParticleSystem::buffer_size(info.capacity);
+        let counter_size = size_of::<ParticleCounters>() as u64;
+
+        let mut create_buffer = |size, usage, flags| -> VkResult<_> {
+            let buffer = Buffer::new(self.device.clone(), size, usage, flags)?;
+            let handle = self
+                .push_storage_slot(buffer.clone())
+                .ok_or(vk::Result::ERROR_OUT_OF_POOL_MEMORY)?;
+            Ok((buffer, handle))
+        };
+
+        let storage = (
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+        // the counters are written once from the cpu to initialize them
+        let counters = (
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        );
+
+        let particle_buffers = [
+            create_buffer(particle_size, storage.0, storage.1)?,
+            create_buffer(particle_size, storage.0, storage.1)?,
+        ];
+        let counter_buffers = [
+            create_buffer(counter_size, counters.0, counters.1)?,
+            create_buffer(counter_size, counters.0, counters.1)?,
+        ];
+
+        let material = self.build_material(material);
         self.materials.materials.push(material.clone());
-        material
+
+        let system = ParticleSystem::new(
+            self.device.clone(),
+            info,
+            material,
+            self.bindless_handler.pipeline_layout,
+            particle_buffers,
+            counter_buffers,
+        )?;
+
+        self.particle_systems.push(system);
+        Ok(self.particle_systems.len() - 1)
+    }
+
+    /// get a particle system to change its emitter
+    pub fn get_particle_system_mut(&mut self, index: usize) -> Option<&mut ParticleSystem> {
+        self.particle_systems.get_mut(index)
+    }
+
+    /// create a new sprite batch that is drawn every frame after the particle systems
+    /// the vertex input of ``material`` is replaced with one sprite per instance,
+    /// see ``shaders/sprites.slang`` in the application
+    /// returns the index of the sprite batch
+    pub fn add_sprite_batch(
+        &mut self,
+        mut material: MaterialCreateInfo,
+        mode: SpriteMode,
+    ) -> usize {
+        material.vertex_input = sprites::vertex_input();
+        material.vertex_pulling = false;
+        let material = self.build_material(material);
+        self.materials.materials.push(material.clone());
+
+        self.sprite_batches
+            .push(SpriteBatch::new(self.device.clone(), material, mode));
+        self.sprite_batches.len() - 1
+    }
+
+    /// copy the object id under the pixel to the cpu in the next frame, see ``MaterialCreateInfo::object_id``
+    /// the result arrives a few frames later in ``take_pick_result``, a newer request replaces the older one
+    /// nothing is picked if ``DeviceFeatures::object_ids`` isn't supported
+    pub fn request_pick(&mut self, pixel: [u32; 2]) {
+        self.picker.request(pixel);
+    }
+
+    /// the newest pick that arrived since the last call
+    pub fn take_pick_result(&mut self) -> Option<PickResult> {
+        self.picker.take_result()
+    }
+
+    /// copy a target to the cpu at the end of the next frame and write it to ``path`` a few frames later
+    /// ``.exr`` keeps the values, ``.png`` clamps them to 0..1
+    /// # Errors
+    /// if the file isn't a png or exr or the renderer was built without the ``image`` feature
+    pub fn dump_image(
+        &mut self,
+        target: DumpTarget,
+        path: impl Into<PathBuf>,
+    ) -> Result<(), DumpError> {
+        self.dumper.request(target, path.into())
+    }
+
+    /// get a sprite batch to change its sprites
+    pub fn get_sprite_batch_mut(&mut self, index: usize) -> Option<&mut SpriteBatch> {
+        self.sprite_batches.get_mut(index)
+    }
+
+    /// record custom commands every frame, after the passes in ``dependencies``, see ``raw_pass``
+    /// the dependencies are names from ``BUILTIN_PASSES`` or of raw passes that were added before
+    /// returns the built in pass it's recorded after
+    ///
+    /// the passes are dropped by ``reinitialize``, the objects they use belong to the old device
+    /// # Errors
+    /// if the name is already used or a dependency doesn't exist
+    pub fn add_raw_pass(
+        &mut self,
+        name: &str,
+        dependencies: &[&str],
+        record: RecordFn,
+    ) -> Result<&'static str, RawPassError> {
+        self.raw_passes.add(name, dependencies, record)
+    }
+
+    /// passes that depend on it are still recorded at the same place
+    /// returns false if there is no raw pass with the name
+    pub fn remove_raw_pass(&mut self, name: &str) -> bool {
+        self.scene_cache.invalidate();
+        self.raw_passes.remove(name)
     }
 }
 
 pub enum DestroyResource {
     Buffer(Buffer),
+    /// other clones keep the buffer alive after the frame has finished
+    SharedBuffer(Arc<Buffer>),
+    Texture(Arc<Texture>),
     Image(vk::Image),
     ImageView(vk::ImageView),
+    Pipeline(Arc<VulkanDevice>, vk::Pipeline),
+}
+
+impl Drop for DestroyResource {
+    fn drop(&mut self) {
+        if let Self::Pipeline(device, pipeline) = self {
+            unsafe { device.destroy_pipeline(*pipeline, None) };
+        }
+    }
 }
 
 impl Drop for RenderHandler {
     fn drop(&mut self) {
+        if let Err(err) = self.wait_idle() {
+            log::error!("failed to wait for the gpu before dropping the renderer: {err}");
+        }
         unsafe {
-            let _ = self.device.device_wait_idle();
             for frame in &self.frames {
                 frame.destroy(&self.device);
             }
             self.bindless_handler.destroy(&self.device);
+            for material in self.owned_materials.values() {
+                self.device.destroy_pipeline(material.pipeline, None);
+                if let Some(pipeline) = material.multiview_pipeline {
+                    self.device.destroy_pipeline(pipeline, None);
+                }
+            }
+            for module in self.shaders.values() {
+                self.device.destroy_shader_module(*module, None);
+            }
         }
     }
 }
+
+/// the features that depend on the device and the swapchain
+fn set_builtin_variants(values: &mut VariantValues, device: &VulkanDevice, swapchain: &Swapchain) {
+    values.set("oit", device.features.weighted_blended_oit);
+    values.set("msaa", swapchain.samples != vk::SampleCountFlags::TYPE_1);
+}
+
+/// the bindless slot of the texture, with the generation the handle was given out with
+fn texture_binding(handle: TextureHandle) -> bindless::BindlessResourceHandle {
+    bindless::BindlessResourceHandle {
+        index: handle.index,
+        ty: bindless::BindlessResourceType::Texture,
+        generation: handle.generation,
+    }
+}
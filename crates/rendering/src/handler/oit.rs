@@ -0,0 +1,193 @@
+// composites weighted blended transparency on to the hdr target, runs right after the main pass
+// the materials with ``Transparency::WeightedBlended`` sum up their weighted colors in the accumulation target
+// and multiply the revealage target with how much of the background they let through,
+// this pass turns the sum in to an average and blends it over the hdr target
+//
+// the pass is only recorded if a visible batch is weighted blended
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::{
+    types::Transparency,
+    vulkan::{Swapchain, VulkanDevice},
+};
+
+use super::{bindless::BindlessHandler, render_batch::RenderBatch};
+
+/// needs to match ``WORK_GROUP_SIZE`` in ``shaders/oit.slang``
+const WORK_GROUP_SIZE: u32 = 8;
+
+/// the push constants used by the resolve shader
+/// needs to match ``shaders/oit.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct OitPushConstants {
+    hdr_image: u32,
+    accum_image: u32,
+    revealage_image: u32,
+    _padding: u32,
+    extent: [u32; 2],
+}
+
+pub(crate) struct OitResolve {
+    device: Arc<VulkanDevice>,
+    pipeline: Option<vk::Pipeline>,
+}
+
+impl OitResolve {
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        swapchain: &Swapchain,
+        bindless: &BindlessHandler,
+    ) -> Self {
+        let resolve = Self {
+            device,
+            pipeline: None,
+        };
+
+        resolve.on_resize(swapchain, bindless);
+        resolve
+    }
+
+    /// write the new transparency targets to the bindless descriptors
+    /// they are no storage images if weighted blended transparency isn't supported, then nothing is written
+    /// the descriptor sets must not be in use
+    /// # Panics
+    /// if the swapchain has more images than ``BindlessHandler::MAX_SWAPCHAIN_IMAGES``
+    pub fn on_resize(&self, swapchain: &Swapchain, bindless: &BindlessHandler) {
+        if !self.device.features.weighted_blended_oit {
+            return;
+        }
+
+        assert!(
+            swapchain.images.len() <= BindlessHandler::MAX_SWAPCHAIN_IMAGES,
+            "too many swapchain images"
+        );
+
+        for (i, image) in swapchain.images.iter().enumerate() {
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                image.accum_view,
+                BindlessHandler::OIT_ACCUM_SLOT + i,
+            );
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                image.revealage_view,
+                BindlessHandler::OIT_REVEALAGE_SLOT + i,
+            );
+        }
+    }
+
+    /// set the compute shader that composites the transparency targets on to the hdr target
+    /// the shader module is not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_shader(
+        &mut self,
+        stage: vk::PipelineShaderStageCreateInfo,
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let create_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+
+        let pipeline = unsafe {
+            self.device
+                .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, err)| err)?
+        }[0];
+
+        if let Some(old) = self.pipeline.replace(pipeline) {
+            unsafe { self.device.destroy_pipeline(old, None) };
+        }
+
+        Ok(())
+    }
+
+    /// composite the transparency on to the hdr target, if any of the batches on the ``layers`` is weighted blended
+    /// needs to be called after the main render pass ended
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        layout: vk::PipelineLayout,
+        batches: &[RenderBatch],
+        layers: u32,
+    ) {
+        let Some(pipeline) = self.pipeline else {
+            return;
+        };
+
+        let used = batches.iter().any(|batch| {
+            batch.is_visible_in(layers) && batch.transparency() == Transparency::WeightedBlended
+        });
+        if !used {
+            return;
+        }
+
+        let device = &self.device;
+        let extent = swapchain.get_image_extent();
+
+        // the main pass wrote every target the shader reads
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ),
+        );
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline);
+
+        let push_constants = OitPushConstants {
+            hdr_image: (BindlessHandler::HDR_TARGET_SLOT + image_index as usize) as u32,
+            accum_image: (BindlessHandler::OIT_ACCUM_SLOT + image_index as usize) as u32,
+            revealage_image: (BindlessHandler::OIT_REVEALAGE_SLOT + image_index as usize) as u32,
+            _padding: 0,
+            extent: [extent.width, extent.height],
+        };
+
+        let data = std::slice::from_raw_parts(
+            std::ptr::from_ref(&push_constants).cast::<u8>(),
+            size_of::<OitPushConstants>(),
+        );
+        device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+
+        device.cmd_dispatch(
+            cmd,
+            extent.width.div_ceil(WORK_GROUP_SIZE),
+            extent.height.div_ceil(WORK_GROUP_SIZE),
+            1,
+        );
+
+        // the later passes read the hdr target in shaders or with a blit
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(
+                    vk::PipelineStageFlags2::COMPUTE_SHADER
+                        | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                        | vk::PipelineStageFlags2::BLIT,
+                )
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::TRANSFER_READ,
+                ),
+        );
+    }
+}
+
+impl Drop for OitResolve {
+    fn drop(&mut self) {
+        if let Some(pipeline) = self.pipeline {
+            unsafe { self.device.destroy_pipeline(pipeline, None) };
+        }
+    }
+}
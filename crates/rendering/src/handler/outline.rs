@@ -0,0 +1,330 @@
+// draws colored outlines around objects on top of the tone mapped frame, before the ui
+// a pixel belongs to an outline if a pixel of an outlined object is within the width in the object id target
+// but the pixel itself isn't part of that object, so only the visible parts of an object get an outline
+//
+// ``OutlineMode::DepthTested`` hides the outline where something in front of the object covers it,
+// ``OutlineMode::XRay`` draws it on top of everything
+//
+// the outlined objects are written to a storage buffer of the frame at ``BindlessHandler::OUTLINE_SLOT``
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+use math::Color;
+
+use crate::vulkan::{Buffer, Swapchain, VulkanDevice};
+
+use super::{
+    bindless::{BindlessHandler, BindlessResourceHandle, BindlessResourceType},
+    ui, FLYING_FRAMES,
+};
+
+/// the most objects that can have an outline at the same time, the rest is skipped
+pub const MAX_OUTLINES: usize = 256;
+/// the widest outline in pixels, wider ones are clamped
+pub const MAX_OUTLINE_WIDTH: u32 = 8;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum OutlineMode {
+    /// hidden behind things that are closer than the object
+    #[default]
+    DepthTested = 0,
+    /// visible through everything
+    XRay = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlineStyle {
+    pub color: Color,
+    /// in pixels, up to ``MAX_OUTLINE_WIDTH``
+    pub width: u32,
+    pub mode: OutlineMode,
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(1.0, 0.5, 0.05),
+            width: 2,
+            mode: OutlineMode::default(),
+        }
+    }
+}
+
+/// an object that gets an outline, see ``RenderHandler::set_outlines``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outline {
+    /// the ``DrawData::object_id`` of the draws of the object
+    pub object_id: u32,
+    pub style: OutlineStyle,
+}
+
+/// needs to match ``OutlinedObject`` in ``shaders/outline.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct GpuOutline {
+    object_id: u32,
+    width: u32,
+    mode: u32,
+    _padding: u32,
+    color: [f32; 4],
+}
+
+/// the push constants used by the outline shader
+/// needs to match ``shaders/outline.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct OutlinePushConstants {
+    object_id_image: u32,
+    depth_image: u32,
+    count: u32,
+    /// the widest outline of the frame, limits how far the shader searches
+    max_width: u32,
+    /// the depth of the near plane, so the shader knows what is in front with and without reverse z
+    near_depth: f32,
+    /// 1 if the swapchain isn't srgb and the colors have to be encoded by the shader
+    to_srgb: u32,
+}
+
+pub(crate) struct OutlinePass {
+    device: Arc<VulkanDevice>,
+    renderpass: vk::RenderPass,
+    framebuffers: Vec<vk::Framebuffer>,
+    pipeline: Option<vk::Pipeline>,
+    buffers: [Arc<Buffer>; FLYING_FRAMES],
+    outlines: Vec<GpuOutline>,
+    near_depth: f32,
+    srgb: bool,
+}
+
+impl OutlinePass {
+    /// # Errors
+    /// if there is no space to allocate the buffers
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        swapchain: &Swapchain,
+        bindless: &BindlessHandler,
+    ) -> VkResult<Self> {
+        let buffer = || {
+            Buffer::new(
+                device.clone(),
+                (MAX_OUTLINES * size_of::<GpuOutline>()) as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            )
+        };
+        let buffers = [buffer()?, buffer()?];
+
+        let handle = BindlessResourceHandle::reserved(
+            BindlessHandler::OUTLINE_SLOT,
+            BindlessResourceType::StorageBuffer,
+        );
+        bindless.set_per_frame_buffer(&*device, buffers.each_ref().map(|v| v.handle()), handle);
+
+        // keeps the tone mapped image and blends the outlines on top, like the ui composite
+        let renderpass = ui::create_composite_renderpass(
+            &device,
+            swapchain.image_format(),
+            swapchain.output_layout(),
+        )?;
+
+        let mut pass = Self {
+            device,
+            renderpass,
+            framebuffers: vec![],
+            pipeline: None,
+            buffers,
+            outlines: vec![],
+            near_depth: 0.0,
+            srgb: swapchain.is_srgb(),
+        };
+
+        pass.on_resize(swapchain, bindless)?;
+        Ok(pass)
+    }
+
+    /// recreate the framebuffers and write the new object id targets to the bindless descriptors
+    /// the descriptor sets and the old framebuffers must not be in use
+    /// # Errors
+    /// if a framebuffer couldn't be created
+    /// # Panics
+    /// if the swapchain has more images than ``BindlessHandler::MAX_SWAPCHAIN_IMAGES``
+    pub fn on_resize(&mut self, swapchain: &Swapchain, bindless: &BindlessHandler) -> VkResult<()> {
+        assert!(
+            swapchain.images.len() <= BindlessHandler::MAX_SWAPCHAIN_IMAGES,
+            "too many swapchain images"
+        );
+
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { self.device.destroy_framebuffer(framebuffer, None) };
+        }
+        self.srgb = swapchain.is_srgb();
+
+        // the object id targets are only storage images if the gpu can write object ids
+        if !self.device.features.object_ids {
+            return Ok(());
+        }
+
+        let extent = swapchain.get_image_extent();
+
+        for (i, image) in swapchain.images.iter().enumerate() {
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                image.object_id_view,
+                BindlessHandler::OBJECT_ID_TARGET_SLOT + i,
+            );
+
+            let attachments = [image.output_view()];
+            let framebuffer_info = vk::FramebufferCreateInfo::default()
+                .render_pass(self.renderpass)
+                .attachments(&attachments)
+                .width(extent.width)
+                .height(extent.height)
+                .layers(1);
+
+            self.framebuffers
+                .push(unsafe { self.device.create_framebuffer(&framebuffer_info, None)? });
+        }
+
+        Ok(())
+    }
+
+    /// set the fullscreen shader that draws the outlines, it's drawn with 3 vertices and no vertex input
+    /// the shader modules are not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_shader(
+        &mut self,
+        stages: &[vk::PipelineShaderStageCreateInfo],
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        let pipeline = ui::create_pipeline(
+            &self.device,
+            stages,
+            &vk::PipelineVertexInputStateCreateInfo::default(),
+            blend,
+            layout,
+            self.renderpass,
+        )?;
+
+        if let Some(old) = self.pipeline.replace(pipeline) {
+            unsafe { self.device.destroy_pipeline(old, None) };
+        }
+
+        Ok(())
+    }
+
+    /// replace the outlined objects, ``near_depth`` is ``Projection::near_depth`` of the camera
+    /// the objects past ``MAX_OUTLINES`` are skipped
+    pub fn set_outlines(&mut self, outlines: &[Outline], near_depth: f32) {
+        self.near_depth = near_depth;
+        self.outlines = outlines
+            .iter()
+            .filter(|outline| outline.object_id != 0 && outline.style.width > 0)
+            .take(MAX_OUTLINES)
+            .map(|outline| GpuOutline {
+                object_id: outline.object_id,
+                width: outline.style.width.min(MAX_OUTLINE_WIDTH),
+                mode: outline.style.mode as u32,
+                _padding: 0,
+                color: outline.style.color.to_array(),
+            })
+            .collect();
+    }
+
+    /// write the outlines to the buffer of the frame, the fence of the frame has to be signaled
+    pub fn upload(&self, frame_index: usize) {
+        if !self.outlines.is_empty() {
+            self.buffers[frame_index].write(0, &self.outlines);
+        }
+    }
+
+    /// needs to be recorded after the tone mapping
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        layout: vk::PipelineLayout,
+    ) {
+        let Some(pipeline) = self.pipeline else {
+            return;
+        };
+        if self.outlines.is_empty() || self.framebuffers.len() != swapchain.images.len() {
+            return;
+        }
+
+        let device = &self.device;
+
+        // the object ids and the depth are written by the main pass
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_READ),
+        );
+
+        let extent = swapchain.get_image_extent();
+        let render_area = vk::Rect2D::default().extent(extent);
+
+        let begin_info = vk::RenderPassBeginInfo::default()
+            .render_pass(self.renderpass)
+            .framebuffer(self.framebuffers[image_index as usize])
+            .render_area(render_area);
+
+        device.cmd_begin_render_pass(cmd, &begin_info, vk::SubpassContents::INLINE);
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline);
+
+        let viewport = vk::Viewport::default()
+            .width(extent.width as f32)
+            .height(extent.height as f32)
+            .max_depth(1.0);
+        device.cmd_set_viewport(cmd, 0, &[viewport]);
+        device.cmd_set_scissor(cmd, 0, &[render_area]);
+
+        let push_constants = OutlinePushConstants {
+            object_id_image: (BindlessHandler::OBJECT_ID_TARGET_SLOT + image_index as usize) as u32,
+            depth_image: (BindlessHandler::DEPTH_TARGET_SLOT + image_index as usize) as u32,
+            count: self.outlines.len() as u32,
+            max_width: self
+                .outlines
+                .iter()
+                .map(|outline| outline.width)
+                .max()
+                .unwrap_or(0),
+            near_depth: self.near_depth,
+            to_srgb: u32::from(!self.srgb),
+        };
+        ui::push(device, cmd, layout, &push_constants);
+
+        device.cmd_draw(cmd, 3, 1, 0, 0);
+        device.cmd_end_render_pass(cmd);
+    }
+}
+
+impl Drop for OutlinePass {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(pipeline) = self.pipeline {
+                self.device.destroy_pipeline(pipeline, None);
+            }
+            for framebuffer in &self.framebuffers {
+                self.device.destroy_framebuffer(*framebuffer, None);
+            }
+            self.device.destroy_render_pass(self.renderpass, None);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Swapchain, ValidationCounts, VulkanDevice};
+
+/// how many presents are allowed to be queued up at the same time
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyMode {
+    /// let up to ``FLYING_FRAMES`` frames queue up, gives the best frame rate
+    #[default]
+    Throughput,
+    /// only one present can be queued at a time
+    /// the frame rate might drop, but input feels more responsive (editors, tools)
+    LowLatency,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameStats {
+    /// the time between the last two calls to ``on_render``
+    pub frame_time: Duration,
+    /// the time from starting to record a frame until it has been presented
+    /// if ``VK_KHR_present_wait`` isn't supported, this is measured until the gpu finished rendering
+    pub latency: Duration,
+    /// how many frames have been presented so far
+    pub presented_frames: u64,
+    /// how many frames only composited the scene of an earlier frame again, see ``RenderHandler::reuse_scene``
+    pub reused_scenes: u64,
+    /// the messages of the validation layer since the device was created
+    pub validation: ValidationCounts,
+    /// tells if the latency has been measured using ``VK_KHR_present_wait``
+    pub present_wait: bool,
+    /// how long the gpu took to execute the commands of the last finished frame
+    /// ``None`` if the graphics queue doesn't support timestamps
+    pub gpu_time: Option<Duration>,
+}
+
+/// a frame that has been submitted but we don't know yet if it has been presented
+struct PendingFrame {
+    present_id: u64,
+    fence: vk::Fence,
+    started: Instant,
+}
+
+pub(crate) struct FramePacer {
+    pub mode: LatencyMode,
+    pub stats: FrameStats,
+    last_frame: Option<Instant>,
+    /// present ids need to be increasing, 0 means no id
+    next_present_id: u64,
+    pending: VecDeque<PendingFrame>,
+}
+
+impl FramePacer {
+    pub fn new(device: &VulkanDevice) -> Self {
+        Self {
+            mode: LatencyMode::default(),
+            stats: FrameStats {
+                present_wait: device.features.present_wait,
+                ..Default::default()
+            },
+            last_frame: None,
+            next_present_id: 1,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// start a new frame, returns the time the frame has been started
+    pub fn begin_frame(&mut self) -> Instant {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame {
+            self.stats.frame_time = now - last;
+        }
+        self.last_frame = Some(now);
+        now
+    }
+
+    /// the present id that should be used for the next present
+    pub fn next_present_id(&self) -> u64 {
+        self.next_present_id
+    }
+
+    /// tell the pacer that a frame has been submitted
+    /// ``fence`` is signaled once the gpu finished rendering the frame
+    pub fn submitted(&mut self, fence: vk::Fence, started: Instant) {
+        self.pending.push_back(PendingFrame {
+            present_id: self.next_present_id,
+            fence,
+            started,
+        });
+        self.next_present_id += 1;
+    }
+
+    /// in low latency mode this blocks until the last frame has been presented
+    /// # Safety
+    /// the fences of the pending frames must still be valid
+    pub unsafe fn wait_for_previous(
+        &mut self,
+        device: &VulkanDevice,
+        swapchain: &Swapchain,
+    ) -> VkResult<()> {
+        if self.mode != LatencyMode::LowLatency {
+            return Ok(());
+        }
+
+        let Some(last) = self.pending.back() else {
+            return Ok(());
+        };
+
+        if let Some(present_wait) = &swapchain.present_wait {
+            match present_wait.wait_for_present(swapchain.handle, last.present_id, u64::MAX) {
+                // the swapchain is going to be recreated, so there is nothing to wait for
+                Ok(()) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {}
+                Err(err) => return Err(err),
+            }
+        } else {
+            device.wait_for_fences(&[last.fence], true, u64::MAX)?;
+        }
+
+        Ok(())
+    }
+
+    /// check what frames have been presented and update the stats
+    /// ``reused_fence`` is the fence that is about to be reset,
+    /// frames that use that fence need to be resolved now, or we would lose track of them
+    /// # Safety
+    /// the fences of the pending frames must still be valid
+    pub unsafe fn poll(
+        &mut self,
+        device: &VulkanDevice,
+        swapchain: &Swapchain,
+        reused_fence: vk::Fence,
+    ) -> VkResult<()> {
+        while let Some(frame) = self.pending.front() {
+            let presented = if let Some(present_wait) = &swapchain.present_wait {
+                match present_wait.wait_for_present(swapchain.handle, frame.present_id, 0) {
+                    Ok(()) => true,
+                    Err(vk::Result::TIMEOUT) => false,
+                    // the frame is never going to be presented, so just drop it
+                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                        self.pending.pop_front();
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                }
+            } else if frame.fence == reused_fence {
+                device.wait_for_fences(&[frame.fence], true, u64::MAX)?;
+                true
+            } else {
+                device.get_fence_status(frame.fence)?
+            };
+
+            if !presented {
+                break;
+            }
+
+            self.stats.latency = frame.started.elapsed();
+            self.stats.presented_frames += 1;
+            self.pending.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// forget all pending frames, needed when the swapchain gets recreated
+    /// as the present ids only count for the old swapchain
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
@@ -0,0 +1,370 @@
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+use math::Color;
+
+use crate::{
+    types::Material,
+    vulkan::{Buffer, VulkanDevice},
+};
+
+use super::bindless::BindlessResourceHandle;
+
+/// how many threads a compute work group of the particle shader has
+pub const PARTICLE_WORK_GROUP_SIZE: u32 = 64;
+
+/// the layout of a single particle on the gpu
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Particle {
+    /// w is the age of the particle in seconds
+    pub position: [f32; 4],
+    /// w is the lifetime of the particle in seconds
+    pub velocity: [f32; 4],
+    pub color: [f32; 4],
+}
+
+/// the counters of one particle buffer
+/// this is also used as the argument buffer for the indirect dispatch and draw
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleCounters {
+    /// ``instance_count`` is the number of alive particles
+    pub draw: vk::DrawIndirectCommand,
+    /// the work groups needed to simulate all alive particles
+    pub dispatch: vk::DispatchIndirectCommand,
+    _padding: u32,
+}
+
+impl Default for ParticleCounters {
+    fn default() -> Self {
+        Self {
+            // every particle is a quad made out of 2 triangles
+            draw: vk::DrawIndirectCommand {
+                vertex_count: 6,
+                instance_count: 0,
+                first_vertex: 0,
+                first_instance: 0,
+            },
+            dispatch: vk::DispatchIndirectCommand { x: 0, y: 1, z: 1 },
+            _padding: 0,
+        }
+    }
+}
+
+/// describes where and how particles are spawned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmitterConfig {
+    pub position: [f32; 3],
+    /// particles are spawned randomly inside this radius
+    pub spawn_radius: f32,
+    pub velocity: [f32; 3],
+    /// random velocity that is added to every particle
+    pub velocity_spread: f32,
+    /// constant acceleration applied to every particle
+    pub gravity: [f32; 3],
+    pub color: Color,
+    /// how long a particle lives in seconds
+    pub lifetime: f32,
+    /// how many particles are spawned per second
+    pub spawn_rate: f32,
+    /// the size of the billboard
+    pub size: f32,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            spawn_radius: 0.1,
+            velocity: [0.0, 1.0, 0.0],
+            velocity_spread: 0.5,
+            gravity: [0.0, -9.81, 0.0],
+            color: Color::WHITE,
+            lifetime: 2.0,
+            spawn_rate: 100.0,
+            size: 0.02,
+        }
+    }
+}
+
+/// the push constants used by all particle shaders
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct ParticlePushConstants {
+    /// w is the spawn radius
+    position: [f32; 4],
+    /// w is the velocity spread
+    velocity: [f32; 4],
+    /// w is the delta time
+    gravity: [f32; 4],
+    color: [f32; 4],
+    lifetime: f32,
+    size: f32,
+    spawn_count: u32,
+    capacity: u32,
+    seed: u32,
+    src_particles: u32,
+    dst_particles: u32,
+    src_counters: u32,
+    dst_counters: u32,
+}
+
+pub struct ParticleSystemCreateInfo {
+    /// the max amount of particles that can be alive at the same time
+    pub capacity: u32,
+    pub emitter: EmitterConfig,
+    /// the same seed spawns the same particles every run
+    pub seed: u32,
+    /// spawns new particles in to the destination buffer
+    pub spawn_shader: vk::PipelineShaderStageCreateInfo<'static>,
+    /// integrates the alive particles and copies them in to the destination buffer
+    pub simulate_shader: vk::PipelineShaderStageCreateInfo<'static>,
+    /// writes the dispatch arguments for the next frame, runs on a single thread
+    pub finalize_shader: vk::PipelineShaderStageCreateInfo<'static>,
+}
+
+/// a particle system that is simulated and drawn completely on the gpu
+/// the particles are stored in two buffers that are swapped every frame,
+/// the simulation reads from one and writes the surviving particles to the other one
+/// the amount of alive particles is never read back, it's only used for indirect dispatches and draws
+///
+/// the compute shader modules are not destroyed by the particle system
+pub struct ParticleSystem {
+    device: Arc<VulkanDevice>,
+    pub emitter: EmitterConfig,
+    capacity: u32,
+    material: Arc<Material>,
+    spawn_pipeline: vk::Pipeline,
+    simulate_pipeline: vk::Pipeline,
+    finalize_pipeline: vk::Pipeline,
+    particle_buffers: [(Arc<Buffer>, BindlessResourceHandle); 2],
+    counter_buffers: [(Arc<Buffer>, BindlessResourceHandle); 2],
+    /// what buffer is read from this frame, swapped once the frame has been recorded
+    src: usize,
+    /// particles that should have been spawned but didn't make it to a whole particle yet
+    spawn_accumulator: f32,
+    /// what is left of ``spawn_accumulator`` once the prepared frame has been recorded
+    next_spawn_accumulator: f32,
+    seed: u32,
+    push_constants: ParticlePushConstants,
+}
+
+impl ParticleSystem {
+    /// # Errors
+    /// if there was an issue creating the pipelines
+    pub(crate) fn new(
+        device: Arc<VulkanDevice>,
+        info: &ParticleSystemCreateInfo,
+        material: Arc<Material>,
+        layout: vk::PipelineLayout,
+        particle_buffers: [(Arc<Buffer>, BindlessResourceHandle); 2],
+        counter_buffers: [(Arc<Buffer>, BindlessResourceHandle); 2],
+    ) -> VkResult<Self> {
+        let create_infos = [
+            info.spawn_shader,
+            info.simulate_shader,
+            info.finalize_shader,
+        ]
+        .map(|stage| {
+            vk::ComputePipelineCreateInfo::default()
+                .stage(stage)
+                .layout(layout)
+        });
+
+        let pipelines = unsafe {
+            device
+                .create_compute_pipelines(vk::PipelineCache::null(), &create_infos, None)
+                .map_err(|(_, err)| err)?
+        };
+
+        for (buffer, _) in &counter_buffers {
+            buffer.write(0, &[ParticleCounters::default()]);
+        }
+
+        Ok(Self {
+            device,
+            emitter: info.emitter,
+            capacity: info.capacity,
+            material,
+            spawn_pipeline: pipelines[0],
+            simulate_pipeline: pipelines[1],
+            finalize_pipeline: pipelines[2],
+            particle_buffers,
+            counter_buffers,
+            src: 0,
+            spawn_accumulator: 0.0,
+            next_spawn_accumulator: 0.0,
+            seed: info.seed,
+            push_constants: ParticlePushConstants::default(),
+        })
+    }
+
+    /// the size of one particle buffer in bytes
+    #[must_use]
+    pub fn buffer_size(capacity: u32) -> u64 {
+        u64::from(capacity) * size_of::<Particle>() as u64
+    }
+
+    /// calculate how many particles need to be spawned this frame
+    /// nothing changes until ``advance``, so a frame that fails to record can be prepared again
+    #[allow(clippy::cast_sign_loss)]
+    pub(crate) fn prepare(&mut self, delta_time: f32, frame_count: u64) {
+        let accumulator = self.spawn_accumulator + self.emitter.spawn_rate * delta_time;
+        let spawn_count = accumulator.floor().min(self.capacity as f32);
+        self.next_spawn_accumulator = accumulator - spawn_count;
+
+        let e = &self.emitter;
+        let dst = 1 - self.src;
+
+        self.push_constants = ParticlePushConstants {
+            position: [e.position[0], e.position[1], e.position[2], e.spawn_radius],
+            velocity: [
+                e.velocity[0],
+                e.velocity[1],
+                e.velocity[2],
+                e.velocity_spread,
+            ],
+            gravity: [e.gravity[0], e.gravity[1], e.gravity[2], delta_time],
+            color: e.color.to_array(),
+            lifetime: e.lifetime,
+            size: e.size,
+            spawn_count: spawn_count as u32,
+            capacity: self.capacity,
+            // spread the frames, so emitters with close seeds don't spawn the same particles a frame apart
+            seed: self.seed ^ (frame_count as u32).wrapping_mul(0x9e37_79b9),
+            src_particles: self.particle_buffers[self.src].1.index as u32,
+            dst_particles: self.particle_buffers[dst].1.index as u32,
+            src_counters: self.counter_buffers[self.src].1.index as u32,
+            dst_counters: self.counter_buffers[dst].1.index as u32,
+        };
+    }
+
+    /// swap the buffers and keep the particles that were left over
+    /// needs to be called once the prepared frame has been recorded
+    pub(crate) fn advance(&mut self) {
+        self.src = 1 - self.src;
+        self.spawn_accumulator = self.next_spawn_accumulator;
+    }
+
+    unsafe fn push_constants(
+        &self,
+        device: &VulkanDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+    ) {
+        let data = std::slice::from_raw_parts(
+            std::ptr::from_ref(&self.push_constants).cast::<u8>(),
+            size_of::<ParticlePushConstants>(),
+        );
+        device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+    }
+
+    /// record the simulation, needs to be called outside of a render pass
+    pub(crate) unsafe fn record_update(
+        &self,
+        device: &VulkanDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+    ) {
+        let src_counters = self.counter_buffers[self.src].0.handle();
+        let dispatch_offset = std::mem::offset_of!(ParticleCounters, dispatch) as u64;
+
+        self.push_constants(device, cmd, layout);
+
+        // the last frame might still be drawing the particles we are about to overwrite
+        // reads don't need to be made available, waiting for them is enough
+        compute_barrier(
+            device,
+            cmd,
+            vk::PipelineStageFlags2::DRAW_INDIRECT | vk::PipelineStageFlags2::VERTEX_SHADER,
+            vk::AccessFlags2::NONE,
+        );
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, self.simulate_pipeline);
+        device.cmd_dispatch_indirect(cmd, src_counters, dispatch_offset);
+
+        compute_barrier(
+            device,
+            cmd,
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_STORAGE_WRITE,
+        );
+
+        let spawn_count = self.push_constants.spawn_count;
+        if spawn_count > 0 {
+            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, self.spawn_pipeline);
+            device.cmd_dispatch(cmd, spawn_count.div_ceil(PARTICLE_WORK_GROUP_SIZE), 1, 1);
+
+            compute_barrier(
+                device,
+                cmd,
+                vk::PipelineStageFlags2::COMPUTE_SHADER,
+                vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            );
+        }
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, self.finalize_pipeline);
+        device.cmd_dispatch(cmd, 1, 1, 1);
+
+        // the draw reads the particles and the counters written by the compute shaders
+        let barrier = vk::MemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .dst_stage_mask(
+                vk::PipelineStageFlags2::DRAW_INDIRECT | vk::PipelineStageFlags2::VERTEX_SHADER,
+            )
+            .dst_access_mask(
+                vk::AccessFlags2::INDIRECT_COMMAND_READ | vk::AccessFlags2::SHADER_STORAGE_READ,
+            );
+
+        device.memory_barrier(cmd, barrier);
+    }
+
+    /// draw the particles as instanced billboards, needs to be called inside the render pass
+    pub(crate) unsafe fn record_draw(
+        &self,
+        device: &VulkanDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        swapchain_size: vk::Extent2D,
+    ) {
+        let dst_counters = self.counter_buffers[1 - self.src].0.handle();
+        let draw_offset = std::mem::offset_of!(ParticleCounters, draw) as u64;
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, self.material.pipeline);
+        self.material
+            .info
+            .viewport
+            .apply(device, cmd, swapchain_size);
+        self.push_constants(device, cmd, layout);
+        device.cmd_draw_indirect(cmd, dst_counters, draw_offset, 1, 0);
+    }
+}
+
+unsafe fn compute_barrier(
+    device: &VulkanDevice,
+    cmd: vk::CommandBuffer,
+    src_stage: vk::PipelineStageFlags2,
+    src_access: vk::AccessFlags2,
+) {
+    let barrier = vk::MemoryBarrier2::default()
+        .src_stage_mask(src_stage)
+        .src_access_mask(src_access)
+        .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+        .dst_access_mask(
+            vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+        );
+
+    device.memory_barrier(cmd, barrier);
+}
+
+impl Drop for ParticleSystem {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_pipeline(self.spawn_pipeline, None);
+            self.device.destroy_pipeline(self.simulate_pipeline, None);
+            self.device.destroy_pipeline(self.finalize_pipeline, None);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+// reads the object id under a pixel back to the cpu, for selecting things with the mouse
+// the pixel is copied out of the object id target after the main pass in to a small buffer of the frame,
+// the buffer is read once the fence of the frame has been waited on the next time it's used,
+// so the result arrives ``FLYING_FRAMES`` frames after the request
+//
+// only materials with ``MaterialCreateInfo::object_id`` write to the target, everything else stays 0
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Buffer, Swapchain, VulkanDevice};
+
+use super::FLYING_FRAMES;
+
+/// the object id under a pixel, see ``RenderHandler::request_pick``
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PickResult {
+    /// in pixels from the top left corner
+    pub pixel: [u32; 2],
+    /// the ``DrawData::object_id`` of the draw that covers the pixel, 0 if there is none
+    pub object_id: u32,
+}
+
+pub(crate) struct ObjectPicker {
+    device: Arc<VulkanDevice>,
+    /// the copied pixel of every frame
+    buffers: [Arc<Buffer>; FLYING_FRAMES],
+    /// the pixel every frame copied, None if it didn't copy one
+    in_flight: [Option<[u32; 2]>; FLYING_FRAMES],
+    /// copied by the next frame
+    request: Option<[u32; 2]>,
+    result: Option<PickResult>,
+}
+
+impl ObjectPicker {
+    pub fn new(device: Arc<VulkanDevice>) -> VkResult<Self> {
+        let buffer = || {
+            Buffer::new(
+                device.clone(),
+                size_of::<u32>() as u64,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )
+        };
+        let buffers = [buffer()?, buffer()?];
+
+        Ok(Self {
+            device,
+            buffers,
+            in_flight: [None; FLYING_FRAMES],
+            request: None,
+            result: None,
+        })
+    }
+
+    /// copy the pixel in the next frame, replaces the request that wasn't recorded yet
+    pub fn request(&mut self, pixel: [u32; 2]) {
+        self.request = Some(pixel);
+    }
+
+    /// the newest result, it's only returned once
+    pub fn take_result(&mut self) -> Option<PickResult> {
+        self.result.take()
+    }
+
+    /// read the pixel the frame copied the last time it was used
+    /// the fence of the frame has to be signaled
+    pub fn collect(&mut self, frame_index: usize) {
+        if let Some(pixel) = self.in_flight[frame_index].take() {
+            self.result = Some(PickResult {
+                pixel,
+                object_id: self.buffers[frame_index].read::<u32>()[0],
+            });
+        }
+    }
+
+    /// needs to be recorded after the main pass
+    pub unsafe fn record(
+        &mut self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        frame_index: usize,
+    ) {
+        let Some(pixel) = self.request.take() else {
+            return;
+        };
+
+        let extent = swapchain.get_image_extent();
+        if !self.device.features.object_ids || pixel[0] >= extent.width || pixel[1] >= extent.height
+        {
+            return;
+        }
+
+        let image = swapchain.images[image_index as usize].object_id_image;
+        let buffer = &self.buffers[frame_index];
+
+        let range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+
+        // the main pass leaves the target in the general layout
+        let image_barrier = vk::ImageMemoryBarrier2::default()
+            .image(image)
+            .old_layout(vk::ImageLayout::GENERAL)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+            .subresource_range(range);
+
+        self.device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&[image_barrier]),
+        );
+
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(
+                vk::ImageSubresourceLayers::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1),
+            )
+            .image_offset(vk::Offset3D {
+                x: pixel[0] as i32,
+                y: pixel[1] as i32,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            });
+
+        self.device.cmd_copy_image_to_buffer(
+            cmd,
+            image,
+            vk::ImageLayout::GENERAL,
+            buffer.handle(),
+            &[region],
+        );
+
+        let buffer_barrier = vk::BufferMemoryBarrier2::default()
+            .buffer(buffer.handle())
+            .size(vk::WHOLE_SIZE)
+            .src_stage_mask(vk::PipelineStageFlags2::COPY)
+            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+            .dst_access_mask(vk::AccessFlags2::HOST_READ);
+
+        self.device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().buffer_memory_barriers(&[buffer_barrier]),
+        );
+
+        self.in_flight[frame_index] = Some(pixel);
+    }
+}
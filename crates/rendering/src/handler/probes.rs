@@ -0,0 +1,825 @@
+// environment probes, the scene is rendered from a point in to the six faces of a cubemap,
+// so shiny materials can reflect what is around them, see ``shaders/environment.slang`` in the application
+//
+// the faces are drawn with the batches of the main pass, before it, in to targets that are compatible
+// with the main render pass, so every material can be used without building it again.
+// the renderer doesn't know the camera, the application tells it which uniform buffer holds it
+// and how the camera of a face looks in it, see ``ProbeCamera``. that buffer is overwritten on the gpu for every face
+// and gets its contents back before the main pass
+//
+// with ``RendererConfig::multiview`` all faces are drawn in one pass instead, in to the layers of the targets.
+// the cameras of the faces are in the ``ViewBuffer`` then and the uniform buffer isn't touched,
+// only materials that read their camera from there can be drawn, see ``MaterialCreateInfo::multiview``
+//
+// the faces are rendered like with a normal camera and mirrored when they're copied in to the cube,
+// so the winding of the triangles doesn't change. the mips are blitted down from the face,
+// rough reflections and diffuse light sample the smaller ones
+//
+// the probes are in a storage buffer at ``BindlessHandler::PROBES_SLOT``
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+use math::{Mat4, Projection, Vec3};
+
+use crate::{
+    assets::texture::TextureHandle,
+    vulkan::{Buffer, ImageTarget, Texture, VulkanDevice, HDR_FORMAT, MAX_VIEWS},
+};
+
+use super::{
+    bindless::{BindlessHandler, BindlessResourceHandle, BindlessResourceType},
+    material::{create_render_pass, main_pass_target_count, MaterialHandler, ViewLoadOps},
+    render_batch::{RenderBatch, ALL_LAYERS},
+    views::{update_buffer, view_mask, ViewBuffer},
+    FLYING_FRAMES,
+};
+
+/// the size of a face of every probe in texels
+pub const PROBE_RESOLUTION: u32 = 128;
+/// needs to match ``MAX_PROBES`` in ``shaders/environment.slang``
+pub const MAX_PROBES: usize = 16;
+
+/// when a probe renders the scene again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeRefresh {
+    /// only once after it was added and every time ``RenderHandler::refresh_probe`` is called
+    OnDemand,
+    /// every n frames, 1 renders it every frame
+    EveryFrames(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeDesc {
+    pub position: [f32; 3],
+    /// materials inside this distance use the probe, the closest one wins
+    pub radius: f32,
+    pub refresh: ProbeRefresh,
+    /// the near and far plane of the faces
+    pub near: f32,
+    pub far: f32,
+    /// only the batches on these layers are drawn in to the probe, see ``RenderBatch::set_layers``
+    pub layers: u32,
+}
+
+impl Default for ProbeDesc {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            radius: 10.0,
+            refresh: ProbeRefresh::OnDemand,
+            near: 0.05,
+            far: 100.0,
+            layers: ALL_LAYERS,
+        }
+    }
+}
+
+/// the camera of one face, given to ``ProbeCamera::write``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeView {
+    pub position: Vec3,
+    pub view: Mat4,
+    /// a 90 degree perspective projection in vulkan clip space, like ``Projection::matrix``
+    pub proj: Mat4,
+}
+
+impl ProbeView {
+    #[must_use]
+    pub fn view_proj(&self) -> Mat4 {
+        self.proj * self.view
+    }
+}
+
+/// the uniform buffer the materials read the camera from and what it contains for a face
+/// the buffer needs ``HOST_VISIBLE`` memory and ``TRANSFER_DST`` usage, at most 65536 bytes are replaced
+pub struct ProbeCamera {
+    pub buffer: Arc<Buffer>,
+    pub write: CameraFn,
+}
+
+/// the bytes of the camera buffer for a face, they replace the start of the buffer
+pub type CameraFn = Box<dyn Fn(&ProbeView) -> Vec<u8> + Send>;
+
+/// the directions the faces look in and which way is up, in the order of the cube layers
+const FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Y),
+    (Vec3::Y, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::Z),
+    (Vec3::Z, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y),
+];
+
+/// the camera of a face before it's mirrored in to the cube
+#[must_use]
+pub fn face_view(position: Vec3, face: usize, near: f32, far: f32) -> ProbeView {
+    let (forward, up) = FACES[face];
+    let projection = Projection::Perspective {
+        fovy: 90.0,
+        znear: near,
+        zfar: far,
+        infinite_reverse_z: false,
+    };
+    ProbeView {
+        position,
+        view: Mat4::look_to_rh(position, forward, up),
+        proj: projection.matrix(1.0),
+    }
+}
+
+/// needs to match ``Probe`` in ``shaders/environment.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct GpuProbe {
+    position: [f32; 3],
+    radius: f32,
+    /// the index of the cube in the texture array
+    texture: u32,
+    mip_levels: u32,
+    _padding: [u32; 2],
+}
+
+/// needs to match ``Probes`` in ``shaders/environment.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuProbes {
+    count: u32,
+    _padding: [u32; 3],
+    probes: [GpuProbe; MAX_PROBES],
+}
+
+struct Probe {
+    desc: ProbeDesc,
+    cube: Arc<Texture>,
+    handle: TextureHandle,
+    /// frames since the last capture, none if it has to be captured in the next frame
+    age: Option<u32>,
+}
+
+impl Probe {
+    fn is_due(&self) -> bool {
+        match (self.age, self.desc.refresh) {
+            (None, _) => true,
+            (Some(age), ProbeRefresh::EveryFrames(frames)) => age + 1 >= frames,
+            (Some(_), ProbeRefresh::OnDemand) => false,
+        }
+    }
+}
+
+/// the main pass targets at the size of a face, shared by every probe
+struct ProbeTargets {
+    render_pass: vk::RenderPass,
+    framebuffer: vk::Framebuffer,
+    /// the hdr target the faces are copied from, the resolve target with msaa
+    hdr: vk::Image,
+    /// with multiview they have a layer for every face
+    images: Vec<ImageTarget>,
+}
+
+pub(crate) struct ProbePass {
+    device: Arc<VulkanDevice>,
+    probes: Vec<Option<Probe>>,
+    camera: Option<ProbeCamera>,
+    targets: Option<ProbeTargets>,
+    /// only exists with multiview
+    views: Option<ViewBuffer>,
+    buffers: [Arc<Buffer>; FLYING_FRAMES],
+    /// a warning was logged because probes are due without a camera
+    warned: bool,
+}
+
+impl ProbePass {
+    /// ``multiview`` renders all faces in one pass, see the top of the file
+    /// # Errors
+    /// if there is no space to allocate the buffers
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        bindless: &BindlessHandler,
+        multiview: bool,
+    ) -> VkResult<Self> {
+        let buffer = || {
+            Buffer::new(
+                device.clone(),
+                size_of::<GpuProbes>() as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            )
+        };
+        let buffers = [buffer()?, buffer()?];
+
+        let handle = BindlessResourceHandle::reserved(
+            BindlessHandler::PROBES_SLOT,
+            BindlessResourceType::StorageBuffer,
+        );
+        bindless.set_per_frame_buffer(&*device, buffers.each_ref().map(|v| v.handle()), handle);
+
+        let views = if multiview {
+            Some(ViewBuffer::new(&device, bindless)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            device,
+            probes: vec![],
+            camera: None,
+            targets: None,
+            views,
+            buffers,
+            warned: false,
+        })
+    }
+
+    pub fn set_camera(&mut self, camera: ProbeCamera) {
+        assert!(
+            camera
+                .buffer
+                .usage()
+                .contains(vk::BufferUsageFlags::TRANSFER_DST),
+            "the camera buffer of the probes needs TRANSFER_DST usage"
+        );
+        self.camera = Some(camera);
+    }
+
+    /// the cube that's rendered to, it's put in to a texture slot by the caller
+    /// returns none if there are already ``MAX_PROBES``
+    /// # Errors
+    /// if there is no space to allocate the cube or the targets
+    pub fn create_cube(&mut self, samples: vk::SampleCountFlags) -> VkResult<Option<Arc<Texture>>> {
+        if self.probes.iter().flatten().count() == MAX_PROBES {
+            return Ok(None);
+        }
+
+        if self.targets.is_none() {
+            let layers = if self.views.is_some() { MAX_VIEWS } else { 1 };
+            self.targets = Some(unsafe { create_targets(&self.device, samples, layers as u32)? });
+        }
+
+        let cube = Texture::new_cube(
+            self.device.clone(),
+            PROBE_RESOLUTION,
+            HDR_FORMAT,
+            Texture::max_mip_levels([PROBE_RESOLUTION; 2]),
+            vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST,
+        )?;
+        Ok(Some(Arc::new(cube)))
+    }
+
+    /// returns the index of the probe
+    pub fn add(&mut self, desc: ProbeDesc, cube: Arc<Texture>, handle: TextureHandle) -> usize {
+        let probe = Some(Probe {
+            desc,
+            cube,
+            handle,
+            age: None,
+        });
+
+        match self.probes.iter().position(Option::is_none) {
+            Some(index) => {
+                self.probes[index] = probe;
+                index
+            }
+            None => {
+                self.probes.push(probe);
+                self.probes.len() - 1
+            }
+        }
+    }
+
+    /// returns the texture slot of the cube, so it can be freed
+    pub fn remove(&mut self, index: usize) -> Option<TextureHandle> {
+        self.probes.get_mut(index)?.take().map(|probe| probe.handle)
+    }
+
+    pub fn desc_mut(&mut self, index: usize) -> Option<&mut ProbeDesc> {
+        Some(&mut self.probes.get_mut(index)?.as_mut()?.desc)
+    }
+
+    /// render the probe again in the next frame
+    pub fn refresh(&mut self, index: usize) {
+        if let Some(Some(probe)) = self.probes.get_mut(index) {
+            probe.age = None;
+        }
+    }
+
+    /// write the probes that have been captured to the buffer of this frame
+    /// the frame must not be executing on the gpu
+    pub fn upload(&self, frame_index: usize) {
+        let mut data = GpuProbes {
+            count: 0,
+            _padding: [0; 3],
+            probes: [GpuProbe::default(); MAX_PROBES],
+        };
+
+        for probe in self.probes.iter().flatten().filter(|p| p.age.is_some()) {
+            data.probes[data.count as usize] = GpuProbe {
+                position: probe.desc.position,
+                radius: probe.desc.radius,
+                texture: probe.handle.index as u32,
+                mip_levels: probe.cube.mip_levels,
+                _padding: [0; 2],
+            };
+            data.count += 1;
+        }
+
+        self.buffers[frame_index].write(0, &[data]);
+    }
+
+    /// render the probes that are due, must be recorded outside of a render pass and before the main pass
+    pub unsafe fn record(
+        &mut self,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        materials: &MaterialHandler,
+        batches: &[RenderBatch],
+    ) {
+        let due = self.probes.iter().flatten().any(Probe::is_due);
+        if !due {
+            for probe in self.probes.iter_mut().flatten() {
+                probe.age = probe.age.map(|age| age + 1);
+            }
+            return;
+        }
+
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        let camera = self.camera.as_ref();
+        if self.views.is_none() && camera.is_none() {
+            if !self.warned {
+                log::warn!("environment probes can't be rendered without a camera, see RenderHandler::set_probe_camera");
+                self.warned = true;
+            }
+            return;
+        }
+
+        let _span = tracing::info_span!("environment probes").entered();
+
+        // what the application wrote for the main view, it's put back after the probes
+        let size = camera.map_or(0, |camera| camera.buffer.size().min(65536) as usize & !3);
+        let main_camera = camera
+            .filter(|_| self.views.is_none())
+            .map(|camera| camera.buffer.read::<u8>()[..size].to_vec());
+
+        let extent = vk::Extent2D {
+            width: PROBE_RESOLUTION,
+            height: PROBE_RESOLUTION,
+        };
+        let clear_values = materials.clear_values();
+        let begin_info = vk::RenderPassBeginInfo::default()
+            .render_pass(targets.render_pass)
+            .framebuffer(targets.framebuffer)
+            .render_area(vk::Rect2D::default().extent(extent))
+            .clear_values(&clear_values);
+
+        for probe in self.probes.iter_mut().flatten() {
+            if !probe.is_due() {
+                probe.age = probe.age.map(|age| age + 1);
+                continue;
+            }
+
+            let cube = &probe.cube;
+            cube_barrier(
+                &self.device,
+                cmd,
+                cube,
+                0,
+                cube.mip_levels,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            );
+
+            let position = Vec3::from(probe.desc.position);
+            let layers = probe.desc.layers;
+            let views: [ProbeView; 6] = std::array::from_fn(|face| {
+                face_view(position, face, probe.desc.near, probe.desc.far)
+            });
+
+            if let Some(view_buffer) = &self.views {
+                let views = views.map(|view| (view.view_proj(), view.position));
+                view_buffer.update(&self.device, cmd, &views);
+
+                draw_batches(
+                    &self.device,
+                    cmd,
+                    &begin_info,
+                    layout,
+                    batches,
+                    layers,
+                    true,
+                );
+                for face in 0..6 {
+                    copy_face(&self.device, cmd, targets.hdr, face, cube, face);
+                }
+            } else if let Some(camera) = camera {
+                for (face, view) in views.iter().enumerate() {
+                    let mut bytes = (camera.write)(view);
+                    bytes.resize(size, 0);
+                    update_buffer(&self.device, cmd, &camera.buffer, &bytes);
+
+                    draw_batches(
+                        &self.device,
+                        cmd,
+                        &begin_info,
+                        layout,
+                        batches,
+                        layers,
+                        false,
+                    );
+                    copy_face(&self.device, cmd, targets.hdr, 0, cube, face as u32);
+                }
+            }
+
+            generate_mips(&self.device, cmd, cube);
+            probe.age = Some(0);
+        }
+
+        if let (Some(camera), Some(main_camera)) = (camera, main_camera) {
+            update_buffer(&self.device, cmd, &camera.buffer, &main_camera);
+        }
+    }
+}
+
+/// one render pass over the batches on the ``layers``,
+/// with the pipelines for the multiview render pass if ``multiview`` is set
+unsafe fn draw_batches(
+    device: &VulkanDevice,
+    cmd: vk::CommandBuffer,
+    begin_info: &vk::RenderPassBeginInfo,
+    layout: vk::PipelineLayout,
+    batches: &[RenderBatch],
+    layers: u32,
+    multiview: bool,
+) {
+    let extent = begin_info.render_area.extent;
+    device.cmd_begin_render_pass(cmd, begin_info, vk::SubpassContents::INLINE);
+    let mut bound_pipeline = vk::Pipeline::null();
+    for batch in batches.iter().filter(|batch| batch.is_visible_in(layers)) {
+        if multiview {
+            batch.execute_multiview(device, cmd, layout, &mut bound_pipeline, extent);
+        } else {
+            batch.execute(device, cmd, layout, &mut bound_pipeline, extent);
+        }
+    }
+    device.cmd_end_render_pass(cmd);
+}
+
+impl Drop for ProbePass {
+    fn drop(&mut self) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        unsafe {
+            self.device.destroy_framebuffer(targets.framebuffer, None);
+            self.device.destroy_render_pass(targets.render_pass, None);
+            for target in &targets.images {
+                self.device.destroy_image_view(target.view, None);
+                self.device.destroy_image(target.image, None);
+            }
+        }
+    }
+}
+
+/// blit a layer of the hdr target in to a layer of the cube, mirrored, see the top of the file
+unsafe fn copy_face(
+    device: &VulkanDevice,
+    cmd: vk::CommandBuffer,
+    hdr: vk::Image,
+    layer: u32,
+    cube: &Texture,
+    face: u32,
+) {
+    let hdr_barrier = |old_layout, new_layout, src, dst| {
+        let (src_stage, src_access) = src;
+        let (dst_stage, dst_access) = dst;
+        vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(dst_stage)
+            .dst_access_mask(dst_access)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(hdr)
+            .subresource_range(color_range(0, 1, layer, 1))
+    };
+    let attachment = (
+        vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+        vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+    );
+    let blit = (
+        vk::PipelineStageFlags2::BLIT,
+        vk::AccessFlags2::TRANSFER_READ,
+    );
+
+    device.image_barrier(
+        cmd,
+        hdr_barrier(
+            vk::ImageLayout::GENERAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            attachment,
+            blit,
+        ),
+    );
+
+    let size = PROBE_RESOLUTION as i32;
+    let region = vk::ImageBlit::default()
+        .src_subresource(color_layers(0, layer, 1))
+        .src_offsets([
+            vk::Offset3D::default(),
+            vk::Offset3D {
+                x: size,
+                y: size,
+                z: 1,
+            },
+        ])
+        .dst_subresource(color_layers(0, face, 1))
+        .dst_offsets([
+            vk::Offset3D {
+                x: size,
+                y: 0,
+                z: 0,
+            },
+            vk::Offset3D {
+                x: 0,
+                y: size,
+                z: 1,
+            },
+        ]);
+    device.cmd_blit_image(
+        cmd,
+        hdr,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        cube.image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[region],
+        vk::Filter::NEAREST,
+    );
+
+    // the next face clears the target
+    device.image_barrier(
+        cmd,
+        hdr_barrier(
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::ImageLayout::GENERAL,
+            (vk::PipelineStageFlags2::BLIT, vk::AccessFlags2::NONE),
+            attachment,
+        ),
+    );
+}
+
+/// blit every mip from the one before it, for all faces at once, the cube can be sampled afterwards
+unsafe fn generate_mips(device: &VulkanDevice, cmd: vk::CommandBuffer, cube: &Texture) {
+    for mip in 1..cube.mip_levels {
+        cube_barrier(
+            device,
+            cmd,
+            cube,
+            mip - 1,
+            1,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        );
+
+        let offsets = |[width, height]: [u32; 2]| {
+            [
+                vk::Offset3D::default(),
+                vk::Offset3D {
+                    x: width as i32,
+                    y: height as i32,
+                    z: 1,
+                },
+            ]
+        };
+        let blit = vk::ImageBlit::default()
+            .src_subresource(color_layers(mip - 1, 0, 6))
+            .src_offsets(offsets(cube.mip_extent(mip - 1)))
+            .dst_subresource(color_layers(mip, 0, 6))
+            .dst_offsets(offsets(cube.mip_extent(mip)));
+        device.cmd_blit_image(
+            cmd,
+            cube.image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            cube.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[blit],
+            vk::Filter::LINEAR,
+        );
+    }
+
+    let last = cube.mip_levels - 1;
+    if last > 0 {
+        cube_barrier(
+            device,
+            cmd,
+            cube,
+            0,
+            last,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+    }
+    cube_barrier(
+        device,
+        cmd,
+        cube,
+        last,
+        1,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    );
+}
+
+/// a barrier for all faces of some mips
+/// the cube is only written by blits and read by shaders and blits
+unsafe fn cube_barrier(
+    device: &VulkanDevice,
+    cmd: vk::CommandBuffer,
+    cube: &Texture,
+    base_mip: u32,
+    mip_count: u32,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) {
+    let usage = |layout| match layout {
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL => (
+            vk::PipelineStageFlags2::BLIT,
+            vk::AccessFlags2::TRANSFER_WRITE,
+        ),
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL => (
+            vk::PipelineStageFlags2::BLIT,
+            vk::AccessFlags2::TRANSFER_READ,
+        ),
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => (
+            vk::PipelineStageFlags2::FRAGMENT_SHADER | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_SAMPLED_READ,
+        ),
+        // the last frames might still sample the old contents
+        _ => (
+            vk::PipelineStageFlags2::FRAGMENT_SHADER | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::NONE,
+        ),
+    };
+
+    let (src_stage, src_access) = usage(old_layout);
+    let (dst_stage, dst_access) = usage(new_layout);
+
+    device.image_barrier(
+        cmd,
+        vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(dst_stage)
+            .dst_access_mask(dst_access)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(cube.image)
+            .subresource_range(color_range(base_mip, mip_count, 0, 6)),
+    );
+}
+
+fn color_range(
+    base_mip: u32,
+    mip_count: u32,
+    base_layer: u32,
+    layer_count: u32,
+) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(base_mip)
+        .level_count(mip_count)
+        .base_array_layer(base_layer)
+        .layer_count(layer_count)
+}
+
+fn color_layers(mip: u32, base_layer: u32, layer_count: u32) -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(mip)
+        .base_array_layer(base_layer)
+        .layer_count(layer_count)
+}
+
+/// the same targets as a swapchain image, at the size of a face, in a render pass compatible with the main one
+/// with more than one layer, it's compatible with ``MaterialHandler::multiview_renderpass``
+unsafe fn create_targets(
+    device: &Arc<VulkanDevice>,
+    samples: vk::SampleCountFlags,
+    layers: u32,
+) -> VkResult<ProbeTargets> {
+    use crate::vulkan::{
+        create_layered_texture, TargetDesc, DEPTH_FORMAT, NORMAL_FORMAT, OBJECT_ID_FORMAT,
+        OIT_ACCUM_FORMAT, OIT_REVEALAGE_FORMAT, VELOCITY_FORMAT,
+    };
+
+    let mask = if layers > 1 {
+        view_mask(layers as usize)
+    } else {
+        0
+    };
+    let render_pass = create_render_pass(device, ViewLoadOps::default(), samples, mask)?;
+
+    let formats = [
+        HDR_FORMAT,
+        NORMAL_FORMAT,
+        DEPTH_FORMAT,
+        VELOCITY_FORMAT,
+        OIT_ACCUM_FORMAT,
+        OIT_REVEALAGE_FORMAT,
+        OBJECT_ID_FORMAT,
+    ];
+    let count = main_pass_target_count(device);
+    let msaa = samples != vk::SampleCountFlags::TYPE_1;
+
+    let target = |format, usage, samples| -> VkResult<ImageTarget> {
+        let (memory, image, view) = create_layered_texture(
+            device,
+            [PROBE_RESOLUTION; 2],
+            TargetDesc::color(format, usage).samples(samples),
+            layers,
+        )?;
+        Ok(ImageTarget {
+            image,
+            memory,
+            view,
+        })
+    };
+
+    let mut images = vec![];
+    if msaa {
+        for &format in &formats[..count] {
+            images.push(target(
+                format,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT,
+                samples,
+            )?);
+        }
+    }
+    // the single sampled targets, or the resolve targets with msaa
+    for (i, &format) in formats[..count].iter().enumerate() {
+        let usage = if i == 0 {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC
+        } else {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+        };
+        images.push(target(format, usage, vk::SampleCountFlags::TYPE_1)?);
+    }
+
+    let hdr = images[if msaa { count } else { 0 }].image;
+    let attachments: Vec<_> = images.iter().map(|target| target.view).collect();
+
+    // with multiview, the framebuffer has a single layer and the views pick the layers of the images
+    let framebuffer = device.create_framebuffer(
+        &vk::FramebufferCreateInfo::default()
+            .render_pass(render_pass)
+            .attachments(&attachments)
+            .width(PROBE_RESOLUTION)
+            .height(PROBE_RESOLUTION)
+            .layers(1),
+        None,
+    )?;
+
+    Ok(ProbeTargets {
+        render_pass,
+        framebuffer,
+        hdr,
+        images,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{Vec3Swizzles, Vec4Swizzles};
+
+    use super::*;
+
+    fn project(view: &ProbeView, point: Vec3) -> Vec3 {
+        let clip = view.view_proj() * point.extend(1.0);
+        clip.xyz() / clip.w
+    }
+
+    #[test]
+    fn faces_look_along_the_axes() {
+        let position = Vec3::new(1.0, 2.0, 3.0);
+        for (face, (forward, _)) in FACES.iter().enumerate() {
+            let view = face_view(position, face, 0.1, 10.0);
+            let center = project(&view, position + *forward);
+            assert!(center.xy().length() < 1e-5, "face {face}: {center}");
+        }
+    }
+
+    #[test]
+    fn faces_match_the_cube_layout_after_mirroring() {
+        // the texel at the left of the first row of +x points to +y and +z, see the cubemap layout in the vulkan spec
+        let view = face_view(Vec3::ZERO, 0, 0.1, 10.0);
+        let corner = project(&view, Vec3::new(1.0, 0.9, 0.9));
+        // the top is at -1 in vulkan, the right side becomes the left after mirroring
+        assert!(corner.y < -0.8 && corner.x > 0.8, "{corner}");
+    }
+}
@@ -0,0 +1,242 @@
+// passes that record their own vulkan commands, for effects the renderer doesn't have
+// there is no frame graph, so a raw pass is recorded right after the last built in pass it depends on,
+// passes with the same place are recorded in the order they were added
+//
+// the dependencies can be built in passes from ``BUILTIN_PASSES`` or raw passes that were added before,
+// so the order is always known when the pass is added and there can't be cycles
+//
+// the layouts of the images aren't tracked, a raw pass has to put barriers around everything it uses
+
+use std::fmt;
+
+use ash::vk;
+
+use crate::vulkan::VulkanDevice;
+
+use super::breadcrumbs::Breadcrumbs;
+
+/// the passes of a frame in the order they are recorded, raw passes can depend on them by name
+/// the main pass ends before raw passes after it are recorded, raw passes can't draw in to it
+pub const BUILTIN_PASSES: [&str; 14] = [
+    "buffer updates",
+    "particle update",
+    "gi propagation",
+    "environment probes",
+    "main pass",
+    "object picking",
+    "oit resolve",
+    "volumetrics",
+    "ssao",
+    "taa",
+    "tonemap",
+    "outline",
+    "upscale",
+    "ui",
+];
+
+/// what a raw pass gets to record its commands
+pub struct RecordCtx<'a> {
+    pub device: &'a VulkanDevice,
+    pub command_buffer: vk::CommandBuffer,
+    /// which of the ``FLYING_FRAMES`` is recorded, for resources that exist once per frame
+    pub frame_index: usize,
+    /// the bindless descriptor set is bound to the graphics and compute bind points with this layout,
+    /// a pass that binds other sets has to bind it again
+    pub pipeline_layout: vk::PipelineLayout,
+    pub descriptor_set: vk::DescriptorSet,
+    pub swapchain: SwapchainInfo,
+}
+
+/// the swapchain image the frame is presented with
+#[derive(Debug, Clone, Copy)]
+pub struct SwapchainInfo {
+    pub image_index: u32,
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    /// the size of the hdr image and the other targets,
+    /// smaller than ``extent`` with ``RendererConfig::internal_resolution``
+    pub render_extent: vk::Extent2D,
+    /// the frame is rendered in to this image before it's tone mapped in to the swapchain image
+    pub hdr_image: vk::Image,
+    pub hdr_view: vk::ImageView,
+}
+
+pub type RecordFn = Box<dyn Fn(&RecordCtx)>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawPassError {
+    /// the name is already used by a built in or raw pass
+    DuplicateName(String),
+    /// no built in pass or raw pass added before has this name
+    UnknownDependency(String),
+}
+
+impl fmt::Display for RawPassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "there is already a pass called {name:?}"),
+            Self::UnknownDependency(name) => write!(f, "there is no pass called {name:?}"),
+        }
+    }
+}
+
+impl std::error::Error for RawPassError {}
+
+struct RawPass {
+    name: String,
+    /// the index in ``BUILTIN_PASSES`` it's recorded after
+    after: usize,
+    record: RecordFn,
+}
+
+#[derive(Default)]
+pub(crate) struct RawPasses {
+    passes: Vec<RawPass>,
+}
+
+impl RawPasses {
+    /// returns the built in pass it's recorded after
+    pub fn add(
+        &mut self,
+        name: &str,
+        dependencies: &[&str],
+        record: RecordFn,
+    ) -> Result<&'static str, RawPassError> {
+        if self.position(name).is_some() {
+            return Err(RawPassError::DuplicateName(name.to_owned()));
+        }
+
+        let mut after = 0;
+        for &dependency in dependencies {
+            after = after.max(
+                self.position(dependency)
+                    .ok_or_else(|| RawPassError::UnknownDependency(dependency.to_owned()))?,
+            );
+        }
+
+        self.passes.push(RawPass {
+            name: name.to_owned(),
+            after,
+            record,
+        });
+        Ok(BUILTIN_PASSES[after])
+    }
+
+    /// passes that depend on it stay where they are
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.passes.len();
+        self.passes.retain(|pass| pass.name != name);
+        self.passes.len() != len
+    }
+
+    /// if a raw pass is recorded before the built in pass
+    pub fn any_before(&self, builtin: &str) -> bool {
+        let Some(index) = BUILTIN_PASSES.iter().position(|pass| *pass == builtin) else {
+            return false;
+        };
+        self.passes.iter().any(|pass| pass.after < index)
+    }
+
+    /// the index of the built in pass a pass is recorded after
+    fn position(&self, name: &str) -> Option<usize> {
+        BUILTIN_PASSES
+            .iter()
+            .position(|builtin| *builtin == name)
+            .or_else(|| {
+                self.passes
+                    .iter()
+                    .find(|pass| pass.name == name)
+                    .map(|pass| pass.after)
+            })
+    }
+
+    /// record the raw passes that come after the built in pass
+    pub unsafe fn record(&self, after: &str, ctx: &RecordCtx, breadcrumbs: &mut Breadcrumbs) {
+        let Some(after) = BUILTIN_PASSES.iter().position(|builtin| *builtin == after) else {
+            return;
+        };
+
+        for pass in self.passes.iter().filter(|pass| pass.after == after) {
+            let _span = tracing::info_span!("raw pass", name = pass.name).entered();
+            breadcrumbs.mark(ctx.device, ctx.command_buffer, || pass.name.clone());
+            (pass.record)(ctx);
+        }
+    }
+
+    #[cfg(test)]
+    fn order(&self) -> Vec<(&str, &str)> {
+        let mut passes: Vec<_> = self.passes.iter().collect();
+        passes.sort_by_key(|pass| pass.after);
+        passes
+            .iter()
+            .map(|pass| (pass.name.as_str(), BUILTIN_PASSES[pass.after]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_after_dependencies() {
+        let mut passes = RawPasses::default();
+        let nothing = || -> RecordFn { Box::new(|_| {}) };
+
+        assert_eq!(passes.add("blur", &["taa"], nothing()), Ok("taa"));
+        assert_eq!(passes.add("upload", &[], nothing()), Ok("buffer updates"));
+        // the latest dependency decides
+        assert_eq!(
+            passes.add("glow", &["blur", "ssao", "upload"], nothing()),
+            Ok("taa")
+        );
+        assert_eq!(
+            passes.add("hud", &["tonemap", "glow"], nothing()),
+            Ok("tonemap")
+        );
+
+        assert_eq!(
+            passes.add("blur", &[], nothing()),
+            Err(RawPassError::DuplicateName("blur".to_owned()))
+        );
+        assert_eq!(
+            passes.add("ssao", &[], nothing()),
+            Err(RawPassError::DuplicateName("ssao".to_owned()))
+        );
+        assert_eq!(
+            passes.add("late", &["bloom"], nothing()),
+            Err(RawPassError::UnknownDependency("bloom".to_owned()))
+        );
+
+        assert_eq!(
+            passes.order(),
+            [
+                ("upload", "buffer updates"),
+                ("blur", "taa"),
+                ("glow", "taa"),
+                ("hud", "tonemap")
+            ]
+        );
+
+        assert!(passes.remove("blur"));
+        assert!(!passes.remove("blur"));
+        assert_eq!(passes.order()[1], ("glow", "taa"));
+    }
+
+    #[test]
+    fn passes_before_a_builtin_pass() {
+        let mut passes = RawPasses::default();
+        let nothing = || -> RecordFn { Box::new(|_| {}) };
+        assert!(!passes.any_before("tonemap"));
+
+        passes.add("hud", &["tonemap"], nothing()).unwrap();
+        assert!(!passes.any_before("tonemap"));
+        assert!(passes.any_before("outline"));
+
+        passes.add("blur", &["taa"], nothing()).unwrap();
+        assert!(passes.any_before("tonemap"));
+        assert!(!passes.any_before("bloom"));
+    }
+}
@@ -1,11 +1,30 @@
 use crate::{
-    types::Material,
-    vulkan::{Buffer, VulkanDevice},
+    types::{DrawState, Material, Transparency},
+    vulkan::{Buffer, GpuDevice},
 };
-use ash::vk;
+use ash::vk::{self, Handle};
+use math::Aabb;
 use std::sync::Arc;
 
-use super::material::MaterialHandler;
+use super::{bindless::BindlessHandler, material::MaterialHandler};
+
+/// where ``PulledBuffers`` is in the push constants,
+/// at the end so it doesn't overlap the push constants of the material
+pub const PULLED_BUFFERS_OFFSET: u32 =
+    BindlessHandler::PUSH_CONSTANT_SIZE - size_of::<PulledBuffers>() as u32;
+
+/// where ``DrawData::object_id`` is in the push constants, right in front of ``PulledBuffers``
+pub const OBJECT_ID_OFFSET: u32 = PULLED_BUFFERS_OFFSET - size_of::<u32>() as u32;
+
+/// the storage buffer slots the vertex shader of a material with ``vertex_pulling`` reads from,
+/// see ``RenderHandler::push_pulled_buffers`` and ``vertex_pulling.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PulledBuffers {
+    pub vertices: u32,
+    /// ``u32::MAX`` if the draw has no instance buffer
+    pub instances: u32,
+}
 
 /// ``DrawData`` contains all the data needed for a single Draw call
 #[derive(Default)]
@@ -14,67 +33,873 @@ pub struct DrawData {
     pub vertex_buffer: Option<Arc<Buffer>>,
     /// if this is Some then ``instance_attribute_descriptions`` must be set
     pub instance_buffer: Option<Arc<Buffer>>,
+    /// used instead of ``vertex_buffer`` and ``instance_buffer`` if the material uses vertex pulling
+    /// the index buffer still works like normal
+    pub pulled_buffers: Option<PulledBuffers>,
     pub index_buffer: Option<Arc<Buffer>>,
     pub index_type: vk::IndexType,
     pub instance_count: u32,
     pub index_count: u32,
     pub vertex_count: u32,
+    /// the number of task shader work groups, used instead of the vertex and index buffers
+    /// if the material has mesh shaders, needs ``DeviceFeatures::mesh_shader``
+    pub mesh_tasks: Option<u32>,
+    /// written to the object id target by materials with ``MaterialCreateInfo::object_id``,
+    /// 0 means nothing, see ``RenderHandler::request_pick``
+    pub object_id: u32,
+    /// only draw inside this rect in pixels, for example to clip ui elements
+    /// clamped to the swapchain, the viewport of the material is used if it's none
+    pub scissor: Option<vk::Rect2D>,
+    /// replaces the state the material made dynamic for this draw, see ``MaterialCreateInfo::dynamic_state``
+    pub state: DrawState,
 }
 
 impl DrawData {
-    unsafe fn execute(&self, device: &VulkanDevice, cmd: vk::CommandBuffer) {
-        let mut vertex_buffers = vec![];
+    /// ``object_id`` tells if the material reads the object id from the push constants
+    unsafe fn execute(
+        &self,
+        device: &dyn GpuDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        object_id: bool,
+    ) {
+        if object_id {
+            device.push_constants(cmd, layout, OBJECT_ID_OFFSET, &self.object_id.to_ne_bytes());
+        }
 
-        if let Some(vertex_b) = &self.vertex_buffer {
-            vertex_buffers.push(vertex_b.handle());
+        if let Some(pulled) = &self.pulled_buffers {
+            let data = std::slice::from_raw_parts(
+                std::ptr::from_ref(pulled).cast::<u8>(),
+                size_of::<PulledBuffers>(),
+            );
+            device.push_constants(cmd, layout, PULLED_BUFFERS_OFFSET, data);
         }
 
-        if let Some(instance_b) = &self.instance_buffer {
-            vertex_buffers.push(instance_b.handle()); // instance buffer is also in vertex buffers
+        if let Some(group_count) = self.mesh_tasks {
+            device.draw_mesh_tasks(cmd, group_count);
+            return;
+        }
+
+        let mut vertex_buffers = [vk::Buffer::null(); 2];
+        let mut count = 0;
+
+        // instance buffer is also in vertex buffers
+        for buffer in [&self.vertex_buffer, &self.instance_buffer]
+            .into_iter()
+            .flatten()
+        {
+            vertex_buffers[count] = buffer.handle();
+            count += 1;
         }
 
         // if there is no Vertex/Instance input then we don't need to bind it
-        if !vertex_buffers.is_empty() {
-            let offsets = vec![0; vertex_buffers.len()];
-            device.cmd_bind_vertex_buffers(cmd, 0, &vertex_buffers, &offsets);
+        if count > 0 {
+            let offsets = [0; 2];
+            device.bind_vertex_buffers(cmd, &vertex_buffers[..count], &offsets[..count]);
         }
 
         if let Some(index_b) = &self.index_buffer {
-            device.cmd_bind_index_buffer(cmd, index_b.handle(), 0, self.index_type);
-            device.cmd_draw_indexed(cmd, self.index_count, self.instance_count.max(1), 0, 0, 0);
+            device.bind_index_buffer(cmd, index_b.handle(), self.index_type);
+            device.draw_indexed(cmd, self.index_count, self.instance_count.max(1));
         } else {
-            device.cmd_draw(cmd, self.vertex_count, self.instance_count.max(1), 0, 0);
+            device.draw(cmd, self.vertex_count, self.instance_count.max(1));
         }
     }
 }
 
-#[derive(Default)]
+/// the part of ``rect`` that is inside an attachment of ``extent``,
+/// scissors can't have a negative offset or reach past the attachment
+pub fn clamp_scissor(rect: vk::Rect2D, extent: vk::Extent2D) -> vk::Rect2D {
+    let clamp = |offset: i32, size: u32, max: u32| {
+        let start = i64::from(offset).clamp(0, i64::from(max));
+        let end = (i64::from(offset) + i64::from(size)).clamp(start, i64::from(max));
+        (start as u32, (end - start) as u32)
+    };
+
+    let (x, width) = clamp(rect.offset.x, rect.extent.width, extent.width);
+    let (y, height) = clamp(rect.offset.y, rect.extent.height, extent.height);
+
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: x as i32,
+            y: y as i32,
+        },
+        extent: vk::Extent2D { width, height },
+    }
+}
+
+/// the layer of a batch that didn't set any, see ``RenderBatch::set_layers``
+pub const DEFAULT_LAYERS: u32 = 1;
+
+/// the layers of a view that draws every batch
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+/// how often a batch changes, decides how it's recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchUsage {
+    /// recorded again every frame
+    #[default]
+    Dynamic,
+    /// recorded once in to a secondary command buffer that is reused until the batch or the swapchain changes,
+    /// for batches that stay the same for many frames like voxel chunks
+    /// writing to the buffers of the draws doesn't count as a change, they are still read every frame
+    /// batches with ``Transparency::Sorted`` are always dynamic, they have to be drawn after everything else
+    Static,
+}
+
 pub struct RenderBatch {
     material: Option<Arc<Material>>,
     draws: Vec<DrawData>,
+    /// shown in the profiler
+    name: Option<String>,
+    /// hidden batches are skipped when recording
+    hidden: bool,
+    /// the batch is only drawn by views with one of these layers
+    layers: u32,
+    /// the box around every draw in world space
+    bounds: Option<Aabb>,
+    usage: BatchUsage,
+    /// changes every time something changes that is recorded, a cached command buffer of an older version is outdated
+    version: u64,
+}
+
+impl Default for RenderBatch {
+    fn default() -> Self {
+        Self {
+            material: None,
+            draws: vec![],
+            name: None,
+            hidden: false,
+            layers: DEFAULT_LAYERS,
+            bounds: None,
+            usage: BatchUsage::default(),
+            version: 0,
+        }
+    }
 }
 
 impl RenderBatch {
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = Some(name.into());
+    }
+
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        // hidden static batches aren't executed, their command buffers can stay
+        self.hidden = !visible;
+    }
+
+    #[must_use]
+    pub fn is_visible(&self) -> bool {
+        !self.hidden
+    }
+
+    /// a bit mask of the layers the batch is on, views skip it if none of their layers are set
+    /// for example to keep editor gizmos out of the environment probes, see ``RenderHandler::set_view_layers``
+    /// like hiding, it doesn't change what a static batch recorded
+    pub fn set_layers(&mut self, layers: u32) {
+        self.layers = layers;
+    }
+
+    #[must_use]
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// if a view with these ``layers`` draws the batch
+    #[must_use]
+    pub fn is_visible_in(&self, layers: u32) -> bool {
+        !self.hidden && self.layers & layers != 0
+    }
+
+    #[must_use]
+    pub fn draw_count(&self) -> usize {
+        self.draws.len()
+    }
+
+    /// the box around everything the batch draws in world space
+    /// the renderer doesn't cull with it, it's used by tools to tell why a batch isn't visible
+    pub fn set_bounds(&mut self, bounds: Aabb) {
+        self.bounds = Some(bounds);
+    }
+
+    /// ``None`` if it was never set
+    #[must_use]
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    #[must_use]
+    pub fn has_material(&self) -> bool {
+        self.material.is_some()
+    }
+
     pub fn set_material(&mut self, material: Arc<Material>) {
         self.material = Some(material);
+        self.version += 1;
     }
 
     pub fn add_draw_call(&mut self, draw_data: DrawData) {
         self.draws.push(draw_data);
+        self.version += 1;
+    }
+
+    pub fn set_usage(&mut self, usage: BatchUsage) {
+        self.usage = usage;
+        self.version += 1;
+    }
+
+    #[must_use]
+    pub fn usage(&self) -> BatchUsage {
+        self.usage
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.version
     }
 
+    /// if the batch is recorded in to a cached secondary command buffer, see ``BatchUsage::Static``
+    pub(crate) fn is_cached(&self) -> bool {
+        self.usage == BatchUsage::Static && self.transparency() != Transparency::Sorted
+    }
+
+    /// the transparency of the material, opaque if there is none
+    #[must_use]
+    pub fn transparency(&self) -> Transparency {
+        self.material
+            .as_ref()
+            .map_or(Transparency::Opaque, |material| material.info.transparency)
+    }
+
+    /// batches are sorted by this to group batches that use the same pipeline
+    /// sorted transparent batches all have the highest key, so they are drawn last in the order they were added
+    pub(crate) fn sort_key(&self) -> u64 {
+        match &self.material {
+            Some(material) if material.info.transparency == Transparency::Sorted => u64::MAX,
+            Some(material) => material.pipeline.as_raw(),
+            None => 0,
+        }
+    }
+
+    /// ``bound_pipeline`` is the pipeline that is currently bound,
+    /// it's only bound again if this batch uses a different one
     pub(crate) unsafe fn execute(
         &self,
-        device: &VulkanDevice,
+        device: &dyn GpuDevice,
         cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        bound_pipeline: &mut vk::Pipeline,
+        swapchain_size: vk::Extent2D,
     ) {
+        if self.hidden {
+            return;
+        }
+
         let Some(material) = &self.material else {
             panic!("no material set when rendering")
         };
-        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, material.pipeline);
+
+        self.execute_pipeline(
+            device,
+            cmd,
+            layout,
+            material,
+            material.pipeline,
+            bound_pipeline,
+            swapchain_size,
+        );
+    }
+
+    /// the same as ``execute``, but with the pipeline for the multiview render pass,
+    /// batches whose material isn't ``MaterialCreateInfo::multiview`` are skipped
+    pub(crate) unsafe fn execute_multiview(
+        &self,
+        device: &dyn GpuDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        bound_pipeline: &mut vk::Pipeline,
+        swapchain_size: vk::Extent2D,
+    ) {
+        if self.hidden {
+            return;
+        }
+
+        let Some(material) = &self.material else {
+            return;
+        };
+        let Some(pipeline) = material.multiview_pipeline else {
+            return;
+        };
+
+        self.execute_pipeline(
+            device,
+            cmd,
+            layout,
+            material,
+            pipeline,
+            bound_pipeline,
+            swapchain_size,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn execute_pipeline(
+        &self,
+        device: &dyn GpuDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        material: &Material,
+        pipeline: vk::Pipeline,
+        bound_pipeline: &mut vk::Pipeline,
+        swapchain_size: vk::Extent2D,
+    ) {
+        let _span = tracing::info_span!(
+            "render batch",
+            name = self.name.as_deref().unwrap_or("unnamed"),
+            draws = self.draws.len()
+        )
+        .entered();
+
+        let material_state = material.info.dynamic_state;
+
+        if *bound_pipeline != pipeline {
+            device.bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            *bound_pipeline = pipeline;
+            material.info.viewport.apply(device, cmd, swapchain_size);
+            material_state.apply(device, cmd, None);
+        }
+
+        // the scissor of the material is set when it's bound, draws with their own scissor change it
+        let material_scissor = material.info.viewport.rect(swapchain_size);
+        let mut scissor = material_scissor;
+        let mut state = material_state;
 
         for command in &self.draws {
-            command.execute(device, cmd);
+            let draw_scissor = command
+                .scissor
+                .map_or(material_scissor, |rect| clamp_scissor(rect, swapchain_size));
+            if draw_scissor != scissor {
+                device.set_scissor(cmd, draw_scissor);
+                scissor = draw_scissor;
+            }
+
+            let draw_state = command.state.merged(&material_state);
+            draw_state.apply(device, cmd, Some(&state));
+            state = draw_state;
+
+            command.execute(device, cmd, layout, material.info.object_id);
+        }
+
+        // the next batch might use the same pipeline without binding it again
+        if scissor != material_scissor {
+            device.set_scissor(cmd, material_scissor);
+        }
+        material_state.apply(device, cmd, Some(&state));
+    }
+}
+
+/// record the batches grouped by their pipeline, so every pipeline only needs to be bound once
+/// the index is part of the sort key, so batches with the same pipeline keep their order
+/// ``order`` is scratch space for the sort, without it the batches are recorded in the order they were added
+/// cached batches are skipped, they are recorded by ``StaticBatches``
+/// so are the batches on none of the ``layers`` of the view
+pub(crate) unsafe fn record_batches(
+    device: &dyn GpuDevice,
+    cmd: vk::CommandBuffer,
+    layout: vk::PipelineLayout,
+    batches: &[RenderBatch],
+    order: Option<&mut [(u64, usize)]>,
+    swapchain_size: vk::Extent2D,
+    layers: u32,
+) {
+    let mut bound_pipeline = vk::Pipeline::null();
+    let recorded = |batch: &RenderBatch| !batch.is_cached() && batch.is_visible_in(layers);
+
+    let Some(order) = order else {
+        for batch in batches.iter().filter(|batch| recorded(batch)) {
+            batch.execute(device, cmd, layout, &mut bound_pipeline, swapchain_size);
+        }
+        return;
+    };
+
+    for (key, (i, batch)) in order.iter_mut().zip(batches.iter().enumerate()) {
+        *key = (batch.sort_key(), i);
+    }
+    order.sort_unstable();
+
+    for &(_, i) in order.iter() {
+        if recorded(&batches[i]) {
+            batches[i].execute(device, cmd, layout, &mut bound_pipeline, swapchain_size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::MaterialCreateInfo,
+        vulkan::mock::{DeviceCall, MockDevice},
+    };
+
+    fn batch(pipeline: u64, vertex_count: u32) -> RenderBatch {
+        let mut batch = RenderBatch::default();
+        batch.set_material(Arc::new(Material {
+            pipeline: vk::Pipeline::from_raw(pipeline),
+            multiview_pipeline: None,
+            info: MaterialCreateInfo::default(),
+        }));
+        batch.add_draw_call(DrawData {
+            vertex_count,
+            ..Default::default()
+        });
+        batch
+    }
+
+    /// the calls without the viewport and scissor
+    fn recorded(device: &MockDevice) -> Vec<DeviceCall> {
+        device
+            .take_calls()
+            .into_iter()
+            .filter(|call| !matches!(call, DeviceCall::SetViewport | DeviceCall::SetScissor(_)))
+            .collect()
+    }
+
+    fn draw(vertices: u32) -> DeviceCall {
+        DeviceCall::Draw {
+            vertices,
+            instances: 1,
+        }
+    }
+
+    #[test]
+    fn batches_are_grouped_by_pipeline() {
+        let device = MockDevice::default();
+        let batches = [batch(2, 0), batch(1, 1), batch(2, 2), batch(1, 3)];
+        let mut order = [(0, 0); 4];
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &batches,
+                Some(&mut order),
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
         }
+
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                draw(1),
+                draw(3),
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(2)),
+                draw(0),
+                draw(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn sorted_transparent_batches_are_drawn_last() {
+        let device = MockDevice::default();
+        let transparent = |pipeline, vertex_count| {
+            let mut batch = RenderBatch::default();
+            batch.set_material(Arc::new(Material {
+                pipeline: vk::Pipeline::from_raw(pipeline),
+                multiview_pipeline: None,
+                info: MaterialCreateInfo {
+                    transparency: Transparency::Sorted,
+                    ..Default::default()
+                },
+            }));
+            batch.add_draw_call(DrawData {
+                vertex_count,
+                ..Default::default()
+            });
+            batch
+        };
+
+        let batches = [
+            transparent(1, 0),
+            batch(2, 1),
+            transparent(3, 2),
+            transparent(1, 3),
+        ];
+        let mut order = [(0, 0); 4];
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &batches,
+                Some(&mut order),
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
+        }
+
+        // the transparent ones keep their order, even if that binds a pipeline again
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(2)),
+                draw(1),
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                draw(0),
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(3)),
+                draw(2),
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                draw(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn pulled_buffers_are_pushed_before_the_draw() {
+        let device = MockDevice::default();
+        let mut pulled = batch(1, 0);
+        pulled.add_draw_call(DrawData {
+            pulled_buffers: Some(PulledBuffers {
+                vertices: 4,
+                instances: u32::MAX,
+            }),
+            vertex_count: 36,
+            ..Default::default()
+        });
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &[pulled],
+                None,
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
+        }
+
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                draw(0),
+                DeviceCall::PushConstants {
+                    offset: BindlessHandler::PUSH_CONSTANT_SIZE - 8,
+                    size: 8,
+                },
+                draw(36),
+            ]
+        );
+    }
+
+    #[test]
+    fn object_ids_are_pushed_for_materials_that_write_them() {
+        let device = MockDevice::default();
+        let mut batch = RenderBatch::default();
+        batch.set_material(Arc::new(Material {
+            pipeline: vk::Pipeline::from_raw(1),
+            multiview_pipeline: None,
+            info: MaterialCreateInfo {
+                object_id: true,
+                ..Default::default()
+            },
+        }));
+        batch.add_draw_call(DrawData {
+            object_id: 7,
+            vertex_count: 36,
+            ..Default::default()
+        });
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &[batch],
+                None,
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
+        }
+
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                DeviceCall::PushConstants {
+                    offset: BindlessHandler::PUSH_CONSTANT_SIZE - 12,
+                    size: 4,
+                },
+                draw(36),
+            ]
+        );
+    }
+
+    #[test]
+    fn mesh_tasks_replace_the_vertex_draw() {
+        let device = MockDevice::default();
+        let mut batch = RenderBatch::default();
+        batch.set_material(Arc::new(Material {
+            pipeline: vk::Pipeline::from_raw(1),
+            multiview_pipeline: None,
+            info: MaterialCreateInfo::default(),
+        }));
+        batch.add_draw_call(DrawData {
+            mesh_tasks: Some(3),
+            vertex_count: 36,
+            ..Default::default()
+        });
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &[batch],
+                None,
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
+        }
+
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                DeviceCall::DrawMeshTasks(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn static_batches_are_left_to_the_cache() {
+        let device = MockDevice::default();
+        let mut batches = [batch(1, 0), batch(1, 1)];
+        batches[0].set_usage(BatchUsage::Static);
+        let version = batches[1].version();
+        batches[1].add_draw_call(DrawData::default());
+        assert_ne!(batches[1].version(), version);
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &batches,
+                Some(&mut [(0, 0); 2]),
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
+        }
+
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                draw(1),
+                draw(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_batches_are_skipped() {
+        let device = MockDevice::default();
+        let mut batches = [batch(1, 0), batch(2, 1), batch(1, 2)];
+        batches[1].set_visible(false);
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &batches,
+                None,
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
+        }
+
+        // without scratch space the order is kept
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                draw(0),
+                draw(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn batches_outside_the_view_layers_are_skipped() {
+        const EDITOR: u32 = 1 << 1;
+
+        let device = MockDevice::default();
+        let mut batches = [batch(1, 0), batch(1, 1), batch(1, 2)];
+        batches[1].set_layers(EDITOR);
+        batches[2].set_layers(DEFAULT_LAYERS | EDITOR);
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &batches,
+                None,
+                vk::Extent2D::default(),
+                !EDITOR,
+            );
+        }
+
+        // a batch on any of the layers of the view is drawn
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                draw(0),
+                draw(2),
+            ]
+        );
+    }
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> vk::Rect2D {
+        vk::Rect2D {
+            offset: vk::Offset2D { x, y },
+            extent: vk::Extent2D { width, height },
+        }
+    }
+
+    #[test]
+    fn scissors_are_clamped_to_the_attachment() {
+        let extent = vk::Extent2D::default().width(800).height(600);
+
+        assert_eq!(
+            clamp_scissor(rect(10, 20, 100, 50), extent),
+            rect(10, 20, 100, 50)
+        );
+        assert_eq!(
+            clamp_scissor(rect(-10, 580, 100, 50), extent),
+            rect(0, 580, 90, 20)
+        );
+        assert_eq!(
+            clamp_scissor(rect(900, -100, 10, 50), extent),
+            rect(800, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn draw_scissors_are_set_and_restored() {
+        let device = MockDevice::default();
+        let extent = vk::Extent2D::default().width(800).height(600);
+
+        let mut clipped = batch(1, 0);
+        clipped.add_draw_call(DrawData {
+            scissor: Some(rect(-10, 10, 100, 100)),
+            vertex_count: 6,
+            ..Default::default()
+        });
+        clipped.add_draw_call(DrawData {
+            scissor: Some(rect(-10, 10, 100, 100)),
+            vertex_count: 12,
+            ..Default::default()
+        });
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &[clipped],
+                None,
+                extent,
+                ALL_LAYERS,
+            );
+        }
+
+        let full = rect(0, 0, 800, 600);
+        assert_eq!(
+            device.take_calls(),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                DeviceCall::SetViewport,
+                DeviceCall::SetScissor(full),
+                draw(0),
+                // draws with the same scissor don't set it again
+                DeviceCall::SetScissor(rect(0, 10, 90, 100)),
+                draw(6),
+                draw(12),
+                DeviceCall::SetScissor(full),
+            ]
+        );
+    }
+
+    #[test]
+    fn draw_states_replace_the_dynamic_state_of_the_material() {
+        use crate::types::DepthBias;
+
+        let device = MockDevice::default();
+        let mut batch = RenderBatch::default();
+        batch.set_material(Arc::new(Material {
+            pipeline: vk::Pipeline::from_raw(1),
+            multiview_pipeline: None,
+            info: MaterialCreateInfo {
+                dynamic_state: DrawState {
+                    depth_bias: Some(DepthBias::default()),
+                    topology: Some(vk::PrimitiveTopology::TRIANGLE_LIST),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }));
+        batch.add_draw_call(DrawData {
+            state: DrawState {
+                topology: Some(vk::PrimitiveTopology::TRIANGLE_STRIP),
+                // the material didn't make it dynamic
+                line_width: Some(4.0),
+                ..Default::default()
+            },
+            vertex_count: 4,
+            ..Default::default()
+        });
+        batch.add_draw_call(DrawData {
+            vertex_count: 6,
+            ..Default::default()
+        });
+
+        unsafe {
+            record_batches(
+                &device,
+                vk::CommandBuffer::null(),
+                vk::PipelineLayout::null(),
+                &[batch],
+                None,
+                vk::Extent2D::default(),
+                ALL_LAYERS,
+            );
+        }
+
+        assert_eq!(
+            recorded(&device),
+            [
+                DeviceCall::BindPipeline(vk::Pipeline::from_raw(1)),
+                DeviceCall::SetDepthBias,
+                DeviceCall::SetPrimitiveTopology(vk::PrimitiveTopology::TRIANGLE_LIST),
+                DeviceCall::SetPrimitiveTopology(vk::PrimitiveTopology::TRIANGLE_STRIP),
+                draw(4),
+                DeviceCall::SetPrimitiveTopology(vk::PrimitiveTopology::TRIANGLE_LIST),
+                draw(6),
+            ]
+        );
     }
 }
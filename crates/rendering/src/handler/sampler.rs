@@ -0,0 +1,126 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::VulkanDevice;
+
+use super::config::RendererConfig;
+
+/// describes how a texture is sampled
+/// samplers with the same description are shared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerDesc {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    /// used for u, v and w
+    pub address_mode: vk::SamplerAddressMode,
+    /// 0 or 1 disables anisotropic filtering
+    /// clamped to ``RendererConfig::max_anisotropy`` and what the gpu supports
+    pub max_anisotropy: u8,
+}
+
+impl SamplerDesc {
+    /// smooth filtering, used for most textures
+    /// uses as much anisotropy as allowed, so textures don't shimmer at grazing angles
+    pub const LINEAR: Self = Self {
+        mag_filter: vk::Filter::LINEAR,
+        min_filter: vk::Filter::LINEAR,
+        mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+        address_mode: vk::SamplerAddressMode::REPEAT,
+        max_anisotropy: 16,
+    };
+
+    /// keeps hard edges between texels, used for pixel art and voxel textures
+    pub const NEAREST: Self = Self {
+        mag_filter: vk::Filter::NEAREST,
+        min_filter: vk::Filter::NEAREST,
+        mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+        address_mode: vk::SamplerAddressMode::REPEAT,
+        max_anisotropy: 0,
+    };
+
+    #[must_use]
+    pub fn address_mode(mut self, address_mode: vk::SamplerAddressMode) -> Self {
+        self.address_mode = address_mode;
+        self
+    }
+
+    #[must_use]
+    pub fn max_anisotropy(mut self, max_anisotropy: u8) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self::LINEAR
+    }
+}
+
+/// creates every sampler only once and destroys them when dropped
+pub(crate) struct SamplerCache {
+    device: Arc<VulkanDevice>,
+    samplers: HashMap<SamplerDesc, vk::Sampler>,
+    /// the highest anisotropy that is allowed, 1.0 if it isn't supported
+    max_anisotropy: f32,
+}
+
+impl SamplerCache {
+    pub fn new(device: Arc<VulkanDevice>, config: &RendererConfig) -> Self {
+        let max_anisotropy = if device.features.sampler_anisotropy {
+            let limits = unsafe {
+                device
+                    .instance
+                    .get_physical_device_properties(device.pdevice)
+                    .limits
+            };
+            config.max_anisotropy.min(limits.max_sampler_anisotropy)
+        } else {
+            1.0
+        };
+
+        Self {
+            device,
+            samplers: HashMap::new(),
+            max_anisotropy,
+        }
+    }
+
+    /// get the sampler for the description, creates it if it doesn't exist yet
+    /// # Errors
+    /// if there is no memory left to create the sampler
+    pub fn get(&mut self, desc: SamplerDesc) -> VkResult<vk::Sampler> {
+        if let Some(sampler) = self.samplers.get(&desc) {
+            return Ok(*sampler);
+        }
+
+        let max_anisotropy = f32::from(desc.max_anisotropy).min(self.max_anisotropy);
+        let anisotropy = max_anisotropy > 1.0;
+
+        let create_info = vk::SamplerCreateInfo::default()
+            .mag_filter(desc.mag_filter)
+            .min_filter(desc.min_filter)
+            .mipmap_mode(desc.mipmap_mode)
+            .address_mode_u(desc.address_mode)
+            .address_mode_v(desc.address_mode)
+            .address_mode_w(desc.address_mode)
+            .anisotropy_enable(anisotropy)
+            .max_anisotropy(max_anisotropy.max(1.0))
+            .max_lod(vk::LOD_CLAMP_NONE);
+
+        let sampler = unsafe { self.device.create_sampler(&create_info, None) }?;
+        self.samplers.insert(desc, sampler);
+
+        Ok(sampler)
+    }
+}
+
+impl Drop for SamplerCache {
+    fn drop(&mut self) {
+        for sampler in self.samplers.values() {
+            unsafe { self.device.destroy_sampler(*sampler, None) };
+        }
+    }
+}
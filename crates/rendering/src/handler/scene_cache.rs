@@ -0,0 +1,88 @@
+// the hdr target of every swapchain image keeps the scene until the image is rendered again,
+// when the scene didn't change only the passes after it run, so a frame where just the ui changed
+// only tone maps the old scene again and paints the ui over it, see ``RenderHandler::reuse_scene``
+//
+// the cache is per swapchain image, an image that was last rendered before the scene changed has to draw it again
+
+/// which swapchain images hold the current scene in their hdr target
+#[derive(Debug, Default)]
+pub(crate) struct SceneCache {
+    /// indexed by the swapchain image
+    cached: Vec<bool>,
+    /// the application said nothing it owns changed since the last frame
+    requested: bool,
+    /// something the renderer owns changed since the last frame
+    invalidated: bool,
+}
+
+impl SceneCache {
+    /// the next frame can reuse the scene, unless ``invalidate`` is called before it starts
+    pub fn request_reuse(&mut self) {
+        self.requested = true;
+    }
+
+    /// the next frame has to draw the scene, no matter if the reuse was requested
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+
+    /// forget every image, after their targets were recreated
+    pub fn clear(&mut self) {
+        self.cached.clear();
+    }
+
+    /// decide if the frame that renders to the image can skip the scene, the image holds the scene afterwards
+    pub fn begin_frame(&mut self, image_index: u32) -> bool {
+        let requested = std::mem::take(&mut self.requested);
+        let invalidated = std::mem::take(&mut self.invalidated);
+        if !requested || invalidated {
+            self.cached.fill(false);
+        }
+
+        let index = image_index as usize;
+        if self.cached.len() <= index {
+            self.cached.resize(index + 1, false);
+        }
+        std::mem::replace(&mut self.cached[index], true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_image_renders_once_after_a_change() {
+        let mut cache = SceneCache::default();
+        assert!(!cache.begin_frame(0));
+
+        cache.request_reuse();
+        assert!(!cache.begin_frame(1));
+        cache.request_reuse();
+        assert!(cache.begin_frame(0));
+        cache.request_reuse();
+        assert!(cache.begin_frame(1));
+
+        // the reuse has to be requested for every frame
+        assert!(!cache.begin_frame(0));
+        cache.request_reuse();
+        assert!(!cache.begin_frame(1));
+    }
+
+    #[test]
+    fn invalidate_wins_over_the_request() {
+        let mut cache = SceneCache::default();
+        cache.begin_frame(0);
+
+        cache.invalidate();
+        cache.request_reuse();
+        assert!(!cache.begin_frame(0));
+
+        cache.request_reuse();
+        assert!(cache.begin_frame(0));
+
+        cache.clear();
+        cache.request_reuse();
+        assert!(!cache.begin_frame(0));
+    }
+}
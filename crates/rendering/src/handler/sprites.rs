@@ -0,0 +1,290 @@
+// textured quads for hud markers, particle sprites and editor gizmos, drawn after the particles in the main pass
+// the sprites of a batch are sorted by their atlas page and every page is one instanced draw of 6 vertices,
+// the instances are written to a buffer of the frame, so the cpu never writes to a buffer the gpu is reading
+//
+// world sprites are billboards that always face the camera, screen sprites are placed in pixels
+// sprites aren't depth tested, they are drawn on top of the batches and particles
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+use math::Color;
+
+use crate::{
+    types::{Material, VertexInput, VertexLayout},
+    vulkan::{Buffer, VulkanDevice},
+};
+
+use super::{ui::write_buffer, FLYING_FRAMES};
+
+/// a page that draws the sprite in its color without sampling a texture, for lines and markers
+pub const UNTEXTURED: u32 = u32::MAX;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpriteMode {
+    /// the position is in world space and the quad faces the camera, the size is in world units
+    #[default]
+    World,
+    /// the position is in pixels from the top left corner, the size is in pixels
+    Screen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    /// z is ignored for screen sprites
+    pub position: [f32; 3],
+    /// in radians, counter clockwise around the center
+    pub rotation: f32,
+    pub size: [f32; 2],
+    /// multiplied with the texture
+    pub color: Color,
+    /// the min and max uv of the sprite on its page
+    pub uv: [f32; 4],
+    /// the bindless texture index of the atlas page, or ``UNTEXTURED``
+    pub page: u32,
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            rotation: 0.0,
+            size: [1.0; 2],
+            color: Color::WHITE,
+            uv: [0.0, 0.0, 1.0, 1.0],
+            page: 0,
+        }
+    }
+}
+
+/// the instance layout used by the sprite shader
+/// needs to match ``shaders/sprites.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SpriteInstance {
+    /// w is the rotation
+    position: [f32; 4],
+    size: [f32; 2],
+    _padding: [f32; 2],
+    color: [f32; 4],
+    uv: [f32; 4],
+}
+
+/// the push constants used by the sprite shader
+/// needs to match ``shaders/sprites.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SpritePushConstants {
+    screen_size: [f32; 2],
+    page: u32,
+    /// 1 for screen sprites
+    screen: u32,
+}
+
+/// the instances of one page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SpriteDraw {
+    page: u32,
+    first_instance: u32,
+    instance_count: u32,
+}
+
+/// one ``SpriteInstance`` per instance at binding 0, the shader builds the quad from the vertex index
+pub(super) fn vertex_input() -> VertexInput {
+    VertexLayout::per_instance()
+        .attr::<[f32; 4]>("position")
+        .attr::<[f32; 2]>("size")
+        .skip(8)
+        .attr::<[f32; 4]>("color")
+        .attr::<[f32; 4]>("uv")
+        .vertex_input()
+}
+
+/// sprites that are drawn with the same material and mode
+/// the sprites are kept until they are changed, so static markers only have to be pushed once
+pub struct SpriteBatch {
+    device: Arc<VulkanDevice>,
+    pub mode: SpriteMode,
+    pub visible: bool,
+    pub sprites: Vec<Sprite>,
+    material: Arc<Material>,
+    /// written every frame, so the cpu never writes to a buffer the gpu is reading
+    instance_buffers: [Option<Arc<Buffer>>; FLYING_FRAMES],
+    /// the draws of the last upload
+    draws: Vec<SpriteDraw>,
+    /// the sprites that were visible in the last upload
+    uploaded: Vec<Sprite>,
+    /// the last upload drew other sprites than the one before
+    changed: bool,
+}
+
+impl SpriteBatch {
+    pub(crate) fn new(
+        device: Arc<VulkanDevice>,
+        material: Arc<Material>,
+        mode: SpriteMode,
+    ) -> Self {
+        Self {
+            device,
+            mode,
+            visible: true,
+            sprites: vec![],
+            material,
+            instance_buffers: [const { None }; FLYING_FRAMES],
+            draws: vec![],
+            uploaded: vec![],
+            changed: false,
+        }
+    }
+
+    pub fn push(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    /// how many draw calls the batch needed in the last frame, one for every page
+    #[must_use]
+    pub fn draw_count(&self) -> usize {
+        self.draws.len()
+    }
+
+    /// sort the sprites by page and write them to the buffer of this frame, the gpu must be done with the frame
+    /// # Errors
+    /// if there is no space to allocate a bigger buffer
+    pub(crate) fn upload(&mut self, frame_index: usize) -> VkResult<()> {
+        let shown = if self.visible { &self.sprites[..] } else { &[] };
+        self.changed = shown != self.uploaded.as_slice();
+        if self.changed {
+            self.uploaded = shown.to_vec();
+        }
+
+        self.draws.clear();
+        if !self.visible || self.sprites.is_empty() {
+            return Ok(());
+        }
+
+        let mut order: Vec<&Sprite> = self.sprites.iter().collect();
+        order.sort_by_key(|sprite| sprite.page);
+
+        self.draws = group_pages(order.iter().map(|sprite| sprite.page));
+
+        let instances: Vec<SpriteInstance> = order
+            .iter()
+            .map(|sprite| {
+                let [x, y, z] = sprite.position;
+                SpriteInstance {
+                    position: [x, y, z, sprite.rotation],
+                    size: sprite.size,
+                    _padding: [0.0; 2],
+                    color: sprite.color.to_array(),
+                    uv: sprite.uv,
+                }
+            })
+            .collect();
+
+        write_buffer(
+            &self.device,
+            &mut self.instance_buffers[frame_index],
+            &instances,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )
+    }
+
+    /// if the last upload drew other sprites than the one before, the scene can't be reused then
+    pub(crate) fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// needs to be called inside the main render pass, after ``upload`` for the same frame
+    pub(crate) unsafe fn record(
+        &self,
+        device: &VulkanDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        swapchain_size: vk::Extent2D,
+        frame_index: usize,
+    ) {
+        let Some(instance_buffer) = &self.instance_buffers[frame_index] else {
+            return;
+        };
+        if self.draws.is_empty() {
+            return;
+        }
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, self.material.pipeline);
+        self.material
+            .info
+            .viewport
+            .apply(device, cmd, swapchain_size);
+
+        for draw in &self.draws {
+            let push_constants = SpritePushConstants {
+                screen_size: [swapchain_size.width as f32, swapchain_size.height as f32],
+                page: draw.page,
+                screen: u32::from(self.mode == SpriteMode::Screen),
+            };
+            let data = std::slice::from_raw_parts(
+                std::ptr::from_ref(&push_constants).cast::<u8>(),
+                size_of::<SpritePushConstants>(),
+            );
+            device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+
+            let offset = u64::from(draw.first_instance) * size_of::<SpriteInstance>() as u64;
+            device.cmd_bind_vertex_buffers(cmd, 0, &[instance_buffer.handle()], &[offset]);
+            device.cmd_draw(cmd, 6, draw.instance_count, 0, 0);
+        }
+    }
+}
+
+/// one draw for every run of the same page, the pages have to be sorted
+fn group_pages(pages: impl Iterator<Item = u32>) -> Vec<SpriteDraw> {
+    let mut draws: Vec<SpriteDraw> = vec![];
+
+    for (i, page) in pages.enumerate() {
+        match draws.last_mut() {
+            Some(draw) if draw.page == page => draw.instance_count += 1,
+            _ => draws.push(SpriteDraw {
+                page,
+                first_instance: i as u32,
+                instance_count: 1,
+            }),
+        }
+    }
+
+    draws
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_draw_per_page() {
+        let draws = group_pages([0, 0, 2, 2, 2, 5].into_iter());
+
+        assert_eq!(
+            draws,
+            [
+                SpriteDraw {
+                    page: 0,
+                    first_instance: 0,
+                    instance_count: 2,
+                },
+                SpriteDraw {
+                    page: 2,
+                    first_instance: 2,
+                    instance_count: 3,
+                },
+                SpriteDraw {
+                    page: 5,
+                    first_instance: 5,
+                    instance_count: 1,
+                },
+            ]
+        );
+        assert!(group_pages(std::iter::empty()).is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+// screen space ambient occlusion, runs between the main pass and temporal anti aliasing
+// the occlusion is computed at half resolution from the normal and depth targets,
+// blurred without bleeding over edges and multiplied in to the color by the tonemap shader
+//
+// there is no frame graph, the passes are recorded one after the other in ``FrameContext``
+// and every pass puts a barrier in front of what it reads
+//
+// only pixels with a 1 in w of the normal target get occlusion, shaders opt in by writing it
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Swapchain, Texture, VulkanDevice};
+
+use super::bindless::BindlessHandler;
+
+/// needs to match ``WORK_GROUP_SIZE`` in ``shaders/ssao.slang``
+const WORK_GROUP_SIZE: u32 = 8;
+
+const AO_FORMAT: vk::Format = vk::Format::R32_SFLOAT;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SsaoSettings {
+    pub enabled: bool,
+    /// how far around a pixel is searched for occluders, in world units
+    pub radius: f32,
+    /// the occlusion is raised to this power, higher values make it darker
+    pub intensity: f32,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: 0.1,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// the push constants used by both ssao shaders
+/// needs to match ``shaders/ssao.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SsaoPushConstants {
+    normal_image: u32,
+    depth_image: u32,
+    ao_image: u32,
+    blurred_image: u32,
+    radius: f32,
+    intensity: f32,
+    /// the size of the ao images
+    extent: [u32; 2],
+}
+
+struct SsaoPipelines {
+    ao: vk::Pipeline,
+    blur: vk::Pipeline,
+}
+
+pub(crate) struct Ssao {
+    device: Arc<VulkanDevice>,
+    pub settings: SsaoSettings,
+    pipelines: Option<SsaoPipelines>,
+    /// the raw and the blurred occlusion
+    images: [Texture; 2],
+}
+
+impl Ssao {
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        swapchain: &Swapchain,
+        bindless: &BindlessHandler,
+    ) -> VkResult<Self> {
+        let images = create_images(&device, swapchain.get_image_extent())?;
+
+        let ssao = Self {
+            device,
+            settings: SsaoSettings::default(),
+            pipelines: None,
+            images,
+        };
+
+        ssao.write_descriptors(swapchain, bindless);
+        Ok(ssao)
+    }
+
+    /// recreate the ao images in the new size and write the new normal and depth targets to the bindless descriptors
+    /// the descriptor sets and the ao images must not be in use
+    /// # Errors
+    /// if there is no space left to allocate the ao images
+    pub fn on_resize(&mut self, swapchain: &Swapchain, bindless: &BindlessHandler) -> VkResult<()> {
+        self.images = create_images(&self.device, swapchain.get_image_extent())?;
+        self.write_descriptors(swapchain, bindless);
+        Ok(())
+    }
+
+    fn write_descriptors(&self, swapchain: &Swapchain, bindless: &BindlessHandler) {
+        assert!(
+            swapchain.images.len() <= BindlessHandler::MAX_SWAPCHAIN_IMAGES,
+            "too many swapchain images"
+        );
+
+        for (i, image) in swapchain.images.iter().enumerate() {
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                image.normal_view,
+                BindlessHandler::NORMAL_TARGET_SLOT + i,
+            );
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                image.depth_view,
+                BindlessHandler::DEPTH_TARGET_SLOT + i,
+            );
+        }
+
+        for (i, texture) in self.images.iter().enumerate() {
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                texture.view,
+                BindlessHandler::SSAO_SLOT + i,
+            );
+        }
+    }
+
+    /// set the compute shaders that compute and blur the occlusion
+    /// the shader modules are not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipelines
+    pub fn set_shaders(
+        &mut self,
+        ao_stage: vk::PipelineShaderStageCreateInfo,
+        blur_stage: vk::PipelineShaderStageCreateInfo,
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let create_infos = [ao_stage, blur_stage].map(|stage| {
+            vk::ComputePipelineCreateInfo::default()
+                .stage(stage)
+                .layout(layout)
+        });
+
+        let pipelines = unsafe {
+            self.device
+                .create_compute_pipelines(vk::PipelineCache::null(), &create_infos, None)
+                .map_err(|(_, err)| err)?
+        };
+
+        let new = SsaoPipelines {
+            ao: pipelines[0],
+            blur: pipelines[1],
+        };
+
+        if let Some(old) = self.pipelines.replace(new) {
+            unsafe {
+                self.device.destroy_pipeline(old.ao, None);
+                self.device.destroy_pipeline(old.blur, None);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the storage image slot of the occlusion the last ``record`` computed, None if the pass is off or has no shaders
+    /// for frames that reuse the last scene, the occlusion still belongs to it
+    pub fn output(&self) -> Option<u32> {
+        self.pipelines
+            .as_ref()
+            .filter(|_| self.settings.enabled)
+            .map(|_| BindlessHandler::SSAO_SLOT as u32 + 1)
+    }
+
+    /// compute and blur the occlusion of the frame
+    /// needs to be called after the main render pass ended
+    /// returns the storage image slot of the blurred occlusion, None if the pass is off or has no shaders
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        image_index: u32,
+        layout: vk::PipelineLayout,
+    ) -> Option<u32> {
+        let pipelines = self.pipelines.as_ref().filter(|_| self.settings.enabled)?;
+
+        let device = &self.device;
+        let [width, height] = self.images[0].extent;
+
+        // the main pass wrote the normal and depth targets,
+        // the previous frame read the ao images in the blur and the tonemap shader
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(
+                    vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                        | vk::PipelineStageFlags2::COMPUTE_SHADER
+                        | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                )
+                .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ),
+        );
+
+        let push_constants = SsaoPushConstants {
+            normal_image: (BindlessHandler::NORMAL_TARGET_SLOT + image_index as usize) as u32,
+            depth_image: (BindlessHandler::DEPTH_TARGET_SLOT + image_index as usize) as u32,
+            ao_image: BindlessHandler::SSAO_SLOT as u32,
+            blurred_image: BindlessHandler::SSAO_SLOT as u32 + 1,
+            radius: self.settings.radius,
+            intensity: self.settings.intensity,
+            extent: [width, height],
+        };
+
+        let data = std::slice::from_raw_parts(
+            std::ptr::from_ref(&push_constants).cast::<u8>(),
+            size_of::<SsaoPushConstants>(),
+        );
+        device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+
+        let groups = [width, height].map(|size| size.div_ceil(WORK_GROUP_SIZE));
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipelines.ao);
+        device.cmd_dispatch(cmd, groups[0], groups[1], 1);
+
+        // the blur reads the raw occlusion
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_READ),
+        );
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipelines.blur);
+        device.cmd_dispatch(cmd, groups[0], groups[1], 1);
+
+        // the tonemap shader reads the blurred occlusion
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_READ),
+        );
+
+        Some(push_constants.blurred_image)
+    }
+}
+
+impl Drop for Ssao {
+    fn drop(&mut self) {
+        if let Some(pipelines) = &self.pipelines {
+            unsafe {
+                self.device.destroy_pipeline(pipelines.ao, None);
+                self.device.destroy_pipeline(pipelines.blur, None);
+            }
+        }
+    }
+}
+
+/// the occlusion is computed for every 2x2 block of pixels
+fn half_extent(extent: vk::Extent2D) -> [u32; 2] {
+    [
+        extent.width.div_ceil(2).max(1),
+        extent.height.div_ceil(2).max(1),
+    ]
+}
+
+/// two half resolution images in the ``GENERAL`` layout, so the shaders can write and read them
+fn create_images(device: &Arc<VulkanDevice>, extent: vk::Extent2D) -> VkResult<[Texture; 2]> {
+    let create = || {
+        Texture::new(
+            device.clone(),
+            half_extent(extent),
+            AO_FORMAT,
+            1,
+            vk::ImageUsageFlags::STORAGE,
+        )
+    };
+    let images = [create()?, create()?];
+
+    let range = vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1);
+
+    let barriers = images.each_ref().map(|texture| {
+        vk::ImageMemoryBarrier2::default()
+            .image(texture.image)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .subresource_range(range)
+    });
+
+    unsafe {
+        device.immediate_submit(|cmd| {
+            device.pipeline_barrier(
+                cmd,
+                &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+            );
+        })?;
+    }
+
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_extent_covers_every_pixel() {
+        let extent = |width, height| vk::Extent2D { width, height };
+
+        assert_eq!(half_extent(extent(1920, 1080)), [960, 540]);
+        assert_eq!(half_extent(extent(801, 601)), [401, 301]);
+        assert_eq!(half_extent(extent(1, 1)), [1, 1]);
+        assert_eq!(half_extent(extent(0, 0)), [1, 1]);
+    }
+}
@@ -0,0 +1,174 @@
+// the secondary command buffers of the batches with ``BatchUsage::Static``
+// every batch has one for every frame in flight, because each of them binds the descriptor set of its frame
+// a command buffer is recorded again when the batch, the descriptor set, the render pass or the swapchain size changed,
+// the descriptor sets aren't update after bind, so writing to them makes the command buffers that bound them invalid
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::VulkanDevice;
+
+use super::{render_batch::RenderBatch, FLYING_FRAMES};
+
+/// everything a recorded command buffer depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RecordKey {
+    batch_version: u64,
+    set_version: u64,
+    render_pass: vk::RenderPass,
+    extent: vk::Extent2D,
+}
+
+struct CachedBatch {
+    command_buffers: [vk::CommandBuffer; FLYING_FRAMES],
+    keys: [Option<RecordKey>; FLYING_FRAMES],
+}
+
+/// what the command buffers are recorded for
+pub(crate) struct StaticTarget {
+    pub render_pass: vk::RenderPass,
+    pub extent: vk::Extent2D,
+    pub layout: vk::PipelineLayout,
+    pub descriptor_set: vk::DescriptorSet,
+    pub set_version: u64,
+    pub frame_index: usize,
+    /// the layers of the view, batches on none of them aren't executed, see ``RenderBatch::is_visible_in``
+    pub layers: u32,
+}
+
+/// the batches are found by their index, batches are never removed so it doesn't change
+pub(crate) struct StaticBatches {
+    device: Arc<VulkanDevice>,
+    command_pool: vk::CommandPool,
+    batches: Vec<Option<CachedBatch>>,
+}
+
+impl StaticBatches {
+    /// # Errors
+    /// if there is no memory left to create the command pool
+    pub fn new(device: Arc<VulkanDevice>) -> VkResult<Self> {
+        let pool_info = vk::CommandPoolCreateInfo::default()
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .queue_family_index(device.queues.graphics.0);
+        let command_pool = unsafe { device.create_command_pool(&pool_info, None) }?;
+
+        Ok(Self {
+            device,
+            command_pool,
+            batches: vec![],
+        })
+    }
+
+    /// record the static batches that changed and return the command buffers of the visible ones,
+    /// they have to be executed in the main render pass, begun with ``SubpassContents::SECONDARY_COMMAND_BUFFERS``
+    /// the command buffers of this frame must not be used by the gpu anymore
+    /// # Errors
+    /// if there is no memory left to allocate or record the command buffers
+    pub unsafe fn prepare(
+        &mut self,
+        batches: &[RenderBatch],
+        target: &StaticTarget,
+    ) -> VkResult<Vec<vk::CommandBuffer>> {
+        let _span = tracing::info_span!("static batches").entered();
+
+        if self.batches.len() < batches.len() {
+            self.batches.resize_with(batches.len(), || None);
+        }
+
+        let mut visible = vec![];
+        for (batch, cached) in batches.iter().zip(&mut self.batches) {
+            if !batch.is_cached() || !batch.is_visible_in(target.layers) {
+                continue;
+            }
+
+            let cached = match cached {
+                Some(cached) => cached,
+                None => {
+                    let alloc_info = vk::CommandBufferAllocateInfo::default()
+                        .command_pool(self.command_pool)
+                        .command_buffer_count(FLYING_FRAMES as u32)
+                        .level(vk::CommandBufferLevel::SECONDARY);
+                    let command_buffers = self.device.allocate_command_buffers(&alloc_info)?;
+
+                    cached.insert(CachedBatch {
+                        command_buffers: std::array::from_fn(|i| command_buffers[i]),
+                        keys: [None; FLYING_FRAMES],
+                    })
+                }
+            };
+
+            let key = RecordKey {
+                batch_version: batch.version(),
+                set_version: target.set_version,
+                render_pass: target.render_pass,
+                extent: target.extent,
+            };
+            let command_buffer = cached.command_buffers[target.frame_index];
+
+            if cached.keys[target.frame_index] != Some(key) {
+                // an outdated key must not stay if the recording fails halfway
+                cached.keys[target.frame_index] = None;
+                record(&self.device, command_buffer, batch, target)?;
+                cached.keys[target.frame_index] = Some(key);
+            }
+
+            visible.push(command_buffer);
+        }
+
+        Ok(visible)
+    }
+}
+
+/// begin a secondary command buffer that continues the main render pass and bind the descriptor set
+/// # Errors
+/// if there is no memory left to begin the command buffer
+pub(crate) unsafe fn begin_secondary(
+    device: &VulkanDevice,
+    command_buffer: vk::CommandBuffer,
+    target: &StaticTarget,
+) -> VkResult<()> {
+    let inheritance = vk::CommandBufferInheritanceInfo::default()
+        .render_pass(target.render_pass)
+        .subpass(0);
+    let begin_info = vk::CommandBufferBeginInfo::default()
+        .flags(vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE)
+        .inheritance_info(&inheritance);
+    device.begin_command_buffer(command_buffer, &begin_info)?;
+
+    // nothing is inherited from the primary command buffer except the render pass
+    device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        target.layout,
+        0,
+        &[target.descriptor_set],
+        &[],
+    );
+    Ok(())
+}
+
+unsafe fn record(
+    device: &VulkanDevice,
+    command_buffer: vk::CommandBuffer,
+    batch: &RenderBatch,
+    target: &StaticTarget,
+) -> VkResult<()> {
+    begin_secondary(device, command_buffer, target)?;
+    let mut bound_pipeline = vk::Pipeline::null();
+    batch.execute(
+        device,
+        command_buffer,
+        target.layout,
+        &mut bound_pipeline,
+        target.extent,
+    );
+    device.end_command_buffer(command_buffer)
+}
+
+impl Drop for StaticBatches {
+    fn drop(&mut self) {
+        // frees the command buffers as well
+        unsafe { self.device.destroy_command_pool(self.command_pool, None) };
+    }
+}
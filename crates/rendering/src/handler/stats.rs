@@ -0,0 +1,92 @@
+// counters for debugging and tooling, collected on demand
+
+use crate::vulkan::HeapBudget;
+
+use super::bindless::ResourceSlot;
+
+/// how many slots of one bindless array are in use
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SlotUsage {
+    /// written or about to be written
+    pub used: usize,
+    /// used by the renderer itself
+    pub reserved: usize,
+    pub capacity: usize,
+}
+
+impl SlotUsage {
+    pub(crate) fn count<T>(slots: &[ResourceSlot<T>]) -> Self {
+        let mut usage = Self {
+            capacity: slots.len(),
+            ..Default::default()
+        };
+
+        for slot in slots {
+            match slot {
+                ResourceSlot::Written(_) | ResourceSlot::Submited => usage.used += 1,
+                ResourceSlot::Reserved => usage.reserved += 1,
+                ResourceSlot::Empty => {}
+            }
+        }
+
+        usage
+    }
+
+    /// slots that can still be taken
+    #[must_use]
+    pub fn free(&self) -> usize {
+        self.capacity - self.used - self.reserved
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BindlessUsage {
+    pub uniform_buffers: SlotUsage,
+    pub storage_buffers: SlotUsage,
+    pub storage_images: SlotUsage,
+    pub textures: SlotUsage,
+}
+
+/// how many resources the renderer currently keeps alive
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceCounts {
+    pub render_batches: usize,
+    pub draw_calls: usize,
+    pub materials: usize,
+    pub material_instances: usize,
+    pub particle_systems: usize,
+    pub sprite_batches: usize,
+    /// resources waiting for the gpu to finish before they are destroyed
+    pub pending_destroys: usize,
+}
+
+/// the memory of every heap of the gpu, see ``RenderHandler::memory_report``
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub heaps: Vec<HeapBudget>,
+}
+
+impl MemoryReport {
+    /// the bytes the device local heaps are over their budget, 0 if everything fits
+    /// when it isn't 0 the application should free something, like distant chunks, before allocating more
+    #[must_use]
+    pub fn over_budget(&self) -> u64 {
+        self.device_local().map(HeapBudget::over_budget).sum()
+    }
+
+    /// the bytes that can still be allocated in the device local heaps
+    #[must_use]
+    pub fn available(&self) -> u64 {
+        self.device_local().map(HeapBudget::available).sum()
+    }
+
+    /// the bytes the renderer allocated in the device local heaps
+    #[must_use]
+    pub fn allocated(&self) -> u64 {
+        self.device_local().map(|heap| heap.allocated).sum()
+    }
+
+    fn device_local(&self) -> impl Iterator<Item = &HeapBudget> {
+        self.heaps.iter().filter(|heap| heap.device_local)
+    }
+}
@@ -0,0 +1,381 @@
+// temporal anti aliasing, runs between the main pass and tone mapping
+// the camera moves by a fraction of a pixel every frame, see ``RenderHandler::taa_jitter``,
+// and every frame is blended with the history of the frames before it
+//
+// the history is reprojected with the velocity target and clipped to the colors around the pixel,
+// so things that moved or got uncovered don't leave ghosts behind
+// the result is written to the other history image and copied back in to the hdr target,
+// so everything after this pass doesn't need to know about it
+//
+// only pixels with a 1 in z of the velocity target are blended, shaders opt in by writing it
+// and materials can opt out with ``MaterialCreateInfo::skip_temporal_aa``
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Swapchain, Texture, VulkanDevice, HDR_FORMAT};
+
+use super::bindless::BindlessHandler;
+
+/// needs to match ``WORK_GROUP_SIZE`` in ``shaders/taa.slang``
+const WORK_GROUP_SIZE: u32 = 8;
+
+/// the jitter repeats after this many frames
+const JITTER_SAMPLES: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaaSettings {
+    pub enabled: bool,
+    /// how much of the history is kept every frame,
+    /// higher values are smoother but take longer to catch up with changes
+    pub history_weight: f32,
+}
+
+impl Default for TaaSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            history_weight: 0.9,
+        }
+    }
+}
+
+/// the push constants used by the taa shader
+/// needs to match ``shaders/taa.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct TaaPushConstants {
+    hdr_image: u32,
+    velocity_image: u32,
+    history: u32,
+    output: u32,
+    history_weight: f32,
+    /// 1 if the history isn't valid and the frame is only copied
+    reset: u32,
+    extent: [u32; 2],
+}
+
+pub(crate) struct TemporalAa {
+    device: Arc<VulkanDevice>,
+    pub settings: TaaSettings,
+    pipeline: Option<vk::Pipeline>,
+    history: [Texture; 2],
+    /// the history image that is written this frame, the other one is read
+    current: usize,
+    /// the history is thrown away after a resize or when taa was turned off
+    history_valid: bool,
+    /// counts the frames for the jitter sequence
+    frame: u32,
+}
+
+impl TemporalAa {
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        swapchain: &Swapchain,
+        bindless: &BindlessHandler,
+    ) -> VkResult<Self> {
+        let history = create_history(&device, swapchain.get_image_extent())?;
+
+        let taa = Self {
+            device,
+            settings: TaaSettings::default(),
+            pipeline: None,
+            history,
+            current: 0,
+            history_valid: false,
+            frame: 0,
+        };
+
+        taa.write_descriptors(swapchain, bindless);
+        Ok(taa)
+    }
+
+    /// recreate the history in the new size and write the new velocity targets to the bindless descriptors
+    /// the descriptor sets and the history must not be in use
+    /// # Errors
+    /// if there is no space left to allocate the history
+    pub fn on_resize(&mut self, swapchain: &Swapchain, bindless: &BindlessHandler) -> VkResult<()> {
+        self.history = create_history(&self.device, swapchain.get_image_extent())?;
+        self.history_valid = false;
+        self.write_descriptors(swapchain, bindless);
+        Ok(())
+    }
+
+    fn write_descriptors(&self, swapchain: &Swapchain, bindless: &BindlessHandler) {
+        assert!(
+            swapchain.images.len() <= BindlessHandler::MAX_SWAPCHAIN_IMAGES,
+            "too many swapchain images"
+        );
+
+        for (i, image) in swapchain.images.iter().enumerate() {
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                image.velocity_view,
+                BindlessHandler::VELOCITY_TARGET_SLOT + i,
+            );
+        }
+
+        for (i, history) in self.history.iter().enumerate() {
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                history.view,
+                BindlessHandler::TAA_HISTORY_SLOT + i,
+            );
+        }
+    }
+
+    /// set the compute shader that blends the frame with the history
+    /// the shader module is not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_shader(
+        &mut self,
+        stage: vk::PipelineShaderStageCreateInfo,
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let create_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+
+        let pipeline = unsafe {
+            self.device
+                .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, err)| err)?
+        }[0];
+
+        if let Some(old) = self.pipeline.replace(pipeline) {
+            unsafe { self.device.destroy_pipeline(old, None) };
+        }
+
+        // the old shader might have left something else in the history
+        self.history_valid = false;
+        Ok(())
+    }
+
+    /// if the pass runs, it needs a shader and to be enabled
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.settings.enabled && self.pipeline.is_some()
+    }
+
+    /// the offset of the projection for the next frame in normalized device coordinates
+    #[must_use]
+    pub fn jitter(&self, extent: vk::Extent2D) -> [f32; 2] {
+        if !self.is_active() || extent.width == 0 || extent.height == 0 {
+            return [0.0; 2];
+        }
+
+        let sample = self.frame % JITTER_SAMPLES + 1;
+        [
+            (halton(sample, 2) - 0.5) * 2.0 / extent.width as f32,
+            (halton(sample, 3) - 0.5) * 2.0 / extent.height as f32,
+        ]
+    }
+
+    /// swap the history images after a frame was recorded
+    pub fn advance(&mut self) {
+        if self.is_active() {
+            self.current = 1 - self.current;
+            self.history_valid = true;
+            self.frame = self.frame.wrapping_add(1);
+        } else {
+            self.history_valid = false;
+        }
+    }
+
+    /// blend the hdr target with the history and write the result back in to it
+    /// needs to be called after the main render pass ended
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        layout: vk::PipelineLayout,
+    ) {
+        let Some(pipeline) = self.pipeline.filter(|_| self.settings.enabled) else {
+            return;
+        };
+
+        let device = &self.device;
+        let extent = swapchain.get_image_extent();
+        let image = &swapchain.images[image_index as usize];
+        let output = &self.history[self.current];
+
+        // the main pass wrote the hdr and velocity targets,
+        // the previous frame wrote the history that is read and read the one that is written
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(
+                    vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                        | vk::PipelineStageFlags2::COMPUTE_SHADER
+                        | vk::PipelineStageFlags2::COPY,
+                )
+                .src_access_mask(
+                    vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                        | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                )
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ),
+        );
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline);
+
+        let push_constants = TaaPushConstants {
+            hdr_image: (BindlessHandler::HDR_TARGET_SLOT + image_index as usize) as u32,
+            velocity_image: (BindlessHandler::VELOCITY_TARGET_SLOT + image_index as usize) as u32,
+            history: (BindlessHandler::TAA_HISTORY_SLOT + 1 - self.current) as u32,
+            output: (BindlessHandler::TAA_HISTORY_SLOT + self.current) as u32,
+            history_weight: self.settings.history_weight,
+            reset: u32::from(!self.history_valid),
+            extent: [extent.width, extent.height],
+        };
+
+        let data = std::slice::from_raw_parts(
+            std::ptr::from_ref(&push_constants).cast::<u8>(),
+            size_of::<TaaPushConstants>(),
+        );
+        device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+
+        device.cmd_dispatch(
+            cmd,
+            extent.width.div_ceil(WORK_GROUP_SIZE),
+            extent.height.div_ceil(WORK_GROUP_SIZE),
+            1,
+        );
+
+        // the copy overwrites the hdr target the shader read
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                .dst_access_mask(
+                    vk::AccessFlags2::TRANSFER_READ | vk::AccessFlags2::TRANSFER_WRITE,
+                ),
+        );
+
+        let layers = vk::ImageSubresourceLayers::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .layer_count(1);
+
+        let region = vk::ImageCopy::default()
+            .src_subresource(layers)
+            .dst_subresource(layers)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            });
+
+        device.cmd_copy_image(
+            cmd,
+            output.image,
+            vk::ImageLayout::GENERAL,
+            image.hdr_image,
+            vk::ImageLayout::GENERAL,
+            &[region],
+        );
+
+        // tone mapping reads the hdr target either in a shader or with a blit
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(
+                    vk::PipelineStageFlags2::FRAGMENT_SHADER | vk::PipelineStageFlags2::BLIT,
+                )
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::TRANSFER_READ,
+                ),
+        );
+    }
+}
+
+impl Drop for TemporalAa {
+    fn drop(&mut self) {
+        if let Some(pipeline) = self.pipeline {
+            unsafe { self.device.destroy_pipeline(pipeline, None) };
+        }
+    }
+}
+
+/// two hdr images in the ``GENERAL`` layout, so they can be written by the shader and copied from
+fn create_history(device: &Arc<VulkanDevice>, extent: vk::Extent2D) -> VkResult<[Texture; 2]> {
+    let create = || {
+        Texture::new(
+            device.clone(),
+            [extent.width, extent.height],
+            HDR_FORMAT,
+            1,
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC,
+        )
+    };
+    let history = [create()?, create()?];
+
+    let range = vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1);
+
+    let barriers = history.each_ref().map(|texture| {
+        vk::ImageMemoryBarrier2::default()
+            .image(texture.image)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .subresource_range(range)
+    });
+
+    unsafe {
+        device.immediate_submit(|cmd| {
+            device.pipeline_barrier(
+                cmd,
+                &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+            );
+        })?;
+    }
+
+    Ok(history)
+}
+
+/// the halton sequence spreads the jitter evenly over the pixel, ``index`` starts at 1
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halton_stays_inside_the_pixel() {
+        assert_eq!(halton(1, 2), 0.5);
+        assert_eq!(halton(2, 2), 0.25);
+        assert_eq!(halton(3, 2), 0.75);
+        assert!((halton(1, 3) - 1.0 / 3.0).abs() < 1e-6);
+        assert!((halton(2, 3) - 2.0 / 3.0).abs() < 1e-6);
+
+        for index in 1..=JITTER_SAMPLES {
+            for base in [2, 3] {
+                let value = halton(index, base);
+                assert!((0.0..1.0).contains(&value));
+            }
+        }
+    }
+}
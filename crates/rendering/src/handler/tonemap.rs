@@ -0,0 +1,366 @@
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Swapchain, VulkanDevice};
+
+use super::bindless::BindlessHandler;
+
+/// the curve used to map the hdr colors in to the range of the swapchain
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum TonemapOperator {
+    #[default]
+    Aces = 0,
+    Reinhard = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonemapSettings {
+    pub operator: TonemapOperator,
+    /// the hdr color is multiplied by this before tone mapping
+    pub exposure: f32,
+    /// should be 1.0 if the swapchain is srgb, as the gpu already does the conversion
+    pub gamma: f32,
+}
+
+/// the push constants used by the tonemap shader
+/// needs to match ``shaders/tonemap.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct TonemapPushConstants {
+    exposure: f32,
+    gamma: f32,
+    operator: u32,
+    hdr_image: u32,
+    /// the half resolution ambient occlusion, ``u32::MAX`` if there is none
+    ao_image: u32,
+}
+
+/// maps the hdr target of every swapchain image in to the swapchain image
+/// this needs a fullscreen shader, if there is none, the hdr image is just copied
+pub(crate) struct Tonemapper {
+    device: Arc<VulkanDevice>,
+    pub settings: TonemapSettings,
+    renderpass: vk::RenderPass,
+    framebuffers: Vec<vk::Framebuffer>,
+    pipeline: Option<vk::Pipeline>,
+}
+
+impl Tonemapper {
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        swapchain: &Swapchain,
+        bindless: &BindlessHandler,
+    ) -> VkResult<Self> {
+        let attachments = [vk::AttachmentDescription::default()
+            .format(swapchain.image_format())
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(swapchain.output_layout())];
+
+        let color_attachments_ref = [vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+
+        // wait for the main render pass to finish writing the hdr image
+        let subpass_dependencies = [vk::SubpassDependency::default()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_stage_mask(
+                vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            )
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            )];
+
+        let subpasses = [vk::SubpassDescription::default()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachments_ref)];
+
+        let renderpass_info = vk::RenderPassCreateInfo::default()
+            .attachments(&attachments)
+            .dependencies(&subpass_dependencies)
+            .subpasses(&subpasses);
+
+        let renderpass = unsafe { device.create_render_pass(&renderpass_info, None)? };
+
+        let gamma = if swapchain.is_srgb() { 1.0 } else { 2.2 };
+
+        let mut tonemapper = Self {
+            device,
+            settings: TonemapSettings {
+                operator: TonemapOperator::default(),
+                exposure: 1.0,
+                gamma,
+            },
+            renderpass,
+            framebuffers: vec![],
+            pipeline: None,
+        };
+
+        tonemapper.on_resize(swapchain, bindless);
+        Ok(tonemapper)
+    }
+
+    /// recreate the framebuffers and write the new hdr targets to the bindless descriptors
+    /// the descriptor sets must not be in use
+    /// # Panics
+    /// if the swapchain has more images than ``BindlessHandler::MAX_SWAPCHAIN_IMAGES``
+    pub fn on_resize(&mut self, swapchain: &Swapchain, bindless: &BindlessHandler) {
+        assert!(
+            swapchain.images.len() <= BindlessHandler::MAX_SWAPCHAIN_IMAGES,
+            "too many swapchain images"
+        );
+
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { self.device.destroy_framebuffer(framebuffer, None) };
+        }
+
+        let extent = swapchain.get_image_extent();
+
+        self.framebuffers = swapchain
+            .images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| {
+                bindless.set_storage_image_all_sets(
+                    &*self.device,
+                    image.hdr_view,
+                    BindlessHandler::HDR_TARGET_SLOT + i,
+                );
+
+                let attachments = [image.output_view()];
+                let framebuffer_info = vk::FramebufferCreateInfo::default()
+                    .render_pass(self.renderpass)
+                    .attachments(&attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1);
+
+                unsafe {
+                    self.device
+                        .create_framebuffer(&framebuffer_info, None)
+                        .unwrap()
+                }
+            })
+            .collect();
+    }
+
+    /// set the fullscreen shader used for tone mapping
+    /// the vertex shader gets no input and is drawn with 3 vertices
+    /// the shader modules are not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_shader(
+        &mut self,
+        stages: &[vk::PipelineShaderStageCreateInfo],
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default();
+
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .line_width(1.0);
+
+        // the viewport changes with the swapchain size
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let attachments = [vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false)];
+
+        let color_blend_state =
+            vk::PipelineColorBlendStateCreateInfo::default().attachments(&attachments);
+
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+        let create_info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .color_blend_state(&color_blend_state)
+            .multisample_state(&multisample_state)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .subpass(0)
+            .render_pass(self.renderpass);
+
+        let pipeline = unsafe {
+            self.device
+                .create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, err)| err)?
+        }[0];
+
+        if let Some(old) = self.pipeline.replace(pipeline) {
+            unsafe { self.device.destroy_pipeline(old, None) };
+        }
+
+        Ok(())
+    }
+
+    /// record the tone mapping in to the swapchain image
+    /// the colors are multiplied with ``ao_image`` if there is one, the blit fallback ignores it
+    /// needs to be called after the main render pass ended
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        layout: vk::PipelineLayout,
+        ao_image: Option<u32>,
+    ) {
+        let Some(pipeline) = self.pipeline else {
+            self.record_blit(cmd, swapchain, image_index);
+            return;
+        };
+
+        let extent = swapchain.get_image_extent();
+
+        let begin_info = vk::RenderPassBeginInfo::default()
+            .render_pass(self.renderpass)
+            .framebuffer(self.framebuffers[image_index as usize])
+            .render_area(vk::Rect2D::default().extent(extent));
+
+        let device = &self.device;
+        device.cmd_begin_render_pass(cmd, &begin_info, vk::SubpassContents::INLINE);
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline);
+
+        let viewport = vk::Viewport::default()
+            .width(extent.width as f32)
+            .height(extent.height as f32)
+            .max_depth(1.0);
+        device.cmd_set_viewport(cmd, 0, &[viewport]);
+        device.cmd_set_scissor(cmd, 0, &[vk::Rect2D::default().extent(extent)]);
+
+        let push_constants = TonemapPushConstants {
+            exposure: self.settings.exposure,
+            gamma: self.settings.gamma,
+            operator: self.settings.operator as u32,
+            hdr_image: (BindlessHandler::HDR_TARGET_SLOT + image_index as usize) as u32,
+            ao_image: ao_image.unwrap_or(u32::MAX),
+        };
+
+        let data = std::slice::from_raw_parts(
+            std::ptr::from_ref(&push_constants).cast::<u8>(),
+            size_of::<TonemapPushConstants>(),
+        );
+        device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+
+        device.cmd_draw(cmd, 3, 1, 0, 0);
+        device.cmd_end_render_pass(cmd);
+    }
+
+    /// fallback if there is no tonemap shader, the colors are just clamped
+    unsafe fn record_blit(&self, cmd: vk::CommandBuffer, swapchain: &Swapchain, image_index: u32) {
+        let device = &self.device;
+        let image = &swapchain.images[image_index as usize];
+        let extent = swapchain.get_image_extent();
+
+        let subresource_range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+
+        let hdr_barrier = vk::ImageMemoryBarrier2::default()
+            .image(image.hdr_image)
+            .old_layout(vk::ImageLayout::GENERAL)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+            .subresource_range(subresource_range);
+
+        // the submit waits for the image to be acquired at the color attachment output stage
+        let to_transfer = vk::ImageMemoryBarrier2::default()
+            .image(image.output_image())
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .subresource_range(subresource_range);
+
+        let barriers = [hdr_barrier, to_transfer];
+        device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+        );
+
+        let layers = vk::ImageSubresourceLayers::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .layer_count(1);
+
+        let offsets = [
+            vk::Offset3D::default(),
+            vk::Offset3D {
+                x: extent.width as i32,
+                y: extent.height as i32,
+                z: 1,
+            },
+        ];
+
+        let region = vk::ImageBlit::default()
+            .src_subresource(layers)
+            .src_offsets(offsets)
+            .dst_subresource(layers)
+            .dst_offsets(offsets);
+
+        device.cmd_blit_image(
+            cmd,
+            image.hdr_image,
+            vk::ImageLayout::GENERAL,
+            image.output_image(),
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+            vk::Filter::NEAREST,
+        );
+
+        // the present waits for the semaphore, the upscale waits for the blit with its own barrier
+        let to_present = vk::ImageMemoryBarrier2::default()
+            .image(image.output_image())
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(swapchain.output_layout())
+            .src_stage_mask(vk::PipelineStageFlags2::BLIT)
+            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .subresource_range(subresource_range);
+
+        device.image_barrier(cmd, to_present);
+    }
+}
+
+impl Drop for Tonemapper {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(pipeline) = self.pipeline {
+                self.device.destroy_pipeline(pipeline, None);
+            }
+            for framebuffer in &self.framebuffers {
+                self.device.destroy_framebuffer(*framebuffer, None);
+            }
+            self.device.destroy_render_pass(self.renderpass, None);
+        }
+    }
+}
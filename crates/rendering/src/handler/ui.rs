@@ -0,0 +1,685 @@
+// draws the ui on top of the tone mapped frame
+//
+// egui blends its premultiplied colors in gamma space, so the ui is first drawn in to a UNORM target
+// where the blending happens exactly like egui expects it, and is then composited on to the swapchain image
+// if the swapchain is srgb, the composite shader converts the ui to linear, so the gpu encodes it back correctly
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Buffer, Swapchain, Texture, VulkanDevice};
+
+use super::{bindless::BindlessHandler, FLYING_FRAMES};
+
+/// the vertex layout used by the ui shader
+/// the color is srgb with premultiplied alpha, like egui outputs it
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct UiVertex {
+    /// in pixels from the top left corner
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [u8; 4],
+}
+
+/// a range of indices drawn with one texture and clip rect
+#[derive(Debug, Clone, Copy)]
+struct UiDraw {
+    clip: vk::Rect2D,
+    texture: u32,
+    first_index: u32,
+    index_count: u32,
+    vertex_offset: i32,
+}
+
+/// the push constants used by the ui shader
+/// needs to match ``shaders/ui.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct UiPushConstants {
+    screen_size: [f32; 2],
+    texture: u32,
+}
+
+/// the push constants used by the ui composite shader
+/// needs to match ``shaders/ui.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CompositePushConstants {
+    ui_image: u32,
+    /// 1 if the swapchain is srgb and the ui has to be converted to linear
+    to_linear: u32,
+}
+
+/// a texture created for egui, it's kept alive until egui frees it
+#[cfg(feature = "egui")]
+pub(crate) struct UiTexture {
+    pub handle: crate::assets::texture::TextureHandle,
+    pub texture: Arc<Texture>,
+}
+
+pub(crate) struct UiPainter {
+    device: Arc<VulkanDevice>,
+    ui_renderpass: vk::RenderPass,
+    composite_renderpass: vk::RenderPass,
+    /// one UNORM target for every swapchain image, only created once the shaders are set
+    targets: Vec<Texture>,
+    ui_framebuffers: Vec<vk::Framebuffer>,
+    composite_framebuffers: Vec<vk::Framebuffer>,
+    /// the ui and the composite pipeline
+    pipelines: Option<[vk::Pipeline; 2]>,
+    srgb: bool,
+
+    vertices: Vec<UiVertex>,
+    indices: Vec<u32>,
+    draws: Vec<UiDraw>,
+    /// written every frame, so the cpu never writes to a buffer the gpu is reading
+    vertex_buffers: [Option<Arc<Buffer>>; FLYING_FRAMES],
+    index_buffers: [Option<Arc<Buffer>>; FLYING_FRAMES],
+
+    /// the textures created for egui, by their managed id
+    #[cfg(feature = "egui")]
+    pub textures: std::collections::HashMap<u64, UiTexture>,
+    /// textures egui has freed, they are removed once the frames that used them have been recorded
+    #[cfg(feature = "egui")]
+    pub pending_free: Vec<u64>,
+}
+
+impl UiPainter {
+    pub fn new(device: Arc<VulkanDevice>, swapchain: &Swapchain) -> VkResult<Self> {
+        let ui_renderpass = create_ui_renderpass(&device)?;
+        let composite_renderpass = create_composite_renderpass(
+            &device,
+            swapchain.image_format(),
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        )?;
+
+        Ok(Self {
+            device,
+            ui_renderpass,
+            composite_renderpass,
+            targets: vec![],
+            ui_framebuffers: vec![],
+            composite_framebuffers: vec![],
+            pipelines: None,
+            srgb: swapchain.is_srgb(),
+            vertices: vec![],
+            indices: vec![],
+            draws: vec![],
+            vertex_buffers: [const { None }; FLYING_FRAMES],
+            index_buffers: [const { None }; FLYING_FRAMES],
+            #[cfg(feature = "egui")]
+            textures: std::collections::HashMap::new(),
+            #[cfg(feature = "egui")]
+            pending_free: vec![],
+        })
+    }
+
+    /// recreate the ui targets and framebuffers, nothing is created as long as there are no shaders
+    /// the descriptor sets and the old targets must not be in use
+    /// # Errors
+    /// if there is no space to allocate the targets
+    pub fn on_resize(&mut self, swapchain: &Swapchain, bindless: &BindlessHandler) -> VkResult<()> {
+        unsafe {
+            for framebuffer in self
+                .ui_framebuffers
+                .drain(..)
+                .chain(self.composite_framebuffers.drain(..))
+            {
+                self.device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+        self.targets.clear();
+        self.srgb = swapchain.is_srgb();
+
+        if self.pipelines.is_none() {
+            return Ok(());
+        }
+
+        // the ui isn't scaled with the internal resolution
+        let extent = swapchain.present_extent();
+
+        for (i, image) in swapchain.images.iter().enumerate() {
+            let target = Texture::new(
+                self.device.clone(),
+                [extent.width, extent.height],
+                vk::Format::R8G8B8A8_UNORM,
+                1,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::STORAGE,
+            )?;
+
+            bindless.set_storage_image_all_sets(
+                &*self.device,
+                target.view,
+                BindlessHandler::UI_TARGET_SLOT + i,
+            );
+
+            for (renderpass, view, framebuffers) in [
+                (self.ui_renderpass, target.view, &mut self.ui_framebuffers),
+                (
+                    self.composite_renderpass,
+                    image.main_view,
+                    &mut self.composite_framebuffers,
+                ),
+            ] {
+                let attachments = [view];
+                let framebuffer_info = vk::FramebufferCreateInfo::default()
+                    .render_pass(renderpass)
+                    .attachments(&attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1);
+
+                framebuffers
+                    .push(unsafe { self.device.create_framebuffer(&framebuffer_info, None)? });
+            }
+
+            self.targets.push(target);
+        }
+
+        Ok(())
+    }
+
+    /// set the shaders that draw the ui meshes and composite the ui on to the swapchain image
+    /// the shader modules are not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipelines or allocating the ui targets
+    pub fn set_shaders(
+        &mut self,
+        ui_stages: &[vk::PipelineShaderStageCreateInfo],
+        composite_stages: &[vk::PipelineShaderStageCreateInfo],
+        layout: vk::PipelineLayout,
+        swapchain: &Swapchain,
+        bindless: &BindlessHandler,
+    ) -> VkResult<()> {
+        let bindings = [vk::VertexInputBindingDescription::default()
+            .binding(0)
+            .stride(size_of::<UiVertex>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)];
+
+        let attributes = [
+            (
+                vk::Format::R32G32_SFLOAT,
+                std::mem::offset_of!(UiVertex, pos),
+            ),
+            (
+                vk::Format::R32G32_SFLOAT,
+                std::mem::offset_of!(UiVertex, uv),
+            ),
+            // the srgb bytes are passed as they are, egui multiplies the colors in gamma space
+            (
+                vk::Format::R8G8B8A8_UNORM,
+                std::mem::offset_of!(UiVertex, color),
+            ),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(location, (format, offset))| {
+            vk::VertexInputAttributeDescription::default()
+                .binding(0)
+                .location(location as u32)
+                .format(format)
+                .offset(offset as u32)
+        })
+        .collect::<Vec<_>>();
+
+        let ui_vertex_input = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        // premultiplied alpha, the same blending egui uses
+        let blend = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::ONE)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_DST_ALPHA)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        let ui = create_pipeline(
+            &self.device,
+            ui_stages,
+            &ui_vertex_input,
+            blend,
+            layout,
+            self.ui_renderpass,
+        )?;
+
+        // the ui target is premultiplied as well
+        let composite_blend = blend
+            .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE);
+
+        let composite = match create_pipeline(
+            &self.device,
+            composite_stages,
+            &vk::PipelineVertexInputStateCreateInfo::default(),
+            composite_blend,
+            layout,
+            self.composite_renderpass,
+        ) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                unsafe { self.device.destroy_pipeline(ui, None) };
+                return Err(err);
+            }
+        };
+
+        let had_targets = self.pipelines.is_some();
+        if let Some(old) = self.pipelines.replace([ui, composite]) {
+            for pipeline in old {
+                unsafe { self.device.destroy_pipeline(pipeline, None) };
+            }
+        }
+
+        if !had_targets {
+            self.on_resize(swapchain, bindless)?;
+        }
+
+        Ok(())
+    }
+
+    /// write the ui meshes to the buffers of this frame, the gpu must be done with the frame
+    /// # Errors
+    /// if there is no space to allocate bigger buffers
+    pub fn upload(&mut self, frame_index: usize) -> VkResult<()> {
+        write_buffer(
+            &self.device,
+            &mut self.vertex_buffers[frame_index],
+            &self.vertices,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        write_buffer(
+            &self.device,
+            &mut self.index_buffers[frame_index],
+            &self.indices,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+        )
+    }
+
+    /// draw the ui and composite it on to the swapchain image
+    /// needs to be called after the tone mapping, the swapchain image is in ``PRESENT_SRC_KHR`` layout
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        frame_index: usize,
+        layout: vk::PipelineLayout,
+    ) {
+        let Some([ui_pipeline, composite_pipeline]) = self.pipelines else {
+            return;
+        };
+        let (Some(vertex_buffer), Some(index_buffer)) = (
+            &self.vertex_buffers[frame_index],
+            &self.index_buffers[frame_index],
+        ) else {
+            return;
+        };
+        if self.draws.is_empty() || self.targets.len() != swapchain.images.len() {
+            return;
+        }
+
+        let _span = tracing::info_span!("ui", draws = self.draws.len()).entered();
+
+        let device = &self.device;
+        let extent = swapchain.present_extent();
+        let render_area = vk::Rect2D::default().extent(extent);
+
+        let viewport = vk::Viewport::default()
+            .width(extent.width as f32)
+            .height(extent.height as f32)
+            .max_depth(1.0);
+
+        let clear_values = [vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 0.0],
+            },
+        }];
+
+        let begin_info = vk::RenderPassBeginInfo::default()
+            .render_pass(self.ui_renderpass)
+            .framebuffer(self.ui_framebuffers[image_index as usize])
+            .render_area(render_area)
+            .clear_values(&clear_values);
+
+        device.cmd_begin_render_pass(cmd, &begin_info, vk::SubpassContents::INLINE);
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, ui_pipeline);
+        device.cmd_set_viewport(cmd, 0, &[viewport]);
+        device.cmd_bind_vertex_buffers(cmd, 0, &[vertex_buffer.handle()], &[0]);
+        device.cmd_bind_index_buffer(cmd, index_buffer.handle(), 0, vk::IndexType::UINT32);
+
+        for draw in &self.draws {
+            let push_constants = UiPushConstants {
+                screen_size: [extent.width as f32, extent.height as f32],
+                texture: draw.texture,
+            };
+            push(device, cmd, layout, &push_constants);
+
+            device.cmd_set_scissor(cmd, 0, &[draw.clip]);
+            device.cmd_draw_indexed(
+                cmd,
+                draw.index_count,
+                1,
+                draw.first_index,
+                draw.vertex_offset,
+                0,
+            );
+        }
+
+        device.cmd_end_render_pass(cmd);
+
+        let begin_info = vk::RenderPassBeginInfo::default()
+            .render_pass(self.composite_renderpass)
+            .framebuffer(self.composite_framebuffers[image_index as usize])
+            .render_area(render_area);
+
+        device.cmd_begin_render_pass(cmd, &begin_info, vk::SubpassContents::INLINE);
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, composite_pipeline);
+        device.cmd_set_viewport(cmd, 0, &[viewport]);
+        device.cmd_set_scissor(cmd, 0, &[render_area]);
+
+        let push_constants = CompositePushConstants {
+            ui_image: (BindlessHandler::UI_TARGET_SLOT + image_index as usize) as u32,
+            to_linear: u32::from(self.srgb),
+        };
+        push(device, cmd, layout, &push_constants);
+
+        device.cmd_draw(cmd, 3, 1, 0, 0);
+        device.cmd_end_render_pass(cmd);
+    }
+}
+
+#[cfg(feature = "egui")]
+impl UiPainter {
+    /// replace the meshes that are drawn every frame
+    /// callbacks are not supported and skipped
+    pub fn set_primitives(
+        &mut self,
+        primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+        extent: vk::Extent2D,
+    ) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.draws.clear();
+
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primitives
+        {
+            let egui::epaint::Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+
+            let texture = match mesh.texture_id {
+                egui::TextureId::Managed(id) => match self.textures.get(&id) {
+                    Some(texture) => texture.handle.index as u32,
+                    None => continue,
+                },
+                // user textures are an index in to the bindless texture array
+                egui::TextureId::User(index) => index as u32,
+            };
+
+            // the clip rect is in points and can go past the screen
+            let min_x = (clip_rect.min.x * pixels_per_point).round().max(0.0) as u32;
+            let min_y = (clip_rect.min.y * pixels_per_point).round().max(0.0) as u32;
+            let max_x =
+                ((clip_rect.max.x * pixels_per_point).round().max(0.0) as u32).min(extent.width);
+            let max_y =
+                ((clip_rect.max.y * pixels_per_point).round().max(0.0) as u32).min(extent.height);
+
+            if min_x >= max_x || min_y >= max_y || mesh.indices.is_empty() {
+                continue;
+            }
+
+            self.draws.push(UiDraw {
+                clip: vk::Rect2D {
+                    offset: vk::Offset2D {
+                        x: min_x as i32,
+                        y: min_y as i32,
+                    },
+                    extent: vk::Extent2D {
+                        width: max_x - min_x,
+                        height: max_y - min_y,
+                    },
+                },
+                texture,
+                first_index: self.indices.len() as u32,
+                index_count: mesh.indices.len() as u32,
+                vertex_offset: self.vertices.len() as i32,
+            });
+
+            self.vertices
+                .extend(mesh.vertices.iter().map(|vertex| UiVertex {
+                    pos: [
+                        vertex.pos.x * pixels_per_point,
+                        vertex.pos.y * pixels_per_point,
+                    ],
+                    uv: [vertex.uv.x, vertex.uv.y],
+                    color: vertex.color.to_array(),
+                }));
+            self.indices.extend_from_slice(&mesh.indices);
+        }
+    }
+}
+
+/// how egui wants a texture to be sampled
+/// egui textures have no mip levels
+#[cfg(feature = "egui")]
+pub(crate) fn sampler_desc(options: egui::TextureOptions) -> super::sampler::SamplerDesc {
+    let filter = |filter| match filter {
+        egui::TextureFilter::Nearest => vk::Filter::NEAREST,
+        egui::TextureFilter::Linear => vk::Filter::LINEAR,
+    };
+
+    super::sampler::SamplerDesc {
+        mag_filter: filter(options.magnification),
+        min_filter: filter(options.minification),
+        mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+        address_mode: match options.wrap_mode {
+            egui::TextureWrapMode::ClampToEdge => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            egui::TextureWrapMode::Repeat => vk::SamplerAddressMode::REPEAT,
+            egui::TextureWrapMode::MirroredRepeat => vk::SamplerAddressMode::MIRRORED_REPEAT,
+        },
+        max_anisotropy: 0,
+    }
+}
+
+pub(super) unsafe fn push<T>(
+    device: &VulkanDevice,
+    cmd: vk::CommandBuffer,
+    layout: vk::PipelineLayout,
+    data: &T,
+) {
+    let data = std::slice::from_raw_parts(std::ptr::from_ref(data).cast::<u8>(), size_of::<T>());
+    device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+}
+
+/// grow the buffer if needed and write the data to it
+/// the old buffer is only used by the same frame, so it can be dropped right away
+pub(super) fn write_buffer<T: Copy>(
+    device: &Arc<VulkanDevice>,
+    buffer: &mut Option<Arc<Buffer>>,
+    data: &[T],
+    usage: vk::BufferUsageFlags,
+) -> VkResult<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let size = size_of_val(data) as u64;
+    let buffer = match buffer {
+        Some(buffer) if buffer.size() >= size => buffer,
+        _ => buffer.insert(Buffer::new(
+            device.clone(),
+            size.next_power_of_two(),
+            usage,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?),
+    };
+
+    buffer.write(0, data);
+    Ok(())
+}
+
+fn create_ui_renderpass(device: &VulkanDevice) -> VkResult<vk::RenderPass> {
+    // the composite pass reads the target as a storage image
+    let attachments = [vk::AttachmentDescription::default()
+        .format(vk::Format::R8G8B8A8_UNORM)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::GENERAL)];
+
+    // the composite of the last frame using this target has to be done reading it
+    let dependencies = [vk::SubpassDependency::default()
+        .src_subpass(vk::SUBPASS_EXTERNAL)
+        .dst_subpass(0)
+        .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+        .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)];
+
+    create_renderpass(device, &attachments, &dependencies)
+}
+
+/// the image is in ``layout`` before and after the pass
+pub(super) fn create_composite_renderpass(
+    device: &VulkanDevice,
+    format: vk::Format,
+    layout: vk::ImageLayout,
+) -> VkResult<vk::RenderPass> {
+    // the tone mapped image is kept and the ui is blended on top
+    let attachments = [vk::AttachmentDescription::default()
+        .format(format)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::LOAD)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+        .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .initial_layout(layout)
+        .final_layout(layout)];
+
+    // wait for the tone mapping or blit to write the swapchain image and the ui pass to write the ui target
+    let dependencies = [vk::SubpassDependency::default()
+        .src_subpass(vk::SUBPASS_EXTERNAL)
+        .dst_subpass(0)
+        .src_stage_mask(
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::TRANSFER,
+        )
+        .dst_stage_mask(
+            vk::PipelineStageFlags::FRAGMENT_SHADER
+                | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        )
+        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(
+            vk::AccessFlags::SHADER_READ
+                | vk::AccessFlags::COLOR_ATTACHMENT_READ
+                | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        )];
+
+    create_renderpass(device, &attachments, &dependencies)
+}
+
+fn create_renderpass(
+    device: &VulkanDevice,
+    attachments: &[vk::AttachmentDescription],
+    dependencies: &[vk::SubpassDependency],
+) -> VkResult<vk::RenderPass> {
+    let color_attachments_ref = [vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    }];
+
+    let subpasses = [vk::SubpassDescription::default()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_attachments_ref)];
+
+    let renderpass_info = vk::RenderPassCreateInfo::default()
+        .attachments(attachments)
+        .dependencies(dependencies)
+        .subpasses(&subpasses);
+
+    unsafe { device.create_render_pass(&renderpass_info, None) }
+}
+
+pub(super) fn create_pipeline(
+    device: &VulkanDevice,
+    stages: &[vk::PipelineShaderStageCreateInfo],
+    vertex_input_state: &vk::PipelineVertexInputStateCreateInfo,
+    blend: vk::PipelineColorBlendAttachmentState,
+    layout: vk::PipelineLayout,
+    renderpass: vk::RenderPass,
+) -> VkResult<vk::Pipeline> {
+    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::default()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+
+    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(vk::PolygonMode::FILL)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .line_width(1.0);
+
+    // the scissor is the clip rect of every mesh
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state =
+        vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+    let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let attachments = [blend];
+    let color_blend_state =
+        vk::PipelineColorBlendStateCreateInfo::default().attachments(&attachments);
+
+    let multisample_state = vk::PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+    let create_info = vk::GraphicsPipelineCreateInfo::default()
+        .stages(stages)
+        .vertex_input_state(vertex_input_state)
+        .input_assembly_state(&input_assembly_state)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization_state)
+        .color_blend_state(&color_blend_state)
+        .multisample_state(&multisample_state)
+        .dynamic_state(&dynamic_state)
+        .layout(layout)
+        .subpass(0)
+        .render_pass(renderpass);
+
+    let pipelines = unsafe {
+        device
+            .create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
+            .map_err(|(_, err)| err)?
+    };
+
+    Ok(pipelines[0])
+}
+
+impl Drop for UiPainter {
+    fn drop(&mut self) {
+        unsafe {
+            for pipeline in self.pipelines.into_iter().flatten() {
+                self.device.destroy_pipeline(pipeline, None);
+            }
+            for framebuffer in self
+                .ui_framebuffers
+                .iter()
+                .chain(&self.composite_framebuffers)
+            {
+                self.device.destroy_framebuffer(*framebuffer, None);
+            }
+            self.device.destroy_render_pass(self.ui_renderpass, None);
+            self.device
+                .destroy_render_pass(self.composite_renderpass, None);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// scales the targets on to the swapchain image if the renderer has a fixed internal resolution,
+// see ``RendererConfig::internal_resolution``
+//
+// the tone mapping and the outlines write ``SwapchainImage::upscale_target`` instead of the swapchain image,
+// which is then blitted in to the biggest rect with the same aspect ratio that fits the window,
+// the bars around it are cleared to black, the ui is drawn afterwards at the size of the window
+
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::vulkan::{Swapchain, VulkanDevice};
+
+/// how the internal resolution is scaled to the window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpscaleFilter {
+    #[default]
+    Linear,
+    /// keeps the pixels sharp, for pixel art
+    Nearest,
+}
+
+pub(crate) struct Upscaler {
+    device: Arc<VulkanDevice>,
+    pub filter: UpscaleFilter,
+    /// if the swapchain format can be blitted with linear filtering, nearest is used otherwise
+    linear_supported: bool,
+}
+
+impl Upscaler {
+    pub fn new(device: Arc<VulkanDevice>, swapchain: &Swapchain, filter: UpscaleFilter) -> Self {
+        let props = unsafe {
+            device
+                .instance
+                .get_physical_device_format_properties(device.pdevice, swapchain.image_format())
+        };
+        let linear_supported = props
+            .optimal_tiling_features
+            .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR);
+
+        Self {
+            device,
+            filter,
+            linear_supported,
+        }
+    }
+
+    /// blit the upscale target on to the swapchain image and leave it in ``PRESENT_SRC_KHR``
+    /// does nothing without an internal resolution, the swapchain image was written directly then
+    pub unsafe fn record(&self, cmd: vk::CommandBuffer, swapchain: &Swapchain, image_index: u32) {
+        let image = &swapchain.images[image_index as usize];
+        let (Some(target), Some(rect)) = (&image.upscale_target, swapchain.letterbox()) else {
+            return;
+        };
+
+        let device = &self.device;
+        let extent = swapchain.get_image_extent();
+
+        let subresource_range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+
+        // written by the tone mapping or its blit and the outlines
+        let target_barrier = vk::ImageMemoryBarrier2::default()
+            .image(target.image)
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_stage_mask(
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags2::BLIT,
+            )
+            .src_access_mask(
+                vk::AccessFlags2::COLOR_ATTACHMENT_WRITE | vk::AccessFlags2::TRANSFER_WRITE,
+            )
+            .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+            .subresource_range(subresource_range);
+
+        // the submit waits for the image to be acquired at the color attachment output stage
+        let to_transfer = vk::ImageMemoryBarrier2::default()
+            .image(image.main_image)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .dst_stage_mask(vk::PipelineStageFlags2::CLEAR)
+            .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .subresource_range(subresource_range);
+
+        let barriers = [target_barrier, to_transfer];
+        device.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+        );
+
+        // the bars, the blit overwrites the rest
+        device.cmd_clear_color_image(
+            cmd,
+            image.main_image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 1.0],
+            },
+            &[subresource_range],
+        );
+
+        device.image_barrier(
+            cmd,
+            vk::ImageMemoryBarrier2::default()
+                .image(image.main_image)
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .src_stage_mask(vk::PipelineStageFlags2::CLEAR)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .subresource_range(subresource_range),
+        );
+
+        let layers = vk::ImageSubresourceLayers::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .layer_count(1);
+
+        let region = vk::ImageBlit::default()
+            .src_subresource(layers)
+            .src_offsets([
+                vk::Offset3D::default(),
+                vk::Offset3D {
+                    x: extent.width as i32,
+                    y: extent.height as i32,
+                    z: 1,
+                },
+            ])
+            .dst_subresource(layers)
+            .dst_offsets([
+                vk::Offset3D {
+                    x: rect.offset.x,
+                    y: rect.offset.y,
+                    z: 0,
+                },
+                vk::Offset3D {
+                    x: rect.offset.x + rect.extent.width as i32,
+                    y: rect.offset.y + rect.extent.height as i32,
+                    z: 1,
+                },
+            ]);
+
+        let filter = match self.filter {
+            UpscaleFilter::Linear if self.linear_supported => vk::Filter::LINEAR,
+            _ => vk::Filter::NEAREST,
+        };
+
+        device.cmd_blit_image(
+            cmd,
+            target.image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            image.main_image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+            filter,
+        );
+
+        // the ui pass waits for transfer writes before it loads the image
+        device.image_barrier(
+            cmd,
+            vk::ImageMemoryBarrier2::default()
+                .image(image.main_image)
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                .src_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .subresource_range(subresource_range),
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// the cameras of a multiview render pass, it draws every batch once for every view, in to its own layer of the targets
+// the shaders read their camera with ``GetView(view_index)`` from ``shaders/views.slang`` in the application,
+// the index comes from ``SV_ViewID``
+//
+// the views are in a storage buffer at ``BindlessHandler::VIEWS_SLOT``,
+// replaced on the gpu by the pass that renders them, so a frame can render more than one set of views.
+// right now only the environment probes do
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+use math::{Mat4, Vec3};
+
+use crate::vulkan::{Buffer, VulkanDevice, MAX_VIEWS};
+
+use super::{
+    bindless::{BindlessHandler, BindlessResourceHandle, BindlessResourceType},
+    FLYING_FRAMES,
+};
+
+/// the view mask of a render pass that draws the first ``count`` views
+/// # Panics
+/// if there are more than ``MAX_VIEWS`` views
+#[must_use]
+pub fn view_mask(count: usize) -> u32 {
+    assert!(
+        count <= MAX_VIEWS,
+        "a render pass has at most {MAX_VIEWS} views"
+    );
+    (1 << count) - 1
+}
+
+/// needs to match ``View`` in ``shaders/views.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct GpuView {
+    view_proj: Mat4,
+    /// w unused
+    position: [f32; 4],
+}
+
+/// needs to match ``Views`` in ``shaders/views.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuViews {
+    count: u32,
+    _padding: [u32; 3],
+    views: [GpuView; MAX_VIEWS],
+}
+
+pub(crate) struct ViewBuffer {
+    buffer: Arc<Buffer>,
+}
+
+impl ViewBuffer {
+    /// # Errors
+    /// if there is no space to allocate the buffer
+    pub fn new(device: &Arc<VulkanDevice>, bindless: &BindlessHandler) -> VkResult<Self> {
+        let buffer = Buffer::new(
+            device.clone(),
+            size_of::<GpuViews>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        // every frame uses the same buffer, it's only written by commands
+        let handle = BindlessResourceHandle::reserved(
+            BindlessHandler::VIEWS_SLOT,
+            BindlessResourceType::StorageBuffer,
+        );
+        bindless.set_per_frame_buffer(&**device, [buffer.handle(); FLYING_FRAMES], handle);
+
+        Ok(Self { buffer })
+    }
+
+    /// replace the view projection matrix and the position of every view for the draws after this
+    /// # Panics
+    /// if there are more than ``MAX_VIEWS`` views
+    pub unsafe fn update(
+        &self,
+        device: &VulkanDevice,
+        cmd: vk::CommandBuffer,
+        views: &[(Mat4, Vec3)],
+    ) {
+        assert!(
+            views.len() <= MAX_VIEWS,
+            "there are at most {MAX_VIEWS} views"
+        );
+
+        let mut data = GpuViews {
+            count: views.len() as u32,
+            _padding: [0; 3],
+            views: [GpuView::default(); MAX_VIEWS],
+        };
+        for (gpu, (view_proj, position)) in data.views.iter_mut().zip(views) {
+            *gpu = GpuView {
+                view_proj: *view_proj,
+                position: position.extend(1.0).to_array(),
+            };
+        }
+
+        let bytes = std::slice::from_raw_parts((&raw const data).cast::<u8>(), size_of_val(&data));
+        update_buffer(device, cmd, &self.buffer, bytes);
+    }
+}
+
+/// replace the start of a buffer with ``cmd_update_buffer``,
+/// every draw and dispatch before waits and every one after sees the new data
+/// # Safety
+/// the data must be at most 65536 bytes and a multiple of 4
+pub(crate) unsafe fn update_buffer(
+    device: &VulkanDevice,
+    cmd: vk::CommandBuffer,
+    buffer: &Buffer,
+    data: &[u8],
+) {
+    device.memory_barrier(
+        cmd,
+        vk::MemoryBarrier2::default()
+            .src_stage_mask(
+                vk::PipelineStageFlags2::ALL_GRAPHICS | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            )
+            .dst_stage_mask(vk::PipelineStageFlags2::CLEAR),
+    );
+
+    device.cmd_update_buffer(cmd, buffer.handle(), 0, data);
+
+    device.memory_barrier(
+        cmd,
+        vk::MemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::CLEAR)
+            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .dst_stage_mask(
+                vk::PipelineStageFlags2::ALL_GRAPHICS | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            )
+            .dst_access_mask(vk::AccessFlags2::UNIFORM_READ | vk::AccessFlags2::SHADER_READ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_have_a_bit_for_every_view() {
+        assert_eq!(view_mask(1), 0b1);
+        assert_eq!(view_mask(2), 0b11);
+        assert_eq!(view_mask(MAX_VIEWS), 0b11_1111);
+    }
+}
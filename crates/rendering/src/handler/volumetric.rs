@@ -0,0 +1,305 @@
+// raymarched fluids like water or smoke, runs right after the main pass and before the post processing
+// the application voxelizes its fluids in to a low resolution grid with ``RenderHandler::set_fluid_voxels``,
+// every pixel marches from the camera through the grid until it reaches the surface in the depth target
+//
+// light is absorbed on the way with beer lambert, so deep water gets dark and tinted,
+// and the sun and ambient light are scattered towards the camera by the color of the fluid
+// the sun is shadowed by the fluid itself with a short march towards it, not by the rest of the scene
+//
+// the grid and the parameters are in the reserved storage buffers at ``BindlessHandler::FLUID_SLOT``
+// and ``BindlessHandler::FLUID_VOXELS_SLOT``, see ``shaders/volumetric.slang`` in the application
+//
+// the faces of fluid voxels shouldn't be in the depth target, or the march stops before it got in to the fluid,
+// transparent colors are fine as they don't write depth
+
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::{Buffer, Swapchain, VulkanDevice};
+
+use super::{
+    bindless::{BindlessHandler, BindlessResourceHandle, BindlessResourceType},
+    FLYING_FRAMES,
+};
+
+/// the cells of the grid along every axis
+pub const FLUID_RESOLUTION: u32 = 64;
+/// the amount of cells in the grid
+pub const FLUID_CELLS: usize = (FLUID_RESOLUTION * FLUID_RESOLUTION * FLUID_RESOLUTION) as usize;
+
+/// needs to match ``WORK_GROUP_SIZE`` in ``shaders/volumetric.slang``
+const WORK_GROUP_SIZE: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumetricSettings {
+    pub enabled: bool,
+    /// how much of every color is absorbed per world unit in a cell with full density,
+    /// the default absorbs red the most, like water
+    pub absorption: [f32; 3],
+    /// how much light is scattered per world unit in a cell with full density, tinted by the color of the cell
+    pub scattering: f32,
+    /// from -1 to 1, positive values scatter the sun forward, so the fluid glows when looking towards it
+    pub anisotropy: f32,
+    /// the most steps a pixel marches, the step size is a grid cell
+    pub max_steps: u32,
+}
+
+impl Default for VolumetricSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            absorption: [4.0, 1.0, 0.6],
+            scattering: 0.5,
+            anisotropy: 0.4,
+            max_steps: 128,
+        }
+    }
+}
+
+/// the box in world space the grid covers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FluidVolume {
+    /// the corner with the lowest coordinates
+    pub origin: [f32; 3],
+    /// the size of one cell, the grid is ``FLUID_RESOLUTION * cell_size`` wide
+    pub cell_size: f32,
+}
+
+impl Default for FluidVolume {
+    /// the box from -1 to 1, the space an octree covers
+    fn default() -> Self {
+        Self {
+            origin: [-1.0; 3],
+            cell_size: 2.0 / FLUID_RESOLUTION as f32,
+        }
+    }
+}
+
+/// needs to match ``FluidParams`` in ``shaders/volumetric.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct GpuFluidParams {
+    origin: [f32; 3],
+    cell_size: f32,
+    absorption: [f32; 3],
+    scattering: f32,
+    resolution: u32,
+    max_steps: u32,
+    anisotropy: f32,
+    _padding: f32,
+}
+
+/// the push constants used by the volumetric shader
+/// needs to match ``shaders/volumetric.slang``
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct VolumetricPushConstants {
+    hdr_image: u32,
+    depth_image: u32,
+    extent: [u32; 2],
+}
+
+pub(crate) struct VolumetricPass {
+    device: Arc<VulkanDevice>,
+    pub settings: VolumetricSettings,
+    volume: FluidVolume,
+    pipeline: Option<vk::Pipeline>,
+    params_buffers: [Arc<Buffer>; FLYING_FRAMES],
+    /// the voxels as rgba8 color with the density in alpha, one buffer per frame in flight
+    voxel_buffers: [Arc<Buffer>; FLYING_FRAMES],
+    voxels: Vec<u32>,
+    /// the frames whose voxel buffer is older than ``voxels``
+    dirty: [bool; FLYING_FRAMES],
+}
+
+impl VolumetricPass {
+    /// # Errors
+    /// if there is no space to allocate the buffers
+    pub fn new(device: Arc<VulkanDevice>, bindless: &BindlessHandler) -> VkResult<Self> {
+        let buffer = |size: usize| {
+            Buffer::new(
+                device.clone(),
+                size as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            )
+        };
+
+        let params_buffers = [
+            buffer(size_of::<GpuFluidParams>())?,
+            buffer(size_of::<GpuFluidParams>())?,
+        ];
+        let voxel_buffers = [
+            buffer(FLUID_CELLS * size_of::<u32>())?,
+            buffer(FLUID_CELLS * size_of::<u32>())?,
+        ];
+
+        for (index, buffers) in [
+            (BindlessHandler::FLUID_SLOT, &params_buffers),
+            (BindlessHandler::FLUID_VOXELS_SLOT, &voxel_buffers),
+        ] {
+            let handle =
+                BindlessResourceHandle::reserved(index, BindlessResourceType::StorageBuffer);
+            bindless.set_per_frame_buffer(&*device, buffers.each_ref().map(|v| v.handle()), handle);
+        }
+
+        Ok(Self {
+            device,
+            settings: VolumetricSettings::default(),
+            volume: FluidVolume::default(),
+            pipeline: None,
+            params_buffers,
+            voxel_buffers,
+            voxels: vec![],
+            dirty: [false; FLYING_FRAMES],
+        })
+    }
+
+    /// set the compute shader that marches through the fluids
+    /// the shader module is not destroyed by the renderer
+    /// # Errors
+    /// if there was an issue creating the pipeline
+    pub fn set_shader(
+        &mut self,
+        stage: vk::PipelineShaderStageCreateInfo,
+        layout: vk::PipelineLayout,
+    ) -> VkResult<()> {
+        let create_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+
+        let pipeline = unsafe {
+            self.device
+                .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, err)| err)?[0]
+        };
+
+        if let Some(old) = self.pipeline.replace(pipeline) {
+            unsafe { self.device.destroy_pipeline(old, None) };
+        }
+
+        Ok(())
+    }
+
+    /// replace the voxels of the grid, ``voxels`` has ``FLUID_CELLS`` rgba8 colors with x changing fastest, then y
+    /// the alpha is the density, 0 is no fluid, an empty slice turns the pass off
+    /// # Panics
+    /// if there are voxels but not ``FLUID_CELLS``
+    pub fn set_voxels(&mut self, volume: FluidVolume, voxels: &[[u8; 4]]) {
+        assert!(
+            voxels.is_empty() || voxels.len() == FLUID_CELLS,
+            "the grid needs FLUID_CELLS voxels"
+        );
+
+        self.volume = volume;
+        self.voxels = voxels
+            .iter()
+            .map(|&voxel| u32::from_le_bytes(voxel))
+            .collect();
+        self.dirty = [true; FLYING_FRAMES];
+    }
+
+    /// write the parameters and the voxels that changed to the buffers of this frame
+    /// the fence of the frame has to be signaled
+    pub fn upload(&mut self, frame_index: usize) {
+        if std::mem::take(&mut self.dirty[frame_index]) && !self.voxels.is_empty() {
+            self.voxel_buffers[frame_index].write(0, &self.voxels);
+        }
+
+        let params = GpuFluidParams {
+            origin: self.volume.origin,
+            cell_size: self.volume.cell_size,
+            absorption: self.settings.absorption,
+            scattering: self.settings.scattering,
+            resolution: FLUID_RESOLUTION,
+            max_steps: self.settings.max_steps,
+            anisotropy: self.settings.anisotropy.clamp(-0.99, 0.99),
+            _padding: 0.0,
+        };
+        self.params_buffers[frame_index].write(0, &[params]);
+    }
+
+    /// march through the fluids and composite them on to the hdr target
+    /// needs to be called after the main render pass ended, does nothing without voxels or a shader
+    pub unsafe fn record(
+        &self,
+        cmd: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        image_index: u32,
+        layout: vk::PipelineLayout,
+    ) {
+        let Some(pipeline) = self.pipeline.filter(|_| self.settings.enabled) else {
+            return;
+        };
+        if self.voxels.is_empty() {
+            return;
+        }
+
+        let device = &self.device;
+        let extent = swapchain.get_image_extent();
+
+        // the main pass wrote the hdr and depth targets, the transparency resolve the hdr target
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(
+                    vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                        | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                )
+                .src_access_mask(
+                    vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                        | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                )
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ),
+        );
+
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline);
+
+        let push_constants = VolumetricPushConstants {
+            hdr_image: (BindlessHandler::HDR_TARGET_SLOT + image_index as usize) as u32,
+            depth_image: (BindlessHandler::DEPTH_TARGET_SLOT + image_index as usize) as u32,
+            extent: [extent.width, extent.height],
+        };
+
+        let data = std::slice::from_raw_parts(
+            std::ptr::from_ref(&push_constants).cast::<u8>(),
+            size_of::<VolumetricPushConstants>(),
+        );
+        device.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, 0, data);
+
+        device.cmd_dispatch(
+            cmd,
+            extent.width.div_ceil(WORK_GROUP_SIZE),
+            extent.height.div_ceil(WORK_GROUP_SIZE),
+            1,
+        );
+
+        // the later passes read the hdr target in shaders or with a blit
+        device.memory_barrier(
+            cmd,
+            vk::MemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(
+                    vk::PipelineStageFlags2::COMPUTE_SHADER
+                        | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                        | vk::PipelineStageFlags2::BLIT,
+                )
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::TRANSFER_READ,
+                ),
+        );
+    }
+}
+
+impl Drop for VolumetricPass {
+    fn drop(&mut self) {
+        if let Some(pipeline) = self.pipeline {
+            unsafe { self.device.destroy_pipeline(pipeline, None) };
+        }
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(clippy::cast_possible_truncation, clippy::needless_pass_by_value)]
-#![feature(get_mut_unchecked)]
 
+pub mod assets;
 pub mod handler;
 pub mod vulkan;
 pub mod types;
@@ -2,9 +2,12 @@
 
 use ash::{khr::swapchain, vk};
 
-use crate::vulkan::VulkanDevice;
+use crate::{
+    handler::material::main_pass_target_count,
+    vulkan::{GpuDevice, VulkanDevice},
+};
 
-use super::MemoryAccessFlags;
+use super::{MemoryAccessFlags, ParamLayout};
 
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub enum CullingMode {
@@ -24,6 +27,25 @@ impl From<CullingMode> for vk::CullModeFlags {
     }
 }
 
+/// how a material is combined with what was drawn before it
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Transparency {
+    /// overwrites every target
+    #[default]
+    Opaque,
+    /// alpha blended over the hdr target, the other targets are left as they are
+    /// the batches are drawn after the opaque ones in the order they were added,
+    /// so they need to be added back to front
+    Sorted,
+    /// weighted blended order independent transparency, the order of the draws doesn't matter
+    /// the fragment shader writes ``color.rgb * color.a * weight, color.a * weight`` to the accumulation target (location 4)
+    /// and ``color.a`` to the revealage target (location 5), the other targets are left as they are
+    /// the weight should get smaller with the distance to the camera, so closer surfaces win
+    /// the targets are composited on to the hdr target after the main pass, see ``RenderHandler::set_oit_shader``
+    /// falls back to ``Sorted`` if ``DeviceFeatures::weighted_blended_oit`` isn't supported
+    WeightedBlended,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct UDim2 {
     /// the size relative to the swapchain in percent (0.0 - 1.0)
@@ -32,12 +54,317 @@ pub struct UDim2 {
     pub offset: [f32; 2],
 }
 
+impl UDim2 {
+    /// the whole swapchain
+    pub const FULL: Self = Self {
+        scale: [1.0, 1.0],
+        offset: [0.0, 0.0],
+    };
+
+    /// the size in pixels for a swapchain of this size
+    #[must_use]
+    pub fn resolve(&self, swapchain_size: vk::Extent2D) -> [f32; 2] {
+        [
+            self.scale[0] * swapchain_size.width as f32 + self.offset[0],
+            self.scale[1] * swapchain_size.height as f32 + self.offset[1],
+        ]
+    }
+}
+
+/// where on the swapchain a material is drawn
+/// the pixel rect is computed from the swapchain size every time the material is bound,
+/// so it follows the window when it's resized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportMode {
+    /// changes with the swapchain size
+    Relative {
+        /// the top left corner
+        position: UDim2,
+        size: UDim2,
+    },
+    /// stays the same size in pixels
+    Constant {
+        /// the top left corner in pixels
+        position: [u32; 2],
+        size: [u32; 2],
+    },
+}
+
+impl Default for ViewportMode {
+    fn default() -> Self {
+        Self::Relative {
+            position: UDim2::default(),
+            size: UDim2::FULL,
+        }
+    }
+}
+
+impl ViewportMode {
+    /// the rect in pixels, clipped to the swapchain
+    pub fn rect(&self, swapchain_size: vk::Extent2D) -> vk::Rect2D {
+        let (position, size) = match self {
+            Self::Relative { position, size } => {
+                let position = position.resolve(swapchain_size);
+                let size = size.resolve(swapchain_size);
+                (
+                    position.map(|v| v.round().max(0.0) as u32),
+                    size.map(|v| v.round().max(0.0) as u32),
+                )
+            }
+            Self::Constant { position, size } => (*position, *size),
+        };
+
+        let max = [swapchain_size.width, swapchain_size.height];
+        let x = position[0].min(max[0]);
+        let y = position[1].min(max[1]);
+
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: x as i32,
+                y: y as i32,
+            },
+            extent: vk::Extent2D {
+                width: size[0].min(max[0] - x),
+                height: size[1].min(max[1] - y),
+            },
+        }
+    }
+
+    pub fn viewport(&self, swapchain_size: vk::Extent2D) -> vk::Viewport {
+        let rect = self.rect(swapchain_size);
+
+        vk::Viewport::default()
+            .x(rect.offset.x as f32)
+            .y(rect.offset.y as f32)
+            .width(rect.extent.width as f32)
+            .height(rect.extent.height as f32)
+            .min_depth(0.0)
+            .max_depth(1.0)
+    }
+
+    /// set the viewport and scissor, the pipeline has to use them as dynamic state
+    pub(crate) unsafe fn apply(
+        &self,
+        device: &dyn GpuDevice,
+        cmd: vk::CommandBuffer,
+        swapchain_size: vk::Extent2D,
+    ) {
+        device.set_viewport(cmd, self.viewport(swapchain_size));
+        device.set_scissor(cmd, self.rect(swapchain_size));
+    }
+}
+
+/// moves the depth of the fragments towards the camera, so decals don't fight with the surface below them
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DepthBias {
+    /// a constant offset in units of the smallest depth difference
+    pub constant: f32,
+    /// scaled by how steep the triangle is to the camera
+    pub slope: f32,
+}
+
+/// state that draws can change without needing another material
+/// in ``MaterialCreateInfo::dynamic_state`` every value that is set becomes dynamic and is used by the draws
+/// that don't set their own, in ``DrawData::state`` the values replace the ones of the material for a single draw,
+/// the values of a draw are ignored if the material didn't make them dynamic
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DrawState {
+    pub depth_bias: Option<DepthBias>,
+    /// the color used by the ``CONSTANT_COLOR`` blend factors
+    pub blend_constants: Option<[f32; 4]>,
+    /// in pixels, needs ``DeviceFeatures::wide_lines`` for other widths than 1
+    pub line_width: Option<f32>,
+    /// the draws can only switch to topologies of the same kind, like from a triangle list to a triangle strip,
+    /// a triangle list if it's none
+    pub topology: Option<vk::PrimitiveTopology>,
+}
+
+impl DrawState {
+    /// the values of this draw where the material made them dynamic, the ones of the material where it has none
+    #[must_use]
+    pub fn merged(&self, material: &Self) -> Self {
+        Self {
+            depth_bias: material.depth_bias.map(|v| self.depth_bias.unwrap_or(v)),
+            blend_constants: material
+                .blend_constants
+                .map(|v| self.blend_constants.unwrap_or(v)),
+            line_width: material.line_width.map(|v| self.line_width.unwrap_or(v)),
+            topology: material.topology.map(|v| self.topology.unwrap_or(v)),
+        }
+    }
+
+    /// set every value that is different to ``current``, or all of them if nothing is set yet
+    pub(crate) unsafe fn apply(
+        &self,
+        device: &dyn GpuDevice,
+        cmd: vk::CommandBuffer,
+        current: Option<&Self>,
+    ) {
+        if let Some(bias) = self.depth_bias {
+            if current.is_none_or(|c| c.depth_bias != self.depth_bias) {
+                device.set_depth_bias(cmd, bias.constant, bias.slope);
+            }
+        }
+        if let Some(constants) = self.blend_constants {
+            if current.is_none_or(|c| c.blend_constants != self.blend_constants) {
+                device.set_blend_constants(cmd, constants);
+            }
+        }
+        if let Some(width) = self.line_width {
+            if current.is_none_or(|c| c.line_width != self.line_width) {
+                device.set_line_width(cmd, width);
+            }
+        }
+        if let Some(topology) = self.topology {
+            if current.is_none_or(|c| c.topology != self.topology) {
+                device.set_primitive_topology(cmd, topology);
+            }
+        }
+    }
+
+    fn dynamic_states(&self) -> impl Iterator<Item = vk::DynamicState> {
+        [
+            (self.depth_bias.is_some(), vk::DynamicState::DEPTH_BIAS),
+            (
+                self.blend_constants.is_some(),
+                vk::DynamicState::BLEND_CONSTANTS,
+            ),
+            (self.line_width.is_some(), vk::DynamicState::LINE_WIDTH),
+            (
+                self.topology.is_some(),
+                vk::DynamicState::PRIMITIVE_TOPOLOGY,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(dynamic, state)| dynamic.then_some(state))
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct VertexInput {
     pub bindings: Vec<vk::VertexInputBindingDescription>,
     pub attributes: Vec<vk::VertexInputAttributeDescription>,
 }
 
+impl VertexInput {
+    /// add an instance buffer with one column major 4x4 matrix per instance
+    /// the matrix takes up the 4 locations starting at ``location``, one for every column
+    pub fn add_instance_matrix(&mut self, binding: u32, location: u32) {
+        const COLUMN_SIZE: u32 = std::mem::size_of::<[f32; 4]>() as u32;
+
+        self.bindings.push(
+            vk::VertexInputBindingDescription::default()
+                .binding(binding)
+                .stride(COLUMN_SIZE * 4)
+                .input_rate(vk::VertexInputRate::INSTANCE),
+        );
+
+        for column in 0..4 {
+            self.attributes.push(
+                vk::VertexInputAttributeDescription::default()
+                    .binding(binding)
+                    .location(location + column)
+                    .format(vk::Format::R32G32B32A32_SFLOAT)
+                    .offset(COLUMN_SIZE * column),
+            );
+        }
+    }
+}
+
+/// a single specialization constant, booleans are 4 bytes like ``VkBool32``
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpecConstant {
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    Bool(bool),
+}
+
+impl SpecConstant {
+    fn to_bytes(self) -> [u8; 4] {
+        match self {
+            Self::U32(v) => v.to_ne_bytes(),
+            Self::I32(v) => v.to_ne_bytes(),
+            Self::F32(v) => v.to_ne_bytes(),
+            Self::Bool(v) => u32::from(v).to_ne_bytes(),
+        }
+    }
+}
+
+impl From<u32> for SpecConstant {
+    fn from(value: u32) -> Self {
+        Self::U32(value)
+    }
+}
+
+impl From<i32> for SpecConstant {
+    fn from(value: i32) -> Self {
+        Self::I32(value)
+    }
+}
+
+impl From<f32> for SpecConstant {
+    fn from(value: f32) -> Self {
+        Self::F32(value)
+    }
+}
+
+impl From<bool> for SpecConstant {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+/// constants that are set when the pipeline is created instead of when the shader is compiled,
+/// so one spir-v module can be used with different values
+/// they are declared as ``[vk::constant_id(0)] const uint MAX_LIGHTS = 4;`` in slang,
+/// the value in the shader is used if the constant isn't set here
+#[derive(Debug, Default, Clone)]
+pub struct SpecializationConstants {
+    entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl SpecializationConstants {
+    #[must_use]
+    pub fn with(mut self, id: u32, value: impl Into<SpecConstant>) -> Self {
+        self.set(id, value);
+        self
+    }
+
+    /// overwrites the value if the constant was set before
+    pub fn set(&mut self, id: u32, value: impl Into<SpecConstant>) {
+        let bytes = value.into().to_bytes();
+
+        if let Some(entry) = self.entries.iter().find(|entry| entry.constant_id == id) {
+            let offset = entry.offset as usize;
+            self.data[offset..offset + bytes.len()].copy_from_slice(&bytes);
+            return;
+        }
+
+        self.entries.push(
+            vk::SpecializationMapEntry::default()
+                .constant_id(id)
+                .offset(self.data.len() as u32)
+                .size(bytes.len()),
+        );
+        self.data.extend_from_slice(&bytes);
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// works for pipeline shader stages and shader objects
+    /// constants that a shader doesn't declare are ignored
+    pub fn info(&self) -> vk::SpecializationInfo<'_> {
+        vk::SpecializationInfo::default()
+            .map_entries(&self.entries)
+            .data(&self.data)
+    }
+}
+
 pub struct ColorAttachmentInfo {
     access: MemoryAccessFlags,
 }
@@ -45,30 +372,166 @@ pub struct ColorAttachmentInfo {
 #[derive(Debug, Default, Clone)]
 pub struct MaterialCreateInfo {
     pub cull_mode: CullingMode,
-    pub viewport: UDim2,
+    pub viewport: ViewportMode,
     pub vertex_input: VertexInput,
+    /// the vertex shader reads the vertices from storage buffers instead of the vertex input,
+    /// ``vertex_input`` is ignored and the draws need ``DrawData::pulled_buffers``
+    pub vertex_pulling: bool,
+    /// temporal anti aliasing leaves the pixels of this material as they are,
+    /// for things that should stay sharp like ui or hard voxel edges
+    /// the velocity the shader writes is replaced with 0, see ``VELOCITY_FORMAT``
+    pub skip_temporal_aa: bool,
+    pub transparency: Transparency,
+    /// the fragment shader writes the object id of the draw to location 6, see ``DrawData::object_id``
+    /// only opaque materials write it, the others leave the object id target as it is
+    pub object_id: bool,
     pub shaders: Vec<vk::PipelineShaderStageCreateInfo<'static>>,
+    /// the values every ``MaterialInstance`` of this material has
+    pub params: ParamLayout,
+    /// set on every stage that doesn't have its own specialization info
+    pub specialization: SpecializationConstants,
+    /// the state the draws can change, see ``DrawData::state``
+    pub dynamic_state: DrawState,
+    /// the vertex shader reads the camera with ``GetView`` from ``shaders/views.slang`` instead of the uniform buffer,
+    /// so the material can be drawn in to the multiview pass of the environment probes, see ``RendererConfig::multiview``
+    pub multiview: bool,
 }
 
 pub struct Material {
     pub pipeline: vk::Pipeline,
+    /// the pipeline for ``MaterialHandler::multiview_renderpass``, if the material is ``MaterialCreateInfo::multiview``
+    pub multiview_pipeline: Option<vk::Pipeline>,
     pub info: MaterialCreateInfo,
 }
 
 impl MaterialCreateInfo {
+    /// if the material has a mesh shader instead of a vertex shader
+    #[must_use]
+    pub fn uses_mesh_shader(&self) -> bool {
+        self.shaders
+            .iter()
+            .any(|stage| stage.stage == vk::ShaderStageFlags::MESH_EXT)
+    }
+
+    /// the blending of the targets of the main pass, in the order they are attached
+    /// without ``independent_blend`` every target has to be blended the same way,
+    /// the object id target isn't attached then
+    fn blend_attachments(
+        &self,
+        independent_blend: bool,
+    ) -> [vk::PipelineColorBlendAttachmentState; 7] {
+        let opaque = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(false);
+
+        let skip = opaque.color_write_mask(vk::ColorComponentFlags::empty());
+
+        let alpha = opaque
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD);
+
+        // the transparency targets have undefined contents then,
+        // they are never resolved as weighted blended materials fall back to sorted ones
+        if !independent_blend {
+            return match self.transparency {
+                Transparency::Opaque => [opaque; 7],
+                Transparency::Sorted | Transparency::WeightedBlended => [alpha; 7],
+            };
+        }
+
+        match self.transparency {
+            Transparency::Opaque => {
+                // blending with zero factors clears the velocity, no matter what the shader wrote
+                let velocity = if self.skip_temporal_aa {
+                    opaque
+                        .blend_enable(true)
+                        .src_color_blend_factor(vk::BlendFactor::ZERO)
+                        .dst_color_blend_factor(vk::BlendFactor::ZERO)
+                        .color_blend_op(vk::BlendOp::ADD)
+                        .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+                        .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+                        .alpha_blend_op(vk::BlendOp::ADD)
+                } else {
+                    opaque
+                };
+
+                // integer targets can't be blended
+                let object_id = if self.object_id {
+                    opaque.color_write_mask(vk::ColorComponentFlags::R)
+                } else {
+                    skip
+                };
+
+                [opaque, opaque, opaque, velocity, skip, skip, object_id]
+            }
+            Transparency::Sorted => [alpha, skip, skip, skip, skip, skip, skip],
+            Transparency::WeightedBlended => {
+                let accum = opaque
+                    .blend_enable(true)
+                    .src_color_blend_factor(vk::BlendFactor::ONE)
+                    .dst_color_blend_factor(vk::BlendFactor::ONE)
+                    .color_blend_op(vk::BlendOp::ADD)
+                    .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                    .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+                    .alpha_blend_op(vk::BlendOp::ADD);
+
+                // every surface multiplies the revealage with 1 - alpha
+                let revealage = opaque
+                    .color_write_mask(vk::ColorComponentFlags::R)
+                    .blend_enable(true)
+                    .src_color_blend_factor(vk::BlendFactor::ZERO)
+                    .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_COLOR)
+                    .color_blend_op(vk::BlendOp::ADD);
+
+                [skip, skip, skip, skip, accum, revealage, skip]
+            }
+        }
+    }
+
     pub(crate) fn build(
         &self,
         device: &VulkanDevice,
         rpass: vk::RenderPass,
+        multiview_rpass: Option<vk::RenderPass>,
         layout: vk::PipelineLayout,
-        swapchain_size: [u32; 2],
+        samples: vk::SampleCountFlags,
     ) -> Material {
-        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
-            .vertex_binding_descriptions(&self.vertex_input.bindings)
-            .vertex_attribute_descriptions(&self.vertex_input.attributes);
+        if !self.vertex_pulling {
+            if let Err(err) = self.vertex_input.validate() {
+                panic!("invalid vertex input: {err}");
+            }
+        }
+
+        let vertex_input_state = if self.vertex_pulling {
+            vk::PipelineVertexInputStateCreateInfo::default()
+        } else {
+            vk::PipelineVertexInputStateCreateInfo::default()
+                .vertex_binding_descriptions(&self.vertex_input.bindings)
+                .vertex_attribute_descriptions(&self.vertex_input.attributes)
+        };
+
+        let mut info = self.clone();
+        if info.transparency == Transparency::WeightedBlended
+            && !device.features.weighted_blended_oit
+        {
+            info.transparency = Transparency::Sorted;
+        }
+        // the topology of the material is still used, only the draws can't change it
+        let topology = info
+            .dynamic_state
+            .topology
+            .unwrap_or(vk::PrimitiveTopology::TRIANGLE_LIST);
+        if !device.features.dynamic_topology {
+            info.dynamic_state.topology = None;
+        }
 
         let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::default()
-            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .topology(topology)
             .primitive_restart_enable(false);
 
         let rasterization_state = vk::PipelineRasterizationStateCreateInfo::default()
@@ -78,70 +541,215 @@ impl MaterialCreateInfo {
             .line_width(1.0)
             .cull_mode(self.cull_mode.into())
             .front_face(vk::FrontFace::CLOCKWISE)
-            .depth_bias_enable(false);
-
-        let screen_size = [
-            self.viewport.scale[0] * swapchain_size[0] as f32 + self.viewport.offset[0],
-            self.viewport.scale[1] * swapchain_size[1] as f32 + self.viewport.offset[1],
-        ];
-
-        let viewport = vk::Viewport::default()
-            .x(0.0)
-            .y(0.0)
-            .width(screen_size[0])
-            .height(screen_size[1])
-            .min_depth(0.0)
-            .max_depth(1.0);
-
-        let scissor = vk::Rect2D::default()
-            .offset(vk::Offset2D { x: 0, y: 0 })
-            .extent(
-                vk::Extent2D::default()
-                    .width(screen_size[0] as u32)
-                    .height(screen_size[1] as u32),
-            );
-        let viewports = &[viewport];
-        let scissors = &[scissor];
+            .depth_bias_enable(info.dynamic_state.depth_bias.is_some());
 
+        // the viewport is set when the material is bound, see ``ViewportMode::apply``
         let viewport_state = vk::PipelineViewportStateCreateInfo::default()
-            .viewports(viewports)
-            .scissors(scissors);
+            .viewport_count(1)
+            .scissor_count(1);
 
-        let attachments = [vk::PipelineColorBlendAttachmentState::default()
-            .color_write_mask(vk::ColorComponentFlags::RGBA)
-            .blend_enable(false); 3];
+        // the rest is set for every draw, see ``DrawState::apply``
+        let dynamic_states: Vec<_> = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]
+            .into_iter()
+            .chain(info.dynamic_state.dynamic_states())
+            .collect();
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+        let attachments = info.blend_attachments(device.features.independent_blend);
+        let attachments = &attachments[..main_pass_target_count(device)];
 
         let color_blend_state = vk::PipelineColorBlendStateCreateInfo::default()
             .logic_op_enable(false)
             .logic_op(vk::LogicOp::COPY)
-            .attachments(&attachments)
+            .attachments(attachments)
             .blend_constants([0.0, 0.0, 0.0, 0.0]);
 
         let multisample_state = vk::PipelineMultisampleStateCreateInfo::default()
             .sample_shading_enable(false)
-            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+            .rasterization_samples(samples);
+
+        let specialization = self.specialization.info();
+        let stages: Vec<_> = self
+            .shaders
+            .iter()
+            .map(|&stage| {
+                if self.specialization.is_empty() || !stage.p_specialization_info.is_null() {
+                    stage
+                } else {
+                    stage.specialization_info(&specialization)
+                }
+            })
+            .collect();
 
-        let create_info = vk::GraphicsPipelineCreateInfo::default()
-            .stages(&self.shaders)
-            .vertex_input_state(&vertex_input_state)
-            .input_assembly_state(&input_assembly_state)
+        let mut create_info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(&stages)
             .viewport_state(&viewport_state)
             .rasterization_state(&rasterization_state)
             .color_blend_state(&color_blend_state)
             .multisample_state(&multisample_state)
+            .dynamic_state(&dynamic_state)
             .layout(layout)
-            .subpass(0)
-            .render_pass(rpass);
+            .subpass(0);
+
+        // mesh shaders make their own primitives, the pipeline can't have vertex input
+        if !self.uses_mesh_shader() {
+            create_info = create_info
+                .vertex_input_state(&vertex_input_state)
+                .input_assembly_state(&input_assembly_state);
+        }
 
-        let pipeline = unsafe {
+        let create_pipeline = |rpass| unsafe {
             device
-                .create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
-                .unwrap()
-        }[0];
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    &[create_info.render_pass(rpass)],
+                    None,
+                )
+                .unwrap()[0]
+        };
+
+        let pipeline = create_pipeline(rpass);
+        let multiview_pipeline = multiview_rpass
+            .filter(|_| self.multiview)
+            .map(create_pipeline);
 
         Material {
-            info: self.clone(),
+            info,
             pipeline,
+            multiview_pipeline,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> vk::Rect2D {
+        vk::Rect2D {
+            offset: vk::Offset2D { x, y },
+            extent: vk::Extent2D { width, height },
+        }
+    }
+
+    #[test]
+    fn viewport_follows_resize() {
+        let right_half = ViewportMode::Relative {
+            position: UDim2 {
+                scale: [0.5, 0.0],
+                offset: [0.0, 10.0],
+            },
+            size: UDim2 {
+                scale: [0.5, 1.0],
+                offset: [0.0, -10.0],
+            },
+        };
+
+        assert_eq!(
+            right_half.rect(vk::Extent2D::default().width(800).height(600)),
+            rect(400, 10, 400, 590)
+        );
+        assert_eq!(
+            right_half.rect(vk::Extent2D::default().width(1920).height(1080)),
+            rect(960, 10, 960, 1070)
+        );
+
+        let full = ViewportMode::default().viewport(vk::Extent2D::default().width(640).height(480));
+        assert_eq!(
+            [full.x, full.y, full.width, full.height],
+            [0.0, 0.0, 640.0, 480.0]
+        );
+    }
+
+    #[test]
+    fn specialization_constants_are_packed() {
+        let mut constants = SpecializationConstants::default()
+            .with(0, 16u32)
+            .with(3, true)
+            .with(1, 0.5f32);
+        // setting a constant again keeps its place
+        constants.set(0, 32u32);
+
+        let info = constants.info();
+        assert_eq!(info.map_entry_count, 3);
+        assert_eq!(info.data_size, 12);
+
+        let ids: Vec<_> = constants
+            .entries
+            .iter()
+            .map(|entry| (entry.constant_id, entry.offset, entry.size))
+            .collect();
+        assert_eq!(ids, [(0, 0, 4), (3, 4, 4), (1, 8, 4)]);
+        assert_eq!(constants.data[..4], 32u32.to_ne_bytes());
+        assert_eq!(constants.data[4..8], 1u32.to_ne_bytes());
+        assert_eq!(constants.data[8..], 0.5f32.to_ne_bytes());
+    }
+
+    #[test]
+    fn transparency_only_writes_its_targets() {
+        let info = |transparency| MaterialCreateInfo {
+            transparency,
+            skip_temporal_aa: true,
+            ..Default::default()
+        };
+        let writes = |attachments: [vk::PipelineColorBlendAttachmentState; 7]| {
+            attachments.map(|attachment| !attachment.color_write_mask.is_empty())
+        };
+
+        let opaque = info(Transparency::Opaque).blend_attachments(true);
+        assert_eq!(
+            writes(opaque),
+            [true, true, true, true, false, false, false]
+        );
+        assert_eq!(opaque[3].dst_color_blend_factor, vk::BlendFactor::ZERO);
+
+        let sorted = info(Transparency::Sorted).blend_attachments(true);
+        assert_eq!(
+            writes(sorted),
+            [true, false, false, false, false, false, false]
+        );
+
+        let weighted = info(Transparency::WeightedBlended).blend_attachments(true);
+        assert_eq!(
+            writes(weighted),
+            [false, false, false, false, true, true, false]
+        );
+        assert_eq!(
+            weighted[5].dst_color_blend_factor,
+            vk::BlendFactor::ONE_MINUS_SRC_COLOR
+        );
+
+        let picked = MaterialCreateInfo {
+            object_id: true,
+            ..info(Transparency::Opaque)
+        };
+        let picked = picked.blend_attachments(true);
+        assert_eq!(picked[6].color_write_mask, vk::ColorComponentFlags::R);
+        assert_eq!(picked[6].blend_enable, vk::FALSE);
+
+        // every attachment has to be the same without independent blending
+        let fallback = info(Transparency::Opaque).blend_attachments(false);
+        assert!(fallback
+            .iter()
+            .all(|attachment| attachment.blend_enable == vk::FALSE));
+    }
+
+    #[test]
+    fn constant_viewport_is_clipped() {
+        let minimap = ViewportMode::Constant {
+            position: [600, 20],
+            size: [256, 256],
+        };
+
+        let big = vk::Extent2D::default().width(1920).height(1080);
+        assert_eq!(minimap.rect(big), rect(600, 20, 256, 256));
+
+        // the window got smaller than the minimap
+        let small = vk::Extent2D::default().width(700).height(200);
+        assert_eq!(minimap.rect(small), rect(600, 20, 100, 180));
+
+        let tiny = vk::Extent2D::default().width(100).height(10);
+        assert_eq!(minimap.rect(tiny), rect(100, 10, 0, 0));
+    }
+}
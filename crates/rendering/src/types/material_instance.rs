@@ -0,0 +1,355 @@
+// per material data that is packed into a block of the material parameter buffer
+// the shader reads the block with ``GetMaterialParams<T>(index)``, see ``shaders/material.slang``
+// ``T`` needs to declare the parameters in the same order as the ``ParamLayout``
+
+use std::sync::Arc;
+
+use crate::{assets::texture::TextureHandle, handler::FLYING_FRAMES};
+
+use super::Material;
+
+/// the size of the block every instance gets in bytes, the parameters of a material can't be bigger
+pub const MATERIAL_BLOCK_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    /// the index of a texture in the bindless array, a ``uint`` in the shader
+    Texture,
+}
+
+impl ParamKind {
+    /// the size in bytes
+    #[must_use]
+    pub fn size(self) -> usize {
+        match self {
+            Self::Float | Self::Texture => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 => 12,
+            Self::Vec4 => 16,
+        }
+    }
+
+    /// the alignment in bytes, like std430
+    #[must_use]
+    pub fn align(self) -> usize {
+        match self {
+            Self::Float | Self::Texture => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 | Self::Vec4 => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterialParam {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Texture(TextureHandle),
+}
+
+impl MaterialParam {
+    #[must_use]
+    pub fn kind(&self) -> ParamKind {
+        match self {
+            Self::Float(_) => ParamKind::Float,
+            Self::Vec2(_) => ParamKind::Vec2,
+            Self::Vec3(_) => ParamKind::Vec3,
+            Self::Vec4(_) => ParamKind::Vec4,
+            Self::Texture(_) => ParamKind::Texture,
+        }
+    }
+
+    fn write(&self, out: &mut [u8]) {
+        let floats: &[f32] = match self {
+            Self::Float(v) => std::slice::from_ref(v),
+            Self::Vec2(v) => v,
+            Self::Vec3(v) => v,
+            Self::Vec4(v) => v,
+            Self::Texture(handle) => {
+                out.copy_from_slice(&(handle.index as u32).to_ne_bytes());
+                return;
+            }
+        };
+
+        for (out, v) in out.chunks_exact_mut(4).zip(floats) {
+            out.copy_from_slice(&v.to_ne_bytes());
+        }
+    }
+
+    fn read(kind: ParamKind, data: &[u8]) -> Self {
+        let float = |i: usize| f32::from_ne_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+
+        match kind {
+            ParamKind::Float => Self::Float(float(0)),
+            ParamKind::Vec2 => Self::Vec2(std::array::from_fn(float)),
+            ParamKind::Vec3 => Self::Vec3(std::array::from_fn(float)),
+            ParamKind::Vec4 => Self::Vec4(std::array::from_fn(float)),
+            // the generation isn't part of the block, ``MaterialInstance::get`` looks it up
+            ParamKind::Texture => Self::Texture(TextureHandle {
+                index: u32::from_ne_bytes(data[..4].try_into().unwrap()) as usize,
+                generation: 0,
+            }),
+        }
+    }
+}
+
+impl From<f32> for MaterialParam {
+    fn from(value: f32) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<[f32; 2]> for MaterialParam {
+    fn from(value: [f32; 2]) -> Self {
+        Self::Vec2(value)
+    }
+}
+
+impl From<[f32; 3]> for MaterialParam {
+    fn from(value: [f32; 3]) -> Self {
+        Self::Vec3(value)
+    }
+}
+
+impl From<[f32; 4]> for MaterialParam {
+    fn from(value: [f32; 4]) -> Self {
+        Self::Vec4(value)
+    }
+}
+
+impl From<TextureHandle> for MaterialParam {
+    fn from(value: TextureHandle) -> Self {
+        Self::Texture(value)
+    }
+}
+
+/// the parameters of a material and where they are in its block
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParamLayout {
+    /// name, kind and offset in bytes
+    params: Vec<(String, ParamKind, usize)>,
+    size: usize,
+}
+
+impl ParamLayout {
+    /// add a parameter after the previous ones
+    /// # Panics
+    /// if the name is already used or the parameters don't fit into ``MATERIAL_BLOCK_SIZE``
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, kind: ParamKind) -> Self {
+        let name = name.into();
+        assert!(
+            self.get(&name).is_none(),
+            "the parameter {name} exists already"
+        );
+
+        let offset = self.size.next_multiple_of(kind.align());
+        self.size = offset + kind.size();
+        assert!(
+            self.size <= MATERIAL_BLOCK_SIZE,
+            "the parameters don't fit into a material block"
+        );
+
+        self.params.push((name, kind, offset));
+        self
+    }
+
+    /// the kind and offset of a parameter
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<(ParamKind, usize)> {
+        self.params
+            .iter()
+            .find(|(param, ..)| param == name)
+            .map(|&(_, kind, offset)| (kind, offset))
+    }
+
+    /// the size in bytes without padding at the end
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+/// the parameter values of one use of a material, created with ``RenderHandler::create_material_instance``
+pub struct MaterialInstance {
+    material: Arc<Material>,
+    data: [u8; MATERIAL_BLOCK_SIZE],
+    /// the texture parameters by their offset, the block only has room for their index
+    textures: Vec<(usize, TextureHandle)>,
+    /// one bit for every frame in flight whose buffer still has the old values
+    dirty: u8,
+}
+
+impl MaterialInstance {
+    const ALL_FRAMES: u8 = (1 << FLYING_FRAMES) - 1;
+
+    /// all parameters start zeroed
+    pub(crate) fn new(material: Arc<Material>) -> Self {
+        Self {
+            material,
+            data: [0; MATERIAL_BLOCK_SIZE],
+            textures: Vec::new(),
+            dirty: Self::ALL_FRAMES,
+        }
+    }
+
+    #[must_use]
+    pub fn material(&self) -> &Arc<Material> {
+        &self.material
+    }
+
+    /// the new value is uploaded before the next frames are rendered
+    /// # Panics
+    /// if the material has no parameter with this name or the value has a different kind
+    pub fn set(&mut self, name: &str, value: impl Into<MaterialParam>) {
+        let value = value.into();
+        let Some((kind, offset)) = self.material.info.params.get(name) else {
+            panic!("the material has no parameter {name}");
+        };
+        assert_eq!(
+            kind,
+            value.kind(),
+            "the parameter {name} has a different kind"
+        );
+
+        if let MaterialParam::Texture(handle) = value {
+            self.textures
+                .retain(|&(texture_offset, _)| texture_offset != offset);
+            self.textures.push((offset, handle));
+        }
+
+        let before = self.data;
+        value.write(&mut self.data[offset..offset + kind.size()]);
+
+        // setting the same value again doesn't need an upload
+        if before != self.data {
+            self.dirty = Self::ALL_FRAMES;
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<MaterialParam> {
+        let (kind, offset) = self.material.info.params.get(name)?;
+        let texture = self
+            .textures
+            .iter()
+            .find(|&&(texture_offset, _)| texture_offset == offset);
+        if let Some(&(_, handle)) = texture {
+            return Some(MaterialParam::Texture(handle));
+        }
+        Some(MaterialParam::read(kind, &self.data[offset..]))
+    }
+
+    /// the bytes of the block if the buffer of this frame doesn't have them yet
+    pub(crate) fn take_dirty(&mut self, frame_index: usize) -> Option<&[u8; MATERIAL_BLOCK_SIZE]> {
+        let bit = 1 << frame_index;
+        if self.dirty & bit == 0 {
+            return None;
+        }
+
+        self.dirty &= !bit;
+        Some(&self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::*;
+    use crate::types::MaterialCreateInfo;
+
+    fn instance(params: ParamLayout) -> MaterialInstance {
+        MaterialInstance::new(Arc::new(Material {
+            pipeline: vk::Pipeline::null(),
+            multiview_pipeline: None,
+            info: MaterialCreateInfo {
+                params,
+                ..Default::default()
+            },
+        }))
+    }
+
+    #[test]
+    fn params_are_packed_like_std430() {
+        let layout = ParamLayout::default()
+            .with("roughness", ParamKind::Float)
+            .with("albedo", ParamKind::Vec3)
+            .with("metallic", ParamKind::Float)
+            .with("uv_scale", ParamKind::Vec2)
+            .with("albedo_texture", ParamKind::Texture);
+
+        assert_eq!(layout.get("roughness"), Some((ParamKind::Float, 0)));
+        assert_eq!(layout.get("albedo"), Some((ParamKind::Vec3, 16)));
+        // a float fits into the padding of a vec3
+        assert_eq!(layout.get("metallic"), Some((ParamKind::Float, 28)));
+        assert_eq!(layout.get("uv_scale"), Some((ParamKind::Vec2, 32)));
+        assert_eq!(layout.get("albedo_texture"), Some((ParamKind::Texture, 40)));
+        assert_eq!(layout.size(), 44);
+    }
+
+    #[test]
+    fn values_are_written_to_the_block() {
+        let mut instance = instance(
+            ParamLayout::default()
+                .with("tint", ParamKind::Vec4)
+                .with("texture", ParamKind::Texture),
+        );
+
+        instance.set("tint", [1.0, 0.5, 0.25, 1.0]);
+        let texture = TextureHandle {
+            index: 7,
+            generation: 3,
+        };
+        instance.set("texture", texture);
+
+        assert_eq!(
+            instance.get("tint"),
+            Some(MaterialParam::Vec4([1.0, 0.5, 0.25, 1.0]))
+        );
+        assert_eq!(
+            instance.get("texture"),
+            Some(MaterialParam::Texture(texture))
+        );
+        assert_eq!(instance.data[4..8], 0.5f32.to_ne_bytes());
+        assert_eq!(instance.data[16..20], 7u32.to_ne_bytes());
+        assert_eq!(instance.get("missing"), None);
+    }
+
+    #[test]
+    fn only_changed_instances_are_uploaded() {
+        let mut instance = instance(ParamLayout::default().with("speed", ParamKind::Float));
+
+        // new instances are uploaded to the buffer of every frame once
+        for frame in 0..FLYING_FRAMES {
+            assert!(instance.take_dirty(frame).is_some());
+            assert!(instance.take_dirty(frame).is_none());
+        }
+
+        instance.set("speed", 0.0);
+        assert!(instance.take_dirty(0).is_none());
+
+        instance.set("speed", 2.0);
+        assert!(instance.take_dirty(1).is_some());
+        assert!(instance.take_dirty(0).is_some());
+        assert!(instance.take_dirty(1).is_none());
+    }
+
+    #[test]
+    #[should_panic = "different kind"]
+    fn wrong_kind_panics() {
+        let mut instance = instance(ParamLayout::default().with("speed", ParamKind::Float));
+        instance.set("speed", [1.0, 2.0]);
+    }
+}
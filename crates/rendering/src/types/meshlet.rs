@@ -0,0 +1,222 @@
+// small groups of triangles for the mesh shading path
+// a task shader work group culls meshlets with their bounding sphere,
+// then one mesh shader work group emits the triangles of every visible meshlet
+// the limits are what most gpus handle well with one work group per meshlet
+
+/// the most vertices a meshlet can have
+pub const MAX_MESHLET_VERTICES: usize = 64;
+/// the most triangles a meshlet can have
+pub const MAX_MESHLET_TRIANGLES: usize = 124;
+
+/// the layout needs to match ``Meshlet`` in the mesh shader
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Meshlet {
+    /// the bounding sphere, used for culling
+    pub center: [f32; 3],
+    pub radius: f32,
+    /// the first entry in ``MeshletMesh::vertices``
+    pub vertex_offset: u32,
+    /// the first entry in ``MeshletMesh::triangles``
+    pub triangle_offset: u32,
+    pub vertex_count: u32,
+    pub triangle_count: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MeshletMesh {
+    pub meshlets: Vec<Meshlet>,
+    /// indices into the vertex buffer, every meshlet has its own range
+    pub vertices: Vec<u32>,
+    /// the corners of every triangle as indices into the vertices of its meshlet
+    /// the fourth byte is padding, so the shader can read a triangle as one ``uint``
+    pub triangles: Vec<[u8; 4]>,
+}
+
+impl MeshletMesh {
+    /// split an indexed triangle list into meshlets, the triangles keep their order
+    /// # Panics
+    /// if the index count isn't a multiple of 3 or an index is out of range
+    #[must_use]
+    pub fn build(positions: &[[f32; 3]], indices: &[u32]) -> Self {
+        assert!(
+            indices.len().is_multiple_of(3),
+            "the indices don't form a triangle list"
+        );
+
+        let mut mesh = Self::default();
+        let mut current = Meshlet::default();
+
+        for triangle in indices.chunks_exact(3) {
+            let meshlet_vertices = &mesh.vertices[current.vertex_offset as usize..];
+            let new_vertices = triangle
+                .iter()
+                .enumerate()
+                .filter(|&(i, index)| {
+                    !meshlet_vertices.contains(index) && !triangle[..i].contains(index)
+                })
+                .count();
+
+            if current.vertex_count as usize + new_vertices > MAX_MESHLET_VERTICES
+                || current.triangle_count as usize == MAX_MESHLET_TRIANGLES
+            {
+                mesh.finish(positions, &mut current);
+            }
+
+            let mut corners = [0; 4];
+            for (corner, &index) in corners.iter_mut().zip(triangle) {
+                assert!(
+                    (index as usize) < positions.len(),
+                    "the index {index} is out of range"
+                );
+
+                let meshlet_vertices = &mesh.vertices[current.vertex_offset as usize..];
+                *corner = match meshlet_vertices.iter().position(|&v| v == index) {
+                    Some(local) => local as u8,
+                    None => {
+                        mesh.vertices.push(index);
+                        current.vertex_count += 1;
+                        (current.vertex_count - 1) as u8
+                    }
+                };
+            }
+
+            mesh.triangles.push(corners);
+            current.triangle_count += 1;
+        }
+
+        if current.triangle_count > 0 {
+            mesh.finish(positions, &mut current);
+        }
+
+        mesh
+    }
+
+    /// compute the bounds of the meshlet, push it and start the next one
+    fn finish(&mut self, positions: &[[f32; 3]], meshlet: &mut Meshlet) {
+        let start = meshlet.vertex_offset as usize;
+        let vertices = || {
+            self.vertices[start..]
+                .iter()
+                .map(|&i| positions[i as usize])
+        };
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for position in vertices() {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+
+        let center: [f32; 3] = std::array::from_fn(|axis| (min[axis] + max[axis]) * 0.5);
+        meshlet.center = center;
+        meshlet.radius = vertices()
+            .map(|position| {
+                (0..3)
+                    .map(|axis| (position[axis] - center[axis]).powi(2))
+                    .sum::<f32>()
+                    .sqrt()
+            })
+            .fold(0.0, f32::max);
+
+        self.meshlets.push(*meshlet);
+        *meshlet = Meshlet {
+            vertex_offset: self.vertices.len() as u32,
+            triangle_offset: self.triangles.len() as u32,
+            ..Default::default()
+        };
+    }
+
+    /// the vertex buffer indices of a triangle, like in the index buffer the mesh was built from
+    #[must_use]
+    pub fn triangle(&self, meshlet: &Meshlet, triangle: usize) -> [u32; 3] {
+        let corners = self.triangles[meshlet.triangle_offset as usize + triangle];
+        std::array::from_fn(|i| self.vertices[meshlet.vertex_offset as usize + corners[i] as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a flat grid of quads, every quad has its own 4 vertices like the faces of a voxel mesh
+    fn grid(size: u32) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let mut positions = vec![];
+        let mut indices = vec![];
+
+        for y in 0..size {
+            for x in 0..size {
+                let first = positions.len() as u32;
+                for (dx, dy) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+                    positions.push([(x + dx) as f32, (y + dy) as f32, 0.0]);
+                }
+                indices.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
+            }
+        }
+
+        (positions, indices)
+    }
+
+    #[test]
+    fn shared_vertices_are_stored_once() {
+        let (positions, indices) = grid(1);
+        let mesh = MeshletMesh::build(&positions, &indices);
+
+        assert_eq!(mesh.meshlets.len(), 1);
+        assert_eq!(mesh.vertices, [0, 1, 2, 3]);
+        assert_eq!(mesh.triangles, [[0, 1, 2, 0], [0, 2, 3, 0]]);
+
+        let meshlet = mesh.meshlets[0];
+        assert_eq!(meshlet.center, [0.5, 0.5, 0.0]);
+        assert!((meshlet.radius - 0.5f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn meshlets_stay_within_the_limits() {
+        let (positions, indices) = grid(20);
+        let mesh = MeshletMesh::build(&positions, &indices);
+
+        // 4 vertices per quad fill a meshlet before the triangles do
+        assert_eq!(mesh.meshlets.len(), 400 / (MAX_MESHLET_VERTICES / 4));
+
+        let mut rebuilt = vec![];
+        for meshlet in &mesh.meshlets {
+            assert!(meshlet.vertex_count as usize <= MAX_MESHLET_VERTICES);
+            assert!(meshlet.triangle_count as usize <= MAX_MESHLET_TRIANGLES);
+
+            for triangle in 0..meshlet.triangle_count as usize {
+                let corners = mesh.triangle(meshlet, triangle);
+                for index in corners {
+                    let position = positions[index as usize];
+                    let distance = (0..3)
+                        .map(|axis| (position[axis] - meshlet.center[axis]).powi(2))
+                        .sum::<f32>()
+                        .sqrt();
+                    assert!(distance <= meshlet.radius + 1e-5);
+                }
+                rebuilt.extend(corners);
+            }
+        }
+
+        assert_eq!(rebuilt, indices);
+    }
+
+    #[test]
+    fn triangle_limit_starts_a_new_meshlet() {
+        // every triangle uses the same 3 vertices
+        let positions = [[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let indices: Vec<u32> = (0..MAX_MESHLET_TRIANGLES as u32 + 1)
+            .flat_map(|_| [0, 1, 2])
+            .collect();
+
+        let mesh = MeshletMesh::build(&positions, &indices);
+        assert_eq!(mesh.meshlets.len(), 2);
+        assert_eq!(
+            mesh.meshlets[1].triangle_offset,
+            MAX_MESHLET_TRIANGLES as u32
+        );
+        assert_eq!(mesh.meshlets[1].vertex_count, 3);
+    }
+}
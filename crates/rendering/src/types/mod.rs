@@ -1,5 +1,14 @@
 mod material;
+mod material_instance;
+mod meshlet;
 mod resource;
+mod shader_layout;
+mod variant;
+mod vertex;
 pub use material::*;
+pub use material_instance::*;
+pub use meshlet::*;
 pub use resource::*;
-
+pub use shader_layout::*;
+pub use variant::*;
+pub use vertex::*;
@@ -1,4 +1,3 @@
-
 #[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct MemoryAccessFlags(u32);
 
@@ -15,9 +14,4 @@ impl MemoryAccessFlags {
 pub enum ResourceType {
     Buffer,
     ImageView,
-} 
-
-
-
-
-
+}
@@ -0,0 +1,274 @@
+// checks that ``#[repr(C)]`` structs have the same layout as in the shader
+// uniform buffers use the std140 rules and storage buffers and push constants std430,
+// a rust struct has to insert the padding the shader expects by hand, for example a ``f32`` after a ``Vec3``
+// starts at byte 12 in both, but a ``Vec4`` after a ``f32`` starts at byte 16 only in the shader
+//
+//     shader_struct!(Uniforms, Std140 { view_proj, time });
+//
+// lists the fields in the order of the shader struct, ``check_layout`` then compares their offsets
+// with the ones the rules give, ``Buffer::write_shader`` does that in debug builds before writing
+//
+// only scalars, vectors and matrices are known, arrays and nested structs can't be checked
+
+use std::fmt;
+
+use math::{IVec2, IVec3, IVec4, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+/// the alignment and size of a field in the shader
+pub trait ShaderType: Copy {
+    const ALIGN: u32;
+    const SIZE: u32;
+}
+
+macro_rules! shader_types {
+    ($($ty:ty => $align:literal, $size:literal;)*) => {
+        $(impl ShaderType for $ty {
+            const ALIGN: u32 = $align;
+            const SIZE: u32 = $size;
+        })*
+    };
+}
+
+shader_types! {
+    f32 => 4, 4;
+    u32 => 4, 4;
+    i32 => 4, 4;
+    [f32; 2] => 8, 8;
+    [u32; 2] => 8, 8;
+    [i32; 2] => 8, 8;
+    Vec2 => 8, 8;
+    UVec2 => 8, 8;
+    IVec2 => 8, 8;
+    // a vec3 is aligned like a vec4, but a scalar can follow it right away
+    [f32; 3] => 16, 12;
+    [u32; 3] => 16, 12;
+    [i32; 3] => 16, 12;
+    Vec3 => 16, 12;
+    UVec3 => 16, 12;
+    IVec3 => 16, 12;
+    [f32; 4] => 16, 16;
+    [u32; 4] => 16, 16;
+    [i32; 4] => 16, 16;
+    Vec4 => 16, 16;
+    UVec4 => 16, 16;
+    IVec4 => 16, 16;
+    [[f32; 4]; 4] => 16, 64;
+    Mat4 => 16, 64;
+    // device addresses
+    u64 => 8, 8;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutRules {
+    /// uniform buffers, structs are aligned to 16 bytes
+    Std140,
+    /// storage buffers and push constants
+    Std430,
+}
+
+/// a field of a ``ShaderStruct`` with its offset in the rust struct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub offset: usize,
+    pub align: u32,
+    pub size: u32,
+}
+
+impl FieldInfo {
+    /// ``field`` only tells the type of the field, it's never called
+    pub fn of<S, F: ShaderType>(
+        name: &'static str,
+        offset: usize,
+        _field: impl Fn(&S) -> &F,
+    ) -> Self {
+        Self {
+            name,
+            offset,
+            align: F::ALIGN,
+            size: F::SIZE,
+        }
+    }
+}
+
+/// a struct that is shared with a shader, implemented with ``shader_struct!``
+pub trait ShaderStruct: Copy {
+    const RULES: LayoutRules;
+
+    /// in the order of the shader struct, padding fields are left out
+    fn fields() -> Vec<FieldInfo>;
+}
+
+/// implement ``ShaderStruct`` for a struct by listing the fields the shader reads
+///
+///     shader_struct!(Uniforms, Std140 { view_proj, cam_pos, time });
+#[macro_export]
+macro_rules! shader_struct {
+    ($ty:ty, $rules:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::types::ShaderStruct for $ty {
+            const RULES: $crate::types::LayoutRules = $crate::types::LayoutRules::$rules;
+
+            fn fields() -> Vec<$crate::types::FieldInfo> {
+                vec![$($crate::types::FieldInfo::of(
+                    stringify!($field),
+                    std::mem::offset_of!($ty, $field),
+                    |value: &$ty| &value.$field,
+                )),*]
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// the field isn't where the shader reads it, padding is missing or there is too much
+    Offset {
+        field: &'static str,
+        offset: usize,
+        expected: usize,
+    },
+    /// the padding at the end doesn't match, it matters for arrays of the struct
+    Size { size: usize, expected: usize },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Offset {
+                field,
+                offset,
+                expected,
+            } => write!(
+                f,
+                "{field} is at byte {offset}, but the shader reads it from byte {expected}"
+            ),
+            Self::Size { size, expected } => {
+                write!(
+                    f,
+                    "the struct is {size} bytes, but {expected} in the shader"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// compare the layout of the rust struct with the one the rules give
+/// # Errors
+/// for the first field that is at the wrong offset, or if the size is wrong
+pub fn check_layout<T: ShaderStruct>() -> Result<(), LayoutError> {
+    let mut offset = 0usize;
+    let mut struct_align = match T::RULES {
+        LayoutRules::Std140 => 16usize,
+        LayoutRules::Std430 => 1,
+    };
+
+    for field in T::fields() {
+        let expected = offset.next_multiple_of(field.align as usize);
+        if field.offset != expected {
+            return Err(LayoutError::Offset {
+                field: field.name,
+                offset: field.offset,
+                expected,
+            });
+        }
+
+        offset = expected + field.size as usize;
+        struct_align = struct_align.max(field.align as usize);
+    }
+
+    let expected = offset.next_multiple_of(struct_align);
+    if size_of::<T>() != expected {
+        return Err(LayoutError::Size {
+            size: size_of::<T>(),
+            expected,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Good {
+        view_proj: Mat4,
+        position: Vec3,
+        time: f32,
+        color: Vec4,
+        frame: u32,
+        _padding: [u32; 3],
+    }
+    shader_struct!(
+        Good,
+        Std140 {
+            view_proj,
+            position,
+            time,
+            color,
+            frame
+        }
+    );
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct MissingPadding {
+        time: f32,
+        color: [f32; 4],
+    }
+    shader_struct!(MissingPadding, Std430 { time, color });
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Packed {
+        offset: Vec2,
+        scale: f32,
+        index: u32,
+    }
+    shader_struct!(
+        Packed,
+        Std430 {
+            offset,
+            scale,
+            index
+        }
+    );
+
+    #[test]
+    fn offsets_follow_the_rules() {
+        assert_eq!(check_layout::<Good>(), Ok(()));
+        assert_eq!(check_layout::<Packed>(), Ok(()));
+
+        // the array has the alignment of a f32 in rust, so it starts right after the time
+        assert_eq!(
+            check_layout::<MissingPadding>(),
+            Err(LayoutError::Offset {
+                field: "color",
+                offset: 4,
+                expected: 16,
+            })
+        );
+    }
+
+    #[test]
+    fn std140_rounds_the_size() {
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        struct Small {
+            time: f32,
+        }
+        shader_struct!(Small, Std140 { time });
+
+        assert_eq!(
+            check_layout::<Small>(),
+            Err(LayoutError::Size {
+                size: 4,
+                expected: 16
+            })
+        );
+    }
+}
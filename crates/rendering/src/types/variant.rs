@@ -0,0 +1,402 @@
+// shader permutations, a material declares the features its shaders can be built with
+// and a variant is built the first time a combination of them is used, after that it's cached
+//
+// the option of every feature comes from the values of the draw, or from the view the material is drawn in,
+// see ``RenderHandler::view_variants``. features without a value use option 0
+//
+// how the features get in to the shader depends on the ``VariantSource``
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ash::vk;
+
+use crate::handler::RenderHandler;
+
+use super::{Material, MaterialCreateInfo};
+
+/// a feature of a material, ``options`` is 2 for features that are on or off
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantFeature {
+    pub name: String,
+    pub options: u32,
+}
+
+impl VariantFeature {
+    #[must_use]
+    pub fn boolean(name: impl Into<String>) -> Self {
+        Self::options(name, 2)
+    }
+
+    /// # Panics
+    /// if there are less than 2 options
+    #[must_use]
+    pub fn options(name: impl Into<String>, options: u32) -> Self {
+        assert!(options >= 2, "a feature needs at least 2 options");
+        Self {
+            name: name.into(),
+            options,
+        }
+    }
+
+    /// the bits the option takes in a ``VariantKey``
+    fn bits(&self) -> u32 {
+        u32::BITS - (self.options - 1).leading_zeros()
+    }
+}
+
+/// which option of every feature a variant uses, packed in to bits in the order the features were declared
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VariantKey(u64);
+
+/// the option of some features by name
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VariantValues {
+    values: HashMap<String, u32>,
+}
+
+impl VariantValues {
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<u32>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<u32>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.values.get(name).copied()
+    }
+}
+
+/// where the spir-v of a variant comes from
+#[derive(Debug, Clone)]
+pub enum VariantSource {
+    /// one module for every variant, feature ``i`` is passed as the specialization constant ``first_constant + i``
+    /// no compiling needed, but the shader contains the code of every feature
+    Specialization { code: Vec<u32>, first_constant: u32 },
+    /// a file for every variant, compiled ahead of time with ``build.sh``, see ``MaterialVariants::file_name``
+    Precompiled { dir: PathBuf, name: String },
+    /// compile the slang source with ``slangc`` the first time a variant is used,
+    /// every feature is defined in upper case, like ``-D FOG=1``, the results are written to ``cache_dir``
+    Compile {
+        source: PathBuf,
+        compiler: PathBuf,
+        cache_dir: PathBuf,
+    },
+}
+
+#[derive(Debug)]
+pub enum VariantError {
+    Io(std::io::Error),
+    /// the compiler failed, contains its output
+    Compile(String),
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for VariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read shader variant: {err}"),
+            Self::Compile(err) => write!(f, "failed to compile shader variant: {err}"),
+            Self::Vulkan(err) => write!(f, "failed to build shader variant: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for VariantError {}
+
+impl From<std::io::Error> for VariantError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<vk::Result> for VariantError {
+    fn from(err: vk::Result) -> Self {
+        Self::Vulkan(err)
+    }
+}
+
+/// a material whose shaders are built for every combination of features that is used
+pub struct MaterialVariants {
+    /// every variant is built from this, the module of every stage is replaced with the one of the variant
+    pub template: MaterialCreateInfo,
+    pub source: VariantSource,
+    features: Vec<VariantFeature>,
+    cache: HashMap<VariantKey, Arc<Material>>,
+}
+
+impl MaterialVariants {
+    #[must_use]
+    pub fn new(template: MaterialCreateInfo, source: VariantSource) -> Self {
+        Self {
+            template,
+            source,
+            features: vec![],
+            cache: HashMap::new(),
+        }
+    }
+
+    /// # Panics
+    /// if the options of all features don't fit in to 64 bits
+    #[must_use]
+    pub fn with_feature(mut self, feature: VariantFeature) -> Self {
+        let bits: u32 = self.features.iter().map(VariantFeature::bits).sum();
+        assert!(
+            bits + feature.bits() <= u64::BITS,
+            "too many variant features"
+        );
+
+        self.features.push(feature);
+        self
+    }
+
+    #[must_use]
+    pub fn features(&self) -> &[VariantFeature] {
+        &self.features
+    }
+
+    /// the values of the draw win over the ones of the view, options that don't exist are clamped to the last one
+    #[must_use]
+    pub fn key(&self, values: &VariantValues, view: &VariantValues) -> VariantKey {
+        let mut key = 0;
+        let mut shift = 0;
+
+        for feature in &self.features {
+            let value = values
+                .get(&feature.name)
+                .or_else(|| view.get(&feature.name))
+                .unwrap_or(0)
+                .min(feature.options - 1);
+
+            key |= u64::from(value) << shift;
+            shift += feature.bits();
+        }
+
+        VariantKey(key)
+    }
+
+    /// the option of every feature in the key
+    pub fn options(&self, key: VariantKey) -> impl Iterator<Item = (&VariantFeature, u32)> {
+        let mut shift = 0;
+        self.features.iter().map(move |feature| {
+            let mask = (1 << feature.bits()) - 1;
+            let value = (key.0 >> shift) & mask;
+            shift += feature.bits();
+            (feature, value as u32)
+        })
+    }
+
+    /// ``name.spv`` with the features that aren't 0 in between,
+    /// booleans only add their name, the others their name and option, like ``voxel.fog.shadows2.spv``
+    #[must_use]
+    pub fn file_name(&self, name: &str, key: VariantKey) -> String {
+        let mut file = name.to_owned();
+
+        for (feature, value) in self.options(key) {
+            match value {
+                0 => {}
+                1 if feature.options == 2 => file += &format!(".{}", feature.name),
+                _ => file += &format!(".{}{value}", feature.name),
+            }
+        }
+
+        file + ".spv"
+    }
+
+    /// the material of the variant, it's built if it's used for the first time
+    /// # Errors
+    /// if the spir-v of the variant can't be read or compiled, or the shader module can't be created
+    pub fn get(
+        &mut self,
+        renderer: &mut RenderHandler,
+        values: &VariantValues,
+    ) -> Result<Arc<Material>, VariantError> {
+        let key = self.key(values, renderer.view_variants());
+        if let Some(material) = self.cache.get(&key) {
+            return Ok(material.clone());
+        }
+
+        let code = self.code(key)?;
+        let module_info = vk::ShaderModuleCreateInfo::default().code(&code);
+        let module = unsafe { renderer.device.create_shader_module(&module_info, None) }?;
+
+        let mut info = self.template.clone();
+        for stage in &mut info.shaders {
+            stage.module = module;
+        }
+
+        if let VariantSource::Specialization { first_constant, .. } = self.source {
+            for (i, (_, value)) in self.options(key).enumerate() {
+                info.specialization.set(first_constant + i as u32, value);
+            }
+        }
+
+        let material = renderer.create_material(info);
+        let material = renderer
+            .material(material)
+            .expect("the material was just created");
+
+        // the pipeline doesn't need the module anymore
+        unsafe { renderer.device.destroy_shader_module(module, None) };
+
+        self.cache.insert(key, material.clone());
+        Ok(material)
+    }
+
+    /// forget the built variants, for example after the renderer was reinitialized
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// how many variants were built
+    #[must_use]
+    pub fn built(&self) -> usize {
+        self.cache.len()
+    }
+
+    fn code(&self, key: VariantKey) -> Result<Vec<u32>, VariantError> {
+        match &self.source {
+            VariantSource::Specialization { code, .. } => Ok(code.clone()),
+            VariantSource::Precompiled { dir, name } => {
+                read_spirv(&dir.join(self.file_name(name, key)))
+            }
+            VariantSource::Compile {
+                source,
+                compiler,
+                cache_dir,
+            } => {
+                let name = source
+                    .file_stem()
+                    .map_or("shader".into(), |stem| stem.to_string_lossy());
+                let output = cache_dir.join(self.file_name(&name, key));
+
+                if !output.exists() {
+                    std::fs::create_dir_all(cache_dir)?;
+                    self.compile(source, compiler, &output, key)?;
+                }
+
+                read_spirv(&output)
+            }
+        }
+    }
+
+    fn compile(
+        &self,
+        source: &Path,
+        compiler: &Path,
+        output: &Path,
+        key: VariantKey,
+    ) -> Result<(), VariantError> {
+        let mut command = std::process::Command::new(compiler);
+        command.arg("-O3").arg(source);
+
+        for (feature, value) in self.options(key) {
+            command
+                .arg("-D")
+                .arg(format!("{}={value}", feature.name.to_uppercase()));
+        }
+
+        let result = command
+            .arg("-target")
+            .arg("spirv")
+            .arg("-o")
+            .arg(output)
+            .output()?;
+
+        if !result.status.success() {
+            return Err(VariantError::Compile(
+                String::from_utf8_lossy(&result.stderr).into_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn read_spirv(path: &Path) -> Result<Vec<u32>, VariantError> {
+    let code = std::fs::read(path)?;
+    Ok(ash::util::read_spv(&mut Cursor::new(code))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variants() -> MaterialVariants {
+        let source = VariantSource::Specialization {
+            code: vec![],
+            first_constant: 0,
+        };
+
+        MaterialVariants::new(MaterialCreateInfo::default(), source)
+            .with_feature(VariantFeature::boolean("fog"))
+            .with_feature(VariantFeature::options("shadows", 3))
+            .with_feature(VariantFeature::boolean("skinning"))
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        let variants = variants();
+        let values = VariantValues::default()
+            .with("shadows", 2u32)
+            .with("skinning", true);
+
+        let key = variants.key(&values, &VariantValues::default());
+        let options: Vec<_> = variants
+            .options(key)
+            .map(|(feature, value)| (feature.name.as_str(), value))
+            .collect();
+
+        assert_eq!(options, [("fog", 0), ("shadows", 2), ("skinning", 1)]);
+    }
+
+    #[test]
+    fn draw_values_override_the_view() {
+        let variants = variants();
+        let view = VariantValues::default()
+            .with("fog", true)
+            .with("shadows", 1u32);
+        let values = VariantValues::default().with("shadows", 7u32);
+
+        let key = variants.key(&values, &view);
+        let options: Vec<_> = variants.options(key).map(|(_, value)| value).collect();
+
+        // the shadows are clamped to the last option
+        assert_eq!(options, [1, 2, 0]);
+    }
+
+    #[test]
+    fn file_names_list_the_used_features() {
+        let variants = variants();
+        let view = VariantValues::default();
+        let name = |values| variants.file_name("voxel", variants.key(&values, &view));
+
+        assert_eq!(name(VariantValues::default()), "voxel.spv");
+        assert_eq!(
+            name(
+                VariantValues::default()
+                    .with("fog", true)
+                    .with("shadows", 2u32)
+            ),
+            "voxel.fog.shadows2.spv"
+        );
+        assert_eq!(
+            name(
+                VariantValues::default()
+                    .with("shadows", 1u32)
+                    .with("skinning", true)
+            ),
+            "voxel.shadows1.skinning.spv"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+// vertex layouts built from the rust types of the attributes, so the formats, offsets and strides
+// don't have to be written by hand
+//
+//     VertexLayout::new().attr::<[f32; 3]>("position").attr::<[f32; 2]>("uv")
+//
+// the attributes are packed in the order they are added, like the fields of a ``#[repr(C)]`` struct without padding
+// ``VertexInput::validate`` checks a layout before the pipeline is created
+
+use std::{fmt, sync::Arc};
+
+use ash::vk;
+
+use crate::{handler::render_batch::DrawData, vulkan::Buffer};
+
+use super::VertexInput;
+
+/// a type that can be a vertex attribute
+pub trait VertexAttribute: Copy {
+    const FORMAT: vk::Format;
+}
+
+macro_rules! vertex_attributes {
+    ($($ty:ty => $format:ident),* $(,)?) => {
+        $(impl VertexAttribute for $ty {
+            const FORMAT: vk::Format = vk::Format::$format;
+        })*
+    };
+}
+
+vertex_attributes! {
+    f32 => R32_SFLOAT,
+    [f32; 2] => R32G32_SFLOAT,
+    [f32; 3] => R32G32B32_SFLOAT,
+    [f32; 4] => R32G32B32A32_SFLOAT,
+    u32 => R32_UINT,
+    [u32; 2] => R32G32_UINT,
+    [u32; 3] => R32G32B32_UINT,
+    [u32; 4] => R32G32B32A32_UINT,
+    i32 => R32_SINT,
+    [i32; 2] => R32G32_SINT,
+    [i32; 3] => R32G32B32_SINT,
+    [i32; 4] => R32G32B32A32_SINT,
+    // normalized to 0 to 1, for colors
+    [u8; 4] => R8G8B8A8_UNORM,
+}
+
+/// the size of a vertex attribute format in bytes, ``None`` for formats that aren't used for vertices here
+#[must_use]
+pub fn attribute_size(format: vk::Format) -> Option<u32> {
+    Some(match format {
+        vk::Format::R32_SFLOAT | vk::Format::R32_UINT | vk::Format::R32_SINT => 4,
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_UINT => 4,
+        vk::Format::R32G32_SFLOAT | vk::Format::R32G32_UINT | vk::Format::R32G32_SINT => 8,
+        vk::Format::R32G32B32_SFLOAT | vk::Format::R32G32B32_UINT | vk::Format::R32G32B32_SINT => {
+            12
+        }
+        vk::Format::R32G32B32A32_SFLOAT
+        | vk::Format::R32G32B32A32_UINT
+        | vk::Format::R32G32B32A32_SINT => 16,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutAttribute {
+    /// what the attribute is for, the shader only sees the location
+    pub name: &'static str,
+    pub format: vk::Format,
+    pub offset: u32,
+}
+
+/// the attributes of one vertex or instance buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    attributes: Vec<LayoutAttribute>,
+    stride: u32,
+    input_rate: vk::VertexInputRate,
+}
+
+impl Default for VertexLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VertexLayout {
+    /// a layout that advances per vertex
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            attributes: vec![],
+            stride: 0,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }
+    }
+
+    /// a layout that advances per instance
+    #[must_use]
+    pub fn per_instance() -> Self {
+        Self {
+            input_rate: vk::VertexInputRate::INSTANCE,
+            ..Self::new()
+        }
+    }
+
+    /// add an attribute right after the last one
+    #[must_use]
+    pub fn attr<T: VertexAttribute>(mut self, name: &'static str) -> Self {
+        self.attributes.push(LayoutAttribute {
+            name,
+            format: T::FORMAT,
+            offset: self.stride,
+        });
+        self.stride += size_of::<T>() as u32;
+        self
+    }
+
+    /// skip bytes that aren't read by the shader, like padding
+    #[must_use]
+    pub fn skip(mut self, bytes: u32) -> Self {
+        self.stride += bytes;
+        self
+    }
+
+    #[must_use]
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    #[must_use]
+    pub fn attributes(&self) -> &[LayoutAttribute] {
+        &self.attributes
+    }
+
+    /// a vertex input with only this layout at binding 0, the locations start at 0
+    #[must_use]
+    pub fn vertex_input(&self) -> VertexInput {
+        let mut input = VertexInput::default();
+        input.add_layout(0, 0, self);
+        input
+    }
+}
+
+/// a vertex type with a fixed layout, see ``PosNormalUv`` and ``PosColor``
+pub trait Vertex: Copy {
+    /// has to match the memory layout of the type, the stride is ``size_of::<Self>()``
+    fn layout() -> VertexLayout;
+}
+
+/// needs to match the vertex input of the shader, locations 0 to 2
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PosNormalUv {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+impl Vertex for PosNormalUv {
+    fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .attr::<[f32; 3]>("position")
+            .attr::<[f32; 3]>("normal")
+            .attr::<[f32; 2]>("uv")
+    }
+}
+
+/// needs to match the vertex input of the shader, locations 0 and 1
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PosColor {
+    pub position: [f32; 3],
+    /// linear with straight alpha
+    pub color: [f32; 4],
+}
+
+impl Vertex for PosColor {
+    fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .attr::<[f32; 3]>("position")
+            .attr::<[f32; 4]>("color")
+    }
+}
+
+impl VertexInput {
+    /// a vertex input with the layout of ``V`` at binding 0
+    #[must_use]
+    pub fn of<V: Vertex>() -> Self {
+        V::layout().vertex_input()
+    }
+
+    /// add a binding with the layout, the attributes get the locations starting at ``first_location``
+    /// returns the location after the last attribute
+    pub fn add_layout(&mut self, binding: u32, first_location: u32, layout: &VertexLayout) -> u32 {
+        self.bindings.push(
+            vk::VertexInputBindingDescription::default()
+                .binding(binding)
+                .stride(layout.stride)
+                .input_rate(layout.input_rate),
+        );
+
+        let mut location = first_location;
+        for attribute in &layout.attributes {
+            self.attributes.push(
+                vk::VertexInputAttributeDescription::default()
+                    .binding(binding)
+                    .location(location)
+                    .format(attribute.format)
+                    .offset(attribute.offset),
+            );
+            location += 1;
+        }
+
+        location
+    }
+
+    /// check that the attributes fit in to their bindings
+    /// # Errors
+    /// if a location or binding is used twice, an attribute has no binding or it reads past the stride
+    pub fn validate(&self) -> Result<(), VertexInputError> {
+        for (i, binding) in self.bindings.iter().enumerate() {
+            if self.bindings[..i]
+                .iter()
+                .any(|other| other.binding == binding.binding)
+            {
+                return Err(VertexInputError::DuplicateBinding(binding.binding));
+            }
+        }
+
+        for (i, attribute) in self.attributes.iter().enumerate() {
+            if self.attributes[..i]
+                .iter()
+                .any(|other| other.location == attribute.location)
+            {
+                return Err(VertexInputError::DuplicateLocation(attribute.location));
+            }
+
+            let Some(binding) = self
+                .bindings
+                .iter()
+                .find(|binding| binding.binding == attribute.binding)
+            else {
+                return Err(VertexInputError::MissingBinding {
+                    location: attribute.location,
+                    binding: attribute.binding,
+                });
+            };
+
+            let size = attribute_size(attribute.format).unwrap_or(0);
+            if attribute.offset + size > binding.stride {
+                return Err(VertexInputError::PastStride {
+                    location: attribute.location,
+                    end: attribute.offset + size,
+                    stride: binding.stride,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexInputError {
+    DuplicateBinding(u32),
+    DuplicateLocation(u32),
+    MissingBinding {
+        location: u32,
+        binding: u32,
+    },
+    PastStride {
+        location: u32,
+        end: u32,
+        stride: u32,
+    },
+}
+
+impl fmt::Display for VertexInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateBinding(binding) => write!(f, "binding {binding} is described twice"),
+            Self::DuplicateLocation(location) => write!(f, "location {location} is used twice"),
+            Self::MissingBinding { location, binding } => {
+                write!(
+                    f,
+                    "location {location} reads from binding {binding}, which isn't described"
+                )
+            }
+            Self::PastStride {
+                location,
+                end,
+                stride,
+            } => write!(
+                f,
+                "location {location} reads up to byte {end}, but the stride is {stride}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VertexInputError {}
+
+impl DrawData {
+    /// draw every vertex in the buffer, the count follows from the size of ``V``
+    #[must_use]
+    pub fn from_vertices<V: Vertex>(buffer: Arc<Buffer>) -> Self {
+        Self {
+            vertex_count: (buffer.size() / size_of::<V>() as u64) as u32,
+            vertex_buffer: Some(buffer),
+            instance_count: 1,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_match_the_types() {
+        assert_eq!(
+            PosNormalUv::layout().stride() as usize,
+            size_of::<PosNormalUv>()
+        );
+        assert_eq!(PosColor::layout().stride() as usize, size_of::<PosColor>());
+
+        let input = VertexInput::of::<PosNormalUv>();
+        assert_eq!(input.bindings[0].stride, 32);
+        let offsets: Vec<_> = input.attributes.iter().map(|a| a.offset).collect();
+        assert_eq!(offsets, [0, 12, 24]);
+        assert_eq!(input.validate(), Ok(()));
+
+        // a second buffer continues with the locations
+        let mut input = VertexInput::of::<PosColor>();
+        let next = input.add_layout(1, 2, &VertexLayout::per_instance().attr::<u32>("id"));
+        assert_eq!(next, 3);
+        assert_eq!(input.bindings[1].input_rate, vk::VertexInputRate::INSTANCE);
+        assert_eq!(input.validate(), Ok(()));
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        let mut input = VertexInput::of::<PosColor>();
+        input.add_layout(1, 1, &VertexLayout::new().attr::<f32>("scale"));
+        assert_eq!(
+            input.validate(),
+            Err(VertexInputError::DuplicateLocation(1))
+        );
+
+        let mut input = VertexInput::of::<PosColor>();
+        input.bindings[0].stride = 20;
+        assert_eq!(
+            input.validate(),
+            Err(VertexInputError::PastStride {
+                location: 1,
+                end: 28,
+                stride: 20
+            })
+        );
+
+        let mut input = VertexInput::default();
+        input.add_instance_matrix(1, 0);
+        input.attributes[0].binding = 3;
+        assert!(matches!(
+            input.validate(),
+            Err(VertexInputError::MissingBinding { binding: 3, .. })
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+// the gpus vulkan can see and which one the device is created on
+// by default a discrete gpu is preferred, laptops with two gpus can pick one with ``AdapterSelection``
+// a selection that matches no usable gpu falls back to the default, so an old config doesn't stop the renderer from starting
+
+use std::{fmt, str::FromStr};
+
+use ash::{prelude::VkResult, vk};
+
+use super::{DeviceFeatures, VulkanDevice};
+
+/// which gpu the device is created on, see ``RendererConfig::adapter``
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum AdapterSelection {
+    /// a discrete gpu if there is one, otherwise the first one that works
+    #[default]
+    Auto,
+    /// the index in ``VulkanDevice::enumerate_adapters``
+    Index(usize),
+    /// the first gpu whose name contains this, ignoring the case
+    Name(String),
+    Discrete,
+    Integrated,
+}
+
+impl FromStr for AdapterSelection {
+    type Err = std::convert::Infallible;
+
+    /// ``auto``, ``discrete``, ``integrated``, an index or else a part of the name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "auto" => Self::Auto,
+            "discrete" | "dgpu" => Self::Discrete,
+            "integrated" | "igpu" => Self::Integrated,
+            _ => s
+                .parse()
+                .map_or_else(|_| Self::Name(s.to_owned()), Self::Index),
+        })
+    }
+}
+
+impl fmt::Display for AdapterSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Name(name) => f.write_str(name),
+            Self::Discrete => f.write_str("discrete"),
+            Self::Integrated => f.write_str("integrated"),
+        }
+    }
+}
+
+/// the vendors that are known to need workarounds, from ``AdapterInfo::vendor_id``
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vendor {
+    Amd,
+    Nvidia,
+    Intel,
+    Arm,
+    Qualcomm,
+    Apple,
+    /// software renderers like lavapipe
+    Mesa,
+    Other(u32),
+}
+
+impl From<u32> for Vendor {
+    fn from(id: u32) -> Self {
+        match id {
+            0x1002 => Self::Amd,
+            0x10de => Self::Nvidia,
+            0x8086 => Self::Intel,
+            0x13b5 => Self::Arm,
+            0x5143 => Self::Qualcomm,
+            0x106b => Self::Apple,
+            0x10005 => Self::Mesa,
+            id => Self::Other(id),
+        }
+    }
+}
+
+/// a gpu the device can be created on
+#[derive(Debug, Clone)]
+pub struct AdapterInfo {
+    /// the index in ``VulkanDevice::enumerate_adapters``
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// encoded differently by every vendor
+    pub driver_version: u32,
+    pub api_version: u32,
+    pub heaps: Vec<vk::MemoryHeap>,
+    /// it can render, and present to the window if there is one
+    pub usable: bool,
+}
+
+impl AdapterInfo {
+    #[must_use]
+    pub fn vendor(&self) -> Vendor {
+        Vendor::from(self.vendor_id)
+    }
+
+    /// the size of the vram, or the memory shared with the cpu for integrated gpus
+    #[must_use]
+    pub fn device_local_memory(&self) -> u64 {
+        self.heaps
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .sum()
+    }
+
+    /// # Safety
+    /// the physical device must be from the instance
+    pub(crate) unsafe fn new(
+        instance: &ash::Instance,
+        index: usize,
+        pdevice: vk::PhysicalDevice,
+        usable: bool,
+    ) -> Self {
+        let props = instance.get_physical_device_properties(pdevice);
+        let memory = instance.get_physical_device_memory_properties(pdevice);
+
+        Self {
+            index,
+            name: props.device_name_as_c_str().map_or_else(
+                |_| String::new(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            device_type: props.device_type,
+            vendor_id: props.vendor_id,
+            device_id: props.device_id,
+            driver_version: props.driver_version,
+            api_version: props.api_version,
+            heaps: memory.memory_heaps[..memory.memory_heap_count as usize].to_vec(),
+            usable,
+        }
+    }
+}
+
+/// called with the chosen gpu before the device is created, see ``RendererConfig::adapter_workaround``
+/// it may only turn features off, turning on ones the gpu doesn't support is undefined behavior
+pub type AdapterWorkaround = fn(&AdapterInfo, &mut DeviceFeatures);
+
+impl AdapterSelection {
+    /// the index of the adapter to use, ``None`` if none of them is usable
+    /// falls back to ``AdapterSelection::Auto`` if nothing matches
+    #[must_use]
+    pub fn select(&self, adapters: &[AdapterInfo]) -> Option<usize> {
+        let mut usable = adapters.iter().filter(|adapter| adapter.usable);
+
+        let selected =
+            match self {
+                Self::Auto => None,
+                Self::Index(index) => usable.find(|adapter| adapter.index == *index),
+                Self::Name(name) => {
+                    let name = name.to_lowercase();
+                    usable.find(|adapter| adapter.name.to_lowercase().contains(&name))
+                }
+                Self::Discrete => usable
+                    .find(|adapter| adapter.device_type == vk::PhysicalDeviceType::DISCRETE_GPU),
+                Self::Integrated => usable
+                    .find(|adapter| adapter.device_type == vk::PhysicalDeviceType::INTEGRATED_GPU),
+            };
+
+        if let Some(adapter) = selected {
+            return Some(adapter.index);
+        }
+
+        if *self != Self::Auto {
+            log::warn!("no usable gpu matches {self}, picking one automatically");
+        }
+
+        adapters
+            .iter()
+            .filter(|adapter| adapter.usable)
+            .min_by_key(|adapter| match adapter.device_type {
+                vk::PhysicalDeviceType::DISCRETE_GPU => 0,
+                _ => 1,
+            })
+            .map(|adapter| adapter.index)
+    }
+}
+
+impl VulkanDevice {
+    /// every gpu vulkan can see, with a temporary instance
+    /// ``AdapterInfo::usable`` only tells if it can render, the window isn't known here
+    /// # Errors
+    /// if the vulkan library can't be loaded
+    pub fn enumerate_adapters() -> VkResult<Vec<AdapterInfo>> {
+        unsafe {
+            let entry = ash::Entry::load().map_err(|_| vk::Result::ERROR_INITIALIZATION_FAILED)?;
+            let app_info = vk::ApplicationInfo::default().api_version(vk::API_VERSION_1_3);
+            let instance = entry.create_instance(
+                &vk::InstanceCreateInfo::default().application_info(&app_info),
+                None,
+            )?;
+
+            let adapters = super::device::enumerate_adapters(&instance, None);
+            instance.destroy_instance(None);
+            Ok(adapters?.into_iter().map(|(_, adapter)| adapter).collect())
+        }
+    }
+
+    /// the gpu the device was created on
+    #[must_use]
+    pub fn adapter(&self) -> &AdapterInfo {
+        &self.adapter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter(index: usize, name: &str, device_type: vk::PhysicalDeviceType) -> AdapterInfo {
+        AdapterInfo {
+            index,
+            name: name.to_owned(),
+            device_type,
+            vendor_id: 0,
+            device_id: 0,
+            driver_version: 0,
+            api_version: 0,
+            heaps: vec![],
+            usable: true,
+        }
+    }
+
+    #[test]
+    fn select_adapters() {
+        let mut adapters = vec![
+            adapter(
+                0,
+                "Intel(R) UHD Graphics",
+                vk::PhysicalDeviceType::INTEGRATED_GPU,
+            ),
+            adapter(
+                1,
+                "NVIDIA GeForce RTX 3060",
+                vk::PhysicalDeviceType::DISCRETE_GPU,
+            ),
+            adapter(2, "llvmpipe", vk::PhysicalDeviceType::CPU),
+        ];
+
+        assert_eq!(AdapterSelection::Auto.select(&adapters), Some(1));
+        assert_eq!(AdapterSelection::Integrated.select(&adapters), Some(0));
+        assert_eq!(
+            "2".parse::<AdapterSelection>().unwrap().select(&adapters),
+            Some(2)
+        );
+        assert_eq!(
+            "geforce"
+                .parse::<AdapterSelection>()
+                .unwrap()
+                .select(&adapters),
+            Some(1)
+        );
+        // nothing matches, so it falls back to the default
+        assert_eq!(AdapterSelection::Index(7).select(&adapters), Some(1));
+
+        // a gpu that can't present to the window is skipped
+        adapters[1].usable = false;
+        assert_eq!(AdapterSelection::Discrete.select(&adapters), Some(0));
+        assert_eq!(AdapterSelection::Auto.select(&adapters), Some(0));
+
+        for adapter in &mut adapters {
+            adapter.usable = false;
+        }
+        assert_eq!(AdapterSelection::Auto.select(&adapters), None);
+    }
+
+    #[test]
+    fn parse_selection() {
+        for text in ["auto", "discrete", "integrated", "3", "Radeon"] {
+            let selection: AdapterSelection = text.parse().unwrap();
+            assert_eq!(selection.to_string(), text);
+        }
+        assert_eq!("iGPU".parse(), Ok(AdapterSelection::Integrated));
+    }
+}
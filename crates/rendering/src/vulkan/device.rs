@@ -4,9 +4,28 @@ use ash::vk;
 
 use ash::prelude::VkResult;
 
+use super::{
+    validation::{ValidationCounts, ValidationLog},
+    AdapterInfo, AdapterSelection, AdapterWorkaround, MemoryTracker, OIT_ACCUM_FORMAT,
+    OIT_REVEALAGE_FORMAT,
+};
+
 #[cfg(debug_assertions)]
 const DEBUG_LAYER: &std::ffi::CStr = c"VK_LAYER_KHRONOS_validation";
 
+/// how much the validation layer checks
+/// the layer is only loaded in debug builds, release builds ignore this
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationLevel {
+    /// don't load the validation layer
+    Off,
+    /// only report errors
+    Errors,
+    /// report everything and also check the synchronization
+    #[default]
+    Full,
+}
+
 #[allow(unused)]
 #[repr(C)]
 pub struct VulkanDevice {
@@ -16,13 +35,26 @@ pub struct VulkanDevice {
     pub pdevice: vk::PhysicalDevice,
     pub device: ash::Device,
     pub queues: DeviceQueues,
+    pub features: DeviceFeatures,
+    /// the functions of ``VK_EXT_mesh_shader``, only loaded if ``DeviceFeatures::mesh_shader`` is set
+    pub mesh_shader: Option<ash::ext::mesh_shader::Device>,
+    /// only loaded if ``DeviceFeatures::diagnostic_checkpoints`` is set
+    pub checkpoints: Option<ash::nv::device_diagnostic_checkpoints::Device>,
+    /// only loaded if ``DeviceFeatures::buffer_marker`` is set
+    pub buffer_marker: Option<ash::amd::buffer_marker::Device>,
+    /// what the ``MemoryBlock``s of this device allocated, see ``VulkanDevice::memory_heaps``
+    pub memory: MemoryTracker,
+    /// the gpu ``pdevice`` is, see ``VulkanDevice::adapter``
+    pub(crate) adapter: AdapterInfo,
 
     pub surface: vk::SurfaceKHR,
     pub surface_loader: ash::khr::surface::Instance,
 
     // debugger is disabled in release mode
     #[cfg(debug_assertions)]
-    debugger: debug::DebugHandler,
+    debugger: Option<debug::DebugHandler>,
+    /// written by the debugger, boxed so it keeps its address when the device moves
+    validation: Box<ValidationLog>,
 }
 
 impl VulkanDevice {
@@ -32,14 +64,33 @@ impl VulkanDevice {
     /// if the window isn't valid
     /// # Errors
     /// if the vulkan API isn't available
-    pub unsafe fn new<T>(window: &T) -> VkResult<Self>
+    pub unsafe fn new<T>(window: &T, validation: ValidationLevel) -> VkResult<Self>
+    where
+        T: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
+    {
+        Self::with_adapter(window, validation, &AdapterSelection::Auto, None)
+    }
+
+    /// create the device on a specific gpu, ``workaround`` can turn off features that are broken on it
+    /// # Safety
+    /// the window needs be valid and must stay valid until the Device has been destroyed
+    /// # Panics
+    /// if the window isn't valid
+    /// # Errors
+    /// if the vulkan API isn't available or no gpu can present to the window
+    pub unsafe fn with_adapter<T>(
+        window: &T,
+        validation: ValidationLevel,
+        adapter: &AdapterSelection,
+        workaround: Option<AdapterWorkaround>,
+    ) -> VkResult<Self>
     where
         T: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
     {
         let window_handle = window.window_handle().unwrap();
         let display_handle = window.display_handle().unwrap();
 
-        let (instance, entry) = create_instance(&display_handle)?;
+        let (instance, entry) = create_instance(Some(display_handle.as_raw()), validation)?;
 
         let surface_loader = ash::khr::surface::Instance::new(&entry, &instance);
 
@@ -51,22 +102,183 @@ impl VulkanDevice {
             None,
         )?;
 
-        let pdevice = get_physical_device(&instance, &surface_loader, surface)?;
+        let (pdevice, adapter) =
+            get_physical_device(&instance, Some((&surface_loader, surface)), adapter)?;
 
-        let (device, queues) = create_device(&instance, pdevice)?;
+        Self::from_instance(
+            entry,
+            instance,
+            (pdevice, adapter),
+            surface,
+            surface_loader,
+            validation,
+            workaround,
+        )
+    }
+
+    /// create a device without a window, for rendering to offscreen targets and reading them back
+    /// ``surface`` is null, so nothing that presents can be used with it
+    /// # Safety
+    /// the vulkan library must not be unloaded while the device exists
+    /// # Errors
+    /// if the vulkan library can't be loaded or there is no gpu that can render
+    pub unsafe fn headless(validation: ValidationLevel) -> VkResult<Self> {
+        let (instance, entry) = create_instance(None, validation)?;
+        let surface_loader = ash::khr::surface::Instance::new(&entry, &instance);
+
+        let pdevice = match get_physical_device(&instance, None, &AdapterSelection::Auto) {
+            Ok(pdevice) => pdevice,
+            Err(err) => {
+                instance.destroy_instance(None);
+                return Err(err);
+            }
+        };
+
+        Self::from_instance(
+            entry,
+            instance,
+            pdevice,
+            vk::SurfaceKHR::null(),
+            surface_loader,
+            validation,
+            None,
+        )
+    }
+
+    unsafe fn from_instance(
+        entry: ash::Entry,
+        instance: ash::Instance,
+        (pdevice, adapter): (vk::PhysicalDevice, AdapterInfo),
+        surface: vk::SurfaceKHR,
+        surface_loader: ash::khr::surface::Instance,
+        validation: ValidationLevel,
+        workaround: Option<AdapterWorkaround>,
+    ) -> VkResult<Self> {
+        let mut features = get_device_features(&instance, pdevice)?;
+        if let Some(workaround) = workaround {
+            workaround(&adapter, &mut features);
+        }
+        let (device, queues) = create_device(&instance, pdevice, features)?;
+        let mesh_shader = features
+            .mesh_shader
+            .then(|| ash::ext::mesh_shader::Device::new(&instance, &device));
+        let checkpoints = features
+            .diagnostic_checkpoints
+            .then(|| ash::nv::device_diagnostic_checkpoints::Device::new(&instance, &device));
+        let buffer_marker = features
+            .buffer_marker
+            .then(|| ash::amd::buffer_marker::Device::new(&instance, &device));
+
+        let validation_log = Box::<ValidationLog>::default();
 
         Ok(Self {
             #[cfg(debug_assertions)]
-            debugger: debug::setup_debugger(&instance, &entry),
+            debugger: (validation != ValidationLevel::Off)
+                .then(|| debug::setup_debugger(&instance, &entry, validation, &validation_log)),
+            validation: validation_log,
             entry,
             instance,
             pdevice,
             device,
             queues,
+            features,
+            mesh_shader,
+            checkpoints,
+            buffer_marker,
+            memory: MemoryTracker::default(),
+            adapter,
             surface,
             surface_loader,
         })
     }
+
+    /// tells if the device was created without a window
+    #[must_use]
+    pub fn is_headless(&self) -> bool {
+        self.surface == vk::SurfaceKHR::null()
+    }
+
+    /// how many messages the validation layer reported so far, see ``FrameStats::validation``
+    #[must_use]
+    pub fn validation_counts(&self) -> ValidationCounts {
+        self.validation.counts()
+    }
+
+    /// make ``check_validation`` panic after a validation error, for tests
+    pub fn set_panic_on_validation_error(&self, enabled: bool) {
+        self.validation.set_panic_on_error(enabled);
+    }
+
+    /// the debug callback can't panic, so the error is kept until this is called, after every frame by the renderer
+    /// # Panics
+    /// if ``set_panic_on_validation_error`` is enabled and there was a validation error since the last call
+    pub fn check_validation(&self) {
+        self.validation.check();
+    }
+}
+
+impl VulkanDevice {
+    /// the highest sample count up to ``samples`` the color targets support
+    #[must_use]
+    pub fn max_sample_count(&self, samples: u32) -> vk::SampleCountFlags {
+        let supported = unsafe { self.instance.get_physical_device_properties(self.pdevice) }
+            .limits
+            .framebuffer_color_sample_counts;
+
+        // the flag bits are the sample counts themselves
+        [64, 32, 16, 8, 4, 2]
+            .into_iter()
+            .map(vk::SampleCountFlags::from_raw)
+            .find(|&count| count.as_raw() <= samples && supported.contains(count))
+            .unwrap_or(vk::SampleCountFlags::TYPE_1)
+    }
+
+    /// record some commands and block until the gpu executed them
+    /// meant for one time uploads, don't use this every frame
+    /// # Safety
+    /// the recorded commands must be valid
+    /// # Errors
+    /// if there is no memory left or the device has been lost
+    pub unsafe fn immediate_submit(&self, record: impl FnOnce(vk::CommandBuffer)) -> VkResult<()> {
+        let pool_info = vk::CommandPoolCreateInfo::default()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(self.queues.graphics.0);
+
+        let pool = self.create_command_pool(&pool_info, None)?;
+        let fence = match self.create_fence(&vk::FenceCreateInfo::default(), None) {
+            Ok(fence) => fence,
+            Err(err) => {
+                self.destroy_command_pool(pool, None);
+                return Err(err);
+            }
+        };
+
+        let result = (|| {
+            let alloc_info = vk::CommandBufferAllocateInfo::default()
+                .command_pool(pool)
+                .command_buffer_count(1)
+                .level(vk::CommandBufferLevel::PRIMARY);
+
+            let cmd = self.allocate_command_buffers(&alloc_info)?[0];
+
+            let begin_info = vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+            self.begin_command_buffer(cmd, &begin_info)?;
+            record(cmd);
+            self.end_command_buffer(cmd)?;
+
+            let command_buffers = [vk::CommandBufferSubmitInfo::default().command_buffer(cmd)];
+            let submit = vk::SubmitInfo2::default().command_buffer_infos(&command_buffers);
+
+            self.submit(self.queues.graphics.1, &[submit], fence)?;
+            self.wait_for_fences(&[fence], true, u64::MAX)
+        })();
+
+        self.destroy_fence(fence, None);
+        self.destroy_command_pool(pool, None);
+        result
+    }
 }
 
 impl Drop for VulkanDevice {
@@ -74,7 +286,10 @@ impl Drop for VulkanDevice {
         unsafe {
             let _ = self.device.device_wait_idle();
             #[cfg(debug_assertions)]
-            self.debugger.destroy();
+            if let Some(debugger) = &self.debugger {
+                debugger.destroy();
+            }
+            // destroying a null surface is allowed, so headless devices don't need a special case
             self.surface_loader.destroy_surface(self.surface, None);
             self.device.destroy_device(None);
             self.instance.destroy_instance(None);
@@ -93,16 +308,24 @@ impl Deref for VulkanDevice {
 /// the entry point is rust specific, we need it to interact with the C library,
 /// the instance contains all the vulkan library data,
 /// as vulkan doesn't use global variables for that
+/// without a display no surface extensions are enabled
+#[cfg_attr(not(debug_assertions), allow(unused_variables))]
 unsafe fn create_instance(
-    display_handle: &raw_window_handle::DisplayHandle,
+    display_handle: Option<raw_window_handle::RawDisplayHandle>,
+    validation: ValidationLevel,
 ) -> VkResult<(ash::Instance, ash::Entry)> {
-    let entry = ash::Entry::load().unwrap();
+    // there is no vulkan library installed
+    let entry = ash::Entry::load().map_err(|_| vk::Result::ERROR_INITIALIZATION_FAILED)?;
 
-    let mut extensions =
-        ash_window::enumerate_required_extensions(display_handle.as_raw())?.to_vec();
+    let mut extensions = match display_handle {
+        Some(display_handle) => ash_window::enumerate_required_extensions(display_handle)?.to_vec(),
+        None => vec![],
+    };
 
     #[cfg(debug_assertions)]
-    extensions.push(ash::ext::debug_utils::NAME.as_ptr());
+    if validation != ValidationLevel::Off {
+        extensions.push(ash::ext::debug_utils::NAME.as_ptr());
+    }
 
     #[cfg(any(target_os = "macos", target_os = "ios"))]
     {
@@ -135,54 +358,263 @@ unsafe fn create_instance(
         .enabled_validation_features(&[vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION]);
 
     #[cfg(debug_assertions)]
-    let instance_info = instance_info
-        .push_next(&mut sync_layers)
-        .enabled_layer_names(&debug_layers);
+    let instance_info = match validation {
+        ValidationLevel::Off => instance_info,
+        ValidationLevel::Errors => instance_info.enabled_layer_names(&debug_layers),
+        ValidationLevel::Full => instance_info
+            .push_next(&mut sync_layers)
+            .enabled_layer_names(&debug_layers),
+    };
 
     let instance = entry.create_instance(&instance_info, None)?;
 
     Ok((instance, entry))
 }
 
-/// choose the best fitting GPU that supports our needs
+/// choose the GPU that fits the selection and supports our needs
 /// this is just used to gather some information
 /// and then create the logical device that's gonna be used for everything from then on
+/// without a surface any gpu that can render is fine
 unsafe fn get_physical_device(
     instance: &ash::Instance,
-    surface_loader: &ash::khr::surface::Instance,
-    surface: vk::SurfaceKHR,
-) -> VkResult<vk::PhysicalDevice> {
+    surface: Option<(&ash::khr::surface::Instance, vk::SurfaceKHR)>,
+    selection: &AdapterSelection,
+) -> VkResult<(vk::PhysicalDevice, AdapterInfo)> {
+    let mut adapters = enumerate_adapters(instance, surface)?;
+
+    let infos: Vec<AdapterInfo> = adapters.iter().map(|(_, info)| info.clone()).collect();
+    let index = selection
+        .select(&infos)
+        .ok_or(vk::Result::ERROR_INCOMPATIBLE_DRIVER)?;
+
+    Ok(adapters.swap_remove(index))
+}
+
+/// every physical device of the instance, with a surface only the ones that can present to it are usable
+pub(super) unsafe fn enumerate_adapters(
+    instance: &ash::Instance,
+    surface: Option<(&ash::khr::surface::Instance, vk::SurfaceKHR)>,
+) -> VkResult<Vec<(vk::PhysicalDevice, AdapterInfo)>> {
     let pdevices = instance.enumerate_physical_devices()?;
 
-    let pdevice = pdevices
-        .iter()
-        .filter_map(|pdevice| {
-            let queue_infos = instance.get_physical_device_queue_family_properties(*pdevice);
+    Ok(pdevices
+        .into_iter()
+        .enumerate()
+        .map(|(index, pdevice)| {
+            let queue_infos = instance.get_physical_device_queue_family_properties(pdevice);
 
             // the device just needs to support rendering
             // that also means that it supports compute and transfer
             // we also need to check if its able to render to the canvas we want to render on
             #[allow(clippy::cast_possible_truncation)]
-            queue_infos.iter().enumerate().find(|(i, v)| {
+            let usable = queue_infos.iter().enumerate().any(|(i, v)| {
                 v.queue_flags.contains(vk::QueueFlags::GRAPHICS)
-                    && surface_loader
-                        .get_physical_device_surface_support(*pdevice, *i as u32, surface)
-                        .unwrap()
-            })?;
-
-            Some(*pdevice)
+                    && surface.is_none_or(|(surface_loader, surface)| {
+                        surface_loader
+                            .get_physical_device_surface_support(pdevice, i as u32, surface)
+                            .unwrap_or(false)
+                    })
+            });
+
+            (pdevice, AdapterInfo::new(instance, index, pdevice, usable))
         })
-        .min_by_key(|pdevice| {
-            let props = instance.get_physical_device_properties(*pdevice);
+        .collect())
+}
 
-            match props.device_type {
-                vk::PhysicalDeviceType::DISCRETE_GPU => 0,
-                _ => 1,
-            }
-        })
-        .expect("failed to find matching physical device");
+/// optional features that are only enabled if the gpu supports them
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DeviceFeatures {
+    /// ``VK_KHR_present_id`` and ``VK_KHR_present_wait`` are enabled
+    /// used to tell when a frame has actually been presented
+    pub present_wait: bool,
+    /// the block compressed texture formats the gpu can sample from
+    pub texture_compression: TextureCompression,
+    /// samplers can use anisotropic filtering
+    pub sampler_anisotropy: bool,
+    /// barriers, timestamps and submits use the ``synchronization2`` commands of vulkan 1.3,
+    /// otherwise the old commands are used, see ``VulkanDevice::pipeline_barrier``
+    pub synchronization2: bool,
+    /// ``VK_EXT_mesh_shader`` is enabled with task and mesh shaders
+    /// materials can use them instead of a vertex shader, see ``DrawData::mesh_tasks``
+    pub mesh_shader: bool,
+    /// the attachments of a pipeline can be blended differently,
+    /// without it ``MaterialCreateInfo::skip_temporal_aa`` is ignored
+    pub independent_blend: bool,
+    /// ``independent_blend`` is supported and the transparency targets can be blended and used as storage images,
+    /// without it ``Transparency::WeightedBlended`` falls back to ``Transparency::Sorted``
+    pub weighted_blended_oit: bool,
+    /// ``independent_blend`` is supported, so the transparent materials can leave the integer object id target alone
+    /// without it the object id target isn't attached and ``RenderHandler::request_pick`` finds nothing
+    pub object_ids: bool,
+    /// ``VK_NV_device_diagnostic_checkpoints`` is enabled,
+    /// the driver tells which breadcrumbs the gpu reached after the device was lost
+    pub diagnostic_checkpoints: bool,
+    /// ``VK_AMD_buffer_marker`` is enabled, breadcrumbs are written to a buffer when the gpu reaches them
+    pub buffer_marker: bool,
+    /// ``VK_EXT_memory_budget`` is enabled, the driver tells how much memory the process may use
+    pub memory_budget: bool,
+    /// lines can be wider than one pixel, without it ``DrawState::line_width`` is always 1
+    pub wide_lines: bool,
+    /// the topology can be changed per draw, needs vulkan 1.3
+    /// without it ``DrawState::topology`` of the material is baked in to its pipeline and the draws can't change it
+    pub dynamic_topology: bool,
+    /// a render pass can draw in to ``MAX_VIEWS`` layers at once, with a view mask
+    /// the environment probes render all faces in one pass with it, see ``RendererConfig::multiview``
+    pub multiview: bool,
+}
 
-    Ok(pdevice)
+/// the most views a multiview render pass draws at once, one for every face of a cube
+pub const MAX_VIEWS: usize = 6;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextureCompression {
+    /// BC1 - BC7, usually supported on desktop
+    pub bc: bool,
+    /// ETC2 and EAC, usually supported on mobile
+    pub etc2: bool,
+    pub astc_ldr: bool,
+}
+
+impl TextureCompression {
+    /// tells if the compression family of the format is supported
+    /// uncompressed formats are always supported
+    #[must_use]
+    pub fn supports(self, format: vk::Format) -> bool {
+        match compression_family(format) {
+            Some(CompressionFamily::Bc) => self.bc,
+            Some(CompressionFamily::Etc2) => self.etc2,
+            Some(CompressionFamily::AstcLdr) => self.astc_ldr,
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFamily {
+    Bc,
+    Etc2,
+    AstcLdr,
+}
+
+/// the block compression used by the format, none if it isn't compressed
+#[must_use]
+pub fn compression_family(format: vk::Format) -> Option<CompressionFamily> {
+    let raw = format.as_raw();
+    let in_range =
+        |first: vk::Format, last: vk::Format| (first.as_raw()..=last.as_raw()).contains(&raw);
+
+    if in_range(vk::Format::BC1_RGB_UNORM_BLOCK, vk::Format::BC7_SRGB_BLOCK) {
+        Some(CompressionFamily::Bc)
+    } else if in_range(
+        vk::Format::ETC2_R8G8B8_UNORM_BLOCK,
+        vk::Format::EAC_R11G11_SNORM_BLOCK,
+    ) {
+        Some(CompressionFamily::Etc2)
+    } else if in_range(
+        vk::Format::ASTC_4X4_UNORM_BLOCK,
+        vk::Format::ASTC_12X12_SRGB_BLOCK,
+    ) {
+        Some(CompressionFamily::AstcLdr)
+    } else {
+        None
+    }
+}
+
+/// check what optional features the gpu supports
+unsafe fn get_device_features(
+    instance: &ash::Instance,
+    pdevice: vk::PhysicalDevice,
+) -> VkResult<DeviceFeatures> {
+    let extensions = instance.enumerate_device_extension_properties(pdevice)?;
+    let supports_extension = |name: &std::ffi::CStr| {
+        extensions
+            .iter()
+            .any(|v| v.extension_name_as_c_str() == Ok(name))
+    };
+
+    let supported = instance.get_physical_device_features(pdevice);
+
+    let mut features = DeviceFeatures {
+        texture_compression: TextureCompression {
+            bc: supported.texture_compression_bc == vk::TRUE,
+            etc2: supported.texture_compression_etc2 == vk::TRUE,
+            astc_ldr: supported.texture_compression_astc_ldr == vk::TRUE,
+        },
+        sampler_anisotropy: supported.sampler_anisotropy == vk::TRUE,
+        independent_blend: supported.independent_blend == vk::TRUE,
+        wide_lines: supported.wide_lines == vk::TRUE,
+        ..Default::default()
+    };
+
+    let blendable_storage = |format| {
+        let props = instance.get_physical_device_format_properties(pdevice, format);
+        props.optimal_tiling_features.contains(
+            vk::FormatFeatureFlags::COLOR_ATTACHMENT_BLEND | vk::FormatFeatureFlags::STORAGE_IMAGE,
+        )
+    };
+    features.object_ids = features.independent_blend;
+    features.weighted_blended_oit = features.independent_blend
+        && blendable_storage(OIT_ACCUM_FORMAT)
+        && blendable_storage(OIT_REVEALAGE_FORMAT);
+
+    if supports_extension(ash::khr::present_id::NAME)
+        && supports_extension(ash::khr::present_wait::NAME)
+    {
+        let mut present_id = vk::PhysicalDevicePresentIdFeaturesKHR::default();
+        let mut present_wait = vk::PhysicalDevicePresentWaitFeaturesKHR::default();
+        let mut features2 = vk::PhysicalDeviceFeatures2::default()
+            .push_next(&mut present_id)
+            .push_next(&mut present_wait);
+
+        instance.get_physical_device_features2(pdevice, &mut features2);
+
+        features.present_wait =
+            present_id.present_id == vk::TRUE && present_wait.present_wait == vk::TRUE;
+    }
+
+    if supports_extension(ash::ext::mesh_shader::NAME) {
+        let mut mesh_shader = vk::PhysicalDeviceMeshShaderFeaturesEXT::default();
+        let mut features2 = vk::PhysicalDeviceFeatures2::default().push_next(&mut mesh_shader);
+
+        instance.get_physical_device_features2(pdevice, &mut features2);
+
+        features.mesh_shader =
+            mesh_shader.task_shader == vk::TRUE && mesh_shader.mesh_shader == vk::TRUE;
+    }
+
+    features.diagnostic_checkpoints =
+        supports_extension(ash::nv::device_diagnostic_checkpoints::NAME);
+    features.buffer_marker = supports_extension(ash::amd::buffer_marker::NAME);
+    features.memory_budget = supports_extension(ash::ext::memory_budget::NAME);
+
+    // drivers that report an older version than the instance asked for can't be given the 1.3 features
+    let api_version = instance.get_physical_device_properties(pdevice).api_version;
+    if api_version >= vk::API_VERSION_1_3 {
+        let mut synchronization2 = vk::PhysicalDeviceSynchronization2Features::default();
+        let mut features2 = vk::PhysicalDeviceFeatures2::default().push_next(&mut synchronization2);
+
+        instance.get_physical_device_features2(pdevice, &mut features2);
+
+        features.synchronization2 = synchronization2.synchronization2 == vk::TRUE;
+        // the extended dynamic state is core in 1.3, it doesn't need a feature
+        features.dynamic_topology = true;
+    }
+
+    // multiview is core since vulkan 1.1
+    if api_version >= vk::API_VERSION_1_1 {
+        let mut multiview = vk::PhysicalDeviceMultiviewFeatures::default();
+        let mut features2 = vk::PhysicalDeviceFeatures2::default().push_next(&mut multiview);
+        instance.get_physical_device_features2(pdevice, &mut features2);
+
+        let mut multiview_props = vk::PhysicalDeviceMultiviewProperties::default();
+        let mut props2 = vk::PhysicalDeviceProperties2::default().push_next(&mut multiview_props);
+        instance.get_physical_device_properties2(pdevice, &mut props2);
+
+        features.multiview = multiview.multiview == vk::TRUE
+            && multiview_props.max_multiview_view_count as usize >= MAX_VIEWS;
+    }
+
+    Ok(features)
 }
 
 #[derive(Debug)]
@@ -198,6 +630,7 @@ pub struct DeviceQueues {
 unsafe fn create_device(
     instance: &ash::Instance,
     pdevice: vk::PhysicalDevice,
+    features: DeviceFeatures,
 ) -> VkResult<(ash::Device, DeviceQueues)> {
     let queue_props = instance.get_physical_device_queue_family_properties(pdevice);
 
@@ -208,20 +641,30 @@ unsafe fn create_device(
     let (compute_family, compute_queue_info) =
         get_best_queue_family(&queue_props, vk::QueueFlags::COMPUTE).unwrap();
 
-    assert!(graphics_family != compute_family, "gpu not supported yet"); // TODO
+    // software renderers like lavapipe only have a single queue family,
+    // then compute uses the second queue of it or shares the graphics queue if there is only one
+    let shared_family = graphics_family == compute_family;
+    let compute_index = u32::from(shared_family && compute_queue_info.queue_count > 1);
 
     let compute_priorities = vec![0.5; compute_queue_info.queue_count as usize];
+    let shared_priorities = [1.0, 0.5];
 
-    let queue_infos = [
-        vk::DeviceQueueCreateInfo::default()
+    let queue_infos = if shared_family {
+        vec![vk::DeviceQueueCreateInfo::default()
             .queue_family_index(graphics_family as u32)
-            .queue_priorities(&[1.0]),
-        vk::DeviceQueueCreateInfo::default()
-            .queue_family_index(compute_family as u32)
-            .queue_priorities(&compute_priorities),
-    ];
+            .queue_priorities(&shared_priorities[..=compute_index as usize])]
+    } else {
+        vec![
+            vk::DeviceQueueCreateInfo::default()
+                .queue_family_index(graphics_family as u32)
+                .queue_priorities(&[1.0]),
+            vk::DeviceQueueCreateInfo::default()
+                .queue_family_index(compute_family as u32)
+                .queue_priorities(&compute_priorities),
+        ]
+    };
 
-    let device_extensions = [
+    let mut device_extensions = vec![
         ash::khr::dynamic_rendering::NAME.as_ptr(),
         ash::ext::shader_object::NAME.as_ptr(),
         ash::khr::swapchain::NAME.as_ptr(),
@@ -229,21 +672,64 @@ unsafe fn create_device(
         ash::khr::portability_subset::NAME.as_ptr(),
     ];
 
+    if features.present_wait {
+        device_extensions.push(ash::khr::present_id::NAME.as_ptr());
+        device_extensions.push(ash::khr::present_wait::NAME.as_ptr());
+    }
+
+    if features.mesh_shader {
+        device_extensions.push(ash::ext::mesh_shader::NAME.as_ptr());
+    }
+
+    if features.diagnostic_checkpoints {
+        device_extensions.push(ash::nv::device_diagnostic_checkpoints::NAME.as_ptr());
+    }
+
+    if features.buffer_marker {
+        device_extensions.push(ash::amd::buffer_marker::NAME.as_ptr());
+    }
+
+    if features.memory_budget {
+        device_extensions.push(ash::ext::memory_budget::NAME.as_ptr());
+    }
+
     let mut dynamic_rendering_features =
         vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
 
     let mut shader_object_features =
         vk::PhysicalDeviceShaderObjectFeaturesEXT::default().shader_object(true);
 
+    let mut synchronization2_features =
+        vk::PhysicalDeviceSynchronization2Features::default().synchronization2(true);
+
     let mut vk12_features = vk::PhysicalDeviceVulkan12Features::default()
         .runtime_descriptor_array(true)
         .descriptor_indexing(true)
         .descriptor_binding_partially_bound(true)
         .descriptor_binding_variable_descriptor_count(true);
 
-    let device_features = vk::PhysicalDeviceFeatures::default().shader_int64(true);
+    let mut present_id_features =
+        vk::PhysicalDevicePresentIdFeaturesKHR::default().present_id(true);
+
+    let mut present_wait_features =
+        vk::PhysicalDevicePresentWaitFeaturesKHR::default().present_wait(true);
 
-    let device_create_info = vk::DeviceCreateInfo::default()
+    let mut multiview_features = vk::PhysicalDeviceMultiviewFeatures::default().multiview(true);
+
+    let mut mesh_shader_features = vk::PhysicalDeviceMeshShaderFeaturesEXT::default()
+        .task_shader(true)
+        .mesh_shader(true);
+
+    let device_features = vk::PhysicalDeviceFeatures::default()
+        .shader_int64(true)
+        .texture_compression_bc(features.texture_compression.bc)
+        .texture_compression_etc2(features.texture_compression.etc2)
+        .texture_compression_astc_ldr(features.texture_compression.astc_ldr)
+        .sampler_anisotropy(features.sampler_anisotropy)
+        .independent_blend(features.independent_blend)
+        .wide_lines(features.wide_lines);
+
+    let mut device_create_info = vk::DeviceCreateInfo::default()
         .queue_create_infos(&queue_infos)
         .enabled_extension_names(&device_extensions)
         .enabled_features(&device_features)
@@ -251,6 +737,24 @@ unsafe fn create_device(
         .push_next(&mut shader_object_features)
         .push_next(&mut vk12_features);
 
+    if features.synchronization2 {
+        device_create_info = device_create_info.push_next(&mut synchronization2_features);
+    }
+
+    if features.present_wait {
+        device_create_info = device_create_info
+            .push_next(&mut present_id_features)
+            .push_next(&mut present_wait_features);
+    }
+
+    if features.mesh_shader {
+        device_create_info = device_create_info.push_next(&mut mesh_shader_features);
+    }
+
+    if features.multiview {
+        device_create_info = device_create_info.push_next(&mut multiview_features);
+    }
+
     let device = instance.create_device(pdevice, &device_create_info, None)?;
 
     let graphics_queue = (
@@ -260,7 +764,7 @@ unsafe fn create_device(
 
     let compute_queue = (
         compute_family as u32,
-        device.get_device_queue(compute_family as u32, 0),
+        device.get_device_queue(compute_family as u32, compute_index),
     );
 
     Ok((
@@ -288,6 +792,8 @@ fn get_best_queue_family(
 
 #[cfg(debug_assertions)]
 mod debug {
+    use super::{ValidationLevel, ValidationLog};
+    use crate::vulkan::validation::Severity;
     use ash::{ext::debug_utils, vk};
     pub struct DebugHandler {
         debug_utils: debug_utils::Instance,
@@ -301,19 +807,33 @@ mod debug {
         }
     }
 
-    pub fn setup_debugger(instance: &ash::Instance, entry: &ash::Entry) -> DebugHandler {
-        let debug_info = vk::DebugUtilsMessengerCreateInfoEXT::default()
-            .message_severity(
+    /// ``log`` has to stay at the same address until the ``DebugHandler`` is destroyed
+    pub fn setup_debugger(
+        instance: &ash::Instance,
+        entry: &ash::Entry,
+        validation: ValidationLevel,
+        log: &ValidationLog,
+    ) -> DebugHandler {
+        let severity = match validation {
+            ValidationLevel::Off | ValidationLevel::Errors => {
+                vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
+            }
+            ValidationLevel::Full => {
                 vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
                     | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
-                    | vk::DebugUtilsMessageSeverityFlagsEXT::INFO,
-            )
+                    | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
+            }
+        };
+
+        let debug_info = vk::DebugUtilsMessengerCreateInfoEXT::default()
+            .message_severity(severity)
             .message_type(
                 vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
                     | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
                     | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
             )
-            .pfn_user_callback(Some(vulkan_debug_callback));
+            .pfn_user_callback(Some(vulkan_debug_callback))
+            .user_data(std::ptr::from_ref(log).cast_mut().cast());
 
         let debug_utils = debug_utils::Instance::new(entry, instance);
 
@@ -331,12 +851,11 @@ mod debug {
 
     unsafe extern "system" fn vulkan_debug_callback(
         message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
-        message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+        _message_type: vk::DebugUtilsMessageTypeFlagsEXT,
         p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT<'_>,
-        _user_data: *mut std::os::raw::c_void,
+        user_data: *mut std::os::raw::c_void,
     ) -> vk::Bool32 {
         let callback_data = *p_callback_data;
-        let message_id_number = callback_data.message_id_number;
 
         let message_id_name = if callback_data.p_message_id_name.is_null() {
             std::borrow::Cow::from("")
@@ -350,25 +869,14 @@ mod debug {
             std::ffi::CStr::from_ptr(callback_data.p_message).to_string_lossy()
         };
 
-        if log::log_enabled!(log::Level::Error) {
-            match message_type {
-                vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => log::info!(
-                "{message_severity:?}:\n[{message_id_name} ({message_id_number})] : {message}\n",
-            ),
-                vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => log::debug!(
-                "{message_severity:?}:\n[{message_id_name} ({message_id_number})] : {message}\n",
-            ),
-                vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
-                | vk::DebugUtilsMessageTypeFlagsEXT::DEVICE_ADDRESS_BINDING => log::error!(
-                "{message_severity:?}:\n[{message_id_name} ({message_id_number})] : {message}\n",
-            ),
-                _ => {}
-            }
-        } else {
-            println!(
-        "{message_severity:?}:\n{message_type:?} [{message_id_name} ({message_id_number})] : {message}\n",
-    );
-        }
+        // the log lives in a box of the device, which destroys the messenger before dropping it
+        let log = &*user_data.cast::<ValidationLog>();
+        log.report(
+            Severity::from_flags(message_severity),
+            callback_data.message_id_number,
+            &message_id_name,
+            &message,
+        );
 
         vk::FALSE
     }
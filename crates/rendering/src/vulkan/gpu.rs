@@ -0,0 +1,348 @@
+// the device calls the bookkeeping of the handler makes
+// code that only talks to the gpu through ``GpuDevice`` can be tested with ``mock::MockDevice``,
+// which records the calls instead of sending them to a gpu
+
+use ash::{prelude::VkResult, vk};
+
+use super::VulkanDevice;
+
+pub trait GpuDevice {
+    fn write_descriptor_sets(&self, writes: &[vk::WriteDescriptorSet<'_>]);
+
+    /// block until the gpu signaled the fence
+    /// # Errors
+    /// if the device was lost
+    fn wait_for_fence(&self, fence: vk::Fence) -> VkResult<()>;
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn bind_pipeline(
+        &self,
+        cmd: vk::CommandBuffer,
+        bind_point: vk::PipelineBindPoint,
+        pipeline: vk::Pipeline,
+    );
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn set_viewport(&self, cmd: vk::CommandBuffer, viewport: vk::Viewport);
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn set_scissor(&self, cmd: vk::CommandBuffer, scissor: vk::Rect2D);
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn set_depth_bias(&self, cmd: vk::CommandBuffer, constant: f32, slope: f32);
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn set_blend_constants(&self, cmd: vk::CommandBuffer, constants: [f32; 4]);
+
+    /// widths other than 1 need ``DeviceFeatures::wide_lines``
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn set_line_width(&self, cmd: vk::CommandBuffer, width: f32);
+
+    /// needs ``DeviceFeatures::dynamic_topology``
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn set_primitive_topology(
+        &self,
+        cmd: vk::CommandBuffer,
+        topology: vk::PrimitiveTopology,
+    );
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn bind_vertex_buffers(
+        &self,
+        cmd: vk::CommandBuffer,
+        buffers: &[vk::Buffer],
+        offsets: &[vk::DeviceSize],
+    );
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn bind_index_buffer(
+        &self,
+        cmd: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        index_type: vk::IndexType,
+    );
+
+    /// # Safety
+    /// the command buffer must be recording
+    unsafe fn push_constants(
+        &self,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        offset: u32,
+        data: &[u8],
+    );
+
+    /// # Safety
+    /// the command buffer must be recording inside a render pass
+    unsafe fn draw(&self, cmd: vk::CommandBuffer, vertex_count: u32, instance_count: u32);
+
+    /// # Safety
+    /// the command buffer must be recording inside a render pass
+    unsafe fn draw_indexed(&self, cmd: vk::CommandBuffer, index_count: u32, instance_count: u32);
+
+    /// # Safety
+    /// the command buffer must be recording inside a render pass and a mesh shading pipeline must be bound
+    unsafe fn draw_mesh_tasks(&self, cmd: vk::CommandBuffer, group_count: u32);
+}
+
+impl GpuDevice for VulkanDevice {
+    fn write_descriptor_sets(&self, writes: &[vk::WriteDescriptorSet<'_>]) {
+        unsafe { self.update_descriptor_sets(writes, &[]) };
+    }
+
+    fn wait_for_fence(&self, fence: vk::Fence) -> VkResult<()> {
+        unsafe { self.wait_for_fences(&[fence], true, u64::MAX) }
+    }
+
+    unsafe fn bind_pipeline(
+        &self,
+        cmd: vk::CommandBuffer,
+        bind_point: vk::PipelineBindPoint,
+        pipeline: vk::Pipeline,
+    ) {
+        self.cmd_bind_pipeline(cmd, bind_point, pipeline);
+    }
+
+    unsafe fn set_viewport(&self, cmd: vk::CommandBuffer, viewport: vk::Viewport) {
+        self.cmd_set_viewport(cmd, 0, &[viewport]);
+    }
+
+    unsafe fn set_scissor(&self, cmd: vk::CommandBuffer, scissor: vk::Rect2D) {
+        self.cmd_set_scissor(cmd, 0, &[scissor]);
+    }
+
+    unsafe fn set_depth_bias(&self, cmd: vk::CommandBuffer, constant: f32, slope: f32) {
+        self.cmd_set_depth_bias(cmd, constant, 0.0, slope);
+    }
+
+    unsafe fn set_blend_constants(&self, cmd: vk::CommandBuffer, constants: [f32; 4]) {
+        self.cmd_set_blend_constants(cmd, &constants);
+    }
+
+    unsafe fn set_line_width(&self, cmd: vk::CommandBuffer, width: f32) {
+        let width = if self.features.wide_lines { width } else { 1.0 };
+        self.cmd_set_line_width(cmd, width);
+    }
+
+    unsafe fn set_primitive_topology(
+        &self,
+        cmd: vk::CommandBuffer,
+        topology: vk::PrimitiveTopology,
+    ) {
+        self.cmd_set_primitive_topology(cmd, topology);
+    }
+
+    unsafe fn bind_vertex_buffers(
+        &self,
+        cmd: vk::CommandBuffer,
+        buffers: &[vk::Buffer],
+        offsets: &[vk::DeviceSize],
+    ) {
+        self.cmd_bind_vertex_buffers(cmd, 0, buffers, offsets);
+    }
+
+    unsafe fn bind_index_buffer(
+        &self,
+        cmd: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        index_type: vk::IndexType,
+    ) {
+        self.cmd_bind_index_buffer(cmd, buffer, 0, index_type);
+    }
+
+    unsafe fn push_constants(
+        &self,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        offset: u32,
+        data: &[u8],
+    ) {
+        self.cmd_push_constants(cmd, layout, vk::ShaderStageFlags::ALL, offset, data);
+    }
+
+    unsafe fn draw(&self, cmd: vk::CommandBuffer, vertex_count: u32, instance_count: u32) {
+        self.cmd_draw(cmd, vertex_count, instance_count, 0, 0);
+    }
+
+    unsafe fn draw_indexed(&self, cmd: vk::CommandBuffer, index_count: u32, instance_count: u32) {
+        self.cmd_draw_indexed(cmd, index_count, instance_count, 0, 0, 0);
+    }
+
+    unsafe fn draw_mesh_tasks(&self, cmd: vk::CommandBuffer, group_count: u32) {
+        let Some(mesh_shader) = &self.mesh_shader else {
+            panic!(
+                "drawing mesh tasks without VK_EXT_mesh_shader, check DeviceFeatures::mesh_shader"
+            );
+        };
+        mesh_shader.cmd_draw_mesh_tasks(cmd, group_count, 1, 1);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::cell::RefCell;
+
+    use ash::{prelude::VkResult, vk};
+
+    use super::GpuDevice;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DeviceCall {
+        WriteDescriptor {
+            set: vk::DescriptorSet,
+            binding: u32,
+            index: u32,
+            ty: vk::DescriptorType,
+        },
+        WaitForFence(vk::Fence),
+        BindPipeline(vk::Pipeline),
+        SetViewport,
+        SetScissor(vk::Rect2D),
+        SetDepthBias,
+        SetBlendConstants,
+        SetLineWidth,
+        SetPrimitiveTopology(vk::PrimitiveTopology),
+        BindVertexBuffers(usize),
+        BindIndexBuffer(vk::Buffer),
+        PushConstants {
+            offset: u32,
+            size: usize,
+        },
+        Draw {
+            vertices: u32,
+            instances: u32,
+        },
+        DrawIndexed {
+            indices: u32,
+            instances: u32,
+        },
+        DrawMeshTasks(u32),
+    }
+
+    /// records every call instead of sending it to a gpu
+    #[derive(Debug, Default)]
+    pub struct MockDevice {
+        calls: RefCell<Vec<DeviceCall>>,
+    }
+
+    impl MockDevice {
+        /// the calls since the last time this was called
+        pub fn take_calls(&self) -> Vec<DeviceCall> {
+            std::mem::take(&mut self.calls.borrow_mut())
+        }
+
+        fn record(&self, call: DeviceCall) {
+            self.calls.borrow_mut().push(call);
+        }
+    }
+
+    impl GpuDevice for MockDevice {
+        fn write_descriptor_sets(&self, writes: &[vk::WriteDescriptorSet<'_>]) {
+            for write in writes {
+                self.record(DeviceCall::WriteDescriptor {
+                    set: write.dst_set,
+                    binding: write.dst_binding,
+                    index: write.dst_array_element,
+                    ty: write.descriptor_type,
+                });
+            }
+        }
+
+        fn wait_for_fence(&self, fence: vk::Fence) -> VkResult<()> {
+            self.record(DeviceCall::WaitForFence(fence));
+            Ok(())
+        }
+
+        unsafe fn bind_pipeline(
+            &self,
+            _cmd: vk::CommandBuffer,
+            _bind_point: vk::PipelineBindPoint,
+            pipeline: vk::Pipeline,
+        ) {
+            self.record(DeviceCall::BindPipeline(pipeline));
+        }
+
+        unsafe fn set_viewport(&self, _cmd: vk::CommandBuffer, _viewport: vk::Viewport) {
+            self.record(DeviceCall::SetViewport);
+        }
+
+        unsafe fn set_scissor(&self, _cmd: vk::CommandBuffer, scissor: vk::Rect2D) {
+            self.record(DeviceCall::SetScissor(scissor));
+        }
+
+        unsafe fn set_depth_bias(&self, _cmd: vk::CommandBuffer, _constant: f32, _slope: f32) {
+            self.record(DeviceCall::SetDepthBias);
+        }
+
+        unsafe fn set_blend_constants(&self, _cmd: vk::CommandBuffer, _constants: [f32; 4]) {
+            self.record(DeviceCall::SetBlendConstants);
+        }
+
+        unsafe fn set_line_width(&self, _cmd: vk::CommandBuffer, _width: f32) {
+            self.record(DeviceCall::SetLineWidth);
+        }
+
+        unsafe fn set_primitive_topology(
+            &self,
+            _cmd: vk::CommandBuffer,
+            topology: vk::PrimitiveTopology,
+        ) {
+            self.record(DeviceCall::SetPrimitiveTopology(topology));
+        }
+
+        unsafe fn bind_vertex_buffers(
+            &self,
+            _cmd: vk::CommandBuffer,
+            buffers: &[vk::Buffer],
+            _offsets: &[vk::DeviceSize],
+        ) {
+            self.record(DeviceCall::BindVertexBuffers(buffers.len()));
+        }
+
+        unsafe fn bind_index_buffer(
+            &self,
+            _cmd: vk::CommandBuffer,
+            buffer: vk::Buffer,
+            _index_type: vk::IndexType,
+        ) {
+            self.record(DeviceCall::BindIndexBuffer(buffer));
+        }
+
+        unsafe fn push_constants(
+            &self,
+            _cmd: vk::CommandBuffer,
+            _layout: vk::PipelineLayout,
+            offset: u32,
+            data: &[u8],
+        ) {
+            self.record(DeviceCall::PushConstants {
+                offset,
+                size: data.len(),
+            });
+        }
+
+        unsafe fn draw(&self, _cmd: vk::CommandBuffer, vertices: u32, instances: u32) {
+            self.record(DeviceCall::Draw {
+                vertices,
+                instances,
+            });
+        }
+
+        unsafe fn draw_indexed(&self, _cmd: vk::CommandBuffer, indices: u32, instances: u32) {
+            self.record(DeviceCall::DrawIndexed { indices, instances });
+        }
+
+        unsafe fn draw_mesh_tasks(&self, _cmd: vk::CommandBuffer, group_count: u32) {
+            self.record(DeviceCall::DrawMeshTasks(group_count));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// how much memory every heap of the gpu has left
+// every ``MemoryBlock`` adds its size to the ``MemoryTracker`` of the device, so the renderer knows what it allocated itself,
+// with ``VK_EXT_memory_budget`` the driver also tells how much the whole process uses and how much it may use
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use ash::vk;
+
+use crate::vulkan::VulkanDevice;
+
+/// the allocations of one device, counted per heap
+#[derive(Debug, Default)]
+pub struct MemoryTracker {
+    allocated: [AtomicU64; vk::MAX_MEMORY_HEAPS],
+    allocations: [AtomicUsize; vk::MAX_MEMORY_HEAPS],
+}
+
+impl MemoryTracker {
+    pub(crate) fn add(&self, heap: usize, size: u64) {
+        self.allocated[heap].fetch_add(size, Ordering::Relaxed);
+        self.allocations[heap].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn remove(&self, heap: usize, size: u64) {
+        self.allocated[heap].fetch_sub(size, Ordering::Relaxed);
+        self.allocations[heap].fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// the bytes allocated from the heap through ``MemoryBlock``s
+    #[must_use]
+    pub fn allocated(&self, heap: usize) -> u64 {
+        self.allocated[heap].load(Ordering::Relaxed)
+    }
+
+    /// the amount of ``MemoryBlock``s in the heap
+    #[must_use]
+    pub fn allocations(&self, heap: usize) -> usize {
+        self.allocations[heap].load(Ordering::Relaxed)
+    }
+}
+
+/// the usage of one memory heap, see ``VulkanDevice::memory_heaps``
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeapBudget {
+    /// the size of the heap in bytes
+    pub size: u64,
+    /// how much the process can use before allocations fail or get slow
+    /// the driver changes it when other applications need memory, without ``VK_EXT_memory_budget`` it's the heap size
+    pub budget: u64,
+    /// how much the whole process uses, without ``VK_EXT_memory_budget`` it's ``allocated``
+    pub usage: u64,
+    /// how much the renderer allocated itself
+    pub allocated: u64,
+    pub allocations: usize,
+    /// vram, the textures and the gpu only buffers live here
+    pub device_local: bool,
+}
+
+impl HeapBudget {
+    /// the bytes that can still be allocated within the budget
+    #[must_use]
+    pub fn available(&self) -> u64 {
+        self.budget.saturating_sub(self.usage)
+    }
+
+    /// the bytes over the budget, 0 if it fits
+    #[must_use]
+    pub fn over_budget(&self) -> u64 {
+        self.usage.saturating_sub(self.budget)
+    }
+}
+
+impl VulkanDevice {
+    /// the budget and usage of every memory heap
+    #[must_use]
+    pub fn memory_heaps(&self) -> Vec<HeapBudget> {
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut properties = vk::PhysicalDeviceMemoryProperties2::default();
+        if self.features.memory_budget {
+            properties = properties.push_next(&mut budget);
+        }
+
+        unsafe {
+            self.instance
+                .get_physical_device_memory_properties2(self.pdevice, &mut properties);
+        }
+        let properties = properties.memory_properties;
+
+        properties.memory_heaps[..properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(i, heap)| {
+                let allocated = self.memory.allocated(i);
+                let (budget, usage) = if self.features.memory_budget {
+                    (budget.heap_budget[i], budget.heap_usage[i])
+                } else {
+                    (heap.size, allocated)
+                };
+
+                HeapBudget {
+                    size: heap.size,
+                    budget,
+                    usage,
+                    allocated,
+                    allocations: self.memory.allocations(i),
+                    device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_allocations_per_heap() {
+        let tracker = MemoryTracker::default();
+        tracker.add(0, 256);
+        tracker.add(0, 64);
+        tracker.add(1, 32);
+        tracker.remove(0, 256);
+
+        assert_eq!(tracker.allocated(0), 64);
+        assert_eq!(tracker.allocations(0), 1);
+        assert_eq!(tracker.allocated(1), 32);
+
+        let heap = HeapBudget {
+            budget: 100,
+            usage: 160,
+            ..Default::default()
+        };
+        assert_eq!(heap.available(), 0);
+        assert_eq!(heap.over_budget(), 60);
+    }
+}
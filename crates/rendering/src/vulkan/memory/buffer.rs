@@ -2,7 +2,9 @@ use std::{ffi::c_void, ptr::NonNull, sync::Arc};
 
 use ash::{prelude::VkResult, vk};
 
-use crate::vulkan::VulkanDevice;
+#[cfg(debug_assertions)]
+use crate::types::check_layout;
+use crate::{types::ShaderStruct, vulkan::VulkanDevice};
 
 use super::MemoryBlock;
 
@@ -80,6 +82,21 @@ impl Buffer {
         slice.copy_from_slice(data);
     }
 
+    /// like ``write``, but in debug builds the layout of ``T`` is checked against the shader first
+    /// # Panics
+    /// if the buffer isn't host visible, or in debug builds if the layout doesn't match the shader
+    pub fn write_shader<T: ShaderStruct>(&self, offset: usize, data: &[T]) {
+        #[cfg(debug_assertions)]
+        if let Err(err) = check_layout::<T>() {
+            panic!(
+                "{} doesn't match the shader: {err}",
+                std::any::type_name::<T>()
+            );
+        }
+
+        self.write(offset, data);
+    }
+
     /// # Panics
     /// if the buffer wasn't created with ``MemoryPropertyFlags::HOST_VISIBLE``
     /// # Safety
@@ -119,6 +136,15 @@ impl Buffer {
     pub fn handle(&self) -> vk::Buffer {
         self.handle
     }
+    /// the size in bytes
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    #[must_use]
+    pub fn usage(&self) -> vk::BufferUsageFlags {
+        self.usage
+    }
     #[must_use]
     pub fn mem_ref(&self) -> &MemoryBlock {
         &self.memory
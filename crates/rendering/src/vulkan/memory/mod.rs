@@ -1,13 +1,20 @@
-use std::sync::Arc;
-use ash::{prelude::VkResult, vk};
 use super::VulkanDevice;
+use ash::{prelude::VkResult, vk};
+pub use budget::{HeapBudget, MemoryTracker};
 pub use buffer::Buffer;
+use std::sync::Arc;
+pub use texture::Texture;
 
+mod budget;
 mod buffer;
+mod texture;
 
 pub struct MemoryBlock {
     device: Arc<VulkanDevice>,
     memory: vk::DeviceMemory,
+    /// the heap the memory is counted in, see ``VulkanDevice::memory``
+    heap: usize,
+    size: u64,
 }
 
 impl MemoryBlock {
@@ -35,22 +42,37 @@ impl MemoryBlock {
 
         let memory = unsafe { device.allocate_memory(&alloc_info, None) }?;
 
-        Ok(Self { device, memory })
+        let heap = mem_props.memory_types[memory_index as usize].heap_index as usize;
+        let size = memory_requirements.size;
+        device.memory.add(heap, size);
+
+        Ok(Self {
+            device,
+            memory,
+            heap,
+            size,
+        })
     }
 
     #[must_use]
     pub fn handle(&self) -> vk::DeviceMemory {
         self.memory
     }
+
+    /// the size of the allocation in bytes
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
 }
 
 impl Drop for MemoryBlock {
     fn drop(&mut self) {
         unsafe { self.device.free_memory(self.memory, None) };
+        self.device.memory.remove(self.heap, self.size);
     }
 }
 
-
 #[must_use]
 pub fn find_memorytype_index(
     memory_req: vk::MemoryRequirements,
@@ -64,5 +86,5 @@ pub fn find_memorytype_index(
             (1 << index) & memory_req.memory_type_bits != 0
                 && memory_type.property_flags & flags == flags
         })
-    .map(|(index, _memory_type)| index as u32)
+        .map(|(index, _memory_type)| index as u32)
 }
@@ -0,0 +1,596 @@
+use std::sync::Arc;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::vulkan::VulkanDevice;
+
+use super::{Buffer, MemoryBlock};
+
+/// a sampled 2D image with all its mip levels, or an array or cube of them, see ``new_array`` and ``new_cube``
+pub struct Texture {
+    device: Arc<VulkanDevice>,
+    memory: MemoryBlock,
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub format: vk::Format,
+    pub extent: [u32; 2],
+    /// the slices of 3d textures, 1 for every other texture
+    pub depth: u32,
+    pub mip_levels: u32,
+    /// 1 for normal textures, 6 for cubes
+    pub layers: u32,
+    /// the view of arrays is a ``TYPE_2D_ARRAY`` even with a single layer
+    pub view_type: vk::ImageViewType,
+    /// the amount of gpu memory used in bytes
+    pub size: u64,
+}
+
+impl Texture {
+    /// # Errors
+    /// if there is no space left to allocate
+    pub fn new(
+        device: Arc<VulkanDevice>,
+        extent: [u32; 2],
+        format: vk::Format,
+        mip_levels: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> VkResult<Self> {
+        Self::create(
+            device,
+            [extent[0], extent[1], 1],
+            format,
+            mip_levels,
+            1,
+            vk::ImageViewType::TYPE_2D,
+            usage,
+        )
+    }
+
+    /// a volume without mip levels, ``upload`` takes the slices one after another
+    /// it's sampled as a ``Sampler3D`` in shaders, see ``GetTexture3D`` in ``bindless.slang``
+    /// # Errors
+    /// if there is no space left to allocate
+    pub fn new_3d(
+        device: Arc<VulkanDevice>,
+        extent: [u32; 3],
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+    ) -> VkResult<Self> {
+        Self::create(
+            device,
+            extent,
+            format,
+            1,
+            1,
+            vk::ImageViewType::TYPE_3D,
+            usage,
+        )
+    }
+
+    /// a texture with the given number of layers that all have the same size and mip levels
+    /// it's sampled as a ``Sampler2DArray`` in shaders, see ``GetTextureArray`` in ``bindless.slang``
+    /// # Errors
+    /// if there is no space left to allocate
+    pub fn new_array(
+        device: Arc<VulkanDevice>,
+        extent: [u32; 2],
+        format: vk::Format,
+        mip_levels: u32,
+        layers: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> VkResult<Self> {
+        Self::create(
+            device,
+            [extent[0], extent[1], 1],
+            format,
+            mip_levels,
+            layers,
+            vk::ImageViewType::TYPE_2D_ARRAY,
+            usage,
+        )
+    }
+
+    /// a cubemap with square faces in the layers, in the order +x, -x, +y, -y, +z, -z
+    /// it's sampled as a ``SamplerCube`` in shaders, see ``GetTextureCube`` in ``bindless.slang``
+    /// # Errors
+    /// if there is no space left to allocate
+    pub fn new_cube(
+        device: Arc<VulkanDevice>,
+        size: u32,
+        format: vk::Format,
+        mip_levels: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> VkResult<Self> {
+        Self::create(
+            device,
+            [size, size, 1],
+            format,
+            mip_levels,
+            6,
+            vk::ImageViewType::CUBE,
+            usage,
+        )
+    }
+
+    fn create(
+        device: Arc<VulkanDevice>,
+        extent: [u32; 3],
+        format: vk::Format,
+        mip_levels: u32,
+        layers: u32,
+        view_type: vk::ImageViewType,
+        usage: vk::ImageUsageFlags,
+    ) -> VkResult<Self> {
+        let flags = if view_type == vk::ImageViewType::CUBE {
+            vk::ImageCreateFlags::CUBE_COMPATIBLE
+        } else {
+            vk::ImageCreateFlags::empty()
+        };
+
+        let image_info = vk::ImageCreateInfo::default()
+            .flags(flags)
+            .image_type(if view_type == vk::ImageViewType::TYPE_3D {
+                vk::ImageType::TYPE_3D
+            } else {
+                vk::ImageType::TYPE_2D
+            })
+            .format(format)
+            .extent(vk::Extent3D {
+                width: extent[0],
+                height: extent[1],
+                depth: extent[2],
+            })
+            .mip_levels(mip_levels)
+            .array_layers(layers)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(usage);
+
+        let image = unsafe { device.create_image(&image_info, None) }?;
+
+        let memory_requirements = unsafe { device.get_image_memory_requirements(image) };
+        let memory = MemoryBlock::new(
+            device.clone(),
+            memory_requirements,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        unsafe { device.bind_image_memory(image, memory.handle(), 0) }?;
+
+        let view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(view_type)
+            .format(format)
+            .subresource_range(subresource_range(0, mip_levels, 0).layer_count(layers));
+
+        let view = unsafe { device.create_image_view(&view_info, None) }?;
+
+        Ok(Self {
+            device,
+            memory,
+            image,
+            view,
+            format,
+            extent: [extent[0], extent[1]],
+            depth: extent[2],
+            mip_levels,
+            layers,
+            view_type,
+            size: memory_requirements.size,
+        })
+    }
+
+    /// the amount of mip levels needed to go down to 1x1
+    #[must_use]
+    pub fn max_mip_levels(extent: [u32; 2]) -> u32 {
+        32 - extent[0].max(extent[1]).max(1).leading_zeros()
+    }
+
+    /// tells if textures of this format can be sampled on this gpu
+    /// block compressed formats also need the matching device feature
+    #[must_use]
+    pub fn is_format_supported(device: &VulkanDevice, format: vk::Format) -> bool {
+        let props = unsafe {
+            device
+                .instance
+                .get_physical_device_format_properties(device.pdevice, format)
+        };
+
+        device.features.texture_compression.supports(format)
+            && props
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE)
+    }
+
+    /// how many bytes this texture would need when stored as uncompressed ``R8G8B8A8``
+    /// used to tell how much memory is saved by using compressed formats
+    #[must_use]
+    pub fn uncompressed_size(&self) -> u64 {
+        let layer: u64 = (0..self.mip_levels)
+            .map(|mip| {
+                let [width, height] = self.mip_extent(mip);
+                u64::from(width) * u64::from(height) * 4
+            })
+            .sum();
+        layer * u64::from(self.layers) * u64::from(self.depth)
+    }
+
+    /// tells if the mip levels of this format can be generated by blitting
+    #[must_use]
+    pub fn can_generate_mips(device: &VulkanDevice, format: vk::Format) -> bool {
+        let props = unsafe {
+            device
+                .instance
+                .get_physical_device_format_properties(device.pdevice, format)
+        };
+
+        props.optimal_tiling_features.contains(
+            vk::FormatFeatureFlags::BLIT_SRC
+                | vk::FormatFeatureFlags::BLIT_DST
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+        )
+    }
+
+    /// copy the given mip levels to the gpu using a staging buffer, starting with the biggest one
+    /// if there are less levels than ``mip_levels``, the rest is generated by blitting the last given level
+    /// the texture is in ``SHADER_READ_ONLY_OPTIMAL`` layout afterwards
+    ///
+    /// this blocks until the upload is done
+    /// generating mips needs ``TRANSFER_SRC`` usage and a format that supports ``can_generate_mips``
+    /// # Panics
+    /// if no level or more levels than ``mip_levels`` are given
+    /// # Errors
+    /// if there is no space left to allocate the staging buffer
+    pub fn upload(&self, levels: &[&[u8]]) -> VkResult<()> {
+        self.upload_layer(0, levels)
+    }
+
+    /// the same as ``upload``, but for a single layer of an array, the other layers are left as they are
+    /// frames in flight must not sample the layer, but can sample the others
+    /// # Panics
+    /// if the layer doesn't exist, no level or more levels than ``mip_levels`` are given
+    /// # Errors
+    /// if there is no space left to allocate the staging buffer
+    pub fn upload_layer(&self, layer: u32, levels: &[&[u8]]) -> VkResult<()> {
+        assert!(layer < self.layers, "the layer doesn't exist");
+        assert!(!levels.is_empty() && levels.len() <= self.mip_levels as usize);
+
+        let size: usize = levels.iter().map(|level| level.len()).sum();
+        let staging = Buffer::new(
+            self.device.clone(),
+            size as u64,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        let mut regions = Vec::with_capacity(levels.len());
+        let mut offset = 0;
+        for (mip, level) in levels.iter().enumerate() {
+            staging.write(offset, level);
+
+            let [width, height] = self.mip_extent(mip as u32);
+            regions.push(
+                vk::BufferImageCopy::default()
+                    .buffer_offset(offset as u64)
+                    .image_subresource(subresource_layers(mip as u32, layer))
+                    .image_extent(vk::Extent3D {
+                        width,
+                        height,
+                        // 3d textures only have the first level
+                        depth: self.depth,
+                    }),
+            );
+
+            offset += level.len();
+        }
+
+        let given = levels.len() as u32;
+
+        unsafe {
+            self.device.immediate_submit(|cmd| {
+                self.barrier(
+                    cmd,
+                    layer,
+                    0,
+                    self.mip_levels,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                );
+
+                self.device.cmd_copy_buffer_to_image(
+                    cmd,
+                    staging.handle(),
+                    self.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &regions,
+                );
+
+                // every level is blitted from the one before it
+                for mip in given..self.mip_levels {
+                    self.barrier(
+                        cmd,
+                        layer,
+                        mip - 1,
+                        1,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    );
+
+                    let offsets = |[width, height]: [u32; 2]| {
+                        [
+                            vk::Offset3D::default(),
+                            vk::Offset3D {
+                                x: width as i32,
+                                y: height as i32,
+                                z: 1,
+                            },
+                        ]
+                    };
+
+                    let blit = vk::ImageBlit::default()
+                        .src_subresource(subresource_layers(mip - 1, layer))
+                        .src_offsets(offsets(self.mip_extent(mip - 1)))
+                        .dst_subresource(subresource_layers(mip, layer))
+                        .dst_offsets(offsets(self.mip_extent(mip)));
+
+                    self.device.cmd_blit_image(
+                        cmd,
+                        self.image,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        self.image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &[blit],
+                        vk::Filter::LINEAR,
+                    );
+                }
+
+                // the levels used as blit source are in a different layout than the rest
+                let blit_sources = (given - 1)..(self.mip_levels - 1);
+
+                if !blit_sources.is_empty() {
+                    self.barrier(
+                        cmd,
+                        layer,
+                        blit_sources.start,
+                        blit_sources.len() as u32,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    );
+                }
+
+                for (start, count) in [
+                    (0, blit_sources.start),
+                    (blit_sources.end, self.mip_levels - blit_sources.end),
+                ] {
+                    if count > 0 {
+                        self.barrier(
+                            cmd,
+                            layer,
+                            start,
+                            count,
+                            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        );
+                    }
+                }
+            })
+        }
+    }
+
+    /// overwrite a part of the first mip level, the other levels are left as they are
+    /// the texture needs to be in ``SHADER_READ_ONLY_OPTIMAL`` layout, so it must have been uploaded before
+    ///
+    /// this blocks until the upload is done, previous submissions reading the texture are waited for on the gpu
+    /// # Panics
+    /// if the region is outside the texture or ``data`` doesn't have 4 bytes for every texel
+    /// # Errors
+    /// if there is no space left to allocate the staging buffer
+    pub fn upload_region(&self, offset: [u32; 2], extent: [u32; 2], data: &[u8]) -> VkResult<()> {
+        assert!(
+            offset[0] + extent[0] <= self.extent[0] && offset[1] + extent[1] <= self.extent[1],
+            "the region is outside the texture"
+        );
+        assert_eq!(data.len(), extent[0] as usize * extent[1] as usize * 4);
+
+        let staging = Buffer::new(
+            self.device.clone(),
+            data.len() as u64,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging.write(0, data);
+
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(subresource_layers(0, 0))
+            .image_offset(vk::Offset3D {
+                x: offset[0] as i32,
+                y: offset[1] as i32,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: extent[0],
+                height: extent[1],
+                depth: 1,
+            });
+
+        unsafe {
+            self.device.immediate_submit(|cmd| {
+                self.barrier(
+                    cmd,
+                    0,
+                    0,
+                    1,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                );
+
+                self.device.cmd_copy_buffer_to_image(
+                    cmd,
+                    staging.handle(),
+                    self.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[region],
+                );
+
+                self.barrier(
+                    cmd,
+                    0,
+                    0,
+                    1,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                );
+            })
+        }
+    }
+
+    /// copy a mip level back to the cpu, the rows are tightly packed
+    /// the texture needs ``TRANSFER_SRC`` usage and has to be in ``SHADER_READ_ONLY_OPTIMAL`` layout
+    ///
+    /// this blocks until the copy is done, meant for tests and screenshots
+    /// # Panics
+    /// if the mip level doesn't exist
+    /// # Errors
+    /// if there is no space left to allocate the staging buffer
+    pub fn download(&self, mip: u32) -> VkResult<Vec<u8>> {
+        assert!(mip < self.mip_levels, "the mip level doesn't exist");
+
+        let [width, height] = self.mip_extent(mip);
+        // only formats with 4 bytes per texel are used for color textures
+        let size = u64::from(width) * u64::from(height) * 4;
+
+        let staging = Buffer::new(
+            self.device.clone(),
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(subresource_layers(mip, 0))
+            .image_extent(vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            });
+
+        unsafe {
+            self.device.immediate_submit(|cmd| {
+                self.barrier(
+                    cmd,
+                    0,
+                    mip,
+                    1,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                );
+
+                self.device.cmd_copy_image_to_buffer(
+                    cmd,
+                    self.image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    staging.handle(),
+                    &[region],
+                );
+
+                self.barrier(
+                    cmd,
+                    0,
+                    mip,
+                    1,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                );
+            })?;
+        }
+
+        Ok(staging.read::<u8>()[..size as usize].to_vec())
+    }
+
+    /// the size of the given mip level in texels
+    #[must_use]
+    pub fn mip_extent(&self, mip: u32) -> [u32; 2] {
+        [
+            (self.extent[0] >> mip).max(1),
+            (self.extent[1] >> mip).max(1),
+        ]
+    }
+
+    unsafe fn barrier(
+        &self,
+        cmd: vk::CommandBuffer,
+        layer: u32,
+        base_mip: u32,
+        mip_count: u32,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) {
+        // the texture is only written by copies and blits and read by shaders and copies
+        // frames that are still in flight might be sampling it when the layout changes
+        let usage = |layout| match layout {
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL => (
+                vk::PipelineStageFlags2::COPY | vk::PipelineStageFlags2::BLIT,
+                vk::AccessFlags2::TRANSFER_WRITE,
+            ),
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL => (
+                vk::PipelineStageFlags2::COPY | vk::PipelineStageFlags2::BLIT,
+                vk::AccessFlags2::TRANSFER_READ,
+            ),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => (
+                vk::PipelineStageFlags2::VERTEX_SHADER
+                    | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                vk::AccessFlags2::SHADER_SAMPLED_READ,
+            ),
+            _ => (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE),
+        };
+
+        let (src_stage, src_access) = usage(old_layout);
+        let (dst_stage, dst_access) = usage(new_layout);
+
+        let barrier = vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(dst_stage)
+            .dst_access_mask(dst_access)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image)
+            .subresource_range(subresource_range(base_mip, mip_count, layer));
+
+        self.device.image_barrier(cmd, barrier);
+    }
+
+    #[must_use]
+    pub fn mem_ref(&self) -> &MemoryBlock {
+        &self.memory
+    }
+}
+
+fn subresource_range(base_mip: u32, mip_count: u32, layer: u32) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(base_mip)
+        .level_count(mip_count)
+        .base_array_layer(layer)
+        .layer_count(1)
+}
+
+fn subresource_layers(mip: u32, layer: u32) -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(mip)
+        .base_array_layer(layer)
+        .layer_count(1)
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_image_view(self.view, None);
+            self.device.destroy_image(self.image, None);
+        }
+    }
+}
@@ -1,8 +1,14 @@
+pub use adapter::*;
 pub use device::*;
-pub use swapchain::*;
+pub use gpu::*;
 pub use memory::*;
+pub use swapchain::*;
+pub use validation::ValidationCounts;
 
+mod adapter;
 mod device;
-mod swapchain;
+mod gpu;
 mod memory;
-
+mod swapchain;
+mod sync;
+mod validation;
@@ -8,6 +8,11 @@ pub struct SwapchainImage {
     pub main_image: vk::Image, // does not need to be destroyed manually
     pub main_view: vk::ImageView,
 
+    /// the batches are rendered in to this image, it's then tone mapped in to the main image
+    pub hdr_image: vk::Image,
+    pub hdr_memory: MemoryBlock,
+    pub hdr_view: vk::ImageView,
+
     pub depth_image: vk::Image,
     pub depth_memory: MemoryBlock,
     pub depth_view: vk::ImageView,
@@ -16,6 +21,38 @@ pub struct SwapchainImage {
     pub normal_memory: MemoryBlock,
     pub normal_view: vk::ImageView,
 
+    /// how far every pixel moved since the last frame, read by the temporal anti aliasing pass
+    pub velocity_image: vk::Image,
+    pub velocity_memory: MemoryBlock,
+    pub velocity_view: vk::ImageView,
+
+    /// weighted blended transparency is accumulated in to these two targets
+    /// and composited on to the hdr target after the main pass, see ``Transparency::WeightedBlended``
+    pub accum_image: vk::Image,
+    pub accum_memory: MemoryBlock,
+    pub accum_view: vk::ImageView,
+
+    pub revealage_image: vk::Image,
+    pub revealage_memory: MemoryBlock,
+    pub revealage_view: vk::ImageView,
+
+    /// the object id of every pixel, see ``MaterialCreateInfo::object_id``
+    /// only attached to the main pass if ``DeviceFeatures::object_ids`` is supported
+    pub object_id_image: vk::Image,
+    pub object_id_memory: MemoryBlock,
+    pub object_id_view: vk::ImageView,
+
+    /// only exists if msaa is enabled, the main pass resolves them in to the targets above
+    pub msaa: Option<MsaaTargets>,
+
+    /// the image the compute present shader writes instead of the swapchain image,
+    /// only exists with ``SwapchainStorage::Intermediate``
+    pub storage_target: Option<ImageTarget>,
+
+    /// the tone mapping and the outlines are drawn in to this image instead of the swapchain image,
+    /// it's then scaled on to the swapchain image, only exists with an internal resolution
+    pub upscale_target: Option<ImageTarget>,
+
     pub available: vk::Fence, // also does not need to be destroyed
 }
 
@@ -23,11 +60,236 @@ impl SwapchainImage {
     unsafe fn destroy(&self, device: &VulkanDevice) {
         device.destroy_image_view(self.main_view, None);
 
+        device.destroy_image_view(self.hdr_view, None);
+        device.destroy_image(self.hdr_image, None);
+
         device.destroy_image_view(self.depth_view, None);
         device.destroy_image(self.depth_image, None);
 
         device.destroy_image_view(self.normal_view, None);
         device.destroy_image(self.normal_image, None);
+
+        device.destroy_image_view(self.velocity_view, None);
+        device.destroy_image(self.velocity_image, None);
+
+        device.destroy_image_view(self.accum_view, None);
+        device.destroy_image(self.accum_image, None);
+
+        device.destroy_image_view(self.revealage_view, None);
+        device.destroy_image(self.revealage_image, None);
+
+        device.destroy_image_view(self.object_id_view, None);
+        device.destroy_image(self.object_id_image, None);
+
+        if let Some(msaa) = &self.msaa {
+            for target in msaa.targets() {
+                device.destroy_image_view(target.view, None);
+                device.destroy_image(target.image, None);
+            }
+        }
+
+        for target in [&self.storage_target, &self.upscale_target]
+            .into_iter()
+            .flatten()
+        {
+            device.destroy_image_view(target.view, None);
+            device.destroy_image(target.image, None);
+        }
+    }
+
+    /// the image the tone mapping writes, the swapchain image or the ``upscale_target``
+    #[must_use]
+    pub fn output_image(&self) -> vk::Image {
+        self.upscale_target
+            .as_ref()
+            .map_or(self.main_image, |target| target.image)
+    }
+
+    #[must_use]
+    pub fn output_view(&self) -> vk::ImageView {
+        self.upscale_target
+            .as_ref()
+            .map_or(self.main_view, |target| target.view)
+    }
+}
+
+/// an image the swapchain owns next to the swapchain image
+pub struct ImageTarget {
+    pub image: vk::Image,
+    pub memory: MemoryBlock,
+    pub view: vk::ImageView,
+}
+
+/// the multisampled hdr, normal, depth, velocity, transparency and object id targets
+pub struct MsaaTargets {
+    pub hdr: ImageTarget,
+    pub normal: ImageTarget,
+    pub depth: ImageTarget,
+    pub velocity: ImageTarget,
+    pub accum: ImageTarget,
+    pub revealage: ImageTarget,
+    pub object_id: ImageTarget,
+}
+
+impl MsaaTargets {
+    /// in the order they are attached to the main pass
+    pub fn targets(&self) -> [&ImageTarget; 7] {
+        [
+            &self.hdr,
+            &self.normal,
+            &self.depth,
+            &self.velocity,
+            &self.accum,
+            &self.revealage,
+            &self.object_id,
+        ]
+    }
+}
+
+/// how finished frames are queued for presentation
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PresentMode {
+    /// wait for the vertical blank, caps the frame rate to the refresh rate (vsync)
+    Fifo,
+    /// replace the queued frame with newer ones, no tearing without capping the frame rate
+    /// falls back to ``Fifo`` if it isn't supported
+    #[default]
+    Mailbox,
+    /// present right away, might tear
+    /// falls back to ``Fifo`` if it isn't supported
+    Immediate,
+}
+
+impl From<PresentMode> for vk::PresentModeKHR {
+    fn from(value: PresentMode) -> Self {
+        match value {
+            PresentMode::Fifo => Self::FIFO,
+            PresentMode::Mailbox => Self::MAILBOX,
+            PresentMode::Immediate => Self::IMMEDIATE,
+        }
+    }
+}
+
+/// how the compute present shader writes the swapchain images, see ``RendererConfig::compute_present``
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SwapchainStorage {
+    /// the swapchain images can't be written by compute shaders
+    #[default]
+    None,
+    /// the swapchain images have storage usage and are written directly
+    Direct,
+    /// the surface doesn't allow storage usage for any of its formats,
+    /// the shader writes an image of ``STORAGE_TARGET_FORMAT`` that is blitted on to the swapchain image
+    Intermediate,
+}
+
+/// the format of the image compute shaders write if the swapchain images can't be storage images
+/// every gpu supports it for storage and blits
+pub const STORAGE_TARGET_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+/// the format of the intermediate target that is rendered to before tone mapping
+pub const HDR_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// the format of the velocity target
+/// xy is the movement since the last frame in uv units, z is 1 where temporal anti aliasing is applied
+pub const VELOCITY_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// the format of the target weighted blended transparency sums up the weighted colors in
+pub const OIT_ACCUM_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// the format of the target that tells how much of the background is still visible after the transparent surfaces
+pub const OIT_REVEALAGE_FORMAT: vk::Format = vk::Format::R16_SFLOAT;
+
+/// the format of the target the materials write the object id of their draw to, 0 is nothing
+pub const OBJECT_ID_FORMAT: vk::Format = vk::Format::R32_UINT;
+
+/// the format of the target the materials write their world space normals to
+pub const NORMAL_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+
+/// the format of the depth target, it's the depth the materials write as a color and not a depth buffer,
+/// the post processing passes read it as a storage image, which depth formats can't be
+pub const DEPTH_FORMAT: vk::Format = vk::Format::R32_SFLOAT;
+
+/// the normal and depth targets are written by the main pass, read by the post processing passes
+/// and can be sampled by passes that need filtering, ``RenderHandler::dump_image`` copies them out
+const GBUFFER_USAGE: vk::ImageUsageFlags = vk::ImageUsageFlags::from_raw(
+    vk::ImageUsageFlags::COLOR_ATTACHMENT.as_raw()
+        | vk::ImageUsageFlags::STORAGE.as_raw()
+        | vk::ImageUsageFlags::SAMPLED.as_raw()
+        | vk::ImageUsageFlags::TRANSFER_SRC.as_raw(),
+);
+
+/// how a render target is created, see ``create_texture``
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetDesc {
+    pub format: vk::Format,
+    pub usage: vk::ImageUsageFlags,
+    /// the aspect of the view
+    pub aspect: vk::ImageAspectFlags,
+    pub samples: vk::SampleCountFlags,
+}
+
+impl TargetDesc {
+    #[must_use]
+    pub fn color(format: vk::Format, usage: vk::ImageUsageFlags) -> Self {
+        Self {
+            format,
+            usage,
+            aspect: vk::ImageAspectFlags::COLOR,
+            samples: vk::SampleCountFlags::TYPE_1,
+        }
+    }
+
+    /// a depth buffer, ``DEPTH_STENCIL_ATTACHMENT`` is added to the usage
+    #[must_use]
+    pub fn depth(format: vk::Format, usage: vk::ImageUsageFlags) -> Self {
+        Self {
+            format,
+            usage: usage | vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            aspect: vk::ImageAspectFlags::DEPTH,
+            samples: vk::SampleCountFlags::TYPE_1,
+        }
+    }
+
+    #[must_use]
+    pub fn samples(self, samples: vk::SampleCountFlags) -> Self {
+        Self { samples, ..self }
+    }
+
+    /// the format features the usage needs with optimal tiling
+    #[must_use]
+    pub fn required_features(&self) -> vk::FormatFeatureFlags {
+        [
+            (
+                vk::ImageUsageFlags::COLOR_ATTACHMENT,
+                vk::FormatFeatureFlags::COLOR_ATTACHMENT,
+            ),
+            (
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            ),
+            (
+                vk::ImageUsageFlags::STORAGE,
+                vk::FormatFeatureFlags::STORAGE_IMAGE,
+            ),
+            (
+                vk::ImageUsageFlags::SAMPLED,
+                vk::FormatFeatureFlags::SAMPLED_IMAGE,
+            ),
+            (
+                vk::ImageUsageFlags::TRANSFER_SRC,
+                vk::FormatFeatureFlags::TRANSFER_SRC,
+            ),
+            (
+                vk::ImageUsageFlags::TRANSFER_DST,
+                vk::FormatFeatureFlags::TRANSFER_DST,
+            ),
+        ]
+        .into_iter()
+        .filter(|(usage, _)| self.usage.contains(*usage))
+        .fold(vk::FormatFeatureFlags::empty(), |features, (_, feature)| {
+            features | feature
+        })
     }
 }
 
@@ -35,29 +297,65 @@ pub struct Swapchain {
     device: Arc<VulkanDevice>,
     pub handle: vk::SwapchainKHR,
     pub loader: ash::khr::swapchain::Device,
+    /// only exists if ``VK_KHR_present_wait`` is supported
+    pub present_wait: Option<ash::khr::present_wait::Device>,
     pub images: Vec<SwapchainImage>,
     pub create_info: vk::SwapchainCreateInfoKHR<'static>,
+    /// the sample count of the main pass targets
+    pub samples: vk::SampleCountFlags,
+    pub storage: SwapchainStorage,
+    /// the size the targets are rendered at instead of the surface size, see ``RendererConfig::internal_resolution``
+    pub internal_resolution: Option<[u32; 2]>,
 }
 
 impl Swapchain {
+    /// ``storage`` lets the swapchain images be written by compute shaders,
+    /// directly if the surface allows it or through an intermediate image, see ``Swapchain::storage``
+    /// with an ``internal_resolution`` the targets keep that size and are scaled on to the swapchain images
     /// # Safety
     /// # Errors
-    pub unsafe fn new(device: Arc<VulkanDevice>, image_extent: [u32; 2]) -> VkResult<Self> {
+    pub unsafe fn new(
+        device: Arc<VulkanDevice>,
+        image_extent: [u32; 2],
+        present_mode: PresentMode,
+        samples: vk::SampleCountFlags,
+        storage: bool,
+        internal_resolution: Option<[u32; 2]>,
+    ) -> VkResult<Self> {
         let surface_capabilities = device
             .surface_loader
             .get_physical_device_surface_capabilities(device.pdevice, device.surface)?;
 
-        let surface_format = device
+        let surface_formats = device
             .surface_loader
-            .get_physical_device_surface_formats(device.pdevice, device.surface)?[0];
+            .get_physical_device_surface_formats(device.pdevice, device.surface)?;
 
-        let surface_resolution = match surface_capabilities.current_extent.width {
-            u32::MAX => vk::Extent2D {
-                width: image_extent[0],
-                height: image_extent[1],
+        let (surface_format, storage) = negotiate_storage(
+            &surface_formats,
+            storage,
+            surface_capabilities.supported_usage_flags,
+            |format| {
+                device
+                    .instance
+                    .get_physical_device_format_properties(device.pdevice, format)
+                    .optimal_tiling_features
+                    .contains(vk::FormatFeatureFlags::STORAGE_IMAGE)
             },
-            _ => surface_capabilities.current_extent,
-        };
+        );
+        if storage == SwapchainStorage::Intermediate {
+            log::warn!(
+                "the surface doesn't support storage images, compute present writes an intermediate image"
+            );
+        }
+
+        // a window that starts minimized still needs a swapchain, it's recreated once the window is restored
+        let window_size = image_extent.map(|v| v.max(1));
+        let surface_resolution =
+            surface_extent(&surface_capabilities, window_size).unwrap_or(vk::Extent2D {
+                width: surface_capabilities.min_image_extent.width.max(1),
+                height: surface_capabilities.min_image_extent.height.max(1),
+            });
+        let image_extent = [surface_resolution.width, surface_resolution.height];
 
         let pre_transform = if surface_capabilities
             .supported_transforms
@@ -72,10 +370,11 @@ impl Swapchain {
             .surface_loader
             .get_physical_device_surface_present_modes(device.pdevice, device.surface)?;
 
+        // fifo is always supported
         let present_mode = present_modes
             .iter()
             .copied()
-            .find(|&mode| mode == vk::PresentModeKHR::MAILBOX)
+            .find(|&mode| mode == present_mode.into())
             .unwrap_or(vk::PresentModeKHR::FIFO);
 
         let mut desired_image_count = surface_capabilities.min_image_count.max(3);
@@ -85,13 +384,20 @@ impl Swapchain {
             desired_image_count = surface_capabilities.max_image_count;
         };
 
+        // transfer is needed to blit the hdr image if there is no tonemap shader
+        let mut image_usage =
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST;
+        if storage == SwapchainStorage::Direct {
+            image_usage |= vk::ImageUsageFlags::STORAGE;
+        }
+
         let swapchain_create_info = vk::SwapchainCreateInfoKHR::default()
             .surface(device.surface)
             .min_image_count(desired_image_count)
             .image_color_space(surface_format.color_space)
             .image_format(surface_format.format)
             .image_extent(surface_resolution)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_usage(image_usage)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
             .pre_transform(pre_transform)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
@@ -103,33 +409,51 @@ impl Swapchain {
 
         let swapchain = swapchain_loader.create_swapchain(&swapchain_create_info, None)?;
 
+        let present_wait = device
+            .features
+            .present_wait
+            .then(|| ash::khr::present_wait::Device::new(&device.instance, &device));
+
+        let internal_resolution = internal_resolution.map(|size| size.map(|v| v.max(1)));
         let images = Self::create_swapchain_images(
             device.clone(),
             &swapchain_loader,
             swapchain,
             surface_format.format,
-            image_extent,
+            internal_resolution.unwrap_or(image_extent),
+            samples,
+            storage,
+            internal_resolution.is_some(),
         )?;
 
         Ok(Self {
             device,
             handle: swapchain,
             loader: swapchain_loader,
+            present_wait,
             create_info: swapchain_create_info,
             images,
+            samples,
+            storage,
+            internal_resolution,
         })
     }
 
+    /// ``image_extent`` is the size of the targets, the swapchain images have the size of the surface
+    #[allow(clippy::too_many_arguments)]
     unsafe fn create_swapchain_images(
         device: Arc<VulkanDevice>,
         swapchain_loader: &ash::khr::swapchain::Device,
         swapchain: vk::SwapchainKHR,
         format: vk::Format,
         image_extent: [u32; 2],
+        samples: vk::SampleCountFlags,
+        storage: SwapchainStorage,
+        upscale: bool,
     ) -> VkResult<Vec<SwapchainImage>> {
         let swapchain_images = swapchain_loader.get_swapchain_images(swapchain)?;
 
-        Ok(swapchain_images
+        let images: Vec<SwapchainImage> = swapchain_images
             .iter()
             .map(|&main_image| {
                 let components = vk::ComponentMapping::default()
@@ -154,27 +478,231 @@ impl Swapchain {
 
                 let main_view = device.create_image_view(&info, None).unwrap();
 
-                let (normal_memory, normal_image, normal_view) =
-                    create_texture(&device, image_extent, vk::Format::R32G32B32A32_SFLOAT).unwrap();
+                let (hdr_memory, hdr_image, hdr_view) = create_texture(
+                    &device,
+                    image_extent,
+                    TargetDesc::color(
+                        HDR_FORMAT,
+                        vk::ImageUsageFlags::COLOR_ATTACHMENT
+                            | vk::ImageUsageFlags::STORAGE
+                            | vk::ImageUsageFlags::TRANSFER_SRC
+                            | vk::ImageUsageFlags::TRANSFER_DST,
+                    ),
+                )
+                .unwrap();
+
+                let (normal_memory, normal_image, normal_view) = create_texture(
+                    &device,
+                    image_extent,
+                    TargetDesc::color(NORMAL_FORMAT, GBUFFER_USAGE),
+                )
+                .unwrap();
+
+                let (depth_memory, depth_image, depth_view) = create_texture(
+                    &device,
+                    image_extent,
+                    TargetDesc::color(DEPTH_FORMAT, GBUFFER_USAGE),
+                )
+                .unwrap();
+
+                let (velocity_memory, velocity_image, velocity_view) = create_texture(
+                    &device,
+                    image_extent,
+                    TargetDesc::color(
+                        VELOCITY_FORMAT,
+                        vk::ImageUsageFlags::COLOR_ATTACHMENT
+                            | vk::ImageUsageFlags::STORAGE
+                            | vk::ImageUsageFlags::TRANSFER_SRC,
+                    ),
+                )
+                .unwrap();
+
+                // the resolve pass only reads them if the formats support it
+                let oit_usage = if device.features.weighted_blended_oit {
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT
+                        | vk::ImageUsageFlags::STORAGE
+                        | vk::ImageUsageFlags::TRANSFER_SRC
+                } else {
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC
+                };
+
+                let (accum_memory, accum_image, accum_view) = create_texture(
+                    &device,
+                    image_extent,
+                    TargetDesc::color(OIT_ACCUM_FORMAT, oit_usage),
+                )
+                .unwrap();
+
+                let (revealage_memory, revealage_image, revealage_view) = create_texture(
+                    &device,
+                    image_extent,
+                    TargetDesc::color(OIT_REVEALAGE_FORMAT, oit_usage),
+                )
+                .unwrap();
+
+                // the picked pixel is copied out of it and the outline pass reads it as a storage image
+                let (object_id_memory, object_id_image, object_id_view) = create_texture(
+                    &device,
+                    image_extent,
+                    TargetDesc::color(
+                        OBJECT_ID_FORMAT,
+                        vk::ImageUsageFlags::COLOR_ATTACHMENT
+                            | vk::ImageUsageFlags::TRANSFER_SRC
+                            | vk::ImageUsageFlags::STORAGE,
+                    ),
+                )
+                .unwrap();
+
+                let msaa = (samples != vk::SampleCountFlags::TYPE_1).then(|| {
+                    let target = |format| {
+                        let (memory, image, view) = create_texture(
+                            &device,
+                            image_extent,
+                            TargetDesc::color(format, vk::ImageUsageFlags::COLOR_ATTACHMENT)
+                                .samples(samples),
+                        )
+                        .unwrap();
+                        ImageTarget {
+                            image,
+                            memory,
+                            view,
+                        }
+                    };
+
+                    MsaaTargets {
+                        hdr: target(HDR_FORMAT),
+                        normal: target(NORMAL_FORMAT),
+                        depth: target(DEPTH_FORMAT),
+                        velocity: target(VELOCITY_FORMAT),
+                        accum: target(OIT_ACCUM_FORMAT),
+                        revealage: target(OIT_REVEALAGE_FORMAT),
+                        object_id: target(OBJECT_ID_FORMAT),
+                    }
+                });
+
+                let storage_target = (storage == SwapchainStorage::Intermediate).then(|| {
+                    let (memory, image, view) = create_texture(
+                        &device,
+                        image_extent,
+                        TargetDesc::color(
+                            STORAGE_TARGET_FORMAT,
+                            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC,
+                        ),
+                    )
+                    .unwrap();
+                    ImageTarget {
+                        image,
+                        memory,
+                        view,
+                    }
+                });
 
-                let (depth_memory, depth_image, depth_view) =
-                    create_texture(&device, image_extent, vk::Format::R32_SFLOAT).unwrap();
+                // transfer dst for the tone mapping blit without a shader, transfer src for the upscale
+                let upscale_target = upscale.then(|| {
+                    let (memory, image, view) = create_texture(
+                        &device,
+                        image_extent,
+                        TargetDesc::color(
+                            format,
+                            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                                | vk::ImageUsageFlags::TRANSFER_SRC
+                                | vk::ImageUsageFlags::TRANSFER_DST,
+                        ),
+                    )
+                    .unwrap();
+                    ImageTarget {
+                        image,
+                        memory,
+                        view,
+                    }
+                });
 
                 SwapchainImage {
                     main_image,
                     main_view,
+                    hdr_image,
+                    hdr_memory,
+                    hdr_view,
                     depth_image,
                     depth_memory,
                     depth_view,
                     normal_image,
                     normal_memory,
                     normal_view,
+                    velocity_image,
+                    velocity_memory,
+                    velocity_view,
+                    accum_image,
+                    accum_memory,
+                    accum_view,
+                    revealage_image,
+                    revealage_memory,
+                    revealage_view,
+                    object_id_image,
+                    object_id_memory,
+                    object_id_view,
+                    msaa,
+                    storage_target,
+                    upscale_target,
                     available: vk::Fence::null(),
                 }
             })
-            .collect())
+            .collect();
+
+        // put the targets in the layout the main render pass leaves them in,
+        // so they can be loaded if the previous contents are kept
+        let range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+
+        let barriers: Vec<_> = images
+            .iter()
+            .flat_map(|image| {
+                [
+                    (image.hdr_image, vk::ImageLayout::GENERAL),
+                    (image.normal_image, vk::ImageLayout::GENERAL),
+                    (image.depth_image, vk::ImageLayout::GENERAL),
+                    (image.velocity_image, vk::ImageLayout::GENERAL),
+                    (image.accum_image, vk::ImageLayout::GENERAL),
+                    (image.revealage_image, vk::ImageLayout::GENERAL),
+                    (image.object_id_image, vk::ImageLayout::GENERAL),
+                ]
+                .into_iter()
+                .chain(image.msaa.iter().flat_map(|msaa| {
+                    msaa.targets()
+                        .map(|target| (target.image, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL))
+                }))
+                .chain(
+                    image
+                        .storage_target
+                        .iter()
+                        .map(|target| (target.image, vk::ImageLayout::GENERAL)),
+                )
+            })
+            .map(|(image, layout)| {
+                vk::ImageMemoryBarrier2::default()
+                    .image(image)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(layout)
+                    .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                    .dst_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                    .subresource_range(range)
+            })
+            .collect();
+
+        device.immediate_submit(|cmd| {
+            device.pipeline_barrier(
+                cmd,
+                &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+            );
+        })?;
+
+        Ok(images)
     }
 
+    /// the extent is clamped to what the surface supports
+    /// returns false and keeps the old swapchain if the surface has no area, for example while the window is minimized
     /// # Safety
     /// there must not currently be written on to one of the swapchain images
     /// the pointer to the swapchain handle is now invalid
@@ -185,11 +713,17 @@ impl Swapchain {
         &mut self,
         device: Arc<VulkanDevice>,
         new_extent: [u32; 2],
-    ) -> VkResult<()> {
-        let image_extent = vk::Extent2D {
-            width: new_extent[0],
-            height: new_extent[1],
+    ) -> VkResult<bool> {
+        let surface_capabilities = device
+            .surface_loader
+            .get_physical_device_surface_capabilities(device.pdevice, device.surface)?;
+
+        let Some(image_extent) = surface_extent(&surface_capabilities, new_extent) else {
+            return Ok(false);
         };
+        let new_extent = self
+            .internal_resolution
+            .unwrap_or([image_extent.width, image_extent.height]);
 
         self.create_info.image_extent = image_extent;
 
@@ -213,50 +747,209 @@ impl Swapchain {
             self.handle,
             create_info.image_format,
             new_extent,
+            self.samples,
+            self.storage,
+            self.internal_resolution.is_some(),
         )?;
 
-        Ok(())
+        Ok(true)
     }
 
     pub fn image_format(&self) -> vk::Format {
         self.create_info.image_format
     }
 
+    /// tells if the gpu converts the colors to srgb when writing to the swapchain
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self.image_format(),
+            vk::Format::B8G8R8A8_SRGB
+                | vk::Format::R8G8B8A8_SRGB
+                | vk::Format::A8B8G8R8_SRGB_PACK32
+        )
+    }
+
+    /// the size every target is rendered at, the internal resolution if there is one
     pub fn get_image_extent(&self) -> vk::Extent2D {
+        self.internal_resolution
+            .map_or(self.create_info.image_extent, |[width, height]| {
+                vk::Extent2D { width, height }
+            })
+    }
+
+    /// the size of the swapchain images, the ui is drawn at this size
+    pub fn present_extent(&self) -> vk::Extent2D {
         self.create_info.image_extent
     }
+
+    /// the layout the tone mapping and the outlines leave ``SwapchainImage::output_image`` in
+    pub fn output_layout(&self) -> vk::ImageLayout {
+        if self.internal_resolution.is_some() {
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        } else {
+            vk::ImageLayout::PRESENT_SRC_KHR
+        }
+    }
+
+    /// where the internal resolution is drawn on the swapchain image, None without one
+    pub fn letterbox(&self) -> Option<vk::Rect2D> {
+        let internal = self.internal_resolution?;
+        Some(letterbox(internal, self.present_extent()))
+    }
+
+    /// destroy the swapchain before it's dropped, so a new one can be created for the same surface
+    /// # Safety
+    /// the images must not be used anymore
+    pub unsafe fn destroy(&mut self) {
+        for image in self.images.drain(..) {
+            image.destroy(&self.device);
+        }
+
+        if self.handle != vk::SwapchainKHR::null() {
+            self.loader.destroy_swapchain(self.handle, None);
+            self.handle = vk::SwapchainKHR::null();
+        }
+    }
 }
 
 impl Drop for Swapchain {
     fn drop(&mut self) {
-        unsafe {
-            for image in &self.images {
-                image.destroy(&self.device);
-            }
+        unsafe { self.destroy() };
+    }
+}
 
-            self.loader.destroy_swapchain(self.handle, None);
+/// the extent a swapchain for the window size can have, clamped to the limits of the surface
+/// ``None`` if it would have no area, a swapchain can't be created then
+/// most platforms report the window size as ``current_extent``, it's 0 while the window is minimized on windows
+#[must_use]
+pub fn surface_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    window_size: [u32; 2],
+) -> Option<vk::Extent2D> {
+    // the surface takes the size of the swapchain, for example on wayland
+    let extent = if capabilities.current_extent.width == u32::MAX {
+        let min = capabilities.min_image_extent;
+        let max = capabilities.max_image_extent;
+        vk::Extent2D {
+            width: window_size[0].clamp(min.width, max.width.max(min.width)),
+            height: window_size[1].clamp(min.height, max.height.max(min.height)),
+        }
+    } else {
+        capabilities.current_extent
+    };
+
+    if extent.width == 0 || extent.height == 0 || window_size.contains(&0) {
+        return None;
+    }
+
+    Some(extent)
+}
+
+/// the biggest rect with the aspect ratio of ``size`` that fits in to ``target``, centered in it
+/// the bars on both sides are at most a pixel apart
+pub fn letterbox(size: [u32; 2], target: vk::Extent2D) -> vk::Rect2D {
+    let [width, height] = size.map(|v| u64::from(v.max(1)));
+    let (target_width, target_height) = (u64::from(target.width), u64::from(target.height));
+
+    // compared in integers so an exact fit doesn't get a bar from rounding
+    let extent = if target_width * height <= target_height * width {
+        vk::Extent2D {
+            width: target.width,
+            height: ((target_width * height + width / 2) / width).max(1) as u32,
+        }
+    } else {
+        vk::Extent2D {
+            width: ((target_height * width + height / 2) / height).max(1) as u32,
+            height: target.height,
         }
+    };
+
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: ((target.width - extent.width) / 2) as i32,
+            y: ((target.height - extent.height) / 2) as i32,
+        },
+        extent,
+    }
+}
+
+/// the surface format and how compute shaders can write the images, formats[0] is used if ``storage`` isn't requested
+/// prefers the first format that supports storage images if the surface allows storage usage,
+/// falls back to an intermediate image otherwise
+/// # Panics
+/// if ``formats`` is empty
+pub fn negotiate_storage(
+    formats: &[vk::SurfaceFormatKHR],
+    storage: bool,
+    supported_usage: vk::ImageUsageFlags,
+    supports_storage: impl Fn(vk::Format) -> bool,
+) -> (vk::SurfaceFormatKHR, SwapchainStorage) {
+    if !storage {
+        return (formats[0], SwapchainStorage::None);
+    }
+
+    let direct = supported_usage
+        .contains(vk::ImageUsageFlags::STORAGE)
+        .then(|| {
+            formats
+                .iter()
+                .find(|format| supports_storage(format.format))
+        })
+        .flatten();
+
+    match direct {
+        Some(&format) => (format, SwapchainStorage::Direct),
+        None => (formats[0], SwapchainStorage::Intermediate),
     }
 }
 
-unsafe fn create_texture(
+/// # Errors
+/// ``ERROR_FORMAT_NOT_SUPPORTED`` if the format doesn't support the usage, see ``TargetDesc::required_features``
+pub(crate) unsafe fn create_texture(
     device: &Arc<VulkanDevice>,
     image_extent: [u32; 2],
-    format: vk::Format,
+    desc: TargetDesc,
 ) -> VkResult<(MemoryBlock, vk::Image, vk::ImageView)> {
+    create_layered_texture(device, image_extent, desc, 1)
+}
+
+/// the view is an array view if there is more than one layer, for multiview render passes
+/// # Errors
+/// ``ERROR_FORMAT_NOT_SUPPORTED`` if the format doesn't support the usage, see ``TargetDesc::required_features``
+pub(crate) unsafe fn create_layered_texture(
+    device: &Arc<VulkanDevice>,
+    image_extent: [u32; 2],
+    desc: TargetDesc,
+    layers: u32,
+) -> VkResult<(MemoryBlock, vk::Image, vk::ImageView)> {
+    // the validation layers only report this when the image is used
+    let supported = device
+        .instance
+        .get_physical_device_format_properties(device.pdevice, desc.format)
+        .optimal_tiling_features;
+    let missing = desc.required_features() & !supported;
+    if !missing.is_empty() {
+        log::error!(
+            "{:?} can't be used as {:?}, it doesn't support {missing:?}",
+            desc.format,
+            desc.usage
+        );
+        return Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+    }
+
     let image_info = vk::ImageCreateInfo::default()
         .image_type(vk::ImageType::TYPE_2D)
-        .format(format)
+        .format(desc.format)
         .extent(vk::Extent3D {
             width: image_extent[0],
             height: image_extent[1],
             depth: 1,
         })
         .mip_levels(1)
-        .array_layers(1)
-        .samples(vk::SampleCountFlags::TYPE_1)
+        .array_layers(layers)
+        .samples(desc.samples)
         .tiling(vk::ImageTiling::OPTIMAL)
-        .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT);
+        .usage(desc.usage);
 
     let image = device.create_image(&image_info, None)?;
 
@@ -270,19 +963,129 @@ unsafe fn create_texture(
     device.bind_image_memory(image, memory.handle(), 0)?;
 
     let subresource = vk::ImageSubresourceRange::default()
-        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .aspect_mask(desc.aspect)
         .base_mip_level(0)
         .level_count(1)
         .base_array_layer(0)
-        .layer_count(1);
+        .layer_count(layers);
+
+    let view_type = if layers > 1 {
+        vk::ImageViewType::TYPE_2D_ARRAY
+    } else {
+        vk::ImageViewType::TYPE_2D
+    };
 
     let view_info = vk::ImageViewCreateInfo::default()
         .image(image)
-        .view_type(vk::ImageViewType::TYPE_2D)
-        .format(format)
+        .view_type(view_type)
+        .format(desc.format)
         .subresource_range(subresource);
 
     let view = device.create_image_view(&view_info, None)?;
 
     Ok((memory, image, view))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [vk::SurfaceFormatKHR; 2] = [
+        vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        },
+        vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_UNORM,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        },
+    ];
+
+    fn unorm_only(format: vk::Format) -> bool {
+        format == vk::Format::B8G8R8A8_UNORM
+    }
+
+    #[test]
+    fn storage_picks_a_format_that_supports_it() {
+        let usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::STORAGE;
+
+        let (format, storage) = negotiate_storage(&FORMATS, true, usage, unorm_only);
+        assert_eq!(format.format, vk::Format::B8G8R8A8_UNORM);
+        assert_eq!(storage, SwapchainStorage::Direct);
+
+        let (format, storage) = negotiate_storage(&FORMATS, false, usage, unorm_only);
+        assert_eq!(format.format, vk::Format::B8G8R8A8_SRGB);
+        assert_eq!(storage, SwapchainStorage::None);
+    }
+
+    #[test]
+    fn storage_falls_back_to_an_intermediate_image() {
+        // the surface doesn't allow storage usage
+        let (format, storage) = negotiate_storage(
+            &FORMATS,
+            true,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            unorm_only,
+        );
+        assert_eq!(format.format, vk::Format::B8G8R8A8_SRGB);
+        assert_eq!(storage, SwapchainStorage::Intermediate);
+
+        // none of the formats supports storage images
+        let (_, storage) =
+            negotiate_storage(&FORMATS, true, vk::ImageUsageFlags::STORAGE, |_| false);
+        assert_eq!(storage, SwapchainStorage::Intermediate);
+    }
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> vk::Rect2D {
+        vk::Rect2D {
+            offset: vk::Offset2D { x, y },
+            extent: vk::Extent2D { width, height },
+        }
+    }
+
+    #[test]
+    fn letterbox_keeps_the_aspect_ratio() {
+        let window = |width, height| vk::Extent2D { width, height };
+
+        // an exact fit has no bars
+        assert_eq!(
+            letterbox([480, 270], window(1920, 1080)),
+            rect(0, 0, 1920, 1080)
+        );
+        // bars on the sides of a wider window
+        assert_eq!(
+            letterbox([1920, 1080], window(2560, 1080)),
+            rect(320, 0, 1920, 1080)
+        );
+        // bars on the top and bottom of a taller window
+        assert_eq!(
+            letterbox([1920, 1080], window(1280, 1024)),
+            rect(0, 152, 1280, 720)
+        );
+        // the pixel in between is rounded
+        assert_eq!(letterbox([4, 3], window(100, 100)), rect(0, 12, 100, 75));
+    }
+
+    #[test]
+    fn usage_needs_format_features() {
+        let gbuffer = TargetDesc::color(DEPTH_FORMAT, GBUFFER_USAGE);
+        assert_eq!(gbuffer.aspect, vk::ImageAspectFlags::COLOR);
+        assert_eq!(
+            gbuffer.required_features(),
+            vk::FormatFeatureFlags::COLOR_ATTACHMENT
+                | vk::FormatFeatureFlags::STORAGE_IMAGE
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE
+                | vk::FormatFeatureFlags::TRANSFER_SRC
+        );
+
+        let depth = TargetDesc::depth(vk::Format::D32_SFLOAT, vk::ImageUsageFlags::SAMPLED)
+            .samples(vk::SampleCountFlags::TYPE_4);
+        assert_eq!(depth.aspect, vk::ImageAspectFlags::DEPTH);
+        assert_eq!(depth.samples, vk::SampleCountFlags::TYPE_4);
+        assert_eq!(
+            depth.required_features(),
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE
+        );
+    }
+}
@@ -0,0 +1,298 @@
+// barriers, timestamps and submits use the synchronization2 versions of the commands
+// gpus without synchronization2 get the same dependencies through the old commands,
+// the stage and access bits below 32 are the same in both versions, the others are mapped to the old bits that cover them
+//
+// synchronization2 is only used through vulkan 1.3, the functions of the KHR extension aren't loaded
+
+use ash::{prelude::VkResult, vk};
+
+use super::VulkanDevice;
+
+impl VulkanDevice {
+    /// ``cmd_pipeline_barrier2`` or the same barriers with ``cmd_pipeline_barrier``
+    /// the old command has a single stage mask for all barriers, so the stages of all of them are combined
+    /// # Safety
+    /// the command buffer must be recording
+    pub unsafe fn pipeline_barrier(&self, cmd: vk::CommandBuffer, dependency: &vk::DependencyInfo) {
+        if self.features.synchronization2 {
+            self.cmd_pipeline_barrier2(cmd, dependency);
+            return;
+        }
+
+        let memory = slice(
+            dependency.p_memory_barriers,
+            dependency.memory_barrier_count,
+        );
+        let buffers = slice(
+            dependency.p_buffer_memory_barriers,
+            dependency.buffer_memory_barrier_count,
+        );
+        let images = slice(
+            dependency.p_image_memory_barriers,
+            dependency.image_memory_barrier_count,
+        );
+
+        let mut src_stage = vk::PipelineStageFlags2::NONE;
+        let mut dst_stage = vk::PipelineStageFlags2::NONE;
+        let mut add_stages = |src, dst| {
+            src_stage |= src;
+            dst_stage |= dst;
+        };
+
+        let memory: Vec<_> = memory
+            .iter()
+            .map(|barrier| {
+                add_stages(barrier.src_stage_mask, barrier.dst_stage_mask);
+                vk::MemoryBarrier::default()
+                    .src_access_mask(legacy_access(barrier.src_access_mask))
+                    .dst_access_mask(legacy_access(barrier.dst_access_mask))
+            })
+            .collect();
+
+        let buffers: Vec<_> = buffers
+            .iter()
+            .map(|barrier| {
+                add_stages(barrier.src_stage_mask, barrier.dst_stage_mask);
+                vk::BufferMemoryBarrier::default()
+                    .src_access_mask(legacy_access(barrier.src_access_mask))
+                    .dst_access_mask(legacy_access(barrier.dst_access_mask))
+                    .src_queue_family_index(barrier.src_queue_family_index)
+                    .dst_queue_family_index(barrier.dst_queue_family_index)
+                    .buffer(barrier.buffer)
+                    .offset(barrier.offset)
+                    .size(barrier.size)
+            })
+            .collect();
+
+        let images: Vec<_> = images
+            .iter()
+            .map(|barrier| {
+                add_stages(barrier.src_stage_mask, barrier.dst_stage_mask);
+                vk::ImageMemoryBarrier::default()
+                    .src_access_mask(legacy_access(barrier.src_access_mask))
+                    .dst_access_mask(legacy_access(barrier.dst_access_mask))
+                    .old_layout(barrier.old_layout)
+                    .new_layout(barrier.new_layout)
+                    .src_queue_family_index(barrier.src_queue_family_index)
+                    .dst_queue_family_index(barrier.dst_queue_family_index)
+                    .image(barrier.image)
+                    .subresource_range(barrier.subresource_range)
+            })
+            .collect();
+
+        self.cmd_pipeline_barrier(
+            cmd,
+            legacy_stages(src_stage, vk::PipelineStageFlags::TOP_OF_PIPE),
+            legacy_stages(dst_stage, vk::PipelineStageFlags::BOTTOM_OF_PIPE),
+            dependency.dependency_flags,
+            &memory,
+            &buffers,
+            &images,
+        );
+    }
+
+    /// shorthand for a single image barrier
+    /// # Safety
+    /// the command buffer must be recording
+    pub unsafe fn image_barrier(&self, cmd: vk::CommandBuffer, barrier: vk::ImageMemoryBarrier2) {
+        let barriers = [barrier];
+        self.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+        );
+    }
+
+    /// shorthand for a single global memory barrier
+    /// # Safety
+    /// the command buffer must be recording
+    pub unsafe fn memory_barrier(&self, cmd: vk::CommandBuffer, barrier: vk::MemoryBarrier2) {
+        let barriers = [barrier];
+        self.pipeline_barrier(
+            cmd,
+            &vk::DependencyInfo::default().memory_barriers(&barriers),
+        );
+    }
+
+    /// # Safety
+    /// the command buffer must be recording and the query must have been reset
+    pub unsafe fn write_timestamp(
+        &self,
+        cmd: vk::CommandBuffer,
+        stage: vk::PipelineStageFlags2,
+        pool: vk::QueryPool,
+        query: u32,
+    ) {
+        if self.features.synchronization2 {
+            self.cmd_write_timestamp2(cmd, stage, pool, query);
+        } else {
+            let stage = legacy_stages(stage, vk::PipelineStageFlags::TOP_OF_PIPE);
+            self.cmd_write_timestamp(cmd, stage, pool, query);
+        }
+    }
+
+    /// ``queue_submit2`` or the same submits with ``queue_submit``
+    /// # Safety
+    /// the command buffers must be fully recorded and the semaphores must be valid
+    /// # Errors
+    /// if there is no memory left or the device has been lost
+    pub unsafe fn submit(
+        &self,
+        queue: vk::Queue,
+        submits: &[vk::SubmitInfo2],
+        fence: vk::Fence,
+    ) -> VkResult<()> {
+        if self.features.synchronization2 {
+            return self.queue_submit2(queue, submits, fence);
+        }
+
+        // the old submit info only points to the arrays, so they have to be kept alive until the submit
+        let converted: Vec<_> = submits
+            .iter()
+            .map(|submit| {
+                let waits = slice(
+                    submit.p_wait_semaphore_infos,
+                    submit.wait_semaphore_info_count,
+                );
+                let signals = slice(
+                    submit.p_signal_semaphore_infos,
+                    submit.signal_semaphore_info_count,
+                );
+                let command_buffers = slice(
+                    submit.p_command_buffer_infos,
+                    submit.command_buffer_info_count,
+                );
+
+                (
+                    waits.iter().map(|wait| wait.semaphore).collect::<Vec<_>>(),
+                    waits
+                        .iter()
+                        .map(|wait| {
+                            legacy_stages(wait.stage_mask, vk::PipelineStageFlags::ALL_COMMANDS)
+                        })
+                        .collect::<Vec<_>>(),
+                    command_buffers
+                        .iter()
+                        .map(|info| info.command_buffer)
+                        .collect::<Vec<_>>(),
+                    signals
+                        .iter()
+                        .map(|signal| signal.semaphore)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+
+        let submits: Vec<_> = converted
+            .iter()
+            .map(|(waits, wait_stages, command_buffers, signals)| {
+                vk::SubmitInfo::default()
+                    .wait_semaphores(waits)
+                    .wait_dst_stage_mask(wait_stages)
+                    .command_buffers(command_buffers)
+                    .signal_semaphores(signals)
+            })
+            .collect();
+
+        self.queue_submit(queue, &submits, fence)
+    }
+}
+
+unsafe fn slice<'a, T>(ptr: *const T, len: u32) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, len as usize)
+    }
+}
+
+/// the old stage bits that cover the stages, ``empty`` is used if there are none
+/// the old commands don't allow empty stage masks
+fn legacy_stages(
+    stages: vk::PipelineStageFlags2,
+    empty: vk::PipelineStageFlags,
+) -> vk::PipelineStageFlags {
+    use vk::PipelineStageFlags as S;
+    use vk::PipelineStageFlags2 as S2;
+
+    let mut legacy = S::from_raw(stages.as_raw() as u32);
+
+    if stages.intersects(S2::COPY | S2::RESOLVE | S2::BLIT | S2::CLEAR) {
+        legacy |= S::TRANSFER;
+    }
+    if stages.intersects(S2::INDEX_INPUT | S2::VERTEX_ATTRIBUTE_INPUT) {
+        legacy |= S::VERTEX_INPUT;
+    }
+    if stages.contains(S2::PRE_RASTERIZATION_SHADERS) {
+        legacy |= S::VERTEX_SHADER
+            | S::TESSELLATION_CONTROL_SHADER
+            | S::TESSELLATION_EVALUATION_SHADER
+            | S::GEOMETRY_SHADER;
+    }
+
+    if legacy.is_empty() {
+        empty
+    } else {
+        legacy
+    }
+}
+
+/// the old access bits that cover the access
+fn legacy_access(access: vk::AccessFlags2) -> vk::AccessFlags {
+    use vk::AccessFlags as A;
+    use vk::AccessFlags2 as A2;
+
+    let mut legacy = A::from_raw(access.as_raw() as u32);
+
+    if access.intersects(A2::SHADER_SAMPLED_READ | A2::SHADER_STORAGE_READ) {
+        legacy |= A::SHADER_READ;
+    }
+    if access.contains(A2::SHADER_STORAGE_WRITE) {
+        legacy |= A::SHADER_WRITE;
+    }
+
+    legacy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_map_to_the_old_bits() {
+        let top = vk::PipelineStageFlags::TOP_OF_PIPE;
+
+        assert_eq!(
+            legacy_stages(
+                vk::PipelineStageFlags2::COMPUTE_SHADER | vk::PipelineStageFlags2::DRAW_INDIRECT,
+                top
+            ),
+            vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::DRAW_INDIRECT
+        );
+        assert_eq!(
+            legacy_stages(vk::PipelineStageFlags2::BLIT, top),
+            vk::PipelineStageFlags::TRANSFER
+        );
+        assert_eq!(
+            legacy_stages(vk::PipelineStageFlags2::INDEX_INPUT, top),
+            vk::PipelineStageFlags::VERTEX_INPUT
+        );
+        // the old commands need at least one stage
+        assert_eq!(legacy_stages(vk::PipelineStageFlags2::NONE, top), top);
+    }
+
+    #[test]
+    fn access_maps_to_the_old_bits() {
+        assert_eq!(
+            legacy_access(vk::AccessFlags2::SHADER_SAMPLED_READ | vk::AccessFlags2::TRANSFER_WRITE),
+            vk::AccessFlags::SHADER_READ | vk::AccessFlags::TRANSFER_WRITE
+        );
+        assert_eq!(
+            legacy_access(vk::AccessFlags2::SHADER_STORAGE_WRITE),
+            vk::AccessFlags::SHADER_WRITE
+        );
+        assert_eq!(
+            legacy_access(vk::AccessFlags2::NONE),
+            vk::AccessFlags::empty()
+        );
+    }
+}
@@ -0,0 +1,255 @@
+// the messages of the validation layer, logged with ``log`` and counted for ``FrameStats::validation``
+//
+// the layer reports the same mistake every frame, so every message id is only logged ``BURST`` times
+// in ``WINDOW``, the others are counted and the next message of the id that is logged says how many were left out
+// without a logger, errors and warnings are printed to stderr, so they can't be missed
+
+// the layer is only loaded in debug builds, release builds only read the counts
+#![cfg_attr(not(debug_assertions), allow(dead_code))]
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use ash::vk;
+
+/// the messages of one id that are logged in every ``WINDOW``
+pub const BURST: u32 = 5;
+pub const WINDOW: Duration = Duration::from_secs(10);
+
+/// how many validation messages were reported since the device was created
+/// they are always 0 in release builds or if the ``ValidationLevel`` is ``Off``
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationCounts {
+    pub errors: u64,
+    pub warnings: u64,
+    /// the info and verbose messages
+    pub infos: u64,
+    /// the messages that were counted but not logged, because their id was reported too often
+    pub suppressed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+    Info,
+    Verbose,
+}
+
+impl Severity {
+    pub fn from_flags(flags: vk::DebugUtilsMessageSeverityFlagsEXT) -> Self {
+        if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            Self::Error
+        } else if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            Self::Warning
+        } else if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+            Self::Info
+        } else {
+            Self::Verbose
+        }
+    }
+
+    /// the layer reports every object it creates as info, so they are only logged when debugging
+    fn level(self) -> log::Level {
+        match self {
+            Self::Error => log::Level::Error,
+            Self::Warning => log::Level::Warn,
+            Self::Info => log::Level::Debug,
+            Self::Verbose => log::Level::Trace,
+        }
+    }
+}
+
+/// how often a message id was reported in its current window
+struct IdWindow {
+    start: Instant,
+    logged: u32,
+    suppressed: u64,
+}
+
+#[derive(Default)]
+struct LogState {
+    counts: ValidationCounts,
+    ids: HashMap<i32, IdWindow>,
+    /// the first error since the last ``ValidationLog::check``, only kept if it panics
+    error: Option<String>,
+}
+
+/// shared with the debug callback of the validation layer, see ``VulkanDevice::validation_counts``
+#[derive(Default)]
+pub(crate) struct ValidationLog {
+    state: Mutex<LogState>,
+    panic_on_error: AtomicBool,
+}
+
+impl ValidationLog {
+    /// log the message unless its id was reported too often
+    pub fn report(&self, severity: Severity, id: i32, id_name: &str, message: &str) {
+        let Some(suppressed) = self.admit(severity, id, message, Instant::now()) else {
+            return;
+        };
+
+        let suffix = if suppressed > 0 {
+            format!(" ({suppressed} more since the last one)")
+        } else {
+            String::new()
+        };
+
+        let level = severity.level();
+        if log::log_enabled!(target: "vulkan", level) {
+            log::log!(target: "vulkan", level, "[{id_name} ({id})] {message}{suffix}");
+        } else if matches!(severity, Severity::Error | Severity::Warning) {
+            eprintln!("vulkan {level}: [{id_name} ({id})] {message}{suffix}");
+        }
+    }
+
+    /// count the message and decide if it's logged
+    /// returns how many messages of the id were left out since the last logged one, None if this one is left out
+    fn admit(&self, severity: Severity, id: i32, message: &str, now: Instant) -> Option<u64> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        match severity {
+            Severity::Error => state.counts.errors += 1,
+            Severity::Warning => state.counts.warnings += 1,
+            Severity::Info | Severity::Verbose => state.counts.infos += 1,
+        }
+        if severity == Severity::Error
+            && state.error.is_none()
+            && self.panic_on_error.load(Ordering::Relaxed)
+        {
+            state.error = Some(message.to_owned());
+        }
+
+        let window = state.ids.entry(id).or_insert(IdWindow {
+            start: now,
+            logged: 0,
+            suppressed: 0,
+        });
+        if now.duration_since(window.start) >= WINDOW {
+            window.start = now;
+            window.logged = 0;
+        }
+
+        if window.logged < BURST {
+            window.logged += 1;
+            Some(std::mem::take(&mut window.suppressed))
+        } else {
+            window.suppressed += 1;
+            state.counts.suppressed += 1;
+            None
+        }
+    }
+
+    pub fn counts(&self) -> ValidationCounts {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .counts
+    }
+
+    pub fn set_panic_on_error(&self, enabled: bool) {
+        self.panic_on_error.store(enabled, Ordering::Relaxed);
+    }
+
+    /// the callback can't unwind in to the driver, so the error is kept until this is called
+    /// # Panics
+    /// if ``set_panic_on_error`` is enabled and there was an error since the last call
+    pub fn check(&self) {
+        let error = self
+            .state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .error
+            .take();
+        if let Some(error) = error {
+            panic!("vulkan validation error: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_ids_are_rate_limited() {
+        let log = ValidationLog::default();
+        let start = Instant::now();
+
+        for _ in 0..BURST {
+            assert_eq!(log.admit(Severity::Warning, 1, "", start), Some(0));
+        }
+        assert_eq!(log.admit(Severity::Warning, 1, "", start), None);
+        assert_eq!(log.admit(Severity::Warning, 1, "", start), None);
+        // other ids have their own limit
+        assert_eq!(log.admit(Severity::Error, 2, "", start), Some(0));
+
+        // the next window tells how many were left out
+        assert_eq!(log.admit(Severity::Warning, 1, "", start + WINDOW), Some(2));
+        assert_eq!(log.admit(Severity::Warning, 1, "", start + WINDOW), Some(0));
+
+        assert_eq!(
+            log.counts(),
+            ValidationCounts {
+                errors: 1,
+                warnings: BURST as u64 + 4,
+                infos: 0,
+                suppressed: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn severities_map_to_levels() {
+        let severity = |flags| Severity::from_flags(flags).level();
+        assert_eq!(
+            severity(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR),
+            log::Level::Error
+        );
+        assert_eq!(
+            severity(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING),
+            log::Level::Warn
+        );
+        // the info spam isn't shown at the default level
+        assert_eq!(
+            severity(vk::DebugUtilsMessageSeverityFlagsEXT::INFO),
+            log::Level::Debug
+        );
+        assert_eq!(
+            severity(vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE),
+            log::Level::Trace
+        );
+    }
+
+    #[test]
+    fn errors_panic_only_when_enabled() {
+        let log = ValidationLog::default();
+        let now = Instant::now();
+
+        log.admit(Severity::Error, 1, "ignored", now);
+        log.check();
+
+        log.set_panic_on_error(true);
+        log.admit(Severity::Warning, 2, "not an error", now);
+        log.check();
+        log.admit(Severity::Error, 1, "first", now);
+        log.admit(Severity::Error, 1, "second", now);
+
+        let panic = std::panic::catch_unwind(|| log.check()).unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("vulkan validation error: first")
+        );
+        // it's only reported once
+        log.check();
+    }
+}
@@ -0,0 +1,262 @@
+// renders without a window and compares the read back pixels to the golden images in tests/golden
+//
+// runs on any vulkan driver, in ci containers that is lavapipe or SwiftShader
+// the tests that need a driver are ignored, so a plain ``cargo test`` without one doesn't report them as passed:
+//     VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json cargo test -p rendering --test offscreen -- --include-ignored
+//
+// after an intended change to the output, rerun with UPDATE_GOLDEN=1 to overwrite the golden images
+//
+// only transfer operations are checked, the RenderHandler can't draw to an offscreen target yet
+// still missing: golden images of a triangle, a cube and a voxel chunk drawn by the RenderHandler,
+// they need a swapchain replacement that renders in to textures and a driver to create the images with
+
+use std::{path::PathBuf, sync::Arc};
+
+use ash::vk;
+use rendering::vulkan::{Texture, ValidationLevel, VulkanDevice};
+
+/// the most a channel may differ from the golden image, drivers round filtered values differently
+const TOLERANCE: u8 = 2;
+
+/// # Panics
+/// if there is no vulkan driver, the tests that call this are ignored by default
+fn device() -> Arc<VulkanDevice> {
+    match unsafe { VulkanDevice::headless(ValidationLevel::Errors) } {
+        Ok(device) => Arc::new(device),
+        Err(err) => panic!("failed to create a headless device: {err}"),
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.ppm"))
+}
+
+/// compare ``RGBA`` pixels to the golden image, the alpha channel is ignored
+fn check_golden(name: &str, extent: [u32; 2], rgba: &[u8]) {
+    let rgb: Vec<u8> = rgba
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let path = golden_path(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, write_ppm(extent, &rgb)).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read(&path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+    let (golden_extent, golden_rgb) = read_ppm(&golden).expect("invalid golden image");
+    assert_eq!(golden_extent, extent, "{name} has a different size");
+
+    if let Err(message) = compare(&rgb, golden_rgb, TOLERANCE) {
+        // keep the output around to look at it
+        let actual = path.with_extension("actual.ppm");
+        std::fs::write(&actual, write_ppm(extent, &rgb)).unwrap();
+        panic!(
+            "{name}: {message}, the output was written to {}",
+            actual.display()
+        );
+    }
+}
+
+/// the amount and the largest difference of the channels that differ more than ``tolerance``
+fn compare(actual: &[u8], expected: &[u8], tolerance: u8) -> Result<(), String> {
+    assert_eq!(actual.len(), expected.len());
+
+    let differences: Vec<u8> = actual
+        .iter()
+        .zip(expected)
+        .map(|(a, b)| a.abs_diff(*b))
+        .filter(|&difference| difference > tolerance)
+        .collect();
+
+    match differences.iter().max() {
+        Some(max) => Err(format!(
+            "{} channels differ, up to {max}",
+            differences.len()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// binary ``P6`` ppm, any viewer can open it and it doesn't need an image crate
+fn write_ppm([width, height]: [u32; 2], rgb: &[u8]) -> Vec<u8> {
+    let mut data = format!("P6\n{width} {height}\n255\n").into_bytes();
+    data.extend_from_slice(rgb);
+    data
+}
+
+fn read_ppm(data: &[u8]) -> Option<([u32; 2], &[u8])> {
+    // the header is 4 whitespace separated fields, the pixels start after the last one
+    let mut fields = Vec::with_capacity(4);
+    let mut pos = 0;
+    while fields.len() < 4 {
+        while data.get(pos)?.is_ascii_whitespace() {
+            pos += 1;
+        }
+        let start = pos;
+        while !data.get(pos)?.is_ascii_whitespace() {
+            pos += 1;
+        }
+        fields.push(std::str::from_utf8(&data[start..pos]).ok()?);
+    }
+
+    if fields[0] != "P6" || fields[3] != "255" {
+        return None;
+    }
+
+    let extent = [fields[1].parse().ok()?, fields[2].parse().ok()?];
+    let pixels = data.get(pos + 1..)?;
+    (pixels.len() == extent[0] as usize * extent[1] as usize * 3).then_some((extent, pixels))
+}
+
+/// a pattern with hard edges and smooth gradients, so filtering differences show up
+fn pattern([width, height]: [u32; 2]) -> Vec<u8> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let checker = if (x / 8 + y / 8) % 2 == 0 { 255 } else { 0 };
+            [(x * 4) as u8, (y * 4) as u8, checker, 255]
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "needs a vulkan driver"]
+fn clear() {
+    let device = device();
+
+    let extent = [32, 32];
+    let texture = Texture::new(
+        device.clone(),
+        extent,
+        vk::Format::R8G8B8A8_UNORM,
+        1,
+        vk::ImageUsageFlags::TRANSFER_DST
+            | vk::ImageUsageFlags::TRANSFER_SRC
+            | vk::ImageUsageFlags::SAMPLED,
+    )
+    .unwrap();
+
+    let range = vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1);
+
+    let barrier = |old_layout, new_layout, (src_stage, src_access), (dst_stage, dst_access)| {
+        vk::ImageMemoryBarrier2::default()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(dst_stage)
+            .dst_access_mask(dst_access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(texture.image)
+            .subresource_range(range)
+    };
+
+    unsafe {
+        device
+            .immediate_submit(|cmd| {
+                device.image_barrier(
+                    cmd,
+                    barrier(
+                        vk::ImageLayout::UNDEFINED,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE),
+                        (
+                            vk::PipelineStageFlags2::CLEAR,
+                            vk::AccessFlags2::TRANSFER_WRITE,
+                        ),
+                    ),
+                );
+
+                let color = vk::ClearColorValue {
+                    float32: [0.2, 0.4, 0.8, 1.0],
+                };
+                device.cmd_clear_color_image(
+                    cmd,
+                    texture.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &color,
+                    &[range],
+                );
+
+                device.image_barrier(
+                    cmd,
+                    barrier(
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        (
+                            vk::PipelineStageFlags2::CLEAR,
+                            vk::AccessFlags2::TRANSFER_WRITE,
+                        ),
+                        (
+                            vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                            vk::AccessFlags2::SHADER_SAMPLED_READ,
+                        ),
+                    ),
+                );
+            })
+            .unwrap();
+    }
+
+    check_golden("clear", extent, &texture.download(0).unwrap());
+}
+
+#[test]
+#[ignore = "needs a vulkan driver"]
+fn generated_mips() {
+    let device = device();
+
+    let format = vk::Format::R8G8B8A8_UNORM;
+    assert!(
+        Texture::can_generate_mips(&device, format),
+        "the driver can't blit {format:?}"
+    );
+
+    let extent = [64, 64];
+    let texture = Texture::new(
+        device,
+        extent,
+        format,
+        Texture::max_mip_levels(extent),
+        vk::ImageUsageFlags::TRANSFER_DST
+            | vk::ImageUsageFlags::TRANSFER_SRC
+            | vk::ImageUsageFlags::SAMPLED,
+    )
+    .unwrap();
+
+    let base = pattern(extent);
+    texture.upload(&[&base]).unwrap();
+
+    // the base level has to come back unchanged
+    assert_eq!(texture.download(0).unwrap(), base);
+
+    for mip in 1..=2 {
+        check_golden(
+            &format!("mip{mip}"),
+            texture.mip_extent(mip),
+            &texture.download(mip).unwrap(),
+        );
+    }
+}
+
+#[test]
+fn golden_comparison() {
+    let rgb = [10, 20, 30, 40, 50, 60];
+    let ppm = write_ppm([2, 1], &rgb);
+    assert_eq!(read_ppm(&ppm), Some(([2, 1], &rgb[..])));
+    assert_eq!(read_ppm(&ppm[..ppm.len() - 1]), None);
+
+    assert!(compare(&rgb, &[12, 18, 30, 40, 50, 60], 2).is_ok());
+    assert_eq!(
+        compare(&rgb, &[13, 20, 30, 40, 50, 0], 2),
+        Err("2 channels differ, up to 60".to_owned())
+    );
+}
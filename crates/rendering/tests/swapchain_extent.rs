@@ -0,0 +1,77 @@
+// the swapchain sizes for a window that is resized, minimized and restored
+// the surface capabilities are what the drivers report, so this runs without a gpu
+
+use ash::vk;
+use rendering::vulkan::surface_extent;
+
+fn capabilities(current: [u32; 2]) -> vk::SurfaceCapabilitiesKHR {
+    vk::SurfaceCapabilitiesKHR {
+        current_extent: vk::Extent2D {
+            width: current[0],
+            height: current[1],
+        },
+        min_image_extent: vk::Extent2D {
+            width: 1,
+            height: 1,
+        },
+        max_image_extent: vk::Extent2D {
+            width: 4096,
+            height: 4096,
+        },
+        ..Default::default()
+    }
+}
+
+fn extent(width: u32, height: u32) -> Option<vk::Extent2D> {
+    Some(vk::Extent2D { width, height })
+}
+
+#[test]
+fn minimize_and_restore() {
+    // on windows the surface reports the window size and 0 while minimized
+    let sequence = [
+        ([800, 600], [800, 600], extent(800, 600)),
+        ([0, 0], [0, 0], None),
+        // some window managers send the size one axis at a time
+        ([0, 600], [0, 600], None),
+        ([1024, 768], [1024, 768], extent(1024, 768)),
+    ];
+
+    for (window_size, current, expected) in sequence {
+        assert_eq!(
+            surface_extent(&capabilities(current), window_size),
+            expected,
+            "window size {window_size:?}"
+        );
+    }
+}
+
+#[test]
+fn surface_without_a_size() {
+    // on wayland the surface takes the size of the swapchain, it has to be within the limits
+    let wayland = capabilities([u32::MAX, u32::MAX]);
+
+    let sequence = [
+        ([800, 600], extent(800, 600)),
+        ([0, 0], None),
+        ([10_000, 600], extent(4096, 600)),
+        ([800, 600], extent(800, 600)),
+    ];
+
+    for (window_size, expected) in sequence {
+        assert_eq!(
+            surface_extent(&wayland, window_size),
+            expected,
+            "window size {window_size:?}"
+        );
+    }
+}
+
+#[test]
+fn surface_size_wins() {
+    // the window size from the event can be outdated, the surface knows better
+    assert_eq!(
+        surface_extent(&capabilities([640, 480]), [800, 600]),
+        extent(640, 480)
+    );
+}
@@ -1,5 +1,5 @@
 use ash::vk;
-use rendering::vulkan::VulkanDevice;
+use rendering::vulkan::{ValidationLevel, VulkanDevice};
 
 fn main() {
     env_logger::builder()
@@ -12,7 +12,7 @@ fn main() {
         .create_window(800, 600, "Some window", glfw::WindowMode::Windowed)
         .unwrap();
 
-    let vk_device = unsafe { VulkanDevice::new(&window) }.unwrap();
+    let vk_device = unsafe { VulkanDevice::new(&window, ValidationLevel::Full) }.unwrap();
 
     // let data = [1u64, 2, 3, 4, 5, 6, 7, 8, 9, 10];
 